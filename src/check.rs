@@ -0,0 +1,165 @@
+//! Validation of protocols without writing any code. The definitions of an import are added to
+//! a tree one after the other, skipping the ones which fail, so that all of their problems are
+//! reported at once rather than only the first.
+
+use anyhow::Result;
+
+use crate::import::Import;
+use crate::tree::GenTree;
+use crate::util::{collect_lints, Config};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Severity {
+    /// A lint, which does not prevent generating the protocol outside of strict mode.
+    Warning,
+    Error,
+}
+
+/// A problem of a protocol.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Kind and name of the definition concerned, e.g. "structure `header`".
+    pub definition: Option<String>,
+    /// Line of the description defining it.
+    pub line: Option<usize>,
+    pub message: String,
+    /// A suggestion to fix the problem.
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    /// The diagnostic as reported for the description `file`, on a line of its own followed by
+    /// its suggestion.
+    pub fn report(&self, file: &str) -> String {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        let mut out = format!("{}: {}", severity, file);
+        if let Some(line) = self.line {
+            out.push_str(&format!(":{}", line));
+        }
+        out.push_str(": ");
+        if let Some(definition) = &self.definition {
+            out.push_str(&format!("{}: ", definition));
+        }
+        out.push_str(&self.message);
+        if let Some(help) = &self.help {
+            out.push_str(&format!("\n  help: {}", help));
+        }
+        out
+    }
+}
+
+/// Check the definitions of `import`, which are added to a tree in order, skipping the ones
+/// which fail.
+pub fn check_import(import: &Import, config: &Config) -> Vec<Diagnostic> {
+    // names a misspelt reference may have meant
+    let mut names = vec![];
+    for definition in import.definitions() {
+        names.push(definition.name());
+    }
+    for structure in &import.structures {
+        names.extend(structure.members.iter().map(|mem| mem.name()));
+    }
+
+    let mut tree = GenTree::from_config(config);
+    let mut diagnostics = vec![];
+    for (name, ident) in &import.renames {
+        if let Err(e) = tree.rename(name, ident) {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Error,
+                definition: None,
+                line: import.lines.get(name).copied(),
+                help: None,
+                message: format!("{:#}", e),
+            });
+        }
+    }
+    for definition in import.definitions() {
+        let (result, lints) = collect_lints(|| definition.add_to(&mut tree));
+        let name = format!("{} `{}`", definition.kind(), definition.name());
+        let line = import.lines.get(definition.name()).copied();
+        let errors = result.err().map(|e| (Severity::Error, format!("{:#}", e)));
+        let lints = lints.into_iter().map(|lint| (Severity::Warning, lint));
+        for (severity, message) in lints.chain(errors) {
+            diagnostics.push(Diagnostic {
+                severity,
+                definition: Some(name.clone()),
+                line,
+                help: help(&message, &names),
+                message,
+            });
+        }
+    }
+    diagnostics
+}
+
+/// Check a built-in protocol, reporting the first error of its `define` function.
+pub fn check_protocol(define: fn(&mut GenTree) -> Result<()>, config: &Config) -> Vec<Diagnostic> {
+    let mut tree = GenTree::from_config(config);
+    let (result, lints) = collect_lints(|| define(&mut tree));
+    let errors = result.err().map(|e| (Severity::Error, format!("{:#}", e)));
+    let lints = lints.into_iter().map(|lint| (Severity::Warning, lint));
+    lints
+        .chain(errors)
+        .map(|(severity, message)| Diagnostic {
+            severity,
+            definition: None,
+            line: None,
+            help: help(&message, &[]),
+            message,
+        })
+        .collect()
+}
+
+/// A suggestion for the problem described by `message`, proposing the closest of `names` for
+/// a reference to an unknown name.
+fn help(message: &str, names: &[&str]) -> Option<String> {
+    if let Some(unknown) = unknown_name(message) {
+        let max = (unknown.len() / 3).max(1);
+        return names
+            .iter()
+            .map(|name| (distance(unknown, name), *name))
+            .filter(|(distance, _)| (1..=max).contains(distance))
+            .min()
+            .map(|(_, name)| format!("did you mean `{}`?", name));
+    }
+    let help = if message.contains("overlaps") {
+        "move the fields apart or make one of them narrower"
+    } else if message.contains("does not fit into") || message.contains("exceeds") {
+        "use a smaller value or widen the field holding it"
+    } else if message.contains("defined more than once") {
+        "rename one of the definitions"
+    } else {
+        return None;
+    };
+    Some(String::from(help))
+}
+
+/// The name following the first "unknown" in `message`, e.g. `len` in "refers to unknown
+/// length field `len`".
+fn unknown_name(message: &str) -> Option<&str> {
+    let rest = &message[message.find("unknown ")?..];
+    let rest = &rest[rest.find('`')? + 1..];
+    Some(&rest[..rest.find('`')?])
+}
+
+/// The Levenshtein distance between `a` and `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1)
+                .min(row[j] + 1)
+                .min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
@@ -0,0 +1,149 @@
+//! ASCII diagrams of the layout of structures and bitfields in the style of the RFCs, with a
+//! column of two characters per bit:
+//!
+//! ```text
+//!  0                   1
+//!  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |Version|  Ihl  |      Tos      |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! ```
+
+/// A field of a diagram spanning `bits` bits, or the rest of its row if `None`, after which
+/// the next field starts a new row.
+pub struct Cell {
+    pub label: String,
+    pub bits: Option<u32>,
+}
+
+impl Cell {
+    pub fn new(label: &str, bits: u32) -> Self {
+        Cell {
+            label: String::from(label),
+            bits: Some(bits),
+        }
+    }
+
+    /// A field of variable size.
+    pub fn variable(label: &str) -> Self {
+        Cell {
+            label: String::from(label),
+            bits: None,
+        }
+    }
+}
+
+/// How the bits of a diagram are numbered above its rows.
+#[derive(Clone, Copy)]
+pub enum Numbering {
+    /// From 0 at the left of every row, like the bits of a message on the wire.
+    Ascending,
+    /// From the most significant bit of an integer of the given number of bits at the left of
+    /// the first row down to 0 at the right of the last.
+    Descending(u32),
+}
+
+/// Draw the `cells` in rows of up to `row_bits` bits. Labels are centered in their fields and
+/// cut to fit.
+pub fn render(cells: &[Cell], row_bits: u32, numbering: Numbering) -> String {
+    let mut rows: Vec<Vec<(&str, u32)>> = vec![vec![]];
+    let mut used = 0;
+    for cell in cells {
+        if cell.bits.is_none() && used == row_bits {
+            rows.push(vec![]);
+            used = 0;
+        }
+        let mut remaining = cell.bits.unwrap_or(row_bits - used);
+        while remaining > 0 {
+            if used == row_bits {
+                rows.push(vec![]);
+                used = 0;
+            }
+            let bits = remaining.min(row_bits - used);
+            rows.last_mut().unwrap().push((&cell.label, bits));
+            used += bits;
+            remaining -= bits;
+        }
+    }
+    rows.retain(|row| !row.is_empty());
+
+    let widths = rows
+        .iter()
+        .map(|row| row.iter().map(|(_, bits)| bits).sum::<u32>())
+        .collect::<Vec<_>>();
+    let mut lines = vec![];
+    match numbering {
+        Numbering::Ascending => {
+            let width = widths.iter().copied().max().unwrap_or(0);
+            lines.extend(header(0..width));
+            for (idx, row) in rows.iter().enumerate() {
+                let above = widths[idx].max(idx.checked_sub(1).map_or(0, |prev| widths[prev]));
+                lines.push(border(above));
+                lines.push(fields(row));
+            }
+            lines.push(border(widths.last().copied().unwrap_or(0)));
+        }
+        Numbering::Descending(bits) => {
+            let mut top = bits;
+            for (row, width) in rows.iter().zip(&widths) {
+                lines.extend(header((top.saturating_sub(*width)..top).rev()));
+                lines.push(border(*width));
+                lines.push(fields(row));
+                lines.push(border(*width));
+                top = top.saturating_sub(*width);
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for line in lines {
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// The numbers of the bits `numbers`, with the tens above the ones if any bit needs them.
+fn header(numbers: impl Iterator<Item = u32> + Clone) -> Vec<String> {
+    let mut lines = vec![];
+    if numbers.clone().any(|n| n >= 10) {
+        // the tens of bit 0 are only drawn at the left, as in the RFCs
+        lines.push(
+            numbers
+                .clone()
+                .enumerate()
+                .map(|(idx, n)| match n {
+                    n if n % 10 == 0 && (n > 0 || idx == 0) => format!(" {}", n / 10 % 10),
+                    _ => String::from("  "),
+                })
+                .collect(),
+        );
+    }
+    lines.push(numbers.map(|n| format!(" {}", n % 10)).collect());
+    lines
+}
+
+/// The line above or below a row of `bits` bits.
+fn border(bits: u32) -> String {
+    let mut line = String::from("+");
+    for _ in 0..bits {
+        line.push_str("-+");
+    }
+    line
+}
+
+/// The line holding the labels of the fields of a row.
+fn fields(row: &[(&str, u32)]) -> String {
+    let mut line = String::from("|");
+    for (label, bits) in row {
+        let width = (2 * bits - 1) as usize;
+        let label = label.chars().take(width).collect::<String>();
+        let len = label.chars().count();
+        let left = (width - len) / 2;
+        line.push_str(&" ".repeat(left));
+        line.push_str(&label);
+        line.push_str(&" ".repeat(width - len - left));
+        line.push('|');
+    }
+    line
+}
@@ -0,0 +1,596 @@
+//! Documentation of a protocol rendered from the same model as the generated code, with a
+//! section holding a layout diagram and a table of the members or fields of every structure and
+//! bitfield, and a table of the values of every enumeration.
+
+use std::fmt::Write;
+
+use anyhow::Result;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::generate::bitfield::{BitField, Enumeration, MaybeField};
+use crate::generate::frame::Frames;
+use crate::generate::structure::{
+    AlternativeOptions, Epoch, SimpleStructure, StringKind, StructMember, Structure,
+};
+use crate::generate::tlv::Tlv;
+use crate::util::BITS_PER_BYTE;
+
+pub mod diagram;
+
+use self::diagram::{Cell, Numbering};
+
+/// Number of bits in a row of the diagrams, as in the RFCs.
+const ROW_BITS: u32 = 32;
+
+/// A block of a [`Document`]. Text may hold code spans between backticks.
+pub enum Block {
+    Heading(String),
+    Paragraph(String),
+    /// A table with its header and its rows.
+    Table(Vec<String>, Vec<Vec<String>>),
+    Diagram(String),
+}
+
+/// The documentation of a protocol, to which the definitions are added in the order they are
+/// added to the tree.
+#[derive(Default)]
+pub struct Document {
+    pub blocks: Vec<Block>,
+}
+
+impl Document {
+    pub fn new() -> Self {
+        Document::default()
+    }
+
+    /// Document the structure `s` and the bitfields defined along with it.
+    pub fn add_struct(&mut self, s: &Structure) -> Result<()> {
+        self.heading(&s.name, &s.desc);
+        if let Some(base) = &s.base {
+            self.blocks.push(Block::Paragraph(format!(
+                "Extends `{}`, whose {} members it starts with.",
+                base.name,
+                base.members.len()
+            )));
+        }
+        if let Some((name, record)) = &s.trailing {
+            self.blocks.push(Block::Paragraph(format!(
+                "Followed by the `{}`, zero or more `{}` up to the end of the buffer.",
+                name, record
+            )));
+        }
+
+        let mut rows = vec![];
+        // offset in bits, which is unknown after a member of variable size
+        let mut offset = Some(0);
+        for mem in &s.members {
+            let bits = member_bits(mem);
+            let size = match bits {
+                Some(bits) if bits % BITS_PER_BYTE == 0 => (bits / BITS_PER_BYTE).to_string(),
+                Some(bits) => format!("{} bits", bits),
+                None => String::from("variable"),
+            };
+            rows.push(vec![
+                format!("`{}`", mem.name()),
+                offset.map_or_else(|| String::from("—"), format_offset),
+                size,
+                member_type(s, mem),
+                mem.desc().unwrap_or_default().to_string(),
+            ]);
+            offset = offset.zip(bits).map(|(offset, bits)| offset + bits);
+        }
+
+        if s.members.iter().any(|mem| member_bits(mem) != Some(0)) {
+            self.blocks.push(Block::Diagram(struct_diagram(s)));
+        }
+        self.blocks.push(Block::Table(
+            header(&["Member", "Offset", "Size", "Type", "Description"]),
+            rows,
+        ));
+
+        for bitfield in &s.bitfields {
+            self.add_bitfield(bitfield)?;
+        }
+        Ok(())
+    }
+
+    pub fn add_struct_simple(&mut self, s: &SimpleStructure) {
+        self.heading(&s.name, "");
+        let bits = s.member.bytes * BITS_PER_BYTE;
+        self.blocks.push(Block::Diagram(simple_struct_diagram(s)));
+        self.blocks.push(Block::Table(
+            header(&["Member", "Offset", "Size", "Type", "Description"]),
+            vec![vec![
+                format!("`{}`", s.member.name),
+                String::from("0"),
+                s.member.bytes.to_string(),
+                format!("u{}", bits),
+                s.member.desc.clone(),
+            ]],
+        ));
+    }
+
+    /// Document a set of alternatives, along with the values of the discriminator selecting
+    /// them.
+    pub fn add_alternatives(&mut self, alts: &AlternativeOptions) {
+        self.heading(&alts.name, "");
+        let rows = alts
+            .alternatives
+            .iter()
+            .map(|alt| {
+                let mut name = format!("`{}`", alt);
+                if *alt == alts.default {
+                    name.push_str(" (default)");
+                }
+                let selected = alts
+                    .discriminants
+                    .get(alt)
+                    .map_or_else(String::new, |value| format!("`{}`", value));
+                vec![name, selected]
+            })
+            .collect();
+        self.blocks
+            .push(Block::Table(header(&["Alternative", "Selected by"]), rows));
+    }
+
+    pub fn add_frames(&mut self, frames: &Frames) {
+        self.heading(&frames.name, &frames.desc);
+        let mut text = format!(
+            "Frames start with the {} byte bitfield `{}`, whose field `{}` selects the frame.",
+            frames.bytes, frames.header, frames.field
+        );
+        if frames.offset > 0 {
+            let _ = write!(text, " The header follows {} bytes.", frames.offset);
+        }
+        if let Some(framing) = frames.framing {
+            let _ = write!(
+                text,
+                " Frames are delimited by `{}` framing.",
+                framing.ident()
+            );
+        }
+        self.blocks.push(Block::Paragraph(text));
+        let rows = frames
+            .frames
+            .iter()
+            .map(|(structure, value, _)| vec![format!("`{}`", structure), format!("`{}`", value)])
+            .collect();
+        self.blocks
+            .push(Block::Table(header(&["Frame", "Selected by"]), rows));
+    }
+
+    pub fn add_tlv(&mut self, tlv: &Tlv) {
+        self.heading(&tlv.name, &tlv.desc);
+        let (first, second) = if tlv.len_first {
+            (("length", tlv.len_bytes), ("tag", tlv.tag_bytes))
+        } else {
+            (("tag", tlv.tag_bytes), ("length", tlv.len_bytes))
+        };
+        let mut text = format!(
+            "Entries start with a {} byte {} and a {} byte {}, followed by the value.",
+            first.1, first.0, second.1, second.0
+        );
+        if tlv.len_extra > 0 {
+            let _ = write!(
+                text,
+                " The length counts {} bytes in addition to the value.",
+                tlv.len_extra
+            );
+        }
+        self.blocks.push(Block::Paragraph(text));
+        let rows = tlv
+            .entries
+            .iter()
+            .map(|(tag, entry, _)| vec![format!("`{}`", entry), format!("`{:#x}`", tag)])
+            .collect();
+        self.blocks
+            .push(Block::Table(header(&["Entry", "Tag"]), rows));
+    }
+
+    pub fn add_bitfield(&mut self, bitfield: &BitField) -> Result<()> {
+        self.heading(&bitfield.name, &bitfield.desc);
+        self.blocks
+            .push(Block::Diagram(bitfield_diagram(bitfield)?));
+
+        let mut layout = bitfield.layout()?;
+        layout.sort_by_key(|(_, pos)| std::cmp::Reverse(*pos));
+        let rows = layout
+            .iter()
+            .map(|(field, pos)| {
+                let bits = match field.bitsize() {
+                    1 => pos.to_string(),
+                    bitsize => format!("{}:{}", pos + bitsize - 1, pos),
+                };
+                match field {
+                    MaybeField::Field(field) => {
+                        let values = match &field.enumeration {
+                            Some(enumeration) => format!("see `{}`", enumeration.name),
+                            None => field
+                                .enumerated_values
+                                .iter()
+                                .map(|value| format!("{} = `{}`", value.2, value.0))
+                                .chain(field.enumerated_ranges.iter().map(|range| {
+                                    format!(
+                                        "{}..={} = `{}`",
+                                        range.2.start(),
+                                        range.2.end(),
+                                        range.0
+                                    )
+                                }))
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                        };
+                        vec![
+                            format!("`{}`", field.name),
+                            bits,
+                            field.desc.clone(),
+                            values,
+                        ]
+                    }
+                    MaybeField::Reserved { .. } => {
+                        vec![String::from("Reserved"), bits, String::new(), String::new()]
+                    }
+                }
+            })
+            .collect();
+        self.blocks.push(Block::Table(
+            header(&["Field", "Bits", "Description", "Values"]),
+            rows,
+        ));
+        Ok(())
+    }
+
+    pub fn add_enumeration(&mut self, enumeration: &Enumeration) {
+        self.heading(&enumeration.name, &enumeration.desc);
+        let rows = enumeration
+            .values
+            .iter()
+            .map(|value| {
+                vec![
+                    value.2.to_string(),
+                    format!("`{}`", value.0),
+                    value.1.clone(),
+                ]
+            })
+            .chain(enumeration.ranges.iter().map(|range| {
+                vec![
+                    format!("{}..={}", range.2.start(), range.2.end()),
+                    format!("`{}`", range.0),
+                    range.1.clone(),
+                ]
+            }))
+            .collect();
+        self.blocks.push(Block::Table(
+            header(&["Value", "Name", "Description"]),
+            rows,
+        ));
+    }
+
+    fn heading(&mut self, name: &str, desc: &str) {
+        self.blocks.push(Block::Heading(String::from(name)));
+        if !desc.is_empty() {
+            self.blocks.push(Block::Paragraph(String::from(desc)));
+        }
+    }
+
+    /// Render the document as Markdown titled `title` and introduced by `desc`.
+    pub fn to_markdown(&self, title: &str, desc: &str) -> String {
+        let mut out = format!("# {}\n", title);
+        if !desc.is_empty() {
+            let _ = write!(out, "\n{}\n", desc);
+        }
+        for block in &self.blocks {
+            out.push('\n');
+            match block {
+                Block::Heading(text) => {
+                    let _ = writeln!(out, "## {}", text);
+                }
+                Block::Paragraph(text) => {
+                    let _ = writeln!(out, "{}", text);
+                }
+                Block::Table(head, rows) => {
+                    let row = |cells: &[String]| {
+                        let cells = cells
+                            .iter()
+                            .map(|cell| cell.replace('|', "\\|"))
+                            .collect::<Vec<_>>();
+                        format!("| {} |\n", cells.join(" | "))
+                    };
+                    out.push_str(&row(head));
+                    out.push_str(&row(&vec![String::from("---"); head.len()]));
+                    for cells in rows {
+                        out.push_str(&row(cells));
+                    }
+                }
+                Block::Diagram(text) => {
+                    let _ = write!(out, "```text\n{}```\n", text);
+                }
+            }
+        }
+        out
+    }
+
+    /// Render the document as a standalone HTML page titled `title` and introduced by `desc`.
+    pub fn to_html(&self, title: &str, desc: &str) -> String {
+        let mut out = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+             <style>\n\
+             body {{ font-family: sans-serif; max-width: 60em; margin: auto; }}\n\
+             table {{ border-collapse: collapse; }}\n\
+             th, td {{ border: 1px solid #ccc; padding: 0.2em 0.5em; text-align: left; }}\n\
+             </style>\n</head>\n<body>\n<h1>{title}</h1>\n",
+            title = escape(title)
+        );
+        if !desc.is_empty() {
+            let _ = writeln!(out, "<p>{}</p>", inline(desc));
+        }
+        for block in &self.blocks {
+            match block {
+                Block::Heading(text) => {
+                    let _ = writeln!(out, "<h2>{}</h2>", escape(text));
+                }
+                Block::Paragraph(text) => {
+                    let _ = writeln!(out, "<p>{}</p>", inline(text));
+                }
+                Block::Table(head, rows) => {
+                    out.push_str("<table>\n<tr>");
+                    for cell in head {
+                        let _ = write!(out, "<th>{}</th>", inline(cell));
+                    }
+                    out.push_str("</tr>\n");
+                    for cells in rows {
+                        out.push_str("<tr>");
+                        for cell in cells {
+                            let _ = write!(out, "<td>{}</td>", inline(cell));
+                        }
+                        out.push_str("</tr>\n");
+                    }
+                    out.push_str("</table>\n");
+                }
+                Block::Diagram(text) => {
+                    let _ = write!(out, "<pre>\n{}</pre>\n", escape(text));
+                }
+            }
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+/// The `#[doc]` attributes of a generated type described by `desc`, followed by the layout
+/// `diagram` in a code block. Every line of the block is indented by a space, which rustdoc
+/// strips like the space after `///`, so the bit numbers stay aligned with the fields.
+pub fn render_doc(desc: &str, diagram: &str) -> TokenStream {
+    let mut block = String::from(" ```text\n");
+    for line in diagram.lines() {
+        let _ = writeln!(block, " {}", line);
+    }
+    block.push_str(" ```");
+
+    let desc = if desc.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            #[doc = #desc]
+            #[doc = ""]
+        }
+    };
+    quote! {
+        #desc
+        #[doc = #block]
+    }
+}
+
+/// The diagram of the fields of `bitfield`, from its most significant bit to bit 0, including
+/// the unused bits of the type holding it.
+pub fn bitfield_diagram(bitfield: &BitField) -> Result<String> {
+    let width = bitfield.width()?;
+    let mut layout = bitfield.layout()?;
+    layout.sort_by_key(|(_, pos)| std::cmp::Reverse(*pos));
+
+    let mut cells = vec![];
+    let mut top = width;
+    for (field, pos) in layout {
+        let end = pos + field.bitsize();
+        if end < top {
+            cells.push(Cell::new("Reserved", top - end));
+        }
+        let label = match &field {
+            MaybeField::Field(field) => field.name.as_str(),
+            MaybeField::Reserved { .. } => "Reserved",
+        };
+        cells.push(Cell::new(label, field.bitsize()));
+        top = pos;
+    }
+    if top > 0 {
+        cells.push(Cell::new("Reserved", top));
+    }
+    Ok(diagram::render(
+        &cells,
+        ROW_BITS,
+        Numbering::Descending(width),
+    ))
+}
+
+/// The diagram of the members of `s` in the order they are sent, with members of variable size
+/// taking the rest of their row.
+pub fn struct_diagram(s: &Structure) -> String {
+    let cells = s
+        .members
+        .iter()
+        .filter_map(|mem| match member_bits(mem) {
+            Some(0) => None,
+            Some(bits) => Some(Cell::new(mem.name(), bits)),
+            None => Some(Cell::variable(mem.name())),
+        })
+        .collect::<Vec<_>>();
+    diagram::render(&cells, ROW_BITS, Numbering::Ascending)
+}
+
+/// The diagram of the single member of `s`.
+pub fn simple_struct_diagram(s: &SimpleStructure) -> String {
+    let bits = s.member.bytes * BITS_PER_BYTE;
+    diagram::render(
+        &[Cell::new(&s.member.name, bits)],
+        ROW_BITS,
+        Numbering::Ascending,
+    )
+}
+
+/// Number of bits of `mem` on the wire, unless it depends on the value of the member.
+fn member_bits(mem: &StructMember) -> Option<u32> {
+    match mem {
+        StructMember::BitsMember(mem) => Some(mem.bits),
+        mem => mem.fixed_size().map(|bytes| bytes * BITS_PER_BYTE),
+    }
+}
+
+/// The offset of a member given in bits, in bytes along with the bits past the last byte.
+fn format_offset(bits: u32) -> String {
+    match bits % BITS_PER_BYTE {
+        0 => (bits / BITS_PER_BYTE).to_string(),
+        rest => format!("{} + {} bits", bits / BITS_PER_BYTE, rest),
+    }
+}
+
+/// Describe the type of `mem` of the structure `s`.
+fn member_type(s: &Structure, mem: &StructMember) -> String {
+    let len_of = |len: &str| {
+        s.members
+            .iter()
+            .filter_map(|other| match other {
+                StructMember::BytesMember(other) if other.len_from == len => Some(&other.name),
+                StructMember::RepeatedMember(other) if other.count_from == len => Some(&other.name),
+                StructMember::StringMember(other) => match &other.kind {
+                    StringKind::Prefixed(from) if from == len => Some(&other.name),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .map(|name| format!("`{}`", name))
+            .collect::<Vec<_>>()
+    };
+
+    match mem {
+        StructMember::BitfieldMember(mem) => format!("bitfield `{}`", mem.bitfield),
+        StructMember::PrimitiveMember(mem) => format!("u{}", mem.bytes * BITS_PER_BYTE),
+        StructMember::AlternativesMember(mem) => match &mem.discriminator {
+            Some(disc) => format!(
+                "one of `{}`, selected by `{}` of `{}`",
+                mem.alternatives, disc.field, disc.bitfield
+            ),
+            None => format!("one of `{}`", mem.alternatives),
+        },
+        StructMember::LengthMember(mem) => {
+            let mut ty = format!("u{} length", mem.bytes * BITS_PER_BYTE);
+            let of = len_of(&mem.name);
+            if !of.is_empty() {
+                let _ = write!(ty, " of {}", of.join(", "));
+            }
+            if mem.extra > 0 {
+                let _ = write!(ty, " plus {}", mem.extra);
+            }
+            ty
+        }
+        StructMember::BytesMember(mem) => format!(
+            "bytes counted by `{}`, up to {}",
+            mem.len_from, mem.capacity
+        ),
+        StructMember::ArrayMember(mem) => {
+            format!("[u{}; {}]", mem.bytes * BITS_PER_BYTE, mem.count)
+        }
+        StructMember::RepeatedMember(mem) => format!(
+            "u{} repeated `{}` times, up to {}",
+            mem.bytes * BITS_PER_BYTE,
+            mem.count_from,
+            mem.capacity
+        ),
+        StructMember::OptionalMember(mem) => {
+            let cond = if let Some(condition) = &mem.condition {
+                format!("{} in `{}`", condition.describe(), mem.flag.bitfield)
+            } else if mem.values.is_empty() {
+                format!("`{}` of `{}` is set", mem.flag.field, mem.flag.bitfield)
+            } else {
+                let values = mem
+                    .values
+                    .iter()
+                    .map(|value| format!("`{}`", value))
+                    .collect::<Vec<_>>();
+                format!(
+                    "`{}` of `{}` is {}",
+                    mem.flag.field,
+                    mem.flag.bitfield,
+                    values.join(" or ")
+                )
+            };
+            format!("optional `{}`, present if {}", mem.structure, cond)
+        }
+        StructMember::ChecksumMember(mem) => format!(
+            "{:?} checksum of the bytes from `{}`",
+            mem.algorithm, mem.from
+        ),
+        StructMember::ConstantMember(mem) => format!("constant {:#x}", mem.value),
+        StructMember::NibbleVarintMember(mem) => match &mem.low {
+            Some(low) => format!("nibble-extended varint, low nibble in `{}`", low),
+            None => String::from("nibble-extended varint"),
+        },
+        StructMember::OffsetMember(mem) if mem.marker_bits > 0 => format!(
+            "{} bit offset, marked by {:#x} in the top {} bits",
+            mem.offset_bits(),
+            mem.marker,
+            mem.marker_bits
+        ),
+        StructMember::OffsetMember(mem) => format!("{} bit offset", mem.offset_bits()),
+        StructMember::ExtendedMember(mem) => {
+            format!("bytes extended by their top bit, up to {}", mem.capacity)
+        }
+        StructMember::BitsMember(mem) => format!("u{}", mem.bits),
+        StructMember::VarintMember(mem) => format!("varint of up to {} bits", mem.bits),
+        StructMember::StringMember(mem) => match &mem.kind {
+            StringKind::Fixed => format!("string of {} bytes", mem.capacity),
+            StringKind::NullTerminated => {
+                format!("null-terminated string, up to {} bytes", mem.capacity)
+            }
+            StringKind::Prefixed(from) => {
+                format!("string of `{}` bytes, up to {}", from, mem.capacity)
+            }
+        },
+        StructMember::BcdMember(mem) => format!("BCD of {} digits", 2 * mem.bytes),
+        StructMember::TimestampMember(mem) => {
+            let epoch = match mem.epoch {
+                Epoch::Unix => String::from("the Unix epoch"),
+                Epoch::Gps => String::from("the GPS epoch"),
+                Epoch::Custom(offset) => format!("{} s after the Unix epoch", offset),
+            };
+            match mem.ticks_per_second {
+                1 => format!("timestamp in seconds since {}", epoch),
+                ticks => format!("timestamp in 1/{} s since {}", ticks, epoch),
+            }
+        }
+        StructMember::EuiMember(mem) => format!("EUI-{}", mem.bytes * BITS_PER_BYTE),
+    }
+}
+
+fn header(cells: &[&str]) -> Vec<String> {
+    cells.iter().map(|cell| String::from(*cell)).collect()
+}
+
+/// Escape `text` for HTML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Escape `text` for HTML, with its code spans between backticks as `code` elements.
+fn inline(text: &str) -> String {
+    escape(text)
+        .split('`')
+        .enumerate()
+        .map(|(idx, part)| match idx % 2 {
+            1 => format!("<code>{}</code>", part),
+            _ => String::from(part),
+        })
+        .collect()
+}
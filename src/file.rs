@@ -1,20 +1,56 @@
+use std::collections::{HashMap, HashSet};
 use std::fs::create_dir_all;
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
 
 use anyhow::Result;
-use bitfield::BitField;
-use proc_macro2::TokenStream;
+use bitfield::{BitField, BitfieldRegistry, Enumeration};
+use frame::Frames;
+use proc_macro2::{Span, TokenStream};
 use quote::quote;
-use structure::{Alternatives, SimpleStructure, Structure};
+use structure::{Alternatives, SimpleStructure, StructMember, Structure};
 
 use crate::generate::bitfield;
+use crate::generate::checksum;
+use crate::generate::frame;
+use crate::generate::frame_trait;
+use crate::generate::framing;
+use crate::generate::fuzz;
+use crate::generate::generic;
+use crate::generate::proptests;
+use crate::generate::roundtrip;
 use crate::generate::structure;
+use crate::generate::tlv::{self, Tlv};
+use crate::util::{
+    self, ByteOrder, Config, Derives, IoBackend, Namespace, ToSanitizedPascalCase,
+    ToSanitizedSnakeCase,
+};
 
+/// `cargo fuzz` targets for the types of a file, reached through `path`.
+struct FuzzTargets {
+    path: TokenStream,
+    targets: Vec<(String, TokenStream)>,
+    /// Bitfields along with the path of their module, whose targets are rendered when written
+    /// as the byte order they are read in is only known once the structures holding them are.
+    bitfields: Vec<(TokenStream, BitField)>,
+}
+
+#[derive(Default)]
 pub struct GenFile {
     items: TokenStream,
     any: bool,
+    config: Config,
+    derives: Derives,
+    tests: Option<TokenStream>,
+    proptests: Option<TokenStream>,
+    fuzz: Option<FuzzTargets>,
+    bitfields: BitfieldRegistry,
+    /// Byte order of the first structure holding each bitfield, by name, which the fuzz target
+    /// of the bitfield reads it in.
+    byte_orders: HashMap<String, ByteOrder>,
+    /// Names of the types of the structures added so far.
+    types: HashSet<String>,
 }
 
 impl GenFile {
@@ -22,36 +58,359 @@ impl GenFile {
         GenFile {
             items: TokenStream::new(),
             any: false,
+            config: Config::default(),
+            derives: Derives::default(),
+            tests: None,
+            proptests: None,
+            fuzz: None,
+            bitfields: BitfieldRegistry::new(),
+            byte_orders: HashMap::new(),
+            types: HashSet::new(),
+        }
+    }
+
+    /// Make a file generated according to `config`, which selects the I/O traits and whether
+    /// questionable definitions are rejected.
+    pub fn from_config(config: &Config) -> Self {
+        GenFile {
+            config: config.clone(),
+            ..GenFile::new()
+        }
+    }
+
+    /// Emit a test module with round trip tests for every structure and bitfield added to the
+    /// file.
+    pub fn set_tests(&mut self, tests: bool) {
+        self.tests = if tests {
+            Some(TokenStream::new())
+        } else {
+            None
+        };
+    }
+
+    /// Emit a `proptests` module with a strategy for every structure and bitfield added to the
+    /// file and a property test checking their round trip. The generated crate requires
+    /// `proptest` as a dev-dependency, the types to derive `Debug` and any optional structures
+    /// to be added to the same file.
+    pub fn set_proptests(&mut self, proptests: bool) {
+        self.proptests = if proptests {
+            Some(TokenStream::new())
+        } else {
+            None
+        };
+    }
+
+    /// Collect a `cargo fuzz` target for every structure and bitfield added to the file, which
+    /// reads it from arbitrary bytes. `path` is the path of the generated module from the fuzz
+    /// crate, e.g. `protocol::mhr`.
+    pub fn set_fuzz_targets(&mut self, path: &str) -> Result<()> {
+        let path = syn::parse_str::<syn::Path>(path)?;
+        self.fuzz = Some(FuzzTargets {
+            path: quote! { #path },
+            targets: vec![],
+            bitfields: vec![],
+        });
+        Ok(())
+    }
+
+    /// Select the I/O traits used by the generated code.
+    pub fn set_io(&mut self, io: IoBackend) {
+        self.config.io = io;
+    }
+
+    /// Select the traits derived on the generated types, which structures may override.
+    pub fn set_derives(&mut self, derives: Derives) {
+        self.derives = derives;
+    }
+
+    /// Derive `serde::Serialize` and `serde::Deserialize` on the generated types when the
+    /// generated crate enables its `serde` feature.
+    pub fn set_serde(&mut self, serde: bool) {
+        self.derives.serde = serde;
+    }
+
+    /// Derive `defmt::Format` on the generated types when the generated crate enables its
+    /// `defmt` feature.
+    pub fn set_defmt(&mut self, defmt: bool) {
+        self.derives.defmt = defmt;
+    }
+
+    /// Check that a structure `name` with the member `conflicts` can be added, reporting all
+    /// conflicts together.
+    fn check_struct(&mut self, name: &str, mut conflicts: Vec<String>) -> Result<()> {
+        let ty = name
+            .to_sanitized_pascal_case(&self.config.naming.types())
+            .to_string();
+        if !self.types.insert(ty.clone()) {
+            conflicts.insert(
+                0,
+                format!("structure `{}` is defined twice as `{}`", name, ty),
+            );
         }
+        util::check_conflicts(&conflicts)
     }
 
     pub fn add_struct_simple(&mut self, s: &SimpleStructure) -> Result<()> {
-        self.items.extend(structure::render_simple(s)?);
+        self.config
+            .naming
+            .declare(Namespace::Types, [s.name.as_str()]);
+        self.check_struct(&s.name, vec![])?;
+        self.items.extend(structure::render_simple(
+            s,
+            &self.derives,
+            &self.config.naming,
+        )?);
+        if let Some(tests) = &mut self.tests {
+            tests.extend(roundtrip::render_simple(s, &self.config.naming)?);
+        }
+        if let Some(proptests) = &mut self.proptests {
+            proptests.extend(proptests::render_simple(s, &self.config.naming)?);
+        }
+        if let Some(fuzz) = &mut self.fuzz {
+            let target = fuzz::render_simple(&fuzz.path, s, &self.config.naming)?;
+            fuzz.targets.push((
+                s.name
+                    .to_sanitized_snake_case(&self.config.naming.types())
+                    .into(),
+                target,
+            ));
+        }
         Ok(())
     }
 
     pub fn add_struct_with_alts(&mut self, s: &Structure, alts: &Alternatives) -> Result<()> {
-        self.items.extend(structure::render_with_alts(s, alts)?);
+        s.declare(&mut self.config.naming);
+        s.check_bitfields(&self.bitfields, &self.config)?;
+        self.check_struct(&s.name, s.conflicts(&self.config.naming))?;
+        self.note_byte_orders(s);
+        self.add_inline_bitfields(s)?;
+        self.items.extend(structure::render_with_alts(
+            s,
+            alts,
+            &self.derives,
+            &self.config,
+        )?);
+        if let Some(tests) = &mut self.tests {
+            tests.extend(roundtrip::render_struct(s, &self.config.naming)?);
+        }
+        if let Some(proptests) = &mut self.proptests {
+            proptests.extend(proptests::render_struct(s, &self.config.naming)?);
+        }
+        if let Some(fuzz) = &mut self.fuzz {
+            let target = fuzz::render_struct(&fuzz.path, s, Some(alts), &self.config.naming)?;
+            fuzz.targets.push((
+                s.name
+                    .to_sanitized_snake_case(&self.config.naming.types())
+                    .into(),
+                target,
+            ));
+        }
         Ok(())
     }
 
     pub fn add_alternatives(&mut self, alts: &Alternatives) -> Result<()> {
-        self.items.extend(structure::render_alternatives(alts)?);
+        alts.declare(&mut self.config.naming);
+        self.items
+            .extend(structure::render_alternatives(alts, &self.derives, &self.config)?);
         Ok(())
     }
 
     pub fn add_struct_imports(&mut self) -> Result<()> {
-        self.items.extend(structure::render_imports());
+        self.items.extend(structure::render_imports(self.config.io));
         Ok(())
     }
 
+    /// Add the `Error` returned by the generated `read` and `write` functions, which every
+    /// generated crate needs once at its root.
+    pub fn add_error_support(&mut self) -> Result<()> {
+        self.items
+            .extend(structure::render_error_support(self.config.io));
+        Ok(())
+    }
+
+    /// Add the `FieldReader`, `Bits` and `Result` support of the generated bitfields and
+    /// structures, which every generated crate needs once at its root.
+    pub fn add_generic_support(&mut self) -> Result<()> {
+        self.items.extend(generic::render());
+        Ok(())
+    }
+
+    pub fn add_checksum_support(&mut self) -> Result<()> {
+        self.items.extend(checksum::render_support(self.config.io));
+        Ok(())
+    }
+
+    /// Add the `Cobs`, `Slip` and `Hdlc` framings of the frames with a framing layer, which every
+    /// generated crate needs once at its root.
+    pub fn add_framing_support(&mut self) -> Result<()> {
+        self.items.extend(framing::render_support());
+        Ok(())
+    }
+
+    /// Add the `traits` module holding the `Frame` trait implemented by every structure, which
+    /// every generated crate needs once at its root.
+    pub fn add_frame_trait_support(&mut self) -> Result<()> {
+        self.items.extend(frame_trait::render_support());
+        Ok(())
+    }
+
+    /// Add the `io` module required by [`IoBackend::Core`] and [`IoBackend::EmbeddedIo`], which
+    /// does nothing for [`IoBackend::Core2`].
+    pub fn add_io_support(&mut self) -> Result<()> {
+        self.items
+            .extend(structure::render_io_support(self.config.io));
+        Ok(())
+    }
+
+    /// Add the structure `s`, whose bitfield members must refer to bitfields defined inline, added
+    /// to this file or registered with [`GenFile::register_bitfield`].
     pub fn add_struct(&mut self, s: &Structure) -> Result<()> {
-        self.items.extend(structure::render(s)?);
+        s.declare(&mut self.config.naming);
+        s.check_bitfields(&self.bitfields, &self.config)?;
+        self.check_struct(&s.name, s.conflicts(&self.config.naming))?;
+        self.note_byte_orders(s);
+        self.add_inline_bitfields(s)?;
+        self.items
+            .extend(structure::render(s, &self.derives, &self.config)?);
+        if let Some(tests) = &mut self.tests {
+            tests.extend(roundtrip::render_struct(s, &self.config.naming)?);
+        }
+        if let Some(proptests) = &mut self.proptests {
+            proptests.extend(proptests::render_struct(s, &self.config.naming)?);
+        }
+        if let Some(fuzz) = &mut self.fuzz {
+            let target = fuzz::render_struct(&fuzz.path, s, None, &self.config.naming)?;
+            fuzz.targets.push((
+                s.name
+                    .to_sanitized_snake_case(&self.config.naming.types())
+                    .into(),
+                target,
+            ));
+        }
         Ok(())
     }
 
+    /// Note the byte order of the structure `s` for the bitfields it holds, see
+    /// [`GenFile::note_byte_order`].
+    fn note_byte_orders(&mut self, s: &Structure) {
+        for mem in &s.members {
+            if let StructMember::BitfieldMember(mem) = mem {
+                self.note_byte_order(&mem.bitfield, s.byte_order);
+            }
+        }
+    }
+
+    /// Note that a structure of byte order `order` holds the bitfield `name`, whose fuzz target
+    /// reads it in the byte order of the first structure holding it, or in little endian if
+    /// none does.
+    pub fn note_byte_order(&mut self, name: &str, order: ByteOrder) {
+        self.byte_orders.entry(String::from(name)).or_insert(order);
+    }
+
+    /// Add the bitfields defined inline by the structure `s` as modules of this file, each with
+    /// its own tests and fuzz targets.
+    fn add_inline_bitfields(&mut self, s: &Structure) -> Result<()> {
+        for bitfield in &s.bitfields {
+            let pkg_name = util::ident(
+                &bitfield
+                    .name
+                    .to_sanitized_snake_case(&self.config.naming.types()),
+                Span::call_site(),
+            );
+
+            let mut file = GenFile::from_config(&self.config);
+            file.set_derives(self.derives.clone());
+            file.set_tests(self.tests.is_some());
+            file.set_proptests(self.proptests.is_some());
+            if let Some(fuzz) = &self.fuzz {
+                let path = &fuzz.path;
+                file.fuzz = Some(FuzzTargets {
+                    path: quote! { #path::#pkg_name },
+                    targets: vec![],
+                    bitfields: vec![],
+                });
+            }
+            file.add_bitfield(bitfield)?;
+
+            let items = file.render();
+            self.items.extend(quote! {
+                pub mod #pkg_name {
+                    #items
+                }
+            });
+            if let (Some(fuzz), Some(inline)) = (&mut self.fuzz, file.fuzz) {
+                fuzz.targets.extend(inline.targets);
+                fuzz.bitfields.extend(inline.bitfields);
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the enum of the `frames` and the `parse` function dispatching on their header, which
+    /// must be known like the bitfields of structures.
+    pub fn add_frames(&mut self, frames: &Frames) -> Result<()> {
+        self.config
+            .naming
+            .declare(Namespace::Types, [frames.name.as_str()]);
+        frames.check_header(&self.bitfields, &self.config)?;
+        self.check_struct(&frames.name, frames.conflicts(&self.config.naming))?;
+        self.items
+            .extend(frame::render(frames, &self.derives, &self.config.naming)?);
+        Ok(())
+    }
+
+    /// Add the enum of the TLV entries `tlv` along with their iterator and builder, whose
+    /// structures must be known like the bitfields of structures.
+    pub fn add_tlv(&mut self, tlv: &Tlv) -> Result<()> {
+        self.config
+            .naming
+            .declare(Namespace::Types, [tlv.name.as_str()]);
+        self.check_struct(&tlv.name, tlv.conflicts(&self.config.naming))?;
+        self.items
+            .extend(tlv::render(tlv, &self.derives, &self.config.naming)?);
+        Ok(())
+    }
+
+    /// Note that `bitfield` is rendered by another file, so structures of this file may refer
+    /// to it.
+    pub fn register_bitfield(&mut self, bitfield: &BitField) -> Result<()> {
+        bitfield.declare(&mut self.config.naming);
+        self.bitfields.register(bitfield)
+    }
+
+    /// Note the bitfields known to `bitfields`, see [`GenFile::register_bitfield`].
+    pub fn register_bitfields(&mut self, bitfields: &BitfieldRegistry) {
+        self.bitfields.extend(bitfields);
+    }
+
     pub fn add_bitfield(&mut self, bitfield: &BitField) -> Result<()> {
-        self.items.extend(bitfield::render(&bitfield)?);
+        bitfield.declare(&mut self.config.naming);
+        util::check_conflicts(&bitfield.conflicts(&self.config.naming))?;
+        self.bitfields.register(bitfield)?;
+        self.items
+            .extend(bitfield::render(bitfield, &self.derives, &self.config)?);
+        if let Some(tests) = &mut self.tests {
+            tests.extend(roundtrip::render_bitfield(bitfield, &self.config.naming)?);
+        }
+        if let Some(proptests) = &mut self.proptests {
+            proptests.extend(proptests::render_bitfield(bitfield, &self.config.naming)?);
+        }
+        if let Some(fuzz) = &mut self.fuzz {
+            fuzz.bitfields.push((fuzz.path.clone(), bitfield.clone()));
+        }
+        Ok(())
+    }
+
+    /// Add an enumeration shared by fields of bitfields, which are rendered in modules of the
+    /// module holding this file.
+    pub fn add_enumeration(&mut self, enumeration: &Enumeration) -> Result<()> {
+        enumeration.declare(&mut self.config.naming);
+        util::check_conflicts(&enumeration.conflicts(&self.config.naming))?;
+        self.items.extend(bitfield::render_enumeration(
+            enumeration,
+            &self.derives,
+            &self.config.naming,
+        )?);
         Ok(())
     }
 
@@ -63,6 +422,15 @@ impl GenFile {
 
         let mut file = File::create(path).expect("Could not create output file.");
 
+        let data = util::unparse(&self.render())?;
+        file.write_all(data.as_ref())
+            .expect("Could not write file.");
+
+        Ok(())
+    }
+
+    /// Render the contents of the file, including its test modules.
+    pub fn render(&self) -> TokenStream {
         let mut dat = TokenStream::new();
 
         if self.any {
@@ -76,10 +444,59 @@ impl GenFile {
             #items
         });
 
-        let data = dat.to_string().replace("] ", "]\n");
-        file.write_all(data.as_ref())
-            .expect("Could not write file.");
+        if let Some(tests) = &self.tests {
+            if !tests.is_empty() {
+                dat.extend(roundtrip::render_module(tests));
+            }
+        }
 
-        Ok(())
+        if let Some(proptests) = &self.proptests {
+            if !proptests.is_empty() {
+                dat.extend(proptests::render_module(proptests));
+            }
+        }
+
+        dat
+    }
+
+    /// Write the collected fuzz targets as `<dir>/<type>.rs`, which does nothing unless
+    /// [`GenFile::set_fuzz_targets`] was called, and return the names of the targets. Every
+    /// target needs a `[[bin]]` entry in the manifest of the fuzz crate, which
+    /// [`GenTree::write_fuzz_crate`](crate::tree::GenTree::write_fuzz_crate) writes along with
+    /// them.
+    pub fn write_fuzz_targets(&self, dir: &str) -> Result<Vec<String>> {
+        let fuzz = match &self.fuzz {
+            Some(fuzz) => fuzz,
+            None => return Ok(vec![]),
+        };
+
+        let mut targets = fuzz.targets.clone();
+        for (path, bitfield) in &fuzz.bitfields {
+            let order = self
+                .byte_orders
+                .get(&bitfield.name)
+                .copied()
+                .unwrap_or_default();
+            targets.push((
+                bitfield
+                    .name
+                    .to_sanitized_snake_case(&self.config.naming.types())
+                    .into(),
+                fuzz::render_bitfield(path, bitfield, order)?,
+            ));
+        }
+
+        let dir = Path::new(dir);
+        create_dir_all(dir)?;
+        let mut names = vec![];
+        for (name, target) in &targets {
+            let name = name.trim_start_matches("r#");
+            let mut file = File::create(dir.join(format!("{}.rs", name)))?;
+            let data = util::unparse(target)?;
+            file.write_all(data.as_ref())?;
+            names.push(String::from(name));
+        }
+
+        Ok(names)
     }
 }
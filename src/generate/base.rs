@@ -0,0 +1,95 @@
+use anyhow::{bail, Result};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+
+use crate::generate::structure::{StructMember, Structure};
+use crate::generate::view;
+use crate::util::{self, Naming, ToSanitizedPascalCase};
+
+/// Render the accessors of the base of `structure`, if it extends one, see
+/// [`Structure::with_base`]. Structures of a fixed size have a `base()` returning a copy of
+/// their base and convert into it, and their views have a `base()` or `base_mut()` returning a
+/// view of the base over the same bytes.
+pub fn render(structure: &Structure, naming: &Naming) -> Result<TokenStream> {
+    let base = match &structure.base {
+        Some(base) => base,
+        None => return Ok(TokenStream::new()),
+    };
+    if base
+        .members
+        .iter()
+        .any(|mem| matches!(mem, StructMember::AlternativesMember(_)))
+    {
+        bail!(
+            "base `{}` of `{}` holds alternatives, which bases cannot",
+            base.name,
+            structure.name
+        );
+    }
+
+    let span = Span::call_site();
+    let str_name = util::ident(
+        &structure.name.to_sanitized_pascal_case(&naming.types()),
+        span,
+    );
+    let base_name = util::ident(&base.name.to_sanitized_pascal_case(&naming.types()), span);
+    let mut out = TokenStream::new();
+
+    if structure.field_offsets().is_some() {
+        let doc = format!(
+            "The [`{}`] this structure extends, i.e. its first members.",
+            base_name
+        );
+        out.extend(quote! {
+            impl #str_name {
+                #[doc = #doc]
+                pub fn base(&self) -> #base_name {
+                    // the bytes of the structure start with the ones of its base
+                    match #base_name::read_from(&self.to_bytes()) {
+                        Ok((base, _)) => base,
+                        Err(_) => unreachable!(),
+                    }
+                }
+            }
+
+            impl From<#str_name> for #base_name {
+                #[inline(always)]
+                fn from(value : #str_name) -> Self {
+                    value.base()
+                }
+            }
+        });
+    }
+
+    if view::has_view(structure) {
+        let ref_name = util::ident(&format!("{}Ref", str_name), span);
+        let mut_name = util::ident(&format!("{}Mut", str_name), span);
+        let base_ref = util::ident(&format!("{}Ref", base_name), span);
+        let base_mut = util::ident(&format!("{}Mut", base_name), span);
+        let doc = format!("View of the [`{}`] this structure extends.", base_name);
+        out.extend(quote! {
+            impl<'a> #ref_name<'a> {
+                #[doc = #doc]
+                pub fn base(&self) -> #base_ref<'a> {
+                    // the checks of the structure include the ones of its base
+                    match #base_ref::new(self.buf) {
+                        Ok(base) => base,
+                        Err(_) => unreachable!(),
+                    }
+                }
+            }
+
+            impl<'a> #mut_name<'a> {
+                #[doc = #doc]
+                pub fn base_mut(&mut self) -> #base_mut<'_> {
+                    match #base_mut::new(self.buf) {
+                        Ok(base) => base,
+                        Err(_) => unreachable!(),
+                    }
+                }
+            }
+        });
+    }
+
+    Ok(out)
+}
@@ -1,17 +1,129 @@
-use anyhow::Result;
-use proc_macro2::{Ident, Span, TokenStream};
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+use anyhow::{bail, Result};
+use proc_macro2::{Ident, Literal, Span, TokenStream};
 use quote::quote;
 
-use crate::util::{self, ToSanitizedPascalCase, ToSanitizedSnakeCase, U32Ext};
+use crate::doc;
+use crate::util::{
+    self, Access, Config, Derives, Namespace, Naming, Scope, ToSanitizedPascalCase,
+    ToSanitizedSnakeCase, U32Ext, BITS_PER_BYTE,
+};
 
+#[derive(Clone)]
 pub struct EnumeratedValue(pub String, pub String, pub u64);
 
+/// A range of values read as a single variant holding the raw bits of the field.
+#[derive(Clone)]
+pub struct EnumeratedRange(pub String, pub String, pub RangeInclusive<u64>);
+
+impl EnumeratedRange {
+    /// Number of values of the range.
+    pub fn count(&self) -> u64 {
+        self.2
+            .end()
+            .saturating_sub(*self.2.start())
+            .saturating_add(1)
+    }
+}
+
+/// Enumerated values defined once and shared by fields of any number of bitfields, see
+/// [`BitFieldMember::with_enumeration`]. The enumeration is rendered on its own, as a sibling of
+/// the modules of the bitfields using it.
+#[derive(Clone)]
+pub struct Enumeration {
+    pub name: String,
+    pub desc: String,
+    pub bitsize: u32,
+    pub values: Vec<EnumeratedValue>,
+    pub ranges: Vec<EnumeratedRange>,
+}
+
+impl Enumeration {
+    pub fn new(name: &str, desc: &str, bitsize: u32) -> Self {
+        Enumeration {
+            name: String::from(name),
+            desc: String::from(desc),
+            bitsize,
+            values: vec![],
+            ranges: vec![],
+        }
+    }
+
+    pub fn add_enum_value(self, name: &str, bits: u64) -> Self {
+        self.add_enum_value_desc(name, "", bits)
+    }
+
+    pub fn add_enum_value_desc(mut self, name: &str, desc: &str, bits: u64) -> Self {
+        let name = String::from(name);
+        let desc = String::from(desc);
+        self.values.push(EnumeratedValue(name, desc, bits));
+        self
+    }
+
+    pub fn add_enum_range(self, name: &str, range: RangeInclusive<u64>) -> Self {
+        self.add_enum_range_desc(name, "", range)
+    }
+
+    pub fn add_enum_range_desc(
+        mut self,
+        name: &str,
+        desc: &str,
+        range: RangeInclusive<u64>,
+    ) -> Self {
+        let name = String::from(name);
+        let desc = String::from(desc);
+        self.ranges.push(EnumeratedRange(name, desc, range));
+        self
+    }
+
+    /// Declare the name of the enumeration and its values in the `naming`.
+    pub fn declare(&self, naming: &mut Naming) {
+        naming.declare(Namespace::Types, [self.name.as_str()]);
+        naming.declare(
+            Namespace::Names(self.name.clone()),
+            value_names(&self.values, &self.ranges),
+        );
+    }
+
+    /// Describe the values which are generated as the same variant or which share a value.
+    pub fn conflicts(&self, naming: &Naming) -> Vec<String> {
+        let owner = format!("enumeration `{}`", self.name);
+        let values = naming.names_of(&self.name);
+        value_conflicts(&owner, &self.values, &self.ranges, &values)
+    }
+}
+
+/// Scale from the raw bits of a numeric field to its engineering value.
+#[derive(Clone)]
+pub struct Scale {
+    /// Engineering value of a single step of the raw bits.
+    pub factor: f32,
+    /// Unit of the engineering value, e.g. `dBm`.
+    pub unit: String,
+}
+
+#[derive(Clone)]
 pub struct BitFieldMember {
     pub name: String,
     pub desc: String,
     pub bitsize: u32,
     pub enumerated_values: Vec<EnumeratedValue>,
+    pub enumerated_ranges: Vec<EnumeratedRange>,
     pub numeric: bool,
+    /// Value of the field written by `W::reset`.
+    pub default: u64,
+    pub bit_order: BitOrder,
+    /// Offset of the first bit of the field in the bit order of the bitfield, which follows the
+    /// previous field otherwise.
+    pub offset: Option<u32>,
+    /// Shared enumeration providing the enumerated values.
+    pub enumeration: Option<Box<Enumeration>>,
+    /// The raw bits are a two's complement number.
+    pub signed: bool,
+    pub scale: Option<Scale>,
+    pub access: Access,
 }
 
 impl BitFieldMember {
@@ -24,10 +136,27 @@ impl BitFieldMember {
             desc,
             bitsize,
             enumerated_values: vec![],
+            enumerated_ranges: vec![],
             numeric,
+            default: 0,
+            bit_order: BitOrder::default(),
+            offset: None,
+            enumeration: None,
+            signed: false,
+            scale: None,
+            access: Access::default(),
         }
     }
 
+    /// Take the enumerated values from the shared `enumeration`, which the field refers to
+    /// instead of generating its own enum.
+    pub fn with_enumeration(mut self, enumeration: &Enumeration) -> Self {
+        self.enumerated_values = enumeration.values.clone();
+        self.enumerated_ranges = enumeration.ranges.clone();
+        self.enumeration = Some(Box::new(enumeration.clone()));
+        self
+    }
+
     pub fn add_enum_value(self, name: &str, bits: u64) -> Self {
         self.add_enum_value_desc(name, "", bits)
     }
@@ -40,21 +169,225 @@ impl BitFieldMember {
         self
     }
 
+    pub fn add_enum_range(self, name: &str, range: RangeInclusive<u64>) -> Self {
+        self.add_enum_range_desc(name, "", range)
+    }
+
+    /// Read the values in `range` as the variant `name`, which holds the raw bits of the field
+    /// and is written back as is.
+    pub fn add_enum_range_desc(
+        mut self,
+        name: &str,
+        desc: &str,
+        range: RangeInclusive<u64>,
+    ) -> Self {
+        let name = String::from(name);
+        let desc = String::from(desc);
+        self.enumerated_ranges
+            .push(EnumeratedRange(name, desc, range));
+        self
+    }
+
     pub fn numeric(mut self) -> Self {
         self.numeric = true;
         self
     }
+
+    /// Restrict the accessors of the field, e.g. to only generate readers for fields which must
+    /// never be set by the host.
+    pub fn with_access(mut self, access: Access) -> Self {
+        self.access = access;
+        self
+    }
+
+    /// Make the field a numeric field holding a two's complement number, which is read and
+    /// written as such by the generated `<field>_value` accessors.
+    pub fn signed(mut self) -> Self {
+        self.numeric = true;
+        self.signed = true;
+        self
+    }
+
+    /// Make the field a numeric field whose raw bits are steps of `factor` `unit`, which the
+    /// generated `<field>_value` accessors convert from and to, e.g. RSSI in 0.5 dBm steps.
+    pub fn with_scale(mut self, factor: f32, unit: &str) -> Self {
+        self.numeric = true;
+        self.scale = Some(Scale {
+            factor,
+            unit: String::from(unit),
+        });
+        self
+    }
+
+    /// Select the order of the bits of the field, for fields with their most significant bit
+    /// at the lowest position of the bitfield.
+    pub fn with_bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.bit_order = bit_order;
+        self
+    }
+
+    /// Raw bits of `value` as stored in the bitfield.
+    pub fn raw(&self, value: u64) -> u64 {
+        match self.bit_order {
+            BitOrder::LsbFirst => value,
+            BitOrder::MsbFirst => value.reverse_bits() >> (64 - self.bitsize),
+        }
+    }
+
+    /// Place the field at `offset` bits from the first bit of the bitfield, which leaves the
+    /// bits skipped since the previous field reserved.
+    pub fn with_offset(mut self, offset: u32) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Set the value of the field written by `W::reset`, which is zero otherwise.
+    pub fn with_default(mut self, default: u64) -> Self {
+        self.default = default;
+        self
+    }
+
+    /// A single bit without enumerated values, which has `bit`, `set_bit` and `clear_bit`
+    /// accessors instead of an enum.
+    pub fn is_bit(&self) -> bool {
+        self.bitsize == 1 && self.enumerated_values.is_empty()
+    }
+
+    /// Largest value of the field.
+    pub fn max(&self) -> u64 {
+        u64::MAX >> (64 - self.bitsize)
+    }
+
+    /// Whether every value the field can hold is enumerated, in which case reading its variant
+    /// cannot fail.
+    pub fn is_complete(&self) -> bool {
+        count_values(&self.enumerated_values, &self.enumerated_ranges) >= 1u128 << self.bitsize
+    }
+}
+
+/// Number of values covered by the enumerated `values` and `ranges`.
+fn count_values(values: &[EnumeratedValue], ranges: &[EnumeratedRange]) -> u128 {
+    // a range over every value of 64 bits holds one more than fits a `u64`
+    ranges.iter().fold(values.len() as u128, |count, range| {
+        count + range.2.end().saturating_sub(*range.2.start()) as u128 + 1
+    })
 }
 
+#[derive(Clone)]
 pub enum MaybeField {
     Field(BitFieldMember),
     Reserved { bitsize: u32 },
 }
 
+/// Order in which bits are numbered.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum BitOrder {
+    /// The first field starts at the least significant bit and the bits of a field increase in
+    /// significance.
+    #[default]
+    LsbFirst,
+    /// The first field starts at the most significant bit and the bits of a field decrease in
+    /// significance, as drawn by many specifications.
+    MsbFirst,
+}
+
+/// How writers treat the reserved bits of a bitfield.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum ReservedPolicy {
+    /// Keep the reserved bits of the value the writer starts from.
+    #[default]
+    Preserve,
+    /// Clear the reserved bits.
+    Zero,
+    /// Set the reserved bits to the bits at the same positions of the value, so every reserved
+    /// bit must be within the first 64.
+    Value(u64),
+}
+
+/// Names of the enumerated values followed by the enumerated ranges.
+fn value_names<'a>(
+    values: &'a [EnumeratedValue],
+    ranges: &'a [EnumeratedRange],
+) -> impl Iterator<Item = &'a str> {
+    values
+        .iter()
+        .map(|value| value.0.as_str())
+        .chain(ranges.iter().map(|range| range.0.as_str()))
+}
+
+/// Describe the enumerated values and ranges of `owner` which are generated as the same variant
+/// in the `scope` or which share a value.
+fn value_conflicts(
+    owner: &str,
+    values: &[EnumeratedValue],
+    ranges: &[EnumeratedRange],
+    scope: &Scope,
+) -> Vec<String> {
+    let names = value_names(values, ranges).collect::<Vec<_>>();
+    let mut conflicts = util::name_conflicts("value", owner, &names, |name| {
+        name.to_sanitized_pascal_case(scope).into()
+    });
+    for (i, value) in values.iter().enumerate() {
+        if let Some(first) = values[..i].iter().find(|first| first.2 == value.2) {
+            conflicts.push(format!(
+                "values `{}` and `{}` of {} are both {:#x}",
+                first.0, value.0, owner, value.2
+            ));
+        }
+    }
+    conflicts
+}
+
+/// The bitfields known to a generated file, which the bitfield members of structures are
+/// checked against.
+#[derive(Clone, Default)]
+pub struct BitfieldRegistry {
+    /// Number of bits and width of the type of every known bitfield.
+    sizes: BTreeMap<String, (u32, u32)>,
+}
+
+impl BitfieldRegistry {
+    pub fn new() -> Self {
+        BitfieldRegistry::default()
+    }
+
+    /// Note that `bitfield` is defined, which fails if its layout is invalid.
+    pub fn register(&mut self, bitfield: &BitField) -> Result<()> {
+        let sizes = (bitfield.bitsize()?, bitfield.width()?);
+        self.sizes.insert(bitfield.name.clone(), sizes);
+        Ok(())
+    }
+
+    /// Note the bitfields known to `other`.
+    pub fn extend(&mut self, other: &BitfieldRegistry) {
+        self.sizes.extend(
+            other
+                .sizes
+                .iter()
+                .map(|(name, sizes)| (name.clone(), *sizes)),
+        );
+    }
+
+    /// Number of bits and width of the type of the bitfield `name`.
+    pub fn get(&self, name: &str) -> Option<(u32, u32)> {
+        self.sizes.get(name).copied()
+    }
+
+    /// Names of the known bitfields in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.sizes.keys().map(String::as_str)
+    }
+}
+
+#[derive(Clone)]
 pub struct BitField {
     pub name: String,
     pub desc: String,
     pub fields: Vec<MaybeField>,
+    pub reserved: ReservedPolicy,
+    pub bit_order: BitOrder,
+    /// Number of bits the fields and reserved bits are expected to add up to.
+    pub declared_width: Option<u32>,
 }
 
 impl BitField {
@@ -65,126 +398,833 @@ impl BitField {
             name,
             desc,
             fields: vec![],
+            reserved: ReservedPolicy::default(),
+            bit_order: BitOrder::default(),
+            declared_width: None,
+        }
+    }
+
+    /// Document the generated reader with `desc`.
+    pub fn with_desc(mut self, desc: &str) -> Self {
+        self.desc = String::from(desc);
+        self
+    }
+
+    /// Declare the number of bits of the bitfield, which rendering checks against the fields
+    /// and reserved bits.
+    pub fn with_width(mut self, bits: u32) -> Self {
+        self.declared_width = Some(bits);
+        self
+    }
+
+    /// Select where the first field is placed, with [`BitOrder::MsbFirst`] placing it at the
+    /// most significant bit of the smallest type holding the bitfield.
+    pub fn with_bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.bit_order = bit_order;
+        self
+    }
+
+    /// The fields and reserved ranges ordered by their offset from the first bit of the
+    /// bitfield, along with that offset. Gaps left by fields placed at explicit offsets are
+    /// reserved, and fields without bits or overlapping fields are an error.
+    pub fn offsets(&self) -> Result<Vec<(MaybeField, u32)>> {
+        let mut cursor = 0;
+        let mut placed = vec![];
+        for field in &self.fields {
+            if let MaybeField::Field(BitFieldMember { bitsize: 0, .. }) = field {
+                bail!(
+                    "{} of bitfield `{}` has no bits",
+                    field.describe(),
+                    self.name
+                );
+            }
+            let offset = match field {
+                MaybeField::Field(BitFieldMember {
+                    offset: Some(offset),
+                    ..
+                }) => *offset,
+                _ => cursor,
+            };
+            cursor = offset + field.bitsize();
+            placed.push((field, offset));
+        }
+        placed.sort_by_key(|(_, offset)| *offset);
+
+        let mut offsets = vec![];
+        let mut end = 0;
+        let mut last: Option<&MaybeField> = None;
+        for (field, offset) in placed {
+            if offset < end {
+                bail!(
+                    "{} overlaps {} in bitfield `{}`",
+                    field.describe(),
+                    last.map_or_else(String::new, |last| last.describe()),
+                    self.name
+                );
+            }
+            if offset > end {
+                offsets.push((
+                    MaybeField::Reserved {
+                        bitsize: offset - end,
+                    },
+                    end,
+                ));
+            }
+            offsets.push((field.clone(), offset));
+            end = offset + field.bitsize();
+            last = Some(field);
+        }
+        Ok(offsets)
+    }
+
+    /// Declare the name of the bitfield, its fields and their values in the `naming`.
+    pub fn declare(&self, naming: &mut Naming) {
+        naming.declare(Namespace::Types, [self.name.as_str()]);
+        let fields = self
+            .fields
+            .iter()
+            .filter_map(|field| match field {
+                MaybeField::Field(field) => Some(field),
+                MaybeField::Reserved { .. } => None,
+            })
+            .collect::<Vec<_>>();
+        naming.declare(
+            Namespace::Names(self.name.clone()),
+            fields.iter().map(|field| field.name.as_str()),
+        );
+        for field in fields {
+            naming.declare(
+                Namespace::Values(self.name.clone(), field.name.clone()),
+                value_names(&field.enumerated_values, &field.enumerated_ranges),
+            );
+        }
+    }
+
+    /// Describe the fields which are generated as the same accessor and the values of each
+    /// field which are generated as the same variant or which share a value.
+    pub fn conflicts(&self, naming: &Naming) -> Vec<String> {
+        let scope = naming.names_of(&self.name);
+        let fields = self
+            .fields
+            .iter()
+            .filter_map(|field| match field {
+                MaybeField::Field(field) => Some(field),
+                MaybeField::Reserved { .. } => None,
+            })
+            .collect::<Vec<_>>();
+        let names = fields
+            .iter()
+            .map(|field| field.name.as_str())
+            .collect::<Vec<_>>();
+        let mut conflicts = util::name_conflicts(
+            "field",
+            &format!("bitfield `{}`", self.name),
+            &names,
+            |name| name.to_sanitized_snake_case(&scope).into(),
+        );
+        for field in fields {
+            let owner = format!("field `{}` in bitfield `{}`", field.name, self.name);
+            conflicts.extend(value_conflicts(
+                &owner,
+                &field.enumerated_values,
+                &field.enumerated_ranges,
+                &naming.values_of(&self.name, &field.name),
+            ));
+        }
+        conflicts
+    }
+
+    /// Names of the enums of the enumerated fields rendered in the module of the bitfield, e.g.
+    /// `FrameTypeA`.
+    pub fn field_enums(&self, naming: &Naming) -> Vec<String> {
+        let fields = naming.names_of(&self.name);
+        self.fields
+            .iter()
+            .filter_map(|field| match field {
+                MaybeField::Field(field) if !field.is_bit() && !field.numeric => {
+                    Some(format!("{}A", field.name.to_sanitized_pascal_case(&fields)))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Number of bits used by the fields and reserved ranges.
+    pub fn bitsize(&self) -> Result<u32> {
+        Ok(self
+            .offsets()?
+            .last()
+            .map_or(0, |(field, offset)| offset + field.bitsize()))
+    }
+
+    /// Number of bits of the type holding the bitfield, which is a byte array for bitfields
+    /// of more than 64 bits.
+    pub fn width(&self) -> Result<u32> {
+        let bitsize = self.bitsize()?;
+        if bitsize > 64 {
+            Ok(bitsize.div_ceil(BITS_PER_BYTE) * BITS_PER_BYTE)
+        } else {
+            bitsize.to_ty_width()
+        }
+    }
+
+    /// Number of bytes the bitfield is read from and written to, the fewest holding all its bits.
+    pub fn bytes(&self) -> Result<u32> {
+        Ok(self.bitsize()?.div_ceil(BITS_PER_BYTE))
+    }
+
+    /// Whether the bitfield is held by a byte array.
+    pub fn is_wide(&self) -> Result<bool> {
+        Ok(self.width()? > 64)
+    }
+
+    /// The fields and reserved ranges along with the position of their least significant bit.
+    /// Most significant bits first are counted from the bytes written rather than the type
+    /// holding the bitfield, whose bits beyond them are never serialized.
+    pub fn layout(&self) -> Result<Vec<(MaybeField, u32)>> {
+        let width = self.bytes()? * BITS_PER_BYTE;
+        Ok(self
+            .offsets()?
+            .into_iter()
+            .map(|(field, offset)| {
+                let pos = match self.bit_order {
+                    BitOrder::LsbFirst => offset,
+                    BitOrder::MsbFirst => width - offset - field.bitsize(),
+                };
+                (field, pos)
+            })
+            .collect())
+    }
+
+    /// Select how writers treat the reserved bits.
+    pub fn with_reserved(mut self, reserved: ReservedPolicy) -> Self {
+        self.reserved = reserved;
+        self
+    }
+
+    /// Little endian bytes of the bitfield, which have every bit `bit(field, i, pos)` set for
+    /// which the closure returns `true`, given the field, the index of the bit within the field
+    /// and its position in the bitfield.
+    fn image<F>(&self, bit: F) -> Result<Vec<u8>>
+    where
+        F: Fn(&MaybeField, u32, u32) -> bool,
+    {
+        let mut bytes = vec![0u8; (self.width()? / BITS_PER_BYTE) as usize];
+        for (field, pos) in self.layout()? {
+            for i in 0..field.bitsize() {
+                let pos = pos + i;
+                if bit(&field, i, pos) {
+                    bytes[(pos / BITS_PER_BYTE) as usize] |= 1 << (pos % BITS_PER_BYTE);
+                }
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Mask of the reserved bits, as little endian bytes.
+    pub fn reserved_mask(&self) -> Result<Vec<u8>> {
+        self.image(|field, _, _| matches!(field, MaybeField::Reserved { .. }))
+    }
+
+    /// Value written by `W::reset` as little endian bytes, made of the defaults of the fields
+    /// and the reserved bits required by the reserved policy.
+    pub fn reset_value(&self) -> Result<Vec<u8>> {
+        self.image(|field, i, pos| match field {
+            MaybeField::Field(field) => i < 64 && field.raw(field.default) >> i & 1 != 0,
+            MaybeField::Reserved { .. } => match self.reserved {
+                // reserved values only apply to the first 64 bits, see `check_reserved`
+                ReservedPolicy::Value(value) => pos < 64 && value >> pos & 1 != 0,
+                ReservedPolicy::Preserve | ReservedPolicy::Zero => false,
+            },
+        })
+    }
+
+    pub fn add_field(mut self, field: MaybeField) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    pub fn add_bit_field<F>(self, name: &str, desc: &str, bitsize: u32, mut f: F) -> Self
+    where
+        F: FnMut(BitFieldMember) -> BitFieldMember,
+    {
+        let field = BitFieldMember::new(name, desc, bitsize);
+        let field = f(field);
+        self.add_field(MaybeField::Field(field))
+    }
+
+    /// Add a field like [`BitField::add_bit_field`] at `offset` bits from the first bit of the
+    /// bitfield, see [`BitFieldMember::with_offset`].
+    pub fn add_bit_field_at<F>(
+        self,
+        name: &str,
+        desc: &str,
+        offset: u32,
+        bitsize: u32,
+        mut f: F,
+    ) -> Self
+    where
+        F: FnMut(BitFieldMember) -> BitFieldMember,
+    {
+        self.add_bit_field(name, desc, bitsize, |field| f(field.with_offset(offset)))
+    }
+
+    /// Add a field like [`BitField::add_bit_field`], which `W::reset` sets to `default`.
+    pub fn add_bit_field_default<F>(
+        self,
+        name: &str,
+        desc: &str,
+        bitsize: u32,
+        default: u64,
+        mut f: F,
+    ) -> Self
+    where
+        F: FnMut(BitFieldMember) -> BitFieldMember,
+    {
+        self.add_bit_field(name, desc, bitsize, |field| f(field.with_default(default)))
+    }
+
+    pub fn add_reserved(self, bitsize: u32) -> Self {
+        self.add_field(MaybeField::Reserved { bitsize })
+    }
+}
+
+impl MaybeField {
+    pub fn bitsize(&self) -> u32 {
+        match self {
+            MaybeField::Field(field) => field.bitsize,
+            MaybeField::Reserved { bitsize } => *bitsize,
+        }
+    }
+
+    /// Describe the field for error messages.
+    fn describe(&self) -> String {
+        match self {
+            MaybeField::Field(field) => format!("field `{}`", field.name),
+            MaybeField::Reserved { bitsize } => format!("{} reserved bits", bitsize),
+        }
+    }
+}
+
+/// Derives of the bitfield types, which always include the traits relied on by the accessors.
+fn deriving_tokens(derives: &Derives) -> Result<TokenStream> {
+    derives
+        .clone()
+        .add_derive("Clone")
+        .add_derive("Copy")
+        .add_derive("PartialEq")
+        .tokens()
+}
+
+/// Decode the field from the raw bits `raw`, reversing them for [`BitOrder::MsbFirst`].
+fn render_decode(field: &BitFieldMember, raw: TokenStream) -> Result<TokenStream> {
+    Ok(match field.bit_order {
+        BitOrder::MsbFirst if field.bitsize > 1 => {
+            let shift = util::unsuffixed((field.bitsize.to_ty_width()? - field.bitsize) as u64);
+            quote! { (#raw).reverse_bits() >> #shift }
+        }
+        _ => raw,
+    })
+}
+
+/// Encode the field `value` into raw bits, reversing them for [`BitOrder::MsbFirst`].
+fn render_encode(field: &BitFieldMember, value: TokenStream) -> Result<TokenStream> {
+    render_decode(field, value)
+}
+
+/// Render reading the raw bits of `field` at `field_pos` of `bits`, a bitfield of
+/// `structsize` bits.
+fn render_extract(
+    field: &BitFieldMember,
+    structsize: u32,
+    field_pos: u32,
+    bits: TokenStream,
+) -> Result<TokenStream> {
+    let fty = field.bitsize.to_ty()?;
+    let field_offset = util::unsuffixed(field_pos as u64);
+    let raw = if structsize > 64 {
+        let bitsize = util::unsuffixed(field.bitsize as u64);
+        quote! { crate::extract_bits(&#bits, #field_offset, #bitsize) }
+    } else {
+        let field_mask = util::hex(field.max());
+        quote! { crate::Bits::extract(#bits, #field_mask, #field_offset) }
+    };
+    Ok(if field.bitsize == 1 {
+        quote! { #raw != 0 }
+    } else {
+        quote! { #raw as #fty }
+    })
+}
+
+/// Render a statement replacing the raw bits of `field` at `field_pos` of the place `bits`, a
+/// bitfield of `structsize` bits, with `value`.
+fn render_insert(
+    field: &BitFieldMember,
+    structsize: u32,
+    field_pos: u32,
+    bits: TokenStream,
+    value: TokenStream,
+) -> Result<TokenStream> {
+    let field_offset = util::unsuffixed(field_pos as u64);
+    Ok(if structsize > 64 {
+        let bitsize = util::unsuffixed(field.bitsize as u64);
+        quote! { crate::insert_bits(&mut #bits, #field_offset, #bitsize, (#value) as u64); }
+    } else {
+        let sty = structsize.to_ty()?;
+        let field_mask = util::hex(field.max());
+        quote! { #bits = crate::Bits::insert(#bits, (#value) as #sty, #field_mask, #field_offset); }
+    })
+}
+
+pub fn add_field_numeric(
+    field: &BitFieldMember,
+    structsize: u32,
+    field_pos: u32,
+    reader_impl: &mut TokenStream,
+    writer_impl: &mut TokenStream,
+    fields: &Scope,
+) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let mod_items = TokenStream::new();
+
+    let field_name = field.name.as_str();
+    let field_name_sc = util::ident(&field_name.to_sanitized_snake_case(fields), span);
+    let field_name_pc = util::ident(&field_name.to_sanitized_pascal_case(fields), span);
+    let fty = field.bitsize.to_ty()?;
+
+    let read_doc = format!("Read the `{}` field.", field_name_pc);
+    let set_doc = format!("Set the `{}` field.", field_name_pc);
+    let decode = render_decode(
+        field,
+        render_extract(field, structsize, field_pos, quote! { self.bits })?,
+    )?;
+    let encode = render_encode(field, quote! { value })?;
+    let insert = render_insert(field, structsize, field_pos, quote! { bits }, encode)?;
+
+    if field.access.can_read() {
+        reader_impl.extend(quote! {
+            #[doc = #read_doc]
+            #[inline(always)]
+            pub fn #field_name_sc(&self) -> #fty {
+                #decode
+            }
+        });
+    }
+
+    if field.access.can_write() {
+        writer_impl.extend(quote! {
+            #[doc = #set_doc]
+            #[inline(always)]
+            pub fn #field_name_sc(&mut self, value : #fty) -> Self {
+                let mut bits = self.bits;
+                #insert
+                Self { bits, ..*self }
+            }
+        });
+    }
+
+    if field.signed || field.scale.is_some() {
+        add_field_value(field, reader_impl, writer_impl, fields)?;
+    }
+
+    Ok(mod_items)
+}
+
+/// Add a single bit field, which is read as a `bool` and has `bit`, `set_bit` and `clear_bit`
+/// writers like the bits of svd2rust.
+pub fn add_field_bit(
+    field: &BitFieldMember,
+    structsize: u32,
+    field_pos: u32,
+    reader_impl: &mut TokenStream,
+    writer_impl: &mut TokenStream,
+    fields: &Scope,
+) -> Result<TokenStream> {
+    if field.signed || field.scale.is_some() {
+        bail!(
+            "field `{}` of a single bit cannot be signed or scaled",
+            field.name
+        );
+    }
+
+    let span = Span::call_site();
+    let field_name_sc = util::ident(&field.name.to_sanitized_snake_case(fields), span);
+    let field_name_pc = util::ident(&field.name.to_sanitized_pascal_case(fields), span);
+    let field_name_pc_r = util::ident(&format!("{}R", field_name_pc), span);
+    let field_name_pc_w = util::ident(&format!("{}W", field_name_pc), span);
+
+    let decode = render_decode(
+        field,
+        render_extract(field, structsize, field_pos, quote! { self.bits })?,
+    )?;
+    let encode = render_encode(field, quote! { value })?;
+    let insert = render_insert(field, structsize, field_pos, quote! { self.w.bits }, encode)?;
+
+    let field_doc_reader = format!("Field `{}` reader - {}", field_name_pc, field.desc);
+    let field_doc_writer = format!("Field `{}` writer - {}", field_name_pc, field.desc);
+    let bit_doc = format!("Set the `{}` bit to `value`.", field_name_pc);
+    let set_bit_doc = format!("Set the `{}` bit.", field_name_pc);
+    let clear_bit_doc = format!("Clear the `{}` bit.", field_name_pc);
+    let mut mod_items = TokenStream::new();
+    let read_doc = format!("Read the `{}` bit.", field_name_pc);
+    let set_doc = format!("Set the `{}` bit.", field_name_pc);
+
+    if field.access.can_read() {
+        mod_items.extend(quote! {
+            #[doc = #field_doc_reader]
+            pub type #field_name_pc_r = crate::FieldReader<bool, bool>;
+        });
+
+        reader_impl.extend(quote! {
+            #[doc = #read_doc]
+            #[inline(always)]
+            pub fn #field_name_sc(&self) -> #field_name_pc_r {
+                #field_name_pc_r::new(#decode)
+            }
+        });
+    }
+
+    if !field.access.can_write() {
+        return Ok(mod_items);
+    }
+
+    mod_items.extend(quote! {
+        #[doc = #field_doc_writer]
+        pub struct #field_name_pc_w<'a> {
+            w : &'a mut W,
+        }
+
+        impl<'a> #field_name_pc_w<'a> {
+            #[doc = #bit_doc]
+            #[inline(always)]
+            pub fn bit(self, value : bool) -> &'a mut W {
+                #insert
+                self.w
+            }
+
+            #[doc = #set_bit_doc]
+            #[inline(always)]
+            pub fn set_bit(self) -> &'a mut W {
+                self.bit(true)
+            }
+
+            #[doc = #clear_bit_doc]
+            #[inline(always)]
+            pub fn clear_bit(self) -> &'a mut W {
+                self.bit(false)
+            }
+        }
+    });
+
+    writer_impl.extend(quote! {
+        #[doc = #set_doc]
+        #[inline(always)]
+        pub fn #field_name_sc(&mut self) -> #field_name_pc_w {
+            #field_name_pc_w { w : self }
+        }
+    });
+
+    Ok(mod_items)
+}
+
+/// Add the `<field>_value` accessors of a signed or scaled numeric field, which convert the raw
+/// bits read and written by the `<field>` accessors.
+fn add_field_value(
+    field: &BitFieldMember,
+    reader_impl: &mut TokenStream,
+    writer_impl: &mut TokenStream,
+    fields: &Scope,
+) -> Result<()> {
+    let span = Span::call_site();
+    let field_name_sc = util::ident(&field.name.to_sanitized_snake_case(fields), span);
+    let field_name_pc = field.name.to_sanitized_pascal_case(fields);
+    let field_value_sc = util::ident(&format!("{}_value", field_name_sc), span);
+    let fty = field.bitsize.to_ty()?;
+
+    // the sign bit is shifted to the top of the signed type and back to extend it
+    let (ity, read) = if field.signed {
+        let width = field.bitsize.to_ty_width()?;
+        let ity = util::ident(&format!("i{}", width), span);
+        let read = match width - field.bitsize {
+            0 => quote! { self.#field_name_sc() as #ity },
+            shift => {
+                let shift = util::unsuffixed(shift as u64);
+                quote! { ((self.#field_name_sc() as #ity) << #shift) >> #shift }
+            }
+        };
+        (ity, read)
+    } else {
+        (fty.clone(), quote! { self.#field_name_sc() })
+    };
+
+    match &field.scale {
+        Some(Scale { factor, unit }) => {
+            let factor_lit = Literal::f32_unsuffixed(*factor);
+            let read_doc = format!(
+                "Read the `{}` field in {}, in steps of {}.",
+                field_name_pc, unit, factor
+            );
+            let set_doc = format!(
+                "Set the `{}` field to `value` in {}, rounded to the nearest step of {}.",
+                field_name_pc, unit, factor
+            );
+            if field.access.can_read() {
+                reader_impl.extend(quote! {
+                    #[doc = #read_doc]
+                    #[inline(always)]
+                    pub fn #field_value_sc(&self) -> f32 {
+                        let steps = #read;
+                        steps as f32 * #factor_lit
+                    }
+                });
+            }
+            if field.access.can_write() {
+                writer_impl.extend(quote! {
+                    #[doc = #set_doc]
+                    #[inline(always)]
+                    pub fn #field_value_sc(&mut self, value : f32) -> Self {
+                        let steps = value / #factor_lit;
+                        let steps = if steps < 0.0 { steps - 0.5 } else { steps + 0.5 };
+                        self.#field_name_sc(steps as #ity as #fty)
+                    }
+                });
+            }
+        }
+        None => {
+            let read_doc = format!("Read the `{}` field as a signed number.", field_name_pc);
+            let set_doc = format!("Set the `{}` field to a signed number.", field_name_pc);
+            if field.access.can_read() {
+                reader_impl.extend(quote! {
+                    #[doc = #read_doc]
+                    #[inline(always)]
+                    pub fn #field_value_sc(&self) -> #ity {
+                        #read
+                    }
+                });
+            }
+            if field.access.can_write() {
+                writer_impl.extend(quote! {
+                    #[doc = #set_doc]
+                    #[inline(always)]
+                    pub fn #field_value_sc(&mut self, value : #ity) -> Self {
+                        self.#field_name_sc(value as #fty)
+                    }
+                });
+            }
         }
     }
 
-    pub fn add_field(mut self, field: MaybeField) -> Self {
-        self.fields.push(field);
-        self
+    Ok(())
+}
+
+/// Render the enum `name` of the enumerated `values` and `ranges` of a field of `bitsize` bits,
+/// named in the `scope`, along with its conversions from and to the raw bits of the field. The
+/// variants of ranges hold the raw bits, so the enum only has discriminants without them.
+fn render_enum(
+    name: &Ident,
+    desc: &str,
+    bitsize: u32,
+    values: &[EnumeratedValue],
+    ranges: &[EnumeratedRange],
+    derives: &Derives,
+    scope: &Scope,
+) -> Result<TokenStream> {
+    let deriving = deriving_tokens(derives)?;
+    let span = Span::call_site();
+    let fty = bitsize.to_ty()?;
+    if bitsize == 1 && !ranges.is_empty() {
+        bail!("enum `{}` of a single bit cannot have ranges", name);
+    }
+
+    let mut evs = TokenStream::new();
+    let mut ev_names = TokenStream::new();
+    let mut ev_variants = TokenStream::new();
+    let mut ev_bits = TokenStream::new();
+
+    for EnumeratedValue(key, desc, val) in values {
+        let key_pc = util::ident(&key.to_sanitized_pascal_case(scope), span);
+        let val_us = util::unsuffixed(*val);
+        let val_us_ob = util::unsuffixed_or_bool(*val, bitsize);
+
+        if ranges.is_empty() {
+            evs.extend(quote! {
+                #[doc = #desc]
+                #key_pc = #val_us,
+            });
+        } else {
+            evs.extend(quote! {
+                #[doc = #desc]
+                #key_pc,
+            });
+        }
+
+        let key_str = key_pc.to_string();
+        ev_names.extend(quote! {
+            #name::#key_pc => f.write_str(#key_str),
+        });
+
+        ev_variants.extend(quote! {
+            #val_us_ob => Ok(#name::#key_pc),
+        });
+
+        ev_bits.extend(quote! {
+            #name::#key_pc => #val_us,
+        });
     }
 
-    pub fn add_bit_field<F>(self, name: &str, desc: &str, bitsize: u32, mut f: F) -> Self
-    where
-        F: FnMut(BitFieldMember) -> BitFieldMember,
-    {
-        let field = BitFieldMember::new(name, desc, bitsize);
-        let field = f(field);
-        self.add_field(MaybeField::Field(field))
-    }
+    for EnumeratedRange(key, desc, range) in ranges {
+        let key_pc = util::ident(&key.to_sanitized_pascal_case(scope), span);
+        let start = util::hex(*range.start());
+        let end = util::hex(*range.end());
 
-    pub fn add_reserved(self, bitsize: u32) -> Self {
-        self.add_field(MaybeField::Reserved { bitsize })
-    }
-}
+        evs.extend(quote! {
+            #[doc = #desc]
+            #key_pc(#fty),
+        });
 
-impl MaybeField {
-    pub fn bitsize(&self) -> u32 {
-        match &self {
-            &MaybeField::Field(field) => field.bitsize,
-            &MaybeField::Reserved { bitsize } => *bitsize,
-        }
+        let key_fmt = format!("{}({{:#x}})", key_pc);
+        ev_names.extend(quote! {
+            #name::#key_pc(bits) => write!(f, #key_fmt, bits),
+        });
+
+        ev_variants.extend(quote! {
+            #start..=#end => Ok(#name::#key_pc(bits)),
+        });
+
+        ev_bits.extend(quote! {
+            #name::#key_pc(bits) => bits,
+        });
     }
-}
 
-pub fn add_field_numeric(
-    field: &BitFieldMember,
-    structsize: u32,
-    offset: u32,
-    reader_impl: &mut TokenStream,
-    writer_impl: &mut TokenStream,
-) -> Result<TokenStream> {
-    let span = Span::call_site();
-    let mut mod_items = TokenStream::new();
+    let noptions = 1u128 << bitsize.to_ty_width()?;
+    if count_values(values, ranges) < noptions {
+        ev_variants.extend(quote! {
+            _ => Err(bits),
+        });
+    }
 
-    let field_name = field.name.as_str();
-    let field_name_sc = Ident::new(&field_name.to_sanitized_snake_case(), span);
-    let field_name_pc = Ident::new(&field_name.to_sanitized_pascal_case(), span);
-    let fty = (field.bitsize as u32).to_ty()?;
-    let sty = (structsize as u32).to_ty()?;
-
-    let reverse_order = false;
-    let field_pos = if reverse_order {
-        (structsize - offset - field.bitsize) as u64
-    } else {
-        offset as u64
-    };
-    let field_offset = &util::unsuffixed(field_pos);
-    let field_mask = &util::hex((1 << field.bitsize) - 1);
+    let mut items = quote! {
+        #[doc = #desc]
+        #deriving
+        pub enum #name {
+            #evs
+        }
 
-    let read_doc = format!("Read the `{}` field.", field_name_pc);
-    let set_doc = format!("Set the `{}` field.", field_name_pc);
+        impl core::convert::TryFrom<#fty> for #name {
+            type Error = #fty;
 
-    reader_impl.extend(quote! {
-        #[doc = #read_doc]
-        #[inline(always)]
-        pub fn #field_name_sc(&self) -> #fty {
-            ((self.bits >> #field_offset) & #field_mask) as #fty
+            #[inline(always)]
+            fn try_from(bits : #fty) -> Result<Self, #fty> {
+                match bits {
+                    #ev_variants
+                }
+            }
         }
-    });
 
-    writer_impl.extend(quote! {
-        #[doc = #set_doc]
-        #[inline(always)]
-        pub fn #field_name_sc(&mut self, value : #fty) -> Self {
-            let bits = (self.bits & !(#field_mask << #field_offset)) | ((value as #sty & #field_mask) << #field_offset);
-                                        Self { bits, ..*self}
+        impl core::fmt::Display for #name {
+            fn fmt(&self, f : &mut core::fmt::Formatter) -> core::fmt::Result {
+                match *self {
+                    #ev_names
+                }
+            }
         }
-    });
+    };
 
-    Ok(mod_items)
+    if bitsize == 1 {
+        items.extend(quote! {
+            impl From<#name> for #fty {
+                #[inline(always)]
+                fn from(variant : #name) -> Self {
+                    variant as u8 != 0
+                }
+            }
+        });
+    } else if ranges.is_empty() {
+        items.extend(quote! {
+            impl From<#name> for #fty {
+                #[inline(always)]
+                fn from(variant : #name) -> Self {
+                    variant as _
+                }
+            }
+        });
+    } else {
+        items.extend(quote! {
+            impl From<#name> for #fty {
+                #[inline(always)]
+                fn from(variant : #name) -> Self {
+                    match variant {
+                        #ev_bits
+                    }
+                }
+            }
+        });
+    }
+
+    Ok(items)
+}
+
+/// Render a shared enumeration, which has to be a sibling of the modules of the bitfields
+/// referring to it.
+pub fn render_enumeration(
+    enumeration: &Enumeration,
+    derives: &Derives,
+    naming: &Naming,
+) -> Result<TokenStream> {
+    let name = util::ident(
+        &enumeration.name.to_sanitized_pascal_case(&naming.types()),
+        Span::call_site(),
+    );
+    render_enum(
+        &name,
+        &enumeration.desc,
+        enumeration.bitsize,
+        &enumeration.values,
+        &enumeration.ranges,
+        derives,
+        &naming.names_of(&enumeration.name),
+    )
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn add_field(
     field: &BitFieldMember,
     structsize: u32,
-    offset: u32,
+    field_pos: u32,
     reader_impl: &mut TokenStream,
     writer_impl: &mut TokenStream,
+    derives: &Derives,
+    bitfield: &str,
+    naming: &Naming,
 ) -> Result<TokenStream> {
     let span = Span::call_site();
     let mut mod_items = TokenStream::new();
+    let fields = naming.names_of(bitfield);
+    let values = naming.values_of(bitfield, &field.name);
 
     let field_name = field.name.as_str();
-    let field_name_sc = Ident::new(&field_name.to_sanitized_snake_case(), span);
-    let field_name_pc = Ident::new(&field_name.to_sanitized_pascal_case(), span);
-    let field_name_pc_r = Ident::new(&format!("{}R", field_name_pc), span);
-    let field_name_pc_w = Ident::new(&format!("{}W", field_name_pc), span);
-    let field_name_pc_a = Ident::new(&format!("{}A", field_name_pc), span);
+    let field_name_sc = util::ident(&field_name.to_sanitized_snake_case(&fields), span);
+    let field_name_pc = util::ident(&field_name.to_sanitized_pascal_case(&fields), span);
+    let field_name_pc_r = util::ident(&format!("{}R", field_name_pc), span);
+    let field_name_pc_w = util::ident(&format!("{}W", field_name_pc), span);
+    let field_name_pc_a = util::ident(&format!("{}A", field_name_pc), span);
     let field_doc = field.desc.as_str();
-    let fty = (field.bitsize as u32).to_ty()?;
-    let sty = (structsize as u32).to_ty()?;
+    let fty = field.bitsize.to_ty()?;
 
-    let reverse_order = false;
-
-    let field_pos = if reverse_order {
-        (structsize - offset - field.bitsize) as u64
-    } else {
-        offset as u64
-    };
-    let field_offset = &util::unsuffixed(field_pos);
-    let field_mask = &util::hex((1 << field.bitsize) - 1);
+    let decode = render_decode(
+        field,
+        render_extract(field, structsize, field_pos, quote! { self.bits })?,
+    )?;
+    let encode = render_encode(field, quote! { value })?;
+    let insert = render_insert(field, structsize, field_pos, quote! { self.w.bits }, encode)?;
 
-    let mut evs = TokenStream::new();
     let mut ev_checkers = TokenStream::new();
     let mut ev_setters = TokenStream::new();
-    let mut ev_variants = TokenStream::new();
 
-    for EnumeratedValue(key, desc, val) in &field.enumerated_values {
-        let key_pc = Ident::new(&key.to_sanitized_pascal_case(), span);
-        let key_sc = Ident::new(&key.to_sanitized_snake_case(), span);
-        let is_key_sc = Ident::new(&format!("is_{}", key_sc), span);
-        let val_us = util::unsuffixed(val.clone());
-        let val_us_ob = util::unsuffixed_or_bool(val.clone(), field.bitsize);
+    for EnumeratedValue(key, _, _) in &field.enumerated_values {
+        let key_pc = util::ident(&key.to_sanitized_pascal_case(&values), span);
+        let key_sc = util::ident(&key.to_sanitized_snake_case(&values), span);
+        let is_key_sc = util::ident(&format!("is_{}", key_sc), span);
 
         let is_doc = format!(
             "Checks if the value of the `{}` field is `{}`",
@@ -203,15 +1243,6 @@ pub fn add_field(
             }
         });
 
-        evs.extend(quote! {
-            #[doc = #desc]
-            #key_pc = #val_us,
-        });
-
-        ev_variants.extend(quote! {
-            #val_us_ob => #field_name_pc_a::#key_pc,
-        });
-
         ev_setters.extend(quote! {
             #[doc = #set_doc]
             #[inline(always)]
@@ -221,70 +1252,147 @@ pub fn add_field(
         });
     }
 
-    let noptions = 1 << field.bitsize.to_ty_width()?;
-    if field.enumerated_values.len() < noptions {
-        ev_variants.extend(quote! {
-            _ => unreachable!(),
+    for EnumeratedRange(key, _, range) in &field.enumerated_ranges {
+        let key_pc = util::ident(&key.to_sanitized_pascal_case(&values), span);
+        let key_sc = util::ident(&key.to_sanitized_snake_case(&values), span);
+        let is_key_sc = util::ident(&format!("is_{}", key_sc), span);
+        let start = util::hex(*range.start());
+        let end = util::hex(*range.end());
+
+        let is_doc = format!(
+            "Checks if the value of the `{}` field is within `{}`",
+            field_name_pc, key_pc
+        );
+        let set_doc = format!(
+            "Set the value of the `{}` field to `{}`, where `value` is expected to be within \
+             `{:#x}..={:#x}`",
+            field_name_pc,
+            key_pc,
+            range.start(),
+            range.end()
+        );
+
+        ev_checkers.extend(quote! {
+            #[doc = #is_doc]
+            #[inline(always)]
+            pub fn #is_key_sc(&self) -> bool {
+                matches!(self.bits, #start..=#end)
+            }
         });
-    }
 
-    let field_doc_reader = format!("Field `{}` reader - {}", field_name_pc, field.desc);
-    mod_items.extend(quote! {
-        #[doc = #field_doc]
-        #[derive(Clone, Copy, Debug, PartialEq)]
-        pub enum #field_name_pc_a {
-            #evs
-        }
-    });
+        ev_setters.extend(quote! {
+            #[doc = #set_doc]
+            #[inline(always)]
+            pub fn #key_sc(self, value : #fty) -> &'a mut W {
+                self.variant(#field_name_pc_a::#key_pc(value))
+            }
+        });
+    }
 
-    if field.bitsize == 1 {
-        mod_items.extend(quote! {
-            impl From<#field_name_pc_a> for #fty {
-                #[inline(always)]
-                fn from(variant : #field_name_pc_a) -> Self {
-                    variant as u8 != 0
+    // the value is masked to the field width, so only incomplete enumerations can fail
+    let variant_fn = if field.is_complete() {
+        quote! {
+            #[inline(always)]
+            pub fn variant(&self) -> #field_name_pc_a {
+                match #field_name_pc_a::try_from(self.bits) {
+                    Ok(variant) => variant,
+                    Err(_) => unreachable!(),
                 }
             }
-        });
+        }
     } else {
+        quote! {
+            /// Read the variant, which is `None` if the value is not enumerated.
+            #[inline(always)]
+            pub fn variant(&self) -> Option<#field_name_pc_a> {
+                #field_name_pc_a::try_from(self.bits).ok()
+            }
+        }
+    };
+
+    match &field.enumeration {
+        Some(enumeration) => {
+            if enumeration.bitsize != field.bitsize {
+                bail!(
+                    "field `{}` has {} bits, but its enumeration `{}` has {}",
+                    field.name,
+                    field.bitsize,
+                    enumeration.name,
+                    enumeration.bitsize
+                );
+            }
+            if enumeration.values.len() != field.enumerated_values.len()
+                || enumeration.ranges.len() != field.enumerated_ranges.len()
+            {
+                bail!(
+                    "field `{}` adds values to its enumeration `{}`",
+                    field.name,
+                    enumeration.name
+                );
+            }
+            let enumeration_pc = util::ident(
+                &enumeration.name.to_sanitized_pascal_case(&naming.types()),
+                span,
+            );
+            mod_items.extend(quote! {
+                #[doc = #field_doc]
+                pub use super::#enumeration_pc as #field_name_pc_a;
+            });
+        }
+        None => mod_items.extend(render_enum(
+            &field_name_pc_a,
+            field_doc,
+            field.bitsize,
+            &field.enumerated_values,
+            &field.enumerated_ranges,
+            derives,
+            &values,
+        )?),
+    }
+
+    let read_doc = format!("Read the `{}` field.", field_name_pc);
+    let set_doc = format!("Set the `{}` field.", field_name_pc);
+
+    let field_doc_reader = format!("Field `{}` reader - {}", field_name_pc, field.desc);
+    if field.access.can_read() {
         mod_items.extend(quote! {
-            impl From<#field_name_pc_a> for #fty {
+            #[doc = #field_doc_reader]
+            pub struct #field_name_pc_r(crate::FieldReader<#fty,#field_name_pc_a>);
+
+            impl #field_name_pc_r {
                 #[inline(always)]
-                fn from(variant : #field_name_pc_a) -> Self {
-                    variant as _
+                pub(crate) fn new(bits : #fty) -> Self {
+                    #field_name_pc_r(crate::FieldReader::new(bits))
                 }
-            }
-        });
-    }
 
-    mod_items.extend(quote! {
-        #[doc = #field_doc_reader]
-        pub struct #field_name_pc_r(crate::FieldReader<#fty,#field_name_pc_a>);
+                #variant_fn
 
-        impl #field_name_pc_r {
-            #[inline(always)]
-            pub(crate) fn new(bits : #fty) -> Self {
-                #field_name_pc_r(crate::FieldReader::new(bits))
+                #ev_checkers
             }
 
-            #[inline(always)]
-            pub fn variant(&self) -> #field_name_pc_a {
-                match self.bits {
-                    #ev_variants
+            impl core::ops::Deref for #field_name_pc_r {
+                type Target = crate::FieldReader<#fty,#field_name_pc_a>;
+                #[inline(always)]
+                fn deref(&self) -> &Self::Target {
+                    &self.0
                 }
             }
+        });
 
-            #ev_checkers
-        }
-
-        impl core::ops::Deref for #field_name_pc_r {
-            type Target = crate::FieldReader<#fty,#field_name_pc_a>;
+        reader_impl.extend(quote! {
+            #[doc = #read_doc]
             #[inline(always)]
-            fn deref(&self) -> &Self::Target {
-                &self.0
+            pub fn #field_name_sc(&self) -> #field_name_pc_r {
+                #field_name_pc_r::new(#decode)
             }
-        }
+        });
+    }
+
+    if !field.access.can_write() {
+        return Ok(mod_items);
+    }
 
+    mod_items.extend(quote! {
         pub struct #field_name_pc_w<'a> {
             w : &'a mut W,
         }
@@ -299,34 +1407,12 @@ pub fn add_field(
 
             #[inline(always)]
             pub unsafe fn bits(self, value: #fty) -> &'a mut W {
-                self.w.bits = (self.w.bits & !(#field_mask << #field_offset)) | ((value as #sty & #field_mask) << #field_offset);
+                #insert
                 self.w
             }
         }
     });
 
-    let read_doc = format!("Read the `{}` field.", field_name_pc);
-    let set_doc = format!("Set the `{}` field.", field_name_pc);
-
-    if field.bitsize == 1 {
-        let mask = &util::hex(1 << field_pos);
-        reader_impl.extend(quote! {
-            #[doc = #read_doc]
-            #[inline(always)]
-            pub fn #field_name_sc(&self) -> #field_name_pc_r {
-                #field_name_pc_r::new((self.bits & #mask) != 0)
-            }
-        })
-    } else {
-        reader_impl.extend(quote! {
-            #[doc = #read_doc]
-            #[inline(always)]
-            pub fn #field_name_sc(&self) -> #field_name_pc_r {
-                #field_name_pc_r::new(((self.bits >> #field_offset) & #field_mask) as #fty)
-            }
-        });
-    }
-
     writer_impl.extend(quote! {
         #[doc = #set_doc]
         #[inline(always)]
@@ -338,24 +1424,258 @@ pub fn add_field(
     Ok(mod_items)
 }
 
-pub fn render(structure: &BitField) -> Result<TokenStream> {
-    let desc = structure.desc.as_str();
+/// Render `Display` and, if `Debug` is derived, `Debug` for the reader, printing the decoded
+/// fields.
+fn render_fmt(structure: &BitField, derives: &Derives, naming: &Naming) -> TokenStream {
+    let span = Span::call_site();
+    let name = structure
+        .name
+        .to_sanitized_pascal_case(&naming.types())
+        .to_string();
+    let scope = naming.names_of(&structure.name);
+
+    let mut display = TokenStream::new();
+    let mut debug_fields = TokenStream::new();
+    let fields = structure.fields.iter().filter_map(|field| match field {
+        MaybeField::Field(field) if field.access.can_read() => Some(field),
+        _ => None,
+    });
+
+    for (idx, field) in fields.enumerate() {
+        let field_name_sc = field.name.to_sanitized_snake_case(&scope);
+        let field_name = util::ident(&field_name_sc, span);
+        let sep = if idx == 0 { "" } else { ", " };
+
+        if field.is_bit() {
+            let fmt = format!("{}{}: {{}}", sep, field_name_sc);
+            display.extend(quote! { write!(f, #fmt, self.#field_name().bit())?; });
+            debug_fields.extend(quote! {
+                s.field(#field_name_sc, &self.#field_name().bit());
+            });
+        } else if field.numeric {
+            let fmt = format!("{}{}: {{:#x}}", sep, field_name_sc);
+            display.extend(quote! { write!(f, #fmt, self.#field_name())?; });
+            debug_fields.extend(quote! {
+                s.field(#field_name_sc, &format_args!("{:#x}", self.#field_name()));
+            });
+        } else if field.is_complete() {
+            let fmt = format!("{}{}: {{}}", sep, field_name_sc);
+            display.extend(quote! { write!(f, #fmt, self.#field_name().variant())?; });
+            debug_fields.extend(quote! {
+                s.field(#field_name_sc, &self.#field_name().variant());
+            });
+        } else {
+            // values which are not enumerated are printed as numbers
+            let fmt = format!("{}{}: {{}}", sep, field_name_sc);
+            let fmt_bits = format!("{}{}: {{:#x}}", sep, field_name_sc);
+            display.extend(quote! {
+                match self.#field_name().variant() {
+                    Some(v) => write!(f, #fmt, v)?,
+                    None => write!(f, #fmt_bits, self.#field_name().bits())?,
+                }
+            });
+            debug_fields.extend(quote! {
+                match self.#field_name().variant() {
+                    Some(v) => s.field(#field_name_sc, &v),
+                    None => s.field(#field_name_sc, &format_args!("{:#x}", self.#field_name().bits())),
+                };
+            });
+        }
+    }
+
+    let mut items = quote! {
+        impl core::fmt::Display for R {
+            fn fmt(&self, f : &mut core::fmt::Formatter) -> core::fmt::Result {
+                #display
+                Ok(())
+            }
+        }
+    };
+
+    if derives.contains("Debug") {
+        items.extend(quote! {
+            impl core::fmt::Debug for R {
+                fn fmt(&self, f : &mut core::fmt::Formatter) -> core::fmt::Result {
+                    let mut s = f.debug_struct(#name);
+                    #debug_fields
+                    s.finish()
+                }
+            }
+        });
+    }
+
+    items
+}
+
+/// The first 8 of the little endian `bytes` as an integer.
+fn low_word(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .take(8)
+        .enumerate()
+        .fold(0, |acc, (i, b)| acc | (*b as u64) << (i * 8))
+}
+
+/// Render the little endian `bytes` as a value of the type holding a bitfield.
+fn render_image(bytes: &[u8]) -> TokenStream {
+    if bytes.len() > 8 {
+        let bytes = bytes.iter().map(|b| util::hex(*b as u64));
+        quote! { [#(#bytes),*] }
+    } else {
+        util::hex(low_word(bytes))
+    }
+}
+
+/// Check the bits of the fields and reserved bits against the declared width.
+fn check_width(structure: &BitField) -> Result<()> {
+    let declared = match structure.declared_width {
+        Some(declared) => declared,
+        None => return Ok(()),
+    };
+    let bitsize = structure.bitsize()?;
+    if bitsize != declared {
+        let fields = structure
+            .offsets()?
+            .iter()
+            .map(|(field, offset)| match field {
+                MaybeField::Field(field) => {
+                    format!("`{}` {}..{}", field.name, offset, offset + field.bitsize)
+                }
+                MaybeField::Reserved { bitsize } => {
+                    format!("reserved {}..{}", offset, offset + bitsize)
+                }
+            })
+            .collect::<Vec<_>>();
+        bail!(
+            "bitfield `{}` is declared as {} bits, but its fields and reserved bits add up to {} \
+             ({} bits {}): {}",
+            structure.name,
+            declared,
+            bitsize,
+            bitsize.abs_diff(declared),
+            if bitsize > declared {
+                "too many"
+            } else {
+                "missing"
+            },
+            fields.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Check that a reserved value covers every reserved bit, as it only holds the first 64.
+fn check_reserved(structure: &BitField) -> Result<()> {
+    if let ReservedPolicy::Value(_) = structure.reserved {
+        if structure.reserved_mask()?.iter().skip(8).any(|b| *b != 0) {
+            bail!(
+                "bitfield `{}` has reserved bits above bit 63, which a reserved value cannot set",
+                structure.name
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Check `structure` for definitions which generate code, but are likely mistakes.
+fn lint(structure: &BitField, config: &Config) -> Result<()> {
+    let bitsize = structure.bitsize()?;
+    if bitsize != structure.width()? {
+        config.lint(&format!(
+            "bitfield `{}` has {} bits, which are padded to {}",
+            structure.name,
+            bitsize,
+            structure.width()?
+        ))?;
+    }
+
+    for field in &structure.fields {
+        let field = match field {
+            MaybeField::Field(field) => field,
+            MaybeField::Reserved { .. } => continue,
+        };
+        if field.default > field.max() {
+            config.lint(&format!(
+                "default of field `{}` in bitfield `{}` does not fit into {} bits",
+                field.name, structure.name, field.bitsize
+            ))?;
+        }
+        for value in &field.enumerated_values {
+            if value.2 > field.max() {
+                config.lint(&format!(
+                    "value `{}` of field `{}` in bitfield `{}` does not fit into {} bits",
+                    value.0, field.name, structure.name, field.bitsize
+                ))?;
+            }
+        }
+        for (i, range) in field.enumerated_ranges.iter().enumerate() {
+            let (start, end) = (*range.2.start(), *range.2.end());
+            if start > end || end > field.max() {
+                config.lint(&format!(
+                    "range `{}` of field `{}` in bitfield `{}` is empty or does not fit into {} bits",
+                    range.0, field.name, structure.name, field.bitsize
+                ))?;
+            }
+            let overlaps = field
+                .enumerated_values
+                .iter()
+                .filter(|value| range.2.contains(&value.2))
+                .map(|value| &value.0)
+                .chain(
+                    field.enumerated_ranges[i + 1..]
+                        .iter()
+                        .filter(|other| *other.2.start() <= end && start <= *other.2.end())
+                        .map(|other| &other.0),
+                );
+            for other in overlaps {
+                config.lint(&format!(
+                    "range `{}` of field `{}` in bitfield `{}` overlaps `{}`",
+                    range.0, field.name, structure.name, other
+                ))?;
+            }
+        }
+    }
+
+    if let ReservedPolicy::Value(value) = structure.reserved {
+        if value & !low_word(&structure.reserved_mask()?) != 0 {
+            config.lint(&format!(
+                "reserved value {:#x} of bitfield `{}` sets bits which are not reserved",
+                value, structure.name
+            ))?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn render(structure: &BitField, derives: &Derives, config: &Config) -> Result<TokenStream> {
+    check_width(structure)?;
+    check_reserved(structure)?;
+    lint(structure, config)?;
+    let naming = &config.naming;
+    let fields = naming.names_of(&structure.name);
+
+    let deriving = deriving_tokens(derives)?;
+    // the reader implements `Debug` itself to print the decoded fields
+    let deriving_r = deriving_tokens(&derives.clone().remove_derive("Debug"))?;
+    let doc = doc::render_doc(&structure.desc, &doc::bitfield_diagram(structure)?);
 
-    let structsize = (structure.fields.iter().map(|v| v.bitsize()).sum::<u32>()).to_ty_width()?;
-    let sty = structsize.to_ty()?;
+    let structsize = structure.width()?;
+    let wide = structure.is_wide()?;
+    let sty = util::raw_ty(structsize / BITS_PER_BYTE)?;
 
     let mut mod_items = TokenStream::new();
     let mut reader_impl = TokenStream::new();
     let mut writer_impl = TokenStream::new();
 
     mod_items.extend(quote! {
-        #[doc = #desc]
-        #[derive(Clone, Copy, Debug, PartialEq)]
+        #doc
+        #deriving_r
         pub struct R {
             bits : #sty,
         }
 
-        #[derive(Clone, Copy, Debug, PartialEq)]
+        #deriving
         pub struct W {
             bits : #sty,
         }
@@ -369,32 +1689,83 @@ pub fn render(structure: &BitField) -> Result<TokenStream> {
         }
     });
 
-    let mut offset = 0u32;
-
-    for field in &structure.fields {
-        match &field {
-            &MaybeField::Field(field) => {
-                if field.numeric {
-                    mod_items.extend(add_field_numeric(
-                        &field,
-                        structsize,
-                        offset,
-                        &mut reader_impl,
-                        &mut writer_impl,
-                    )?)
-                } else {
-                    mod_items.extend(add_field(
-                        &field,
-                        structsize,
-                        offset,
-                        &mut reader_impl,
-                        &mut writer_impl,
-                    )?)
+    let (new_doc, new_bits) = match structure.reserved {
+        ReservedPolicy::Preserve => ("Start writing from `bits`.", quote! { bits }),
+        // the reserved bits of the reset value are those required by the policy
+        _ if wide => (
+            "Start writing from `bits` with the reserved bits set to their required value.",
+            quote! {{
+                let mut bits = bits;
+                for (i, b) in bits.iter_mut().enumerate() {
+                    *b = (*b & !RESERVED_MASK[i]) | (RESET_VALUE[i] & RESERVED_MASK[i]);
                 }
+                bits
+            }},
+        ),
+        ReservedPolicy::Zero => (
+            "Start writing from `bits` with the reserved bits cleared.",
+            quote! { bits & !RESERVED_MASK },
+        ),
+        ReservedPolicy::Value(value) => {
+            let value = util::hex(value & low_word(&structure.reserved_mask()?));
+            (
+                "Start writing from `bits` with the reserved bits set to their required value.",
+                quote! { (bits & !RESERVED_MASK) | #value },
+            )
+        }
+    };
+    let is_default = if wide {
+        quote! {
+            self.bits
+                .iter()
+                .enumerate()
+                .all(|(i, b)| b & !RESERVED_MASK[i] == RESET_VALUE[i] & !RESERVED_MASK[i])
+        }
+    } else {
+        quote! { self.bits & !RESERVED_MASK == RESET_VALUE & !RESERVED_MASK }
+    };
+    let reserved_mask = render_image(&structure.reserved_mask()?);
+    let reset_value = render_image(&structure.reset_value()?);
+    mod_items.extend(quote! {
+        /// Mask of the reserved bits.
+        pub const RESERVED_MASK : #sty = #reserved_mask;
+        /// Value written by `W::reset`.
+        pub const RESET_VALUE : #sty = #reset_value;
+    });
+
+    for (field, pos) in structure.layout()? {
+        if let MaybeField::Field(field) = &field {
+            if field.is_bit() {
+                mod_items.extend(add_field_bit(
+                    field,
+                    structsize,
+                    pos,
+                    &mut reader_impl,
+                    &mut writer_impl,
+                    &fields,
+                )?)
+            } else if field.numeric {
+                mod_items.extend(add_field_numeric(
+                    field,
+                    structsize,
+                    pos,
+                    &mut reader_impl,
+                    &mut writer_impl,
+                    &fields,
+                )?)
+            } else {
+                mod_items.extend(add_field(
+                    field,
+                    structsize,
+                    pos,
+                    &mut reader_impl,
+                    &mut writer_impl,
+                    derives,
+                    &structure.name,
+                    naming,
+                )?)
             }
-            _ => (),
         }
-        offset += field.bitsize()
     }
 
     mod_items.extend(quote! {
@@ -404,18 +1775,33 @@ pub fn render(structure: &BitField) -> Result<TokenStream> {
                 R { bits }
             }
 
+            /// Whether every field holds its default value, ignoring the reserved bits.
+            #[inline(always)]
+            pub fn is_default(&self) -> bool {
+                #is_default
+            }
+
             #reader_impl
         }
 
         impl W {
+            #[doc = #new_doc]
             #[inline(always)]
             pub fn new(bits : #sty) -> Self {
-                W { bits }
+                W { bits : #new_bits }
+            }
+
+            /// Start writing from the default value of every field.
+            #[inline(always)]
+            pub fn reset() -> Self {
+                W::new(RESET_VALUE)
             }
 
             #writer_impl
         }
     });
 
+    mod_items.extend(render_fmt(structure, derives, naming));
+
     Ok(mod_items)
 }
@@ -0,0 +1,273 @@
+use anyhow::{bail, Result};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+
+use crate::generate::structure::{StructMember, Structure};
+use crate::util::{
+    self, Naming, ToSanitizedPascalCase, ToSanitizedSnakeCase, U32Ext, BITS_PER_BYTE,
+};
+
+/// Render the builder of `structure`, whose type parameters track which of the required members
+/// are set so `build` only exists once all of them are. Only primitive, offset, bitfield,
+/// alternatives and optional members can be set by the builder, the others keep their defaults.
+pub fn render(structure: &Structure, const_generic: bool, naming: &Naming) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let members = naming.names_of(&structure.name);
+
+    let str_name = util::ident(
+        &structure.name.to_sanitized_pascal_case(&naming.types()),
+        span,
+    );
+    let builder_name = util::ident(&format!("{}Builder", str_name), span);
+
+    for name in &structure.required {
+        match structure.members.iter().find(|mem| mem.name() == name) {
+            None => bail!(
+                "`{}` requires the unknown member `{}`",
+                structure.name,
+                name
+            ),
+            Some(mem) if render_setter(structure, mem, const_generic, naming)?.is_none() => bail!(
+                "member `{}` of `{}` cannot be required as the builder cannot set it",
+                name,
+                structure.name
+            ),
+            Some(_) => {}
+        }
+    }
+
+    let mut templ = TokenStream::new();
+    let mut templ_decl = TokenStream::new();
+    let mut where_clause = TokenStream::new();
+    for mem in &structure.members {
+        if let StructMember::AlternativesMember(alt) = mem {
+            let param = alt.param(const_generic, &members, naming);
+            templ_decl.extend(param.decl);
+            templ.extend(param.arg);
+            where_clause.extend(param.bound);
+        }
+    }
+
+    let states = structure
+        .required
+        .iter()
+        .map(|name| {
+            util::ident(
+                &format!("{}S", name.to_sanitized_pascal_case(&members)),
+                span,
+            )
+        })
+        .collect::<Vec<_>>();
+    let unset = states.iter().map(|_| quote! { crate::Unset, });
+    let set = states.iter().map(|_| quote! { crate::Set, });
+
+    let mut setters = TokenStream::new();
+    for mem in &structure.members {
+        let (params, value_ty, bounds, assign) =
+            match render_setter(structure, mem, const_generic, naming)? {
+                Some(setter) => setter,
+                None => continue,
+            };
+        let mem_name = util::ident(&mem.name().to_sanitized_snake_case(&members), span);
+        let doc = match mem.desc() {
+            Some(desc) => format!("Set `{}`: {}", mem.name(), desc),
+            None => format!("Set `{}`.", mem.name()),
+        };
+
+        match structure
+            .required
+            .iter()
+            .position(|name| name == mem.name())
+        {
+            Some(idx) => {
+                let next = states.iter().enumerate().map(|(i, state)| {
+                    if i == idx {
+                        quote! { crate::Set, }
+                    } else {
+                        quote! { #state, }
+                    }
+                });
+                setters.extend(quote! {
+                    #[doc = #doc]
+                    pub fn #mem_name #params(mut self, value : #value_ty) -> #builder_name<#templ #(#next)*> #bounds {
+                        #assign
+                        #builder_name {
+                            data : self.data,
+                            _state : core::marker::PhantomData,
+                        }
+                    }
+                });
+            }
+            None => setters.extend(quote! {
+                #[doc = #doc]
+                pub fn #mem_name #params(mut self, value : #value_ty) -> Self #bounds {
+                    #assign
+                    self
+                }
+            }),
+        }
+    }
+
+    let required = structure
+        .required
+        .iter()
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>();
+    let doc = format!(
+        "Builder of [`{}`], which only builds once the members {} are set.",
+        str_name,
+        required.join(", ")
+    );
+
+    Ok(quote! {
+        #[doc = #doc]
+        pub struct #builder_name<#templ_decl #(#states,)*> where #where_clause {
+            data : #str_name<#templ>,
+            _state : core::marker::PhantomData<(#(#states,)*)>,
+        }
+
+        impl<#templ_decl> #str_name<#templ> where #where_clause {
+            #[doc = "Start building the structure, whose required members must be set before `build`."]
+            pub fn builder() -> #builder_name<#templ #(#unset)*> {
+                #builder_name {
+                    data : Self::new(),
+                    _state : core::marker::PhantomData,
+                }
+            }
+        }
+
+        impl<#templ_decl #(#states,)*> #builder_name<#templ #(#states,)*> where #where_clause {
+            #setters
+        }
+
+        impl<#templ_decl> #builder_name<#templ #(#set)*> where #where_clause {
+            #[doc = "Build the structure, all required members being set."]
+            pub fn build(self) -> #str_name<#templ> {
+                self.data
+            }
+        }
+    })
+}
+
+/// Render the generic parameters, the type of the value, the where clause and the assignment of
+/// the builder setter of `mem`, if the builder can set it.
+fn render_setter(
+    structure: &Structure,
+    mem: &StructMember,
+    const_generic: bool,
+    naming: &Naming,
+) -> Result<Option<(TokenStream, TokenStream, TokenStream, TokenStream)>> {
+    let span = Span::call_site();
+    let members = naming.names_of(&structure.name);
+    let mem_name = util::ident(&mem.name().to_sanitized_snake_case(&members), span);
+    if !mem.access().can_write() {
+        return Ok(None);
+    }
+
+    Ok(Some(match mem {
+        StructMember::PrimitiveMember(mem) => {
+            let sty = (mem.bytes * BITS_PER_BYTE).to_ty()?;
+            (
+                quote! {},
+                quote! { #sty },
+                quote! {},
+                quote! { self.data.#mem_name = value; },
+            )
+        }
+        StructMember::EuiMember(mem) => {
+            let ty = mem.ty();
+            (
+                quote! {},
+                quote! { crate::#ty },
+                quote! {},
+                quote! { self.data.#mem_name = value.0; },
+            )
+        }
+        StructMember::TimestampMember(mem) => {
+            let check = mem.render_check(quote! { value });
+            (
+                quote! {},
+                quote! { u64 },
+                quote! {},
+                quote! {
+                    #check
+                    self.data.#mem_name = value;
+                },
+            )
+        }
+        StructMember::BcdMember(mem) => {
+            let sty = mem.ty()?;
+            let max = util::unsuffixed(mem.max());
+            (
+                quote! {},
+                quote! { #sty },
+                quote! {},
+                quote! {
+                    assert!(value <= #max);
+                    self.data.#mem_name = value;
+                },
+            )
+        }
+        StructMember::OffsetMember(mem) => {
+            let sty = (mem.bytes * BITS_PER_BYTE).to_ty()?;
+            let max = util::hex(mem.max());
+            (
+                quote! {},
+                quote! { #sty },
+                quote! {},
+                quote! {
+                    assert!(value <= #max);
+                    self.data.#mem_name = value;
+                },
+            )
+        }
+        StructMember::BitsMember(mem) => {
+            let sty = mem.ty()?;
+            let max = util::hex(mem.max());
+            (
+                quote! {},
+                quote! { #sty },
+                quote! {},
+                quote! {
+                    assert!(value <= #max);
+                    self.data.#mem_name = value;
+                },
+            )
+        }
+        StructMember::BitfieldMember(mem) => {
+            let pkg_name = structure.bitfield_path(&mem.bitfield, false, naming);
+            (
+                quote! { <F> },
+                quote! { F },
+                quote! { where for <'w> F : FnOnce(&'w mut #pkg_name::W) -> &'w mut #pkg_name::W },
+                quote! { self.data.#mem_name = **value(&mut #pkg_name::W::new(self.data.#mem_name)); },
+            )
+        }
+        StructMember::AlternativesMember(alt) => {
+            let ty = alt.param(const_generic, &members, naming).ty;
+            let check = alt.render_check(const_generic, quote! { value }, &members);
+            (
+                quote! {},
+                ty,
+                quote! {},
+                quote! {
+                    #check
+                    self.data.#mem_name = value;
+                },
+            )
+        }
+        StructMember::OptionalMember(mem) => {
+            let sty = util::ident(
+                &mem.structure.to_sanitized_pascal_case(&naming.types()),
+                span,
+            );
+            (
+                quote! {},
+                quote! { #sty },
+                quote! {},
+                quote! { self.data.#mem_name = Some(value); },
+            )
+        }
+        _ => return Ok(None),
+    }))
+}
@@ -0,0 +1,349 @@
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+
+use crate::util::{self, ByteOrder, IoBackend};
+
+/// Checksum algorithms supported by checksum members.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ChecksumAlgorithm {
+    /// CRC-16/CCITT-FALSE (polynomial `0x1021`, initial value `0xffff`).
+    Crc16Ccitt,
+    /// CRC-16/MODBUS (reflected polynomial `0x8005`, initial value `0xffff`), which is always
+    /// written low byte first.
+    Crc16Modbus,
+    /// CRC-16/MCRF4XX (reflected polynomial `0x1021`, initial value `0xffff`) as used by
+    /// MAVLink, which is always written low byte first.
+    Crc16Mcrf4xx,
+    /// CRC-16/KERMIT (reflected polynomial `0x1021`, initial value `0`) as used by the frame
+    /// check sequence of IEEE 802.15.4, which is always written low byte first.
+    Crc16Kermit,
+    /// CRC-8/DVB-S2 (polynomial `0xd5`, initial value `0`) as used by CRSF.
+    Crc8DvbS2,
+    /// CRC-32 as used by Ethernet and zlib.
+    Crc32,
+    /// 8-bit Fletcher checksum as used by UBX, whose two running sums modulo 256 are written in
+    /// order.
+    Fletcher8,
+    /// Wrapping sum of all bytes.
+    Sum8,
+    /// Exclusive or of all bytes.
+    Xor8,
+}
+
+impl ChecksumAlgorithm {
+    /// Number of bytes of the checksum on the wire.
+    pub fn bytes(&self) -> u32 {
+        match self {
+            ChecksumAlgorithm::Crc16Ccitt
+            | ChecksumAlgorithm::Crc16Modbus
+            | ChecksumAlgorithm::Crc16Mcrf4xx
+            | ChecksumAlgorithm::Crc16Kermit
+            | ChecksumAlgorithm::Fletcher8 => 2,
+            ChecksumAlgorithm::Crc32 => 4,
+            ChecksumAlgorithm::Crc8DvbS2 | ChecksumAlgorithm::Sum8 | ChecksumAlgorithm::Xor8 => 1,
+        }
+    }
+
+    /// Name of the generated type computing the checksum.
+    pub fn ident(&self) -> Ident {
+        let name = match self {
+            ChecksumAlgorithm::Crc16Ccitt => "Crc16Ccitt",
+            ChecksumAlgorithm::Crc16Modbus => "Crc16Modbus",
+            ChecksumAlgorithm::Crc16Mcrf4xx => "Crc16Mcrf4xx",
+            ChecksumAlgorithm::Crc16Kermit => "Crc16Kermit",
+            ChecksumAlgorithm::Crc8DvbS2 => "Crc8DvbS2",
+            ChecksumAlgorithm::Crc32 => "Crc32",
+            ChecksumAlgorithm::Fletcher8 => "Fletcher8",
+            ChecksumAlgorithm::Sum8 => "Sum8",
+            ChecksumAlgorithm::Xor8 => "Xor8",
+        };
+        Ident::new(name, Span::call_site())
+    }
+
+    /// Byte order of the checksum on the wire within a structure of byte order `order`, which
+    /// some algorithms fix regardless of the structure.
+    pub fn byte_order(&self, order: ByteOrder) -> ByteOrder {
+        match self {
+            ChecksumAlgorithm::Crc16Modbus
+            | ChecksumAlgorithm::Crc16Mcrf4xx
+            | ChecksumAlgorithm::Crc16Kermit
+            | ChecksumAlgorithm::Fletcher8 => ByteOrder::LittleEndian,
+            _ => order,
+        }
+    }
+}
+
+/// A byte appended to the input of a checksum, selected by the value of an integer member
+/// preceding the checksum, such as the `CRC_EXTRA` of MAVLink selected by the message id.
+#[derive(Clone, Debug)]
+pub struct ChecksumExtra {
+    /// Member whose value selects the byte.
+    pub key: String,
+    /// The values of the key along with the byte they select.
+    pub values: Vec<(u64, u8)>,
+}
+
+impl ChecksumExtra {
+    pub fn new(key: &str) -> Self {
+        ChecksumExtra {
+            key: String::from(key),
+            values: vec![],
+        }
+    }
+
+    /// Append `extra` when the key holds `value`.
+    pub fn add_value(mut self, value: u64, extra: u8) -> Self {
+        self.values.push((value, extra));
+        self
+    }
+
+    /// Render an expression selecting the byte for the key `value`, which is `None` for the
+    /// values without a byte.
+    pub(crate) fn render(&self, value: TokenStream) -> TokenStream {
+        let arms = self.values.iter().map(|(value, extra)| {
+            let value = util::unsuffixed(*value);
+            let extra = util::hex(*extra as u64);
+            quote! { #value => Some(#extra), }
+        });
+        quote! {
+            match #value {
+                #(#arms)*
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Render a checksum accumulator type, which computes the checksum of everything written to it.
+fn render_accumulator(
+    io: IoBackend,
+    name: &Ident,
+    doc: &str,
+    ty: TokenStream,
+    init: TokenStream,
+    update: TokenStream,
+    finish: TokenStream,
+) -> TokenStream {
+    let write_impl = render_write_impl(io, name);
+    quote! {
+        #[doc = #doc]
+        #[derive(Clone, Copy, Debug)]
+        pub struct #name {
+            state : #ty,
+        }
+
+        impl #name {
+            #[inline(always)]
+            pub fn new() -> Self {
+                Self { state : #init }
+            }
+
+            pub fn update(&mut self, bytes : &[u8]) {
+                for byte in bytes {
+                    let byte = *byte;
+                    #update
+                }
+            }
+
+            #[inline(always)]
+            pub fn finish(&self) -> #ty {
+                #finish
+            }
+        }
+
+        #write_impl
+    }
+}
+
+/// Render the `Write` implementation of a checksum accumulator for the selected I/O backend.
+fn render_write_impl(io: IoBackend, name: &Ident) -> TokenStream {
+    match io {
+        IoBackend::Core2 | IoBackend::Core => {
+            let io = io.path();
+            quote! {
+                impl #io::Write for #name {
+                    fn write(&mut self, buf : &[u8]) -> Result<usize, #io::Error> {
+                        self.update(buf);
+                        Ok(buf.len())
+                    }
+
+                    fn flush(&mut self) -> Result<(), #io::Error> {
+                        Ok(())
+                    }
+                }
+            }
+        }
+        IoBackend::EmbeddedIo => quote! {
+            impl embedded_io::ErrorType for #name {
+                type Error = core::convert::Infallible;
+            }
+
+            impl embedded_io::Write for #name {
+                fn write(&mut self, buf : &[u8]) -> Result<usize, Self::Error> {
+                    self.update(buf);
+                    Ok(buf.len())
+                }
+
+                fn flush(&mut self) -> Result<(), Self::Error> {
+                    Ok(())
+                }
+            }
+        },
+    }
+}
+
+/// Render the checksum accumulators referenced by structures with checksum members. These are
+/// expected to be placed at the root of the generated crate.
+pub fn render_support(io: IoBackend) -> TokenStream {
+    let mut items = TokenStream::new();
+
+    items.extend(render_accumulator(
+        io,
+        &ChecksumAlgorithm::Crc16Ccitt.ident(),
+        "CRC-16/CCITT-FALSE checksum.",
+        quote! { u16 },
+        quote! { 0xffff },
+        quote! {
+            self.state ^= (byte as u16) << 8;
+            for _ in 0..8 {
+                self.state = if self.state & 0x8000 != 0 {
+                    (self.state << 1) ^ 0x1021
+                } else {
+                    self.state << 1
+                };
+            }
+        },
+        quote! { self.state },
+    ));
+
+    items.extend(render_accumulator(
+        io,
+        &ChecksumAlgorithm::Crc16Modbus.ident(),
+        "CRC-16/MODBUS checksum.",
+        quote! { u16 },
+        quote! { 0xffff },
+        quote! {
+            self.state ^= byte as u16;
+            for _ in 0..8 {
+                self.state = if self.state & 1 != 0 {
+                    (self.state >> 1) ^ 0xa001
+                } else {
+                    self.state >> 1
+                };
+            }
+        },
+        quote! { self.state },
+    ));
+
+    items.extend(render_accumulator(
+        io,
+        &ChecksumAlgorithm::Crc16Mcrf4xx.ident(),
+        "CRC-16/MCRF4XX checksum.",
+        quote! { u16 },
+        quote! { 0xffff },
+        quote! {
+            self.state ^= byte as u16;
+            for _ in 0..8 {
+                self.state = if self.state & 1 != 0 {
+                    (self.state >> 1) ^ 0x8408
+                } else {
+                    self.state >> 1
+                };
+            }
+        },
+        quote! { self.state },
+    ));
+
+    items.extend(render_accumulator(
+        io,
+        &ChecksumAlgorithm::Crc16Kermit.ident(),
+        "CRC-16/KERMIT checksum.",
+        quote! { u16 },
+        quote! { 0 },
+        quote! {
+            self.state ^= byte as u16;
+            for _ in 0..8 {
+                self.state = if self.state & 1 != 0 {
+                    (self.state >> 1) ^ 0x8408
+                } else {
+                    self.state >> 1
+                };
+            }
+        },
+        quote! { self.state },
+    ));
+
+    items.extend(render_accumulator(
+        io,
+        &ChecksumAlgorithm::Crc8DvbS2.ident(),
+        "CRC-8/DVB-S2 checksum.",
+        quote! { u8 },
+        quote! { 0 },
+        quote! {
+            self.state ^= byte;
+            for _ in 0..8 {
+                self.state = if self.state & 0x80 != 0 {
+                    (self.state << 1) ^ 0xd5
+                } else {
+                    self.state << 1
+                };
+            }
+        },
+        quote! { self.state },
+    ));
+
+    items.extend(render_accumulator(
+        io,
+        &ChecksumAlgorithm::Crc32.ident(),
+        "CRC-32 checksum.",
+        quote! { u32 },
+        quote! { 0xffff_ffff },
+        quote! {
+            self.state ^= byte as u32;
+            for _ in 0..8 {
+                self.state = if self.state & 1 != 0 {
+                    (self.state >> 1) ^ 0xedb8_8320
+                } else {
+                    self.state >> 1
+                };
+            }
+        },
+        quote! { !self.state },
+    ));
+
+    items.extend(render_accumulator(
+        io,
+        &ChecksumAlgorithm::Fletcher8.ident(),
+        "8-bit Fletcher checksum, holding the first sum in the low byte.",
+        quote! { u16 },
+        quote! { 0 },
+        quote! {
+            let a = (self.state as u8).wrapping_add(byte);
+            let b = ((self.state >> 8) as u8).wrapping_add(a);
+            self.state = (b as u16) << 8 | a as u16;
+        },
+        quote! { self.state },
+    ));
+
+    items.extend(render_accumulator(
+        io,
+        &ChecksumAlgorithm::Sum8.ident(),
+        "Wrapping sum of all bytes.",
+        quote! { u8 },
+        quote! { 0 },
+        quote! { self.state = self.state.wrapping_add(byte); },
+        quote! { self.state },
+    ));
+
+    items.extend(render_accumulator(
+        io,
+        &ChecksumAlgorithm::Xor8.ident(),
+        "Exclusive or of all bytes.",
+        quote! { u8 },
+        quote! { 0 },
+        quote! { self.state ^= byte; },
+        quote! { self.state },
+    ));
+
+    items
+}
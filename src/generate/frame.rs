@@ -0,0 +1,342 @@
+use anyhow::{bail, Result};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+
+use crate::generate::bitfield::BitfieldRegistry;
+use crate::generate::frame_trait;
+use crate::generate::framing::Framing;
+use crate::generate::parser;
+use crate::generate::structure::{deriving_tokens_no_copy, StructMember, Structure};
+use crate::util::{
+    self, ByteOrder, Config, Derives, Naming, ToSanitizedPascalCase, ToSanitizedSnakeCase, U32Ext,
+};
+use crate::util::{unsuffixed, BITS_PER_BYTE};
+
+/// Frames of a protocol, which all start with a header bitfield whose enumerated field selects
+/// the structure of the frame, possibly after a fixed number of bytes. Rendered as an enum of the structures along with a `parse`
+/// function dispatching on the header, which needs another name for every further set of frames.
+pub struct Frames {
+    pub name: String,
+    pub desc: String,
+    /// Bitfield at the start of every frame.
+    pub header: String,
+    pub bytes: u32,
+    /// Bytes preceding the header in every frame.
+    pub offset: u32,
+    /// Byte order of the header, which is the one of the structures of the frames.
+    pub byte_order: ByteOrder,
+    /// Enumerated field of the header selecting the frame.
+    pub field: String,
+    /// The structures of the frames along with the enumerated value selecting them and whether
+    /// they are held as their generic structure.
+    pub frames: Vec<(String, String, bool)>,
+    /// Derives overriding the ones of the generated file.
+    pub derives: Option<Derives>,
+    /// Name of the function parsing a frame.
+    pub parse_fn: String,
+    /// Framing layer delimiting the frames on a serial link, see [`Frames::with_framing`].
+    pub framing: Option<Framing>,
+}
+
+impl Frames {
+    pub fn new(name: &str, header: &str, bytes: u32, field: &str) -> Self {
+        Frames {
+            name: String::from(name),
+            desc: String::new(),
+            header: String::from(header),
+            bytes,
+            offset: 0,
+            byte_order: ByteOrder::default(),
+            field: String::from(field),
+            frames: vec![],
+            derives: None,
+            parse_fn: String::from("parse"),
+            framing: None,
+        }
+    }
+
+    /// Document the generated enum with `desc`.
+    pub fn with_desc(mut self, desc: &str) -> Self {
+        self.desc = String::from(desc);
+        self
+    }
+
+    /// Look for the header `offset` bytes into the frames, e.g. after a length field.
+    pub fn with_offset(mut self, offset: u32) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Read the header in `byte_order`, which should match the structures of the frames.
+    pub fn with_byte_order(mut self, byte_order: ByteOrder) -> Self {
+        self.byte_order = byte_order;
+        self
+    }
+
+    /// Name the function parsing a frame `parse_fn` instead of `parse`, e.g. to add more than
+    /// one set of frames to a tree.
+    pub fn with_parse_fn(mut self, parse_fn: &str) -> Self {
+        self.parse_fn = String::from(parse_fn);
+        self
+    }
+
+    /// Delimit the frames with `framing` on the wire, which generates a `write_framed` encoding
+    /// a frame and a `read_framed` decoding the first frame of its input.
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = Some(framing);
+        self
+    }
+
+    /// Derive `derives` on the enum instead of the derives of the generated file.
+    pub fn with_derives(mut self, derives: Derives) -> Self {
+        self.derives = Some(derives);
+        self
+    }
+
+    /// Add the frame `structure`, which is read when the field of the header is `variant`.
+    /// Structures with alternatives are held as their generic structure, so all of their
+    /// alternatives must have a discriminator.
+    pub fn add_frame(mut self, variant: &str, structure: &Structure) -> Self {
+        let generic = structure
+            .members
+            .iter()
+            .any(|mem| matches!(mem, StructMember::AlternativesMember(_)));
+        self.frames
+            .push((String::from(variant), structure.name.clone(), generic));
+        self
+    }
+
+    /// Check that the header refers to a bitfield known to `bitfields` like the bitfield members
+    /// of every frame, see [`Structure::check_bitfields`].
+    pub fn check_header(&self, bitfields: &BitfieldRegistry, config: &Config) -> Result<()> {
+        Structure::new(&self.name)
+            .add_bitfield(&self.header, &self.header, self.bytes)
+            .check_bitfields(bitfields, config)
+    }
+
+    /// Describe the frames which are generated as the same variant and the enumerated values
+    /// selecting more than one frame.
+    pub fn conflicts(&self, naming: &Naming) -> Vec<String> {
+        let owner = format!("frames `{}`", self.name);
+        let frames = self
+            .frames
+            .iter()
+            .map(|(_, frame, _)| frame.as_str())
+            .collect::<Vec<_>>();
+        let mut conflicts = util::name_conflicts("frame", &owner, &frames, |name| {
+            name.to_sanitized_pascal_case(&naming.types()).into()
+        });
+        let values = naming.values_of(&self.header, &self.field);
+        let variants = self
+            .frames
+            .iter()
+            .map(|(variant, _, _)| variant.as_str())
+            .collect::<Vec<_>>();
+        conflicts.extend(util::name_conflicts(
+            "selecting value",
+            &owner,
+            &variants,
+            |name| name.to_sanitized_pascal_case(&values).into(),
+        ));
+        conflicts
+    }
+}
+
+pub fn render(frames: &Frames, derives: &Derives, naming: &Naming) -> Result<TokenStream> {
+    if frames.frames.is_empty() {
+        bail!("frames `{}` have no frames", frames.name);
+    }
+
+    let span = Span::call_site();
+    let deriving = deriving_tokens_no_copy(frames.derives.as_ref().unwrap_or(derives))?;
+
+    let name_pc = util::ident(&frames.name.to_sanitized_pascal_case(&naming.types()), span);
+    let pkg_name = util::ident(
+        &frames.header.to_sanitized_snake_case(&naming.types()),
+        span,
+    );
+    let fields = naming.names_of(&frames.header);
+    let field_sc = util::ident(&frames.field.to_sanitized_snake_case(&fields), span);
+    let field_pc_a = util::ident(
+        &format!("{}A", frames.field.to_sanitized_pascal_case(&fields)),
+        span,
+    );
+    let values = naming.values_of(&frames.header, &frames.field);
+    let field_name = frames.field.as_str();
+    let doc = if frames.desc.is_empty() {
+        format!(
+            "Frames selected by the `{}` field of `{}`.",
+            frames.field, frames.header
+        )
+    } else {
+        frames.desc.clone()
+    };
+
+    if frames.bytes > 8 {
+        bail!(
+            "header `{}` of frames `{}` does not fit into an integer",
+            frames.header,
+            frames.name
+        );
+    }
+    let sty = (frames.bytes * BITS_PER_BYTE).to_ty()?;
+    let width = (frames.bytes * BITS_PER_BYTE).to_ty_width()? / BITS_PER_BYTE;
+    let buffer_len = unsuffixed(width as u64);
+    let range = frames.byte_order.range(frames.bytes, width);
+    let from_bytes = frames.byte_order.from_bytes();
+    let parse_fn = util::ident(
+        &frames.parse_fn.to_sanitized_snake_case(&naming.types()),
+        span,
+    );
+    let start = unsuffixed(frames.offset as u64);
+    let end = unsuffixed((frames.offset + frames.bytes) as u64);
+
+    let mut variants = TokenStream::new();
+    let mut read_arms = TokenStream::new();
+    let mut write_arms = TokenStream::new();
+    let mut wire_size_arms = TokenStream::new();
+    let mut display_arms = TokenStream::new();
+    let mut frame_names = vec![];
+    for (variant, frame, generic) in &frames.frames {
+        let variant = util::ident(&variant.to_sanitized_pascal_case(&values), span);
+        let frame = util::ident(&frame.to_sanitized_pascal_case(&naming.types()), span);
+        let ty = if *generic {
+            util::ident(&format!("{}Generic", frame), span)
+        } else {
+            frame.clone()
+        };
+
+        variants.extend(quote! {
+            #frame(#ty),
+        });
+        read_arms.extend(quote! {
+            Ok(super::#pkg_name::#field_pc_a::#variant) => {
+                let (frame, len) = #ty::read_from(buf)?;
+                Ok((#name_pc::#frame(frame), len))
+            }
+        });
+        write_arms.extend(quote! {
+            #name_pc::#frame(frame) => frame.write(out),
+        });
+        wire_size_arms.extend(quote! {
+            #name_pc::#frame(frame) => frame.wire_size(),
+        });
+        display_arms.extend(quote! {
+            #name_pc::#frame(frame) => core::fmt::Display::fmt(frame, f),
+        });
+        frame_names.push(frame);
+    }
+
+    let parser = parser::render_frames(frames, naming)?;
+    let framed = render_framed(frames, naming);
+    let frame_impl = frame_trait::render_frames_impl(&name_pc, &frame_names);
+
+    Ok(quote! {
+        #[doc = #doc]
+        #deriving
+        pub enum #name_pc {
+            #variants
+        }
+
+        impl #name_pc {
+            #[doc = "Deserialize the frame selected by the header at the start of `buf`, returning the number of bytes read."]
+            pub fn read_from(buf : &[u8]) -> Result<(Self, usize), Error> {
+                if buf.len() < #end {
+                    return Err(Error::UnexpectedEof);
+                }
+                let mut buffer = [0u8; #buffer_len];
+                buffer[#range].copy_from_slice(&buf[#start..#end]);
+                let bits = super::#pkg_name::R::new(#sty::#from_bytes(buffer)).#field_sc().bits();
+                match super::#pkg_name::#field_pc_a::try_from(bits) {
+                    #read_arms
+                    #[allow(unreachable_patterns)]
+                    _ => Err(Error::InvalidEnumValue { field : #field_name, value : bits as u64 }),
+                }
+            }
+
+            pub fn write<W>(&self, out : &mut W) -> Result<(), Error> where W : Write {
+                match self {
+                    #write_arms
+                }
+            }
+
+            #[doc = "Serialize into `buf`, returning the number of bytes written."]
+            pub fn write_to(&self, buf : &mut [u8]) -> Result<usize, Error> {
+                let len = buf.len();
+                let mut out = buf;
+                self.write(&mut out)?;
+                Ok(len - out.len())
+            }
+
+            #[doc = "Number of bytes written by `write` for the current frame."]
+            pub fn wire_size(&self) -> usize {
+                match self {
+                    #wire_size_arms
+                }
+            }
+        }
+
+        impl core::fmt::Display for #name_pc {
+            fn fmt(&self, f : &mut core::fmt::Formatter) -> core::fmt::Result {
+                match self {
+                    #display_arms
+                }
+            }
+        }
+
+        #[doc = "Parse the frame at the start of `buf`."]
+        pub fn #parse_fn(buf : &[u8]) -> Result<#name_pc, Error> {
+            #name_pc::read_from(buf).map(|(frame, _)| frame)
+        }
+
+        #frame_impl
+
+        #parser
+
+        #framed
+    })
+}
+
+/// Render the functions writing and reading the `frames` delimited by their framing, if they
+/// have one.
+fn render_framed(frames: &Frames, naming: &Naming) -> TokenStream {
+    let framing = match frames.framing {
+        Some(framing) => framing.ident(),
+        None => return TokenStream::new(),
+    };
+    let name_pc = util::ident(
+        &frames.name.to_sanitized_pascal_case(&naming.types()),
+        Span::call_site(),
+    );
+    let write_doc = format!(
+        "Serialize into `out` delimited by [`{}`](crate::{}), returning the number of bytes written.",
+        framing, framing
+    );
+    let read_doc = format!(
+        "Deserialize the first frame delimited by [`{}`](crate::{}) in `data`, returning the number of bytes of `data` read.",
+        framing, framing
+    );
+
+    quote! {
+        impl #name_pc {
+            #[doc = #write_doc]
+            pub fn write_framed(&self, out : &mut [u8]) -> Result<usize, Error> {
+                let mut buf = [0u8; Self::MAX_SIZE];
+                let len = self.write_to(&mut buf)?;
+                crate::#framing::encode(&buf[..len], out)
+            }
+
+            #[doc = #read_doc]
+            pub fn read_framed(data : &[u8]) -> Result<(Self, usize), Error> {
+                let mut buf = [0u8; Self::MAX_SIZE];
+                let (len, read) = match crate::#framing::decode(data, &mut buf) {
+                    // frames longer than any frame are not framed correctly
+                    Err(Error::WriteZero) => return Err(Error::InvalidFraming),
+                    result => result?,
+                };
+                let (frame, _) = Self::read_from(&buf[..len])?;
+                Ok((frame, read))
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+
+/// Render the `traits` module holding the `Frame` trait, which every structure and set of frames
+/// implements. The trait is object safe, so outgoing frames of different structures can be
+/// queued as `&dyn Frame` without an enum of all of them. It is expected to be placed at the
+/// root of the generated crate, where a type of the protocol may well be called `Frame`.
+pub fn render_support() -> TokenStream {
+    quote! {
+        pub mod traits {
+            use crate::Error;
+
+            #[doc = "A frame which can be serialized without knowing its type, e.g. as `&dyn Frame`."]
+            pub trait Frame {
+                #[doc = "Name of the structure of the frame as in the protocol, e.g. to tell apart the frames of a queue."]
+                fn frame_kind(&self) -> &'static str;
+
+                #[doc = "Number of bytes written by `write_to` for the current values."]
+                fn wire_size(&self) -> usize;
+
+                #[doc = "Serialize into `buf`, returning the number of bytes written."]
+                fn write_to(&self, buf : &mut [u8]) -> Result<usize, Error>;
+            }
+        }
+    }
+}
+
+/// Render the implementation of `Frame` for the structure `ty` named `kind` in the protocol,
+/// which relies on the `wire_size` and `write` of the structure.
+pub fn render_impl(ty: &Ident, kind: &str) -> TokenStream {
+    render(ty, quote! { #kind })
+}
+
+/// Render the implementation of `Frame` for the enum of frames `ty`, whose `frame_kind` is the one
+/// of the frame held.
+pub fn render_frames_impl(ty: &Ident, frames: &[Ident]) -> TokenStream {
+    render(
+        ty,
+        quote! {
+            match self {
+                #(#ty::#frames(frame) => crate::traits::Frame::frame_kind(frame),)*
+            }
+        },
+    )
+}
+
+fn render(ty: &Ident, kind: TokenStream) -> TokenStream {
+    quote! {
+        impl crate::traits::Frame for #ty {
+            fn frame_kind(&self) -> &'static str {
+                #kind
+            }
+
+            fn wire_size(&self) -> usize {
+                #ty::wire_size(self)
+            }
+
+            fn write_to(&self, buf : &mut [u8]) -> Result<usize, Error> {
+                let len = buf.len();
+                let mut out = buf;
+                self.write(&mut out)?;
+                Ok(len - out.len())
+            }
+        }
+    }
+}
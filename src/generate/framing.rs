@@ -0,0 +1,234 @@
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+
+/// Framing layers delimiting frames on a serial link, which escape the bytes of a frame so
+/// its delimiter never occurs within it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Framing {
+    /// Consistent overhead byte stuffing, ending every frame with a zero byte.
+    Cobs,
+    /// SLIP (RFC 1055), ending every frame with `0xc0` and escaping `0xc0` and `0xdb` with
+    /// `0xdb`.
+    Slip,
+    /// HDLC-style byte stuffing (RFC 1662), starting and ending every frame with a `0x7e` flag
+    /// and escaping `0x7e` and `0x7d` as `0x7d` followed by the byte xor `0x20`.
+    Hdlc,
+}
+
+impl Framing {
+    /// Name of the generated type encoding and decoding the framing.
+    pub fn ident(&self) -> Ident {
+        let name = match self {
+            Framing::Cobs => "Cobs",
+            Framing::Slip => "Slip",
+            Framing::Hdlc => "Hdlc",
+        };
+        Ident::new(name, Span::call_site())
+    }
+}
+
+/// Render a framing `name` documented by `doc`, whose frames end with `delimiter`, whose
+/// encoding of `len` bytes takes at most `max_len` bytes and which encodes and decodes
+/// with `encode` and `decode`. `encode` writes to `out` at `o`, which is large enough, while
+/// `decode` reads `data` from `i` and writes to `out` at `o` through `put`.
+fn render_framing(
+    name: &Ident,
+    doc: &str,
+    delimiter: TokenStream,
+    max_len: TokenStream,
+    encode: TokenStream,
+    decode: TokenStream,
+) -> TokenStream {
+    quote! {
+        #[doc = #doc]
+        pub struct #name;
+
+        impl #name {
+            /// Byte ending every frame.
+            pub const DELIMITER : u8 = #delimiter;
+
+            /// Largest number of bytes of the encoding of `len` bytes, including its delimiters.
+            pub const fn max_encoded_len(len : usize) -> usize {
+                #max_len
+            }
+
+            /// Encode the frame `data` into `out` along with its delimiters, returning the
+            /// number of bytes written.
+            pub fn encode(data : &[u8], out : &mut [u8]) -> Result<usize, Error> {
+                if out.len() < Self::max_encoded_len(data.len()) {
+                    return Err(Error::WriteZero);
+                }
+                let mut o = 0;
+                #encode
+                Ok(o)
+            }
+
+            /// Decode the first frame of `data` into `out`, returning the number of bytes
+            /// decoded and the number of bytes of `data` read up to and including the delimiter
+            /// ending the frame. A frame missing its delimiter ends with `data`.
+            pub fn decode(data : &[u8], out : &mut [u8]) -> Result<(usize, usize), Error> {
+                let mut i = 0;
+                let mut o = 0;
+                let mut put = |o : &mut usize, byte : u8| -> Result<(), Error> {
+                    match out.get_mut(*o) {
+                        Some(slot) => {
+                            *slot = byte;
+                            *o += 1;
+                            Ok(())
+                        }
+                        None => Err(Error::WriteZero),
+                    }
+                };
+                #decode
+                Ok((o, i))
+            }
+        }
+    }
+}
+
+/// Render the `Cobs`, `Slip` and `Hdlc` framings, which are expected to be placed at the root
+/// of the generated crate.
+pub fn render_support() -> TokenStream {
+    let mut items = TokenStream::new();
+
+    items.extend(render_framing(
+        &Framing::Cobs.ident(),
+        "Consistent overhead byte stuffing, ending every frame with a zero byte.",
+        quote! { 0 },
+        quote! { len + len / 254 + 2 },
+        quote! {
+            // every run of non-zero bytes is preceded by its length plus one
+            let mut code_at = o;
+            o += 1;
+            let mut code = 1u8;
+            for &byte in data {
+                if byte != 0 {
+                    out[o] = byte;
+                    o += 1;
+                    code += 1;
+                }
+                if byte == 0 || code == 0xff {
+                    out[code_at] = code;
+                    code_at = o;
+                    o += 1;
+                    code = 1;
+                }
+            }
+            out[code_at] = code;
+            out[o] = Self::DELIMITER;
+            o += 1;
+        },
+        quote! {
+            while i < data.len() && data[i] != Self::DELIMITER {
+                let code = data[i] as usize;
+                i += 1;
+                for _ in 1..code {
+                    match data.get(i) {
+                        Some(&byte) if byte != Self::DELIMITER => put(&mut o, byte)?,
+                        _ => return Err(Error::InvalidFraming),
+                    }
+                    i += 1;
+                }
+                if code < 0xff && i < data.len() && data[i] != Self::DELIMITER {
+                    put(&mut o, 0)?;
+                }
+            }
+            if i < data.len() {
+                i += 1;
+            }
+        },
+    ));
+
+    items.extend(render_framing(
+        &Framing::Slip.ident(),
+        "SLIP (RFC 1055), ending every frame with `0xc0` and escaping `0xc0` and `0xdb` with `0xdb`.",
+        quote! { 0xc0 },
+        quote! { 2 * len + 1 },
+        quote! {
+            for &byte in data {
+                let escaped = match byte {
+                    Self::DELIMITER => 0xdc,
+                    0xdb => 0xdd,
+                    _ => {
+                        out[o] = byte;
+                        o += 1;
+                        continue;
+                    }
+                };
+                out[o..o + 2].copy_from_slice(&[0xdb, escaped]);
+                o += 2;
+            }
+            out[o] = Self::DELIMITER;
+            o += 1;
+        },
+        quote! {
+            // delimiters sent before the frame to flush noise are skipped
+            while data.get(i) == Some(&Self::DELIMITER) {
+                i += 1;
+            }
+            while i < data.len() {
+                let byte = data[i];
+                i += 1;
+                match byte {
+                    Self::DELIMITER => break,
+                    0xdb => {
+                        match data.get(i) {
+                            Some(0xdc) => put(&mut o, 0xc0)?,
+                            Some(0xdd) => put(&mut o, 0xdb)?,
+                            _ => return Err(Error::InvalidFraming),
+                        }
+                        i += 1;
+                    }
+                    _ => put(&mut o, byte)?,
+                }
+            }
+        },
+    ));
+
+    items.extend(render_framing(
+        &Framing::Hdlc.ident(),
+        "HDLC-style byte stuffing (RFC 1662), starting and ending every frame with a `0x7e` flag and escaping `0x7e` and `0x7d` as `0x7d` followed by the byte xor `0x20`.",
+        quote! { 0x7e },
+        quote! { 2 * len + 2 },
+        quote! {
+            out[o] = Self::DELIMITER;
+            o += 1;
+            for &byte in data {
+                if byte == Self::DELIMITER || byte == 0x7d {
+                    out[o..o + 2].copy_from_slice(&[0x7d, byte ^ 0x20]);
+                    o += 2;
+                } else {
+                    out[o] = byte;
+                    o += 1;
+                }
+            }
+            out[o] = Self::DELIMITER;
+            o += 1;
+        },
+        quote! {
+            // the flag ending a frame may also start the next one
+            while data.get(i) == Some(&Self::DELIMITER) {
+                i += 1;
+            }
+            while i < data.len() {
+                let byte = data[i];
+                i += 1;
+                match byte {
+                    Self::DELIMITER => break,
+                    0x7d => {
+                        match data.get(i) {
+                            Some(&escaped) if escaped != Self::DELIMITER => {
+                                put(&mut o, escaped ^ 0x20)?
+                            }
+                            _ => return Err(Error::InvalidFraming),
+                        }
+                        i += 1;
+                    }
+                    _ => put(&mut o, byte)?,
+                }
+            }
+        },
+    ));
+
+    items
+}
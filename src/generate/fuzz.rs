@@ -0,0 +1,165 @@
+use anyhow::{bail, Result};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+
+use crate::generate::bitfield::BitField;
+use crate::generate::structure::{Alternatives, SimpleStructure, StructMember, Structure};
+use crate::util::{self, ByteOrder, Naming, ToSanitizedPascalCase, U32Ext, BITS_PER_BYTE};
+
+/// Wrap `body` into a `cargo fuzz` target, which has the fuzzed input bound to `data`.
+fn render_target(body: TokenStream) -> TokenStream {
+    quote! {
+        #![no_main]
+
+        use libfuzzer_sys::fuzz_target;
+
+        fuzz_target!(|data: &[u8]| {
+            #body
+        });
+    }
+}
+
+/// Render a fuzz target reading `structure` from arbitrary bytes through the module `path` and
+/// formatting the result. Structures with alternatives are read as the generic structure, using
+/// one leading byte of the input to pick the selector of every alternative without a
+/// discriminator.
+pub fn render_struct(
+    path: &TokenStream,
+    structure: &Structure,
+    alternatives: Option<&Alternatives>,
+    naming: &Naming,
+) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let str_name = util::ident(
+        &structure.name.to_sanitized_pascal_case(&naming.types()),
+        span,
+    );
+
+    let selected = structure
+        .members
+        .iter()
+        .filter_map(|mem| match mem {
+            StructMember::AlternativesMember(alt) => Some(alt),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    if selected.is_empty() {
+        return Ok(render_target(quote! {
+            if let Ok((value, _)) = #path::#str_name::read_from(data) {
+                let _ = format!("{}", value);
+            }
+        }));
+    }
+
+    let mut selectors = vec![];
+    for (idx, alt) in selected
+        .iter()
+        .filter(|alt| alt.discriminator.is_none())
+        .enumerate()
+    {
+        let options = match alternatives {
+            Some(alternatives) => alternatives.get(&alt.alternatives)?,
+            None => bail!(
+                "alternatives `{}` are required to fuzz `{}`",
+                alt.alternatives,
+                structure.name
+            ),
+        };
+        let alt_pc_sel = util::ident(
+            &format!(
+                "{}Selector",
+                alt.alternatives.to_sanitized_pascal_case(&naming.types())
+            ),
+            span,
+        );
+        let count = util::unsuffixed(options.alternatives.len() as u64);
+        let idx = util::unsuffixed(idx as u64);
+        let arms = options.alternatives.iter().enumerate().map(|(i, altopt)| {
+            let altopt = util::ident(&altopt.to_sanitized_pascal_case(&naming.types()), span);
+            let i = util::unsuffixed(i as u64);
+            quote! { #i => #path::#alt_pc_sel::#altopt, }
+        });
+        selectors.push(quote! {
+            match selectors[#idx] % #count {
+                #(#arms)*
+                _ => unreachable!(),
+            }
+        });
+    }
+
+    let str_name_gen = util::ident(&format!("{}Generic", str_name), span);
+    let count = util::unsuffixed(selectors.len() as u64);
+    let check_len = if selectors.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            if data.len() < #count {
+                return;
+            }
+        }
+    };
+    Ok(render_target(quote! {
+        #check_len
+        let (selectors, mut reader) = data.split_at(#count);
+        if let Ok(value) = #path::#str_name_gen::read(&mut reader, #(#selectors),*) {
+            let _ = format!("{}", value);
+        }
+    }))
+}
+
+/// Render a fuzz target reading a simple structure from arbitrary bytes.
+pub fn render_simple(
+    path: &TokenStream,
+    structure: &SimpleStructure,
+    naming: &Naming,
+) -> Result<TokenStream> {
+    let str_name = util::ident(
+        &structure.name.to_sanitized_pascal_case(&naming.types()),
+        Span::call_site(),
+    );
+
+    Ok(render_target(quote! {
+        if let Ok((value, _)) = #path::#str_name::read_from(data) {
+            let _ = format!("{}", value);
+        }
+    }))
+}
+
+/// Render a fuzz target decoding every field of a bitfield read from arbitrary bytes in byte
+/// order `order`, like the structures holding it read it.
+pub fn render_bitfield(
+    path: &TokenStream,
+    bitfield: &BitField,
+    order: ByteOrder,
+) -> Result<TokenStream> {
+    let bytes = bitfield.bytes()?;
+    let len = util::unsuffixed(bytes as u64);
+    let bits = if bitfield.is_wide()? {
+        quote! {
+            let mut bits = [0u8; #len];
+            bits.copy_from_slice(&data[..#len]);
+        }
+    } else {
+        let structsize = bitfield.width()?;
+        let sty = structsize.to_ty()?;
+        let width = structsize / BITS_PER_BYTE;
+        let buffer_len = util::unsuffixed(width as u64);
+        let range = order.range(bytes, width);
+        let from_bytes = order.from_bytes();
+        quote! {
+            let mut buffer = [0u8; #buffer_len];
+            buffer[#range].copy_from_slice(&data[..#len]);
+            let bits = #sty::#from_bytes(buffer);
+        }
+    };
+
+    Ok(render_target(quote! {
+        if data.len() < #len {
+            return;
+        }
+        #bits
+        let r = #path::R::new(bits);
+        let _ = format!("{}", r);
+    }))
+}
@@ -1,282 +1,315 @@
-use core::marker;
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
 
-/// Raw register type
-pub trait RegisterSpec {
-    /// Raw register type (`u8`, `u16`, `u32`, ...).
-    type Ux: Copy;
-}
+/// Raw types of the generated bitfields, which implement `Bits`.
+const BITS_TYPES: &[&str] = &["u8", "u16", "u32", "u64"];
 
-/// Trait implemented by readable registers to enable the `read` method.
-///
-/// Registers marked with `Writable` can be also `modify`'ed.
-pub trait Readable: RegisterSpec {
-    /// Result from a call to `read` and argument to `modify`.
-    type Reader: From<R<Self>> + core::ops::Deref<Target = R<Self>>;
-}
+/// Types of the addresses of address members, with their number of octets.
+const EUI_TYPES: &[(&str, &str, usize)] = &[("Eui48", "EUI-48", 6), ("Eui64", "EUI-64", 8)];
 
-/// Trait implemented by writeable registers.
-///
-/// This enables the  `write`, `write_with_zero` and `reset` methods.
-///
-/// Registers marked with `Readable` can be also `modify`'ed.
-pub trait Writable: RegisterSpec {
-    /// Writer type argument to `write`, et al.
-    type Writer: From<W<Self>> + core::ops::DerefMut<Target = W<Self>>;
-}
+/// Render the runtime support shared by the generated code: the `FieldReader` wrapped by the
+/// field readers of bitfields, the `Bits` trait and the bit array functions used to access
+/// them, the states of the members of structure builders, the addresses of address members, the
+/// `HexDisplay` dumping fixed-size structures and a `Result` alias defaulting to the crate's
+/// `Error`. Like the `Error`, it is needed once at the root of the generated crate.
+pub fn render() -> TokenStream {
+    let mut items = quote! {
+        /// Result of reading or writing a structure.
+        pub type Result<T, E = crate::Error> = core::result::Result<T, E>;
 
-/// Reset value of the register.
-///
-/// This value is the initial value for the `write` method. It can also be directly written to the
-/// register by using the `reset` method.
-pub trait Resettable: RegisterSpec {
-    /// Reset value of the register.
-    fn reset_value() -> Self::Ux;
-}
+        /// Field reader.
+        ///
+        /// Result of the `read` methods of fields.
+        pub struct FieldReader<U, T> {
+            pub(crate) bits: U,
+            _reg: core::marker::PhantomData<T>,
+        }
 
-/// This structure provides volatile access to registers.
-#[repr(transparent)]
-pub struct Reg<REG: RegisterSpec> {
-    register: vcell::VolatileCell<REG::Ux>,
-    _marker: marker::PhantomData<REG>,
-}
+        impl<U, T> FieldReader<U, T>
+        where
+            U: Copy,
+        {
+            /// Creates a new instance of the reader.
+            #[allow(unused)]
+            #[inline(always)]
+            pub(crate) fn new(bits: U) -> Self {
+                Self {
+                    bits,
+                    _reg: core::marker::PhantomData,
+                }
+            }
 
-unsafe impl<REG: RegisterSpec> Send for Reg<REG> where REG::Ux: Send {}
-
-impl<REG: RegisterSpec> Reg<REG> {
-    /// Returns the underlying memory address of register.
-    ///
-    /// ```ignore
-    /// let reg_ptr = periph.reg.as_ptr();
-    /// ```
-    #[inline(always)]
-    pub fn as_ptr(&self) -> *mut REG::Ux {
-        self.register.as_ptr()
-    }
-}
+            /// Reads raw bits from field.
+            #[inline(always)]
+            pub fn bits(&self) -> U {
+                self.bits
+            }
+        }
 
-impl<REG: Readable> Reg<REG> {
-    /// Reads the contents of a `Readable` register.
-    ///
-    /// You can read the raw contents of a register by using `bits`:
-    /// ```ignore
-    /// let bits = periph.reg.read().bits();
-    /// ```
-    /// or get the content of a particular field of a register:
-    /// ```ignore
-    /// let reader = periph.reg.read();
-    /// let bits = reader.field1().bits();
-    /// let flag = reader.field2().bit_is_set();
-    /// ```
-    #[inline(always)]
-    pub fn read(&self) -> REG::Reader {
-        REG::Reader::from(R {
-            bits: self.register.get(),
-            _reg: marker::PhantomData,
-        })
-    }
-}
+        impl<U, T, FI> PartialEq<FI> for FieldReader<U, T>
+        where
+            U: PartialEq,
+            FI: Copy + Into<U>,
+        {
+            #[inline(always)]
+            fn eq(&self, other: &FI) -> bool {
+                self.bits.eq(&(*other).into())
+            }
+        }
 
-impl<REG: Resettable + Writable> Reg<REG> {
-    /// Writes the reset value to `Writable` register.
-    ///
-    /// Resets the register to its initial state.
-    #[inline(always)]
-    pub fn reset(&self) {
-        self.register.set(REG::reset_value())
-    }
+        impl<FI> FieldReader<bool, FI> {
+            /// Value of the field as raw bits.
+            #[inline(always)]
+            pub fn bit(&self) -> bool {
+                self.bits
+            }
+            /// Returns `true` if the bit is clear (0).
+            #[inline(always)]
+            pub fn bit_is_clear(&self) -> bool {
+                !self.bit()
+            }
+            /// Returns `true` if the bit is set (1).
+            #[inline(always)]
+            pub fn bit_is_set(&self) -> bool {
+                self.bit()
+            }
+        }
 
-    /// Writes bits to a `Writable` register.
-    ///
-    /// You can write raw bits into a register:
-    /// ```ignore
-    /// periph.reg.write(|w| unsafe { w.bits(rawbits) });
-    /// ```
-    /// or write only the fields you need:
-    /// ```ignore
-    /// periph.reg.write(|w| w
-    ///     .field1().bits(newfield1bits)
-    ///     .field2().set_bit()
-    ///     .field3().variant(VARIANT)
-    /// );
-    /// ```
-    /// In the latter case, other fields will be set to their reset value.
-    #[inline(always)]
-    pub fn write<F>(&self, f: F)
-    where
-        F: FnOnce(&mut REG::Writer) -> &mut W<REG>
-    {
-        self.register.set(
-            f(&mut REG::Writer::from(W {
-                bits: REG::reset_value(),
-                _reg: marker::PhantomData,
-            }))
-            .bits,
-        );
-    }
-}
+        /// State of a required member the builder of a structure has not set yet.
+        pub struct Unset;
 
-impl<REG: Writable> Reg<REG>
-where
-    REG::Ux: Default,
-{
-    /// Writes 0 to a `Writable` register.
-    ///
-    /// Similar to `write`, but unused bits will contain 0.
-    #[inline(always)]
-    pub unsafe fn write_with_zero<F>(&self, f: F)
-    where
-        F: FnOnce(&mut REG::Writer) -> &mut W<REG>
-    {
-        self.register.set(
-            (*f(&mut REG::Writer::from(W {
-                bits: REG::Ux::default(),
-                _reg: marker::PhantomData,
-            })))
-            .bits,
-        );
+        /// State of a required member the builder of a structure has set.
+        pub struct Set;
+
+        /// Raw bits of a bitfield.
+        pub trait Bits: Copy {
+            /// Extract the field selected by `mask` at `offset`.
+            fn extract(self, mask: Self, offset: u32) -> Self;
+            /// Replace the field selected by `mask` at `offset` with `value`, which is masked.
+            fn insert(self, value: Self, mask: Self, offset: u32) -> Self;
+        }
+    };
+
+    items.extend(quote! {
+        /// Extract the `width` bits at `offset` of the little endian `bytes` of a bitfield of
+        /// more than 64 bits.
+        #[inline]
+        pub fn extract_bits(bytes: &[u8], offset: u32, width: u32) -> u64 {
+            let mut value = 0;
+            for i in 0..width {
+                let pos = offset + i;
+                if bytes[(pos / 8) as usize] >> (pos % 8) & 1 != 0 {
+                    value |= 1 << i;
+                }
+            }
+            value
+        }
+
+        /// Replace the `width` bits at `offset` of the little endian `bytes` of a bitfield of
+        /// more than 64 bits with `value`.
+        #[inline]
+        pub fn insert_bits(bytes: &mut [u8], offset: u32, width: u32, value: u64) {
+            for i in 0..width {
+                let pos = offset + i;
+                let byte = &mut bytes[(pos / 8) as usize];
+                if value >> i & 1 != 0 {
+                    *byte |= 1 << (pos % 8);
+                } else {
+                    *byte &= !(1 << (pos % 8));
+                }
+            }
+        }
+
+        /// Number of bytes of the chain at the start of `bytes`, which ends with the first byte
+        /// whose most significant bit is clear, or all of `bytes` if there is none.
+        #[inline]
+        pub fn chain_len(bytes: &[u8]) -> usize {
+            match bytes.iter().position(|byte| byte & 0x80 == 0) {
+                Some(i) => i + 1,
+                None => bytes.len(),
+            }
+        }
+
+        /// Number of bytes of `value` encoded as an unsigned LEB128 varint.
+        #[inline]
+        pub fn varint_len(value: u64) -> usize {
+            (64 - (value | 1).leading_zeros() as usize).div_ceil(7)
+        }
+
+        /// Decode the packed BCD digits of `raw`, or `None` if a nibble is above 9.
+        #[inline]
+        pub fn bcd_decode(mut raw: u64) -> Option<u64> {
+            let mut value = 0;
+            let mut scale = 1;
+            while raw != 0 {
+                let digit = raw & 0xf;
+                if digit > 9 {
+                    return None;
+                }
+                value += digit * scale;
+                scale *= 10;
+                raw >>= 4;
+            }
+            Some(value)
+        }
+
+        /// Encode `value` as packed BCD digits, dropping any beyond the sixteenth.
+        #[inline]
+        pub fn bcd_encode(mut value: u64) -> u64 {
+            let mut raw = 0;
+            let mut shift = 0;
+            while value != 0 && shift < 64 {
+                raw |= (value % 10) << shift;
+                value /= 10;
+                shift += 4;
+            }
+            raw
+        }
+    });
+
+    for ty in BITS_TYPES {
+        let ty = Ident::new(ty, Span::call_site());
+        items.extend(quote! {
+            impl Bits for #ty {
+                #[inline(always)]
+                fn extract(self, mask: Self, offset: u32) -> Self {
+                    (self >> offset) & mask
+                }
+
+                #[inline(always)]
+                fn insert(self, value: Self, mask: Self, offset: u32) -> Self {
+                    (self & !(mask << offset)) | ((value & mask) << offset)
+                }
+            }
+        });
     }
-}
 
-impl<REG: Readable + Writable> Reg<REG> {
-    /// Modifies the contents of the register by reading and then writing it.
-    ///
-    /// E.g. to do a read-modify-write sequence to change parts of a register:
-    /// ```ignore
-    /// periph.reg.modify(|r, w| unsafe { w.bits(
-    ///    r.bits() | 3
-    /// ) });
-    /// ```
-    /// or
-    /// ```ignore
-    /// periph.reg.modify(|_, w| w
-    ///     .field1().bits(newfield1bits)
-    ///     .field2().set_bit()
-    ///     .field3().variant(VARIANT)
-    /// );
-    /// ```
-    /// Other fields will have the value they had before the call to `modify`.
-    #[inline(always)]
-    pub fn modify<F>(&self, f: F)
-    where
-        for<'w> F: FnOnce(&REG::Reader, &'w mut REG::Writer) -> &'w mut W<REG>
-    {
-        let bits = self.register.get();
-        self.register.set(
-            f(
-                &REG::Reader::from(R {
-                    bits,
-                    _reg: marker::PhantomData,
-                }),
-                &mut REG::Writer::from(W {
-                    bits,
-                    _reg: marker::PhantomData,
-                }),
-            )
-            .bits,
+    for (ty, name, bytes) in EUI_TYPES {
+        let doc = format!(
+            "An {} address such as a MAC address, as the octets of its canonical form.",
+            name
         );
-    }
-}
+        let ty = Ident::new(ty, Span::call_site());
+        items.extend(quote! {
+            #[doc = #doc]
+            #[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+            pub struct #ty(pub [u8; #bytes]);
 
-/// Register reader.
-///
-/// Result of the `read` methods of registers. Also used as a closure argument in the `modify`
-/// method.
-pub struct R<REG: RegisterSpec + ?Sized> {
-    pub(crate) bits: REG::Ux,
-    _reg: marker::PhantomData<REG>,
-}
+            impl #ty {
+                /// The broadcast address, whose bits are all set.
+                pub const BROADCAST: Self = Self([0xff; #bytes]);
 
-impl<REG: RegisterSpec> R<REG> {
-    /// Reads raw bits from register.
-    #[inline(always)]
-    pub fn bits(&self) -> REG::Ux {
-        self.bits
-    }
-}
+                #[inline(always)]
+                pub fn octets(&self) -> [u8; #bytes] {
+                    self.0
+                }
 
-impl<REG: RegisterSpec, FI> PartialEq<FI> for R<REG>
-where
-    REG::Ux: PartialEq,
-    FI: Copy + Into<REG::Ux>,
-{
-    #[inline(always)]
-    fn eq(&self, other: &FI) -> bool {
-        self.bits.eq(&(*other).into())
-    }
-}
+                /// Whether this is the broadcast address.
+                #[inline(always)]
+                pub fn is_broadcast(&self) -> bool {
+                    *self == Self::BROADCAST
+                }
 
-/// Register writer.
-///
-/// Used as an argument to the closures in the `write` and `modify` methods of the register.
-pub struct W<REG: RegisterSpec + ?Sized> {
-    ///Writable bits
-    pub(crate) bits: REG::Ux,
-    _reg: marker::PhantomData<REG>,
-}
+                /// Whether this is a group address, including the broadcast address, which has
+                /// the I/G bit of its first octet set.
+                #[inline(always)]
+                pub fn is_multicast(&self) -> bool {
+                    self.0[0] & 0x01 != 0
+                }
+
+                /// Whether this is the address of a single interface.
+                #[inline(always)]
+                pub fn is_unicast(&self) -> bool {
+                    !self.is_multicast()
+                }
+
+                /// Whether this address is locally administered, which has the U/L bit of its
+                /// first octet set.
+                #[inline(always)]
+                pub fn is_local(&self) -> bool {
+                    self.0[0] & 0x02 != 0
+                }
+            }
 
-impl<REG: RegisterSpec> W<REG> {
-    /// Writes raw bits to the register.
-    #[inline(always)]
-    pub unsafe fn bits(&mut self, bits: REG::Ux) -> &mut Self {
-        self.bits = bits;
-        self
+            impl From<[u8; #bytes]> for #ty {
+                #[inline(always)]
+                fn from(octets: [u8; #bytes]) -> Self {
+                    Self(octets)
+                }
+            }
+
+            impl From<#ty> for [u8; #bytes] {
+                #[inline(always)]
+                fn from(address: #ty) -> Self {
+                    address.0
+                }
+            }
+
+            /// Formats the address as its octets in lower case hexadecimal separated by colons,
+            /// e.g. `02:00:5e:10:00:01`.
+            impl core::fmt::Display for #ty {
+                fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                    for (i, octet) in self.0.iter().enumerate() {
+                        if i > 0 {
+                            f.write_str(":")?;
+                        }
+                        write!(f, "{:02x}", octet)?;
+                    }
+                    Ok(())
+                }
+            }
+
+            impl core::fmt::Debug for #ty {
+                fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                    core::fmt::Display::fmt(self, f)
+                }
+            }
+        });
     }
-}
 
-/// Field reader.
-///
-/// Result of the `read` methods of fields.
-pub struct FieldReader<U, T> {
-    pub(crate) bits: U,
-    _reg: marker::PhantomData<T>,
-}
+    items.extend(quote! {
+        /// Hex dump of the bytes of a structure with one line per member, or run of bit members,
+        /// annotated with its name. Lines of long members hold 16 bytes each.
+        #[derive(Clone, Copy)]
+        pub struct HexDisplay<B> {
+            bytes: B,
+            fields: &'static [(&'static str, usize)],
+        }
 
-impl<U, T> FieldReader<U, T>
-where
-    U: Copy,
-{
-    /// Creates a new instance of the reader.
-    #[allow(unused)]
-    #[inline(always)]
-    pub(crate) fn new(bits: U) -> Self {
-        Self {
-            bits,
-            _reg: marker::PhantomData,
+        impl<B: AsRef<[u8]>> HexDisplay<B> {
+            /// Dump `bytes`, where the members named in `fields` start at their offsets.
+            pub fn new(bytes: B, fields: &'static [(&'static str, usize)]) -> Self {
+                Self { bytes, fields }
+            }
         }
-    }
 
-    /// Reads raw bits from field.
-    #[inline(always)]
-    pub fn bits(&self) -> U {
-        self.bits
-    }
-}
+        impl<B: AsRef<[u8]>> core::fmt::Display for HexDisplay<B> {
+            fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                let bytes = self.bytes.as_ref();
+                for (i, (name, start)) in self.fields.iter().enumerate() {
+                    let end = self.fields.get(i + 1).map_or(bytes.len(), |(_, end)| *end);
+                    let member = bytes.get(*start..end).unwrap_or(&[]);
+                    for (line, chunk) in member.chunks(16).enumerate() {
+                        write!(f, "{:04x}:", start + line * 16)?;
+                        for byte in chunk {
+                            write!(f, " {:02x}", byte)?;
+                        }
+                        if line == 0 {
+                            write!(f, "{:width$}  {}", "", name, width = (16 - chunk.len()) * 3)?;
+                        }
+                        writeln!(f)?;
+                    }
+                }
+                Ok(())
+            }
+        }
 
-impl<U, T, FI> PartialEq<FI> for FieldReader<U, T>
-where
-    U: PartialEq,
-    FI: Copy + Into<U>,
-{
-    #[inline(always)]
-    fn eq(&self, other: &FI) -> bool {
-        self.bits.eq(&(*other).into())
-    }
-}
+        impl<B: AsRef<[u8]>> core::fmt::Debug for HexDisplay<B> {
+            fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                core::fmt::Display::fmt(self, f)
+            }
+        }
+    });
 
-impl<FI> FieldReader<bool, FI> {
-    /// Value of the field as raw bits.
-    #[inline(always)]
-    pub fn bit(&self) -> bool {
-        self.bits
-    }
-    /// Returns `true` if the bit is clear (0).
-    #[inline(always)]
-    pub fn bit_is_clear(&self) -> bool {
-        !self.bit()
-    }
-    /// Returns `true` if the bit is set (1).
-    #[inline(always)]
-    pub fn bit_is_set(&self) -> bool {
-        self.bit()
-    }
+    items
 }
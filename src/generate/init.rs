@@ -0,0 +1,233 @@
+use anyhow::Result;
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+
+use crate::generate::structure::{StructMember, Structure};
+use crate::util::{
+    self, Naming, ToSanitizedPascalCase, ToSanitizedSnakeCase, U32Ext, BITS_PER_BYTE,
+};
+
+/// Render the `Init` structure of `structure`, holding a public field for every member which can
+/// be set on its own, so a structure can be built in one expression:
+/// `Foo::init(FooInit { a: 1, ..Default::default() })`. Members counting others, and members
+/// of variable size other than optional structures, keep their value from `new()`.
+pub fn render(structure: &Structure, const_generic: bool, naming: &Naming) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let members = naming.names_of(&structure.name);
+
+    let str_name = util::ident(
+        &structure.name.to_sanitized_pascal_case(&naming.types()),
+        span,
+    );
+    let init_name = util::ident(&format!("{}Init", str_name), span);
+
+    let mut templ = TokenStream::new();
+    let mut templ_decl = TokenStream::new();
+    let mut where_clause = TokenStream::new();
+    for mem in &structure.members {
+        if let StructMember::AlternativesMember(alt) = mem {
+            let param = alt.param(const_generic, &members, naming);
+            templ_decl.extend(param.decl);
+            templ.extend(param.arg);
+            where_clause.extend(param.bound);
+        }
+    }
+
+    let mut fields = TokenStream::new();
+    let mut assigns = TokenStream::new();
+    let mut defaults = TokenStream::new();
+    for mem in &structure.members {
+        let (ty, assign, default) = match render_field(structure, mem, const_generic, naming)? {
+            Some(field) => field,
+            None => continue,
+        };
+        let mem_name = util::ident(&mem.name().to_sanitized_snake_case(&members), span);
+        let doc = match mem.desc() {
+            Some(desc) => desc.to_string(),
+            None => format!("Value of `{}`.", mem.name()),
+        };
+
+        fields.extend(quote! {
+            #[doc = #doc]
+            pub #mem_name : #ty,
+        });
+        assigns.extend(quote! {
+            let value = init.#mem_name;
+            #assign
+        });
+        defaults.extend(quote! { #mem_name : { let value = data.#mem_name; #default }, });
+    }
+
+    if fields.is_empty() {
+        return Ok(TokenStream::new());
+    }
+
+    let doc = format!(
+        "Members of [`{}`] set by [`{}::init`], defaulting to their values in `new()`.",
+        str_name, str_name
+    );
+
+    Ok(quote! {
+        #[doc = #doc]
+        pub struct #init_name<#templ_decl> where #where_clause {
+            #fields
+        }
+
+        impl<#templ_decl> Default for #init_name<#templ> where #where_clause {
+            fn default() -> Self {
+                let data = #str_name::<#templ>::new();
+                Self { #defaults }
+            }
+        }
+
+        impl<#templ_decl> #str_name<#templ> where #where_clause {
+            #[doc = "Build the structure from the values of its members, the others keeping their values from `new()`."]
+            pub fn init(init : #init_name<#templ>) -> Self {
+                let mut data = Self::new();
+                #assigns
+                data
+            }
+        }
+
+        impl<#templ_decl> From<#init_name<#templ>> for #str_name<#templ> where #where_clause {
+            #[inline(always)]
+            fn from(init : #init_name<#templ>) -> Self {
+                Self::init(init)
+            }
+        }
+    })
+}
+
+/// Render the type of the field of `mem` in the `Init` structure, the assignment of its `value`
+/// to `data` and the conversion of its `value` in `data` back to the field, if it has a field.
+fn render_field(
+    structure: &Structure,
+    mem: &StructMember,
+    const_generic: bool,
+    naming: &Naming,
+) -> Result<Option<(TokenStream, TokenStream, TokenStream)>> {
+    let span = Span::call_site();
+    let members = naming.names_of(&structure.name);
+    let mem_name = util::ident(&mem.name().to_sanitized_snake_case(&members), span);
+    if !mem.access().can_write() || structure.is_len_source(mem.name()) {
+        return Ok(None);
+    }
+
+    Ok(Some(match mem {
+        StructMember::PrimitiveMember(mem) => {
+            let sty = (mem.bytes * BITS_PER_BYTE).to_ty()?;
+            (
+                quote! { #sty },
+                quote! { data.#mem_name = value; },
+                quote! { value },
+            )
+        }
+        StructMember::ArrayMember(mem) => {
+            let sty = (mem.bytes * BITS_PER_BYTE).to_ty()?;
+            let count = util::unsuffixed(mem.count as u64);
+            (
+                quote! { [#sty; #count] },
+                quote! { data.#mem_name = value; },
+                quote! { value },
+            )
+        }
+        StructMember::EuiMember(mem) => {
+            let ty = mem.ty();
+            (
+                quote! { crate::#ty },
+                quote! { data.#mem_name = value.0; },
+                quote! { crate::#ty(value) },
+            )
+        }
+        StructMember::TimestampMember(mem) => {
+            let check = mem.render_check(quote! { value });
+            (
+                quote! { u64 },
+                quote! {
+                    #check
+                    data.#mem_name = value;
+                },
+                quote! { value },
+            )
+        }
+        StructMember::BcdMember(mem) => {
+            let sty = mem.ty()?;
+            let max = util::unsuffixed(mem.max());
+            (
+                quote! { #sty },
+                quote! {
+                    assert!(value <= #max);
+                    data.#mem_name = value;
+                },
+                quote! { value },
+            )
+        }
+        StructMember::OffsetMember(mem) => {
+            let sty = (mem.bytes * BITS_PER_BYTE).to_ty()?;
+            let max = util::hex(mem.max());
+            (
+                quote! { #sty },
+                quote! {
+                    assert!(value <= #max);
+                    data.#mem_name = value;
+                },
+                quote! { value },
+            )
+        }
+        StructMember::BitsMember(mem) => {
+            let sty = mem.ty()?;
+            let max = util::hex(mem.max());
+            (
+                quote! { #sty },
+                quote! {
+                    assert!(value <= #max);
+                    data.#mem_name = value;
+                },
+                quote! { value },
+            )
+        }
+        StructMember::VarintMember(mem) => {
+            let sty = mem.ty()?;
+            let max = util::hex(mem.max());
+            (
+                quote! { #sty },
+                quote! {
+                    assert!(value <= #max);
+                    data.#mem_name = value;
+                },
+                quote! { value },
+            )
+        }
+        StructMember::BitfieldMember(mem) => {
+            let pkg_name = structure.bitfield_path(&mem.bitfield, false, naming);
+            (
+                quote! { #pkg_name::W },
+                quote! { data.#mem_name = *value; },
+                quote! { #pkg_name::W::new(value) },
+            )
+        }
+        StructMember::AlternativesMember(alt) => {
+            let check = alt.render_check(const_generic, quote! { value }, &members);
+            (
+                alt.param(const_generic, &members, naming).ty,
+                quote! {
+                    #check
+                    data.#mem_name = value;
+                },
+                quote! { value },
+            )
+        }
+        StructMember::OptionalMember(mem) => {
+            let sty = util::ident(
+                &mem.structure.to_sanitized_pascal_case(&naming.types()),
+                span,
+            );
+            (
+                quote! { Option<#sty> },
+                quote! { data.#mem_name = value; },
+                quote! { value },
+            )
+        }
+        _ => return Ok(None),
+    }))
+}
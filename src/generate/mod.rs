@@ -1,2 +1,18 @@
+pub mod base;
 pub mod bitfield;
+pub mod builder;
+pub mod checksum;
+pub mod frame;
+pub mod frame_trait;
+pub mod framing;
+pub mod fuzz;
+pub mod generic;
+pub mod init;
+pub mod parser;
+pub mod proptests;
+pub mod roundtrip;
 pub mod structure;
+pub mod tlv;
+pub mod trailing;
+pub mod vectored;
+pub mod view;
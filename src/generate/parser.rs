@@ -0,0 +1,134 @@
+use anyhow::Result;
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+
+use crate::generate::frame::Frames;
+use crate::generate::structure::{StructMember, Structure};
+use crate::util::{self, Naming, ToSanitizedPascalCase};
+
+/// Render the push parser of `structure`, which yields its generic structure if it holds
+/// alternatives.
+pub fn render_struct(structure: &Structure, naming: &Naming) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let str_name = structure.name.to_sanitized_pascal_case(&naming.types());
+    let generic = structure
+        .members
+        .iter()
+        .any(|mem| matches!(mem, StructMember::AlternativesMember(_)));
+    let ty = if generic {
+        util::ident(&format!("{}Generic", str_name), span)
+    } else {
+        util::ident(&str_name, span)
+    };
+    let parser_name = util::ident(&format!("{}Parser", str_name), span);
+
+    Ok(render(&parser_name, &ty))
+}
+
+/// Render the push parser of the enum of `frames` along with the `MAX_SIZE` of the enum, which
+/// is the largest one of its frames.
+pub fn render_frames(frames: &Frames, naming: &Naming) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let name_pc = util::ident(&frames.name.to_sanitized_pascal_case(&naming.types()), span);
+    let parser_name = util::ident(&format!("{}Parser", name_pc), span);
+
+    let sizes = frames.frames.iter().map(|(_, frame, generic)| {
+        let frame = frame.to_sanitized_pascal_case(&naming.types());
+        let ty = if *generic {
+            util::ident(&format!("{}Generic", frame), span)
+        } else {
+            util::ident(&frame, span)
+        };
+        quote! {
+            if #ty::MAX_SIZE > max {
+                max = #ty::MAX_SIZE;
+            }
+        }
+    });
+
+    let mut out = quote! {
+        impl #name_pc {
+            #[doc = "Largest size of a frame on the wire."]
+            pub const MAX_SIZE : usize = {
+                let mut max = 0;
+                #(#sizes)*
+                max
+            };
+        }
+    };
+    out.extend(render(&parser_name, &name_pc));
+    Ok(out)
+}
+
+/// Render the parser `parser_name` of the type `ty`, which collects the bytes of `ty` as they
+/// arrive, e.g. from the interrupt handler of a UART, and yields `ty` once complete. The bytes
+/// are collected in a buffer of the `MAX_SIZE` of `ty`, so no more than one frame is ever
+/// buffered.
+fn render(parser_name: &Ident, ty: &Ident) -> TokenStream {
+    let doc = format!(
+        "Push parser of [`{}`], decoding it from fragments as they arrive.",
+        ty
+    );
+
+    quote! {
+        #[doc = #doc]
+        #[doc = ""]
+        #[doc = "The bytes of the frame received so far are buffered until it is complete, and dropped when it cannot be read."]
+        #[derive(Clone, Debug)]
+        pub struct #parser_name {
+            buf : [u8; #ty::MAX_SIZE],
+            len : usize,
+        }
+
+        impl Default for #parser_name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl #parser_name {
+            pub const fn new() -> Self {
+                Self { buf : [0u8; #ty::MAX_SIZE], len : 0 }
+            }
+
+            #[doc = "Number of bytes received of the incomplete frame."]
+            pub fn buffered(&self) -> usize {
+                self.len
+            }
+
+            #[doc = "Drop the bytes of the incomplete frame, e.g. after a pause on the line."]
+            pub fn reset(&mut self) {
+                self.len = 0;
+            }
+
+            #[doc = "Feed the fragment `bytes`, advancing it past the bytes consumed. Returns the frame once it is complete, after which the rest of `bytes` is left to feed again, or the error reading it."]
+            pub fn feed(&mut self, bytes : &mut &[u8]) -> Option<Result<#ty, Error>> {
+                let start = self.len;
+                let n = core::cmp::min(bytes.len(), self.buf.len() - start);
+                self.buf[start..start + n].copy_from_slice(&bytes[..n]);
+                self.len += n;
+                match #ty::read_from(&self.buf[..self.len]) {
+                    Ok((frame, len)) => {
+                        self.len = 0;
+                        *bytes = &bytes[len.saturating_sub(start)..];
+                        Some(Ok(frame))
+                    }
+                    Err(Error::UnexpectedEof) if self.len < self.buf.len() => {
+                        *bytes = &bytes[n..];
+                        None
+                    }
+                    Err(e) => {
+                        self.len = 0;
+                        *bytes = &bytes[n..];
+                        Some(Err(e))
+                    }
+                }
+            }
+
+            #[doc = "Feed the single byte `byte`, see `feed`."]
+            pub fn push(&mut self, byte : u8) -> Option<Result<#ty, Error>> {
+                self.feed(&mut &[byte][..])
+            }
+        }
+    }
+}
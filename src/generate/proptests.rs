@@ -0,0 +1,552 @@
+use anyhow::{bail, Result};
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+
+use crate::generate::bitfield::{BitField, MaybeField};
+use crate::generate::roundtrip;
+use crate::generate::structure::{
+    NibbleVarintMember, Payloads, SimpleStructure, StringKind, StructMember, Structure,
+};
+use crate::util::{
+    self, Access, Naming, Scope, ToSanitizedPascalCase, ToSanitizedSnakeCase, U32Ext, BITS_PER_BYTE,
+};
+
+/// Render the name of the strategy function for the type `name`.
+fn strategy_ident(name: &str, scope: &Scope) -> Ident {
+    util::ident(
+        &format!("arb_{}", name.to_sanitized_snake_case(scope)),
+        Span::call_site(),
+    )
+}
+
+/// Render a strategy generating integers of `bytes` bytes. Values wider than the member are
+/// truncated on the wire, so are left out.
+fn int_strategy(bytes: u32) -> Result<TokenStream> {
+    let bits = bytes * BITS_PER_BYTE;
+    let sty = bits.to_ty()?;
+    Ok(if bits == bits.to_ty_width()? {
+        quote! { any::<#sty>() }
+    } else {
+        let max = util::hex((1u64 << bits) - 1);
+        quote! { 0..=#max as #sty }
+    })
+}
+
+/// Combine `strategies` into a single strategy mapped by `body`, which has every value bound to
+/// the corresponding name in `names`. The strategies are nested in pairs as tuple strategies
+/// only exist up to a limited arity.
+fn render_map(strategies: &[TokenStream], names: &[Ident], body: TokenStream) -> TokenStream {
+    let mut strategy = quote! { Just(()) };
+    let mut pattern = quote! { () };
+    for (s, name) in strategies.iter().zip(names).rev() {
+        strategy = quote! { (#s, #strategy) };
+        pattern = quote! { (#name, #pattern) };
+    }
+
+    quote! {
+        #strategy.prop_map(|#pattern| { #body })
+    }
+}
+
+/// Render a strategy generating arbitrary values of `structure`, for structures with
+/// alternatives the generic structure holding the default alternatives is generated. Length
+/// fields and counts are kept consistent with the members they count, and checksums, constants
+/// and presence flags are left to the writer. Absent structures which an arbitrary field happens
+/// to flag as present are made present, and structures whose condition does not hold absent.
+pub fn render_strategy(structure: &Structure, naming: &Naming) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let members = naming.names_of(&structure.name);
+    let fn_name = strategy_ident(&structure.name, &naming.types());
+    let str_name = util::ident(
+        &structure.name.to_sanitized_pascal_case(&naming.types()),
+        span,
+    );
+    let has_alt = structure
+        .members
+        .iter()
+        .any(|mem| matches!(mem, StructMember::AlternativesMember(_)));
+    let (ty, init) = if has_alt {
+        let str_name_gen = util::ident(&format!("{}Generic", str_name), span);
+        (
+            quote! { #str_name_gen },
+            quote! { #str_name_gen::default() },
+        )
+    } else {
+        (quote! { #str_name }, quote! { #str_name::new() })
+    };
+
+    let mut strategies = Vec::new();
+    let mut names = Vec::new();
+    let mut sets = TokenStream::new();
+    let mut conditions = TokenStream::new();
+
+    for (idx, mem) in structure.members.iter().enumerate() {
+        let mem_name = util::ident(&mem.name().to_sanitized_snake_case(&members), span);
+        // bound with a prefix so that no member shadows the generated `value`
+        let arb_name = strategy_ident(mem.name(), &members);
+
+        let strategy = match mem {
+            StructMember::PrimitiveMember(mem) => {
+                let sty = (mem.bytes * BITS_PER_BYTE).to_ty()?;
+                sets.extend(quote! { value.#mem_name = #arb_name; });
+                match structure.checksum_extra(&mem.name) {
+                    // a key of a checksum must select one of its extra bytes
+                    Some(extra) if !extra.values.is_empty() => {
+                        let values = extra.values.iter().map(|(value, _)| util::hex(*value));
+                        quote! { proptest::sample::select(vec![#(#values as #sty),*]) }
+                    }
+                    _ => int_strategy(mem.bytes)?,
+                }
+            }
+            StructMember::BitfieldMember(mem) if mem.bytes > 8 => {
+                let bytes = util::unsuffixed(mem.bytes as u64);
+                sets.extend(quote! { value.#mem_name = #arb_name; });
+                quote! {
+                    proptest::collection::vec(any::<u8>(), #bytes).prop_map(|v| {
+                        let mut array = [0u8; #bytes];
+                        array.copy_from_slice(&v);
+                        array
+                    })
+                }
+            }
+            StructMember::BitfieldMember(mem) => {
+                let sty = (mem.bytes * BITS_PER_BYTE).to_ty()?;
+                sets.extend(quote! { value.#mem_name = #arb_name; });
+                quote! { any::<#sty>() }
+            }
+            StructMember::OffsetMember(mem) => {
+                let sty = (mem.bytes * BITS_PER_BYTE).to_ty()?;
+                let max = util::hex(mem.max());
+                sets.extend(quote! { value.#mem_name = #arb_name; });
+                quote! { 0..=#max as #sty }
+            }
+            StructMember::BitsMember(mem) => {
+                let sty = mem.ty()?;
+                let max = util::hex(mem.max());
+                sets.extend(quote! { value.#mem_name = #arb_name; });
+                quote! { 0..=#max as #sty }
+            }
+            StructMember::EuiMember(mem) => {
+                let bytes = util::unsuffixed(mem.bytes as u64);
+                sets.extend(quote! { value.#mem_name = #arb_name; });
+                quote! {
+                    proptest::collection::vec(any::<u8>(), #bytes).prop_map(|v| {
+                        let mut octets = [0u8; #bytes];
+                        octets.copy_from_slice(&v);
+                        octets
+                    })
+                }
+            }
+            StructMember::TimestampMember(mem) => {
+                let max = util::hex(mem.max());
+                sets.extend(quote! { value.#mem_name = #arb_name; });
+                quote! { 0..=#max as u64 }
+            }
+            StructMember::BcdMember(mem) => {
+                let sty = mem.ty()?;
+                let max = util::unsuffixed(mem.max());
+                sets.extend(quote! { value.#mem_name = #arb_name; });
+                quote! { 0..=#max as #sty }
+            }
+            StructMember::ArrayMember(mem) => {
+                let sty = (mem.bytes * BITS_PER_BYTE).to_ty()?;
+                let element = int_strategy(mem.bytes)?;
+                let count = util::unsuffixed(mem.count as u64);
+                sets.extend(quote! { value.#mem_name = #arb_name; });
+                quote! {
+                    proptest::collection::vec(#element, #count).prop_map(|v| {
+                        let mut array = [0 as #sty; #count];
+                        array.copy_from_slice(&v);
+                        array
+                    })
+                }
+            }
+            StructMember::ExtendedMember(mem) => {
+                let capacity = util::unsuffixed(mem.capacity as u64);
+                sets.extend(quote! { value.#mem_name = #arb_name; });
+                quote! {
+                    proptest::collection::vec(any::<u8>(), 1..=#capacity).prop_map(|v| {
+                        let mut chain = [0u8; #capacity];
+                        for (i, byte) in v.iter().enumerate() {
+                            chain[i] = if i + 1 < v.len() { byte | 0x80 } else { byte & 0x7f };
+                        }
+                        chain
+                    })
+                }
+            }
+            StructMember::BytesMember(mem) => {
+                let len = match structure.find_len_source(&mem.len_from, idx) {
+                    Some(len) => len,
+                    None => bail!(
+                        "bytes field `{}` refers to unknown length field `{}`",
+                        mem.name,
+                        mem.len_from
+                    ),
+                };
+                let len_name = util::ident(&len.name().to_sanitized_snake_case(&members), span);
+                let lty = len.ty()?;
+                let capacity = util::unsuffixed(mem.capacity as u64);
+                let max = util::unsuffixed(mem.capacity.min(len.max()) as u64);
+                sets.extend(quote! {
+                    value.#mem_name = #arb_name.0;
+                    value.#len_name = #arb_name.1;
+                });
+                let data = match structure.payloads {
+                    Payloads::Array => quote! {
+                        let mut data = [0u8; #capacity];
+                        data[..v.len()].copy_from_slice(&v);
+                    },
+                    Payloads::Heapless => quote! {
+                        let data = heapless::Vec::<u8, #capacity>::from_slice(&v).unwrap();
+                    },
+                    Payloads::Alloc => quote! {
+                        let data = v.clone();
+                    },
+                };
+                quote! {
+                    proptest::collection::vec(any::<u8>(), 0..=#max).prop_map(|v| {
+                        #data
+                        (data, v.len() as #lty)
+                    })
+                }
+            }
+            // ASCII strings without NUL characters
+            StructMember::StringMember(mem) => {
+                let max = match &mem.kind {
+                    StringKind::Prefixed(len_from) => {
+                        let len = match structure.find_len_source(len_from, idx) {
+                            Some(len) => len,
+                            None => bail!(
+                                "string field `{}` refers to unknown length field `{}`",
+                                mem.name,
+                                len_from
+                            ),
+                        };
+                        let len_name =
+                            util::ident(&len.name().to_sanitized_snake_case(&members), span);
+                        let lty = len.ty()?;
+                        sets.extend(quote! {
+                            value.#len_name = #arb_name.len() as #lty;
+                        });
+                        mem.capacity.min(len.max())
+                    }
+                    _ => mem.capacity,
+                };
+                let max = util::unsuffixed(max as u64);
+                sets.extend(quote! { value.#mem_name = #arb_name; });
+                let string = match structure.payloads {
+                    Payloads::Alloc => quote! { alloc::string::String::from_utf8(v).unwrap() },
+                    _ => quote! {
+                        let mut string = heapless::String::new();
+                        string.push_str(core::str::from_utf8(&v).unwrap()).unwrap();
+                        string
+                    },
+                };
+                quote! {
+                    proptest::collection::vec(1u8..0x80, 0..=#max).prop_map(|v| {
+                        #string
+                    })
+                }
+            }
+            StructMember::RepeatedMember(mem) if mem.count_field.is_some() => {
+                let max = util::unsuffixed(mem.capacity as u64);
+                let set_count = roundtrip::render_count(
+                    structure,
+                    mem,
+                    idx,
+                    quote! { #arb_name.len() },
+                    naming,
+                )?;
+                sets.extend(quote! {
+                    for v in #arb_name.iter() {
+                        value.#mem_name.push(*v).unwrap();
+                    }
+                    #set_count
+                });
+                let element = int_strategy(mem.bytes)?;
+                quote! { proptest::collection::vec(#element, 0..=#max) }
+            }
+            StructMember::RepeatedMember(mem) => {
+                let len = match structure.find_len_source(&mem.count_from, idx) {
+                    Some(len) => len,
+                    None => bail!(
+                        "repeated field `{}` refers to unknown length field `{}`",
+                        mem.name,
+                        mem.count_from
+                    ),
+                };
+                let len_name = util::ident(&len.name().to_sanitized_snake_case(&members), span);
+                let lty = len.ty()?;
+                let max = util::unsuffixed(mem.capacity.min(len.max()) as u64);
+                sets.extend(quote! {
+                    for v in #arb_name.iter() {
+                        value.#mem_name.push(*v).unwrap();
+                    }
+                    value.#len_name = #arb_name.len() as #lty;
+                });
+                let element = int_strategy(mem.bytes)?;
+                quote! { proptest::collection::vec(#element, 0..=#max) }
+            }
+            StructMember::NibbleVarintMember(mem) if !structure.is_len_source(&mem.name) => {
+                let max = util::unsuffixed(NibbleVarintMember::MAX as u64);
+                sets.extend(quote! { value.#mem_name = #arb_name; });
+                quote! { 0..=#max as u32 }
+            }
+            StructMember::VarintMember(mem) if !structure.is_len_source(&mem.name) => {
+                let sty = mem.ty()?;
+                let max = util::hex(mem.max());
+                sets.extend(quote! { value.#mem_name = #arb_name; });
+                quote! { 0..=#max as #sty }
+            }
+            StructMember::OptionalMember(mem) => {
+                let arb = strategy_ident(&mem.structure, &naming.types());
+                sets.extend(quote! { value.#mem_name = #arb_name; });
+                if mem.condition.is_some() {
+                    // the fields of a condition are not updated by the writer, so must agree
+                    // once the counts it may test are set
+                    let sty = util::ident(
+                        &mem.structure.to_sanitized_pascal_case(&naming.types()),
+                        span,
+                    );
+                    let present = roundtrip::render_present(
+                        structure,
+                        mem,
+                        idx,
+                        quote! { value },
+                        has_alt,
+                        naming,
+                    )?;
+                    conditions.extend(quote! {
+                        if !(#present) {
+                            value.#mem_name = None;
+                        } else if value.#mem_name.is_none() {
+                            value.#mem_name = Some(#sty::new());
+                        }
+                    });
+                } else if !mem.values.is_empty() {
+                    // an absent structure must not be flagged by an arbitrary value of the field
+                    let sty = util::ident(
+                        &mem.structure.to_sanitized_pascal_case(&naming.types()),
+                        span,
+                    );
+                    let present = roundtrip::render_present(
+                        structure,
+                        mem,
+                        idx,
+                        quote! { value },
+                        has_alt,
+                        naming,
+                    )?;
+                    sets.extend(quote! {
+                        if value.#mem_name.is_none() && (#present) {
+                            value.#mem_name = Some(#sty::new());
+                        }
+                    });
+                }
+                quote! { proptest::option::of(#arb()) }
+            }
+            // lengths are set with the members they count, the others are set by the writer
+            StructMember::LengthMember(_)
+            | StructMember::NibbleVarintMember(_)
+            | StructMember::VarintMember(_)
+            | StructMember::ChecksumMember(_)
+            | StructMember::ConstantMember(_)
+            | StructMember::AlternativesMember(_) => continue,
+        };
+
+        strategies.push(strategy);
+        names.push(arb_name);
+    }
+    sets.extend(conditions);
+
+    let map = render_map(
+        &strategies,
+        &names,
+        quote! {
+            let mut value = #init;
+            #sets
+            value
+        },
+    );
+
+    Ok(quote! {
+        pub fn #fn_name() -> impl Strategy<Value = #ty> {
+            #map
+        }
+    })
+}
+
+/// Render a strategy and a property test checking that arbitrary values of `structure` are
+/// serialized identically after writing and reading them back.
+pub fn render_struct(structure: &Structure, naming: &Naming) -> Result<TokenStream> {
+    let test_name = roundtrip::test_ident(&structure.name, naming);
+    let fn_name = strategy_ident(&structure.name, &naming.types());
+    let strategy = render_strategy(structure, naming)?;
+
+    let check = roundtrip::render_check(structure, naming);
+
+    Ok(quote! {
+        #strategy
+
+        proptest! {
+            #[test]
+            fn #test_name(value in #fn_name()) {
+                #check
+            }
+        }
+    })
+}
+
+/// Render a strategy and a property test for a simple structure.
+pub fn render_simple(structure: &SimpleStructure, naming: &Naming) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let test_name = roundtrip::test_ident(&structure.name, naming);
+    let fn_name = strategy_ident(&structure.name, &naming.types());
+    let str_name = util::ident(
+        &structure.name.to_sanitized_pascal_case(&naming.types()),
+        span,
+    );
+    let strategy = int_strategy(structure.member.bytes)?;
+
+    Ok(quote! {
+        pub fn #fn_name() -> impl Strategy<Value = #str_name> {
+            (#strategy).prop_map(#str_name::of_value)
+        }
+
+        proptest! {
+            #[test]
+            fn #test_name(value in #fn_name()) {
+                let mut buf = [0u8; #str_name::SIZE];
+                assert_eq!(value.write_to(&mut buf).unwrap(), #str_name::SIZE);
+                let (read, len) = #str_name::read_from(&buf).unwrap();
+                assert_eq!(len, #str_name::SIZE);
+                assert_eq!(read.get(), value.get());
+            }
+        }
+    })
+}
+
+/// Render a strategy generating readers of a bitfield holding only valid values, which are
+/// chosen by index for enumerated fields, and a property test checking that every field reads
+/// back the value written through the writer.
+pub fn render_bitfield(bitfield: &BitField, naming: &Naming) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let test_name = roundtrip::test_ident(&bitfield.name, naming);
+    let fn_name = strategy_ident(&bitfield.name, &naming.types());
+    let fields = naming.names_of(&bitfield.name);
+
+    let mut strategies = Vec::new();
+    let mut names = Vec::new();
+    let mut writes = TokenStream::new();
+    let mut checks = TokenStream::new();
+
+    for field in &bitfield.fields {
+        // only fields which are both read and written can be checked
+        let field = match field {
+            MaybeField::Field(field) if field.access == Access::ReadWrite => field,
+            _ => continue,
+        };
+        let field_name_sc = util::ident(&field.name.to_sanitized_snake_case(&fields), span);
+        let values = naming.values_of(&bitfield.name, &field.name);
+
+        if field.is_bit() {
+            writes.extend(quote! { w.#field_name_sc().bit(#field_name_sc); });
+            checks.extend(quote! {
+                prop_assert_eq!(r.#field_name_sc().bit(), #field_name_sc);
+            });
+            strategies.push(quote! { any::<bool>() });
+        } else if field.numeric {
+            let fty = field.bitsize.to_ty()?;
+            let max = util::hex(field.max());
+            let strategy = quote! { 0..=(#max as #fty) };
+            writes.extend(quote! { w = w.#field_name_sc(#field_name_sc); });
+            checks.extend(quote! {
+                prop_assert_eq!(r.#field_name_sc(), #field_name_sc);
+            });
+            strategies.push(strategy);
+        } else {
+            let field_name_pc_a = util::ident(
+                &format!("{}A", field.name.to_sanitized_pascal_case(&fields)),
+                span,
+            );
+            let mut count = field.enumerated_values.len() as u64;
+            let mut arms = field
+                .enumerated_values
+                .iter()
+                .enumerate()
+                .map(|(i, value)| {
+                    let key_pc = util::ident(&value.0.to_sanitized_pascal_case(&values), span);
+                    let i = util::unsuffixed(i as u64);
+                    quote! { #i => #field_name_pc_a::#key_pc, }
+                })
+                .collect::<Vec<_>>();
+            // ranges follow the values, with every value of a range being an index of its own
+            if !field.enumerated_ranges.is_empty() {
+                let fty = field.bitsize.to_ty()?;
+                for range in &field.enumerated_ranges {
+                    let key_pc = util::ident(&range.0.to_sanitized_pascal_case(&values), span);
+                    let first = util::unsuffixed(count);
+                    count += range.count();
+                    let last = util::unsuffixed(count - 1);
+                    let start = util::unsuffixed(*range.2.start());
+                    arms.push(quote! {
+                        i @ #first..=#last => #field_name_pc_a::#key_pc((i - #first + #start) as #fty),
+                    });
+                }
+            }
+            let count = util::unsuffixed(count);
+            writes.extend(quote! {
+                let #field_name_sc = match #field_name_sc {
+                    #(#arms)*
+                    _ => unreachable!(),
+                };
+                w.#field_name_sc().variant(#field_name_sc);
+            });
+            checks.extend(quote! {
+                prop_assert!(#field_name_pc_a::try_from(r.#field_name_sc().bits()) == Ok(#field_name_sc));
+            });
+            strategies.push(quote! { 0..#count });
+        }
+        names.push(field_name_sc);
+    }
+
+    let map = render_map(
+        &strategies,
+        &names,
+        quote! {
+            let mut w = W::reset();
+            #writes
+            let r = R::new(*w);
+            #checks
+            Ok(r)
+        },
+    );
+
+    Ok(quote! {
+        /// Generate a reader holding only valid values, checking each field while doing so.
+        fn #fn_name() -> impl Strategy<Value = Result<R, TestCaseError>> {
+            #map
+        }
+
+        proptest! {
+            #[test]
+            fn #test_name(r in #fn_name()) {
+                r?;
+            }
+        }
+    })
+}
+
+/// Wrap the rendered strategies and property tests into a test module, which is visible to the
+/// rest of the crate so that other modules can reuse its strategies.
+pub fn render_module(tests: &TokenStream) -> TokenStream {
+    quote! {
+        #[cfg(test)]
+        pub(crate) mod proptests {
+            use super::*;
+            use proptest::prelude::*;
+
+            #tests
+        }
+    }
+}
@@ -0,0 +1,647 @@
+use anyhow::{bail, Result};
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+
+use crate::generate::bitfield::{BitField, MaybeField};
+use crate::generate::structure::{
+    AlternativesMember, OptionalMember, Payloads, RepeatedMember, SimpleStructure, StringKind,
+    StructMember, Structure,
+};
+use crate::util::{
+    self, Access, Naming, ToSanitizedPascalCase, ToSanitizedSnakeCase, U32Ext, BITS_PER_BYTE,
+};
+
+/// A non-zero value of `bytes` bytes with a different value in every byte.
+fn pattern(bytes: u32) -> u64 {
+    (0..bytes as u64).fold(0, |acc, i| acc | ((i + 1) * 0x11) << (i * 8))
+}
+
+/// Render a test name for the type `name`.
+pub(crate) fn test_ident(name: &str, naming: &Naming) -> Ident {
+    util::ident(
+        &format!(
+            "{}_round_trip",
+            name.to_sanitized_snake_case(&naming.types())
+        ),
+        Span::call_site(),
+    )
+}
+
+/// Render assignments filling every stored member of `value` with a non-zero pattern.
+fn render_pattern(structure: &Structure, naming: &Naming) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let members = naming.names_of(&structure.name);
+    let mut sets = TokenStream::new();
+    let mut conditions = TokenStream::new();
+
+    for (idx, mem) in structure.members.iter().enumerate() {
+        let mem_name = util::ident(&mem.name().to_sanitized_snake_case(&members), span);
+
+        sets.extend(match mem {
+            StructMember::PrimitiveMember(mem) => {
+                // a key of a checksum must select one of its extra bytes
+                let value = match structure.checksum_extra(&mem.name) {
+                    Some(extra) if !extra.values.is_empty() => util::hex(extra.values[0].0),
+                    _ => util::hex(pattern(mem.bytes)),
+                };
+                quote! { value.#mem_name = #value; }
+            }
+            StructMember::BitfieldMember(mem) if mem.bytes > 8 => {
+                let bytes = (0..mem.bytes as u64).map(|i| util::hex(((i + 1) * 0x11) & 0xff));
+                quote! { value.#mem_name = [#(#bytes),*]; }
+            }
+            StructMember::BitfieldMember(mem) => {
+                let value = util::hex(pattern(mem.bytes));
+                quote! { value.#mem_name = #value; }
+            }
+            StructMember::ArrayMember(mem) => {
+                let value = util::hex(pattern(mem.bytes));
+                let count = util::unsuffixed(mem.count as u64);
+                quote! { value.#mem_name = [#value; #count]; }
+            }
+            StructMember::BytesMember(mem) => {
+                let len = match structure.find_len_source(&mem.len_from, idx) {
+                    Some(len) => len,
+                    None => bail!(
+                        "bytes field `{}` refers to unknown length field `{}`",
+                        mem.name,
+                        mem.len_from
+                    ),
+                };
+                let len_name = util::ident(&len.name().to_sanitized_snake_case(&members), span);
+                let capacity = util::unsuffixed(mem.capacity as u64);
+                let count = util::unsuffixed(mem.capacity.min(len.max()) as u64);
+                let payload = match structure.payloads {
+                    Payloads::Array => quote! { [0x5a; #capacity] },
+                    Payloads::Heapless => {
+                        quote! { heapless::Vec::from_slice(&[0x5a; #count]).unwrap() }
+                    }
+                    Payloads::Alloc => quote! { alloc::vec![0x5a; #count] },
+                };
+                quote! {
+                    value.#mem_name = #payload;
+                    value.#len_name = #count;
+                }
+            }
+            // a string filling its capacity, or as much as its length field can count
+            StructMember::StringMember(mem) => {
+                let (count, set_len) = match &mem.kind {
+                    StringKind::Prefixed(len_from) => {
+                        let len = match structure.find_len_source(len_from, idx) {
+                            Some(len) => len,
+                            None => bail!(
+                                "string field `{}` refers to unknown length field `{}`",
+                                mem.name,
+                                len_from
+                            ),
+                        };
+                        let len_name =
+                            util::ident(&len.name().to_sanitized_snake_case(&members), span);
+                        let count = mem.capacity.min(len.max());
+                        let len_value = util::unsuffixed(count as u64);
+                        (count, quote! { value.#len_name = #len_value; })
+                    }
+                    _ => (mem.capacity, quote! {}),
+                };
+                let string = (0..count)
+                    .map(|i| char::from(b'a' + (i % 26) as u8))
+                    .collect::<String>();
+                let push = match structure.payloads {
+                    Payloads::Alloc => quote! { value.#mem_name.push_str(#string); },
+                    _ => quote! { value.#mem_name.push_str(#string).unwrap(); },
+                };
+                quote! {
+                    #push
+                    #set_len
+                }
+            }
+            StructMember::RepeatedMember(mem) if mem.count_field.is_some() => {
+                let count = util::unsuffixed(mem.capacity as u64);
+                let value = util::hex(pattern(mem.bytes));
+                let set_count = render_count(structure, mem, idx, quote! { #count }, naming)?;
+                quote! {
+                    for _ in 0..#count {
+                        value.#mem_name.push(#value).unwrap();
+                    }
+                    #set_count
+                }
+            }
+            StructMember::RepeatedMember(mem) => {
+                let len = match structure.find_len_source(&mem.count_from, idx) {
+                    Some(len) => len,
+                    None => bail!(
+                        "repeated field `{}` refers to unknown length field `{}`",
+                        mem.name,
+                        mem.count_from
+                    ),
+                };
+                let len_name = util::ident(&len.name().to_sanitized_snake_case(&members), span);
+                let count = util::unsuffixed(mem.capacity.min(len.max()) as u64);
+                let value = util::hex(pattern(mem.bytes));
+                quote! {
+                    for _ in 0..#count {
+                        value.#mem_name.push(#value).unwrap();
+                    }
+                    value.#len_name = #count;
+                }
+            }
+            StructMember::NibbleVarintMember(mem) if !structure.is_len_source(&mem.name) => {
+                let value = util::hex(0x1234);
+                quote! { value.#mem_name = #value; }
+            }
+            // the largest value takes the most bytes
+            StructMember::VarintMember(mem) if !structure.is_len_source(&mem.name) => {
+                let value = util::hex(mem.max());
+                quote! { value.#mem_name = #value; }
+            }
+            StructMember::BitsMember(mem) => {
+                let value = util::hex(pattern(mem.bits.div_ceil(BITS_PER_BYTE)) & mem.max());
+                quote! { value.#mem_name = #value; }
+            }
+            StructMember::EuiMember(mem) => {
+                // distinct octets, so that their order is checked
+                let octets = (0..mem.bytes).map(|i| util::hex(0x11 * (i as u64 + 1)));
+                quote! { value.#mem_name = [#(#octets),*]; }
+            }
+            StructMember::TimestampMember(mem) => {
+                let value = util::hex(pattern(mem.bytes) & mem.max());
+                quote! { value.#mem_name = #value; }
+            }
+            StructMember::BcdMember(mem) => {
+                let value = util::unsuffixed(mem.max());
+                quote! { value.#mem_name = #value; }
+            }
+            StructMember::OffsetMember(mem) => {
+                let value = util::hex(pattern(mem.bytes) & mem.max());
+                quote! { value.#mem_name = #value; }
+            }
+            StructMember::ExtendedMember(mem) => {
+                // a chain filling its capacity
+                let bytes = (0..mem.capacity)
+                    .map(|i| util::hex(if i + 1 < mem.capacity { 0xda } else { 0x5a }));
+                quote! { value.#mem_name = [#(#bytes),*]; }
+            }
+            // the fields of a condition are not updated by the writer, so must agree once the
+            // counts it may test are set
+            StructMember::OptionalMember(mem) if mem.condition.is_some() => {
+                let sty = util::ident(
+                    &mem.structure.to_sanitized_pascal_case(&naming.types()),
+                    span,
+                );
+                let present = render_present(structure, mem, idx, quote! { value }, false, naming)?;
+                conditions.extend(quote! {
+                    value.#mem_name = if #present { Some(#sty::new()) } else { None };
+                });
+                continue;
+            }
+            StructMember::OptionalMember(mem) => {
+                let sty = util::ident(
+                    &mem.structure.to_sanitized_pascal_case(&naming.types()),
+                    span,
+                );
+                quote! { value.#mem_name = Some(#sty::new()); }
+            }
+            // lengths are set with the members they count, the others are not stored
+            StructMember::LengthMember(_)
+            | StructMember::NibbleVarintMember(_)
+            | StructMember::VarintMember(_)
+            | StructMember::ChecksumMember(_)
+            | StructMember::ConstantMember(_)
+            | StructMember::AlternativesMember(_) => continue,
+        });
+    }
+    sets.extend(conditions);
+
+    Ok(sets)
+}
+
+/// Render the assignment of `count` to the field of the bitfield of `value` counting the
+/// elements of the repeated member `mem` at index `idx`, which the writer updates as well.
+pub(crate) fn render_count(
+    structure: &Structure,
+    mem: &RepeatedMember,
+    idx: usize,
+    count: TokenStream,
+    naming: &Naming,
+) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let members = naming.names_of(&structure.name);
+    let field = match &mem.count_field {
+        Some(field) => field,
+        None => return Ok(quote! {}),
+    };
+    let bitfield = match structure.find_bitfield(&mem.count_from, idx) {
+        Some(bitfield) => bitfield,
+        None => bail!(
+            "repeated field `{}` is counted by unknown bitfield `{}`",
+            mem.name,
+            mem.count_from
+        ),
+    };
+    let pkg_name = structure.bitfield_path(&bitfield.bitfield, true, naming);
+    let bitfield_name = util::ident(&mem.count_from.to_sanitized_snake_case(&members), span);
+    let field_sc = util::ident(
+        &field.to_sanitized_snake_case(&naming.names_of(&bitfield.bitfield)),
+        span,
+    );
+    Ok(quote! {
+        value.#bitfield_name = *#pkg_name::W::new(value.#bitfield_name).#field_sc(#count as _);
+    })
+}
+
+/// Render an expression whether the flags of the optional member `mem` at `idx` mark it as
+/// present in `value`. The flags of the generic structure are taken as written, after updating
+/// the discriminators of its alternatives.
+pub(crate) fn render_present(
+    structure: &Structure,
+    mem: &OptionalMember,
+    idx: usize,
+    value: TokenStream,
+    generic: bool,
+    naming: &Naming,
+) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let members = naming.names_of(&structure.name);
+    let bitfield = match structure.find_bitfield(&mem.flag.bitfield, idx) {
+        Some(bitfield) => bitfield,
+        None => bail!(
+            "optional field `{}` is flagged by unknown bitfield `{}`",
+            mem.name,
+            mem.flag.bitfield
+        ),
+    };
+    let pkg_name = structure.bitfield_path(&bitfield.bitfield, true, naming);
+    let flag_name = mem.flag.bitfield.to_sanitized_snake_case(&members);
+    let discriminated = structure.members.iter().any(|mem| match mem {
+        StructMember::AlternativesMember(alt) => alt
+            .discriminator
+            .as_ref()
+            .is_some_and(|disc| disc.bitfield == bitfield.name),
+        _ => false,
+    });
+    let bits = if generic && discriminated {
+        let written = util::ident(&format!("written_{}", flag_name), span);
+        quote! { #value.#written() }
+    } else {
+        let flag_name = util::ident(&flag_name, span);
+        quote! { #value.#flag_name }
+    };
+    Ok(mem.render_present(
+        quote! { #pkg_name::R::new(#bits) },
+        &bitfield.bitfield,
+        naming,
+    ))
+}
+
+/// Render statements making the absent optional members of `value` present which its flags
+/// mark as present, as the writer only updates the flags of present members.
+fn render_flagged(
+    structure: &Structure,
+    value: TokenStream,
+    generic: bool,
+    naming: &Naming,
+) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let members = naming.names_of(&structure.name);
+    let mut sets = TokenStream::new();
+    for (idx, mem) in structure.members.iter().enumerate() {
+        let mem = match mem {
+            StructMember::OptionalMember(mem)
+                if !mem.values.is_empty() || mem.condition.is_some() =>
+            {
+                mem
+            }
+            _ => continue,
+        };
+        let mem_name = util::ident(&mem.name.to_sanitized_snake_case(&members), span);
+        let sty = util::ident(
+            &mem.structure.to_sanitized_pascal_case(&naming.types()),
+            span,
+        );
+        let present = render_present(structure, mem, idx, value.clone(), generic, naming)?;
+        sets.extend(quote! {
+            if #present {
+                #value.#mem_name = Some(#sty::new());
+            }
+        });
+    }
+    Ok(sets)
+}
+
+/// The largest value of a length field of `bytes` bytes which fits into a `u32`.
+pub(crate) fn len_max(bytes: u32) -> u32 {
+    if bytes >= 4 {
+        u32::MAX
+    } else {
+        (1 << (bytes * BITS_PER_BYTE)) - 1
+    }
+}
+
+/// Alternatives members of `structure`.
+fn alternatives(structure: &Structure) -> Vec<&AlternativesMember> {
+    structure
+        .members
+        .iter()
+        .filter_map(|mem| match mem {
+            StructMember::AlternativesMember(alt) => Some(alt),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Render assertions that every member of `read` which can be read holds the same value as in
+/// `value`. Optional and alternatives members are left to the checks of their own structures,
+/// as are the bitfields written from them and from counted members, whose flags, counts and
+/// discriminators `value` need not agree with until it is written. Generic structures have no
+/// getters, so their members are compared directly, except for payloads whose capacity beyond
+/// their length is not written.
+fn render_field_checks(structure: &Structure, generic: bool, naming: &Naming) -> TokenStream {
+    let span = Span::call_site();
+    let members = naming.names_of(&structure.name);
+    let synced = |bitfield: &str| {
+        structure.members.iter().any(|mem| match mem {
+            StructMember::OptionalMember(opt) => opt.flag.bitfield == bitfield,
+            StructMember::RepeatedMember(rep) => {
+                rep.count_field.is_some() && rep.count_from == bitfield
+            }
+            StructMember::AlternativesMember(alt) => alt
+                .discriminator
+                .as_ref()
+                .is_some_and(|disc| disc.bitfield == bitfield),
+            _ => false,
+        })
+    };
+
+    let mut checks = TokenStream::new();
+    for mem in &structure.members {
+        match mem {
+            StructMember::BitfieldMember(mem) if !mem.access.can_read() || synced(&mem.name) => {
+                continue
+            }
+            StructMember::PrimitiveMember(mem) if !mem.access.can_read() => continue,
+            StructMember::BytesMember(_)
+            | StructMember::StringMember(_)
+            | StructMember::RepeatedMember(_)
+                if generic =>
+            {
+                continue
+            }
+            StructMember::OptionalMember(_)
+            | StructMember::AlternativesMember(_)
+            | StructMember::ChecksumMember(_)
+            | StructMember::ConstantMember(_) => continue,
+            _ => {}
+        }
+        let name = mem.name().to_sanitized_snake_case(&members);
+        let msg = format!("`{}` differs after reading it back", name);
+        checks.extend(if generic {
+            let mem_name = util::ident(&name, span);
+            quote! { assert!(read.#mem_name == value.#mem_name, #msg); }
+        } else {
+            let getter = util::ident(&format!("get_{}", name), span);
+            quote! { assert!(read.#getter() == value.#getter(), #msg); }
+        });
+    }
+    checks
+}
+
+/// Render statements checking that `value` is serialized identically after writing and reading
+/// it back, and that the value of every member survives. For structures with alternatives
+/// `value` is the generic structure, which is read back using the selectors of its
+/// alternatives without a discriminator.
+pub(crate) fn render_check(structure: &Structure, naming: &Naming) -> TokenStream {
+    let span = Span::call_site();
+    let members = naming.names_of(&structure.name);
+    let str_name = util::ident(
+        &structure.name.to_sanitized_pascal_case(&naming.types()),
+        span,
+    );
+
+    let alts = alternatives(structure);
+    if alts.is_empty() {
+        let field_checks = render_field_checks(structure, false, naming);
+        return quote! {
+            let mut buf = [0u8; #str_name::MAX_SIZE];
+            let len = value.write_to(&mut buf).unwrap();
+            assert_eq!(len, value.wire_size());
+
+            let (read, read_len) = #str_name::read_from(&buf[..len]).unwrap();
+            assert_eq!(read_len, len);
+            #field_checks
+
+            let mut reread = [0u8; #str_name::MAX_SIZE];
+            let reread_len = read.write_to(&mut reread).unwrap();
+            assert_eq!(&buf[..len], &reread[..reread_len]);
+        };
+    }
+
+    let str_name_gen = util::ident(&format!("{}Generic", str_name), span);
+    let field_checks = render_field_checks(structure, true, naming);
+    let selectors = alts
+        .iter()
+        .filter(|alt| alt.discriminator.is_none())
+        .map(|alt| {
+            let mem_name = util::ident(&alt.name.to_sanitized_snake_case(&members), span);
+            quote! { value.#mem_name.selector() }
+        });
+
+    quote! {
+        let write_to = |value : &#str_name_gen, buf : &mut [u8]| -> usize {
+            let len = buf.len();
+            let mut out = buf;
+            value.write(&mut out).unwrap();
+            len - out.len()
+        };
+
+        let mut buf = [0u8; #str_name_gen::MAX_SIZE];
+        let len = write_to(&value, &mut buf);
+        assert_eq!(len, value.wire_size());
+
+        let mut reader = &buf[..len];
+        let read = #str_name_gen::read(&mut reader, #(#selectors),*).unwrap();
+        assert!(reader.is_empty());
+        #field_checks
+
+        let mut reread = [0u8; #str_name_gen::MAX_SIZE];
+        let reread_len = write_to(&read, &mut reread);
+        assert_eq!(&buf[..len], &reread[..reread_len]);
+    }
+}
+
+/// Render a test writing and reading back the default value and a value with every member set,
+/// checking that the value is serialized identically and holds the same members after reading
+/// it.
+pub fn render_struct(structure: &Structure, naming: &Naming) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let members = naming.names_of(&structure.name);
+    let test_name = test_ident(&structure.name, naming);
+    let str_name = util::ident(
+        &structure.name.to_sanitized_pascal_case(&naming.types()),
+        span,
+    );
+
+    let check = render_check(structure, naming);
+
+    if !alternatives(structure).is_empty() {
+        let str_name_gen = util::ident(&format!("{}Generic", str_name), span);
+        let flagged = render_flagged(structure, quote! { value }, true, naming)?;
+        let value = if flagged.is_empty() {
+            quote! { let value = #str_name_gen::default(); }
+        } else {
+            quote! {
+                let mut value = #str_name_gen::default();
+                #flagged
+            }
+        };
+        return Ok(quote! {
+            #[test]
+            fn #test_name() {
+                #value
+                #check
+            }
+        });
+    }
+
+    let sets = render_pattern(structure, naming)?;
+    // a key of a checksum must select one of its extra bytes even in the default value
+    let mut keys = structure
+        .members
+        .iter()
+        .filter_map(|mem| match structure.checksum_extra(mem.name()) {
+            Some(extra) if !extra.values.is_empty() => {
+                let mem_name = util::ident(&mem.name().to_sanitized_snake_case(&members), span);
+                let key = util::hex(extra.values[0].0);
+                Some(quote! { default.#mem_name = #key; })
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    // as must an optional structure flagged by the default value of its field
+    let flagged = render_flagged(structure, quote! { default }, false, naming)?;
+    if !flagged.is_empty() {
+        keys.push(flagged);
+    }
+    let default = if keys.is_empty() {
+        quote! { let default = #str_name::new(); }
+    } else {
+        quote! {
+            let mut default = #str_name::new();
+            #(#keys)*
+        }
+    };
+    Ok(quote! {
+        #[test]
+        fn #test_name() {
+            let mut value = #str_name::new();
+            #sets
+            #default
+            for value in [default, value].iter() {
+                #check
+            }
+        }
+    })
+}
+
+/// Render a test writing and reading back a simple structure.
+pub fn render_simple(structure: &SimpleStructure, naming: &Naming) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let test_name = test_ident(&structure.name, naming);
+    let str_name = util::ident(
+        &structure.name.to_sanitized_pascal_case(&naming.types()),
+        span,
+    );
+    let value = util::hex(pattern(structure.member.bytes));
+
+    Ok(quote! {
+        #[test]
+        fn #test_name() {
+            for value in [#str_name::new(), #str_name::of_value(#value)].iter() {
+                let mut buf = [0u8; #str_name::SIZE];
+                assert_eq!(value.write_to(&mut buf).unwrap(), #str_name::SIZE);
+                let (read, len) = #str_name::read_from(&buf).unwrap();
+                assert_eq!(len, #str_name::SIZE);
+                assert_eq!(read.get(), value.get());
+            }
+        }
+    })
+}
+
+/// Render a test setting every field of a bitfield through the writer to each of its
+/// enumerated values, or its largest value if numeric, and reading it back through the reader.
+pub fn render_bitfield(bitfield: &BitField, naming: &Naming) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let test_name = test_ident(&bitfield.name, naming);
+    let fields = naming.names_of(&bitfield.name);
+    let mut checks = TokenStream::new();
+
+    for field in &bitfield.fields {
+        // only fields which are both read and written can be checked
+        let field = match field {
+            MaybeField::Field(field) if field.access == Access::ReadWrite => field,
+            _ => continue,
+        };
+        let field_name_sc = util::ident(&field.name.to_sanitized_snake_case(&fields), span);
+        let values = naming.values_of(&bitfield.name, &field.name);
+
+        if field.is_bit() {
+            checks.extend(quote! {
+                let mut w = W::reset();
+                w.#field_name_sc().set_bit();
+                assert!(R::new(*w).#field_name_sc().bit_is_set());
+                w.#field_name_sc().clear_bit();
+                assert!(R::new(*w).#field_name_sc().bit_is_clear());
+            });
+        } else if field.numeric {
+            let fty = field.bitsize.to_ty()?;
+            let max = util::hex(field.max());
+            let max = quote! { #max as #fty };
+            checks.extend(quote! {
+                let w = W::reset().#field_name_sc(#max);
+                assert_eq!(R::new(*w).#field_name_sc(), #max);
+            });
+        } else {
+            let field_name_pc_a = util::ident(
+                &format!("{}A", field.name.to_sanitized_pascal_case(&fields)),
+                span,
+            );
+            for value in &field.enumerated_values {
+                let key_pc = util::ident(&value.0.to_sanitized_pascal_case(&values), span);
+                checks.extend(quote! {
+                    let mut w = W::reset();
+                    w.#field_name_sc().variant(#field_name_pc_a::#key_pc);
+                    assert!(#field_name_pc_a::try_from(R::new(*w).#field_name_sc().bits()) == Ok(#field_name_pc_a::#key_pc));
+                });
+            }
+            for range in &field.enumerated_ranges {
+                let key_pc = util::ident(&range.0.to_sanitized_pascal_case(&values), span);
+                for value in [*range.2.start(), *range.2.end()] {
+                    let value = util::unsuffixed(value);
+                    checks.extend(quote! {
+                        let mut w = W::reset();
+                        w.#field_name_sc().variant(#field_name_pc_a::#key_pc(#value));
+                        assert!(#field_name_pc_a::try_from(R::new(*w).#field_name_sc().bits()) == Ok(#field_name_pc_a::#key_pc(#value)));
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(quote! {
+        #[test]
+        fn #test_name() {
+            #checks
+        }
+    })
+}
+
+/// Wrap the rendered tests into a test module.
+pub fn render_module(tests: &TokenStream) -> TokenStream {
+    quote! {
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+
+            #tests
+        }
+    }
+}
@@ -1,37 +1,77 @@
-use anyhow::Result;
-use inflections::Inflect;
+use anyhow::{bail, Context, Result};
 
 use std::collections::HashMap;
 
-use proc_macro2::{Ident, Span, TokenStream};
+use proc_macro2::{Ident, Span, TokenStream, TokenTree};
 use quote::quote;
 
-use crate::util::{unsuffixed, ToSanitizedPascalCase, ToSanitizedSnakeCase, U32Ext};
+use crate::doc;
+use crate::generate::base;
+use crate::generate::bitfield::{BitField, BitfieldRegistry};
+use crate::generate::builder;
+use crate::generate::checksum::{ChecksumAlgorithm, ChecksumExtra};
+use crate::generate::frame_trait;
+use crate::generate::init;
+use crate::generate::parser;
+use crate::generate::roundtrip;
+use crate::generate::trailing;
+use crate::generate::vectored;
+use crate::generate::view;
+use crate::util::{
+    self, hex, raw_ty, unsuffixed, Access, ByteOrder, Config, Derives, IoBackend, Namespace,
+    Naming, Scope, ToSanitizedPascalCase, ToSanitizedSnakeCase, ToSanitizedUpperCase, U32Ext,
+    BITS_PER_BYTE,
+};
 
-pub fn deriving_tokens() -> TokenStream {
-    quote! {#[derive(Clone, Copy, Debug, Eq, PartialEq)]}
+pub fn deriving_tokens(derives: &Derives) -> Result<TokenStream> {
+    derives.tokens()
+}
+
+/// Derives for structures owning data which cannot be `Copy`.
+pub fn deriving_tokens_no_copy(derives: &Derives) -> Result<TokenStream> {
+    derives.clone().remove_derive("Copy").tokens()
 }
 
 pub trait Type {
-    fn name<'a>(&'a self) -> &'a str;
+    fn name(&self) -> &str;
+
+    /// Whether the type can be `Copy`, which it cannot if it owns data.
+    fn is_copy(&self) -> bool {
+        true
+    }
 }
 
+#[derive(Clone)]
 pub struct PrimitiveMember {
     pub name: String,
+    pub desc: String,
     pub bytes: u32,
+    pub access: Access,
 }
 
 impl PrimitiveMember {
     pub fn new(name: &str, bytes: u32) -> Self {
+        Self::new_desc(name, "", bytes)
+    }
+
+    pub fn new_desc(name: &str, desc: &str, bytes: u32) -> Self {
         let name = String::from(name);
-        Self { name, bytes }
+        let desc = String::from(desc);
+        Self {
+            name,
+            desc,
+            bytes,
+            access: Access::default(),
+        }
     }
 }
 
+#[derive(Clone)]
 pub struct BitfieldMember {
     pub name: String,
     pub bitfield: String,
     pub bytes: u32,
+    pub access: Access,
 }
 
 impl BitfieldMember {
@@ -43,632 +83,5873 @@ impl BitfieldMember {
             name,
             bitfield,
             bytes,
+            access: Access::default(),
         }
     }
 }
 
+/// Identifies the field of a bitfield member which determines the layout of other members,
+/// e.g. by selecting the active alternative or flagging the presence of an optional member.
+#[derive(Clone)]
+pub struct Discriminator {
+    pub bitfield: String,
+    pub field: String,
+}
+
+impl Discriminator {
+    pub fn new(bitfield: &str, field: &str) -> Self {
+        let bitfield = String::from(bitfield);
+        let field = String::from(field);
+        Self { bitfield, field }
+    }
+}
+
+#[derive(Clone)]
 pub struct AlternativesMember {
     pub name: String,
     pub alternatives: String,
+    pub discriminator: Option<Discriminator>,
 }
 
 impl AlternativesMember {
     pub fn new(name: &str, alternatives: &str) -> Self {
         let name = String::from(name);
         let alternatives = String::from(alternatives);
-        Self { name, alternatives }
+        Self {
+            name,
+            alternatives,
+            discriminator: None,
+        }
     }
-}
 
-pub enum StructMember {
-    BitfieldMember(BitfieldMember),
-    PrimitiveMember(PrimitiveMember),
-    AlternativesMember(AlternativesMember),
-}
+    pub fn discriminated(mut self, discriminator: Discriminator) -> Self {
+        self.discriminator = Some(discriminator);
+        self
+    }
 
-impl StructMember {
-    pub fn name(&self) -> &str {
-        match &self {
-            &StructMember::PrimitiveMember(mem) => &mem.name,
-            &StructMember::BitfieldMember(mem) => &mem.name,
-            &StructMember::AlternativesMember(mem) => &mem.name,
+    /// The generic parameter of the typed structure selecting the alternative of the member,
+    /// which is named among the `members` of the structure. With `const_generic` it is the
+    /// `u8` index of the alternative in the selector of the alternatives rather than a type
+    /// bounded by their trait, and the member holds the enum of the alternatives.
+    pub(crate) fn param(&self, const_generic: bool, members: &Scope, naming: &Naming) -> AltParam {
+        let span = Span::call_site();
+        let alt_trait = util::ident(
+            &self.alternatives.to_sanitized_pascal_case(&naming.types()),
+            span,
+        );
+        if const_generic {
+            let index = util::ident(&self.name.to_sanitized_upper_case(members), span);
+            let alt_pc_a = util::ident(&format!("{}A", alt_trait), span);
+            AltParam {
+                decl: quote! { const #index : u8, },
+                arg: quote! { #index, },
+                bound: quote! {},
+                ty: quote! { #alt_pc_a },
+            }
+        } else {
+            let alt_name_templ = util::ident(
+                &format!("{}T", self.name.to_sanitized_pascal_case(members)),
+                span,
+            );
+            AltParam {
+                decl: quote! { #alt_name_templ, },
+                arg: quote! { #alt_name_templ, },
+                bound: quote! { #alt_name_templ : #alt_trait, },
+                ty: quote! { #alt_name_templ },
+            }
+        }
+    }
+
+    /// Render a statement asserting that `value`, which is about to become the member of the
+    /// typed structure, holds the alternative selected by its const parameter. Type parameters
+    /// are checked by the compiler instead.
+    pub(crate) fn render_check(
+        &self,
+        const_generic: bool,
+        value: TokenStream,
+        members: &Scope,
+    ) -> TokenStream {
+        if !const_generic {
+            return quote! {};
+        }
+        let index = util::ident(
+            &self.name.to_sanitized_upper_case(members),
+            Span::call_site(),
+        );
+        let msg = format!("`{}` holds another alternative than `{}`", self.name, index);
+        quote! {
+            assert!(#value.selector().index() == #index, #msg);
         }
     }
 }
 
-#[derive(Clone)]
-pub struct AlternativeOptions {
-    pub name: String,
-    pub default: String,
-    pub alternatives: Vec<String>,
+/// The generic parameter of a typed structure selecting the alternative held by one of its
+/// members, see [`AlternativesMember::param`].
+pub(crate) struct AltParam {
+    /// The parameter as declared, e.g. in `impl<...>`.
+    pub decl: TokenStream,
+    /// The parameter as an argument of the structure.
+    pub arg: TokenStream,
+    /// The bound of the parameter, which consts do not have.
+    pub bound: TokenStream,
+    /// The type of the member.
+    pub ty: TokenStream,
 }
 
-pub struct Alternatives {
-    pub map: HashMap<String, AlternativeOptions>,
+/// A length field whose value is governed by a [`BytesMember`] referencing it.
+#[derive(Clone)]
+pub struct LengthMember {
+    pub name: String,
+    pub bytes: u32,
+    /// Bytes counted on the wire besides the governing member, e.g. the fixed part of a
+    /// message whose length field holds the length of the whole message.
+    pub extra: u32,
 }
 
-impl Alternatives {
-    pub fn new() -> Self {
+impl LengthMember {
+    pub fn new(name: &str, bytes: u32) -> Self {
+        let name = String::from(name);
         Self {
-            map: HashMap::new(),
+            name,
+            bytes,
+            extra: 0,
         }
     }
 
-    pub fn insert(mut self, options: &AlternativeOptions) -> Self {
-        let key = options.name.clone();
-        self.map.insert(key, options.clone());
-        self
+    /// The largest number of elements the field can count.
+    pub fn max(&self) -> u32 {
+        roundtrip::len_max(self.bytes).saturating_sub(self.extra)
     }
+}
 
-    pub fn insert_new_option<F>(mut self, key: &str, default: &Structure, mut f: F) -> Self
-    where
-        F: FnMut(AlternativeOptions) -> AlternativeOptions,
-    {
-        let options = AlternativeOptions::new(key, default);
-        let options = f(options);
-        let key = String::from(key);
-        self.map.insert(key, options);
-        self
+/// A byte payload whose length on the wire is given by a preceding [`LengthMember`].
+#[derive(Clone)]
+pub struct BytesMember {
+    pub name: String,
+    pub len_from: String,
+    pub capacity: u32,
+}
+
+impl BytesMember {
+    pub fn new(name: &str, len_from: &str, capacity: u32) -> Self {
+        let name = String::from(name);
+        let len_from = String::from(len_from);
+        Self {
+            name,
+            len_from,
+            capacity,
+        }
     }
+}
 
-    pub fn get(&self, name: &str) -> Result<&AlternativeOptions> {
-        let v = self.map.get(&String::from(name));
-        match v {
-            None => Err(anyhow::Error::msg("Could not find alternative.")),
-            Some(v) => Ok(v),
+/// How a generated structure holds the payloads of its [`BytesMember`]s, which it owns either
+/// way, see [`Structure::with_payloads`].
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Payloads {
+    /// An array of the capacity, of which the length field gives the bytes in use, keeping
+    /// the structure `Copy`.
+    #[default]
+    Array,
+    /// A `heapless::Vec` of the capacity holding just the payload, which is not `Copy` but
+    /// hands out the payload without looking at the length field.
+    Heapless,
+    /// An `alloc::vec::Vec`, which also holds the strings as an `alloc::string::String`. The
+    /// structure is only compiled with the `alloc` feature of the generated crate, e.g. for
+    /// tools and tests running on a host.
+    Alloc,
+}
+
+/// How a [`StringMember`] is delimited on the wire.
+#[derive(Clone)]
+pub enum StringKind {
+    /// Always takes its capacity, padded with NUL bytes.
+    Fixed,
+    /// Ends with a NUL byte, which the capacity does not count.
+    NullTerminated,
+    /// Counted by the length field or varint of the name.
+    Prefixed(String),
+}
+
+/// A UTF-8 string of up to `capacity` bytes such as a device name, which is validated when
+/// reading. Strings delimited by NUL bytes cannot hold any.
+#[derive(Clone)]
+pub struct StringMember {
+    pub name: String,
+    pub kind: StringKind,
+    pub capacity: u32,
+}
+
+impl StringMember {
+    pub fn new(name: &str, kind: StringKind, capacity: u32) -> Self {
+        let name = String::from(name);
+        Self {
+            name,
+            kind,
+            capacity,
         }
     }
 }
 
-pub struct Structure {
+/// A chain of up to `capacity` bytes, each but the last of which has its most significant bit
+/// set to mark that another byte follows, like the DIF and VIF of M-Bus with their extensions.
+/// The bytes are kept along with their extension bits.
+#[derive(Clone)]
+pub struct ExtendedMember {
     pub name: String,
-    pub members: Vec<StructMember>,
+    pub capacity: u32,
 }
 
-impl Type for Structure {
-    fn name<'a>(&'a self) -> &'a str {
-        &self.name
+impl ExtendedMember {
+    pub fn new(name: &str, capacity: u32) -> Self {
+        let name = String::from(name);
+        Self { name, capacity }
     }
 }
 
-impl Structure {
-    pub fn new(name: &str) -> Structure {
+/// A value of `bits` bits packed together with the neighbouring bit members into whole bytes,
+/// like the 11 bit channels of SBUS. Consecutive bit members form a run starting at a byte
+/// boundary, in which the member starts at the bit `offset`. Bits are counted from the least
+/// significant bit of the first byte in little endian structures, and from the most significant
+/// bit in big endian ones.
+#[derive(Clone)]
+pub struct BitsMember {
+    pub name: String,
+    pub desc: String,
+    pub bits: u32,
+    pub offset: u32,
+}
+
+impl BitsMember {
+    pub fn new(name: &str, desc: &str, bits: u32, offset: u32) -> Self {
         let name = String::from(name);
-        Structure {
+        let desc = String::from(desc);
+        Self {
             name,
-            members: vec![],
+            desc,
+            bits,
+            offset,
         }
     }
 
-    pub fn add_bitfield(mut self, name: &str, bitfield: &str, bytes: u32) -> Self {
-        let member = BitfieldMember::new(name, bitfield, bytes);
-        self.members.push(StructMember::BitfieldMember(member));
-        self
+    /// Number of bytes of the run completed by the member, so that the bytes of the members of
+    /// a run add up to its size and the bytes before a member lead to its first byte.
+    pub fn bytes(&self) -> u32 {
+        (self.offset + self.bits) / BITS_PER_BYTE - self.offset / BITS_PER_BYTE
     }
 
-    pub fn add_prim_field(mut self, name: &str, bytes: u32) -> Self {
-        let member = PrimitiveMember::new(name, bytes);
-        self.members.push(StructMember::PrimitiveMember(member));
-        self
+    /// Whether the member ends on a byte boundary, which ends its run.
+    pub fn is_aligned(&self) -> bool {
+        (self.offset + self.bits).is_multiple_of(BITS_PER_BYTE)
     }
 
-    pub fn add_u8_field(self, name: &str) -> Self {
-        self.add_prim_field(name, 1)
+    /// Largest value of the member.
+    pub fn max(&self) -> u64 {
+        u64::MAX >> (64 - self.bits)
     }
 
-    pub fn add_u16_field(self, name: &str) -> Self {
-        self.add_prim_field(name, 2)
+    /// Type holding the value of the member.
+    pub fn ty(&self) -> Result<Ident> {
+        self.bits.max(BITS_PER_BYTE).to_ty()
     }
+}
 
-    pub fn add_u32_field(self, name: &str) -> Self {
-        self.add_prim_field(name, 4)
+/// A value encoded as a nibble-extended varint, like the option delta and length of CoAP: a
+/// nibble holds values below 13, while the nibbles 13 and 14 are followed by one or two bytes
+/// holding the value minus 13 or 269. Varints come in pairs sharing the byte of their nibbles,
+/// where the first of the pair takes the high nibble and names the second in `low`. The
+/// extended bytes of both follow the shared byte in the order of the pair.
+#[derive(Clone)]
+pub struct NibbleVarintMember {
+    pub name: String,
+    pub low: Option<String>,
+}
+
+impl NibbleVarintMember {
+    /// The largest value of a varint.
+    pub const MAX: u32 = 269 + 0xffff;
+
+    pub fn new(name: &str, low: Option<&str>) -> Self {
+        let name = String::from(name);
+        let low = low.map(String::from);
+        Self { name, low }
     }
+}
 
-    pub fn add_u64_field(self, name: &str) -> Self {
-        self.add_prim_field(name, 8)
+/// An unsigned LEB128 varint of up to `bits` bits, like the varints of protobuf and the
+/// remaining length of MQTT: each byte holds the next seven bits of the value starting with the
+/// least significant ones, and has its most significant bit set if another byte follows.
+#[derive(Clone)]
+pub struct VarintMember {
+    pub name: String,
+    pub bits: u32,
+}
+
+impl VarintMember {
+    pub fn new(name: &str, bits: u32) -> Self {
+        let name = String::from(name);
+        Self { name, bits }
     }
 
-    pub fn add_alt_field(mut self, name: &str, alternatives: &AlternativeOptions) -> Self {
-        let member = AlternativesMember::new(name, &alternatives.name);
-        self.members.push(StructMember::AlternativesMember(member));
-        self
+    /// Largest number of bytes of the varint on the wire.
+    pub fn max_bytes(&self) -> u32 {
+        self.bits.div_ceil(7)
+    }
+
+    /// Largest value of the varint.
+    pub fn max(&self) -> u64 {
+        u64::MAX >> (64 - self.bits)
+    }
+
+    /// Type holding the value of the varint.
+    pub fn ty(&self) -> Result<Ident> {
+        self.bits.max(BITS_PER_BYTE).to_ty()
     }
 }
 
-pub struct SimpleStructure {
+/// An unsigned integer of `bytes` bytes in packed BCD, like the meter readings of M-Bus: each
+/// nibble holds a decimal digit, the most significant digits in the most significant nibble
+/// of the integer in the byte order of the structure. Reading fails on nibbles above 9.
+#[derive(Clone)]
+pub struct BcdMember {
     pub name: String,
-    pub member: PrimitiveMember,
+    pub desc: String,
+    pub bytes: u32,
 }
 
-impl Type for SimpleStructure {
-    fn name<'a>(&'a self) -> &'a str {
-        &self.name
+impl BcdMember {
+    pub fn new(name: &str, desc: &str, bytes: u32) -> Self {
+        let name = String::from(name);
+        let desc = String::from(desc);
+        Self { name, desc, bytes }
+    }
+
+    /// Largest value of the member, whose digits are all 9.
+    pub fn max(&self) -> u64 {
+        10u64.pow(2 * self.bytes) - 1
+    }
+
+    /// Type holding the encoded bytes of the member.
+    pub fn raw_ty(&self) -> Result<Ident> {
+        (self.bytes * BITS_PER_BYTE).to_ty()
+    }
+
+    /// Type holding the value of the member.
+    pub fn ty(&self) -> Result<Ident> {
+        (u64::BITS - self.max().leading_zeros()).to_ty()
     }
 }
 
-impl SimpleStructure {
-    pub fn new(name: &str, mem_name: &str, bytes: u32) -> Self {
+/// An EUI-48 or EUI-64 address of `bytes` bytes such as a MAC address, accessed as a
+/// `crate::Eui48` or `crate::Eui64`. The structure holds the octets in the order of the
+/// canonical form of the address, which big endian structures send as is and little endian
+/// ones, like the frames of IEEE 802.15.4, in reverse.
+#[derive(Clone)]
+pub struct EuiMember {
+    pub name: String,
+    pub desc: String,
+    pub bytes: u32,
+}
+
+impl EuiMember {
+    pub fn new(name: &str, desc: &str, bytes: u32) -> Self {
         let name = String::from(name);
-        Self {
-            name,
-            member: PrimitiveMember::new(mem_name, bytes),
+        let desc = String::from(desc);
+        Self { name, desc, bytes }
+    }
+
+    /// Type of the address, `Eui48` or `Eui64`.
+    pub fn ty(&self) -> Ident {
+        util::ident(
+            &format!("Eui{}", self.bytes * BITS_PER_BYTE),
+            Span::call_site(),
+        )
+    }
+}
+
+/// The instant a [`TimestampMember`] counts from.
+#[derive(Clone)]
+pub enum Epoch {
+    /// 1970-01-01 00:00:00 UTC.
+    Unix,
+    /// 1980-01-06 00:00:00 UTC, the start of GPS time, which does not count the leap seconds
+    /// since. These are not accounted for when converting to the date and time of UTC.
+    Gps,
+    /// The given number of seconds after the Unix epoch.
+    Custom(i64),
+}
+
+impl Epoch {
+    /// Seconds from the Unix epoch to the epoch.
+    pub fn unix_offset(&self) -> i64 {
+        match self {
+            Epoch::Unix => 0,
+            Epoch::Gps => 315_964_800,
+            Epoch::Custom(offset) => *offset,
         }
     }
 }
 
-impl AlternativeOptions {
-    pub fn new<T>(name: &str, default: &T) -> Self
-    where
-        T: Type,
-    {
+/// A point in time as `bytes` bytes counting the ticks since an `epoch`, of which there are
+/// `ticks_per_second`. The structure holds the count as a `u64`, which may be converted into
+/// the types of `chrono` and `fugit` when the generated crate enables the respective features.
+#[derive(Clone)]
+pub struct TimestampMember {
+    pub name: String,
+    pub desc: String,
+    pub bytes: u32,
+    pub epoch: Epoch,
+    pub ticks_per_second: u32,
+}
+
+impl TimestampMember {
+    pub fn new(name: &str, desc: &str, bytes: u32, epoch: Epoch, ticks_per_second: u32) -> Self {
         let name = String::from(name);
-        let default_name = String::from(default.name());
+        let desc = String::from(desc);
         Self {
             name,
-            default: default_name,
-            alternatives: vec![],
+            desc,
+            bytes,
+            epoch,
+            ticks_per_second,
         }
-        .insert_type(default)
     }
 
-    pub fn insert_type<T>(mut self, structure: &T) -> Self
-    where
-        T: Type,
-    {
-        let name = String::from(structure.name());
-        self.alternatives.push(name);
-        self
+    /// Largest count of the member.
+    pub fn max(&self) -> u64 {
+        u64::MAX >> (64 - self.bytes * BITS_PER_BYTE)
     }
-}
-
-pub fn render_alternatives(alternatives: &Alternatives) -> Result<TokenStream> {
-    let deriving = deriving_tokens();
 
-    let span = Span::call_site();
+    /// Render asserting that the count `v` fits the member, which any count of 8 bytes does.
+    pub(crate) fn render_check(&self, v: TokenStream) -> TokenStream {
+        if self.bytes >= 8 {
+            return quote! {};
+        }
+        let max = hex(self.max());
+        quote! { assert!(#v <= #max); }
+    }
+}
 
-    let mut mod_items = TokenStream::new();
-    let mut trait_extends = TokenStream::new();
+/// A member counting the elements of a [`BytesMember`] or [`RepeatedMember`].
+pub enum LengthSource<'a> {
+    Length(&'a LengthMember),
+    NibbleVarint(&'a NibbleVarintMember),
+    Varint(&'a VarintMember),
+}
 
-    for (key, alt) in &alternatives.map {
-        let alt_pc = Ident::new(&key.to_sanitized_pascal_case(), span);
-        let alt_pc_a = Ident::new(&format!("{}A", alt_pc), span);
+impl LengthSource<'_> {
+    pub fn name(&self) -> &str {
+        match self {
+            LengthSource::Length(mem) => &mem.name,
+            LengthSource::NibbleVarint(mem) => &mem.name,
+            LengthSource::Varint(mem) => &mem.name,
+        }
+    }
 
-        let mut alt_enum_entries = TokenStream::new();
-        let mut write_entries = TokenStream::new();
-        let mut read_funs = TokenStream::new();
+    /// The Rust type of the count.
+    pub fn ty(&self) -> Result<Ident> {
+        match self {
+            LengthSource::Length(mem) => (mem.bytes * BITS_PER_BYTE).to_ty(),
+            LengthSource::NibbleVarint(_) => Ok(Ident::new("u32", Span::call_site())),
+            LengthSource::Varint(mem) => mem.ty(),
+        }
+    }
 
-        for altopt in &alt.alternatives {
-            let alt_struct = Ident::new(&altopt.to_sanitized_pascal_case(), span);
-            let alt_enum = Ident::new(&altopt.to_sanitized_pascal_case(), span);
-            let alt_enum_read =
-                Ident::new(&format!("read_{}", altopt.to_sanitized_snake_case()), span);
+    /// The largest number of elements the member can count.
+    pub fn max(&self) -> u32 {
+        match self {
+            LengthSource::Length(mem) => mem.max(),
+            LengthSource::NibbleVarint(_) => NibbleVarintMember::MAX,
+            LengthSource::Varint(mem) => mem.max().min(u32::MAX as u64) as u32,
+        }
+    }
+}
 
-            trait_extends.extend(quote! {
-                impl #alt_pc for #alt_struct {
-                    fn default() -> Self {
-                        Self::new()
-                    }
-                }
-            });
+/// A fixed number of integer elements of `bytes` bytes each.
+#[derive(Clone)]
+pub struct ArrayMember {
+    pub name: String,
+    pub bytes: u32,
+    pub count: u32,
+}
 
-            alt_enum_entries.extend(quote! {
-                #alt_enum(#alt_struct),
-            });
+impl ArrayMember {
+    pub fn new(name: &str, bytes: u32, count: u32) -> Self {
+        let name = String::from(name);
+        Self { name, bytes, count }
+    }
+}
 
-            write_entries.extend(quote! {
-                #alt_pc_a::#alt_enum(v) => v.write(out),
-            });
+/// Up to `capacity` integer elements of `bytes` bytes each, where the number of elements is
+/// given by a preceding [`LengthMember`], or by a numeric field of a preceding bitfield.
+#[derive(Clone)]
+pub struct RepeatedMember {
+    pub name: String,
+    pub bytes: u32,
+    pub count_from: String,
+    /// Numeric field of the bitfield member `count_from` holding the number of elements, which
+    /// is updated when writing like a presence flag.
+    pub count_field: Option<String>,
+    pub capacity: u32,
+}
 
-            read_funs.extend(quote! {
-                pub fn #alt_enum_read<R>(reader : &mut R) -> Result<Self, Error> where R : Read {
-                    Ok(#alt_pc_a::#alt_enum(#alt_struct::read(reader)?))
-                }
-            });
+impl RepeatedMember {
+    pub fn new(name: &str, bytes: u32, count_from: &str, capacity: u32) -> Self {
+        let name = String::from(name);
+        let count_from = String::from(count_from);
+        Self {
+            name,
+            bytes,
+            count_from,
+            count_field: None,
+            capacity,
+        }
+    }
+}
+
+/// A condition on the fields of a bitfield, e.g. marking an optional structure as present
+/// depending on several flags and enumerated fields. Negated using `!`.
+#[derive(Clone, Debug)]
+pub enum Condition {
+    /// The single bit field is set.
+    Set(String),
+    /// The enumerated field holds one of the values.
+    Is(String, Vec<String>),
+    /// The numeric field is not zero.
+    NonZero(String),
+    /// The condition does not hold.
+    Not(Box<Condition>),
+    /// Every condition holds.
+    All(Vec<Condition>),
+    /// Any of the conditions holds.
+    Any(Vec<Condition>),
+}
+
+impl Condition {
+    /// The single bit field `field` is set.
+    pub fn set(field: &str) -> Self {
+        Condition::Set(String::from(field))
+    }
+
+    /// The enumerated field `field` holds one of `values`.
+    pub fn is(field: &str, values: &[&str]) -> Self {
+        let values = values.iter().map(|value| String::from(*value)).collect();
+        Condition::Is(String::from(field), values)
+    }
+
+    /// The numeric field `field` is not zero, e.g. a count of elements following it.
+    pub fn non_zero(field: &str) -> Self {
+        Condition::NonZero(String::from(field))
+    }
+
+    /// Every condition of `conditions` holds.
+    pub fn all(conditions: Vec<Condition>) -> Self {
+        Condition::All(conditions)
+    }
+
+    /// Any condition of `conditions` holds.
+    pub fn any(conditions: Vec<Condition>) -> Self {
+        Condition::Any(conditions)
+    }
+
+    /// Render an expression whether the condition holds for the reader `r` of the `bitfield`.
+    pub(crate) fn render(&self, r: &TokenStream, bitfield: &str, naming: &Naming) -> TokenStream {
+        let span = Span::call_site();
+        let fields = naming.names_of(bitfield);
+        match self {
+            Condition::Set(field) => {
+                let field_sc = util::ident(&field.to_sanitized_snake_case(&fields), span);
+                quote! { #r.#field_sc().bit_is_set() }
+            }
+            Condition::Is(field, values) => {
+                let field_sc = util::ident(&field.to_sanitized_snake_case(&fields), span);
+                let scope = naming.values_of(bitfield, field);
+                let is_values = values.iter().map(|value| {
+                    util::ident(
+                        &format!("is_{}", value.to_sanitized_snake_case(&scope)),
+                        span,
+                    )
+                });
+                quote! { #(#r.#field_sc().#is_values())||* }
+            }
+            Condition::NonZero(field) => {
+                let field_sc = util::ident(&field.to_sanitized_snake_case(&fields), span);
+                quote! { #r.#field_sc() != 0 }
+            }
+            Condition::Not(condition) => {
+                let condition = condition.render_operand(r, bitfield, naming);
+                quote! { !#condition }
+            }
+            Condition::All(conditions) if conditions.is_empty() => quote! { true },
+            Condition::Any(conditions) if conditions.is_empty() => quote! { false },
+            Condition::All(conditions) => {
+                let conditions = conditions
+                    .iter()
+                    .map(|condition| condition.render_operand(r, bitfield, naming));
+                quote! { #(#conditions)&&* }
+            }
+            Condition::Any(conditions) => {
+                let conditions = conditions
+                    .iter()
+                    .map(|condition| condition.render_operand(r, bitfield, naming));
+                quote! { #(#conditions)||* }
+            }
+        }
+    }
+
+    /// Render the condition like [`Condition::render`] as the operand of another one, in
+    /// parentheses unless it is a single call of the reader.
+    fn render_operand(&self, r: &TokenStream, bitfield: &str, naming: &Naming) -> TokenStream {
+        let condition = self.render(r, bitfield, naming);
+        match self {
+            Condition::Is(_, values) if values.len() > 1 => quote! { (#condition) },
+            Condition::NonZero(_) => quote! { (#condition) },
+            Condition::All(conditions) | Condition::Any(conditions) if conditions.len() > 1 => {
+                quote! { (#condition) }
+            }
+            _ => condition,
+        }
+    }
+
+    /// Describe the condition for the documentation, e.g. "`Security_enabled` is set".
+    pub fn describe(&self) -> String {
+        let nested = |condition: &Condition| match condition {
+            Condition::All(_) | Condition::Any(_) => format!("({})", condition.describe()),
+            _ => condition.describe(),
+        };
+        match self {
+            Condition::Set(field) => format!("`{}` is set", field),
+            Condition::Is(field, values) => {
+                let values = values
+                    .iter()
+                    .map(|value| format!("`{}`", value))
+                    .collect::<Vec<_>>();
+                format!("`{}` is {}", field, values.join(" or "))
+            }
+            Condition::NonZero(field) => format!("`{}` is not zero", field),
+            Condition::Not(condition) => match condition.as_ref() {
+                Condition::Set(field) => format!("`{}` is clear", field),
+                Condition::Is(field, values) => {
+                    let values = values
+                        .iter()
+                        .map(|value| format!("`{}`", value))
+                        .collect::<Vec<_>>();
+                    format!("`{}` is not {}", field, values.join(" or "))
+                }
+                Condition::NonZero(field) => format!("`{}` is zero", field),
+                condition => format!("not {}", nested(condition)),
+            },
+            Condition::All(conditions) => conditions
+                .iter()
+                .map(nested)
+                .collect::<Vec<_>>()
+                .join(" and "),
+            Condition::Any(conditions) => conditions
+                .iter()
+                .map(nested)
+                .collect::<Vec<_>>()
+                .join(" or "),
+        }
+    }
+}
+
+impl std::ops::Not for Condition {
+    type Output = Condition;
+
+    fn not(self) -> Condition {
+        Condition::Not(Box::new(self))
+    }
+}
+
+/// A structure which is only present on the wire when a single bit flag is set, when an
+/// enumerated field holds one of `values`, or when a `condition` on the bitfield holds.
+#[derive(Clone)]
+pub struct OptionalMember {
+    pub name: String,
+    pub structure: String,
+    pub flag: Discriminator,
+    /// Enumerated values of the flag marking the structure as present, the first of which is
+    /// written when it is. Empty if the flag is a single bit.
+    pub values: Vec<String>,
+    /// Condition on the fields of the bitfield of `flag` marking the structure as present,
+    /// which replaces the field of `flag`. The fields are left to the user when writing.
+    pub condition: Option<Condition>,
+    /// Whether the structure is `Copy`.
+    pub copy: bool,
+}
+
+impl OptionalMember {
+    pub fn new(name: &str, structure: &str, flag: Discriminator) -> Self {
+        let name = String::from(name);
+        let structure = String::from(structure);
+        Self {
+            name,
+            structure,
+            flag,
+            values: vec![],
+            condition: None,
+            copy: true,
+        }
+    }
+
+    /// Render an expression whether the reader `r` of the `bitfield` holding the flag marks
+    /// the structure as present.
+    pub(crate) fn render_present(
+        &self,
+        r: TokenStream,
+        bitfield: &str,
+        naming: &Naming,
+    ) -> TokenStream {
+        let span = Span::call_site();
+        if let Some(condition) = &self.condition {
+            return condition.render(&r, bitfield, naming);
+        }
+        let field_sc = util::ident(
+            &self
+                .flag
+                .field
+                .to_sanitized_snake_case(&naming.names_of(bitfield)),
+            span,
+        );
+        if self.values.is_empty() {
+            return quote! { #r.#field_sc().bit_is_set() };
+        }
+        let values = naming.values_of(bitfield, &self.flag.field);
+        let is_values = self.values.iter().map(|value| {
+            util::ident(
+                &format!("is_{}", value.to_sanitized_snake_case(&values)),
+                span,
+            )
+        });
+        quote! { #(#r.#field_sc().#is_values())||* }
+    }
+}
+
+/// A checksum over the members from `from` up to the checksum itself. It is computed when
+/// writing and verified when reading, so is not stored in the structure.
+#[derive(Clone)]
+pub struct ChecksumMember {
+    pub name: String,
+    pub from: String,
+    pub algorithm: ChecksumAlgorithm,
+    /// Byte appended to the covered members, selected by another member.
+    pub extra: Option<ChecksumExtra>,
+}
+
+impl ChecksumMember {
+    pub fn new(name: &str, from: &str, algorithm: ChecksumAlgorithm) -> Self {
+        let name = String::from(name);
+        let from = String::from(from);
+        Self {
+            name,
+            from,
+            algorithm,
+            extra: None,
+        }
+    }
+}
+
+/// An offset into an enclosing buffer such as a whole message, marked by the `marker` in its
+/// `marker_bits` most significant bits, e.g. a compressed name in DNS. The structure holds the
+/// offset without the marker, which is checked when reading and added when writing.
+#[derive(Clone)]
+pub struct OffsetMember {
+    pub name: String,
+    pub bytes: u32,
+    pub marker_bits: u32,
+    pub marker: u64,
+}
+
+impl OffsetMember {
+    pub fn new(name: &str, bytes: u32, marker_bits: u32, marker: u64) -> Self {
+        let name = String::from(name);
+        Self {
+            name,
+            bytes,
+            marker_bits,
+            marker,
+        }
+    }
+
+    /// Number of bits of the offset.
+    pub fn offset_bits(&self) -> u32 {
+        self.bytes * BITS_PER_BYTE - self.marker_bits
+    }
+
+    /// Largest offset.
+    pub fn max(&self) -> u64 {
+        (1 << self.offset_bits()) - 1
+    }
+}
+
+/// A constant such as a sync word or protocol identifier. It is always written with `value`
+/// and reading fails on any other value, so is not stored in the structure.
+#[derive(Clone)]
+pub struct ConstantMember {
+    pub name: String,
+    pub bytes: u32,
+    pub value: u64,
+}
+
+impl ConstantMember {
+    pub fn new(name: &str, bytes: u32, value: u64) -> Self {
+        let name = String::from(name);
+        Self { name, bytes, value }
+    }
+}
+
+#[derive(Clone)]
+pub enum StructMember {
+    BitfieldMember(BitfieldMember),
+    PrimitiveMember(PrimitiveMember),
+    AlternativesMember(AlternativesMember),
+    LengthMember(LengthMember),
+    BytesMember(BytesMember),
+    ArrayMember(ArrayMember),
+    RepeatedMember(RepeatedMember),
+    OptionalMember(OptionalMember),
+    ChecksumMember(ChecksumMember),
+    ConstantMember(ConstantMember),
+    NibbleVarintMember(NibbleVarintMember),
+    OffsetMember(OffsetMember),
+    ExtendedMember(ExtendedMember),
+    BitsMember(BitsMember),
+    VarintMember(VarintMember),
+    StringMember(StringMember),
+    BcdMember(BcdMember),
+    TimestampMember(TimestampMember),
+    EuiMember(EuiMember),
+}
+
+impl StructMember {
+    pub fn name(&self) -> &str {
+        match self {
+            StructMember::PrimitiveMember(mem) => &mem.name,
+            StructMember::BitfieldMember(mem) => &mem.name,
+            StructMember::AlternativesMember(mem) => &mem.name,
+            StructMember::LengthMember(mem) => &mem.name,
+            StructMember::BytesMember(mem) => &mem.name,
+            StructMember::ArrayMember(mem) => &mem.name,
+            StructMember::RepeatedMember(mem) => &mem.name,
+            StructMember::OptionalMember(mem) => &mem.name,
+            StructMember::ChecksumMember(mem) => &mem.name,
+            StructMember::ConstantMember(mem) => &mem.name,
+            StructMember::NibbleVarintMember(mem) => &mem.name,
+            StructMember::OffsetMember(mem) => &mem.name,
+            StructMember::ExtendedMember(mem) => &mem.name,
+            StructMember::BitsMember(mem) => &mem.name,
+            StructMember::VarintMember(mem) => &mem.name,
+            StructMember::StringMember(mem) => &mem.name,
+            StructMember::BcdMember(mem) => &mem.name,
+            StructMember::TimestampMember(mem) => &mem.name,
+            StructMember::EuiMember(mem) => &mem.name,
+        }
+    }
+
+    /// Description of the member, if it has one.
+    pub fn desc(&self) -> Option<&str> {
+        match self {
+            StructMember::PrimitiveMember(mem) if !mem.desc.is_empty() => Some(&mem.desc),
+            StructMember::BitsMember(mem) if !mem.desc.is_empty() => Some(&mem.desc),
+            StructMember::BcdMember(mem) if !mem.desc.is_empty() => Some(&mem.desc),
+            StructMember::TimestampMember(mem) if !mem.desc.is_empty() => Some(&mem.desc),
+            StructMember::EuiMember(mem) if !mem.desc.is_empty() => Some(&mem.desc),
+            _ => None,
+        }
+    }
+
+    /// Number of bytes of the member on the wire, unless it depends on the value of the member.
+    pub fn fixed_size(&self) -> Option<u32> {
+        match self {
+            StructMember::BitfieldMember(mem) => Some(mem.bytes),
+            StructMember::PrimitiveMember(mem) => Some(mem.bytes),
+            StructMember::LengthMember(mem) => Some(mem.bytes),
+            StructMember::ConstantMember(mem) => Some(mem.bytes),
+            StructMember::ChecksumMember(mem) => Some(mem.algorithm.bytes()),
+            StructMember::ArrayMember(mem) => Some(mem.bytes * mem.count),
+            StructMember::OffsetMember(mem) => Some(mem.bytes),
+            StructMember::BitsMember(mem) => Some(mem.bytes()),
+            StructMember::BcdMember(mem) => Some(mem.bytes),
+            StructMember::TimestampMember(mem) => Some(mem.bytes),
+            StructMember::EuiMember(mem) => Some(mem.bytes),
+            StructMember::StringMember(StringMember {
+                kind: StringKind::Fixed,
+                capacity,
+                ..
+            }) => Some(*capacity),
+            StructMember::BytesMember(_)
+            | StructMember::RepeatedMember(_)
+            | StructMember::AlternativesMember(_)
+            | StructMember::OptionalMember(_)
+            | StructMember::NibbleVarintMember(_)
+            | StructMember::ExtendedMember(_)
+            | StructMember::VarintMember(_)
+            | StructMember::StringMember(_) => None,
+        }
+    }
+
+    /// Accessors generated for the member, which only primitive and bitfield members restrict.
+    pub fn access(&self) -> Access {
+        match self {
+            StructMember::BitfieldMember(mem) => mem.access,
+            StructMember::PrimitiveMember(mem) => mem.access,
+            _ => Access::ReadWrite,
+        }
+    }
+
+    /// Largest value the member can be initialized with instead of zero, if it can have a
+    /// default at all.
+    pub fn default_max(&self) -> Option<u64> {
+        match self {
+            StructMember::PrimitiveMember(PrimitiveMember { bytes, .. })
+            | StructMember::BitfieldMember(BitfieldMember { bytes, .. })
+                if *bytes <= 8 =>
+            {
+                Some(u64::MAX >> (64 - bytes * BITS_PER_BYTE))
+            }
+            StructMember::BitsMember(mem) => Some(mem.max()),
+            StructMember::VarintMember(mem) => Some(mem.max()),
+            StructMember::BcdMember(mem) => Some(mem.max()),
+            StructMember::TimestampMember(mem) => Some(mem.max()),
+            StructMember::OffsetMember(mem) => Some(mem.max()),
+            _ => None,
+        }
+    }
+
+    /// Whether the member can be stored in a `Copy` structure.
+    pub fn is_copy(&self) -> bool {
+        match self {
+            StructMember::RepeatedMember(_) | StructMember::StringMember(_) => false,
+            StructMember::OptionalMember(mem) => mem.copy,
+            _ => true,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AlternativeOptions {
+    pub name: String,
+    pub default: String,
+    pub alternatives: Vec<String>,
+    /// Maps an alternative to the enumerated value of a discriminator field selecting it.
+    pub discriminants: HashMap<String, String>,
+}
+
+#[derive(Default)]
+pub struct Alternatives {
+    pub map: HashMap<String, AlternativeOptions>,
+}
+
+impl Alternatives {
+    pub fn new() -> Self {
+        Self {
+            map: HashMap::new(),
+        }
+    }
+
+    pub fn insert(mut self, options: &AlternativeOptions) -> Self {
+        let key = options.name.clone();
+        self.map.insert(key, options.clone());
+        self
+    }
+
+    pub fn insert_new_option<F>(mut self, key: &str, default: &Structure, mut f: F) -> Self
+    where
+        F: FnMut(AlternativeOptions) -> AlternativeOptions,
+    {
+        let options = AlternativeOptions::new(key, default);
+        let options = f(options);
+        let key = String::from(key);
+        self.map.insert(key, options);
+        self
+    }
+
+    /// Declare the names of the alternatives in the order of their names, each followed by
+    /// the structures it holds, in the `naming`.
+    pub fn declare(&self, naming: &mut Naming) {
+        let mut keys = self.map.keys().collect::<Vec<_>>();
+        keys.sort();
+        for key in keys {
+            let options = &self.map[key];
+            naming.declare(Namespace::Types, [key.as_str()]);
+            naming.declare(
+                Namespace::Types,
+                options.alternatives.iter().map(String::as_str),
+            );
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<&AlternativeOptions> {
+        let v = self.map.get(&String::from(name));
+        match v {
+            None => Err(anyhow::Error::msg("Could not find alternative.")),
+            Some(v) => Ok(v),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Structure {
+    pub name: String,
+    pub desc: String,
+    pub members: Vec<StructMember>,
+    /// Derives overriding the ones of the generated file.
+    pub derives: Option<Derives>,
+    /// Bitfields defined along with the structure, which are rendered as modules of the module
+    /// holding it.
+    pub bitfields: Vec<BitField>,
+    /// Members which the generated builder must set before building the structure.
+    pub required: Vec<String>,
+    /// Values of integer members in the structure returned by `new()`, which are zero
+    /// otherwise, or the reset value of the bitfield for bitfield members.
+    pub defaults: Vec<(String, u64)>,
+    /// Byte order of the integer and bitfield members on the wire.
+    pub byte_order: ByteOrder,
+    /// Versions of the protocol having the structure.
+    pub versions: Versions,
+    /// Versions having the members which are not part of all versions of the structure.
+    pub member_versions: Vec<(String, Versions)>,
+    /// Structure whose members this structure starts with, see [`Structure::with_base`].
+    pub base: Option<Box<Structure>>,
+    /// Name of the records following the structure up to the end of the buffer along with
+    /// their structure, see [`Structure::with_trailing`].
+    pub trailing: Option<(String, String)>,
+    /// How the byte payloads are held, see [`Structure::with_payloads`].
+    pub payloads: Payloads,
+}
+
+/// The versions of a protocol having a structure or member, from the version introducing it
+/// up to the version removing it, named as in [`GenTree::set_versions`].
+///
+/// [`GenTree::set_versions`]: crate::tree::GenTree::set_versions
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Versions {
+    pub introduced: Option<String>,
+    pub removed: Option<String>,
+}
+
+impl Versions {
+    /// Whether the version `versions[idx]` is one of these versions.
+    pub fn contains(&self, versions: &[String], idx: usize) -> Result<bool> {
+        let position = |version: &String| {
+            versions
+                .iter()
+                .position(|known| known == version)
+                .with_context(|| format!("unknown version `{}`", version))
+        };
+        let introduced = self.introduced.as_ref().map(position).transpose()?;
+        let removed = self.removed.as_ref().map(position).transpose()?;
+        Ok(introduced.is_none_or(|introduced| introduced <= idx)
+            && removed.is_none_or(|removed| idx < removed))
+    }
+}
+
+impl Type for Structure {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn is_copy(&self) -> bool {
+        Structure::is_copy(self)
+    }
+}
+
+impl Structure {
+    pub fn new(name: &str) -> Structure {
+        let name = String::from(name);
+        Structure {
+            name,
+            desc: String::new(),
+            members: vec![],
+            derives: None,
+            bitfields: vec![],
+            required: vec![],
+            defaults: vec![],
+            byte_order: ByteOrder::default(),
+            versions: Versions::default(),
+            member_versions: vec![],
+            base: None,
+            trailing: None,
+            payloads: Payloads::default(),
+        }
+    }
+
+    /// Document the generated structure with `desc`.
+    pub fn with_desc(mut self, desc: &str) -> Self {
+        self.desc = String::from(desc);
+        self
+    }
+
+    /// Derive `derives` on this structure instead of the derives of the generated file.
+    pub fn with_derives(mut self, derives: Derives) -> Self {
+        self.derives = Some(derives);
+        self
+    }
+
+    /// Serialize the integer members, i.e. bitfield, primitive, length, constant, checksum,
+    /// array and repeated members as well as the extended bytes of nibble-extended varints, in
+    /// byte order `byte_order`.
+    pub fn with_byte_order(mut self, byte_order: ByteOrder) -> Self {
+        self.byte_order = byte_order;
+        self
+    }
+
+    /// Require the member `name` to be set by the generated `builder` before it builds the
+    /// structure, which is checked at compile time. The builder is only generated for
+    /// structures requiring members.
+    pub fn require(mut self, name: &str) -> Self {
+        self.required.push(String::from(name));
+        self
+    }
+
+    /// Initialize the member `name` with `value` in `new()` and `Default`, e.g. with the
+    /// version of the protocol, instead of zero or the reset value of a bitfield. Only integer
+    /// members such as primitive, bitfield, bit, varint, BCD, timestamp and offset members can
+    /// have a default.
+    pub fn with_default(mut self, name: &str, value: u64) -> Self {
+        self.defaults.retain(|(member, _)| member != name);
+        self.defaults.push((String::from(name), value));
+        self
+    }
+
+    /// Start the structure with the members of `base`, such as the header shared by the
+    /// messages of a protocol, followed by the members added to the structure. The required
+    /// members, defaults, byte order and payloads of `base` carry over. The generated structure converts
+    /// into its base if it has a fixed size, and its views give views of the base over the same
+    /// bytes.
+    pub fn with_base(mut self, base: &Structure) -> Self {
+        self.members.splice(0..0, base.members.iter().cloned());
+        self.bitfields.splice(0..0, base.bitfields.iter().cloned());
+        self.required.splice(0..0, base.required.iter().cloned());
+        for (name, value) in &base.defaults {
+            if self.default_of(name).is_none() {
+                self.defaults.push((name.clone(), *value));
+            }
+        }
+        self.byte_order = base.byte_order;
+        self.payloads = base.payloads;
+        self.base = Some(Box::new(base.clone()));
+        self
+    }
+
+    /// Follow the structure with zero or more `record` structures named `name` up to the end of
+    /// the buffer, such as the entries of a neighbor list. The records are not members of the
+    /// generated structure but read by an iterator over the remaining bytes, which is returned
+    /// by `read_with_<name>` and the `<name>()` of the view.
+    pub fn with_trailing(mut self, name: &str, record: &Structure) -> Self {
+        self.trailing = Some((String::from(name), record.name.clone()));
+        self
+    }
+
+    /// Hold the byte payloads as `payloads`, e.g. in a `heapless::Vec` of the capacity of each
+    /// payload, which still needs no allocator. Structures holding `heapless` or `alloc`
+    /// payloads are not `Copy`.
+    pub fn with_payloads(mut self, payloads: Payloads) -> Self {
+        self.payloads = payloads;
+        self
+    }
+
+    /// Only generate the structure for `version` and the versions after it.
+    pub fn with_introduced(mut self, version: &str) -> Self {
+        self.versions.introduced = Some(String::from(version));
+        self
+    }
+
+    /// Only generate the structure for the versions before `version`.
+    pub fn with_removed(mut self, version: &str) -> Self {
+        self.versions.removed = Some(String::from(version));
+        self
+    }
+
+    /// Only generate the member `name` for `version` and the versions after it. The structure
+    /// gets a module for each of its layouts, see [`GenTree::set_versions`].
+    ///
+    /// [`GenTree::set_versions`]: crate::tree::GenTree::set_versions
+    pub fn introduce(mut self, name: &str, version: &str) -> Self {
+        self.member_versions_mut(name).introduced = Some(String::from(version));
+        self
+    }
+
+    /// Only generate the member `name` for the versions before `version`.
+    pub fn remove(mut self, name: &str, version: &str) -> Self {
+        self.member_versions_mut(name).removed = Some(String::from(version));
+        self
+    }
+
+    fn member_versions_mut(&mut self, name: &str) -> &mut Versions {
+        let idx = match self
+            .member_versions
+            .iter()
+            .position(|(member, _)| member == name)
+        {
+            Some(idx) => idx,
+            None => {
+                self.member_versions
+                    .push((String::from(name), Versions::default()));
+                self.member_versions.len() - 1
+            }
+        };
+        &mut self.member_versions[idx].1
+    }
+
+    /// Whether the structure or any of its members is not part of all versions.
+    pub fn is_versioned(&self) -> bool {
+        self.versions != Versions::default() || !self.member_versions.is_empty()
+    }
+
+    /// The structure as of the version `versions[idx]`, holding only the members of that
+    /// version, or `None` if the version does not have the structure.
+    pub fn at_version(&self, versions: &[String], idx: usize) -> Result<Option<Structure>> {
+        if !self.versions.contains(versions, idx)? {
+            return Ok(None);
+        }
+        let mut removed = vec![];
+        for (name, member_versions) in &self.member_versions {
+            if !self.members.iter().any(|mem| mem.name() == name) {
+                bail!(
+                    "`{}` has versions for the unknown member `{}`",
+                    self.name,
+                    name
+                );
+            }
+            if !member_versions.contains(versions, idx)? {
+                removed.push(name.as_str());
+            }
+        }
+
+        let mut structure = self.clone();
+        structure
+            .members
+            .retain(|mem| !removed.contains(&mem.name()));
+        structure
+            .required
+            .retain(|name| !removed.contains(&name.as_str()));
+        structure
+            .defaults
+            .retain(|(name, _)| !removed.contains(&name.as_str()));
+        structure.versions = Versions::default();
+        structure.member_versions.clear();
+        Ok(Some(structure))
+    }
+
+    /// The value of the member `name` in `new()`, if it has a default.
+    pub fn default_of(&self, name: &str) -> Option<u64> {
+        self.defaults
+            .iter()
+            .find(|(member, _)| member == name)
+            .map(|(_, value)| *value)
+    }
+
+    /// Add a bitfield member `name` of the bitfield defined by `f`, which is named after the
+    /// member and rendered along with the structure.
+    pub fn add_bitfield_inline<F>(mut self, name: &str, f: F) -> Self
+    where
+        F: FnOnce(BitField) -> BitField,
+    {
+        let bitfield = f(BitField::new(name, ""));
+        // an invalid layout is reported when checking the bitfields
+        let bytes = bitfield.bytes().unwrap_or(0);
+        self.bitfields.push(bitfield);
+        self.add_bitfield(name, name, bytes)
+    }
+
+    /// Add a bitfield member `name` of `bitfield`, which takes as many bytes as its fields and
+    /// reserved bits need.
+    pub fn add_bitfield_of(self, name: &str, bitfield: &BitField) -> Self {
+        // an invalid layout is reported when checking the bitfields
+        let bytes = bitfield.bytes().unwrap_or(0);
+        self.add_bitfield(name, &bitfield.name, bytes)
+    }
+
+    pub fn add_bitfield(mut self, name: &str, bitfield: &str, bytes: u32) -> Self {
+        let member = BitfieldMember::new(name, bitfield, bytes);
+        self.members.push(StructMember::BitfieldMember(member));
+        self
+    }
+
+    /// Add a bitfield member with restricted accessors, e.g. without `modify` for a bitfield
+    /// which must never be set by the host.
+    pub fn add_bitfield_access(
+        mut self,
+        name: &str,
+        bitfield: &str,
+        bytes: u32,
+        access: Access,
+    ) -> Self {
+        let mut member = BitfieldMember::new(name, bitfield, bytes);
+        member.access = access;
+        self.members.push(StructMember::BitfieldMember(member));
+        self
+    }
+
+    pub fn add_prim_field(self, name: &str, bytes: u32) -> Self {
+        self.add_prim_field_desc(name, "", bytes)
+    }
+
+    pub fn add_prim_field_desc(mut self, name: &str, desc: &str, bytes: u32) -> Self {
+        let member = PrimitiveMember::new_desc(name, desc, bytes);
+        self.members.push(StructMember::PrimitiveMember(member));
+        self
+    }
+
+    /// Add a primitive member with restricted accessors, e.g. without `set` for a member which
+    /// must never be set by the host.
+    pub fn add_prim_field_access(
+        mut self,
+        name: &str,
+        desc: &str,
+        bytes: u32,
+        access: Access,
+    ) -> Self {
+        let mut member = PrimitiveMember::new_desc(name, desc, bytes);
+        member.access = access;
+        self.members.push(StructMember::PrimitiveMember(member));
+        self
+    }
+
+    pub fn add_u8_field(self, name: &str) -> Self {
+        self.add_prim_field(name, 1)
+    }
+
+    pub fn add_u8_field_desc(self, name: &str, desc: &str) -> Self {
+        self.add_prim_field_desc(name, desc, 1)
+    }
+
+    pub fn add_u16_field(self, name: &str) -> Self {
+        self.add_prim_field(name, 2)
+    }
+
+    pub fn add_u16_field_desc(self, name: &str, desc: &str) -> Self {
+        self.add_prim_field_desc(name, desc, 2)
+    }
+
+    pub fn add_u32_field(self, name: &str) -> Self {
+        self.add_prim_field(name, 4)
+    }
+
+    pub fn add_u32_field_desc(self, name: &str, desc: &str) -> Self {
+        self.add_prim_field_desc(name, desc, 4)
+    }
+
+    pub fn add_u64_field(self, name: &str) -> Self {
+        self.add_prim_field(name, 8)
+    }
+
+    pub fn add_u64_field_desc(self, name: &str, desc: &str) -> Self {
+        self.add_prim_field_desc(name, desc, 8)
+    }
+
+    /// Add a 24 bit field, stored as a `u32` but serialized as 3 bytes.
+    pub fn add_u24_field(self, name: &str) -> Self {
+        self.add_prim_field(name, 3)
+    }
+
+    pub fn add_u24_field_desc(self, name: &str, desc: &str) -> Self {
+        self.add_prim_field_desc(name, desc, 3)
+    }
+
+    /// Add a 40 bit field, stored as a `u64` but serialized as 5 bytes.
+    pub fn add_u40_field(self, name: &str) -> Self {
+        self.add_prim_field(name, 5)
+    }
+
+    pub fn add_u40_field_desc(self, name: &str, desc: &str) -> Self {
+        self.add_prim_field_desc(name, desc, 5)
+    }
+
+    /// Add a 48 bit field, stored as a `u64` but serialized as 6 bytes.
+    pub fn add_u48_field(self, name: &str) -> Self {
+        self.add_prim_field(name, 6)
+    }
+
+    pub fn add_u48_field_desc(self, name: &str, desc: &str) -> Self {
+        self.add_prim_field_desc(name, desc, 6)
+    }
+
+    /// Add a 56 bit field, stored as a `u64` but serialized as 7 bytes.
+    pub fn add_u56_field(self, name: &str) -> Self {
+        self.add_prim_field(name, 7)
+    }
+
+    pub fn add_u56_field_desc(self, name: &str, desc: &str) -> Self {
+        self.add_prim_field_desc(name, desc, 7)
+    }
+
+    pub fn add_alt_field(mut self, name: &str, alternatives: &AlternativeOptions) -> Self {
+        let member = AlternativesMember::new(name, &alternatives.name);
+        self.members.push(StructMember::AlternativesMember(member));
+        self
+    }
+
+    /// Add an alternatives field whose active alternative is selected by the enumerated
+    /// field `field` of the bitfield member `bitfield`.
+    pub fn add_alt_field_discriminated(
+        mut self,
+        name: &str,
+        alternatives: &AlternativeOptions,
+        bitfield: &str,
+        field: &str,
+    ) -> Self {
+        let member = AlternativesMember::new(name, &alternatives.name)
+            .discriminated(Discriminator::new(bitfield, field));
+        self.members.push(StructMember::AlternativesMember(member));
+        self
+    }
+
+    /// Add an array of `count` integers, each serialized as `bytes` bytes.
+    pub fn add_array_field(mut self, name: &str, bytes: u32, count: u32) -> Self {
+        let member = ArrayMember::new(name, bytes, count);
+        self.members.push(StructMember::ArrayMember(member));
+        self
+    }
+
+    /// Add up to `capacity` integers of `bytes` bytes each, where the number of elements is
+    /// stored in the length field `count_from`.
+    pub fn add_repeated_field(
+        mut self,
+        name: &str,
+        bytes: u32,
+        count_from: &str,
+        capacity: u32,
+    ) -> Self {
+        let member = RepeatedMember::new(name, bytes, count_from, capacity);
+        self.members.push(StructMember::RepeatedMember(member));
+        self
+    }
+
+    /// Add up to `capacity` integers of `bytes` bytes each, where the number of elements is
+    /// held by the numeric field `field` of the bitfield member `bitfield`, e.g. the counts of
+    /// a specification byte preceding several lists. The field is updated when writing, so
+    /// `capacity` must fit it.
+    pub fn add_repeated_field_counted_by(
+        mut self,
+        name: &str,
+        bytes: u32,
+        bitfield: &str,
+        field: &str,
+        capacity: u32,
+    ) -> Self {
+        let mut member = RepeatedMember::new(name, bytes, bitfield, capacity);
+        member.count_field = Some(String::from(field));
+        self.members.push(StructMember::RepeatedMember(member));
+        self
+    }
+
+    /// Add a structure which is only present when the single bit enumerated field `field` of
+    /// the bitfield member `bitfield` is set.
+    pub fn add_optional_field<T>(
+        mut self,
+        name: &str,
+        structure: &T,
+        bitfield: &str,
+        field: &str,
+    ) -> Self
+    where
+        T: Type,
+    {
+        let flag = Discriminator::new(bitfield, field);
+        let mut member = OptionalMember::new(name, structure.name(), flag);
+        member.copy = structure.is_copy();
+        self.members.push(StructMember::OptionalMember(member));
+        self
+    }
+
+    /// Add a structure which is only present when the enumerated field `field` of the bitfield
+    /// member `bitfield` holds one of `values`, e.g. a header announced by a type field. The
+    /// field is set to the first of `values` when writing the structure, and must hold another
+    /// value when it is absent.
+    pub fn add_optional_field_when<T>(
+        mut self,
+        name: &str,
+        structure: &T,
+        bitfield: &str,
+        field: &str,
+        values: &[&str],
+    ) -> Self
+    where
+        T: Type,
+    {
+        let flag = Discriminator::new(bitfield, field);
+        let mut member = OptionalMember::new(name, structure.name(), flag);
+        member.values = values.iter().map(|value| String::from(*value)).collect();
+        member.copy = structure.is_copy();
+        self.members.push(StructMember::OptionalMember(member));
+        self
+    }
+
+    /// Add a structure which is only present when `condition` holds for the bitfield member
+    /// `bitfield`, e.g. an identifier depending on several addressing modes. Unlike a flag the
+    /// fields of the condition are not updated when writing, so they must agree with whether
+    /// the structure is held.
+    pub fn add_optional_field_if<T>(
+        mut self,
+        name: &str,
+        structure: &T,
+        bitfield: &str,
+        condition: Condition,
+    ) -> Self
+    where
+        T: Type,
+    {
+        let flag = Discriminator::new(bitfield, "");
+        let mut member = OptionalMember::new(name, structure.name(), flag);
+        member.condition = Some(condition);
+        member.copy = structure.is_copy();
+        self.members.push(StructMember::OptionalMember(member));
+        self
+    }
+
+    /// Add a checksum computed using `algorithm` over all members starting at `from`.
+    pub fn add_checksum_field(
+        mut self,
+        name: &str,
+        from: &str,
+        algorithm: ChecksumAlgorithm,
+    ) -> Self {
+        let member = ChecksumMember::new(name, from, algorithm);
+        self.members.push(StructMember::ChecksumMember(member));
+        self
+    }
+
+    /// Add a checksum like [`Structure::add_checksum_field`] whose input ends with the byte
+    /// `extra` selects by the value of its key. Reading and writing fail with
+    /// `Error::InvalidEnumValue` when the key selects no byte.
+    pub fn add_checksum_field_extra(
+        mut self,
+        name: &str,
+        from: &str,
+        algorithm: ChecksumAlgorithm,
+        extra: &ChecksumExtra,
+    ) -> Self {
+        let mut member = ChecksumMember::new(name, from, algorithm);
+        member.extra = Some(extra.clone());
+        self.members.push(StructMember::ChecksumMember(member));
+        self
+    }
+
+    /// Add an offset of `bytes` bytes into an enclosing buffer, marked by `marker` in its
+    /// `marker_bits` most significant bits.
+    pub fn add_offset_field(
+        mut self,
+        name: &str,
+        bytes: u32,
+        marker_bits: u32,
+        marker: u64,
+    ) -> Self {
+        let member = OffsetMember::new(name, bytes, marker_bits, marker);
+        self.members.push(StructMember::OffsetMember(member));
+        self
+    }
+
+    /// Add a chain of up to `capacity` bytes which continues as long as the most significant
+    /// bit of its last byte is set.
+    pub fn add_extended_field(mut self, name: &str, capacity: u32) -> Self {
+        let member = ExtendedMember::new(name, capacity);
+        self.members.push(StructMember::ExtendedMember(member));
+        self
+    }
+
+    /// Add an unsigned LEB128 varint holding values of up to `bits` bits.
+    pub fn add_varint_field(mut self, name: &str, bits: u32) -> Self {
+        let member = VarintMember::new(name, bits);
+        self.members.push(StructMember::VarintMember(member));
+        self
+    }
+
+    /// Add an unsigned integer of `bytes` bytes in packed BCD.
+    pub fn add_bcd_field(self, name: &str, bytes: u32) -> Self {
+        self.add_bcd_field_desc(name, "", bytes)
+    }
+
+    pub fn add_bcd_field_desc(mut self, name: &str, desc: &str, bytes: u32) -> Self {
+        let member = BcdMember::new(name, desc, bytes);
+        self.members.push(StructMember::BcdMember(member));
+        self
+    }
+
+    /// Add an EUI-48 address such as a MAC address.
+    pub fn add_eui48_field(self, name: &str) -> Self {
+        self.add_eui48_field_desc(name, "")
+    }
+
+    pub fn add_eui48_field_desc(mut self, name: &str, desc: &str) -> Self {
+        let member = EuiMember::new(name, desc, 6);
+        self.members.push(StructMember::EuiMember(member));
+        self
+    }
+
+    /// Add an EUI-64 address such as the extended address of IEEE 802.15.4.
+    pub fn add_eui64_field(self, name: &str) -> Self {
+        self.add_eui64_field_desc(name, "")
+    }
+
+    pub fn add_eui64_field_desc(mut self, name: &str, desc: &str) -> Self {
+        let member = EuiMember::new(name, desc, 8);
+        self.members.push(StructMember::EuiMember(member));
+        self
+    }
+
+    /// Add a timestamp of `bytes` bytes counting `ticks_per_second` ticks per second since
+    /// `epoch`.
+    pub fn add_timestamp_field(
+        self,
+        name: &str,
+        bytes: u32,
+        epoch: Epoch,
+        ticks_per_second: u32,
+    ) -> Self {
+        self.add_timestamp_field_desc(name, "", bytes, epoch, ticks_per_second)
+    }
+
+    pub fn add_timestamp_field_desc(
+        mut self,
+        name: &str,
+        desc: &str,
+        bytes: u32,
+        epoch: Epoch,
+        ticks_per_second: u32,
+    ) -> Self {
+        let member = TimestampMember::new(name, desc, bytes, epoch, ticks_per_second);
+        self.members.push(StructMember::TimestampMember(member));
+        self
+    }
+
+    /// Add a value of `bits` bits, which continues the bit members before it unless they end
+    /// on a byte boundary.
+    pub fn add_bits_field(self, name: &str, bits: u32) -> Self {
+        self.add_bits_field_desc(name, "", bits)
+    }
+
+    pub fn add_bits_field_desc(mut self, name: &str, desc: &str, bits: u32) -> Self {
+        let offset = match self.members.last() {
+            Some(StructMember::BitsMember(prev)) if !prev.is_aligned() => prev.offset + prev.bits,
+            _ => 0,
+        };
+        let member = BitsMember::new(name, desc, bits, offset);
+        self.members.push(StructMember::BitsMember(member));
+        self
+    }
+
+    /// Add a constant of `bytes` bytes which must always hold `value`.
+    pub fn add_const_field(mut self, name: &str, bytes: u32, value: u64) -> Self {
+        let member = ConstantMember::new(name, bytes, value);
+        self.members.push(StructMember::ConstantMember(member));
+        self
+    }
+
+    pub fn add_len_field(self, name: &str, bytes: u32) -> Self {
+        self.add_len_field_extra(name, bytes, 0)
+    }
+
+    /// Add a length field which holds the length of the member governing it plus `extra`
+    /// bytes on the wire, e.g. the length of a whole message with `extra` bytes besides its
+    /// payload. The field of the structure holds the length of the member only.
+    pub fn add_len_field_extra(mut self, name: &str, bytes: u32, extra: u32) -> Self {
+        let member = LengthMember {
+            extra,
+            ..LengthMember::new(name, bytes)
+        };
+        self.members.push(StructMember::LengthMember(member));
+        self
+    }
+
+    /// Add a byte payload of at most `capacity` bytes, whose length is stored in the
+    /// length field `len_from`.
+    pub fn add_bytes_field(mut self, name: &str, len_from: &str, capacity: u32) -> Self {
+        let member = BytesMember::new(name, len_from, capacity);
+        self.members.push(StructMember::BytesMember(member));
+        self
+    }
+
+    /// Add a string of `capacity` bytes padded with NUL bytes.
+    pub fn add_fixed_string_field(mut self, name: &str, capacity: u32) -> Self {
+        let member = StringMember::new(name, StringKind::Fixed, capacity);
+        self.members.push(StructMember::StringMember(member));
+        self
+    }
+
+    /// Add a string of up to `capacity` bytes ending with a NUL byte.
+    pub fn add_cstring_field(mut self, name: &str, capacity: u32) -> Self {
+        let member = StringMember::new(name, StringKind::NullTerminated, capacity);
+        self.members.push(StructMember::StringMember(member));
+        self
+    }
+
+    /// Add a string of up to `capacity` bytes whose length is held by the length field or
+    /// varint `len_from`.
+    pub fn add_string_field(mut self, name: &str, len_from: &str, capacity: u32) -> Self {
+        let kind = StringKind::Prefixed(String::from(len_from));
+        let member = StringMember::new(name, kind, capacity);
+        self.members.push(StructMember::StringMember(member));
+        self
+    }
+
+    /// Add a pair of nibble-extended varints `high` and `low`, whose nibbles share a byte.
+    pub fn add_nibble_varints(mut self, high: &str, low: &str) -> Self {
+        let high = NibbleVarintMember::new(high, Some(low));
+        let low = NibbleVarintMember::new(low, None);
+        self.members.push(StructMember::NibbleVarintMember(high));
+        self.members.push(StructMember::NibbleVarintMember(low));
+        self
+    }
+
+    pub fn is_copy(&self) -> bool {
+        self.members.iter().all(|mem| match mem {
+            StructMember::BytesMember(_) => self.payloads == Payloads::Array,
+            StructMember::StringMember(_) => false,
+            _ => mem.is_copy(),
+        })
+    }
+
+    /// The names of the members with their offsets on the wire if the structure has a fixed
+    /// size. Bit members sharing their bytes with the next member are named along with it.
+    pub fn field_offsets(&self) -> Option<Vec<(String, u32)>> {
+        let mut fields = vec![];
+        let mut names = vec![];
+        let mut offset = 0;
+        for mem in &self.members {
+            let size = mem.fixed_size()?;
+            names.push(mem.name());
+            if size > 0 {
+                fields.push((names.join(", "), offset));
+                names.clear();
+            }
+            offset += size;
+        }
+        Some(fields)
+    }
+
+    /// Find the bitfield member named `name` declared before the member at index `before`.
+    /// Path of the module of the bitfield `bitfield` from the module holding the structure, or
+    /// from a module nested in it if `nested`. Bitfields defined inline are modules of the
+    /// module holding the structure, the others are its siblings.
+    pub fn bitfield_path(&self, bitfield: &str, nested: bool, naming: &Naming) -> TokenStream {
+        let pkg_name = util::ident(
+            &bitfield.to_sanitized_snake_case(&naming.types()),
+            Span::call_site(),
+        );
+        let inline = self.bitfields.iter().any(|bf| bf.name == bitfield);
+        match (inline, nested) {
+            (true, false) => quote! { self::#pkg_name },
+            (true, true) | (false, false) => quote! { super::#pkg_name },
+            (false, true) => quote! { super::super::#pkg_name },
+        }
+    }
+
+    /// Check that the bitfield members refer to bitfields defined inline or known to
+    /// `bitfields`, and that their bytes hold the bitfield in the type it is read from. Members
+    /// with bytes the bitfield does not use are linted according to `config`.
+    pub fn check_bitfields(&self, bitfields: &BitfieldRegistry, config: &Config) -> Result<()> {
+        for mem in &self.members {
+            let mem = match mem {
+                StructMember::BitfieldMember(mem) => mem,
+                _ => continue,
+            };
+
+            let sizes = match self.bitfields.iter().find(|bf| bf.name == mem.bitfield) {
+                Some(bitfield) => (bitfield.bitsize()?, bitfield.width()?),
+                None => match bitfields.get(&mem.bitfield) {
+                    Some(sizes) => sizes,
+                    None => {
+                        let known = bitfields
+                            .names()
+                            .map(|name| format!("`{}`", name))
+                            .collect::<Vec<_>>();
+                        let known = if known.is_empty() {
+                            String::from("no bitfields are known")
+                        } else {
+                            format!("known bitfields are {}", known.join(", "))
+                        };
+                        bail!(
+                            "member `{}` of `{}` refers to unknown bitfield `{}`, {}",
+                            mem.name,
+                            self.name,
+                            mem.bitfield,
+                            known
+                        );
+                    }
+                },
+            };
+
+            let (bitsize, width) = sizes;
+            let bits = mem.bytes * BITS_PER_BYTE;
+            let mem_width = if mem.bytes > 8 {
+                Ok(bits)
+            } else {
+                bits.to_ty_width()
+            };
+            if bitsize > bits {
+                bail!(
+                    "member `{}` of `{}` is {} bytes, which cannot hold the {} bits of bitfield `{}`",
+                    mem.name,
+                    self.name,
+                    mem.bytes,
+                    bitsize,
+                    mem.bitfield
+                );
+            }
+            if mem_width.ok() != Some(width) {
+                bail!(
+                    "member `{}` of `{}` is {} bytes, but bitfield `{}` is held by {} bytes",
+                    mem.name,
+                    self.name,
+                    mem.bytes,
+                    mem.bitfield,
+                    width / BITS_PER_BYTE
+                );
+            }
+            if bitsize.div_ceil(BITS_PER_BYTE) < mem.bytes {
+                config.lint(&format!(
+                    "member `{}` of `{}` is {} bytes, but bitfield `{}` only has {} bits",
+                    mem.name, self.name, mem.bytes, mem.bitfield, bitsize
+                ))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Describe the members and required members which are generated as the same identifier.
+    pub fn conflicts(&self, naming: &Naming) -> Vec<String> {
+        let owner = format!("`{}`", self.name);
+        let members = naming.names_of(&self.name);
+        let mut names = self
+            .members
+            .iter()
+            .map(|mem| mem.name())
+            .collect::<Vec<_>>();
+        // the records following the structure are accessed like its members
+        if let Some((name, _)) = &self.trailing {
+            names.push(name);
+        }
+        let mut conflicts = util::name_conflicts("member", &owner, &names, |name| {
+            name.to_sanitized_snake_case(&members).into()
+        });
+        let required = self.required.iter().map(String::as_str).collect::<Vec<_>>();
+        conflicts.extend(util::name_conflicts(
+            "required member",
+            &owner,
+            &required,
+            |name| name.to_sanitized_snake_case(&members).into(),
+        ));
+        conflicts
+    }
+
+    /// Declare the names of the structure, its members and its inline bitfields in the
+    /// `naming`.
+    pub fn declare(&self, naming: &mut Naming) {
+        naming.declare(Namespace::Types, [self.name.as_str()]);
+        let mut names = self
+            .members
+            .iter()
+            .map(|mem| mem.name())
+            .collect::<Vec<_>>();
+        if let Some((name, _)) = &self.trailing {
+            names.push(name);
+        }
+        naming.declare(Namespace::Names(self.name.clone()), names);
+        for bitfield in &self.bitfields {
+            bitfield.declare(naming);
+        }
+    }
+
+    /// The bitfield of the bitfield member `name`, which names its fields and values.
+    pub fn bitfield_of<'a>(&'a self, name: &'a str) -> &'a str {
+        self.members
+            .iter()
+            .find_map(|mem| match mem {
+                StructMember::BitfieldMember(mem) if mem.name == name => {
+                    Some(mem.bitfield.as_str())
+                }
+                _ => None,
+            })
+            .unwrap_or(name)
+    }
+
+    pub fn find_bitfield(&self, name: &str, before: usize) -> Option<&BitfieldMember> {
+        self.members[..before].iter().find_map(|mem| match mem {
+            StructMember::BitfieldMember(mem) if mem.name == name => Some(mem),
+            _ => None,
+        })
+    }
+
+    /// Find the length field named `name` declared before the member at index `before`.
+    pub fn find_len_field(&self, name: &str, before: usize) -> Option<&LengthMember> {
+        self.members[..before].iter().find_map(|mem| match mem {
+            StructMember::LengthMember(mem) if mem.name == name => Some(mem),
+            _ => None,
+        })
+    }
+
+    /// The byte appended to a checksum which is selected by the member `name`, if any.
+    pub fn checksum_extra(&self, name: &str) -> Option<&ChecksumExtra> {
+        self.members.iter().find_map(|mem| match mem {
+            StructMember::ChecksumMember(ChecksumMember {
+                extra: Some(extra), ..
+            }) if extra.key == name => Some(extra),
+            _ => None,
+        })
+    }
+
+    /// Whether the member `name` counts the elements of a bytes or repeated member.
+    pub fn is_len_source(&self, name: &str) -> bool {
+        self.members.iter().any(|mem| match mem {
+            StructMember::BytesMember(mem) => mem.len_from == name,
+            StructMember::RepeatedMember(mem) => {
+                mem.count_from == name && mem.count_field.is_none()
+            }
+            StructMember::StringMember(StringMember {
+                kind: StringKind::Prefixed(len_from),
+                ..
+            }) => len_from == name,
+            _ => false,
+        })
+    }
+
+    /// Find the length field or varint named `name` declared before the member
+    /// at index `before`.
+    pub fn find_len_source(&self, name: &str, before: usize) -> Option<LengthSource<'_>> {
+        self.members[..before].iter().find_map(|mem| match mem {
+            StructMember::LengthMember(mem) if mem.name == name => Some(LengthSource::Length(mem)),
+            StructMember::NibbleVarintMember(mem) if mem.name == name => {
+                Some(LengthSource::NibbleVarint(mem))
+            }
+            StructMember::VarintMember(mem) if mem.name == name => Some(LengthSource::Varint(mem)),
+            _ => None,
+        })
+    }
+
+    /// Index of the member starting the run of bit members the member at `idx` belongs to.
+    pub fn bits_run_start(&self, idx: usize) -> usize {
+        (0..=idx)
+            .rev()
+            .find(|i| matches!(&self.members[*i], StructMember::BitsMember(mem) if mem.offset == 0))
+            .unwrap_or(idx)
+    }
+
+    /// Number of bytes of the run of bit members starting at `idx`.
+    pub fn bits_run_bytes(&self, idx: usize) -> u32 {
+        let mut bytes = 0;
+        for mem in &self.members[idx..] {
+            match mem {
+                StructMember::BitsMember(mem) => {
+                    bytes += mem.bytes();
+                    if mem.is_aligned() {
+                        break;
+                    }
+                }
+                _ => break,
+            }
+        }
+        bytes
+    }
+}
+
+pub struct SimpleStructure {
+    pub name: String,
+    pub member: PrimitiveMember,
+    /// Derives overriding the ones of the generated file.
+    pub derives: Option<Derives>,
+    /// Byte order of the member on the wire.
+    pub byte_order: ByteOrder,
+}
+
+impl Type for SimpleStructure {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl SimpleStructure {
+    pub fn new(name: &str, mem_name: &str, bytes: u32) -> Self {
+        let name = String::from(name);
+        Self {
+            name,
+            member: PrimitiveMember::new(mem_name, bytes),
+            derives: None,
+            byte_order: ByteOrder::default(),
+        }
+    }
+
+    /// Derive `derives` on this structure instead of the derives of the generated file.
+    pub fn with_derives(mut self, derives: Derives) -> Self {
+        self.derives = Some(derives);
+        self
+    }
+
+    /// Serialize the member in byte order `byte_order`.
+    pub fn with_byte_order(mut self, byte_order: ByteOrder) -> Self {
+        self.byte_order = byte_order;
+        self
+    }
+}
+
+impl AlternativeOptions {
+    pub fn new<T>(name: &str, default: &T) -> Self
+    where
+        T: Type,
+    {
+        let name = String::from(name);
+        let default_name = String::from(default.name());
+        Self {
+            name,
+            default: default_name,
+            alternatives: vec![],
+            discriminants: HashMap::new(),
+        }
+        .insert_type(default)
+    }
+
+    pub fn insert_type<T>(mut self, structure: &T) -> Self
+    where
+        T: Type,
+    {
+        let name = String::from(structure.name());
+        self.alternatives.push(name);
+        self
+    }
+
+    /// Declare that `structure` is selected when a discriminator field holds the
+    /// enumerated value `variant`.
+    pub fn set_discriminant<T>(mut self, structure: &T, variant: &str) -> Self
+    where
+        T: Type,
+    {
+        let name = String::from(structure.name());
+        let variant = String::from(variant);
+        self.discriminants.insert(name, variant);
+        self
+    }
+
+    pub fn insert_type_discriminant<T>(self, structure: &T, variant: &str) -> Self
+    where
+        T: Type,
+    {
+        self.insert_type(structure)
+            .set_discriminant(structure, variant)
+    }
+
+    pub fn discriminant(&self, alternative: &str) -> Result<&str> {
+        match self.discriminants.get(alternative) {
+            Some(variant) => Ok(variant),
+            None => bail!(
+                "alternative `{}` of `{}` has no discriminant value",
+                alternative,
+                self.name
+            ),
+        }
+    }
+}
+
+/// Render a constant expression for the largest of `sizes`.
+fn render_const_max(sizes: &[TokenStream]) -> TokenStream {
+    quote! {
+        {
+            let mut max = 0;
+            #(if #sizes > max { max = #sizes; })*
+            max
+        }
+    }
+}
+
+/// Render a constant expression for the largest number of bytes `mem` occupies on the wire.
+fn render_max_size(mem: &StructMember, naming: &Naming) -> Result<TokenStream> {
+    let span = Span::call_site();
+
+    if let Some(size) = mem.fixed_size() {
+        let size = unsuffixed(size as u64);
+        return Ok(quote! { #size });
+    }
+
+    Ok(match mem {
+        StructMember::BytesMember(mem) => {
+            let capacity = unsuffixed(mem.capacity as u64);
+            quote! { #capacity }
+        }
+        StructMember::ExtendedMember(mem) => {
+            let capacity = unsuffixed(mem.capacity as u64);
+            quote! { #capacity }
+        }
+        StructMember::VarintMember(mem) => {
+            let max_bytes = unsuffixed(mem.max_bytes() as u64);
+            quote! { #max_bytes }
+        }
+        // the terminator follows the capacity
+        StructMember::StringMember(StringMember {
+            kind: StringKind::NullTerminated,
+            capacity,
+            ..
+        }) => {
+            let size = unsuffixed(*capacity as u64 + 1);
+            quote! { #size }
+        }
+        StructMember::StringMember(mem) => {
+            let capacity = unsuffixed(mem.capacity as u64);
+            quote! { #capacity }
+        }
+        StructMember::RepeatedMember(mem) => {
+            let size = unsuffixed((mem.bytes * mem.capacity) as u64);
+            quote! { #size }
+        }
+        StructMember::AlternativesMember(mem) => {
+            let alt_pc_a = util::ident(
+                &format!(
+                    "{}A",
+                    mem.alternatives.to_sanitized_pascal_case(&naming.types())
+                ),
+                span,
+            );
+            quote! { #alt_pc_a::MAX_SIZE }
+        }
+        StructMember::OptionalMember(mem) => {
+            let sty = util::ident(
+                &mem.structure.to_sanitized_pascal_case(&naming.types()),
+                span,
+            );
+            quote! { #sty::MAX_SIZE }
+        }
+        // the shared byte of the nibbles is counted with the high one
+        StructMember::NibbleVarintMember(mem) if mem.low.is_some() => quote! { 3 },
+        StructMember::NibbleVarintMember(_) => quote! { 2 },
+        _ => bail!("member `{}` has no fixed size", mem.name()),
+    })
+}
+
+/// Render the `wire_size` function computing the number of bytes the structure occupies on the
+/// wire with its current values.
+fn render_wire_size(structure: &Structure, naming: &Naming) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let members = naming.names_of(&structure.name);
+
+    let mut sizes = vec![];
+    for (idx, mem) in structure.members.iter().enumerate() {
+        let mem_name = util::ident(&mem.name().to_sanitized_snake_case(&members), span);
+
+        if let Some(size) = mem.fixed_size() {
+            let size = unsuffixed(size as u64);
+            sizes.push(quote! { #size });
+            continue;
+        }
+
+        sizes.push(match mem {
+            StructMember::BytesMember(_) if structure.payloads != Payloads::Array => {
+                quote! { self.#mem_name.len() }
+            }
+            StructMember::BytesMember(mem) => {
+                let len = match structure.find_len_source(&mem.len_from, idx) {
+                    Some(len) => len,
+                    None => bail!(
+                        "bytes field `{}` refers to unknown length field `{}`",
+                        mem.name,
+                        mem.len_from
+                    ),
+                };
+                let len_name = util::ident(&len.name().to_sanitized_snake_case(&members), span);
+                quote! { self.#len_name as usize }
+            }
+            StructMember::NibbleVarintMember(mem) => {
+                let nibbles = if mem.low.is_some() {
+                    quote! { 1 + }
+                } else {
+                    quote! {}
+                };
+                quote! {
+                    #nibbles match self.#mem_name {
+                        0..=12 => 0,
+                        13..=268 => 1,
+                        _ => 2,
+                    }
+                }
+            }
+            StructMember::RepeatedMember(mem) => {
+                let bytes = unsuffixed(mem.bytes as u64);
+                quote! { self.#mem_name.len() * #bytes }
+            }
+            StructMember::ExtendedMember(_) => quote! { crate::chain_len(&self.#mem_name) },
+            StructMember::VarintMember(_) => {
+                quote! { crate::varint_len(u64::from(self.#mem_name)) }
+            }
+            StructMember::StringMember(mem) => match &mem.kind {
+                StringKind::Prefixed(len_from) => {
+                    let len = match structure.find_len_source(len_from, idx) {
+                        Some(len) => len,
+                        None => bail!(
+                            "string field `{}` refers to unknown length field `{}`",
+                            mem.name,
+                            len_from
+                        ),
+                    };
+                    let len_name = util::ident(&len.name().to_sanitized_snake_case(&members), span);
+                    quote! { self.#len_name as usize }
+                }
+                StringKind::NullTerminated => quote! { self.#mem_name.len() + 1 },
+                StringKind::Fixed => unsuffixed(mem.capacity as u64),
+            },
+            StructMember::AlternativesMember(_) => quote! { self.#mem_name.wire_size() },
+            StructMember::OptionalMember(_) => quote! {
+                match &self.#mem_name {
+                    Some(v) => v.wire_size(),
+                    None => 0,
+                }
+            },
+            _ => bail!("member `{}` has no fixed size", mem.name()),
+        });
+    }
+
+    Ok(quote! {
+        #[doc = "Number of bytes written by `write` for the current values."]
+        pub fn wire_size(&self) -> usize {
+            0 #(+ #sizes)*
+        }
+    })
+}
+
+/// Render the `SIZE` constant if the structure always has the same size on the wire, and the
+/// `MAX_SIZE` constant giving the largest size of the structure on the wire.
+fn render_size_consts(structure: &Structure, naming: &Naming) -> Result<TokenStream> {
+    let mut sizes = vec![];
+    for mem in &structure.members {
+        sizes.push(render_max_size(mem, naming)?);
+    }
+    let max_size = quote! { 0 #(+ #sizes)* };
+
+    let fixed = structure
+        .members
+        .iter()
+        .map(|mem| mem.fixed_size())
+        .sum::<Option<u32>>();
+
+    Ok(match fixed {
+        Some(size) => {
+            let size = unsuffixed(size as u64);
+            quote! {
+                #[doc = "Size of the structure on the wire."]
+                pub const SIZE : usize = #size;
+                #[doc = "Largest size of the structure on the wire, which is always `SIZE`."]
+                pub const MAX_SIZE : usize = Self::SIZE;
+            }
+        }
+        None => quote! {
+            #[doc = "Largest size of the structure on the wire."]
+            pub const MAX_SIZE : usize = #max_size;
+        },
+    })
+}
+
+/// Render the trait, enum and selector of every set of `alternatives`. With the
+/// `const_generic` of `config` the selector also gives the index of every alternative, which
+/// selects it as the const parameter of a typed structure.
+pub fn render_alternatives(
+    alternatives: &Alternatives,
+    derives: &Derives,
+    config: &Config,
+) -> Result<TokenStream> {
+    let deriving = deriving_tokens(derives)?;
+
+    let span = Span::call_site();
+    let naming = &config.naming;
+
+    let mut mod_items = TokenStream::new();
+    let mut trait_extends = TokenStream::new();
+    let mut conversions = TokenStream::new();
+
+    for (key, alt) in &alternatives.map {
+        let alt_pc = util::ident(&key.to_sanitized_pascal_case(&naming.types()), span);
+        let alt_pc_a = util::ident(&format!("{}A", alt_pc), span);
+        let alt_pc_sel = util::ident(&format!("{}Selector", alt_pc), span);
+
+        let mut alt_enum_entries = TokenStream::new();
+        let mut sel_enum_entries = TokenStream::new();
+        let mut write_entries = TokenStream::new();
+        let mut display_entries = TokenStream::new();
+        let mut wire_size_entries = TokenStream::new();
+        let mut sel_entries = TokenStream::new();
+        let mut read_entries = TokenStream::new();
+        let mut read_funs = TokenStream::new();
+        let mut index_consts = TokenStream::new();
+        let mut from_index_entries = TokenStream::new();
+        let mut default_at_entries = TokenStream::new();
+        let mut max_sizes = vec![];
+
+        for (index, altopt) in alt.alternatives.iter().enumerate() {
+            let alt_struct = util::ident(&altopt.to_sanitized_pascal_case(&naming.types()), span);
+            let alt_enum = util::ident(&altopt.to_sanitized_pascal_case(&naming.types()), span);
+            let alt_enum_read = util::ident(
+                &format!("read_{}", altopt.to_sanitized_snake_case(&naming.types())),
+                span,
+            );
+
+            trait_extends.extend(quote! {
+                impl #alt_pc for #alt_struct {
+                    fn default() -> Self {
+                        Self::new()
+                    }
+
+                    fn wire_size(&self) -> usize {
+                        #alt_struct::wire_size(self)
+                    }
+                }
+            });
+
+            alt_enum_entries.extend(quote! {
+                #alt_enum(#alt_struct),
+            });
+
+            max_sizes.push(quote! { #alt_struct::MAX_SIZE });
+
+            sel_enum_entries.extend(quote! {
+                #alt_enum,
+            });
+
+            write_entries.extend(quote! {
+                #alt_pc_a::#alt_enum(v) => v.write(out),
+            });
+
+            wire_size_entries.extend(quote! {
+                #alt_pc_a::#alt_enum(v) => v.wire_size(),
+            });
+
+            display_entries.extend(quote! {
+                #alt_pc_a::#alt_enum(v) => core::fmt::Display::fmt(v, f),
+            });
+
+            sel_entries.extend(quote! {
+                #alt_pc_a::#alt_enum(_) => #alt_pc_sel::#alt_enum,
+            });
+
+            conversions.extend(quote! {
+                impl From<#alt_struct> for #alt_pc_a {
+                    fn from(value : #alt_struct) -> Self {
+                        #alt_pc_a::#alt_enum(value)
+                    }
+                }
+
+                impl TryFrom<#alt_pc_a> for #alt_struct {
+                    type Error = Error;
+
+                    fn try_from(value : #alt_pc_a) -> Result<Self, Error> {
+                        match value {
+                            #alt_pc_a::#alt_enum(value) => Ok(value),
+                            #[allow(unreachable_patterns)]
+                            _ => Err(Error::UnexpectedAlternative { field : #key }),
+                        }
+                    }
+                }
+            });
+
+            read_entries.extend(quote! {
+                #alt_pc_sel::#alt_enum => Self::#alt_enum_read(reader),
+            });
+
+            let index_const = util::ident(&altopt.to_sanitized_upper_case(&naming.types()), span);
+            let index = unsuffixed(index as u64);
+            let index_doc = format!("Index of [`{}`] as a const parameter.", alt_struct);
+            index_consts.extend(quote! {
+                #[doc = #index_doc]
+                pub const #index_const : u8 = #index;
+            });
+            from_index_entries.extend(quote! {
+                #index => Some(Self::#alt_enum),
+            });
+            default_at_entries.extend(quote! {
+                #index => Self::#alt_enum(#alt_struct::new()),
+            });
+
+            read_funs.extend(quote! {
+                pub fn #alt_enum_read<R>(reader : &mut R) -> Result<Self, Error> where R : Read {
+                    Ok(#alt_pc_a::#alt_enum(#alt_struct::read(reader)?))
+                }
+            });
+        }
+
+        let max_size = render_const_max(&max_sizes);
+
+        if config.const_generic {
+            mod_items.extend(quote! {
+                impl #alt_pc_sel {
+                    #index_consts
+
+                    #[doc = "Index of the alternative, which selects it as the const parameter of a structure."]
+                    pub const fn index(self) -> u8 {
+                        self as u8
+                    }
+
+                    #[doc = "The alternative of index `index`, if any."]
+                    pub const fn from_index(index : u8) -> Option<Self> {
+                        match index {
+                            #from_index_entries
+                            _ => None,
+                        }
+                    }
+                }
+
+                impl #alt_pc_a {
+                    #[doc = "The default of the alternative of index `index`, which must be one of the alternatives."]
+                    pub fn default_at(index : u8) -> Self {
+                        match index {
+                            #default_at_entries
+                            _ => panic!("no alternative of index {}", index),
+                        }
+                    }
+                }
+            });
         }
 
         let hd = &alt.alternatives[0];
-        let def_alt_struct = Ident::new(&hd.to_sanitized_pascal_case(), span);
+        let def_alt_struct = util::ident(&hd.to_sanitized_pascal_case(&naming.types()), span);
+
+        mod_items.extend(quote! {
+            pub trait #alt_pc : Copy {
+                fn default() -> Self;
+
+                fn wire_size(&self) -> usize;
+            }
+
+            #deriving
+            pub enum #alt_pc_a {
+                #alt_enum_entries
+            }
+
+            #[doc = "Selects which alternative is read."]
+            #deriving
+            pub enum #alt_pc_sel {
+                #sel_enum_entries
+            }
+
+            impl #alt_pc_a {
+                #[doc = "Largest size of any alternative on the wire."]
+                pub const MAX_SIZE : usize = #max_size;
+
+                pub fn default() -> Self {
+                    Self::#def_alt_struct(#def_alt_struct::new())
+                }
+
+                pub fn selector(&self) -> #alt_pc_sel {
+                    match self {
+                        #sel_entries
+                    }
+                }
+
+                pub fn write<W>(&self, out : &mut W) -> Result<(), Error> where W : Write {
+                    match self {
+                        #write_entries
+                    }
+                }
+
+                #[doc = "Number of bytes written by `write` for the current alternative."]
+                pub fn wire_size(&self) -> usize {
+                    match self {
+                        #wire_size_entries
+                    }
+                }
+
+                pub fn read<R>(reader : &mut R, selector : #alt_pc_sel) -> Result<Self, Error> where R : Read {
+                    match selector {
+                        #read_entries
+                    }
+                }
+
+                #read_funs
+            }
+
+            impl core::fmt::Display for #alt_pc_a {
+                fn fmt(&self, f : &mut core::fmt::Formatter) -> core::fmt::Result {
+                    match self {
+                        #display_entries
+                    }
+                }
+            }
+        });
+    }
+
+    mod_items.extend(quote! {#trait_extends});
+    mod_items.extend(conversions);
+
+    Ok(mod_items)
+}
+
+pub fn render_simple(
+    structure: &SimpleStructure,
+    derives: &Derives,
+    naming: &Naming,
+) -> Result<TokenStream> {
+    let members = naming.names_of(&structure.name);
+    let derives = structure.derives.as_ref().unwrap_or(derives);
+    let deriving = deriving_tokens(derives)?;
+
+    let mut mod_items = TokenStream::new();
+
+    let span = Span::call_site();
+    let str_name = util::ident(
+        &structure.name.to_sanitized_pascal_case(&naming.types()),
+        span,
+    );
+    let mem_name = util::ident(
+        &structure.member.name.to_sanitized_snake_case(&members),
+        span,
+    );
+    let sty = (structure.member.bytes * 8).to_ty()?;
+    let order = structure.byte_order;
+    let read_mem = render_read_int(&mem_name, structure.member.bytes, order)?;
+    let write_mem = render_write_int(quote! { self.#mem_name }, structure.member.bytes, order)?;
+    let slice_fns = render_slice_fns();
+    let size = unsuffixed(structure.member.bytes as u64);
+    let display = format!(
+        "{} {{{{ {}: {{:#x}} }}}}",
+        str_name,
+        structure.member.name.to_sanitized_snake_case(&members)
+    );
+
+    let str_doc = doc::render_doc(
+        &structure.member.desc,
+        &doc::simple_struct_diagram(structure),
+    );
+
+    mod_items.extend(quote! {
+        #str_doc
+        #deriving
+        pub struct #str_name {
+            #mem_name : #sty
+        }
+
+        impl #str_name {
+            #[doc = "Size of the structure on the wire."]
+            pub const SIZE : usize = #size;
+            #[doc = "Largest size of the structure on the wire, which is always `SIZE`."]
+            pub const MAX_SIZE : usize = Self::SIZE;
+
+            pub fn new() -> Self {
+                Self { #mem_name : 0 }
+            }
+
+            pub fn of_value(val : #sty) -> Self {
+                Self { #mem_name : val }
+            }
+
+            pub fn get(&self) -> #sty {
+                self.#mem_name
+            }
+
+            pub fn set(&mut self, v : #sty) -> &mut Self {
+                self.#mem_name = v;
+                self
+            }
+
+            #[doc = "Number of bytes written by `write`, which is always `SIZE`."]
+            pub fn wire_size(&self) -> usize {
+                Self::SIZE
+            }
+
+            pub fn write<W>(&self, out : &mut W) -> Result<(), Error> where W : Write {
+                #write_mem
+                Ok(())
+            }
+
+            pub fn read<R>(reader : &mut R) -> Result<Self, Error> where R : Read {
+                #read_mem
+                Ok(Self { #mem_name })
+            }
+
+            #slice_fns
+        }
+
+        impl core::fmt::Display for #str_name {
+            fn fmt(&self, f : &mut core::fmt::Formatter) -> core::fmt::Result {
+                write!(f, #display, self.#mem_name)
+            }
+        }
+    });
+    mod_items.extend(render_byte_conversions(
+        &str_name,
+        Some(vec![(structure.member.name.clone(), 0)]),
+    ));
+    mod_items.extend(frame_trait::render_impl(&str_name, &structure.name));
+
+    Ok(mod_items)
+}
+
+/// Check `structure` for definitions which generate code, but are likely mistakes.
+fn lint(structure: &Structure, config: &Config) -> Result<()> {
+    for (name, value) in &structure.defaults {
+        let mem = match structure.members.iter().find(|mem| mem.name() == name) {
+            Some(mem) => mem,
+            None => bail!(
+                "`{}` has a default for the unknown member `{}`",
+                structure.name,
+                name
+            ),
+        };
+        match mem.default_max() {
+            None => bail!(
+                "member `{}` of `{}` cannot have a default",
+                name,
+                structure.name
+            ),
+            Some(max) if *value > max => bail!(
+                "default {} of `{}` in `{}` exceeds its largest value {}",
+                value,
+                name,
+                structure.name,
+                max
+            ),
+            Some(_) => {}
+        }
+    }
+
+    for (idx, mem) in structure.members.iter().enumerate() {
+        let (name, capacity, len_from) = match mem {
+            StructMember::ConstantMember(mem) => {
+                if mem.bytes < 8 && mem.value >> (mem.bytes * BITS_PER_BYTE) != 0 {
+                    config.lint(&format!(
+                        "constant `{}` of `{}` does not fit into {} bytes",
+                        mem.name, structure.name, mem.bytes
+                    ))?;
+                }
+                continue;
+            }
+            StructMember::OffsetMember(mem) => {
+                if mem.marker_bits >= mem.bytes * BITS_PER_BYTE {
+                    bail!(
+                        "marker of offset `{}` of `{}` leaves no bits for the offset",
+                        mem.name,
+                        structure.name
+                    );
+                }
+                if mem.marker >> mem.marker_bits != 0 {
+                    config.lint(&format!(
+                        "marker of offset `{}` of `{}` does not fit into {} bits",
+                        mem.name, structure.name, mem.marker_bits
+                    ))?;
+                }
+                continue;
+            }
+            StructMember::BcdMember(mem) => {
+                if mem.bytes == 0 || mem.bytes > 8 {
+                    bail!(
+                        "BCD member `{}` of `{}` must have 1 to 8 bytes",
+                        mem.name,
+                        structure.name
+                    );
+                }
+                continue;
+            }
+            StructMember::TimestampMember(mem) => {
+                if mem.bytes == 0 || mem.bytes > 8 {
+                    bail!(
+                        "timestamp `{}` of `{}` must have 1 to 8 bytes",
+                        mem.name,
+                        structure.name
+                    );
+                }
+                // ticks must convert into whole nanoseconds
+                if mem.ticks_per_second == 0 || 1_000_000_000 % mem.ticks_per_second != 0 {
+                    bail!(
+                        "timestamp `{}` of `{}` must have a number of ticks per second dividing 10^9",
+                        mem.name,
+                        structure.name
+                    );
+                }
+                continue;
+            }
+            StructMember::VarintMember(mem) => {
+                if mem.bits == 0 || mem.bits > 64 {
+                    bail!(
+                        "varint `{}` of `{}` must have 1 to 64 bits",
+                        mem.name,
+                        structure.name
+                    );
+                }
+                continue;
+            }
+            StructMember::BitsMember(mem) => {
+                if mem.bits == 0 || mem.bits > 64 {
+                    bail!(
+                        "bit member `{}` of `{}` must have 1 to 64 bits",
+                        mem.name,
+                        structure.name
+                    );
+                }
+                let continued = matches!(
+                    structure.members.get(idx + 1),
+                    Some(StructMember::BitsMember(next)) if next.offset != 0
+                );
+                if !continued && !mem.is_aligned() {
+                    bail!(
+                        "bit member `{}` of `{}` ends its run of bit members within a byte",
+                        mem.name,
+                        structure.name
+                    );
+                }
+                continue;
+            }
+            StructMember::LengthMember(mem) => {
+                if mem.extra > roundtrip::len_max(mem.bytes) {
+                    bail!(
+                        "length `{}` of `{}` cannot hold its {} extra bytes",
+                        mem.name,
+                        structure.name,
+                        mem.extra
+                    );
+                }
+                continue;
+            }
+            StructMember::ExtendedMember(mem) => {
+                if mem.capacity == 0 {
+                    bail!(
+                        "chain `{}` of `{}` cannot hold a single byte",
+                        mem.name,
+                        structure.name
+                    );
+                }
+                continue;
+            }
+            StructMember::BytesMember(mem) => (&mem.name, mem.capacity, &mem.len_from),
+            StructMember::RepeatedMember(mem) => (&mem.name, mem.capacity, &mem.count_from),
+            StructMember::StringMember(StringMember {
+                name,
+                kind: StringKind::Prefixed(len_from),
+                capacity,
+            }) => (name, *capacity, len_from),
+            _ => continue,
+        };
+        // the count, along with the extra bytes of a length field, must fit its type
+        if let Some(len) = structure.find_len_source(len_from, idx) {
+            if capacity > len.max() {
+                bail!(
+                    "capacity of `{}` in `{}` cannot be counted by `{}`",
+                    name,
+                    structure.name,
+                    len.name()
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Name of the module holding the member proxies of `structure`.
+pub(crate) fn fields_mod_ident(structure: &Structure, naming: &Naming) -> Ident {
+    util::ident(
+        &format!(
+            "{}_fields",
+            structure
+                .name
+                .to_sanitized_not_keyword_snake_case(&naming.types())
+        ),
+        Span::call_site(),
+    )
+}
+
+pub fn render_with_alts(
+    structure: &Structure,
+    alternatives: &Alternatives,
+    derives: &Derives,
+    config: &Config,
+) -> Result<TokenStream> {
+    lint(structure, config)?;
+
+    let derives = structure.derives.as_ref().unwrap_or(derives);
+    let span = Span::call_site();
+    let naming = &config.naming;
+    let members = naming.names_of(&structure.name);
+
+    let mut mod_items = TokenStream::new();
+
+    let str_name = util::ident(
+        &structure.name.to_sanitized_pascal_case(&naming.types()),
+        span,
+    );
+    let str_name_def = util::ident(&format!("{}Default", str_name), span);
+    let str_name_gen = util::ident(&format!("{}Generic", str_name), span);
+    let fields_mod_name = fields_mod_ident(structure, naming);
+
+    let mut str_mems = TokenStream::new();
+    let mut str_mems_gen = TokenStream::new();
+    let mut templ = TokenStream::new();
+    let mut templ_decl = TokenStream::new();
+    let mut default_templ = TokenStream::new();
+
+    let mut where_clause = TokenStream::new();
+    let mut into_gen_bounds = TokenStream::new();
+    let mut from_gen_bounds = TokenStream::new();
+    let mut display_bounds = TokenStream::new();
+    let mut fields_where_clause = TokenStream::new();
+    let mut inst_default = TokenStream::new();
+
+    let mut str_items = TokenStream::new();
+    let mut str_fns = TokenStream::new();
+    // bitfields of the generic structure updated from its alternatives and optional members
+    let mut written_fns = TokenStream::new();
+    // accessors of the members taking `&self` for reading, rendered in the module of the proxies
+    let mut direct_fns = TokenStream::new();
+    let mut reader_fns = TokenStream::new();
+    let str_reader_name = util::ident(&format!("{}Reader", str_name), span);
+
+    let mut default_mems = TokenStream::new();
+    let mut read_mem = TokenStream::new();
+    let mut read_mems = TokenStream::new();
+    let mut write_mem = TokenStream::new();
+    let mut read_mem_gen = TokenStream::new();
+    let mut read_mems_gen = TokenStream::new();
+    let mut read_params_gen = TokenStream::new();
+    let mut into_gen_mems = TokenStream::new();
+    let mut from_gen_mems = TokenStream::new();
+    let mut read_checks = TokenStream::new();
+    let mut checksum_fns = TokenStream::new();
+    let mut consts = render_size_consts(structure, naming)?;
+    let wire_size = render_wire_size(structure, naming)?;
+
+    // the serialization of each member, used to compute checksums
+    let mut write_pieces = vec![];
+    let mut write_mem_gen = TokenStream::new();
+
+    let mut has_alt = false;
+
+    for mem in &structure.members {
+        if let StructMember::AlternativesMember(alt) = mem {
+            let alts = alternatives.get(&alt.alternatives)?;
+
+            let alt_default = util::ident(
+                &alts.default.to_sanitized_pascal_case(&naming.types()),
+                span,
+            );
+            let alt_trait = util::ident(
+                &alt.alternatives.to_sanitized_pascal_case(&naming.types()),
+                span,
+            );
+            let param = alt.param(config.const_generic, &members, naming);
+
+            templ_decl.extend(param.decl);
+            templ.extend(param.arg);
+            where_clause.extend(param.bound);
+            if config.const_generic {
+                let alt_pc_sel = util::ident(&format!("{}Selector", alt_trait), span);
+                let index_const =
+                    util::ident(&alts.default.to_sanitized_upper_case(&naming.types()), span);
+                default_templ.extend(quote! { { #alt_pc_sel::#index_const }, });
+            } else {
+                let alt_name_templ = param.ty;
+                let alt_pc_a = util::ident(&format!("{}A", alt_trait), span);
+                into_gen_bounds
+                    .extend(quote! { #alt_name_templ : #alt_trait + Into<#alt_pc_a>, });
+                from_gen_bounds
+                    .extend(quote! { #alt_name_templ : #alt_trait + TryFrom<#alt_pc_a>, });
+                display_bounds.extend(quote! { #alt_name_templ : core::fmt::Display, });
+                fields_where_clause.extend(quote! { #alt_name_templ : super::#alt_trait, });
+                default_templ.extend(quote! { #alt_default, });
+            }
+
+            has_alt = true;
+        }
+    }
+
+    for (idx, mem) in structure.members.iter().enumerate() {
+        let mem_name_str = mem.name();
+        let mem_name = util::ident(&mem_name_str.to_sanitized_snake_case(&members), span);
+        let ty_name = util::ident(&mem_name_str.to_sanitized_pascal_case(&members), span);
+        let fty_name = quote! { #fields_mod_name :: #ty_name };
+        let getter = util::ident(&format!("get_{}", mem_name), span);
+        let setter = util::ident(&format!("set_{}", mem_name), span);
+
+        let mut mem_str_impl = TokenStream::new();
+        let mem_doc = match mem.desc() {
+            Some(desc) => quote! { #[doc = #desc] },
+            None => quote! {},
+        };
+
+        // the value of integer members in `new()` and `default()`
+        let init = match structure.default_of(mem_name_str) {
+            Some(value) => unsuffixed(value),
+            None => quote! { 0 },
+        };
+
+        let mut default_value = TokenStream::new();
+        let mut mem_ty = TokenStream::new();
+        let mut mem_ty_gen = TokenStream::new();
+
+        let mut mem_read = TokenStream::new();
+        let mut mem_write = TokenStream::new();
+        let mut mem_read_gen = None;
+        let mut mem_write_gen = None;
+        let mut stored = true;
+
+        match mem {
+            StructMember::BitfieldMember(mem) => {
+                let pkg_name = structure.bitfield_path(&mem.bitfield, false, naming);
+                let pkg_fields = structure.bitfield_path(&mem.bitfield, true, naming);
+                let sty = raw_ty(mem.bytes)?;
+                let init = match structure.default_of(&mem.name) {
+                    Some(_) => init.clone(),
+                    None => quote! { #pkg_name::RESET_VALUE },
+                };
+
+                default_value.extend(quote! { #init });
+                mem_ty.extend(quote! {#sty});
+                mem_ty_gen.extend(quote! {#sty});
+
+                str_fns.extend(quote! {
+                    pub fn #mem_name(&mut self) -> #fty_name<#templ> {
+                        #fty_name::new(self)
+                    }
+                });
+
+                if mem.access.can_read() {
+                    mem_str_impl.extend(quote! {
+                        #[inline(always)]
+                        pub fn read(&self) -> #pkg_fields::R {
+                            self.data.#getter()
+                        }
+                    });
+                    direct_fns.extend(quote! {
+                        #[inline(always)]
+                        pub fn #getter(&self) -> #pkg_fields::R {
+                            #pkg_fields::R::new(self.#mem_name)
+                        }
+                    });
+                    reader_fns.extend(render_reader_fn(&mem_doc, &mem_name, &getter, quote! { #pkg_fields::R }));
+                }
+
+                if mem.access.can_write() {
+                    mem_str_impl.extend(quote! {
+                        #[inline(always)]
+                        pub fn modify<F>(&'a mut self, f : F) -> &'a mut super::#str_name<#templ> where for <'w> F : FnOnce(&'w mut #pkg_fields::W) -> &'w mut #pkg_fields::W {
+                            let bits = self.data.#mem_name;
+                            self.data.#mem_name = **f(&mut #pkg_fields::W::new(bits));
+                            self.data
+                        }
+                    });
+                }
+
+                default_mems.extend(quote! {#mem_name : #init,});
+
+                mem_read.extend(render_read_int(&mem_name, mem.bytes, structure.byte_order)?);
+                read_mems.extend(quote! {#mem_name, });
+
+                let flag_syncs = render_flag_syncs(structure, &mem.name, &pkg_name, naming);
+                let syncs = render_discriminator_syncs(
+                    structure,
+                    alternatives,
+                    &mem.name,
+                    &pkg_name,
+                    naming,
+                )?;
+                let write_bits = render_write_int(quote! { *w }, mem.bytes, structure.byte_order)?;
+
+                if flag_syncs.is_empty() {
+                    mem_write.extend(render_write_int(
+                        quote! { self.#mem_name },
+                        mem.bytes,
+                        structure.byte_order,
+                    )?);
+                } else {
+                    mem_write.extend(quote! {
+                        let mut w = #pkg_name::W::new(self.#mem_name);
+                        #flag_syncs
+                        #write_bits
+                    });
+                }
+
+                if !syncs.is_empty() {
+                    let written = util::ident(&format!("written_{}", mem_name), span);
+                    let doc = format!(
+                        "The bits of `{}` as written, with the discriminators of the alternatives and the presence flags updated.",
+                        mem_name
+                    );
+                    written_fns.extend(quote! {
+                        #[doc = #doc]
+                        #[inline(always)]
+                        fn #written(&self) -> #sty {
+                            let mut w = #pkg_name::W::new(self.#mem_name);
+                            #flag_syncs
+                            #syncs
+                            *w
+                        }
+                    });
+                    mem_write_gen = Some(render_write_int(
+                        quote! { self.#written() },
+                        mem.bytes,
+                        structure.byte_order,
+                    )?);
+                }
+            }
+            StructMember::PrimitiveMember(mem) => {
+                let sty = (mem.bytes * 8).to_ty()?;
+
+                default_value.extend(quote! { #init });
+                mem_ty.extend(quote! {#sty});
+                mem_ty_gen.extend(quote! {#sty});
+
+                let doc = mem_doc.clone();
+                str_fns.extend(quote! {
+                    #doc
+                    pub fn #mem_name(&mut self) -> #fty_name<#templ> {
+                            #fty_name::new(self)
+                        }
+                });
+
+                if mem.access.can_read() {
+                    mem_str_impl.extend(quote! {
+                        #[inline(always)]
+                        pub fn read(&self) -> #sty {
+                            self.data.#getter()
+                        }
+                    });
+                    direct_fns.extend(quote! {
+                        #[inline(always)]
+                        pub fn #getter(&self) -> #sty {
+                            self.#mem_name
+                        }
+                    });
+                    reader_fns.extend(render_reader_fn(&mem_doc, &mem_name, &getter, quote! { #sty }));
+                }
+
+                if mem.access.can_write() {
+                    mem_str_impl.extend(quote! {
+                        #[inline(always)]
+                        pub fn set(&'a mut self, v : #sty) -> &'a mut super::#str_name<#templ> {
+                            self.data.#mem_name = v;
+                            self.data
+                        }
+                    });
+                    direct_fns.extend(quote! {
+                        #[inline(always)]
+                        pub fn #setter(&mut self, v : #sty) {
+                            #ty_name::new(self).set(v);
+                        }
+                    });
+                }
+
+                default_mems.extend(quote! {#mem_name : #init,});
+
+                mem_read.extend(render_read_int(&mem_name, mem.bytes, structure.byte_order)?);
+                read_mems.extend(quote! {#mem_name, });
+
+                mem_write.extend(render_write_int(
+                    quote! { self.#mem_name },
+                    mem.bytes,
+                    structure.byte_order,
+                )?);
+            }
+            StructMember::EuiMember(mem) => {
+                let ty = mem.ty();
+                let bytes = unsuffixed(mem.bytes as u64);
+
+                default_value.extend(quote! { [0; #bytes] });
+                mem_ty.extend(quote! {[u8; #bytes]});
+                mem_ty_gen.extend(quote! {[u8; #bytes]});
+
+                let doc = mem_doc.clone();
+                str_fns.extend(quote! {
+                    #doc
+                    pub fn #mem_name(&mut self) -> #fty_name<#templ> {
+                            #fty_name::new(self)
+                        }
+                });
+
+                mem_str_impl.extend(quote! {
+                    #[inline(always)]
+                    pub fn read(&self) -> crate::#ty {
+                        self.data.#getter()
+                    }
+
+                    #[inline(always)]
+                    pub fn set(&'a mut self, v : crate::#ty) -> &'a mut super::#str_name<#templ> {
+                        self.data.#mem_name = v.0;
+                        self.data
+                    }
+                });
+                direct_fns.extend(quote! {
+                    #[inline(always)]
+                    pub fn #getter(&self) -> crate::#ty {
+                        crate::#ty(self.#mem_name)
+                    }
+
+                    #[inline(always)]
+                    pub fn #setter(&mut self, v : crate::#ty) {
+                        #ty_name::new(self).set(v);
+                    }
+                });
+                reader_fns.extend(render_reader_fn(&mem_doc, &mem_name, &getter, quote! { crate::#ty }));
+
+                default_mems.extend(quote! {#mem_name : [0; #bytes],});
+
+                let reverse = match structure.byte_order {
+                    ByteOrder::LittleEndian => quote! { #mem_name.reverse(); },
+                    ByteOrder::BigEndian => quote! {},
+                };
+                mem_read.extend(quote! {
+                    let mut #mem_name = [0u8; #bytes];
+                    reader.read_exact(&mut #mem_name)?;
+                    #reverse
+                });
+                read_mems.extend(quote! {#mem_name, });
+
+                mem_write.extend(match structure.byte_order {
+                    ByteOrder::LittleEndian => quote! {
+                        let mut octets = self.#mem_name;
+                        octets.reverse();
+                        out.write_all(&octets)?;
+                    },
+                    ByteOrder::BigEndian => quote! {
+                        out.write_all(&self.#mem_name)?;
+                    },
+                });
+            }
+            StructMember::TimestampMember(mem) => {
+                let raw_ty = (mem.bytes * BITS_PER_BYTE).to_ty()?;
+                let check = mem.render_check(quote! { v });
+                let epoch = mem.epoch.unix_offset();
+                let tps = unsuffixed(mem.ticks_per_second as u64);
+                let nanos_per_tick = unsuffixed((1_000_000_000 / mem.ticks_per_second) as u64);
+
+                default_value.extend(quote! { #init });
+                mem_ty.extend(quote! {u64});
+                mem_ty_gen.extend(quote! {u64});
+
+                let doc = mem_doc.clone();
+                str_fns.extend(quote! {
+                    #doc
+                    pub fn #mem_name(&mut self) -> #fty_name<#templ> {
+                            #fty_name::new(self)
+                        }
+                });
+
+                mem_str_impl.extend(quote! {
+                    #[doc = "The number of ticks since the epoch."]
+                    #[inline(always)]
+                    pub fn read(&self) -> u64 {
+                        self.data.#getter()
+                    }
+
+                    #[inline(always)]
+                    pub fn set(&'a mut self, v : u64) -> &'a mut super::#str_name<#templ> {
+                        #check
+                        self.data.#mem_name = v;
+                        self.data
+                    }
+
+                    #[doc = "The date and time of the timestamp, or `None` if `chrono` cannot represent it."]
+                    #[cfg(feature = "chrono")]
+                    pub fn datetime(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+                        let ticks = self.data.#mem_name;
+                        let secs = i64::try_from(ticks / #tps).ok()?.checked_add(#epoch)?;
+                        let nanos = (ticks % #tps) as u32 * #nanos_per_tick;
+                        chrono::DateTime::from_timestamp(secs, nanos)
+                    }
+
+                    #[doc = "Set the timestamp to the date and time `v`, dropping the fraction of a tick. Panics if the timestamp cannot represent `v`."]
+                    #[cfg(feature = "chrono")]
+                    pub fn set_datetime(&'a mut self, v : chrono::DateTime<chrono::Utc>) -> &'a mut super::#str_name<#templ> {
+                        let ticks = v
+                            .timestamp()
+                            .checked_sub(#epoch)
+                            .and_then(|secs| u64::try_from(secs).ok())
+                            .and_then(|secs| secs.checked_mul(#tps))
+                            .and_then(|ticks| ticks.checked_add(u64::from(v.timestamp_subsec_nanos() / #nanos_per_tick)))
+                            .expect("timestamp out of range");
+                        self.set(ticks)
+                    }
+
+                    #[doc = "The timestamp as an instant counting from the epoch."]
+                    #[cfg(feature = "fugit")]
+                    #[inline(always)]
+                    pub fn instant(&self) -> fugit::Instant<u64, 1, #tps> {
+                        fugit::Instant::<u64, 1, #tps>::from_ticks(self.data.#mem_name)
+                    }
+
+                    #[cfg(feature = "fugit")]
+                    #[inline(always)]
+                    pub fn set_instant(&'a mut self, v : fugit::Instant<u64, 1, #tps>) -> &'a mut super::#str_name<#templ> {
+                        self.set(v.ticks())
+                    }
+                });
+                direct_fns.extend(quote! {
+                    #[inline(always)]
+                    pub fn #getter(&self) -> u64 {
+                        self.#mem_name
+                    }
+
+                    #[inline(always)]
+                    pub fn #setter(&mut self, v : u64) {
+                        #ty_name::new(self).set(v);
+                    }
+                });
+                reader_fns.extend(render_reader_fn(&mem_doc, &mem_name, &getter, quote! { u64 }));
+
+                default_mems.extend(quote! {#mem_name : #init,});
+
+                mem_read.extend(render_read_int(&mem_name, mem.bytes, structure.byte_order)?);
+                mem_read.extend(quote! {
+                    let #mem_name = u64::from(#mem_name);
+                });
+                read_mems.extend(quote! {#mem_name, });
+
+                mem_write.extend(render_write_int(
+                    quote! { self.#mem_name as #raw_ty },
+                    mem.bytes,
+                    structure.byte_order,
+                )?);
+            }
+            StructMember::BcdMember(mem) => {
+                let sty = mem.ty()?;
+                let raw_ty = mem.raw_ty()?;
+                let field_name = mem.name.as_str();
+                let max = unsuffixed(mem.max());
+
+                default_value.extend(quote! { #init });
+                mem_ty.extend(quote! {#sty});
+                mem_ty_gen.extend(quote! {#sty});
+
+                let doc = mem_doc.clone();
+                str_fns.extend(quote! {
+                    #doc
+                    pub fn #mem_name(&mut self) -> #fty_name<#templ> {
+                            #fty_name::new(self)
+                        }
+                });
+
+                mem_str_impl.extend(quote! {
+                    #[inline(always)]
+                    pub fn read(&self) -> #sty {
+                        self.data.#getter()
+                    }
+
+                    /// Set the value, or fail if it has more digits than the member.
+                    #[inline(always)]
+                    pub fn set(&'a mut self, v : #sty) -> Result<&'a mut super::#str_name<#templ>, crate::Error> {
+                        if v > #max {
+                            return Err(crate::Error::OutOfRange { field : #field_name, value : u64::from(v) });
+                        }
+                        self.data.#mem_name = v;
+                        Ok(self.data)
+                    }
+                });
+                direct_fns.extend(quote! {
+                    #[inline(always)]
+                    pub fn #getter(&self) -> #sty {
+                        self.#mem_name
+                    }
+
+                    /// Set the value, or fail if it has more digits than the member.
+                    #[inline(always)]
+                    pub fn #setter(&mut self, v : #sty) -> Result<(), crate::Error> {
+                        #ty_name::new(self).set(v).map(|_| ())
+                    }
+                });
+                reader_fns.extend(render_reader_fn(&mem_doc, &mem_name, &getter, quote! { #sty }));
+
+                default_mems.extend(quote! {#mem_name : #init,});
+
+                mem_read.extend(render_read_int(&mem_name, mem.bytes, structure.byte_order)?);
+                mem_read.extend(quote! {
+                    let #mem_name = match crate::bcd_decode(u64::from(#mem_name)) {
+                        Some(value) => value as #sty,
+                        None => return Err(Error::InvalidBcd { field : #field_name }),
+                    };
+                });
+                read_mems.extend(quote! {#mem_name, });
+
+                mem_write.extend(render_write_int(
+                    quote! { crate::bcd_encode(u64::from(self.#mem_name)) as #raw_ty },
+                    mem.bytes,
+                    structure.byte_order,
+                )?);
+            }
+            StructMember::OffsetMember(mem) => {
+                let sty = (mem.bytes * BITS_PER_BYTE).to_ty()?;
+                let field_name = mem.name.as_str();
+                let max = hex(mem.max());
+                let marker = hex(mem.marker);
+                let offset_bits = unsuffixed(mem.offset_bits() as u64);
+
+                default_value.extend(quote! { #init });
+                mem_ty.extend(quote! {#sty});
+                mem_ty_gen.extend(quote! {#sty});
+
+                let doc = mem_doc.clone();
+                str_fns.extend(quote! {
+                    #doc
+                    pub fn #mem_name(&mut self) -> #fty_name<#templ> {
+                            #fty_name::new(self)
+                        }
+                });
+
+                mem_str_impl.extend(quote! {
+                    #[inline(always)]
+                    pub fn read(&self) -> #sty {
+                        self.data.#getter()
+                    }
+
+                    #[doc = "The bytes of `buf` starting at the offset, where `buf` is the buffer the offset refers to."]
+                    pub fn resolve<'b>(&self, buf : &'b [u8]) -> Result<&'b [u8], crate::Error> {
+                        let offset = self.data.#mem_name as usize;
+                        match buf.get(offset..) {
+                            Some(target) if !target.is_empty() => Ok(target),
+                            _ => Err(crate::Error::InvalidOffset { field : #field_name, offset }),
+                        }
+                    }
+
+                    #[inline(always)]
+                    pub fn set(&'a mut self, v : #sty) -> &'a mut super::#str_name<#templ> {
+                        assert!(v <= #max);
+                        self.data.#mem_name = v;
+                        self.data
+                    }
+                });
+                direct_fns.extend(quote! {
+                    #[inline(always)]
+                    pub fn #getter(&self) -> #sty {
+                        self.#mem_name
+                    }
+
+                    #[inline(always)]
+                    pub fn #setter(&mut self, v : #sty) {
+                        #ty_name::new(self).set(v);
+                    }
+                });
+                reader_fns.extend(render_reader_fn(&mem_doc, &mem_name, &getter, quote! { #sty }));
+
+                default_mems.extend(quote! {#mem_name : #init,});
+
+                mem_read.extend(render_read_int(&mem_name, mem.bytes, structure.byte_order)?);
+                mem_read.extend(quote! {
+                    if #mem_name >> #offset_bits != #marker {
+                        return Err(Error::InvalidConstant { field : #field_name, value : #mem_name as u64 });
+                    }
+                    let #mem_name = #mem_name & #max;
+                });
+                read_mems.extend(quote! {#mem_name, });
+
+                mem_write.extend(render_write_int(
+                    quote! { (#marker << #offset_bits) | (self.#mem_name & #max) },
+                    mem.bytes,
+                    structure.byte_order,
+                )?);
+            }
+            StructMember::AlternativesMember(alt) => {
+                let alt_name_templ = alt.param(config.const_generic, &members, naming).ty;
+                let alt_pc_a = util::ident(
+                    &format!(
+                        "{}A",
+                        alt.alternatives.to_sanitized_pascal_case(&naming.types())
+                    ),
+                    span,
+                );
+
+                // the enum of the alternatives is seen from the module of the proxies
+                let field_ty = if config.const_generic {
+                    let index = util::ident(&alt.name.to_sanitized_upper_case(&members), span);
+                    default_value.extend(quote! { #alt_pc_a::default_at(#index) });
+                    quote! { super::#alt_pc_a }
+                } else {
+                    default_value.extend(quote! { #alt_name_templ::default() });
+                    alt_name_templ.clone()
+                };
+                mem_ty.extend(quote! {#alt_name_templ});
+                mem_ty_gen.extend(quote! {#alt_pc_a});
+                let check = alt.render_check(config.const_generic, quote! { value }, &members);
+
+                str_fns.extend(quote! {
+                    pub fn #mem_name(&mut self) -> #fty_name<#templ> {
+                        #fty_name::new(self)
+                    }
+                });
+
+                mem_str_impl.extend(quote! {
+                    #[inline(always)]
+                    pub fn read(&self) -> #field_ty {
+                        self.data.#getter()
+                    }
+
+                    #[inline(always)]
+                    pub fn modify<F>(&'a mut self, f : F) -> &'a mut super::#str_name<#templ> where for <'w> F : FnOnce(&'w mut #field_ty) -> &'w mut #field_ty {
+                        let mut cp = self.data.#mem_name;
+                        let value = *f(&mut cp);
+                        #check
+                        self.data.#mem_name = value;
+                        self.data
+                    }
+                });
+                direct_fns.extend(quote! {
+                    #[inline(always)]
+                    pub fn #getter(&self) -> #field_ty {
+                        self.#mem_name
+                    }
+                });
+                reader_fns.extend(render_reader_fn(&mem_doc, &mem_name, &getter, quote! { #field_ty }));
+
+                default_mems.extend(quote! {#mem_name : #mem_ty_gen::default(), });
+
+                mem_write.extend(quote! {
+                    self.#mem_name.write(out)?;
+                });
+
+                if let Some(disc) = &alt.discriminator {
+                    let bitfield = match structure.find_bitfield(&disc.bitfield, idx) {
+                        Some(bitfield) => bitfield,
+                        None => bail!(
+                            "alternatives field `{}` is discriminated by unknown bitfield `{}`",
+                            alt.name,
+                            disc.bitfield
+                        ),
+                    };
+                    let alts = alternatives.get(&alt.alternatives)?;
+                    let pkg_name = structure.bitfield_path(&bitfield.bitfield, false, naming);
+                    let disc_name =
+                        util::ident(&disc.bitfield.to_sanitized_snake_case(&members), span);
+                    let fields = naming.names_of(&bitfield.bitfield);
+                    let field_sc = util::ident(&disc.field.to_sanitized_snake_case(&fields), span);
+                    let field_pc_a = util::ident(
+                        &format!("{}A", disc.field.to_sanitized_pascal_case(&fields)),
+                        span,
+                    );
+                    let values = naming.values_of(&bitfield.bitfield, &disc.field);
+
+                    let field_name = disc.field.as_str();
+                    let mut read_arms = TokenStream::new();
+                    for altopt in &alts.alternatives {
+                        let variant = util::ident(
+                            &alts.discriminant(altopt)?.to_sanitized_pascal_case(&values),
+                            span,
+                        );
+                        let alt_enum_read = util::ident(
+                            &format!("read_{}", altopt.to_sanitized_snake_case(&naming.types())),
+                            span,
+                        );
+
+                        read_arms.extend(quote! {
+                            Ok(#pkg_name::#field_pc_a::#variant) => #alt_pc_a::#alt_enum_read(reader)?,
+                        });
+                    }
+
+                    mem_read_gen = Some(quote! {
+                        let #mem_name = {
+                            let bits = #pkg_name::R::new(#disc_name).#field_sc().bits();
+                            match #pkg_name::#field_pc_a::try_from(bits) {
+                                #read_arms
+                                #[allow(unreachable_patterns)]
+                                _ => return Err(Error::InvalidEnumValue { field : #field_name, value : bits as u64 }),
+                            }
+                        };
+                    });
+                } else {
+                    let alt_pc_sel = util::ident(
+                        &format!(
+                            "{}Selector",
+                            alt.alternatives.to_sanitized_pascal_case(&naming.types())
+                        ),
+                        span,
+                    );
+
+                    read_params_gen.extend(quote! { #mem_name : #alt_pc_sel, });
+                    mem_read_gen = Some(quote! {
+                        let #mem_name = #alt_pc_a::read(reader, #mem_name)?;
+                    });
+                }
+            }
+            StructMember::LengthMember(mem) => {
+                let sty = (mem.bytes * 8).to_ty()?;
+
+                default_value.extend(quote! { 0 });
+                mem_ty.extend(quote! {#sty});
+                mem_ty_gen.extend(quote! {#sty});
+
+                str_fns.extend(quote! {
+                    pub fn #mem_name(&mut self) -> #fty_name<#templ> {
+                        #fty_name::new(self)
+                    }
+                });
+
+                mem_str_impl.extend(quote! {
+                    #[inline(always)]
+                    pub fn read(&self) -> #sty {
+                        self.data.#getter()
+                    }
+                });
+                direct_fns.extend(quote! {
+                    #[inline(always)]
+                    pub fn #getter(&self) -> #sty {
+                        self.#mem_name
+                    }
+                });
+                reader_fns.extend(render_reader_fn(&mem_doc, &mem_name, &getter, quote! { #sty }));
+
+                default_mems.extend(quote! {#mem_name : 0,});
+
+                mem_read.extend(render_read_int(&mem_name, mem.bytes, structure.byte_order)?);
+                if mem.extra == 0 {
+                    mem_write.extend(render_write_int(
+                        quote! { self.#mem_name },
+                        mem.bytes,
+                        structure.byte_order,
+                    )?);
+                } else {
+                    let extra = unsuffixed(mem.extra as u64);
+                    let field_name = mem.name.as_str();
+                    mem_read.extend(quote! {
+                        let #mem_name = match #mem_name.checked_sub(#extra) {
+                            Some(len) => len,
+                            None => return Err(Error::LengthMismatch { field : #field_name, len : #mem_name as usize }),
+                        };
+                    });
+                    mem_write.extend(render_write_int(
+                        quote! { self.#mem_name + #extra },
+                        mem.bytes,
+                        structure.byte_order,
+                    )?);
+                }
+                read_mems.extend(quote! {#mem_name, });
+            }
+            StructMember::BytesMember(mem) => {
+                let len = match structure.find_len_source(&mem.len_from, idx) {
+                    Some(len) => len,
+                    None => bail!(
+                        "bytes field `{}` refers to unknown length field `{}`",
+                        mem.name,
+                        mem.len_from
+                    ),
+                };
+                let len_name = util::ident(&len.name().to_sanitized_snake_case(&members), span);
+                let lty = len.ty()?;
+                let capacity = unsuffixed(mem.capacity as u64);
+
+                str_fns.extend(quote! {
+                    pub fn #mem_name(&mut self) -> #fty_name<#templ> {
+                        #fty_name::new(self)
+                    }
+                });
+
+                let field_name = mem.name.as_str();
+                mem_read.extend(quote! {
+                    if #len_name as usize > #capacity {
+                        return Err(Error::LengthMismatch { field : #field_name, len : #len_name as usize });
+                    }
+                });
+                // shared with the setter of the mutable view
+                mem_str_impl.extend(quote! {
+                    /// Check that the payload `v` fits the capacity.
+                    #[inline(always)]
+                    pub fn check(v : &[u8]) -> Result<(), crate::Error> {
+                        if v.len() > #capacity {
+                            return Err(crate::Error::LengthMismatch { field : #field_name, len : v.len() });
+                        }
+                        Ok(())
+                    }
+                });
+                let check = quote! { Self::check(v)?; };
+                read_mems.extend(quote! {#mem_name, });
+
+                match structure.payloads {
+                    Payloads::Array => {
+                        default_value.extend(quote! { [0u8; #capacity] });
+                        mem_ty.extend(quote! {[u8; #capacity]});
+                        mem_ty_gen.extend(quote! {[u8; #capacity]});
+
+                        mem_str_impl.extend(quote! {
+                            #[inline(always)]
+                            pub fn read(&self) -> &[u8] {
+                                self.data.#getter()
+                            }
+
+                            /// Set the payload, updating the associated length field, or fail if it
+                            /// exceeds the capacity.
+                            #[inline(always)]
+                            pub fn set(&'a mut self, v : &[u8]) -> Result<&'a mut super::#str_name<#templ>, crate::Error> {
+                                #check
+                                self.data.#mem_name[..v.len()].copy_from_slice(v);
+                                self.data.#len_name = v.len() as #lty;
+                                Ok(self.data)
+                            }
+                        });
+                        direct_fns.extend(quote! {
+                            #[inline(always)]
+                            pub fn #getter(&self) -> &[u8] {
+                                let len = self.#len_name as usize;
+                                &self.#mem_name[..len]
+                            }
+
+                            /// Set the payload, updating the associated length field, or fail if it
+                            /// exceeds the capacity.
+                            #[inline(always)]
+                            pub fn #setter(&mut self, v : &[u8]) -> Result<(), crate::Error> {
+                                #ty_name::new(self).set(v).map(|_| ())
+                            }
+                        });
+                        reader_fns.extend(render_reader_fn(&mem_doc, &mem_name, &getter, quote! { &[u8] }));
+
+                        default_mems.extend(quote! {#mem_name : [0u8; #capacity],});
+
+                        mem_read.extend(quote! {
+                            let mut #mem_name = [0u8; #capacity];
+                            reader.read_exact(&mut #mem_name[..#len_name as usize])?;
+                        });
+
+                        mem_write.extend(quote! {
+                            out.write_all(&self.#mem_name[..self.#len_name as usize])?;
+                        });
+                    }
+                    Payloads::Heapless | Payloads::Alloc => {
+                        let (ty, new, extend, alloc) = match structure.payloads {
+                            Payloads::Heapless => (
+                                quote! { heapless::Vec<u8, #capacity> },
+                                quote! { heapless::Vec::new() },
+                                quote! {
+                                    // cannot fail as the length was checked against the capacity
+                                    let _ = self.data.#mem_name.extend_from_slice(v);
+                                },
+                                quote! {
+                                    let mut #mem_name = heapless::Vec::<u8, #capacity>::new();
+                                    // cannot fail as the length was checked against the capacity
+                                    let _ = #mem_name.resize(#len_name as usize, 0);
+                                },
+                            ),
+                            _ => (
+                                quote! { alloc::vec::Vec<u8> },
+                                quote! { alloc::vec::Vec::new() },
+                                quote! {
+                                    self.data.#mem_name.extend_from_slice(v);
+                                },
+                                quote! {
+                                    let mut #mem_name = alloc::vec![0u8; #len_name as usize];
+                                },
+                            ),
+                        };
+
+                        default_value.extend(new.clone());
+                        mem_ty.extend(ty.clone());
+                        mem_ty_gen.extend(ty);
+
+                        mem_str_impl.extend(quote! {
+                            #[inline(always)]
+                            pub fn read(&self) -> &[u8] {
+                                self.data.#getter()
+                            }
+
+                            /// Set the payload, updating the associated length field, or fail if it
+                            /// exceeds the capacity.
+                            #[inline(always)]
+                            pub fn set(&'a mut self, v : &[u8]) -> Result<&'a mut super::#str_name<#templ>, crate::Error> {
+                                #check
+                                self.data.#mem_name.clear();
+                                #extend
+                                self.data.#len_name = v.len() as #lty;
+                                Ok(self.data)
+                            }
+                        });
+                        direct_fns.extend(quote! {
+                            #[inline(always)]
+                            pub fn #getter(&self) -> &[u8] {
+                                &self.#mem_name
+                            }
+
+                            /// Set the payload, updating the associated length field, or fail if it
+                            /// exceeds the capacity.
+                            #[inline(always)]
+                            pub fn #setter(&mut self, v : &[u8]) -> Result<(), crate::Error> {
+                                #ty_name::new(self).set(v).map(|_| ())
+                            }
+                        });
+                        reader_fns.extend(render_reader_fn(&mem_doc, &mem_name, &getter, quote! { &[u8] }));
+
+                        default_mems.extend(quote! {#mem_name : #new,});
+
+                        mem_read.extend(quote! {
+                            #alloc
+                            reader.read_exact(&mut #mem_name)?;
+                        });
+
+                        mem_write.extend(quote! {
+                            out.write_all(&self.#mem_name)?;
+                        });
+                    }
+                }
+            }
+            StructMember::ExtendedMember(mem) => {
+                let capacity = unsuffixed(mem.capacity as u64);
+
+                default_value.extend(quote! { [0u8; #capacity] });
+                mem_ty.extend(quote! {[u8; #capacity]});
+                mem_ty_gen.extend(quote! {[u8; #capacity]});
+
+                str_fns.extend(quote! {
+                    pub fn #mem_name(&mut self) -> #fty_name<#templ> {
+                        #fty_name::new(self)
+                    }
+                });
+
+                mem_str_impl.extend(quote! {
+                    #[inline(always)]
+                    pub fn read(&self) -> &[u8] {
+                        self.data.#getter()
+                    }
+
+                    /// Set the bytes of the chain, setting the extension bit of all but the last.
+                    #[inline(always)]
+                    pub fn set(&'a mut self, v : &[u8]) -> &'a mut super::#str_name<#templ> {
+                        assert!(!v.is_empty() && v.len() <= #capacity);
+                        self.data.#mem_name = [0u8; #capacity];
+                        for (i, byte) in v.iter().enumerate() {
+                            self.data.#mem_name[i] = if i + 1 < v.len() { byte | 0x80 } else { byte & 0x7f };
+                        }
+                        self.data
+                    }
+                });
+                direct_fns.extend(quote! {
+                    #[inline(always)]
+                    pub fn #getter(&self) -> &[u8] {
+                        &self.#mem_name[..crate::chain_len(&self.#mem_name)]
+                    }
+
+                    /// Set the bytes of the chain, setting the extension bit of all but the last.
+                    #[inline(always)]
+                    pub fn #setter(&mut self, v : &[u8]) {
+                        #ty_name::new(self).set(v);
+                    }
+                });
+                reader_fns.extend(render_reader_fn(&mem_doc, &mem_name, &getter, quote! { &[u8] }));
+
+                default_mems.extend(quote! {#mem_name : [0u8; #capacity],});
+
+                let field_name = mem.name.as_str();
+                mem_read.extend(quote! {
+                    let mut #mem_name = [0u8; #capacity];
+                    let mut len = 0;
+                    loop {
+                        if len == #capacity {
+                            return Err(Error::LengthMismatch { field : #field_name, len : len + 1 });
+                        }
+                        reader.read_exact(&mut #mem_name[len..len + 1])?;
+                        len += 1;
+                        if #mem_name[len - 1] & 0x80 == 0 {
+                            break;
+                        }
+                    }
+                });
+                read_mems.extend(quote! {#mem_name, });
+
+                mem_write.extend(quote! {
+                    out.write_all(&self.#mem_name[..crate::chain_len(&self.#mem_name)])?;
+                });
+            }
+            StructMember::BitsMember(mem) => {
+                let sty = mem.ty()?;
+                let max = hex(mem.max());
+
+                default_value.extend(quote! { #init });
+                mem_ty.extend(quote! {#sty});
+                mem_ty_gen.extend(quote! {#sty});
+
+                let doc = mem_doc.clone();
+                str_fns.extend(quote! {
+                    #doc
+                    pub fn #mem_name(&mut self) -> #fty_name<#templ> {
+                            #fty_name::new(self)
+                        }
+                });
+
+                mem_str_impl.extend(quote! {
+                    #[inline(always)]
+                    pub fn read(&self) -> #sty {
+                        self.data.#getter()
+                    }
+
+                    #[inline(always)]
+                    pub fn set(&'a mut self, v : #sty) -> &'a mut super::#str_name<#templ> {
+                        assert!(v <= #max);
+                        self.data.#mem_name = v;
+                        self.data
+                    }
+                });
+                direct_fns.extend(quote! {
+                    #[inline(always)]
+                    pub fn #getter(&self) -> #sty {
+                        self.#mem_name
+                    }
+
+                    #[inline(always)]
+                    pub fn #setter(&mut self, v : #sty) {
+                        #ty_name::new(self).set(v);
+                    }
+                });
+                reader_fns.extend(render_reader_fn(&mem_doc, &mem_name, &getter, quote! { #sty }));
+
+                default_mems.extend(quote! {#mem_name : #init,});
+
+                // the bytes of a run are read with its first member and written with its last
+                let start = structure.bits_run_start(idx);
+                let run = util::ident(
+                    &format!(
+                        "bits_{}",
+                        structure.members[start]
+                            .name()
+                            .to_sanitized_snake_case(&members)
+                    ),
+                    span,
+                );
+                if mem.offset == 0 {
+                    let run_bytes = unsuffixed(structure.bits_run_bytes(idx) as u64);
+                    mem_read.extend(quote! {
+                        let mut #run = [0u8; #run_bytes];
+                        reader.read_exact(&mut #run)?;
+                    });
+                    mem_write.extend(quote! {
+                        let mut #run = [0u8; #run_bytes];
+                    });
+                }
+
+                let run = quote! { #run };
+                let value = render_read_bits(&run, mem.offset, mem.bits, structure.byte_order)?;
+                mem_read.extend(quote! {
+                    let #mem_name = #value;
+                });
+                read_mems.extend(quote! {#mem_name, });
+
+                mem_write.extend(render_write_bits(
+                    &run,
+                    &quote! { self.#mem_name },
+                    mem.offset,
+                    mem.bits,
+                    structure.byte_order,
+                ));
+                if mem.is_aligned() {
+                    mem_write.extend(quote! {
+                        out.write_all(&#run)?;
+                    });
+                }
+            }
+            StructMember::ArrayMember(mem) => {
+                let sty = (mem.bytes * 8).to_ty()?;
+                let count = unsuffixed(mem.count as u64);
+                let element = Ident::new("element", span);
+                let read_element = render_read_int(&element, mem.bytes, structure.byte_order)?;
+                let write_element =
+                    render_write_int(quote! { *#element }, mem.bytes, structure.byte_order)?;
+
+                default_value.extend(quote! { [0; #count] });
+                mem_ty.extend(quote! {[#sty; #count]});
+                mem_ty_gen.extend(quote! {[#sty; #count]});
+
+                str_fns.extend(quote! {
+                    pub fn #mem_name(&mut self) -> #fty_name<#templ> {
+                        #fty_name::new(self)
+                    }
+                });
+
+                mem_str_impl.extend(quote! {
+                    #[inline(always)]
+                    pub fn read(&self) -> [#sty; #count] {
+                        self.data.#getter()
+                    }
+
+                    #[inline(always)]
+                    pub fn set(&'a mut self, v : [#sty; #count]) -> &'a mut super::#str_name<#templ> {
+                        self.data.#mem_name = v;
+                        self.data
+                    }
+                });
+                direct_fns.extend(quote! {
+                    #[inline(always)]
+                    pub fn #getter(&self) -> [#sty; #count] {
+                        self.#mem_name
+                    }
+
+                    #[inline(always)]
+                    pub fn #setter(&mut self, v : [#sty; #count]) {
+                        #ty_name::new(self).set(v);
+                    }
+                });
+                reader_fns.extend(render_reader_fn(&mem_doc, &mem_name, &getter, quote! { [#sty; #count] }));
+
+                default_mems.extend(quote! {#mem_name : [0; #count],});
+
+                mem_read.extend(quote! {
+                    let mut #mem_name = [0; #count];
+                    for slot in #mem_name.iter_mut() {
+                        #read_element
+                        *slot = #element;
+                    }
+                });
+                read_mems.extend(quote! {#mem_name, });
+
+                mem_write.extend(quote! {
+                    for #element in self.#mem_name.iter() {
+                        #write_element
+                    }
+                });
+            }
+            StructMember::OptionalMember(mem) => {
+                let bitfield = match structure.find_bitfield(&mem.flag.bitfield, idx) {
+                    Some(bitfield) => bitfield,
+                    None => bail!(
+                        "optional field `{}` is flagged by unknown bitfield `{}`",
+                        mem.name,
+                        mem.flag.bitfield
+                    ),
+                };
+                let pkg_name = structure.bitfield_path(&bitfield.bitfield, false, naming);
+                let flag_name =
+                    util::ident(&mem.flag.bitfield.to_sanitized_snake_case(&members), span);
+                let sty = util::ident(
+                    &mem.structure.to_sanitized_pascal_case(&naming.types()),
+                    span,
+                );
+                let value = if mem.copy {
+                    quote! { self.#mem_name }
+                } else {
+                    quote! { self.#mem_name.clone() }
+                };
+
+                default_value.extend(quote! { None });
+                mem_ty.extend(quote! {Option<#sty>});
+                mem_ty_gen.extend(quote! {Option<#sty>});
+
+                str_fns.extend(quote! {
+                    pub fn #mem_name(&mut self) -> #fty_name<#templ> {
+                        #fty_name::new(self)
+                    }
+                });
+
+                mem_str_impl.extend(quote! {
+                    #[inline(always)]
+                    pub fn read(&self) -> Option<super::#sty> {
+                        self.data.#getter()
+                    }
+
+                    /// Set the optional value, the presence flag is updated when writing.
+                    #[inline(always)]
+                    pub fn set(&'a mut self, v : Option<super::#sty>) -> &'a mut super::#str_name<#templ> {
+                        self.data.#mem_name = v;
+                        self.data
+                    }
+                });
+                direct_fns.extend(quote! {
+                    #[inline(always)]
+                    pub fn #getter(&self) -> Option<super::#sty> {
+                        #value
+                    }
+
+                    /// Set the optional value, the presence flag is updated when writing.
+                    #[inline(always)]
+                    pub fn #setter(&mut self, v : Option<super::#sty>) {
+                        #ty_name::new(self).set(v);
+                    }
+                });
+                reader_fns.extend(render_reader_fn(&mem_doc, &mem_name, &getter, quote! { Option<super::#sty> }));
+
+                default_mems.extend(quote! {#mem_name : None,});
+
+                let present = mem.render_present(
+                    quote! { #pkg_name::R::new(#flag_name) },
+                    &bitfield.bitfield,
+                    naming,
+                );
+                mem_read.extend(quote! {
+                    let #mem_name = if #present {
+                        Some(#sty::read(reader)?)
+                    } else {
+                        None
+                    };
+                });
+                read_mems.extend(quote! {#mem_name, });
+
+                mem_write.extend(quote! {
+                    if let Some(v) = &self.#mem_name {
+                        v.write(out)?;
+                    }
+                });
+            }
+            StructMember::StringMember(mem) => {
+                let capacity = unsuffixed(mem.capacity as u64);
+                let field_name = mem.name.as_str();
+                let (ty, new, push, fill) = match structure.payloads {
+                    Payloads::Alloc => (
+                        quote! { alloc::string::String },
+                        quote! { alloc::string::String::new() },
+                        quote! {
+                            assert!(v.len() <= #capacity);
+                            self.data.#mem_name.push_str(v);
+                        },
+                        quote! { string.push_str(s); },
+                    ),
+                    _ => (
+                        quote! { heapless::String<#capacity> },
+                        quote! { heapless::String::new() },
+                        quote! {
+                            self.data.#mem_name.push_str(v).expect("string too long");
+                        },
+                        quote! { let _ = string.push_str(s); },
+                    ),
+                };
+
+                default_value.extend(new.clone());
+                mem_ty.extend(ty.clone());
+                mem_ty_gen.extend(ty);
+
+                str_fns.extend(quote! {
+                    pub fn #mem_name(&mut self) -> #fty_name<#templ> {
+                        #fty_name::new(self)
+                    }
+                });
+
+                mem_str_impl.extend(quote! {
+                    #[inline(always)]
+                    pub fn read(&self) -> &str {
+                        self.data.#getter()
+                    }
+                });
+                direct_fns.extend(quote! {
+                    #[inline(always)]
+                    pub fn #getter(&self) -> &str {
+                        self.#mem_name.as_str()
+                    }
+                });
+                reader_fns.extend(render_reader_fn(&mem_doc, &mem_name, &getter, quote! { &str }));
+
+                // the bytes of the string are read into `buffer` up to `len`
+                let (set, read, write) = match &mem.kind {
+                    StringKind::Fixed => (
+                        quote! {
+                            assert!(!v.contains('\0'));
+                        },
+                        quote! {
+                            let mut buffer = [0u8; #capacity];
+                            reader.read_exact(&mut buffer)?;
+                            let len = buffer.iter().position(|b| *b == 0).unwrap_or(#capacity);
+                        },
+                        quote! {
+                            out.write_all(self.#mem_name.as_bytes())?;
+                            out.write_all(&[0u8; #capacity][self.#mem_name.len()..])?;
+                        },
+                    ),
+                    StringKind::NullTerminated => (
+                        quote! {
+                            assert!(!v.contains('\0'));
+                        },
+                        quote! {
+                            let mut buffer = [0u8; #capacity];
+                            let mut len = 0;
+                            loop {
+                                let mut byte = [0u8; 1];
+                                reader.read_exact(&mut byte)?;
+                                if byte[0] == 0 {
+                                    break;
+                                }
+                                if len == #capacity {
+                                    return Err(Error::LengthMismatch { field : #field_name, len : len + 1 });
+                                }
+                                buffer[len] = byte[0];
+                                len += 1;
+                            }
+                        },
+                        quote! {
+                            out.write_all(self.#mem_name.as_bytes())?;
+                            out.write_all(&[0])?;
+                        },
+                    ),
+                    StringKind::Prefixed(len_from) => {
+                        let len = match structure.find_len_source(len_from, idx) {
+                            Some(len) => len,
+                            None => bail!(
+                                "string field `{}` refers to unknown length field `{}`",
+                                mem.name,
+                                len_from
+                            ),
+                        };
+                        let len_name =
+                            util::ident(&len.name().to_sanitized_snake_case(&members), span);
+                        let lty = len.ty()?;
+                        (
+                            quote! {
+                                self.data.#len_name = v.len() as #lty;
+                            },
+                            quote! {
+                                if #len_name as usize > #capacity {
+                                    return Err(Error::LengthMismatch { field : #field_name, len : #len_name as usize });
+                                }
+                                let mut buffer = [0u8; #capacity];
+                                let len = #len_name as usize;
+                                reader.read_exact(&mut buffer[..len])?;
+                            },
+                            quote! {
+                                out.write_all(self.#mem_name.as_bytes())?;
+                            },
+                        )
+                    }
+                };
+
+                let doc = match &mem.kind {
+                    StringKind::Prefixed(_) => {
+                        "Set the string, updating the associated length field."
+                    }
+                    _ => "Set the string, which must not hold a NUL character.",
+                };
+                mem_str_impl.extend(quote! {
+                    #[doc = #doc]
+                    #[inline(always)]
+                    pub fn set(&'a mut self, v : &str) -> &'a mut super::#str_name<#templ> {
+                        #set
+                        self.data.#mem_name.clear();
+                        #push
+                        self.data
+                    }
+                });
+                direct_fns.extend(quote! {
+                    #[doc = #doc]
+                    #[inline(always)]
+                    pub fn #setter(&mut self, v : &str) {
+                        #ty_name::new(self).set(v);
+                    }
+                });
+
+                default_mems.extend(quote! {#mem_name : #new,});
+
+                mem_read.extend(quote! {
+                    let #mem_name = {
+                        #read
+                        let mut string = #new;
+                        match core::str::from_utf8(&buffer[..len]) {
+                            Ok(s) => {
+                                #fill
+                            }
+                            Err(_) => return Err(Error::InvalidString { field : #field_name }),
+                        }
+                        string
+                    };
+                });
+                read_mems.extend(quote! {#mem_name, });
+
+                mem_write.extend(write);
+            }
+            StructMember::VarintMember(mem) => {
+                let sty = mem.ty()?;
+                let max = hex(mem.max());
+                let max_bits = unsuffixed((mem.max_bytes() * 7) as u64);
+                let field_name = mem.name.as_str();
+
+                default_value.extend(quote! { #init });
+                mem_ty.extend(quote! {#sty});
+                mem_ty_gen.extend(quote! {#sty});
+
+                str_fns.extend(quote! {
+                    pub fn #mem_name(&mut self) -> #fty_name<#templ> {
+                        #fty_name::new(self)
+                    }
+                });
+
+                mem_str_impl.extend(quote! {
+                    #[inline(always)]
+                    pub fn read(&self) -> #sty {
+                        self.data.#getter()
+                    }
+                });
+                direct_fns.extend(quote! {
+                    #[inline(always)]
+                    pub fn #getter(&self) -> #sty {
+                        self.#mem_name
+                    }
+                });
+                reader_fns.extend(render_reader_fn(&mem_doc, &mem_name, &getter, quote! { #sty }));
+
+                // varints counting another member are set along with it
+                if !structure.is_len_source(&mem.name) {
+                    mem_str_impl.extend(quote! {
+                        #[inline(always)]
+                        pub fn set(&'a mut self, v : #sty) -> &'a mut super::#str_name<#templ> {
+                            assert!(v <= #max);
+                            self.data.#mem_name = v;
+                            self.data
+                        }
+                    });
+                    direct_fns.extend(quote! {
+                        #[inline(always)]
+                        pub fn #setter(&mut self, v : #sty) {
+                            #ty_name::new(self).set(v);
+                        }
+                    });
+                }
+
+                default_mems.extend(quote! {#mem_name : #init,});
+
+                // bits shifted out of the value or beyond its largest value make it invalid
+                mem_read.extend(quote! {
+                    let #mem_name = {
+                        let mut value = 0u64;
+                        let mut shift = 0;
+                        loop {
+                            if shift == #max_bits {
+                                return Err(Error::InvalidVarint { field : #field_name });
+                            }
+                            let mut buffer = [0u8; 1];
+                            reader.read_exact(&mut buffer)?;
+                            let bits = (buffer[0] & 0x7f) as u64;
+                            if (bits << shift) >> shift != bits {
+                                return Err(Error::InvalidVarint { field : #field_name });
+                            }
+                            value |= bits << shift;
+                            shift += 7;
+                            if buffer[0] & 0x80 == 0 {
+                                break;
+                            }
+                        }
+                        if value > #max {
+                            return Err(Error::InvalidVarint { field : #field_name });
+                        }
+                        value as #sty
+                    };
+                });
+                read_mems.extend(quote! {#mem_name, });
+
+                mem_write.extend(quote! {
+                    {
+                        let mut value = u64::from(self.#mem_name);
+                        loop {
+                            let byte = (value & 0x7f) as u8;
+                            value >>= 7;
+                            if value == 0 {
+                                out.write_all(&[byte])?;
+                                break;
+                            }
+                            out.write_all(&[byte | 0x80])?;
+                        }
+                    }
+                });
+            }
+            StructMember::NibbleVarintMember(mem) => {
+                let max = unsuffixed(NibbleVarintMember::MAX as u64);
+                let field_name = mem.name.as_str();
+
+                default_value.extend(quote! { 0 });
+                mem_ty.extend(quote! {u32});
+                mem_ty_gen.extend(quote! {u32});
+
+                str_fns.extend(quote! {
+                    pub fn #mem_name(&mut self) -> #fty_name<#templ> {
+                        #fty_name::new(self)
+                    }
+                });
+
+                mem_str_impl.extend(quote! {
+                    #[inline(always)]
+                    pub fn read(&self) -> u32 {
+                        self.data.#getter()
+                    }
+                });
+                direct_fns.extend(quote! {
+                    #[inline(always)]
+                    pub fn #getter(&self) -> u32 {
+                        self.#mem_name
+                    }
+                });
+                reader_fns.extend(render_reader_fn(&mem_doc, &mem_name, &getter, quote! { u32 }));
+
+                // varints counting another member are set along with it
+                if !structure.is_len_source(&mem.name) {
+                    mem_str_impl.extend(quote! {
+                        #[inline(always)]
+                        pub fn set(&'a mut self, v : u32) -> &'a mut super::#str_name<#templ> {
+                            assert!(v <= #max);
+                            self.data.#mem_name = v;
+                            self.data
+                        }
+                    });
+                    direct_fns.extend(quote! {
+                        #[inline(always)]
+                        pub fn #setter(&mut self, v : u32) {
+                            #ty_name::new(self).set(v);
+                        }
+                    });
+                }
+
+                default_mems.extend(quote! {#mem_name : 0,});
+
+                let nibble = match &mem.low {
+                    Some(low) => {
+                        let low_name = util::ident(&low.to_sanitized_snake_case(&members), span);
+                        let high_nibble = render_nibble(quote! { self.#mem_name }, field_name);
+                        let low_nibble = render_nibble(quote! { self.#low_name }, low);
+
+                        mem_read.extend(quote! {
+                            let mut buffer = [0u8; 1];
+                            reader.read_exact(&mut buffer)?;
+                            let nibbles = buffer[0];
+                        });
+                        mem_write.extend(quote! {
+                            out.write_all(&[(#high_nibble << 4) | #low_nibble])?;
+                        });
+                        quote! { nibbles >> 4 }
+                    }
+                    None => quote! { nibbles & 0x0f },
+                };
+
+                let extended = Ident::new("extended", span);
+                let read_extended = render_read_int(&extended, 2, structure.byte_order)?;
+                mem_read.extend(quote! {
+                    let #mem_name = match #nibble {
+                        nibble @ 0..=12 => nibble as u32,
+                        13 => {
+                            let mut buffer = [0u8; 1];
+                            reader.read_exact(&mut buffer)?;
+                            buffer[0] as u32 + 13
+                        }
+                        14 => {
+                            #read_extended
+                            #extended as u32 + 269
+                        }
+                        _ => return Err(Error::InvalidVarint { field : #field_name }),
+                    };
+                });
+                read_mems.extend(quote! {#mem_name, });
+
+                let write_extended = render_write_int(
+                    quote! { (self.#mem_name - 269) as u16 },
+                    2,
+                    structure.byte_order,
+                )?;
+                mem_write.extend(quote! {
+                    match self.#mem_name {
+                        0..=12 => {}
+                        13..=268 => out.write_all(&[(self.#mem_name - 13) as u8])?,
+                        _ => {
+                            #write_extended
+                        }
+                    }
+                });
+            }
+            StructMember::ConstantMember(mem) => {
+                let sty = (mem.bytes * 8).to_ty()?;
+                let const_name = util::ident(&mem.name.to_sanitized_upper_case(&members), span);
+                let value = hex(mem.value);
+                let doc = format!("Value of the `{}` constant.", mem.name);
+                let field_name = mem.name.as_str();
+
+                stored = false;
+
+                consts.extend(quote! {
+                    #[doc = #doc]
+                    pub const #const_name : #sty = #value;
+                });
+
+                mem_read.extend(render_read_int(&mem_name, mem.bytes, structure.byte_order)?);
+                mem_read.extend(quote! {
+                    if #mem_name != Self::#const_name {
+                        return Err(Error::InvalidConstant { field : #field_name, value : #mem_name as u64 });
+                    }
+                });
+
+                mem_write.extend(render_write_int(
+                    quote! { Self::#const_name },
+                    mem.bytes,
+                    structure.byte_order,
+                )?);
+            }
+            StructMember::ChecksumMember(mem) => {
+                let from = match structure.members[..idx]
+                    .iter()
+                    .position(|other| other.name() == mem.from)
+                {
+                    Some(from) => from,
+                    None => bail!(
+                        "checksum field `{}` starts at unknown member `{}`",
+                        mem.name,
+                        mem.from
+                    ),
+                };
+                let sty = (mem.algorithm.bytes() * 8).to_ty()?;
+                let algorithm = mem.algorithm.ident();
+                let compute_fn = util::ident(&format!("compute_{}", mem_name), span);
+                let covered: TokenStream = write_pieces[from..idx].iter().cloned().collect();
+                let doc = format!(
+                    "Compute the `{}` checksum over the members starting at `{}`.",
+                    mem.name, mem.from
+                );
+                let extra = match &mem.extra {
+                    Some(extra) => {
+                        let key = match structure.members[..idx]
+                            .iter()
+                            .find(|other| other.name() == extra.key)
+                        {
+                            Some(StructMember::PrimitiveMember(key)) => key,
+                            _ => bail!(
+                                "checksum field `{}` selects its extra byte by `{}`, which is not a preceding primitive field",
+                                mem.name,
+                                extra.key
+                            ),
+                        };
+                        let key_name =
+                            util::ident(&key.name.to_sanitized_snake_case(&members), span);
+                        let key_field = key.name.as_str();
+                        let select = extra.render(quote! { self.#key_name });
+                        quote! {
+                            match #select {
+                                Some(extra) => checksum.update(&[extra]),
+                                None => return Err(Error::InvalidEnumValue { field : #key_field, value : self.#key_name as u64 }),
+                            }
+                        }
+                    }
+                    None => quote! {},
+                };
+
+                stored = false;
+
+                checksum_fns.extend(quote! {
+                    #[doc = #doc]
+                    pub fn #compute_fn(&self) -> Result<#sty, Error> {
+                        let mut checksum = crate::#algorithm::new();
+                        let out = &mut checksum;
+                        #covered
+                        #extra
+                        Ok(checksum.finish())
+                    }
+                });
+
+                let order = mem.algorithm.byte_order(structure.byte_order);
+                mem_read.extend(render_read_int(&mem_name, mem.algorithm.bytes(), order)?);
+                let field_name = mem.name.as_str();
+                read_checks.extend(quote! {
+                    if result.#compute_fn()? != #mem_name {
+                        return Err(Error::ChecksumMismatch { field : #field_name });
+                    }
+                });
+
+                mem_write.extend(render_write_int(
+                    quote! { self.#compute_fn()? },
+                    mem.algorithm.bytes(),
+                    order,
+                )?);
+            }
+            StructMember::RepeatedMember(mem) => {
+                // a length field is set along with the elements, a field of a bitfield is
+                // updated when writing
+                let (count, set_count) = match &mem.count_field {
+                    Some(field) => {
+                        let bitfield = match structure.find_bitfield(&mem.count_from, idx) {
+                            Some(bitfield) => bitfield,
+                            None => bail!(
+                                "repeated field `{}` is counted by unknown bitfield `{}`",
+                                mem.name,
+                                mem.count_from
+                            ),
+                        };
+                        let pkg_name = structure.bitfield_path(&bitfield.bitfield, false, naming);
+                        let bitfield_name =
+                            util::ident(&mem.count_from.to_sanitized_snake_case(&members), span);
+                        let field_sc = util::ident(
+                            &field.to_sanitized_snake_case(&naming.names_of(&bitfield.bitfield)),
+                            span,
+                        );
+                        (quote! { #pkg_name::R::new(#bitfield_name).#field_sc() }, quote! {})
+                    }
+                    None => {
+                        let len = match structure.find_len_source(&mem.count_from, idx) {
+                            Some(len) => len,
+                            None => bail!(
+                                "repeated field `{}` refers to unknown length field `{}`",
+                                mem.name,
+                                mem.count_from
+                            ),
+                        };
+                        let len_name =
+                            util::ident(&len.name().to_sanitized_snake_case(&members), span);
+                        let lty = len.ty()?;
+                        (
+                            quote! { #len_name },
+                            quote! { self.data.#len_name = self.data.#mem_name.len() as #lty; },
+                        )
+                    }
+                };
+                let (set_doc, push_doc) = match mem.count_field {
+                    Some(_) => (
+                        "Set the elements, the count is updated when writing.",
+                        "Append an element, the count is updated when writing.",
+                    ),
+                    None => (
+                        "Set the elements, updating the associated length field.",
+                        "Append an element, updating the associated length field.",
+                    ),
+                };
+                let sty = (mem.bytes * 8).to_ty()?;
+                let capacity = unsuffixed(mem.capacity as u64);
+                let element = Ident::new("element", span);
+                let read_element = render_read_int(&element, mem.bytes, structure.byte_order)?;
+                let write_element =
+                    render_write_int(quote! { *#element }, mem.bytes, structure.byte_order)?;
+
+                default_value.extend(quote! { heapless::Vec::new() });
+                mem_ty.extend(quote! {heapless::Vec<#sty, #capacity>});
+                mem_ty_gen.extend(quote! {heapless::Vec<#sty, #capacity>});
+
+                str_fns.extend(quote! {
+                    pub fn #mem_name(&mut self) -> #fty_name<#templ> {
+                        #fty_name::new(self)
+                    }
+                });
+
+                mem_str_impl.extend(quote! {
+                    #[inline(always)]
+                    pub fn read(&self) -> &[#sty] {
+                        self.data.#getter()
+                    }
+
+                    #[doc = #set_doc]
+                    #[inline(always)]
+                    pub fn set(&'a mut self, v : &[#sty]) -> &'a mut super::#str_name<#templ> {
+                        self.data.#mem_name.clear();
+                        self.data.#mem_name.extend_from_slice(v).expect("too many elements");
+                        #set_count
+                        self.data
+                    }
+
+                    #[doc = #push_doc]
+                    #[inline(always)]
+                    pub fn push(&'a mut self, v : #sty) -> &'a mut super::#str_name<#templ> {
+                        self.data.#mem_name.push(v).expect("too many elements");
+                        #set_count
+                        self.data
+                    }
+                });
+                direct_fns.extend(quote! {
+                    #[inline(always)]
+                    pub fn #getter(&self) -> &[#sty] {
+                        &self.#mem_name
+                    }
+
+                    #[doc = #set_doc]
+                    #[inline(always)]
+                    pub fn #setter(&mut self, v : &[#sty]) {
+                        #ty_name::new(self).set(v);
+                    }
+                });
+                reader_fns.extend(render_reader_fn(&mem_doc, &mem_name, &getter, quote! { &[#sty] }));
+
+                default_mems.extend(quote! {#mem_name : heapless::Vec::new(),});
+
+                let field_name = mem.name.as_str();
+                mem_read.extend(quote! {
+                    if #count as usize > #capacity {
+                        return Err(Error::LengthMismatch { field : #field_name, len : #count as usize });
+                    }
+                    let mut #mem_name = heapless::Vec::new();
+                    for _ in 0..#count {
+                        #read_element
+                        let _ = #mem_name.push(#element);
+                    }
+                });
+                read_mems.extend(quote! {#mem_name, });
+
+                mem_write.extend(quote! {
+                    for #element in self.#mem_name.iter() {
+                        #write_element
+                    }
+                });
+            }
+        }
+
+        // only differs from `mem_write` when updating discriminators of the generic structure
+        let mem_write_gen = mem_write_gen.unwrap_or_else(|| mem_write.clone());
+        write_pieces.push(mem_write_gen.clone());
+
+        read_mem_gen.extend(mem_read_gen.unwrap_or_else(|| mem_read.clone()));
+        write_mem_gen.extend(mem_write_gen);
+        read_mem.extend(mem_read);
+        write_mem.extend(mem_write);
+
+        if !stored {
+            continue;
+        }
+
+        read_mems_gen.extend(quote! {#mem_name, });
+
+        if let StructMember::AlternativesMember(alt) = mem {
+            into_gen_mems.extend(quote! { #mem_name : value.#mem_name.into(), });
+            if config.const_generic {
+                let index = util::ident(&alt.name.to_sanitized_upper_case(&members), span);
+                from_gen_mems.extend(quote! {
+                    #mem_name : if value.#mem_name.selector().index() == #index {
+                        value.#mem_name
+                    } else {
+                        return Err(Error::UnexpectedAlternative { field : #mem_name_str });
+                    },
+                });
+            } else {
+                let alt_name_templ = alt.param(false, &members, naming).ty;
+                from_gen_mems.extend(quote! {
+                    #mem_name : #alt_name_templ::try_from(value.#mem_name).map_err(|_| Error::UnexpectedAlternative { field : #mem_name_str })?,
+                });
+            }
+        } else {
+            into_gen_mems.extend(quote! { #mem_name : value.#mem_name, });
+            from_gen_mems.extend(quote! { #mem_name : value.#mem_name, });
+        }
+
+        str_mems.extend(quote! { #mem_name : #mem_ty, });
+        inst_default.extend(quote! {
+            #mem_name : #default_value,
+        });
+
+        str_mems_gen.extend(quote! {
+            pub #mem_name : #mem_ty_gen,
+        });
+
+        str_items.extend(quote! {
+            #mem_doc
+            pub struct #ty_name<'a, #templ_decl> where #fields_where_clause { data : &'a mut super::#str_name<#templ> }
+
+            impl<'a, #templ_decl> #ty_name<'a, #templ> where #fields_where_clause {
+                #[inline(always)]
+                pub(crate) fn new(data : &'a mut super::#str_name<#templ>) -> Self {
+                    Self { data }
+                }
+
+                #mem_str_impl
+            }
+        });
+    }
+
+    let out_name = if write_mem.is_empty() {
+        quote! {_out}
+    } else {
+        quote! {out}
+    };
+    let write_fun = quote! {
+        pub fn write<W>(&self, #out_name : &mut W) -> Result<(), Error> where W : Write {
+            #write_mem
+            Ok(())
+        }
+    };
+    let maybe_write_fun = if has_alt {
+        quote! {}
+    } else {
+        let vectored = vectored::render(structure, &write_pieces, naming)?;
+        quote! {
+            #write_fun
+
+            #vectored
+        }
+    };
+
+    let reader_name = if read_mem.is_empty() {
+        quote! {_reader}
+    } else {
+        quote! {reader}
+    };
+    let read_result = if read_checks.is_empty() {
+        quote! { Ok(Self {#read_mems}) }
+    } else {
+        quote! {
+            let result = Self {#read_mems};
+            #read_checks
+            Ok(result)
+        }
+    };
+    let read_fun = quote! {
+        pub fn read<R>(#reader_name : &mut R) -> Result<Self, Error> where R : Read {
+            #read_mem
+            #read_result
+        }
+    };
+    let maybe_read_fun = if has_alt {
+        quote! {}
+    } else {
+        let slice_fns = render_slice_fns();
+        quote! {
+            #read_fun
 
-        mod_items.extend(quote! {
-            pub trait #alt_pc : Copy {
-                fn default() -> Self;
-            }
+            #slice_fns
+        }
+    };
+    let maybe_checksum_fns = if has_alt {
+        quote! {}
+    } else {
+        checksum_fns.clone()
+    };
 
-            #deriving
-            pub enum #alt_pc_a {
-                #alt_enum_entries
+    let is_copy = structure.is_copy();
+    // `Debug` is implemented by `render_debug` to print decoded fields, and `Default` in terms
+    // of `new()` to use the defaults of the members
+    let custom_debug = !has_alt && derives.contains("Debug");
+    if !has_alt {
+        let derives = derives
+            .clone()
+            .remove_derive("Debug")
+            .remove_derive("Default");
+        if is_copy {
+            mod_items.extend(deriving_tokens(&derives)?);
+        } else {
+            mod_items.extend(deriving_tokens_no_copy(&derives)?);
+        }
+    }
+
+    // `modify` works on a copy, so only structures deriving `Clone` have it
+    let modify = if !has_alt && derives.contains("Clone") {
+        quote! {
+            #[doc = "Set members through their proxies on a copy of the structure, which replaces it only if `f` succeeds so that no member is written unless every value is valid, e.g. `modify(|w| { w.a().set(1); w.b().set(2)?; Ok(()) })`."]
+            #[inline(always)]
+            pub fn modify<F>(&mut self, f : F) -> Result<&mut Self, Error> where F : FnOnce(&mut Self) -> Result<(), Error> {
+                let mut w = Clone::clone(self);
+                f(&mut w)?;
+                *self = w;
+                Ok(self)
             }
+        }
+    } else {
+        quote! {}
+    };
 
-            impl #alt_pc_a {
-                pub fn default() -> Self {
-                    Self::#def_alt_struct(#def_alt_struct::default())
-                }
+    let fields_mod = if str_items.is_empty() {
+        str_items
+    } else {
+        quote! {
+            mod #fields_mod_name {
+                #str_items
 
-                pub fn write<W>(&self, out : &mut W) -> Result<(), Error> where W : Write {
-                    match self {
-                        #write_entries
-                    }
+                impl<#templ_decl> super::#str_name<#templ> where #fields_where_clause {
+                    #direct_fns
                 }
 
-                #read_funs
+                impl<'a, #templ_decl> super::#str_reader_name<'a, #templ> where #fields_where_clause {
+                    #reader_fns
+                }
             }
-        });
-    }
-
-    mod_items.extend(quote! {#trait_extends});
+        }
+    };
+    let str_reader_doc = format!(
+        "Read-only accessors of [`{}`] borrowing it immutably, e.g. to inspect a decoded frame shared with other tasks. Borrowed members live as long as the structure.",
+        str_name
+    );
 
-    Ok(mod_items)
-}
+    let str_doc = doc::render_doc(&structure.desc, &doc::struct_diagram(structure));
 
-pub fn render_simple(structure: &SimpleStructure) -> Result<TokenStream> {
-    let deriving = deriving_tokens();
+    mod_items.extend(quote! {
+        #str_doc
+        pub struct #str_name<#templ_decl> where #where_clause {
+            #str_mems
+        }
 
-    let mut mod_items = TokenStream::new();
+        #fields_mod
 
-    let span = Span::call_site();
-    let str_name = Ident::new(&structure.name.to_sanitized_pascal_case(), span);
-    let mem_name = Ident::new(&structure.member.name.to_sanitized_snake_case(), span);
-    let sty = (structure.member.bytes * 8).to_ty()?;
-    let bytes = unsuffixed(structure.member.bytes as u64);
+        #[doc = #str_reader_doc]
+        pub struct #str_reader_name<'a, #templ_decl> where #where_clause { data : &'a #str_name<#templ> }
 
-    mod_items.extend(quote! {
-        #deriving
-        pub struct #str_name {
-            #mem_name : #sty
+        impl<'a, #templ_decl> Clone for #str_reader_name<'a, #templ> where #where_clause {
+            fn clone(&self) -> Self {
+                *self
+            }
         }
 
-        impl #str_name {
+        impl<'a, #templ_decl> Copy for #str_reader_name<'a, #templ> where #where_clause {}
+
+        impl<#templ_decl> #str_name<#templ> where #where_clause {
+            #consts
+
+            #[inline(always)]
             pub fn new() -> Self {
-                Self { #mem_name : 0 }
+                Self {
+                    #inst_default
+                }
             }
 
-            pub fn of_value(val : #sty) -> Self {
-                Self { #mem_name : val }
+            #str_fns
+
+            #[doc = "Read-only accessors of the members borrowing the structure immutably."]
+            #[inline(always)]
+            pub fn reader(&self) -> #str_reader_name<'_, #templ> {
+                #str_reader_name { data : self }
             }
 
-            pub fn get(&self) -> #sty {
-                self.#mem_name
+            #modify
+
+            #wire_size
+
+            #maybe_write_fun
+
+            #maybe_read_fun
+
+            #maybe_checksum_fns
+        }
+    });
+
+    mod_items.extend(quote! {
+        impl<#templ_decl> Default for #str_name<#templ> where #where_clause {
+            #[inline(always)]
+            fn default() -> Self {
+                Self::new()
             }
+        }
+    });
 
-            pub fn set(&mut self, v : #sty) -> &mut Self {
-                self.#mem_name = v;
-                self
+    let display = render_display(structure, naming)?;
+    mod_items.extend(quote! {
+        impl<#templ_decl> core::fmt::Display for #str_name<#templ> where #where_clause #display_bounds {
+            #display
+        }
+    });
+
+    if !has_alt {
+        mod_items.extend(render_byte_conversions(
+            &str_name,
+            structure.field_offsets(),
+        ));
+    }
+
+    if custom_debug {
+        let debug = render_debug(structure, naming)?;
+        mod_items.extend(quote! {
+            impl core::fmt::Debug for #str_name {
+                #debug
             }
+        });
+    }
 
+    if has_alt {
+        let gen_fns = quote! {
+            #[doc = "Write the structure, updating the discriminators of the alternatives. Alternatives without a discriminator are not recorded on the wire, so reading them back requires their `selector`."]
             pub fn write<W>(&self, out : &mut W) -> Result<(), Error> where W : Write {
-                out.write(&self.#mem_name.to_le_bytes())?;
+                #write_mem_gen
                 Ok(())
             }
 
-            pub fn read<R>(reader : &mut R) -> Result<Self, Error> where R : Read {
-                let mut bytes = [0u8; #bytes];
-                reader.read_exact(&mut bytes)?;
-                Ok(Self { #mem_name : #sty::from_le_bytes(bytes) })
+            #[doc = "Read the structure, selecting alternatives without a discriminator using the given selectors."]
+            pub fn read<R>(reader : &mut R, #read_params_gen) -> Result<Self, Error> where R : Read {
+                #read_mem_gen
+                let result = Self {#read_mems_gen};
+                #read_checks
+                Ok(result)
+            }
+
+            #checksum_fns
+        };
+        // the slice conversions have no way to pass selectors
+        let gen_slice_fns = if read_params_gen.is_empty() {
+            render_slice_fns()
+        } else {
+            quote! {}
+        };
+
+        let deriving_gen = if is_copy {
+            deriving_tokens(derives)?
+        } else {
+            deriving_tokens_no_copy(derives)?
+        };
+        mod_items.extend(quote! {
+            #str_doc
+            #deriving_gen
+            pub struct #str_name_gen {
+                #str_mems_gen
+            }
+
+            impl #str_name_gen {
+                #consts
+
+                pub fn default() -> Self {
+                    Self { #default_mems }
+                }
+
+                #wire_size
+
+                #gen_fns
+
+                #gen_slice_fns
+
+                #written_fns
+            }
+
+            impl core::fmt::Display for #str_name_gen {
+                #display
             }
-        }
-    });
+
+            impl<#templ_decl> From<#str_name<#templ>> for #str_name_gen where #into_gen_bounds {
+                fn from(value : #str_name<#templ>) -> Self {
+                    Self { #into_gen_mems }
+                }
+            }
+
+            impl<#templ_decl> TryFrom<#str_name_gen> for #str_name<#templ> where #from_gen_bounds {
+                type Error = Error;
+
+                #[doc = "Narrow the generic structure, failing if a member holds another alternative than the one of the typed structure."]
+                fn try_from(value : #str_name_gen) -> Result<Self, Error> {
+                    Ok(Self { #from_gen_mems })
+                }
+            }
+        });
+    }
+
+    // typed structures with alternatives are written through their generic structure
+    let frame_ty = if has_alt { &str_name_gen } else { &str_name };
+    mod_items.extend(frame_trait::render_impl(frame_ty, &structure.name));
+
+    if !default_templ.is_empty() {
+        mod_items.extend(quote! {
+            pub type #str_name_def = #str_name<#default_templ>;
+        });
+    }
+
+    if !structure.required.is_empty() {
+        mod_items.extend(builder::render(structure, config.const_generic, naming)?);
+    }
+
+    mod_items.extend(init::render(structure, config.const_generic, naming)?);
+    mod_items.extend(base::render(structure, naming)?);
+    mod_items.extend(trailing::render(structure, naming)?);
+    mod_items.extend(parser::render_struct(structure, naming)?);
+
+    if view::has_view(structure) {
+        mod_items.extend(view::render_ref(structure, naming)?);
+        mod_items.extend(view::render_mut(structure, naming)?);
+    }
 
     Ok(mod_items)
 }
 
-pub fn render_with_alts(structure: &Structure, alternatives: &Alternatives) -> Result<TokenStream> {
+/// Render the `fmt` function of the `Display` implementation of a structure, printing integers
+/// in hex and decoding bitfields.
+/// Render the accessor `mem_name` of the reader of a structure, which reads the member through
+/// `getter` of the structure borrowed for `'a`, so that a member of type `ty` borrowed from the
+/// structure outlives the reader.
+fn render_reader_fn(
+    mem_doc: &TokenStream,
+    mem_name: &Ident,
+    getter: &Ident,
+    ty: TokenStream,
+) -> TokenStream {
+    let mut tokens = ty.into_iter().peekable();
+    let ty = match tokens.peek() {
+        Some(TokenTree::Punct(p)) if p.as_char() == '&' => {
+            tokens.next();
+            let referent = tokens.collect::<TokenStream>();
+            quote! { &'a #referent }
+        }
+        _ => tokens.collect(),
+    };
+    quote! {
+        #mem_doc
+        #[inline(always)]
+        pub fn #mem_name(&self) -> #ty {
+            self.data.#getter()
+        }
+    }
+}
+
+fn render_display(structure: &Structure, naming: &Naming) -> Result<TokenStream> {
     let span = Span::call_site();
+    let members = naming.names_of(&structure.name);
 
-    let mut mod_items = TokenStream::new();
+    let open = format!(
+        "{} {{{{",
+        structure.name.to_sanitized_pascal_case(&naming.types())
+    );
+    let mut body = quote! { write!(f, #open)?; };
 
-    let str_name = Ident::new(&structure.name.to_sanitized_pascal_case(), span);
-    let str_name_def = Ident::new(&format!("{}Default", str_name), span);
-    let str_name_gen = Ident::new(&format!("{}Generic", str_name), span);
-    let fields_mod_name = Ident::new(&format!("{}_fields", &structure.name.to_snake_case()), span);
+    let mut sep = " ";
+    for mem in &structure.members {
+        let mem_name_str = mem.name().to_sanitized_snake_case(&members);
+        let mem_name = util::ident(&mem_name_str, span);
+        let value = quote! { self.#mem_name };
 
-    let mut str_mems = TokenStream::new();
-    let mut str_mems_gen = TokenStream::new();
-    let mut templ = TokenStream::new();
-    let mut default_templ = TokenStream::new();
+        let (fmt, arg) = match mem {
+            StructMember::PrimitiveMember(_)
+            | StructMember::OffsetMember(_)
+            | StructMember::BitsMember(_) => ("{:#x}", value),
+            StructMember::LengthMember(_)
+            | StructMember::NibbleVarintMember(_)
+            | StructMember::VarintMember(_)
+            | StructMember::BcdMember(_)
+            | StructMember::TimestampMember(_) => ("{}", value),
+            StructMember::EuiMember(mem) => {
+                let ty = mem.ty();
+                ("{}", quote! { crate::#ty(self.#mem_name) })
+            }
+            StructMember::BitfieldMember(mem) => {
+                let pkg_name = structure.bitfield_path(&mem.bitfield, false, naming);
+                ("{{ {} }}", quote! { #pkg_name::R::new(self.#mem_name) })
+            }
+            StructMember::AlternativesMember(_) => ("{}", value),
+            StructMember::ArrayMember(_) => ("{:x?}", value),
+            StructMember::BytesMember(_) if structure.payloads != Payloads::Array => {
+                ("{:x?}", quote! { &#value[..] })
+            }
+            StructMember::BytesMember(mem) => {
+                let len_name = util::ident(&mem.len_from.to_sanitized_snake_case(&members), span);
+                ("{:x?}", quote! { &#value[..self.#len_name as usize] })
+            }
+            StructMember::RepeatedMember(_) => ("{:x?}", quote! { &self.#mem_name[..] }),
+            StructMember::ExtendedMember(_) => {
+                ("{:x?}", quote! { &#value[..crate::chain_len(&#value)] })
+            }
+            StructMember::StringMember(_) => ("{:?}", quote! { #value.as_str() }),
+            StructMember::OptionalMember(_) => {
+                let none = format!("{}{}: none", sep, mem_name_str);
+                let some = format!("{}{}: {{}}", sep, mem_name_str);
+                body.extend(quote! {
+                    match &#value {
+                        Some(v) => write!(f, #some, v)?,
+                        None => write!(f, #none)?,
+                    }
+                });
+                sep = ", ";
+                continue;
+            }
+            // not stored in the structure
+            StructMember::ChecksumMember(_) | StructMember::ConstantMember(_) => continue,
+        };
 
-    let mut where_clause = TokenStream::new();
-    let mut fields_where_clause = TokenStream::new();
-    let mut inst_default = TokenStream::new();
+        let fmt = format!("{}{}: {}", sep, mem_name_str, fmt);
+        body.extend(quote! { write!(f, #fmt, #arg)?; });
+        sep = ", ";
+    }
 
-    let mut str_items = TokenStream::new();
-    let mut str_fns = TokenStream::new();
+    Ok(quote! {
+        fn fmt(&self, f : &mut core::fmt::Formatter) -> core::fmt::Result {
+            #body
+            write!(f, " }}")
+        }
+    })
+}
+
+/// Render the `fmt` function of the `Debug` implementation of a structure without alternatives,
+/// which prints integers in hex and decodes bitfields like [`render_display`].
+fn render_debug(structure: &Structure, naming: &Naming) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let members = naming.names_of(&structure.name);
+    let name = structure
+        .name
+        .to_sanitized_pascal_case(&naming.types())
+        .to_string();
+
+    let mut fields = TokenStream::new();
+    for mem in &structure.members {
+        let mem_name_str = mem.name().to_sanitized_snake_case(&members).to_string();
+        let mem_name = util::ident(&mem_name_str, span);
+        let value = quote! { self.#mem_name };
+
+        let arg = match mem {
+            StructMember::PrimitiveMember(_)
+            | StructMember::OffsetMember(_)
+            | StructMember::BitsMember(_) => {
+                quote! { &format_args!("{:#x}", #value) }
+            }
+            StructMember::LengthMember(_)
+            | StructMember::NibbleVarintMember(_)
+            | StructMember::VarintMember(_)
+            | StructMember::BcdMember(_)
+            | StructMember::TimestampMember(_) => {
+                quote! { &#value }
+            }
+            StructMember::EuiMember(mem) => {
+                let ty = mem.ty();
+                quote! { &crate::#ty(self.#mem_name) }
+            }
+            StructMember::BitfieldMember(mem) => {
+                let pkg_name = structure.bitfield_path(&mem.bitfield, false, naming);
+                quote! { &#pkg_name::R::new(self.#mem_name) }
+            }
+            StructMember::AlternativesMember(_) | StructMember::OptionalMember(_) => {
+                quote! { &#value }
+            }
+            StructMember::ArrayMember(_) => quote! { &format_args!("{:x?}", #value) },
+            StructMember::BytesMember(_) if structure.payloads != Payloads::Array => {
+                quote! { &format_args!("{:x?}", &#value[..]) }
+            }
+            StructMember::BytesMember(mem) => {
+                let len_name = util::ident(&mem.len_from.to_sanitized_snake_case(&members), span);
+                quote! { &format_args!("{:x?}", &#value[..self.#len_name as usize]) }
+            }
+            StructMember::RepeatedMember(_) => {
+                quote! { &format_args!("{:x?}", &self.#mem_name[..]) }
+            }
+            StructMember::ExtendedMember(_) => {
+                quote! { &format_args!("{:x?}", &#value[..crate::chain_len(&#value)]) }
+            }
+            StructMember::StringMember(_) => quote! { &#value.as_str() },
+            // not stored in the structure
+            StructMember::ChecksumMember(_) | StructMember::ConstantMember(_) => continue,
+        };
+
+        fields.extend(quote! { .field(#mem_name_str, #arg) });
+    }
+
+    Ok(quote! {
+        fn fmt(&self, f : &mut core::fmt::Formatter) -> core::fmt::Result {
+            f.debug_struct(#name)
+                #fields
+                .finish()
+        }
+    })
+}
+
+/// Render reading an integer of `bytes` bytes in byte order `order` from `reader` into the
+/// variable `name`. Integers which are narrower than their Rust type are zero extended, and
+/// those of more than 8 bytes are kept as byte arrays.
+fn render_read_int(name: &Ident, bytes: u32, order: ByteOrder) -> Result<TokenStream> {
+    if bytes > 8 {
+        let bytes = unsuffixed(bytes as u64);
+        return Ok(quote! {
+            let mut #name = [0u8; #bytes];
+            reader.read_exact(&mut #name)?;
+        });
+    }
+
+    let sty = (bytes * BITS_PER_BYTE).to_ty()?;
+    let width = (bytes * BITS_PER_BYTE).to_ty_width()? / BITS_PER_BYTE;
+    let buffer_len = unsuffixed(width as u64);
+    let from_bytes = order.from_bytes();
+
+    Ok(if width == bytes {
+        quote! {
+            let mut buffer = [0u8; #buffer_len];
+            reader.read_exact(&mut buffer)?;
+            let #name = #sty::#from_bytes(buffer);
+        }
+    } else {
+        let range = order.range(bytes, width);
+        quote! {
+            let mut buffer = [0u8; #buffer_len];
+            reader.read_exact(&mut buffer[#range])?;
+            let #name = #sty::#from_bytes(buffer);
+        }
+    })
+}
+
+/// Render writing the integer `value` to `out` as `bytes` bytes in byte order `order`,
+/// truncating any higher bytes of the Rust type. Integers of more than 8 bytes are byte arrays.
+fn render_write_int(value: TokenStream, bytes: u32, order: ByteOrder) -> Result<TokenStream> {
+    if bytes > 8 {
+        return Ok(quote! {
+            out.write_all(&#value)?;
+        });
+    }
+
+    let width = (bytes * BITS_PER_BYTE).to_ty_width()? / BITS_PER_BYTE;
+    let to_bytes = order.to_bytes();
+
+    Ok(if width == bytes {
+        quote! {
+            out.write_all(&(#value).#to_bytes())?;
+        }
+    } else {
+        let range = order.range(bytes, width);
+        quote! {
+            out.write_all(&(#value).#to_bytes()[#range])?;
+        }
+    })
+}
+
+/// The bytes spanned by a bit member of `bits` bits starting at the bit `offset` of its run in
+/// byte order `order`, each with the index of the byte, the shift and mask of the bits of the
+/// member within the byte, and the shift of these bits within the value.
+fn bits_pieces(offset: u32, bits: u32, order: ByteOrder) -> Vec<(u32, u32, u64, u32)> {
+    let first = offset / BITS_PER_BYTE;
+    let last = (offset + bits - 1) / BITS_PER_BYTE;
+    (first..=last)
+        .map(|byte| {
+            let start = offset.max(byte * BITS_PER_BYTE);
+            let end = (offset + bits).min((byte + 1) * BITS_PER_BYTE);
+            let mask = (1 << (end - start)) - 1;
+            match order {
+                ByteOrder::LittleEndian => {
+                    (byte, start - byte * BITS_PER_BYTE, mask, start - offset)
+                }
+                ByteOrder::BigEndian => (
+                    byte,
+                    (byte + 1) * BITS_PER_BYTE - end,
+                    mask,
+                    offset + bits - end,
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Render an expression decoding the bit member of `bits` bits starting at the bit `offset` of
+/// the byte slice expression `buf` in byte order `order`.
+pub(crate) fn render_read_bits(
+    buf: &TokenStream,
+    offset: u32,
+    bits: u32,
+    order: ByteOrder,
+) -> Result<TokenStream> {
+    let sty = bits.max(BITS_PER_BYTE).to_ty()?;
+    let pieces = bits_pieces(offset, bits, order)
+        .into_iter()
+        .map(|(byte, shift, mask, value_shift)| {
+            let byte = unsuffixed(byte as u64);
+            let mut piece = quote! { #buf[#byte] };
+            let mut operand = piece.clone();
+            if shift > 0 {
+                let shift = unsuffixed(shift as u64);
+                piece = quote! { #operand >> #shift };
+                operand = quote! { (#piece) };
+            }
+            if mask != 0xff {
+                let mask = hex(mask);
+                piece = quote! { #operand & #mask };
+                operand = quote! { (#piece) };
+            }
+            if bits > BITS_PER_BYTE {
+                piece = quote! { #operand as #sty };
+                operand = quote! { (#piece) };
+            }
+            if value_shift > 0 {
+                let value_shift = unsuffixed(value_shift as u64);
+                piece = quote! { #operand << #value_shift };
+                operand = quote! { (#piece) };
+            }
+            (piece, operand)
+        })
+        .collect::<Vec<_>>();
+
+    Ok(match pieces.as_slice() {
+        [(piece, _)] => piece.clone(),
+        _ => {
+            let operands = pieces.iter().map(|(_, operand)| operand);
+            quote! { #(#operands)|* }
+        }
+    })
+}
+
+/// Render the statements encoding `value` as the bit member of `bits` bits starting at the bit
+/// `offset` of the mutable byte slice expression `buf` in byte order `order`, keeping the bits
+/// of the other members.
+pub(crate) fn render_write_bits(
+    buf: &TokenStream,
+    value: &TokenStream,
+    offset: u32,
+    bits: u32,
+    order: ByteOrder,
+) -> TokenStream {
+    let mut stmts = TokenStream::new();
+    for (byte, shift, mask, value_shift) in bits_pieces(offset, bits, order) {
+        let byte = unsuffixed(byte as u64);
+        // parenthesize the piece only once it is an operation itself
+        let mut piece = value.clone();
+        let mut operand = value.clone();
+        if value_shift > 0 {
+            let value_shift = unsuffixed(value_shift as u64);
+            piece = quote! { #operand >> #value_shift };
+            operand = quote! { (#piece) };
+        }
+        if mask != 0xff || bits > BITS_PER_BYTE {
+            let mask = hex(mask);
+            piece = quote! { #operand & #mask };
+            operand = quote! { (#piece) };
+        }
+        if bits > BITS_PER_BYTE {
+            piece = quote! { #operand as u8 };
+            operand = quote! { (#piece) };
+        }
+        if shift > 0 {
+            let shift = unsuffixed(shift as u64);
+            piece = quote! { #operand << #shift };
+            operand = quote! { (#piece) };
+        }
+        let keep = !(mask << shift) & 0xff;
+        stmts.extend(if keep == 0 {
+            quote! { #buf[#byte] = #piece; }
+        } else {
+            let keep = hex(keep);
+            quote! { #buf[#byte] = (#buf[#byte] & #keep) | #operand; }
+        });
+    }
+    stmts
+}
+
+/// Render an expression for the nibble of the nibble-extended varint `value` of the member
+/// `field`, returning an error if it is too large to be encoded.
+fn render_nibble(value: TokenStream, field: &str) -> TokenStream {
+    let max = unsuffixed(NibbleVarintMember::MAX as u64);
+    quote! {
+        match #value {
+            value @ 0..=12 => value as u8,
+            13..=268 => 13,
+            269..=#max => 14,
+            _ => return Err(Error::InvalidVarint { field : #field }),
+        }
+    }
+}
+
+/// Render the statements updating the flag fields of the bitfield member `bitfield` in the
+/// writer `w` to match the presence of the optional members they gate, and its count fields
+/// to match the number of elements of the repeated members they count.
+fn render_flag_syncs(
+    structure: &Structure,
+    bitfield: &str,
+    pkg_name: &TokenStream,
+    naming: &Naming,
+) -> TokenStream {
+    let span = Span::call_site();
+    let mut syncs = TokenStream::new();
+    let members = naming.names_of(&structure.name);
+    let fields = naming.names_of(structure.bitfield_of(bitfield));
+
+    for mem in &structure.members {
+        if let StructMember::OptionalMember(opt) = mem {
+            // the fields of a condition are left to the user
+            if opt.flag.bitfield == bitfield && opt.condition.is_none() {
+                let mem_name = util::ident(&opt.name.to_sanitized_snake_case(&members), span);
+                let field_sc = util::ident(&opt.flag.field.to_sanitized_snake_case(&fields), span);
+
+                // a field of values is only set when present, as its other values are unknown
+                syncs.extend(match opt.values.first() {
+                    None => quote! {
+                        let present = matches!(self.#mem_name, Some(_));
+                        unsafe { w.#field_sc().bits(present) };
+                    },
+                    Some(value) => {
+                        let values =
+                            naming.values_of(structure.bitfield_of(bitfield), &opt.flag.field);
+                        let value_sc = util::ident(&value.to_sanitized_snake_case(&values), span);
+                        let flagged = opt.render_present(
+                            quote! { #pkg_name::R::new(*w) },
+                            structure.bitfield_of(bitfield),
+                            naming,
+                        );
+                        quote! {
+                            if matches!(self.#mem_name, Some(_)) && !(#flagged) {
+                                w.#field_sc().#value_sc();
+                            }
+                        }
+                    }
+                });
+            }
+        }
+        if let StructMember::RepeatedMember(RepeatedMember {
+            name,
+            count_from,
+            count_field: Some(field),
+            ..
+        }) = mem
+        {
+            if count_from == bitfield {
+                let mem_name = util::ident(&name.to_sanitized_snake_case(&members), span);
+                let field_sc = util::ident(&field.to_sanitized_snake_case(&fields), span);
+                syncs.extend(quote! {
+                    w = w.#field_sc(self.#mem_name.len() as _);
+                });
+            }
+        }
+    }
 
-    let mut default_mems = TokenStream::new();
-    let mut read_mem = TokenStream::new();
-    let mut read_mems = TokenStream::new();
-    let mut write_mem = TokenStream::new();
+    syncs
+}
 
-    let mut has_alt = false;
+/// Render the statements updating the discriminator fields of the bitfield member `bitfield`
+/// in the writer `w` to match the alternatives they select.
+fn render_discriminator_syncs(
+    structure: &Structure,
+    alternatives: &Alternatives,
+    bitfield: &str,
+    pkg_name: &TokenStream,
+    naming: &Naming,
+) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let mut syncs = TokenStream::new();
+    let members = naming.names_of(&structure.name);
+    let fields = naming.names_of(structure.bitfield_of(bitfield));
 
     for mem in &structure.members {
-        match mem {
-            StructMember::AlternativesMember(alt) => {
-                let alts = alternatives.get(&alt.alternatives)?;
+        let (alt, disc) = match mem {
+            StructMember::AlternativesMember(alt) => match &alt.discriminator {
+                Some(disc) if disc.bitfield == bitfield => (alt, disc),
+                _ => continue,
+            },
+            _ => continue,
+        };
 
-                let alt_default = Ident::new(&alts.default.to_sanitized_pascal_case(), span);
-                let alt_name_templ =
-                    Ident::new(&format!("{}T", alt.name.to_sanitized_pascal_case()), span);
-                let alt_trait = Ident::new(&alt.alternatives.to_sanitized_pascal_case(), span);
+        let alts = alternatives.get(&alt.alternatives)?;
+        let mem_name = util::ident(&alt.name.to_sanitized_snake_case(&members), span);
+        let alt_pc_a = util::ident(
+            &format!(
+                "{}A",
+                alt.alternatives.to_sanitized_pascal_case(&naming.types())
+            ),
+            span,
+        );
+        let field_sc = util::ident(&disc.field.to_sanitized_snake_case(&fields), span);
+        let field_pc_a = util::ident(
+            &format!("{}A", disc.field.to_sanitized_pascal_case(&fields)),
+            span,
+        );
+        let values = naming.values_of(structure.bitfield_of(bitfield), &disc.field);
 
-                templ.extend(quote! { #alt_name_templ, });
-                where_clause.extend(quote! { #alt_name_templ : #alt_trait, });
-                fields_where_clause.extend(quote! { #alt_name_templ : super::#alt_trait, });
-                default_templ.extend(quote! { #alt_default, });
+        let mut arms = TokenStream::new();
+        for altopt in &alts.alternatives {
+            let alt_enum = util::ident(&altopt.to_sanitized_pascal_case(&naming.types()), span);
+            let variant = util::ident(
+                &alts.discriminant(altopt)?.to_sanitized_pascal_case(&values),
+                span,
+            );
 
-                has_alt = true;
-            }
-            _ => {}
+            arms.extend(quote! {
+                #alt_pc_a::#alt_enum(_) => w.#field_sc().variant(#pkg_name::#field_pc_a::#variant),
+            });
         }
-    }
 
-    for mem in &structure.members {
-        let mem_name_str = mem.name();
-        let mem_name = Ident::new(&mem_name_str.to_sanitized_snake_case(), span);
-        let ty_name = Ident::new(&mem_name_str.to_sanitized_pascal_case(), span);
-        let fty_name = quote!{ #fields_mod_name :: #ty_name };
+        syncs.extend(quote! {
+            match self.#mem_name {
+                #arms
+            };
+        });
+    }
 
-        let mut mem_str_impl = TokenStream::new();
+    Ok(syncs)
+}
 
-        let mut default_value = TokenStream::new();
-        let mut mem_ty = TokenStream::new();
-        let mut mem_ty_gen = TokenStream::new();
+pub fn render_imports(io: IoBackend) -> TokenStream {
+    let io = io.path();
+    quote! {
+        use #io::{Read, Write};
+        use crate::Error;
+    }
+}
 
-        match mem {
-            StructMember::BitfieldMember(mem) => {
-                let pkg_name = Ident::new(&mem.bitfield.to_sanitized_snake_case(), span);
-                let sty = (mem.bytes * 8).to_ty()?;
-                let bytes = unsuffixed(mem.bytes as u64);
+/// Render the `Error` returned when reading or writing structures, which distinguishes invalid
+/// input from errors of the underlying reader or writer. This is expected to be placed at the
+/// root of the generated crate.
+pub fn render_error_support(io: IoBackend) -> TokenStream {
+    let io = io.path();
+    quote! {
+        /// Error reading or writing a structure.
+        #[derive(Debug)]
+        pub enum Error {
+            /// The input ended before the structure was complete.
+            UnexpectedEof,
+            /// The output is too small for the structure.
+            WriteZero,
+            /// A field holds a value which is not enumerated.
+            InvalidEnumValue { field : &'static str, value : u64 },
+            /// A constant field or the marker of an offset holds an unexpected value.
+            InvalidConstant { field : &'static str, value : u64 },
+            /// The stored checksum does not match the one computed over the input.
+            ChecksumMismatch { field : &'static str },
+            /// A length exceeds the capacity of the member it counts.
+            LengthMismatch { field : &'static str, len : usize },
+            /// A member holds another alternative than the one of the typed structure.
+            UnexpectedAlternative { field : &'static str },
+            /// A varint is reserved on the wire or too large to be written.
+            InvalidVarint { field : &'static str },
+            /// A string is not valid UTF-8.
+            InvalidString { field : &'static str },
+            /// A BCD field holds a nibble which is not a decimal digit.
+            InvalidBcd { field : &'static str },
+            /// A value set to a member is too large for it.
+            OutOfRange { field : &'static str, value : u64 },
+            /// An offset refers to no byte of the buffer it is resolved against.
+            InvalidOffset { field : &'static str, offset : usize },
+            /// A frame is not escaped correctly by its framing or too long to be held.
+            InvalidFraming,
+            /// Any other error of the reader or writer.
+            Io(#io::Error),
+        }
 
-                default_value.extend(quote! { 0 });
-                mem_ty.extend(quote! {#sty});
-                mem_ty_gen.extend(quote! {#sty});
+        impl From<#io::Error> for Error {
+            fn from(e : #io::Error) -> Self {
+                match e.kind() {
+                    #io::ErrorKind::UnexpectedEof => Error::UnexpectedEof,
+                    #io::ErrorKind::WriteZero => Error::WriteZero,
+                    _ => Error::Io(e),
+                }
+            }
+        }
 
-                str_fns.extend(quote! {
-                    pub fn #mem_name(&mut self) -> #fty_name<#templ> {
-                        #fty_name::new(self)
-                    }
-                });
+        impl core::fmt::Display for Error {
+            fn fmt(&self, f : &mut core::fmt::Formatter) -> core::fmt::Result {
+                match self {
+                    Error::UnexpectedEof => f.write_str("unexpected end of input"),
+                    Error::WriteZero => f.write_str("output too small"),
+                    Error::InvalidEnumValue { field, value } => write!(f, "invalid value {:#x} of `{}`", value, field),
+                    Error::InvalidConstant { field, value } => write!(f, "unexpected value {:#x} of `{}`", value, field),
+                    Error::ChecksumMismatch { field } => write!(f, "checksum `{}` mismatch", field),
+                    Error::LengthMismatch { field, len } => write!(f, "length {} of `{}` exceeds its capacity", len, field),
+                    Error::UnexpectedAlternative { field } => write!(f, "unexpected alternative of `{}`", field),
+                    Error::InvalidVarint { field } => write!(f, "invalid varint `{}`", field),
+                    Error::InvalidString { field } => write!(f, "invalid UTF-8 in `{}`", field),
+                    Error::InvalidBcd { field } => write!(f, "invalid BCD digit in `{}`", field),
+                    Error::OutOfRange { field, value } => write!(f, "value {} is out of range of `{}`", value, field),
+                    Error::InvalidOffset { field, offset } => write!(f, "offset {} of `{}` is out of bounds", offset, field),
+                    Error::InvalidFraming => f.write_str("invalid framing"),
+                    Error::Io(e) => write!(f, "{:?}", e),
+                }
+            }
+        }
+    }
+}
 
-                mem_str_impl.extend(quote! {
-                        #[inline(always)]
-                        pub fn read(&self) -> super::super::#pkg_name::R {
-                            super::super::#pkg_name::R::new(self.data.#mem_name)
-                        }
+/// Render the conversions of the structure `str_name` from byte slices and, if it has a fixed
+/// size with its members at the offsets of `fields`, into byte arrays and hex dumps, in terms of
+/// `read_from` and `write_to`.
+fn render_byte_conversions(str_name: &Ident, fields: Option<Vec<(String, u32)>>) -> TokenStream {
+    let mut conversions = quote! {
+        impl<'a> TryFrom<&'a [u8]> for #str_name {
+            type Error = Error;
 
-                        #[inline(always)]
-                        pub fn modify<F>(&'a mut self, f : F) -> &'a mut super::#str_name<#templ> where for <'w> F : FnOnce(&'w mut super::super::#pkg_name::W) -> &'w mut super::super::#pkg_name::W {
-                            let bits = self.data.#mem_name;
-                            self.data.#mem_name = **f(&mut super::super::#pkg_name::W::new(bits));
-                            self.data
-                        }
-                });
+            #[doc = "Deserialize from the start of `buf`, ignoring any bytes after the structure."]
+            fn try_from(buf : &'a [u8]) -> Result<Self, Error> {
+                Self::read_from(buf).map(|(value, _)| value)
+            }
+        }
+    };
 
-                default_mems.extend(quote! {#mem_name : 0,});
+    if let Some(fields) = fields {
+        let fields = fields.iter().map(|(name, offset)| {
+            let offset = unsuffixed(*offset as u64);
+            quote! { (#name, #offset), }
+        });
+        conversions.extend(quote! {
+            impl #str_name {
+                #[doc = "Names of the members, or runs of bit members, with their offsets on the wire."]
+                pub const FIELDS : &'static [(&'static str, usize)] = &[#(#fields)*];
 
-                read_mem.extend(quote! {
-                    let mut buffer = [0u8; #bytes];
-                    reader.read_exact(&mut buffer)?;
-                    let #mem_name = #sty::from_le_bytes(buffer);
-                });
-                read_mems.extend(quote! {#mem_name, });
+                #[doc = "Serialize into an array of `SIZE` bytes."]
+                pub fn to_bytes(&self) -> [u8; #str_name::SIZE] {
+                    let mut buf = [0u8; #str_name::SIZE];
+                    // the structure always fits into its size
+                    let _ = self.write_to(&mut buf);
+                    buf
+                }
 
-                write_mem.extend(quote! {
-                    out.write(&self.#mem_name.to_le_bytes())?;
-                });
+                #[doc = "Hex dump of the serialized structure, annotated with the names of its members."]
+                pub fn hex(&self) -> crate::HexDisplay<[u8; #str_name::SIZE]> {
+                    crate::HexDisplay::new(self.to_bytes(), Self::FIELDS)
+                }
             }
-            StructMember::PrimitiveMember(mem) => {
-                let sty = (mem.bytes * 8).to_ty()?;
-                let bytes = unsuffixed(mem.bytes as u64);
 
-                default_value.extend(quote! { 0 });
-                mem_ty.extend(quote! {#sty});
-                mem_ty_gen.extend(quote! {#sty});
+            impl From<#str_name> for [u8; #str_name::SIZE] {
+                #[inline(always)]
+                fn from(value : #str_name) -> Self {
+                    value.to_bytes()
+                }
+            }
+        });
+    }
 
-                str_fns.extend(quote! {
-                    pub fn #mem_name(&mut self) -> #fty_name<#templ> {
-                            #fty_name::new(self)
-                        }
-                });
+    conversions
+}
 
-                mem_str_impl.extend(quote! {
-                    #[inline(always)]
-                    pub fn read(&self) -> #sty {
-                        self.data.#mem_name
-                    }
+/// Render functions serializing to and from byte slices in terms of `write` and `read`.
+fn render_slice_fns() -> TokenStream {
+    quote! {
+        /// Serialize into `buf`, returning the number of bytes written.
+        pub fn write_to(&self, buf : &mut [u8]) -> Result<usize, Error> {
+            let len = buf.len();
+            let mut out = buf;
+            self.write(&mut out)?;
+            Ok(len - out.len())
+        }
 
-                    #[inline(always)]
-                    pub fn set(&'a mut self, v : #sty) -> &'a mut super::#str_name<#templ> {
-                        self.data.#mem_name = v;
-                        self.data
-                    }
-                });
+        /// Deserialize from the start of `buf`, returning the number of bytes read.
+        pub fn read_from(buf : &[u8]) -> Result<(Self, usize), Error> {
+            let mut reader = buf;
+            let value = Self::read(&mut reader)?;
+            Ok((value, buf.len() - reader.len()))
+        }
+    }
+}
 
-                default_mems.extend(quote! {#mem_name : 0,});
+/// Render the `io` module required by [`IoBackend::Core`] and [`IoBackend::EmbeddedIo`]. This
+/// is expected to be placed at the root of the generated crate.
+pub fn render_io_support(io: IoBackend) -> TokenStream {
+    let (other_kind, traits) = match io {
+        IoBackend::Core2 => return quote! {},
+        IoBackend::Core => (quote! {}, render_core_io_traits()),
+        IoBackend::EmbeddedIo => (quote! { Other, }, render_embedded_io_traits()),
+    };
 
-                read_mem.extend(quote! {
-                    let mut buffer = [0u8; #bytes];
-                    reader.read_exact(&mut buffer)?;
-                    let #mem_name = #sty::from_le_bytes(buffer);
-                });
-                read_mems.extend(quote! {#mem_name, });
+    quote! {
+        pub mod io {
+            #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+            pub enum ErrorKind {
+                InvalidData,
+                InvalidInput,
+                UnexpectedEof,
+                WriteZero,
+                #other_kind
+            }
 
-                write_mem.extend(quote! {
-                    out.write(&self.#mem_name.to_le_bytes())?;
-                });
+            #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+            pub struct Error {
+                kind : ErrorKind,
+                msg : &'static str,
             }
-            StructMember::AlternativesMember(alt) => {
-                let alt_name_templ =
-                    Ident::new(&format!("{}T", alt.name.to_sanitized_pascal_case()), span);
-                let alt_pc_a = Ident::new(
-                    &format!("{}A", alt.alternatives.to_sanitized_pascal_case()),
-                    span,
-                );
 
-                default_value.extend(quote! { #alt_name_templ::default() });
-                mem_ty.extend(quote! {#alt_name_templ});
-                mem_ty_gen.extend(quote! {#alt_pc_a});
+            impl Error {
+                pub fn new(kind : ErrorKind, msg : &'static str) -> Self {
+                    Self { kind, msg }
+                }
 
-                str_fns.extend(quote! {
-                    pub fn #mem_name(&mut self) -> #fty_name<#templ> {
-                        #fty_name::new(self)
-                    }
-                });
+                pub fn kind(&self) -> ErrorKind {
+                    self.kind
+                }
 
-                mem_str_impl.extend(quote! {
-                    #[inline(always)]
-                    pub fn read(&self) -> #alt_name_templ {
-                        self.data.#mem_name
-                    }
+                pub fn msg(&self) -> &'static str {
+                    self.msg
+                }
+            }
 
-                    #[inline(always)]
-                    pub fn modify<F>(&'a mut self, f : F) -> &'a mut super::#str_name<#templ> where for <'w> F : FnOnce(&'w mut #alt_name_templ) -> &'w mut #alt_name_templ {
-                        let mut cp = self.data.#mem_name;
-                        self.data.#mem_name = *f(&mut cp);
-                        self.data
-                    }
-                });
+            pub type Result<T> = core::result::Result<T, Error>;
 
-                default_mems.extend(quote! {#mem_name : #mem_ty_gen::default(), });
+            #traits
+        }
+    }
+}
 
-                write_mem.extend(quote! {
-                    self.#mem_name.write(out)?;
-                });
-            }
+/// `Read` and `Write` traits implemented for byte slices.
+fn render_core_io_traits() -> TokenStream {
+    quote! {
+        pub trait Read {
+            fn read_exact(&mut self, buf : &mut [u8]) -> Result<()>;
         }
 
-        str_mems.extend(quote! { #mem_name : #mem_ty, });
-        inst_default.extend(quote! {
-            #mem_name : #default_value,
-        });
+        pub trait Write {
+            fn write(&mut self, buf : &[u8]) -> Result<usize>;
 
-        str_mems_gen.extend(quote! {
-            pub #mem_name : #mem_ty_gen,
-        });
+            fn flush(&mut self) -> Result<()>;
 
-        str_items.extend(quote! {
-            pub struct #ty_name<'a, #templ> where #fields_where_clause { data : &'a mut super::#str_name<#templ> }
+            fn write_all(&mut self, mut buf : &[u8]) -> Result<()> {
+                while !buf.is_empty() {
+                    match self.write(buf)? {
+                        0 => return Err(Error::new(ErrorKind::WriteZero, "failed to write whole buffer")),
+                        n => buf = &buf[n..],
+                    }
+                }
+                Ok(())
+            }
+        }
 
-            impl<'a, #templ> #ty_name<'a, #templ> where #fields_where_clause {
-                #[inline(always)]
-                pub(crate) fn new(data : &'a mut super::#str_name<#templ>) -> Self {
-                    Self { data }
+        impl Read for &[u8] {
+            fn read_exact(&mut self, buf : &mut [u8]) -> Result<()> {
+                if buf.len() > self.len() {
+                    return Err(Error::new(ErrorKind::UnexpectedEof, "failed to fill whole buffer"));
                 }
+                let (a, b) = self.split_at(buf.len());
+                buf.copy_from_slice(a);
+                *self = b;
+                Ok(())
+            }
+        }
 
-                #mem_str_impl
+        impl Write for &mut [u8] {
+            fn write(&mut self, data : &[u8]) -> Result<usize> {
+                let amt = core::cmp::min(data.len(), self.len());
+                let (a, b) = core::mem::take(self).split_at_mut(amt);
+                a.copy_from_slice(&data[..amt]);
+                *self = b;
+                Ok(amt)
             }
-        });
+
+            fn flush(&mut self) -> Result<()> {
+                Ok(())
+            }
+        }
     }
+}
 
-    let write_fun_unsafe = if has_alt {
-        quote! { unsafe }
-    } else {
-        quote! {}
-    };
-    let out_name = if write_mem.is_empty() {
-        quote! {_out}
-    } else {
-        quote! {out}
-    };
-    let write_fun = quote! {
-        pub #write_fun_unsafe fn write<W>(&self, #out_name : &mut W) -> Result<(), Error> where W : Write {
-            #write_mem
-            Ok(())
+/// `Read` and `Write` traits implemented for every `embedded_io` reader and writer, mapping their
+/// error types onto [`Error`].
+fn render_embedded_io_traits() -> TokenStream {
+    quote! {
+        impl<E : embedded_io::Error> From<E> for Error {
+            fn from(e : E) -> Self {
+                let kind = match e.kind() {
+                    embedded_io::ErrorKind::InvalidData => ErrorKind::InvalidData,
+                    embedded_io::ErrorKind::InvalidInput => ErrorKind::InvalidInput,
+                    embedded_io::ErrorKind::WriteZero => ErrorKind::WriteZero,
+                    _ => ErrorKind::Other,
+                };
+                Error::new(kind, "embedded-io error")
+            }
         }
-    };
-    let maybe_write_fun = if has_alt {
-        quote! {}
-    } else {
-        quote! { #write_fun }
-    };
 
-    let reader_name = if read_mem.is_empty() {
-        quote! {_reader}
-    } else {
-        quote! {reader}
-    };
-    let read_fun = quote! {
-        pub fn read<R>(#reader_name : &mut R) -> Result<Self, Error> where R : Read {
-            #read_mem
-            Ok(Self {#read_mems})
+        pub trait Read {
+            fn read_exact(&mut self, buf : &mut [u8]) -> Result<()>;
         }
-    };
-    let maybe_read_fun = if has_alt {
-        quote! {}
-    } else {
-        quote! { #read_fun }
-    };
 
-    if !has_alt {
-        mod_items.extend(deriving_tokens());
-    }
+        pub trait Write {
+            fn write_all(&mut self, buf : &[u8]) -> Result<()>;
+        }
 
-    if structure.members.len() > 1 {
-        mod_items.extend(quote! {
-            #[repr(packed)]
-        });
+        impl<T : embedded_io::Read + ?Sized> Read for T {
+            fn read_exact(&mut self, buf : &mut [u8]) -> Result<()> {
+                embedded_io::Read::read_exact(self, buf).map_err(|e| match e {
+                    embedded_io::ReadExactError::UnexpectedEof => {
+                        Error::new(ErrorKind::UnexpectedEof, "failed to fill whole buffer")
+                    }
+                    embedded_io::ReadExactError::Other(e) => e.into(),
+                })
+            }
+        }
+
+        impl<T : embedded_io::Write + ?Sized> Write for T {
+            fn write_all(&mut self, buf : &[u8]) -> Result<()> {
+                Ok(embedded_io::Write::write_all(self, buf)?)
+            }
+        }
     }
+}
 
-    let fields_mod = if str_items.is_empty() {
-        str_items
-    } else {
-        quote! { mod #fields_mod_name { #str_items } }
-    };
+pub fn render(structure: &Structure, derives: &Derives, config: &Config) -> Result<TokenStream> {
+    render_with_alts(structure, &Alternatives::new(), derives, config)
+}
 
-    mod_items.extend(quote! {
-        pub struct #str_name<#templ> where #where_clause {
-            #str_mems
-        }
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::process::Command;
 
-        #fields_mod
+    use super::Structure;
+    use crate::tree::GenTree;
+    use crate::util::{ByteOrder, IoBackend};
 
-        impl<#templ> #str_name<#templ> where #where_clause {
-            #[inline(always)]
-            pub fn new() -> Self {
-                Self {
-                    #inst_default
-                }
-            }
+    /// Write `structure` as the crate `name` with the test module `checks` appended to its
+    /// `lib.rs`, and run its tests.
+    fn check_encoding(name: &str, structure: &Structure, checks: &str) {
+        let mut tree = GenTree::new();
+        tree.set_io(IoBackend::Core);
+        tree.add_struct(structure).unwrap();
 
-            #str_fns
+        let tmp = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("target")
+            .join("tmp");
+        let dir = tmp.join(name);
+        let _ = fs::remove_dir_all(&dir);
+        tree.write_crate(dir.to_str().unwrap(), name, "0.1.0")
+            .unwrap();
 
-            #maybe_write_fun
+        let mut lib = OpenOptions::new()
+            .append(true)
+            .open(dir.join("src").join("lib.rs"))
+            .unwrap();
+        write!(
+            lib,
+            "\n#[cfg(test)]\nmod checks {{\n    use super::*;\n\n{}\n}}\n",
+            checks
+        )
+        .unwrap();
 
-            #maybe_read_fun
-        }
-    });
+        let status = Command::new(env!("CARGO"))
+            .arg("test")
+            .current_dir(&dir)
+            .env("CARGO_TARGET_DIR", tmp.join("target"))
+            .status()
+            .unwrap();
+        assert!(status.success(), "tests of the crate `{}` failed", name);
+    }
 
-    if has_alt {
-        mod_items.extend(quote! {
-            pub struct #str_name_gen {
-                #str_mems_gen
-            }
+    #[test]
+    fn varint_edge_values() {
+        let structure = Structure::new("number").add_varint_field("value", 64);
 
-            impl #str_name_gen {
-                pub fn default() -> Self {
-                    Self { #default_mems }
-                }
+        check_encoding(
+            "varint_member",
+            &structure,
+            r#"
+    fn encode(v: u64) -> ([u8; number::Number::MAX_SIZE], usize) {
+        let mut value = number::Number::new();
+        value.set_value(v);
+        let mut buf = [0u8; number::Number::MAX_SIZE];
+        let len = value.write_to(&mut buf).unwrap();
+        assert_eq!(number::Number::read_from(&buf[..len]).unwrap().0.get_value(), v);
+        (buf, len)
+    }
 
-                #write_fun
-            }
-        });
+    #[test]
+    fn edge_values() {
+        let (buf, len) = encode(0);
+        assert_eq!(&buf[..len], &[0x00]);
+        let (buf, len) = encode(127);
+        assert_eq!(&buf[..len], &[0x7f]);
+        let (buf, len) = encode(128);
+        assert_eq!(&buf[..len], &[0x80, 0x01]);
+        let (buf, len) = encode(u64::MAX);
+        assert_eq!(&buf[..len], &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]);
+    }
+"#,
+        );
     }
 
-    if !default_templ.is_empty() {
-        mod_items.extend(quote! {
-            pub type #str_name_def = #str_name<#default_templ>;
-        });
+    #[test]
+    fn bcd_edge_values() {
+        let structure = Structure::new("reading")
+            .with_byte_order(ByteOrder::BigEndian)
+            .add_bcd_field("value", 2);
+
+        check_encoding(
+            "bcd_member",
+            &structure,
+            r#"
+    #[test]
+    fn edge_values() {
+        let mut value = reading::Reading::new();
+        value.set_value(9999).unwrap();
+        let mut buf = [0u8; reading::Reading::SIZE];
+        value.write_to(&mut buf).unwrap();
+        assert_eq!(buf, [0x99, 0x99]);
+        assert!(matches!(
+            value.set_value(10000),
+            Err(Error::OutOfRange { field: "value", value: 10000 })
+        ));
+
+        let (read, _) = reading::Reading::read_from(&[0x12, 0x34]).unwrap();
+        assert_eq!(read.get_value(), 1234);
+        assert!(matches!(
+            reading::Reading::read_from(&[0x12, 0x3a]),
+            Err(Error::InvalidBcd { field: "value" })
+        ));
+    }
+"#,
+        );
     }
 
-    Ok(mod_items)
-}
+    #[test]
+    fn counted_array_over_capacity() {
+        let structure = Structure::new("samples")
+            .with_byte_order(ByteOrder::BigEndian)
+            .add_len_field("count", 1)
+            .add_repeated_field("values", 2, "count", 3);
 
-pub fn render_imports() -> TokenStream {
-    quote! {
-        use core2::io::{Error, Read, Write};
-        use defmt::Format;
+        check_encoding(
+            "repeated_member",
+            &structure,
+            r#"
+    #[test]
+    fn count_over_capacity() {
+        let mut value = samples::Samples::new();
+        value.values().set(&[1, 2, 3]);
+        let mut buf = [0u8; samples::Samples::MAX_SIZE];
+        let len = value.write_to(&mut buf).unwrap();
+        assert_eq!(&buf[..len], &[3, 0, 1, 0, 2, 0, 3]);
+
+        assert!(matches!(
+            samples::Samples::read_from(&[4, 0, 1, 0, 2, 0, 3, 0, 4]),
+            Err(Error::LengthMismatch { field: "values", len: 4 })
+        ));
+    }
+"#,
+        );
     }
-}
 
-pub fn render(structure: &Structure) -> Result<TokenStream> {
-    render_with_alts(structure, &Alternatives::new())
+    #[test]
+    fn terminated_string_without_terminator() {
+        let structure = Structure::new("label").add_cstring_field("name", 4);
+
+        check_encoding(
+            "cstring_member",
+            &structure,
+            r#"
+    #[test]
+    fn missing_terminator() {
+        let (read, len) = label::Label::read_from(b"abc\0").unwrap();
+        assert_eq!((read.get_name(), len), ("abc", 4));
+
+        // the input ends before the terminator
+        assert!(matches!(label::Label::read_from(b"abc"), Err(Error::UnexpectedEof)));
+        // no terminator within the capacity
+        assert!(matches!(
+            label::Label::read_from(b"abcde\0"),
+            Err(Error::LengthMismatch { field: "name", len: 5 })
+        ));
+    }
+"#,
+        );
+    }
 }
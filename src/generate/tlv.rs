@@ -0,0 +1,388 @@
+use anyhow::{bail, Result};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+
+use crate::generate::structure::{deriving_tokens_no_copy, StructMember, Structure};
+use crate::util::BITS_PER_BYTE;
+use crate::util::{
+    self, hex, unsuffixed, ByteOrder, Derives, Naming, ToSanitizedPascalCase, U32Ext,
+};
+
+/// Type-length-value entries, such as the information elements of IEEE 802.15.4 or the AD
+/// structures of Bluetooth LE, each of which is a tag selecting the structure of its value
+/// followed by the length of the value. Rendered as an enum of the entries, an iterator over
+/// the entries of a buffer and a builder appending entries to a buffer, which fixes up their
+/// lengths. Entries of unknown tags are kept as their raw value.
+pub struct Tlv {
+    pub name: String,
+    pub desc: String,
+    /// Bytes of the tag of every entry.
+    pub tag_bytes: u32,
+    /// Bytes of the length of every entry.
+    pub len_bytes: u32,
+    /// Whether the length precedes the tag rather than following it.
+    pub len_first: bool,
+    /// Bytes counted by the length in addition to the value, e.g. a tag following the length.
+    pub len_extra: u32,
+    /// Byte order of the tag and length, which is the one of the structures of the entries.
+    pub byte_order: ByteOrder,
+    /// The tags along with the structures of the values they select and whether those hold
+    /// alternatives, which entries cannot.
+    pub entries: Vec<(u64, String, bool)>,
+    /// Derives overriding the ones of the generated file.
+    pub derives: Option<Derives>,
+}
+
+impl Tlv {
+    pub fn new(name: &str, tag_bytes: u32, len_bytes: u32) -> Self {
+        Tlv {
+            name: String::from(name),
+            desc: String::new(),
+            tag_bytes,
+            len_bytes,
+            len_first: false,
+            len_extra: 0,
+            byte_order: ByteOrder::default(),
+            entries: vec![],
+            derives: None,
+        }
+    }
+
+    /// Document the generated enum with `desc`.
+    pub fn with_desc(mut self, desc: &str) -> Self {
+        self.desc = String::from(desc);
+        self
+    }
+
+    /// Place the length before the tag of every entry.
+    pub fn with_len_first(mut self) -> Self {
+        self.len_first = true;
+        self
+    }
+
+    /// Count `extra` bytes in addition to the value in the length, e.g. the tag of AD
+    /// structures, whose length precedes it.
+    pub fn with_len_extra(mut self, extra: u32) -> Self {
+        self.len_extra = extra;
+        self
+    }
+
+    /// Read the tag and length in `byte_order`, which should match the structures of the
+    /// entries.
+    pub fn with_byte_order(mut self, byte_order: ByteOrder) -> Self {
+        self.byte_order = byte_order;
+        self
+    }
+
+    /// Derive `derives` on the enum instead of the derives of the generated file.
+    pub fn with_derives(mut self, derives: Derives) -> Self {
+        self.derives = Some(derives);
+        self
+    }
+
+    /// Add the entry `structure`, whose value is read when the tag is `tag`. The structure
+    /// must not hold alternatives.
+    pub fn add_entry(mut self, tag: u64, structure: &Structure) -> Self {
+        let alternatives = structure
+            .members
+            .iter()
+            .any(|mem| matches!(mem, StructMember::AlternativesMember(_)));
+        self.entries
+            .push((tag, structure.name.clone(), alternatives));
+        self
+    }
+
+    /// Bytes of the tag and length preceding every value.
+    pub fn header_bytes(&self) -> u32 {
+        self.tag_bytes + self.len_bytes
+    }
+
+    /// Describe the entries which are generated as the same variant and the tags selecting
+    /// more than one entry.
+    pub fn conflicts(&self, naming: &Naming) -> Vec<String> {
+        let owner = format!("entries `{}`", self.name);
+        let mut entries = self
+            .entries
+            .iter()
+            .map(|(_, entry, _)| entry.as_str())
+            .collect::<Vec<_>>();
+        // entries of unknown tags share the enum
+        entries.push("unknown");
+        let mut conflicts = util::name_conflicts("entry", &owner, &entries, |name| {
+            name.to_sanitized_pascal_case(&naming.types()).into()
+        });
+        for (i, (tag, entry, _)) in self.entries.iter().enumerate() {
+            if let Some((_, first, _)) = self.entries[..i].iter().find(|(other, _, _)| other == tag)
+            {
+                conflicts.push(format!(
+                    "entries `{}` and `{}` of {} both have the tag {:#x}",
+                    first, entry, owner, tag
+                ));
+            }
+        }
+        conflicts
+    }
+}
+
+pub fn render(tlv: &Tlv, derives: &Derives, naming: &Naming) -> Result<TokenStream> {
+    for (name, bytes) in [("tag", tlv.tag_bytes), ("length", tlv.len_bytes)] {
+        if bytes == 0 || bytes > 8 {
+            bail!(
+                "{} of entries `{}` does not fit into an integer",
+                name,
+                tlv.name
+            );
+        }
+    }
+    let max_tag = u64::MAX >> (64 - tlv.tag_bytes * BITS_PER_BYTE);
+    let max_len = u64::MAX >> (64 - tlv.len_bytes * BITS_PER_BYTE);
+    if let Some((_, entry, _)) = tlv.entries.iter().find(|(_, _, alts)| *alts) {
+        bail!(
+            "entry `{}` of `{}` holds alternatives, which entries cannot",
+            entry,
+            tlv.name
+        );
+    }
+    if let Some((tag, entry, _)) = tlv.entries.iter().find(|(tag, _, _)| *tag > max_tag) {
+        bail!(
+            "tag {:#x} of entry `{}` of `{}` does not fit into {} bytes",
+            tag,
+            entry,
+            tlv.name,
+            tlv.tag_bytes
+        );
+    }
+    if tlv.len_extra as u64 > max_len {
+        bail!(
+            "length of entries `{}` cannot count {} extra bytes",
+            tlv.name,
+            tlv.len_extra
+        );
+    }
+
+    let span = Span::call_site();
+    let deriving = deriving_tokens_no_copy(tlv.derives.as_ref().unwrap_or(derives))?;
+
+    let name_pc = util::ident(&tlv.name.to_sanitized_pascal_case(&naming.types()), span);
+    let iter_name = util::ident(&format!("{}Iter", name_pc), span);
+    let builder_name = util::ident(&format!("{}Builder", name_pc), span);
+    let field_name = tlv.name.as_str();
+    let doc = if tlv.desc.is_empty() {
+        format!("Entries selected by a tag of {} bytes.", tlv.tag_bytes)
+    } else {
+        tlv.desc.clone()
+    };
+    let iter_doc = format!("Iterator over the [`{}`] entries of a buffer.", name_pc);
+    let builder_doc = format!(
+        "Appends [`{}`] entries to a buffer, writing their lengths after their values.",
+        name_pc
+    );
+
+    let tag_ty = (tlv.tag_bytes * BITS_PER_BYTE).to_ty()?;
+    let (tag_start, len_start) = if tlv.len_first {
+        (tlv.len_bytes, 0)
+    } else {
+        (0, tlv.tag_bytes)
+    };
+    let tag_range = tlv.byte_order.range(tlv.tag_bytes, 8);
+    let len_range = tlv.byte_order.range(tlv.len_bytes, 8);
+    let tag_end = unsuffixed((tag_start + tlv.tag_bytes) as u64);
+    let len_end = unsuffixed((len_start + tlv.len_bytes) as u64);
+    let tag_start = unsuffixed(tag_start as u64);
+    let len_start = unsuffixed(len_start as u64);
+    let from_bytes = tlv.byte_order.from_bytes();
+    let to_bytes = tlv.byte_order.to_bytes();
+    let header = unsuffixed(tlv.header_bytes() as u64);
+    let extra = unsuffixed(tlv.len_extra as u64);
+    let max_len = hex(max_len);
+
+    let mut variants = TokenStream::new();
+    let mut conversions = TokenStream::new();
+    let mut tag_arms = TokenStream::new();
+    let mut read_arms = TokenStream::new();
+    let mut write_arms = TokenStream::new();
+    let mut size_arms = TokenStream::new();
+    for (tag, entry, _) in &tlv.entries {
+        let entry = util::ident(&entry.to_sanitized_pascal_case(&naming.types()), span);
+        let tag = hex(*tag);
+
+        variants.extend(quote! {
+            #entry(#entry),
+        });
+        conversions.extend(quote! {
+            impl<'a> From<#entry> for #name_pc<'a> {
+                #[inline(always)]
+                fn from(value : #entry) -> Self {
+                    #name_pc::#entry(value)
+                }
+            }
+        });
+        tag_arms.extend(quote! {
+            #name_pc::#entry(_) => #tag,
+        });
+        read_arms.extend(quote! {
+            #tag => #name_pc::#entry(#entry::read_from(value)?.0),
+        });
+        write_arms.extend(quote! {
+            #name_pc::#entry(entry) => entry.write_to(out)?,
+        });
+        size_arms.extend(quote! {
+            #name_pc::#entry(entry) => entry.wire_size(),
+        });
+    }
+
+    Ok(quote! {
+        #[doc = #doc]
+        #deriving
+        pub enum #name_pc<'a> {
+            #variants
+            #[doc = "An entry whose tag selects none of the known entries, holding its raw value."]
+            Unknown { tag : #tag_ty, value : &'a [u8] },
+        }
+
+        #conversions
+
+        impl<'a> #name_pc<'a> {
+            #[doc = "Bytes of the tag and length preceding the value of every entry."]
+            pub const HEADER_SIZE : usize = #header;
+
+            #[doc = "The tag selecting the entry."]
+            pub fn tag(&self) -> #tag_ty {
+                match self {
+                    #tag_arms
+                    #name_pc::Unknown { tag, .. } => *tag,
+                }
+            }
+
+            #[doc = "Deserialize the entry at the start of `buf`, returning the number of bytes read. The value of a known entry may be followed by bytes it ignores."]
+            pub fn read_from(buf : &'a [u8]) -> Result<(Self, usize), Error> {
+                if buf.len() < #header {
+                    return Err(Error::UnexpectedEof);
+                }
+                let mut tag = [0u8; 8];
+                tag[#tag_range].copy_from_slice(&buf[#tag_start..#tag_end]);
+                let tag = u64::#from_bytes(tag) as #tag_ty;
+                let mut len = [0u8; 8];
+                len[#len_range].copy_from_slice(&buf[#len_start..#len_end]);
+                let len = u64::#from_bytes(len) as usize;
+                let end = match len.checked_sub(#extra) {
+                    Some(value_len) => #header + value_len,
+                    None => return Err(Error::LengthMismatch { field : #field_name, len }),
+                };
+                if buf.len() < end {
+                    return Err(Error::UnexpectedEof);
+                }
+                let value = &buf[#header..end];
+                #[allow(clippy::match_single_binding)]
+                let entry = match tag {
+                    #read_arms
+                    _ => #name_pc::Unknown { tag, value },
+                };
+                Ok((entry, end))
+            }
+
+            #[doc = "Serialize into `buf` along with the length of the value, returning the number of bytes written."]
+            pub fn write_to(&self, buf : &mut [u8]) -> Result<usize, Error> {
+                if buf.len() < #header {
+                    return Err(Error::WriteZero);
+                }
+                let (header, out) = buf.split_at_mut(#header);
+                let written = match self {
+                    #write_arms
+                    #name_pc::Unknown { value, .. } => {
+                        if out.len() < value.len() {
+                            return Err(Error::WriteZero);
+                        }
+                        out[..value.len()].copy_from_slice(value);
+                        value.len()
+                    }
+                };
+                // the length is fixed up once the value is written
+                let len = written + #extra;
+                if len as u64 > #max_len {
+                    return Err(Error::LengthMismatch { field : #field_name, len });
+                }
+                header[#tag_start..#tag_end].copy_from_slice(&(self.tag() as u64).#to_bytes()[#tag_range]);
+                header[#len_start..#len_end].copy_from_slice(&(len as u64).#to_bytes()[#len_range]);
+                Ok(#header + written)
+            }
+
+            #[doc = "Number of bytes written by `write_to` for the current entry."]
+            pub fn wire_size(&self) -> usize {
+                #header + match self {
+                    #size_arms
+                    #name_pc::Unknown { value, .. } => value.len(),
+                }
+            }
+
+            #[doc = "Iterate over the entries filling `buf`."]
+            pub fn iter(buf : &'a [u8]) -> #iter_name<'a> {
+                #iter_name { buf }
+            }
+        }
+
+        #[doc = #iter_doc]
+        #[doc = ""]
+        #[doc = "Iteration stops after the first entry which cannot be read."]
+        pub struct #iter_name<'a> {
+            buf : &'a [u8],
+        }
+
+        impl<'a> Iterator for #iter_name<'a> {
+            type Item = Result<#name_pc<'a>, Error>;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                if self.buf.is_empty() {
+                    return None;
+                }
+                match #name_pc::read_from(self.buf) {
+                    Ok((entry, len)) => {
+                        self.buf = &self.buf[len..];
+                        Some(Ok(entry))
+                    }
+                    Err(e) => {
+                        self.buf = &[];
+                        Some(Err(e))
+                    }
+                }
+            }
+        }
+
+        impl<'a> core::iter::FusedIterator for #iter_name<'a> {}
+
+        #[doc = #builder_doc]
+        pub struct #builder_name<'b> {
+            buf : &'b mut [u8],
+            len : usize,
+        }
+
+        impl<'b> #builder_name<'b> {
+            #[doc = "Append entries to the start of `buf`."]
+            pub fn new(buf : &'b mut [u8]) -> Self {
+                Self { buf, len : 0 }
+            }
+
+            #[doc = "Append `entry`, keeping the entries appended so far if it does not fit."]
+            pub fn push(&mut self, entry : &#name_pc<'_>) -> Result<&mut Self, Error> {
+                self.len += entry.write_to(&mut self.buf[self.len..])?;
+                Ok(self)
+            }
+
+            #[doc = "Number of bytes appended so far."]
+            pub fn len(&self) -> usize {
+                self.len
+            }
+
+            #[doc = "Whether no entry was appended so far."]
+            pub fn is_empty(&self) -> bool {
+                self.len == 0
+            }
+
+            #[doc = "The bytes of the entries appended."]
+            pub fn finish(self) -> &'b [u8] {
+                let buf : &'b [u8] = self.buf;
+                &buf[..self.len]
+            }
+        }
+    })
+}
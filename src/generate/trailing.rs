@@ -0,0 +1,133 @@
+use anyhow::Result;
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+
+use crate::generate::structure::Structure;
+use crate::generate::view;
+use crate::util::{self, Naming, ToSanitizedPascalCase, ToSanitizedSnakeCase};
+
+/// Render the iterator over the records following `structure` up to the end of the buffer, if
+/// it has any, see [`Structure::with_trailing`]. The structure reads and writes itself along
+/// with its records, and its view gives the records following it.
+pub fn render(structure: &Structure, naming: &Naming) -> Result<TokenStream> {
+    let members = naming.names_of(&structure.name);
+    let (name, record) = match &structure.trailing {
+        Some(trailing) => trailing,
+        None => return Ok(TokenStream::new()),
+    };
+
+    let span = Span::call_site();
+    let str_name = util::ident(
+        &structure.name.to_sanitized_pascal_case(&naming.types()),
+        span,
+    );
+    let record_name = util::ident(&record.to_sanitized_pascal_case(&naming.types()), span);
+    let iter_name = util::ident(
+        &format!(
+            "{}{}Iter",
+            str_name,
+            name.to_sanitized_pascal_case(&members)
+        ),
+        span,
+    );
+    let name_sc = name.to_sanitized_snake_case(&members);
+    let read_fn = util::ident(&format!("read_with_{}", name_sc), span);
+    let write_fn = util::ident(&format!("write_with_{}", name_sc), span);
+    let iter_doc = format!(
+        "Iterator over the [`{}`] records following a [`{}`] up to the end of the buffer.",
+        record_name, str_name
+    );
+    let read_doc = format!(
+        "Deserialize from the start of `buf`, returning an iterator over the `{}` following it.",
+        name
+    );
+    let write_doc = format!(
+        "Serialize into `buf` followed by the `{}`, returning the number of bytes written.",
+        name
+    );
+
+    let mut out = quote! {
+        #[doc = #iter_doc]
+        #[doc = ""]
+        #[doc = "Iteration stops after the first record which cannot be read."]
+        #[derive(Clone, Debug)]
+        pub struct #iter_name<'a> {
+            buf : &'a [u8],
+        }
+
+        impl<'a> #iter_name<'a> {
+            #[doc = "Iterate over the records filling `buf`."]
+            pub fn new(buf : &'a [u8]) -> Self {
+                Self { buf }
+            }
+
+            #[doc = "The bytes of the records not read yet."]
+            pub fn remaining(&self) -> &'a [u8] {
+                self.buf
+            }
+        }
+
+        impl<'a> Iterator for #iter_name<'a> {
+            type Item = Result<#record_name, Error>;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                if self.buf.is_empty() {
+                    return None;
+                }
+                match #record_name::read_from(self.buf) {
+                    // records of no bytes would never reach the end of the buffer
+                    Ok((record, 0)) => {
+                        self.buf = &[];
+                        Some(Ok(record))
+                    }
+                    Ok((record, len)) => {
+                        self.buf = &self.buf[len..];
+                        Some(Ok(record))
+                    }
+                    Err(e) => {
+                        self.buf = &[];
+                        Some(Err(e))
+                    }
+                }
+            }
+        }
+
+        impl<'a> core::iter::FusedIterator for #iter_name<'a> {}
+
+        impl #str_name {
+            #[doc = #read_doc]
+            pub fn #read_fn(buf : &[u8]) -> Result<(Self, #iter_name<'_>), Error> {
+                let (value, len) = Self::read_from(buf)?;
+                Ok((value, #iter_name::new(&buf[len..])))
+            }
+
+            #[doc = #write_doc]
+            pub fn #write_fn(&self, records : &[#record_name], buf : &mut [u8]) -> Result<usize, Error> {
+                let mut len = self.write_to(buf)?;
+                for record in records {
+                    len += record.write_to(&mut buf[len..])?;
+                }
+                Ok(len)
+            }
+        }
+    };
+
+    if view::has_view(structure) {
+        let ref_name = util::ident(&format!("{}Ref", str_name), span);
+        let getter = util::ident(&name_sc, span);
+        let doc = format!(
+            "Iterate over the `{}` following the structure up to the end of the buffer.",
+            name
+        );
+        out.extend(quote! {
+            impl<'a> #ref_name<'a> {
+                #[doc = #doc]
+                pub fn #getter(&self) -> #iter_name<'a> {
+                    #iter_name::new(&self.buf[self.wire_len()..])
+                }
+            }
+        });
+    }
+
+    Ok(out)
+}
@@ -0,0 +1,76 @@
+use anyhow::Result;
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+
+use crate::generate::structure::{Payloads, StructMember, Structure};
+use crate::util::{self, unsuffixed, Naming, ToSanitizedSnakeCase};
+
+/// Render the `write_vectored` of `structure` if it holds byte payloads, where `pieces` are the
+/// serializations of its members to `out`. The payloads are borrowed from the structure while
+/// the runs of members between them are written to a scratch buffer, so the structure can be
+/// handed to scatter-gather DMA or a vectored send without assembling it in one buffer.
+pub fn render(
+    structure: &Structure,
+    pieces: &[TokenStream],
+    naming: &Naming,
+) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let members = naming.names_of(&structure.name);
+
+    let mut writes = TokenStream::new();
+    let mut slices = vec![];
+    let mut runs = 0usize;
+    let mut start = quote! { 0 };
+    for (idx, mem) in structure.members.iter().enumerate() {
+        let mem = match mem {
+            StructMember::BytesMember(mem) => mem,
+            _ => {
+                writes.extend(pieces[idx].clone());
+                continue;
+            }
+        };
+        let len = match structure.find_len_source(&mem.len_from, idx) {
+            Some(len) => len,
+            // reported when rendering the structure itself
+            None => return Ok(TokenStream::new()),
+        };
+        let len_name = util::ident(&len.name().to_sanitized_snake_case(&members), span);
+        let mem_name = util::ident(&mem.name.to_sanitized_snake_case(&members), span);
+
+        let run = unsuffixed(runs as u64);
+        writes.extend(quote! {
+            ends[#run] = len - out.len();
+        });
+        slices.push(quote! { &scratch[#start..ends[#run]] });
+        slices.push(match structure.payloads {
+            Payloads::Array => quote! { &self.#mem_name[..self.#len_name as usize] },
+            Payloads::Heapless | Payloads::Alloc => quote! { &self.#mem_name[..] },
+        });
+        start = quote! { ends[#run] };
+        runs += 1;
+    }
+    if runs == 0 {
+        return Ok(TokenStream::new());
+    }
+
+    let run = unsuffixed(runs as u64);
+    writes.extend(quote! {
+        ends[#run] = len - out.len();
+    });
+    slices.push(quote! { &scratch[#start..ends[#run]] });
+    let count = unsuffixed(slices.len() as u64);
+    let runs = unsuffixed(runs as u64 + 1);
+
+    Ok(quote! {
+        #[doc = "Serialize into slices to be sent one after the other, e.g. by scatter-gather DMA, which borrow the payloads of the structure and hold the other members written to `scratch`. Some of the slices may be empty."]
+        pub fn write_vectored<'s>(&'s self, scratch : &'s mut [u8]) -> Result<[&'s [u8]; #count], Error> {
+            let mut ends = [0usize; #runs];
+            let len = scratch.len();
+            let mut cursor = &mut scratch[..];
+            let out = &mut cursor;
+            #writes
+            let scratch : &'s [u8] = scratch;
+            Ok([#(#slices),*])
+        }
+    })
+}
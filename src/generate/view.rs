@@ -0,0 +1,1113 @@
+use anyhow::{bail, Result};
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+
+use crate::generate::structure::{
+    self, render_read_bits, render_write_bits, RepeatedMember, StringKind, StringMember,
+    StructMember, Structure,
+};
+use crate::util::{
+    self, hex, unsuffixed, ByteOrder, Naming, Scope, ToSanitizedPascalCase, ToSanitizedSnakeCase,
+    ToSanitizedUpperCase, U32Ext, BITS_PER_BYTE,
+};
+
+/// Whether the wire layout of `structure` can be determined from the bytes alone, which is
+/// required to generate views over byte slices. Varints, extension bit chains and NUL-terminated
+/// strings are left out as the size of every following member would depend on decoding them,
+/// as are elements counted by a field of a bitfield rather than by a length field.
+pub fn has_view(structure: &Structure) -> bool {
+    structure.members.iter().all(|mem| {
+        !matches!(
+            mem,
+            StructMember::AlternativesMember(_)
+                | StructMember::OptionalMember(_)
+                | StructMember::NibbleVarintMember(_)
+                | StructMember::ExtendedMember(_)
+                | StructMember::VarintMember(_)
+                | StructMember::StringMember(StringMember {
+                    kind: StringKind::NullTerminated,
+                    ..
+                })
+                | StructMember::RepeatedMember(RepeatedMember {
+                    count_field: Some(_),
+                    ..
+                })
+        )
+    })
+}
+
+/// Name of the method returning the byte offset of the member `name` within the view.
+fn offset_ident(name: &str, members: &Scope) -> Ident {
+    util::ident(
+        &format!("offset_of_{}", name.to_sanitized_snake_case(members)),
+        Span::call_site(),
+    )
+}
+
+/// Render an expression decoding the integer of `bytes` bytes in byte order `order` in the
+/// slice expression `slice`, which is a byte array for more than 8 bytes.
+pub(crate) fn render_decode_int(
+    slice: TokenStream,
+    bytes: u32,
+    order: ByteOrder,
+) -> Result<TokenStream> {
+    if bytes > 8 {
+        let bytes = unsuffixed(bytes as u64);
+        return Ok(quote! {
+            {
+                let mut buffer = [0u8; #bytes];
+                buffer.copy_from_slice(&#slice[..#bytes]);
+                buffer
+            }
+        });
+    }
+
+    let sty = (bytes * BITS_PER_BYTE).to_ty()?;
+    let width = (bytes * BITS_PER_BYTE).to_ty_width()? / BITS_PER_BYTE;
+    let buffer_len = unsuffixed(width as u64);
+    let range = order.range(bytes, width);
+    let from_bytes = order.from_bytes();
+    let bytes = unsuffixed(bytes as u64);
+
+    Ok(quote! {
+        {
+            let mut buffer = [0u8; #buffer_len];
+            buffer[#range].copy_from_slice(&#slice[..#bytes]);
+            #sty::#from_bytes(buffer)
+        }
+    })
+}
+
+/// Render an expression for the number of bytes `mem` occupies on the wire, which may refer
+/// to previous length fields of the view `this`.
+pub(crate) fn render_size(
+    mem: &StructMember,
+    this: &TokenStream,
+    members: &Scope,
+) -> Result<TokenStream> {
+    let span = Span::call_site();
+
+    Ok(match mem {
+        StructMember::BitfieldMember(mem) => unsuffixed(mem.bytes as u64),
+        StructMember::PrimitiveMember(mem) => unsuffixed(mem.bytes as u64),
+        StructMember::LengthMember(mem) => unsuffixed(mem.bytes as u64),
+        StructMember::ConstantMember(mem) => unsuffixed(mem.bytes as u64),
+        StructMember::OffsetMember(mem) => unsuffixed(mem.bytes as u64),
+        StructMember::BcdMember(mem) => unsuffixed(mem.bytes as u64),
+        StructMember::TimestampMember(mem) => unsuffixed(mem.bytes as u64),
+        StructMember::EuiMember(mem) => unsuffixed(mem.bytes as u64),
+        StructMember::BitsMember(mem) => unsuffixed(mem.bytes() as u64),
+        StructMember::ChecksumMember(mem) => unsuffixed(mem.algorithm.bytes() as u64),
+        StructMember::ArrayMember(mem) => unsuffixed((mem.bytes * mem.count) as u64),
+        StructMember::BytesMember(mem) => {
+            let len_name = util::ident(&mem.len_from.to_sanitized_snake_case(members), span);
+            quote! { (#this.#len_name() as usize) }
+        }
+        StructMember::StringMember(StringMember {
+            kind: StringKind::Prefixed(len_from),
+            ..
+        }) => {
+            let len_name = util::ident(&len_from.to_sanitized_snake_case(members), span);
+            quote! { (#this.#len_name() as usize) }
+        }
+        StructMember::StringMember(StringMember {
+            kind: StringKind::Fixed,
+            capacity,
+            ..
+        }) => unsuffixed(*capacity as u64),
+        StructMember::RepeatedMember(mem) => {
+            let len_name = util::ident(&mem.count_from.to_sanitized_snake_case(members), span);
+            let bytes = unsuffixed(mem.bytes as u64);
+            quote! { (#this.#len_name() as usize * #bytes) }
+        }
+        StructMember::AlternativesMember(_)
+        | StructMember::OptionalMember(_)
+        | StructMember::NibbleVarintMember(_)
+        | StructMember::ExtendedMember(_)
+        | StructMember::VarintMember(_)
+        | StructMember::StringMember(_) => {
+            bail!("member `{}` has no layout known from its bytes", mem.name())
+        }
+    })
+}
+
+/// Render the methods computing the offset of each member of the view, as well as the total
+/// length of the structure on the wire.
+pub(crate) fn render_offsets(structure: &Structure, naming: &Naming) -> Result<TokenStream> {
+    let this = quote! { self };
+    let members = naming.names_of(&structure.name);
+    let mut fns = TokenStream::new();
+    let mut offset = quote! { 0 };
+
+    for mem in &structure.members {
+        let offset_fn = offset_ident(mem.name(), &members);
+        let size = render_size(mem, &this, &members)?;
+
+        fns.extend(quote! {
+            #[inline(always)]
+            fn #offset_fn(&self) -> usize {
+                #offset
+            }
+        });
+
+        offset = quote! { self.#offset_fn() + #size };
+    }
+
+    fns.extend(quote! {
+        /// Length of the structure on the wire.
+        #[inline(always)]
+        pub fn wire_len(&self) -> usize {
+            #offset
+        }
+    });
+
+    Ok(fns)
+}
+
+/// Render the statements of a view constructor checking that `view` fits into `buf`. When
+/// `validate` is set, the constants and checksums held by `buf` are checked as well.
+pub(crate) fn render_checks(
+    structure: &Structure,
+    validate: bool,
+    naming: &Naming,
+) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let members = naming.names_of(&structure.name);
+    let str_name = util::ident(
+        &structure.name.to_sanitized_pascal_case(&naming.types()),
+        span,
+    );
+    let this = quote! { view };
+    let mut checks = TokenStream::new();
+
+    for mem in &structure.members {
+        let offset_fn = offset_ident(mem.name(), &members);
+        let size = render_size(mem, &this, &members)?;
+
+        checks.extend(quote! {
+            if buf.len() < view.#offset_fn() + #size {
+                return Err(Error::UnexpectedEof);
+            }
+        });
+
+        if !validate {
+            continue;
+        }
+
+        match mem {
+            StructMember::LengthMember(mem) if mem.extra > 0 => {
+                let value = render_decode_int(
+                    quote! { buf[view.#offset_fn()..] },
+                    mem.bytes,
+                    structure.byte_order,
+                )?;
+                let extra = unsuffixed(mem.extra as u64);
+                let field_name = mem.name.as_str();
+
+                checks.extend(quote! {
+                    let value = #value;
+                    if value < #extra {
+                        return Err(Error::LengthMismatch { field : #field_name, len : value as usize });
+                    }
+                });
+            }
+            StructMember::ConstantMember(mem) => {
+                let const_name = util::ident(&mem.name.to_sanitized_upper_case(&members), span);
+                let value = render_decode_int(
+                    quote! { buf[view.#offset_fn()..] },
+                    mem.bytes,
+                    structure.byte_order,
+                )?;
+                let field_name = mem.name.as_str();
+
+                checks.extend(quote! {
+                    let value = #value;
+                    if value != #str_name::#const_name {
+                        return Err(Error::InvalidConstant { field : #field_name, value : value as u64 });
+                    }
+                });
+            }
+            StructMember::OffsetMember(mem) => {
+                let value = render_decode_int(
+                    quote! { buf[view.#offset_fn()..] },
+                    mem.bytes,
+                    structure.byte_order,
+                )?;
+                let offset_bits = unsuffixed(mem.offset_bits() as u64);
+                let marker = hex(mem.marker);
+                let field_name = mem.name.as_str();
+
+                checks.extend(quote! {
+                    let value = #value;
+                    if value >> #offset_bits != #marker {
+                        return Err(Error::InvalidConstant { field : #field_name, value : value as u64 });
+                    }
+                });
+            }
+            StructMember::ChecksumMember(mem) => {
+                let from_fn = offset_ident(&mem.from, &members);
+                let algorithm = mem.algorithm.ident();
+                let field_name = mem.name.as_str();
+                let value = render_decode_int(
+                    quote! { buf[view.#offset_fn()..] },
+                    mem.algorithm.bytes(),
+                    mem.algorithm.byte_order(structure.byte_order),
+                )?;
+
+                let extra = match &mem.extra {
+                    Some(extra) => {
+                        let key_name =
+                            util::ident(&extra.key.to_sanitized_snake_case(&members), span);
+                        let key_field = extra.key.as_str();
+                        let select = extra.render(quote! { view.#key_name() });
+                        quote! {
+                            match #select {
+                                Some(extra) => checksum.update(&[extra]),
+                                None => return Err(Error::InvalidEnumValue { field : #key_field, value : view.#key_name() as u64 }),
+                            }
+                        }
+                    }
+                    None => quote! {},
+                };
+
+                checks.extend(quote! {
+                    let mut checksum = crate::#algorithm::new();
+                    checksum.update(&buf[view.#from_fn()..view.#offset_fn()]);
+                    #extra
+                    if checksum.finish() != #value {
+                        return Err(Error::ChecksumMismatch { field : #field_name });
+                    }
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(checks)
+}
+
+/// Render the accessors decoding each member of a view. Borrowed data is returned with the
+/// lifetime `lt`.
+fn render_getters(structure: &Structure, lt: &TokenStream, naming: &Naming) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let members = naming.names_of(&structure.name);
+    let this = quote! { self };
+    let mut accessors = TokenStream::new();
+
+    for mem in &structure.members {
+        let mem_name = util::ident(&mem.name().to_sanitized_snake_case(&members), span);
+        let offset_fn = offset_ident(mem.name(), &members);
+        let slice = quote! { self.buf[self.#offset_fn()..] };
+
+        match mem {
+            StructMember::BitfieldMember(mem) if mem.access.can_read() => {
+                let pkg_name = structure.bitfield_path(&mem.bitfield, false, naming);
+                let value = render_decode_int(slice, mem.bytes, structure.byte_order)?;
+
+                accessors.extend(quote! {
+                    #[inline(always)]
+                    pub fn #mem_name(&self) -> #pkg_name::R {
+                        #pkg_name::R::new(#value)
+                    }
+                });
+            }
+            StructMember::PrimitiveMember(mem) if mem.access.can_read() => {
+                let sty = (mem.bytes * BITS_PER_BYTE).to_ty()?;
+                let value = render_decode_int(slice, mem.bytes, structure.byte_order)?;
+
+                accessors.extend(quote! {
+                    #[inline(always)]
+                    pub fn #mem_name(&self) -> #sty {
+                        #value
+                    }
+                });
+            }
+            StructMember::LengthMember(mem) => {
+                let sty = (mem.bytes * BITS_PER_BYTE).to_ty()?;
+                let value = render_decode_int(slice, mem.bytes, structure.byte_order)?;
+                let value = if mem.extra == 0 {
+                    value
+                } else {
+                    let extra = unsuffixed(mem.extra as u64);
+                    quote! { #value.saturating_sub(#extra) }
+                };
+
+                accessors.extend(quote! {
+                    #[inline(always)]
+                    pub fn #mem_name(&self) -> #sty {
+                        #value
+                    }
+                });
+            }
+            StructMember::ChecksumMember(mem) => {
+                let sty = (mem.algorithm.bytes() * BITS_PER_BYTE).to_ty()?;
+                let value = render_decode_int(
+                    slice,
+                    mem.algorithm.bytes(),
+                    mem.algorithm.byte_order(structure.byte_order),
+                )?;
+
+                accessors.extend(quote! {
+                    #[inline(always)]
+                    pub fn #mem_name(&self) -> #sty {
+                        #value
+                    }
+                });
+            }
+            StructMember::BytesMember(_) => {
+                let size = render_size(mem, &this, &members)?;
+
+                accessors.extend(quote! {
+                    #[inline(always)]
+                    pub fn #mem_name(&self) -> &#lt [u8] {
+                        let buf : &#lt [u8] = self.buf;
+                        let offset = self.#offset_fn();
+                        &buf[offset..offset + #size]
+                    }
+                });
+            }
+            // strings are validated on access, as mutable views may hold any bytes
+            StructMember::StringMember(string) => {
+                let size = render_size(mem, &this, &members)?;
+                let field_name = string.name.as_str();
+                let len = match string.kind {
+                    StringKind::Fixed => quote! {
+                        bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len())
+                    },
+                    _ => quote! { bytes.len() },
+                };
+
+                accessors.extend(quote! {
+                    #[inline(always)]
+                    pub fn #mem_name(&self) -> Result<&#lt str, Error> {
+                        let buf : &#lt [u8] = self.buf;
+                        let offset = self.#offset_fn();
+                        let bytes = &buf[offset..offset + #size];
+                        let len = #len;
+                        core::str::from_utf8(&bytes[..len]).map_err(|_| Error::InvalidString { field : #field_name })
+                    }
+                });
+            }
+            StructMember::ArrayMember(mem) => {
+                let sty = (mem.bytes * BITS_PER_BYTE).to_ty()?;
+                let count = unsuffixed(mem.count as u64);
+                let size = unsuffixed(mem.bytes as u64);
+                let element = render_decode_int(quote! { chunk }, mem.bytes, structure.byte_order)?;
+
+                accessors.extend(quote! {
+                    #[inline(always)]
+                    pub fn #mem_name(&self) -> [#sty; #count] {
+                        let mut #mem_name = [0; #count];
+                        let offset = self.#offset_fn();
+                        let chunks = self.buf[offset..].chunks_exact(#size);
+                        for (slot, chunk) in #mem_name.iter_mut().zip(chunks) {
+                            *slot = #element;
+                        }
+                        #mem_name
+                    }
+                });
+            }
+            StructMember::RepeatedMember(rep) => {
+                let sty = (rep.bytes * BITS_PER_BYTE).to_ty()?;
+                let bytes = unsuffixed(rep.bytes as u64);
+                let size = render_size(mem, &this, &members)?;
+                let element = render_decode_int(quote! { chunk }, rep.bytes, structure.byte_order)?;
+
+                accessors.extend(quote! {
+                    #[inline(always)]
+                    pub fn #mem_name(&self) -> impl Iterator<Item = #sty> + #lt {
+                        let buf : &#lt [u8] = self.buf;
+                        let offset = self.#offset_fn();
+                        buf[offset..offset + #size].chunks_exact(#bytes).map(|chunk| #element)
+                    }
+                });
+            }
+            // the member starts within the byte at its offset
+            StructMember::BitsMember(mem) => {
+                let sty = mem.ty()?;
+                let value = render_read_bits(
+                    &slice,
+                    mem.offset % BITS_PER_BYTE,
+                    mem.bits,
+                    structure.byte_order,
+                )?;
+
+                accessors.extend(quote! {
+                    #[inline(always)]
+                    pub fn #mem_name(&self) -> #sty {
+                        #value
+                    }
+                });
+            }
+            StructMember::EuiMember(mem) => {
+                let ty = mem.ty();
+                let bytes = unsuffixed(mem.bytes as u64);
+                let reverse = match structure.byte_order {
+                    ByteOrder::LittleEndian => quote! { octets.reverse(); },
+                    ByteOrder::BigEndian => quote! {},
+                };
+
+                accessors.extend(quote! {
+                    #[inline(always)]
+                    pub fn #mem_name(&self) -> crate::#ty {
+                        let offset = self.#offset_fn();
+                        let mut octets = [0u8; #bytes];
+                        octets.copy_from_slice(&self.buf[offset..offset + #bytes]);
+                        #reverse
+                        crate::#ty(octets)
+                    }
+                });
+            }
+            StructMember::TimestampMember(mem) => {
+                let value = render_decode_int(slice, mem.bytes, structure.byte_order)?;
+
+                accessors.extend(quote! {
+                    #[inline(always)]
+                    pub fn #mem_name(&self) -> u64 {
+                        u64::from(#value)
+                    }
+                });
+            }
+            // like strings, BCD digits are validated on access
+            StructMember::BcdMember(mem) => {
+                let sty = mem.ty()?;
+                let value = render_decode_int(slice, mem.bytes, structure.byte_order)?;
+                let field_name = mem.name.as_str();
+
+                accessors.extend(quote! {
+                    #[inline(always)]
+                    pub fn #mem_name(&self) -> Result<#sty, Error> {
+                        match crate::bcd_decode(u64::from(#value)) {
+                            Some(value) => Ok(value as #sty),
+                            None => Err(Error::InvalidBcd { field : #field_name }),
+                        }
+                    }
+                });
+            }
+            StructMember::OffsetMember(mem) => {
+                let sty = (mem.bytes * BITS_PER_BYTE).to_ty()?;
+                let value = render_decode_int(slice, mem.bytes, structure.byte_order)?;
+                let max = hex(mem.max());
+
+                accessors.extend(quote! {
+                    #[inline(always)]
+                    pub fn #mem_name(&self) -> #sty {
+                        (#value) & #max
+                    }
+                });
+            }
+            // write only members have no getter
+            StructMember::BitfieldMember(_)
+            | StructMember::PrimitiveMember(_)
+            | StructMember::ConstantMember(_) => {}
+            StructMember::AlternativesMember(_)
+            | StructMember::OptionalMember(_)
+            | StructMember::NibbleVarintMember(_)
+            | StructMember::ExtendedMember(_)
+            | StructMember::VarintMember(_) => {
+                bail!("member `{}` has no layout known from its bytes", mem.name())
+            }
+        }
+    }
+
+    Ok(accessors)
+}
+
+/// Render a statement encoding the integer `value` as `bytes` bytes in byte order `order` at
+/// the start of the slice expression `slice`, where `value` is a byte array for more than 8
+/// bytes.
+fn render_encode_int(
+    slice: TokenStream,
+    value: TokenStream,
+    bytes: u32,
+    order: ByteOrder,
+) -> Result<TokenStream> {
+    if bytes > 8 {
+        let bytes = unsuffixed(bytes as u64);
+        return Ok(quote! {
+            #slice[..#bytes].copy_from_slice(&#value);
+        });
+    }
+
+    let width = (bytes * BITS_PER_BYTE).to_ty_width()? / BITS_PER_BYTE;
+    let range = order.range(bytes, width);
+    let to_bytes = order.to_bytes();
+    let bytes = unsuffixed(bytes as u64);
+    Ok(quote! {
+        #slice[..#bytes].copy_from_slice(&(#value).#to_bytes()[#range]);
+    })
+}
+
+/// Render the value written to a length field of type `lty` counting `extra` bytes besides
+/// the `v` set, which fits as the capacity of `v` was checked against the length field.
+fn render_len_extra(extra: u32, lty: &Ident) -> TokenStream {
+    if extra == 0 {
+        quote! { v.len() as #lty }
+    } else {
+        let extra = unsuffixed(extra as u64);
+        quote! { (v.len() + #extra) as #lty }
+    }
+}
+
+/// Render a statement replacing the `old` bytes at `offset` by the `new` bytes, moving the
+/// remainder of the structure accordingly.
+fn render_resize(old: &TokenStream, new: &TokenStream) -> TokenStream {
+    quote! {
+        let end = self.wire_len();
+        if self.buf.len() < end - #old + #new {
+            return Err(Error::WriteZero);
+        }
+        self.buf.copy_within(offset + #old..end, offset + #new);
+    }
+}
+
+/// Render the setters of a mutable view, which encode each member in place.
+fn render_setters(structure: &Structure, naming: &Naming) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let members = naming.names_of(&structure.name);
+    let this = quote! { self };
+    let mut setters = TokenStream::new();
+    let mut checksums = TokenStream::new();
+
+    for mem in &structure.members {
+        let mem_name = util::ident(&mem.name().to_sanitized_snake_case(&members), span);
+        let set_name = util::ident(&format!("set_{}", mem_name), span);
+        let offset_fn = offset_ident(mem.name(), &members);
+        let slice = quote! { self.buf[offset..] };
+
+        match mem {
+            StructMember::BitfieldMember(mem) if mem.access.can_write() => {
+                let pkg_name = structure.bitfield_path(&mem.bitfield, false, naming);
+                let modify_name = util::ident(&format!("modify_{}", mem_name), span);
+                let decode = render_decode_int(
+                    quote! { self.buf[offset..] },
+                    mem.bytes,
+                    structure.byte_order,
+                )?;
+                let encode =
+                    render_encode_int(slice, quote! { bits }, mem.bytes, structure.byte_order)?;
+
+                setters.extend(quote! {
+                    #[inline(always)]
+                    pub fn #modify_name<F>(&mut self, f : F) where for <'w> F : FnOnce(&'w mut #pkg_name::W) -> &'w mut #pkg_name::W {
+                        let offset = self.#offset_fn();
+                        let bits = **f(&mut #pkg_name::W::new(#decode));
+                        #encode
+                    }
+                });
+            }
+            StructMember::PrimitiveMember(mem) if mem.access.can_write() => {
+                let sty = (mem.bytes * BITS_PER_BYTE).to_ty()?;
+                let encode =
+                    render_encode_int(slice, quote! { v }, mem.bytes, structure.byte_order)?;
+
+                setters.extend(quote! {
+                    #[inline(always)]
+                    pub fn #set_name(&mut self, v : #sty) {
+                        let offset = self.#offset_fn();
+                        #encode
+                    }
+                });
+            }
+            StructMember::ArrayMember(mem) => {
+                let sty = (mem.bytes * BITS_PER_BYTE).to_ty()?;
+                let count = unsuffixed(mem.count as u64);
+                let size = unsuffixed(mem.bytes as u64);
+                let encode = render_encode_int(
+                    quote! { chunk },
+                    quote! { *element },
+                    mem.bytes,
+                    structure.byte_order,
+                )?;
+
+                setters.extend(quote! {
+                    #[inline(always)]
+                    pub fn #set_name(&mut self, v : [#sty; #count]) {
+                        let offset = self.#offset_fn();
+                        let chunks = self.buf[offset..].chunks_exact_mut(#size);
+                        for (element, chunk) in v.iter().zip(chunks) {
+                            #encode
+                        }
+                    }
+                });
+            }
+            StructMember::BytesMember(bytes) => {
+                let len = match structure.find_len_field(&bytes.len_from, structure.members.len()) {
+                    Some(len) => len,
+                    None => bail!("unknown length field `{}`", bytes.len_from),
+                };
+                let lty = (len.bytes * BITS_PER_BYTE).to_ty()?;
+                let len_offset_fn = offset_ident(&len.name, &members);
+                let size = render_size(mem, &this, &members)?;
+                let resize = render_resize(&size, &quote! { v.len() });
+                let len_value = render_len_extra(len.extra, &lty);
+                let encode_len = render_encode_int(
+                    quote! { self.buf[len_offset..] },
+                    len_value,
+                    len.bytes,
+                    structure.byte_order,
+                )?;
+                let doc = format!(
+                    "Set `{}` in place, moving the following members and updating `{}`.",
+                    bytes.name, bytes.len_from
+                );
+
+                let fields_mod_name = structure::fields_mod_ident(structure, naming);
+                let ty_name = util::ident(&bytes.name.to_sanitized_pascal_case(&members), span);
+                setters.extend(quote! {
+                    #[doc = #doc]
+                    pub fn #set_name(&mut self, v : &[u8]) -> Result<(), Error> {
+                        #fields_mod_name::#ty_name::check(v)?;
+                        let offset = self.#offset_fn();
+                        #resize
+                        self.buf[offset..offset + v.len()].copy_from_slice(v);
+                        let len_offset = self.#len_offset_fn();
+                        #encode_len
+                        Ok(())
+                    }
+                });
+            }
+            StructMember::StringMember(StringMember {
+                name,
+                kind: StringKind::Fixed,
+                capacity,
+            }) => {
+                let capacity = unsuffixed(*capacity as u64);
+                let doc = format!("Set `{}`, which must not hold a NUL character.", name);
+
+                setters.extend(quote! {
+                    #[doc = #doc]
+                    pub fn #set_name(&mut self, v : &str) {
+                        assert!(v.len() <= #capacity && !v.contains('\0'));
+                        let offset = self.#offset_fn();
+                        self.buf[offset..offset + v.len()].copy_from_slice(v.as_bytes());
+                        self.buf[offset + v.len()..offset + #capacity].fill(0);
+                    }
+                });
+            }
+            StructMember::StringMember(StringMember {
+                name,
+                kind: StringKind::Prefixed(len_from),
+                capacity,
+            }) => {
+                let len = match structure.find_len_field(len_from, structure.members.len()) {
+                    Some(len) => len,
+                    None => bail!("unknown length field `{}`", len_from),
+                };
+                let lty = (len.bytes * BITS_PER_BYTE).to_ty()?;
+                let len_offset_fn = offset_ident(&len.name, &members);
+                let size = render_size(mem, &this, &members)?;
+                let resize = render_resize(&size, &quote! { v.len() });
+                let len_value = render_len_extra(len.extra, &lty);
+                let encode_len = render_encode_int(
+                    quote! { self.buf[len_offset..] },
+                    len_value,
+                    len.bytes,
+                    structure.byte_order,
+                )?;
+                let doc = format!(
+                    "Set `{}` in place, moving the following members and updating `{}`.",
+                    name, len_from
+                );
+
+                let field_name = name.as_str();
+                let capacity = unsuffixed(*capacity as u64);
+                setters.extend(quote! {
+                    #[doc = #doc]
+                    pub fn #set_name(&mut self, v : &str) -> Result<(), Error> {
+                        let v = v.as_bytes();
+                        if v.len() > #capacity {
+                            return Err(Error::LengthMismatch { field : #field_name, len : v.len() });
+                        }
+                        let offset = self.#offset_fn();
+                        #resize
+                        self.buf[offset..offset + v.len()].copy_from_slice(v);
+                        let len_offset = self.#len_offset_fn();
+                        #encode_len
+                        Ok(())
+                    }
+                });
+            }
+            StructMember::RepeatedMember(rep) => {
+                let len = match structure.find_len_field(&rep.count_from, structure.members.len()) {
+                    Some(len) => len,
+                    None => bail!("unknown length field `{}`", rep.count_from),
+                };
+                let sty = (rep.bytes * BITS_PER_BYTE).to_ty()?;
+                let lty = (len.bytes * BITS_PER_BYTE).to_ty()?;
+                let bytes = unsuffixed(rep.bytes as u64);
+                let len_offset_fn = offset_ident(&len.name, &members);
+                let size = render_size(mem, &this, &members)?;
+                let resize = render_resize(&size, &quote! { v.len() * #bytes });
+                let encode = render_encode_int(
+                    quote! { chunk },
+                    quote! { *element },
+                    rep.bytes,
+                    structure.byte_order,
+                )?;
+                let len_value = render_len_extra(len.extra, &lty);
+                let encode_len = render_encode_int(
+                    quote! { self.buf[len_offset..] },
+                    len_value,
+                    len.bytes,
+                    structure.byte_order,
+                )?;
+                let doc = format!(
+                    "Set `{}` in place, moving the following members and updating `{}`.",
+                    rep.name, rep.count_from
+                );
+
+                let field_name = rep.name.as_str();
+                let capacity = unsuffixed(rep.capacity as u64);
+                setters.extend(quote! {
+                    #[doc = #doc]
+                    pub fn #set_name(&mut self, v : &[#sty]) -> Result<(), Error> {
+                        if v.len() > #capacity {
+                            return Err(Error::LengthMismatch { field : #field_name, len : v.len() });
+                        }
+                        let offset = self.#offset_fn();
+                        #resize
+                        let chunks = self.buf[offset..offset + v.len() * #bytes].chunks_exact_mut(#bytes);
+                        for (element, chunk) in v.iter().zip(chunks) {
+                            #encode
+                        }
+                        let len_offset = self.#len_offset_fn();
+                        #encode_len
+                        Ok(())
+                    }
+                });
+            }
+            StructMember::ChecksumMember(mem) => {
+                let from_fn = offset_ident(&mem.from, &members);
+                let algorithm = mem.algorithm.ident();
+                let encode = render_encode_int(
+                    slice,
+                    quote! { checksum.finish() },
+                    mem.algorithm.bytes(),
+                    mem.algorithm.byte_order(structure.byte_order),
+                )?;
+
+                let update = quote! {
+                    let mut checksum = crate::#algorithm::new();
+                    let offset = self.#offset_fn();
+                    checksum.update(&self.buf[self.#from_fn()..offset]);
+                };
+                checksums.extend(match &mem.extra {
+                    Some(extra) => {
+                        let key_name =
+                            util::ident(&extra.key.to_sanitized_snake_case(&members), span);
+                        let select = extra.render(quote! { self.#key_name() });
+                        quote! {
+                            if let Some(extra) = #select {
+                                #update
+                                checksum.update(&[extra]);
+                                #encode
+                            }
+                        }
+                    }
+                    None => quote! {
+                        #update
+                        #encode
+                    },
+                });
+            }
+            StructMember::BitsMember(mem) => {
+                let sty = mem.ty()?;
+                let max = hex(mem.max());
+                let encode = render_write_bits(
+                    &slice,
+                    &quote! { v },
+                    mem.offset % BITS_PER_BYTE,
+                    mem.bits,
+                    structure.byte_order,
+                );
+
+                setters.extend(quote! {
+                    #[inline(always)]
+                    pub fn #set_name(&mut self, v : #sty) {
+                        assert!(v <= #max);
+                        let offset = self.#offset_fn();
+                        #encode
+                    }
+                });
+            }
+            StructMember::EuiMember(mem) => {
+                let ty = mem.ty();
+                let bytes = unsuffixed(mem.bytes as u64);
+                let reverse = match structure.byte_order {
+                    ByteOrder::LittleEndian => quote! { octets.reverse(); },
+                    ByteOrder::BigEndian => quote! {},
+                };
+
+                setters.extend(quote! {
+                    #[inline(always)]
+                    pub fn #set_name(&mut self, v : crate::#ty) {
+                        let offset = self.#offset_fn();
+                        let mut octets = v.0;
+                        #reverse
+                        self.buf[offset..offset + #bytes].copy_from_slice(&octets);
+                    }
+                });
+            }
+            StructMember::TimestampMember(mem) => {
+                let raw_ty = (mem.bytes * BITS_PER_BYTE).to_ty()?;
+                let check = mem.render_check(quote! { v });
+                let encode = render_encode_int(
+                    slice,
+                    quote! { (v as #raw_ty) },
+                    mem.bytes,
+                    structure.byte_order,
+                )?;
+
+                setters.extend(quote! {
+                    #[inline(always)]
+                    pub fn #set_name(&mut self, v : u64) {
+                        #check
+                        let offset = self.#offset_fn();
+                        #encode
+                    }
+                });
+            }
+            StructMember::BcdMember(mem) => {
+                let sty = mem.ty()?;
+                let raw_ty = mem.raw_ty()?;
+                let max = unsuffixed(mem.max());
+                let encode = render_encode_int(
+                    slice,
+                    quote! { (crate::bcd_encode(u64::from(v)) as #raw_ty) },
+                    mem.bytes,
+                    structure.byte_order,
+                )?;
+
+                let field_name = mem.name.as_str();
+                setters.extend(quote! {
+                    /// Set the value, or fail if it has more digits than the member.
+                    #[inline(always)]
+                    pub fn #set_name(&mut self, v : #sty) -> Result<(), Error> {
+                        if v > #max {
+                            return Err(Error::OutOfRange { field : #field_name, value : u64::from(v) });
+                        }
+                        let offset = self.#offset_fn();
+                        #encode
+                        Ok(())
+                    }
+                });
+            }
+            StructMember::OffsetMember(mem) => {
+                let sty = (mem.bytes * BITS_PER_BYTE).to_ty()?;
+                let max = hex(mem.max());
+                let marker = hex(mem.marker);
+                let offset_bits = unsuffixed(mem.offset_bits() as u64);
+                let encode = render_encode_int(
+                    slice,
+                    quote! { ((#marker << #offset_bits) | v) },
+                    mem.bytes,
+                    structure.byte_order,
+                )?;
+
+                setters.extend(quote! {
+                    #[inline(always)]
+                    pub fn #set_name(&mut self, v : #sty) {
+                        assert!(v <= #max);
+                        let offset = self.#offset_fn();
+                        #encode
+                    }
+                });
+            }
+            // length fields are kept consistent by the setters of the members they govern and
+            // constants are written when creating the view
+            // read only members have no setter
+            StructMember::BitfieldMember(_)
+            | StructMember::PrimitiveMember(_)
+            | StructMember::LengthMember(_)
+            | StructMember::ConstantMember(_) => {}
+            StructMember::AlternativesMember(_)
+            | StructMember::OptionalMember(_)
+            | StructMember::NibbleVarintMember(_)
+            | StructMember::ExtendedMember(_)
+            | StructMember::VarintMember(_)
+            | StructMember::StringMember(_) => {
+                bail!("member `{}` has no layout known from its bytes", mem.name())
+            }
+        }
+    }
+
+    setters.extend(quote! {
+        /// Recompute all checksums, which should be done after the last modification. Checksums
+        /// whose extra byte is selected by an unknown value are left unchanged.
+        pub fn update_checksums(&mut self) {
+            #checksums
+        }
+    });
+
+    Ok(setters)
+}
+
+/// Render the statements writing all constants of `structure` into `view`.
+fn render_constants(structure: &Structure, naming: &Naming) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let members = naming.names_of(&structure.name);
+    let str_name = util::ident(
+        &structure.name.to_sanitized_pascal_case(&naming.types()),
+        span,
+    );
+    let mut constants = TokenStream::new();
+
+    for mem in &structure.members {
+        if let StructMember::ConstantMember(mem) = mem {
+            let const_name = util::ident(&mem.name.to_sanitized_upper_case(&members), span);
+            let offset_fn = offset_ident(&mem.name, &members);
+
+            let encode = render_encode_int(
+                quote! { view.buf[offset..] },
+                quote! { #str_name::#const_name },
+                mem.bytes,
+                structure.byte_order,
+            )?;
+
+            constants.extend(quote! {
+                let offset = view.#offset_fn();
+                #encode
+            });
+        }
+    }
+
+    Ok(constants)
+}
+
+/// Render a `FooRef<'a>` view over a byte slice, decoding the members of `structure` lazily.
+pub fn render_ref(structure: &Structure, naming: &Naming) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let str_name = util::ident(
+        &structure.name.to_sanitized_pascal_case(&naming.types()),
+        span,
+    );
+    let ref_name = util::ident(&format!("{}Ref", str_name), span);
+    let doc = format!(
+        "Zero-copy view of a serialized [`{}`], decoding members on access.",
+        str_name
+    );
+
+    let offsets = render_offsets(structure, naming)?;
+    let checks = render_checks(structure, true, naming)?;
+    let getters = render_getters(structure, &quote! { 'a }, naming)?;
+    let hex = render_hex(structure, naming);
+
+    Ok(quote! {
+        #[doc = #doc]
+        #[derive(Clone, Copy, Debug)]
+        pub struct #ref_name<'a> {
+            buf : &'a [u8],
+        }
+
+        impl<'a> #ref_name<'a> {
+            /// Create a view over `buf`, checking that it holds a complete structure.
+            pub fn new(buf : &'a [u8]) -> Result<Self, Error> {
+                let view = Self { buf };
+                #checks
+                Ok(view)
+            }
+
+            /// The bytes of the structure.
+            #[inline(always)]
+            pub fn as_bytes(&self) -> &'a [u8] {
+                &self.buf[..self.wire_len()]
+            }
+
+            #hex
+
+            #offsets
+
+            #getters
+        }
+    })
+}
+
+/// Render the hex dump of a view of `structure`, if it has a fixed size.
+fn render_hex(structure: &Structure, naming: &Naming) -> TokenStream {
+    let str_name = util::ident(
+        &structure.name.to_sanitized_pascal_case(&naming.types()),
+        Span::call_site(),
+    );
+    match structure.field_offsets() {
+        Some(_) => quote! {
+            /// Hex dump of the bytes of the structure, annotated with the names of its members.
+            pub fn hex(&self) -> crate::HexDisplay<&[u8]> {
+                crate::HexDisplay::new(self.as_bytes(), #str_name::FIELDS)
+            }
+        },
+        None => quote! {},
+    }
+}
+
+/// Render a `FooMut<'a>` view over a mutable byte slice, encoding the members of `structure`
+/// in place.
+pub fn render_mut(structure: &Structure, naming: &Naming) -> Result<TokenStream> {
+    let span = Span::call_site();
+    let str_name = util::ident(
+        &structure.name.to_sanitized_pascal_case(&naming.types()),
+        span,
+    );
+    let ref_name = util::ident(&format!("{}Ref", str_name), span);
+    let mut_name = util::ident(&format!("{}Mut", str_name), span);
+    let doc = format!(
+        "Mutable view of a serialized [`{}`], encoding members in place.",
+        str_name
+    );
+
+    let offsets = render_offsets(structure, naming)?;
+    let checks = render_checks(structure, false, naming)?;
+    let getters = render_getters(structure, &quote! { '_ }, naming)?;
+    let setters = render_setters(structure, naming)?;
+    let hex = render_hex(structure, naming);
+
+    // empty structures check nothing
+    let checks = if checks.is_empty() {
+        checks
+    } else {
+        quote! {
+            let buf = &*view.buf;
+            #checks
+        }
+    };
+    let constants = render_constants(structure, naming)?;
+
+    Ok(quote! {
+        #[doc = #doc]
+        #[derive(Debug)]
+        pub struct #mut_name<'a> {
+            buf : &'a mut [u8],
+        }
+
+        impl<'a> #mut_name<'a> {
+            /// Create a view over `buf`, checking that it is large enough for the structure
+            /// described by its length fields and writing all constants.
+            pub fn new(buf : &'a mut [u8]) -> Result<Self, Error> {
+                let view = Self { buf };
+                #checks
+                #constants
+                Ok(view)
+            }
+
+            /// A read only view of the structure.
+            #[inline(always)]
+            pub fn as_ref(&self) -> #ref_name<'_> {
+                #ref_name { buf : &*self.buf }
+            }
+
+            /// The bytes of the structure.
+            #[inline(always)]
+            pub fn as_bytes(&self) -> &[u8] {
+                &self.buf[..self.wire_len()]
+            }
+
+            #hex
+
+            #offsets
+
+            #getters
+
+            #setters
+        }
+    })
+}
@@ -0,0 +1,748 @@
+//! Import of Kaitai Struct format descriptions (`.ksy`).
+//!
+//! The subset supported covers the fixed layouts and length-prefixed members of binary
+//! protocols:
+//!
+//! - unsigned and signed integers `u1` to `u8` and `s1` to `s8`, optionally suffixed by their
+//!   byte order, where signed integers are read as unsigned integers of the same size,
+//! - bit members `b1` to `b64`, whose `bit-endian` must match the byte order,
+//! - integers of whole bytes mapped to an `enum`, which become bitfields sharing an
+//!   enumeration,
+//! - magic `contents`,
+//! - byte arrays, `str` and `strz` strings of a fixed `size` or sized by an earlier integer,
+//! - integers repeated a fixed number of times or as often as an earlier integer says,
+//! - user types, which become structures of their own and are inlined into the structures
+//!   using them, prefixing the names of their members with the name of the member,
+//! - types with `params`, which are only inlined where they are used as `name(args...)`, with
+//!   the arguments substituted for the parameters named as `type`, `enum`, `size` or
+//!   `repeat-expr` of their attributes. Unlike in Kaitai Struct, where parameters are values
+//!   known when parsing, arguments are names or integers fixed when generating, so that e.g.
+//!   one TLV type can be used with several enums of tags,
+//! - the types and enums of the descriptions listed in `meta/imports`, see [`import_file`],
+//! - a `-rust-name` of types, attributes and enum values, which is the identifier generated for
+//!   them instead of their `id`, see [`GenTree::rename`](crate::tree::GenTree::rename). The
+//!   name of an attribute of an inlined type is prefixed like its `id`.
+//!
+//! Members whose size is held by an earlier member hold up to [`MAX_CAPACITY`] bytes or
+//! elements, or fewer if the member cannot count as many. Expressions, conditional members,
+//! `instances` and processing are not supported.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use crate::generate::bitfield::{BitField, Enumeration};
+use crate::generate::structure::Structure;
+use crate::import::yaml::{self, Lines, Yaml};
+use crate::import::Import;
+use crate::util::{ByteOrder, BITS_PER_BYTE};
+
+/// Largest number of bytes or elements of members whose size is held by another member.
+pub const MAX_CAPACITY: u32 = 1024;
+
+/// Keys of attributes which the importer does not support.
+const UNSUPPORTED_KEYS: &[&str] = &[
+    "if",
+    "process",
+    "size-eos",
+    "terminator",
+    "consume",
+    "include",
+    "eos-error",
+    "pad-right",
+    "repeat-until",
+    "pos",
+    "io",
+    "value",
+    "parent",
+];
+
+/// Import the Kaitai Struct description `src`, whose top-level type becomes a structure named
+/// after its `meta/id`. Descriptions with `meta/imports` are imported with [`import_file`].
+pub fn import(src: &str) -> Result<Import> {
+    let (root, lines) = yaml::parse_with_lines(src)?;
+    if !imports(&root)?.is_empty() {
+        bail!("`meta/imports` can only be resolved when importing a file");
+    }
+    import_docs(&[(root, lines)])
+}
+
+/// Import the Kaitai Struct description at `path` along with the descriptions it imports with
+/// `meta/imports`, which are found relative to it and share its namespace: their types, named
+/// after their `meta/id`, their nested types and their enums are used like its own, and become
+/// structures and enumerations as well.
+pub fn import_file(path: &Path) -> Result<Import> {
+    let mut docs = vec![];
+    load(path, &mut vec![], &mut docs)?;
+    import_docs(&docs)
+}
+
+/// Parse the description at `path` into `docs` after the descriptions it imports, unless one
+/// of `loaded` is the same file.
+fn load(path: &Path, loaded: &mut Vec<PathBuf>, docs: &mut Vec<(Yaml, Lines)>) -> Result<()> {
+    let file = path
+        .canonicalize()
+        .with_context(|| format!("Error reading {}", path.display()))?;
+    if loaded.contains(&file) {
+        return Ok(());
+    }
+    loaded.push(file);
+
+    let src =
+        fs::read_to_string(path).with_context(|| format!("Error reading {}", path.display()))?;
+    let (root, lines) =
+        yaml::parse_with_lines(&src).with_context(|| format!("in {}", path.display()))?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    for name in imports(&root)? {
+        if name.starts_with('/') {
+            bail!("absolute import `{}` is not supported", name);
+        }
+        load(&dir.join(format!("{}.ksy", name)), loaded, docs)
+            .with_context(|| format!("in the import `{}` of {}", name, path.display()))?;
+    }
+    docs.push((root, lines));
+    Ok(())
+}
+
+/// The names of the descriptions imported by `root`.
+fn imports(root: &Yaml) -> Result<Vec<&str>> {
+    let imports = match root.get("meta").and_then(|meta| meta.get("imports")) {
+        None | Some(Yaml::Null) => return Ok(vec![]),
+        Some(imports) => imports
+            .as_seq()
+            .context("`meta/imports` must be a sequence")?,
+    };
+    imports
+        .iter()
+        .map(|name| name.as_str().context("imports must be names"))
+        .collect()
+}
+
+/// Import the last of `docs`, which may use the types and enums of the others.
+fn import_docs(docs: &[(Yaml, Lines)]) -> Result<Import> {
+    let (root, lines) = docs.last().context("no description")?;
+    let (name, desc) = id(root)?;
+    let mut importer = Importer {
+        types: HashMap::new(),
+        enums: HashMap::new(),
+        enumerations: HashMap::new(),
+        lines,
+        import: Import::new(name, desc),
+    };
+
+    // the lines are only known of the definitions of the last description
+    let main = docs.len() - 1;
+    let mut structures = vec![];
+    for (idx, (doc, _)) in docs.iter().enumerate() {
+        let path = (idx == main).then(String::new);
+        let endian = Endian::of(doc, &Endian::default())?;
+        importer.collect(doc, &endian, path.as_deref())?;
+
+        let mut names = vec![];
+        collect_type_names(doc, &mut names);
+        structures.extend(names.into_iter().rev());
+        let (name, _) = id(doc)?;
+        if idx != main {
+            importer.insert_type(name, doc, endian, None)?;
+            structures.push(String::from(name));
+        }
+    }
+
+    for ty in &structures {
+        let (node, endian, path) = importer.types[ty.as_str()].clone();
+        // parameterized types are only inlined, as their layout depends on their arguments
+        if node.get("params").is_some() {
+            continue;
+        }
+        let structure = importer.structure(ty, node, &endian, path.as_deref())?;
+        importer.import.structures.push(structure);
+    }
+    let endian = Endian::of(root, &Endian::default())?;
+    let structure = importer.structure(name, root, &endian, Some(""))?;
+    importer.import.structures.push(structure);
+
+    let mut enums = importer.enumerations.into_values().collect::<Vec<_>>();
+    enums.sort_by(|a, b| a.name.cmp(&b.name));
+    importer.import.enumerations = enums;
+    Ok(importer.import)
+}
+
+/// The `meta/id` of the description `root` and its title.
+fn id(root: &Yaml) -> Result<(&str, &str)> {
+    let meta = root.get("meta").context("missing `meta`")?;
+    let name = meta
+        .get("id")
+        .and_then(Yaml::as_str)
+        .context("missing `meta/id`")?;
+    let desc = meta
+        .get("title")
+        .or_else(|| root.get("doc"))
+        .and_then(Yaml::as_str)
+        .map_or("", str::trim);
+    Ok((name, desc))
+}
+
+/// The name of the type or enum `path`, ignoring the path of the type defining it.
+fn type_name(path: &str) -> &str {
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+/// The type and arguments of the use `ty` of a type, e.g. `tlv` and `[tag, 2]` for
+/// `tlv(tag, 2)`.
+fn call(ty: &str) -> Result<(&str, Vec<&str>)> {
+    let (name, args) = match ty.strip_suffix(')').and_then(|ty| ty.split_once('(')) {
+        Some((name, args)) => (name.trim(), args),
+        None => return Ok((ty, vec![])),
+    };
+    let args = args.split(',').map(str::trim).collect::<Vec<_>>();
+    if args
+        .iter()
+        .any(|arg| arg.is_empty() || arg.contains(['(', ')']))
+    {
+        bail!("arguments of `{}` must be names or integers", ty);
+    }
+    Ok((name, args))
+}
+
+/// The parameterized type `node` named `name` with `args` substituted for its `params` where
+/// the attributes of its `seq` name them as `type`, `enum`, `size` or `repeat-expr`, or as
+/// arguments of a type they use.
+fn instantiate(node: &Yaml, name: &str, args: &[&str]) -> Result<Yaml> {
+    let params = match node.get("params") {
+        None | Some(Yaml::Null) => &[],
+        Some(params) => params.as_seq().context("`params` must be a sequence")?,
+    };
+    if params.len() != args.len() {
+        bail!(
+            "type `{}` takes {} arguments but is given {}",
+            name,
+            params.len(),
+            args.len()
+        );
+    }
+    let mut values = HashMap::new();
+    for (param, arg) in params.iter().zip(args) {
+        let id = param
+            .get("id")
+            .and_then(Yaml::as_str)
+            .with_context(|| format!("parameter of `{}` without `id`", name))?;
+        values.insert(id, *arg);
+    }
+    let substitute = |value: &str| String::from(*values.get(value).unwrap_or(&value));
+
+    let mut node = node.clone();
+    if let Some(Yaml::Seq(attrs)) = node.get_mut("seq") {
+        for attr in attrs {
+            for key in &["type", "enum", "size", "repeat-expr"] {
+                let value = match attr.get_mut(key) {
+                    Some(Yaml::Scalar(value)) => value,
+                    _ => continue,
+                };
+                let (ty, args) = call(value)?;
+                *value = if args.is_empty() {
+                    substitute(ty)
+                } else {
+                    let args = args.into_iter().map(substitute).collect::<Vec<_>>();
+                    format!("{}({})", ty, args.join(", "))
+                };
+            }
+        }
+    }
+    Ok(node)
+}
+
+/// The names of the types nested in `node`, outermost first.
+fn collect_type_names(node: &Yaml, names: &mut Vec<String>) {
+    for (name, ty) in node.get("types").and_then(Yaml::as_map).unwrap_or(&[]) {
+        names.push(name.clone());
+        collect_type_names(ty, names);
+    }
+}
+
+/// The byte order of a type and the order of the bits of its bit members.
+#[derive(Clone, Default)]
+struct Endian {
+    bytes: Option<ByteOrder>,
+    bits: Option<ByteOrder>,
+}
+
+impl Endian {
+    /// The byte and bit order of the type `node`, inheriting those not given from `parent`.
+    fn of(node: &Yaml, parent: &Endian) -> Result<Self> {
+        let meta = node.get("meta");
+        let order = |key| match meta.and_then(|meta| meta.get(key)) {
+            None => Ok(None),
+            Some(value) => match value.as_str() {
+                Some("le") => Ok(Some(ByteOrder::LittleEndian)),
+                Some("be") => Ok(Some(ByteOrder::BigEndian)),
+                _ => bail!("unsupported `{}` {:?}", key, value),
+            },
+        };
+        Ok(Endian {
+            bytes: order("endian")?.or(parent.bytes),
+            bits: order("bit-endian")?.or(parent.bits),
+        })
+    }
+
+    /// The byte order of structures, which is the order of their bit members if they only
+    /// hold single bytes, or big endian.
+    fn byte_order(&self) -> ByteOrder {
+        self.bytes.or(self.bits).unwrap_or(ByteOrder::BigEndian)
+    }
+}
+
+struct Importer<'a> {
+    /// Types by name with their byte order and path in the imported description, if they are
+    /// defined by it rather than one it imports.
+    types: HashMap<String, (&'a Yaml, Endian, Option<String>)>,
+    /// Enums by name with their path in the imported description.
+    enums: HashMap<String, (&'a Yaml, Option<String>)>,
+    /// Enumerations of the enums used.
+    enumerations: HashMap<String, Enumeration>,
+    /// Lines of the imported description.
+    lines: &'a Lines,
+    import: Import,
+}
+
+impl<'a> Importer<'a> {
+    /// Collect the types and enums defined in `node` at `path` and the types nested in it.
+    fn collect(&mut self, node: &'a Yaml, endian: &Endian, path: Option<&str>) -> Result<()> {
+        for (name, values) in node.get("enums").and_then(Yaml::as_map).unwrap_or(&[]) {
+            let path = path.map(|path| format!("{}enums/{}", path, name));
+            if self.enums.insert(name.clone(), (values, path)).is_some() {
+                bail!("enum `{}` is defined more than once", name);
+            }
+        }
+        for (name, ty) in node.get("types").and_then(Yaml::as_map).unwrap_or(&[]) {
+            let endian = Endian::of(ty, endian)?;
+            let path = path.map(|path| format!("{}types/{}/", path, name));
+            self.collect(ty, &endian, path.as_deref())?;
+            self.insert_type(name, ty, endian, path)?;
+        }
+        Ok(())
+    }
+
+    fn insert_type(
+        &mut self,
+        name: &str,
+        node: &'a Yaml,
+        endian: Endian,
+        path: Option<String>,
+    ) -> Result<()> {
+        if self
+            .types
+            .insert(String::from(name), (node, endian, path))
+            .is_some()
+        {
+            bail!("type `{}` is defined more than once", name);
+        }
+        Ok(())
+    }
+
+    /// Convert the type `node` at `path` into the structure `name`.
+    fn structure(
+        &mut self,
+        name: &str,
+        node: &'a Yaml,
+        endian: &Endian,
+        path: Option<&str>,
+    ) -> Result<Structure> {
+        let mut structure = Structure::new(name).with_byte_order(endian.byte_order());
+        if let Some(doc) = node.get("doc").and_then(Yaml::as_str) {
+            structure = structure.with_desc(doc.trim());
+        }
+        if let Some(ident) = rust_name(node).with_context(|| format!("in type `{}`", name))? {
+            self.import
+                .renames
+                .push((String::from(name), String::from(ident)));
+        }
+        let line = path.and_then(|path| {
+            self.lines
+                .get(if path.is_empty() { "meta/id" } else { path })
+        });
+        self.import.locate(name, line);
+        self.seq(structure, node, path, endian, "", &mut vec![])
+            .with_context(|| format!("in type `{}`", name))
+    }
+
+    /// Add the attributes of the `seq` of the type `node` at `path` to `structure`, prefixing
+    /// their names with `prefix`. `inlined` holds the types being inlined, which must not refer
+    /// to each other.
+    fn seq(
+        &mut self,
+        mut structure: Structure,
+        node: &Yaml,
+        path: Option<&str>,
+        endian: &Endian,
+        prefix: &str,
+        inlined: &mut Vec<String>,
+    ) -> Result<Structure> {
+        let attrs = match node.get("seq") {
+            None | Some(Yaml::Null) => return Ok(structure),
+            Some(seq) => seq.as_seq().context("`seq` must be a sequence")?,
+        };
+
+        // integers giving the size of other members become their length fields
+        let mut sources = vec![];
+        for attr in attrs {
+            for key in &["size", "repeat-expr"] {
+                if let Some(source) = attr.get(key).and_then(Yaml::as_str) {
+                    if yaml::parse_int(source).is_none() {
+                        sources.push(source);
+                    }
+                }
+            }
+        }
+
+        for (idx, attr) in attrs.iter().enumerate() {
+            let line = path.and_then(|path| self.lines.get(&format!("{}seq/{}", path, idx)));
+            let at = line.map_or(String::new(), |line| format!(" at line {}", line));
+            let id = attr
+                .get("id")
+                .and_then(Yaml::as_str)
+                .with_context(|| format!("attribute without `id`{}", at))?;
+            // bitfields made for the attribute are defined by it
+            let bitfields = self.import.bitfields.len();
+            structure = self
+                .attr(structure, attr, endian, prefix, &sources, inlined)
+                .with_context(|| format!("in attribute `{}`{}", id, at))?;
+            for idx in bitfields..self.import.bitfields.len() {
+                let name = self.import.bitfields[idx].name.clone();
+                self.import.locate(&name, line);
+            }
+        }
+        Ok(structure)
+    }
+
+    fn attr(
+        &mut self,
+        structure: Structure,
+        attr: &Yaml,
+        endian: &Endian,
+        prefix: &str,
+        sources: &[&str],
+        inlined: &mut Vec<String>,
+    ) -> Result<Structure> {
+        for key in UNSUPPORTED_KEYS {
+            if attr.get(key).is_some() {
+                bail!("`{}` is not supported", key);
+            }
+        }
+
+        let id = attr.get("id").and_then(Yaml::as_str).unwrap_or_default();
+        let name = format!("{}{}", prefix, id);
+        if let Some(ident) = rust_name(attr)? {
+            let ident = format!("{}{}", prefix, ident);
+            self.import.renames.push((name.clone(), ident));
+        }
+        let desc = attr.get("doc").and_then(Yaml::as_str).map_or("", str::trim);
+        let ty = attr.get("type").and_then(Yaml::as_str);
+        let repeat = attr.get("repeat").and_then(Yaml::as_str);
+        let order = endian.byte_order();
+
+        if let Some(contents) = attr.get("contents") {
+            return contents_field(structure, &name, contents, order);
+        }
+
+        let size = match attr.get("size") {
+            None => None,
+            Some(size) => Some(match size.as_u64() {
+                Some(size) => Size::Fixed(u32::try_from(size)?),
+                None => Size::From(format!(
+                    "{}{}",
+                    prefix,
+                    size.as_str()
+                        .context("`size` must be an integer or an attribute")?
+                )),
+            }),
+        };
+
+        if let Some(repeat) = repeat {
+            let (bytes, _) = match ty.and_then(int_type) {
+                Some((bytes, signed, suffix)) => {
+                    check_order(suffix, bytes, endian)?;
+                    (bytes, signed)
+                }
+                None => bail!("only integers can be repeated"),
+            };
+            if repeat != "expr" {
+                bail!("`repeat: {}` is not supported", repeat);
+            }
+            let count = attr
+                .get("repeat-expr")
+                .context("`repeat: expr` without `repeat-expr`")?;
+            return Ok(match count.as_u64() {
+                Some(count) => structure.add_array_field(&name, bytes, u32::try_from(count)?),
+                None => {
+                    let count_from = count
+                        .as_str()
+                        .context("`repeat-expr` must be an integer or an attribute")?;
+                    let count_from = format!("{}{}", prefix, count_from);
+                    let capacity = capacity(&structure, &count_from);
+                    structure.add_repeated_field(&name, bytes, &count_from, capacity)
+                }
+            });
+        }
+
+        match (ty, size) {
+            (None, Some(Size::Fixed(size))) => Ok(structure.add_array_field(&name, 1, size)),
+            (None, Some(Size::From(len_from))) => {
+                let capacity = capacity(&structure, &len_from);
+                Ok(structure.add_bytes_field(&name, &len_from, capacity))
+            }
+            (None, None) => bail!("attribute without `type`, `size` or `contents`"),
+            (Some(ty @ "str"), size) | (Some(ty @ "strz"), size) => {
+                if let Some(encoding) = attr.get("encoding").and_then(Yaml::as_str) {
+                    if !["ascii", "utf-8", "utf8"].contains(&encoding.to_lowercase().as_str()) {
+                        bail!("encoding `{}` is not supported", encoding);
+                    }
+                }
+                Ok(match (ty, size) {
+                    (_, Some(Size::Fixed(size))) => structure.add_fixed_string_field(&name, size),
+                    ("str", Some(Size::From(len_from))) => {
+                        let capacity = capacity(&structure, &len_from);
+                        structure.add_string_field(&name, &len_from, capacity)
+                    }
+                    ("strz", None) => structure.add_cstring_field(&name, MAX_CAPACITY),
+                    _ => bail!("`{}` of unsupported size", ty),
+                })
+            }
+            (Some(ty), size) => {
+                let (ty_name, args) = call(ty)?;
+                let ty_name = type_name(ty_name);
+                if let Some(bytes) = int_type(ty) {
+                    if size.is_some() {
+                        bail!("integers cannot have a `size`");
+                    }
+                    self.int_field(structure, attr, &name, desc, bytes, endian, sources)
+                } else if let Some(bits) = bits_type(ty) {
+                    if attr.get("enum").is_some() {
+                        bail!("bit members cannot have an `enum`, only integers of whole bytes");
+                    }
+                    if endian.bits.unwrap_or(ByteOrder::BigEndian) != endian.byte_order() {
+                        bail!("bit members must have the `bit-endian` of the structure");
+                    }
+                    Ok(structure.add_bits_field_desc(&name, desc, bits))
+                } else if let Some((node, ty_endian, path)) = self.types.get(ty_name).cloned() {
+                    if size.is_some() {
+                        bail!("user types cannot have a `size`");
+                    }
+                    if ty_endian.byte_order() != endian.byte_order() {
+                        bail!("type `{}` has another byte order", ty);
+                    }
+                    if inlined.iter().any(|outer| outer == ty_name) {
+                        bail!("type `{}` contains itself", ty_name);
+                    }
+                    let node = instantiate(node, ty_name, &args)?;
+                    inlined.push(String::from(ty_name));
+                    let prefix = format!("{}_", name);
+                    let structure = self.seq(
+                        structure,
+                        &node,
+                        path.as_deref(),
+                        &ty_endian,
+                        &prefix,
+                        inlined,
+                    )?;
+                    inlined.pop();
+                    Ok(structure)
+                } else {
+                    bail!("unsupported type `{}`", ty)
+                }
+            }
+        }
+    }
+
+    /// Add the integer `name` of `bytes` bytes, which is a length field if it is one of
+    /// `sources`, or a bitfield if it has an `enum`.
+    #[allow(clippy::too_many_arguments)]
+    fn int_field(
+        &mut self,
+        structure: Structure,
+        attr: &Yaml,
+        name: &str,
+        desc: &str,
+        (bytes, signed, suffix): (u32, bool, Option<ByteOrder>),
+        endian: &Endian,
+        sources: &[&str],
+    ) -> Result<Structure> {
+        check_order(suffix, bytes, endian)?;
+
+        let id = attr.get("id").and_then(Yaml::as_str).unwrap_or_default();
+        if sources.contains(&id) {
+            if signed {
+                bail!("signed integers cannot hold sizes");
+            }
+            return Ok(structure.add_len_field(name, bytes));
+        }
+
+        let enum_name = match attr.get("enum").and_then(Yaml::as_str) {
+            Some(enum_name) => enum_name,
+            None => return Ok(structure.add_prim_field_desc(name, desc, bytes)),
+        };
+        if signed {
+            bail!("signed integers cannot have an `enum`");
+        }
+
+        let bits = bytes * BITS_PER_BYTE;
+        let enumeration = self.enumeration(enum_name, bits)?;
+        let bitfield = BitField::new(&format!("{}_{}", structure.name, name), desc).add_bit_field(
+            id,
+            desc,
+            bits,
+            |f| f.with_enumeration(&enumeration),
+        );
+        let structure = structure.add_bitfield_of(name, &bitfield);
+        self.import.bitfields.push(bitfield);
+        Ok(structure)
+    }
+
+    /// The enumeration of the enum `name` (ignoring the path of the type defining it) with
+    /// values of `bits` bits.
+    fn enumeration(&mut self, name: &str, bits: u32) -> Result<Enumeration> {
+        let name = type_name(name);
+        if let Some(enumeration) = self.enumerations.get(name) {
+            if enumeration.bitsize != bits {
+                bail!("enum `{}` is used by integers of different sizes", name);
+            }
+            return Ok(enumeration.clone());
+        }
+
+        let (values, path) = self
+            .enums
+            .get(name)
+            .with_context(|| format!("unknown enum `{}`", name))?;
+        let line = path.as_deref().and_then(|path| self.lines.get(path));
+        self.import.locate(name, line);
+        let mut enumeration = Enumeration::new(name, "", bits);
+        for (value, variant) in values.as_map().unwrap_or(&[]) {
+            let value = yaml::parse_int(value)
+                .with_context(|| format!("invalid value `{}` of enum `{}`", value, name))?;
+            let (id, doc) = match variant {
+                Yaml::Scalar(id) => (id.as_str(), ""),
+                _ => (
+                    variant
+                        .get("id")
+                        .and_then(Yaml::as_str)
+                        .with_context(|| format!("value {} of `{}` without `id`", value, name))?,
+                    variant.get("doc").and_then(Yaml::as_str).unwrap_or(""),
+                ),
+            };
+            if let Some(ident) = rust_name(variant)? {
+                self.import
+                    .renames
+                    .push((String::from(id), String::from(ident)));
+            }
+            enumeration = enumeration.add_enum_value_desc(id, doc.trim(), value);
+        }
+        self.enumerations
+            .insert(String::from(name), enumeration.clone());
+        Ok(enumeration)
+    }
+}
+
+/// The identifier given to the type, attribute or enum value `node` by its `-rust-name`.
+fn rust_name(node: &Yaml) -> Result<Option<&str>> {
+    match node.get("-rust-name") {
+        None => Ok(None),
+        Some(ident) => match ident.as_str() {
+            Some(ident) => Ok(Some(ident)),
+            None => bail!("`-rust-name` must be an identifier"),
+        },
+    }
+}
+
+/// The capacity of a member whose size is held by the length field `len_from`, which is the
+/// largest size it can hold up to [`MAX_CAPACITY`].
+fn capacity(structure: &Structure, len_from: &str) -> u32 {
+    structure
+        .find_len_field(len_from, structure.members.len())
+        .map_or(MAX_CAPACITY, |len| len.max().min(MAX_CAPACITY))
+}
+
+/// The size of a byte array or string.
+enum Size {
+    Fixed(u32),
+    /// Held by the member named.
+    From(String),
+}
+
+/// The number of bytes, the signedness and the byte order of the integer type `ty`, e.g.
+/// `u2le`.
+fn int_type(ty: &str) -> Option<(u32, bool, Option<ByteOrder>)> {
+    let (ty, order) = match ty.len() {
+        4 if ty.ends_with("le") => (&ty[..2], Some(ByteOrder::LittleEndian)),
+        4 if ty.ends_with("be") => (&ty[..2], Some(ByteOrder::BigEndian)),
+        _ => (ty, None),
+    };
+    let signed = match ty.get(..1) {
+        Some("u") => false,
+        Some("s") => true,
+        _ => return None,
+    };
+    match &ty[1..] {
+        "1" if order.is_none() => Some((1, signed, None)),
+        "2" | "4" | "8" => Some((ty[1..].parse().ok()?, signed, order)),
+        _ => None,
+    }
+}
+
+/// The number of bits of the bit type `ty`, e.g. `b3`.
+fn bits_type(ty: &str) -> Option<u32> {
+    let bits = ty.strip_prefix('b')?.parse().ok()?;
+    (1..=64).contains(&bits).then_some(bits)
+}
+
+/// Check that an integer of `bytes` bytes with the byte order `suffix` of its type has the
+/// byte order of the structure.
+fn check_order(suffix: Option<ByteOrder>, bytes: u32, endian: &Endian) -> Result<()> {
+    match (suffix, endian.bytes) {
+        (Some(order), _) if order != endian.byte_order() => {
+            bail!("integers of different byte orders are not supported")
+        }
+        (None, None) if bytes > 1 => bail!("missing `meta/endian`"),
+        _ => Ok(()),
+    }
+}
+
+/// Add the constants holding the magic `contents`, each of up to 8 bytes, named `name` or
+/// `name_0`, `name_1` and so on.
+fn contents_field(
+    mut structure: Structure,
+    name: &str,
+    contents: &Yaml,
+    order: ByteOrder,
+) -> Result<Structure> {
+    let mut bytes = vec![];
+    let items = match contents {
+        Yaml::Seq(items) => items.clone(),
+        _ => vec![contents.clone()],
+    };
+    for item in &items {
+        match (item.as_u64(), item.as_str()) {
+            (Some(byte), _) => bytes.push(u8::try_from(byte).context("content exceeds a byte")?),
+            (None, Some(text)) => bytes.extend_from_slice(text.as_bytes()),
+            _ => bail!("`contents` must hold bytes and strings"),
+        }
+    }
+    if bytes.is_empty() {
+        bail!("empty `contents`");
+    }
+
+    let chunks = bytes.chunks(8).collect::<Vec<_>>();
+    for (idx, chunk) in chunks.iter().enumerate() {
+        let value = match order {
+            ByteOrder::BigEndian => chunk.iter().fold(0, |v, b| v << 8 | u64::from(*b)),
+            ByteOrder::LittleEndian => chunk.iter().rev().fold(0, |v, b| v << 8 | u64::from(*b)),
+        };
+        let name = match chunks.len() {
+            1 => String::from(name),
+            _ => format!("{}_{}", name, idx),
+        };
+        structure = structure.add_const_field(&name, chunk.len() as u32, value);
+    }
+    Ok(structure)
+}
@@ -0,0 +1,107 @@
+//! Import of protocols described in other formats into the structures, bitfields and
+//! enumerations generated by the tree.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::generate::bitfield::{BitField, Enumeration};
+use crate::generate::structure::Structure;
+use crate::tree::GenTree;
+
+pub mod ksy;
+pub mod svd;
+pub mod xml;
+pub mod yaml;
+
+/// A protocol read from a description in another format.
+pub struct Import {
+    /// Name of the protocol, which names the generated crate.
+    pub name: String,
+    pub desc: String,
+    pub enumerations: Vec<Enumeration>,
+    pub bitfields: Vec<BitField>,
+    pub structures: Vec<Structure>,
+    /// Line of the description defining each enumeration, bitfield and structure, by name.
+    pub lines: HashMap<String, usize>,
+    /// Identifiers given by the description to its definitions, by name, see
+    /// [`GenTree::rename`].
+    pub renames: Vec<(String, String)>,
+}
+
+/// A definition of an [`Import`].
+#[derive(Clone, Copy)]
+pub enum Definition<'a> {
+    Enumeration(&'a Enumeration),
+    BitField(&'a BitField),
+    Structure(&'a Structure),
+}
+
+impl<'a> Definition<'a> {
+    pub fn name(&self) -> &'a str {
+        match self {
+            Definition::Enumeration(enumeration) => &enumeration.name,
+            Definition::BitField(bitfield) => &bitfield.name,
+            Definition::Structure(structure) => &structure.name,
+        }
+    }
+
+    /// The kind of the definition, as named in messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Definition::Enumeration(_) => "enumeration",
+            Definition::BitField(_) => "bitfield",
+            Definition::Structure(_) => "structure",
+        }
+    }
+
+    /// Add the definition to `tree`.
+    pub fn add_to(&self, tree: &mut GenTree) -> Result<()> {
+        match self {
+            Definition::Enumeration(enumeration) => tree.add_enumeration(enumeration),
+            Definition::BitField(bitfield) => tree.add_bitfield(bitfield),
+            Definition::Structure(structure) => tree.add_struct(structure),
+        }
+    }
+}
+
+impl Import {
+    pub fn new(name: &str, desc: &str) -> Self {
+        Self {
+            name: String::from(name),
+            desc: String::from(desc),
+            enumerations: vec![],
+            bitfields: vec![],
+            structures: vec![],
+            lines: HashMap::new(),
+            renames: vec![],
+        }
+    }
+
+    /// The definitions in the order they are added to a tree: the enumerations, then the
+    /// bitfields which may use them, then the structures which may hold the bitfields.
+    pub fn definitions(&self) -> impl Iterator<Item = Definition<'_>> {
+        let enumerations = self.enumerations.iter().map(Definition::Enumeration);
+        let bitfields = self.bitfields.iter().map(Definition::BitField);
+        let structures = self.structures.iter().map(Definition::Structure);
+        enumerations.chain(bitfields).chain(structures)
+    }
+
+    /// Note that the definition `name` is found at `line` of the description.
+    pub(crate) fn locate(&mut self, name: &str, line: Option<usize>) {
+        if let Some(line) = line {
+            self.lines.insert(String::from(name), line);
+        }
+    }
+
+    /// Add the imported definitions to `tree`, like the `define` function of a protocol.
+    pub fn define(&self, tree: &mut GenTree) -> Result<()> {
+        for (name, ident) in &self.renames {
+            tree.rename(name, ident)?;
+        }
+        for definition in self.definitions() {
+            definition.add_to(tree)?;
+        }
+        Ok(())
+    }
+}
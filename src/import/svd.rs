@@ -0,0 +1,422 @@
+//! Import of the registers of CMSIS-SVD descriptions (`.svd`) as bitfields, e.g. to reuse the
+//! packet configuration registers of a radio as protocol bitfields.
+//!
+//! The root element may be a `device`, a single `peripheral`, a `registers` list or a single
+//! `register`, so fragments of a vendor description can be imported on their own. Every
+//! register becomes a bitfield of its `size`, where:
+//!
+//! - fields are placed by their `bitOffset` and `bitWidth`, `lsb` and `msb` or `bitRange`, and
+//!   the bits not covered by a field are reserved,
+//! - the `resetValue` of the register sets the defaults of the fields, and the reserved bits
+//!   are written with their reset value if it is not zero,
+//! - `read-only` and `write-only` fields only get readers or writers,
+//! - enumerated values become the values of their field, other fields of more than one bit
+//!   are numeric,
+//! - `size`, `resetValue` and `access` are inherited from the cluster, peripheral and device.
+//!
+//! Registers of a device with more than one peripheral are prefixed with the name of their
+//! peripheral, and registers of clusters with the name of their cluster. Arrays of registers
+//! and clusters share a single bitfield named without their index, while arrays of fields are
+//! expanded. Peripherals derived from another one are skipped, as their registers are those of
+//! the other one. Other kinds of `derivedFrom` are not supported, and default enumerated values
+//! are ignored.
+
+use anyhow::{bail, Context, Result};
+
+use crate::generate::bitfield::{BitField, ReservedPolicy};
+use crate::import::xml::{self, Element};
+use crate::import::Import;
+use crate::util::{respace, Access, Naming, ToSanitizedSnakeCase};
+
+/// Register properties inherited from the enclosing elements.
+#[derive(Clone, Copy)]
+struct Properties {
+    size: u32,
+    reset: u64,
+    access: Access,
+}
+
+impl Default for Properties {
+    fn default() -> Self {
+        Properties {
+            size: 32,
+            reset: 0,
+            access: Access::ReadWrite,
+        }
+    }
+}
+
+impl Properties {
+    /// The properties of `element`, inheriting those it does not give.
+    fn of(element: &Element, parent: Properties) -> Result<Self> {
+        let size = match element.child_text("size") {
+            Some(size) => parse_number(size).context("invalid `size`")? as u32,
+            None => parent.size,
+        };
+        if size == 0 || size > 64 {
+            bail!("registers of {} bits are not supported", size);
+        }
+        let reset = match element.child_text("resetValue") {
+            Some(reset) => parse_number(reset).context("invalid `resetValue`")?,
+            None => parent.reset,
+        };
+        let access = match element.child_text("access") {
+            Some(access) => parse_access(access)?,
+            None => parent.access,
+        };
+        Ok(Properties {
+            size,
+            reset,
+            access,
+        })
+    }
+}
+
+/// Import the SVD description `src`, which is named after its root element.
+pub fn import(src: &str) -> Result<Import> {
+    let root = xml::parse(src)?;
+    let name = root
+        .child_text("name")
+        .with_context(|| format!("missing `name` of `{}`", root.name))?;
+    let desc = root
+        .child_text("description")
+        .map(respace)
+        .unwrap_or_default();
+    let mut import = Import::new(
+        &name.to_sanitized_snake_case(&Naming::default().types()),
+        &desc,
+    );
+
+    match root.name.as_str() {
+        "device" => {
+            let props = Properties::of(&root, Properties::default())?;
+            let peripherals = root
+                .child("peripherals")
+                .map(|peripherals| {
+                    peripherals
+                        .children("peripheral")
+                        .filter(|peripheral| peripheral.attr("derivedFrom").is_none())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            for peripheral in &peripherals {
+                let prefix = match peripherals.len() {
+                    1 => String::new(),
+                    _ => prefix("", peripheral)?,
+                };
+                peripheral_registers(&mut import, peripheral, &prefix, props)?;
+            }
+        }
+        "peripheral" => {
+            peripheral_registers(&mut import, &root, "", Properties::default())?;
+        }
+        "registers" => registers(&mut import, &root, "", Properties::default())?,
+        "register" => register(&mut import, &root, "", Properties::default())?,
+        other => bail!(
+            "expected a `device`, `peripheral`, `registers` or `register` element instead of `{}`",
+            other
+        ),
+    }
+
+    if import.bitfields.is_empty() {
+        bail!("`{}` holds no registers", name);
+    }
+    Ok(import)
+}
+
+/// The prefix of the registers in `element` within an element prefixing them with `parent`.
+fn prefix(parent: &str, element: &Element) -> Result<String> {
+    Ok(format!("{}{}_", parent, element_name(element)?))
+}
+
+/// The name of `element`, without the index placeholder of arrays.
+fn element_name(element: &Element) -> Result<String> {
+    let name = element
+        .child_text("name")
+        .with_context(|| format!("`{}` without `name`", element.name))?;
+    Ok(name.replace("[%s]", "").replace("%s", ""))
+}
+
+fn peripheral_registers(
+    import: &mut Import,
+    peripheral: &Element,
+    prefix: &str,
+    parent: Properties,
+) -> Result<()> {
+    let props = Properties::of(peripheral, parent)?;
+    match peripheral.child("registers") {
+        Some(list) => registers(import, list, prefix, props),
+        None => Ok(()),
+    }
+}
+
+/// Import the registers and clusters of `list`.
+fn registers(import: &mut Import, list: &Element, prefix: &str, parent: Properties) -> Result<()> {
+    for element in &list.children {
+        match element.name.as_str() {
+            "register" => register(import, element, prefix, parent)?,
+            "cluster" => {
+                let props = Properties::of(element, parent)?;
+                let prefix = self::prefix(prefix, element)?;
+                registers(import, element, &prefix, props)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Import the register `element` as a bitfield.
+fn register(
+    import: &mut Import,
+    element: &Element,
+    prefix: &str,
+    parent: Properties,
+) -> Result<()> {
+    let name = format!("{}{}", prefix, element_name(element)?);
+    if element.attr("derivedFrom").is_some() {
+        bail!(
+            "register `{}` is derived from another one, which is not supported",
+            name
+        );
+    }
+    let props =
+        Properties::of(element, parent).with_context(|| format!("in register `{}`", name))?;
+    let fields = fields(element, props).with_context(|| format!("in register `{}`", name))?;
+
+    let desc = element
+        .child_text("description")
+        .map(respace)
+        .unwrap_or_default();
+    let mut bitfield = BitField::new(&name, &desc).with_width(props.size);
+    let mut mask = 0u64;
+    let mut end = 0;
+    for field in fields {
+        let field_mask = u64::MAX >> (64 - field.width);
+        mask |= field_mask << field.lsb;
+        end = end.max(field.lsb + field.width);
+        let default = props.reset >> field.lsb & field_mask;
+        bitfield =
+            bitfield.add_bit_field_at(&field.name, &field.desc, field.lsb, field.width, |f| {
+                let mut f = f.with_default(default).with_access(field.access);
+                for (value_name, value_desc, value) in &field.values {
+                    f = f.add_enum_value_desc(value_name, value_desc, *value);
+                }
+                if field.values.is_empty() && field.width > 1 {
+                    f = f.numeric();
+                }
+                f
+            });
+    }
+    if end < props.size {
+        bitfield = bitfield.add_reserved(props.size - end);
+    }
+
+    let size_mask = u64::MAX >> (64 - props.size);
+    let reserved_reset = props.reset & !mask & size_mask;
+    if reserved_reset != 0 {
+        bitfield = bitfield.with_reserved(ReservedPolicy::Value(reserved_reset));
+    }
+
+    import.locate(&name, Some(element.line));
+    import.bitfields.push(bitfield);
+    Ok(())
+}
+
+/// A field of a register.
+struct Field {
+    name: String,
+    desc: String,
+    lsb: u32,
+    width: u32,
+    access: Access,
+    values: Vec<(String, String, u64)>,
+}
+
+/// The fields of the register `element`, with arrays of fields expanded, ordered by their
+/// least significant bit.
+fn fields(element: &Element, props: Properties) -> Result<Vec<Field>> {
+    let mut fields = vec![];
+    for field in element
+        .child("fields")
+        .iter()
+        .flat_map(|list| list.children("field"))
+    {
+        let name = field.child_text("name").context("field without `name`")?;
+        let (lsb, width) = bit_range(field).with_context(|| format!("in field `{}`", name))?;
+        let access = match field.child_text("access") {
+            Some(access) => parse_access(access)?,
+            None => props.access,
+        };
+        let desc = field
+            .child_text("description")
+            .map(respace)
+            .unwrap_or_default();
+        let values = enumerated_values(field).with_context(|| format!("in field `{}`", name))?;
+
+        let dims = dims(field).with_context(|| format!("in field `{}`", name))?;
+        for (idx, (index, increment)) in dims.iter().enumerate() {
+            let lsb = lsb + idx as u32 * increment;
+            if lsb + width > props.size {
+                bail!(
+                    "field `{}` at bits {}..{} exceeds the {} bits of the register",
+                    name.replace("%s", index),
+                    lsb,
+                    lsb + width,
+                    props.size
+                );
+            }
+            fields.push(Field {
+                name: name.replace("%s", index),
+                desc: desc.clone(),
+                lsb,
+                width,
+                access,
+                values: values.clone(),
+            });
+        }
+    }
+    fields.sort_by_key(|field| field.lsb);
+    Ok(fields)
+}
+
+/// The least significant bit and the number of bits of `field`.
+fn bit_range(field: &Element) -> Result<(u32, u32)> {
+    let number = |name| -> Result<Option<u32>> {
+        match field.child_text(name) {
+            Some(text) => Ok(Some(
+                parse_number(text).with_context(|| format!("invalid `{}`", name))? as u32,
+            )),
+            None => Ok(None),
+        }
+    };
+
+    let (lsb, msb) = if let Some(offset) = number("bitOffset")? {
+        let width = number("bitWidth")?.unwrap_or(1);
+        (offset, offset + width.max(1) - 1)
+    } else if let (Some(lsb), Some(msb)) = (number("lsb")?, number("msb")?) {
+        (lsb, msb)
+    } else if let Some(range) = field.child_text("bitRange") {
+        let (msb, lsb) = range
+            .strip_prefix('[')
+            .and_then(|range| range.strip_suffix(']'))
+            .and_then(|range| range.split_once(':'))
+            .context("`bitRange` must be written as `[msb:lsb]`")?;
+        let bit = |text: &str| {
+            text.trim()
+                .parse::<u32>()
+                .with_context(|| format!("invalid `bitRange` {}", range))
+        };
+        (bit(lsb)?, bit(msb)?)
+    } else {
+        bail!("missing `bitOffset`, `lsb` and `msb` or `bitRange`");
+    };
+    if msb < lsb || msb >= 64 {
+        bail!("invalid bits {}..={}", lsb, msb);
+    }
+    Ok((lsb, msb - lsb + 1))
+}
+
+/// The index names and the increment of the offset of the elements of an array of fields, or
+/// a single unnamed element for other fields.
+fn dims(field: &Element) -> Result<Vec<(String, u32)>> {
+    let dim = match field.child_text("dim") {
+        Some(dim) => parse_number(dim).context("invalid `dim`")? as usize,
+        None => return Ok(vec![(String::new(), 0)]),
+    };
+    let increment = field
+        .child_text("dimIncrement")
+        .map(parse_number)
+        .transpose()
+        .context("invalid `dimIncrement`")?
+        .context("array without `dimIncrement`")? as u32;
+
+    let indices = match field.child_text("dimIndex") {
+        None => (0..dim).map(|idx| idx.to_string()).collect::<Vec<_>>(),
+        Some(index) => match index.split_once('-') {
+            Some((first, last)) if !index.contains(',') => {
+                let first = parse_number(first).context("invalid `dimIndex`")?;
+                let last = parse_number(last).context("invalid `dimIndex`")?;
+                (first..=last).map(|idx| idx.to_string()).collect()
+            }
+            _ => index
+                .split(',')
+                .map(|idx| String::from(idx.trim()))
+                .collect(),
+        },
+    };
+    if indices.len() != dim {
+        bail!(
+            "`dimIndex` names {} elements instead of {}",
+            indices.len(),
+            dim
+        );
+    }
+    Ok(indices.into_iter().map(|idx| (idx, increment)).collect())
+}
+
+/// The enumerated values of `field` from all its `enumeratedValues`, keeping the first name
+/// of a value given more than once, e.g. for reading and writing.
+fn enumerated_values(field: &Element) -> Result<Vec<(String, String, u64)>> {
+    let mut values: Vec<(String, String, u64)> = vec![];
+    for list in field.children("enumeratedValues") {
+        if list.attr("derivedFrom").is_some() {
+            bail!("enumerated values derived from others are not supported");
+        }
+        for value in list.children("enumeratedValue") {
+            let name = value
+                .child_text("name")
+                .context("enumerated value without `name`")?;
+            let number = match value.child_text("value") {
+                Some(number) => {
+                    parse_number(number).with_context(|| format!("invalid value of `{}`", name))?
+                }
+                // default values cover the values not enumerated otherwise
+                None => continue,
+            };
+            if values.iter().any(|(_, _, other)| *other == number) {
+                continue;
+            }
+            let desc = value
+                .child_text("description")
+                .map(respace)
+                .unwrap_or_default();
+            values.push((String::from(name), desc, number));
+        }
+    }
+    Ok(values)
+}
+
+fn parse_access(access: &str) -> Result<Access> {
+    Ok(match access {
+        "read-only" => Access::ReadOnly,
+        "write-only" | "writeOnce" => Access::WriteOnly,
+        "read-write" | "read-writeOnce" => Access::ReadWrite,
+        _ => bail!("unknown access `{}`", access),
+    })
+}
+
+/// Parse a number written in decimal, in hexadecimal with a `0x` prefix or in binary with a
+/// `#` or `0b` prefix. Binary numbers with don't care bits are not supported.
+fn parse_number(text: &str) -> Result<u64> {
+    let text = text.trim();
+    let (digits, radix) =
+        if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            (hex, 16)
+        } else if let Some(bin) = text
+            .strip_prefix('#')
+            .or_else(|| text.strip_prefix("0b"))
+            .or_else(|| text.strip_prefix("0B"))
+        {
+            if bin.contains(['x', 'X']) {
+                bail!(
+                    "binary number `{}` with don't care bits is not supported",
+                    text
+                );
+            }
+            (bin, 2)
+        } else {
+            (text, 10)
+        };
+    u64::from_str_radix(digits, radix).with_context(|| format!("invalid number `{}`", text))
+}
@@ -0,0 +1,73 @@
+//! XML documents of descriptions like CMSIS-SVD, parsed by `roxmltree` into a tree of elements
+//! holding their attributes and text. Elements and attributes are named without the prefix of
+//! their namespace, and the entities declared by a document type declaration are expanded.
+
+use anyhow::Result;
+use roxmltree::{Document, Node, ParsingOptions};
+
+/// A parsed element, holding the concatenated text of its content besides its children.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Element {
+    pub name: String,
+    pub attrs: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    pub text: String,
+    /// Line of the start tag.
+    pub line: usize,
+}
+
+impl Element {
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(attr, _)| attr == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The first child named `name`.
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// The children named `name`, in document order.
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// The trimmed text of the first child named `name`.
+    pub fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|child| child.text.trim())
+    }
+}
+
+/// Parse the document `src` into its root element.
+pub fn parse(src: &str) -> Result<Element> {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(src, options)?;
+    Ok(element_of(&doc, doc.root_element()))
+}
+
+/// The element of the element node `node` of `doc`, whose text joins its text and CDATA
+/// sections.
+fn element_of(doc: &Document, node: Node) -> Element {
+    let mut element = Element {
+        name: String::from(node.tag_name().name()),
+        attrs: node
+            .attributes()
+            .map(|attr| (String::from(attr.name()), String::from(attr.value())))
+            .collect(),
+        line: doc.text_pos_at(node.range().start).row as usize,
+        ..Element::default()
+    };
+    for child in node.children() {
+        if child.is_element() {
+            element.children.push(element_of(doc, child));
+        } else if let Some(text) = child.text().filter(|_| child.is_text()) {
+            element.text.push_str(text);
+        }
+    }
+    element
+}
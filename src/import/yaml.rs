@@ -0,0 +1,236 @@
+//! YAML documents of format descriptions like Kaitai Struct's, parsed by `yaml-rust` into a
+//! tree of scalars kept as text along with the line of every key and item. Anchors, aliases
+//! and tags are not supported, and only the first document of a stream is read.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Result};
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::{Marker, TScalarStyle};
+
+/// A parsed YAML node. Scalars are kept as text and only interpreted when they are accessed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Yaml {
+    Scalar(String),
+    Seq(Vec<Yaml>),
+    Map(Vec<(String, Yaml)>),
+    Null,
+}
+
+impl Yaml {
+    /// The value of `key` if this is a mapping holding it.
+    pub fn get(&self, key: &str) -> Option<&Yaml> {
+        match self {
+            Yaml::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Yaml> {
+        match self {
+            Yaml::Map(entries) => entries.iter_mut().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Yaml::Scalar(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The scalar as an integer written in decimal, or in hexadecimal, octal or binary with a
+    /// `0x`, `0o` or `0b` prefix, which may be grouped by underscores.
+    pub fn as_u64(&self) -> Option<u64> {
+        parse_int(self.as_str()?)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.as_str()? {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn as_seq(&self) -> Option<&[Yaml]> {
+        match self {
+            Yaml::Seq(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&[(String, Yaml)]> {
+        match self {
+            Yaml::Map(entries) => Some(entries),
+            _ => None,
+        }
+    }
+}
+
+/// Parse an integer in the notations accepted by [`Yaml::as_u64`].
+pub fn parse_int(s: &str) -> Option<u64> {
+    let s = s.replace('_', "");
+    let (digits, radix) = match s.get(..2) {
+        Some("0x") | Some("0X") => (&s[2..], 16),
+        Some("0o") => (&s[2..], 8),
+        Some("0b") => (&s[2..], 2),
+        _ => (&s[..], 10),
+    };
+    u64::from_str_radix(digits, radix).ok()
+}
+
+/// The lines of the keys and items of the collections of a document by their path, e.g.
+/// `types/header/seq/0`.
+#[derive(Default)]
+pub struct Lines(HashMap<String, usize>);
+
+impl Lines {
+    /// The line of the node at `path`, or of the closest node holding it whose line is known.
+    pub fn get(&self, path: &str) -> Option<usize> {
+        let mut path = path.trim_end_matches('/');
+        loop {
+            if let Some(line) = self.0.get(path) {
+                return Some(*line);
+            }
+            path = &path[..path.rfind('/')?];
+        }
+    }
+}
+
+/// Parse the YAML document `src`.
+pub fn parse(src: &str) -> Result<Yaml> {
+    parse_with_lines(src).map(|(node, _)| node)
+}
+
+/// Parse the YAML document `src` along with the lines of its keys and items.
+pub fn parse_with_lines(src: &str) -> Result<(Yaml, Lines)> {
+    let mut builder = Builder::default();
+    Parser::new(src.chars()).load(&mut builder, false)?;
+    if let Some(error) = builder.error {
+        return Err(error);
+    }
+    Ok((builder.root.unwrap_or(Yaml::Null), builder.lines))
+}
+
+/// A collection whose items or entries are being parsed.
+enum Collection {
+    Seq(Vec<Yaml>),
+    /// The entries along with the key of the entry whose value is being parsed.
+    Map(Vec<(String, Yaml)>, Option<String>),
+}
+
+/// Builds the tree of a document from the events of the parser.
+#[derive(Default)]
+struct Builder {
+    stack: Vec<Collection>,
+    /// Keys and indices leading to the innermost collection.
+    path: Vec<String>,
+    lines: Lines,
+    root: Option<Yaml>,
+    /// The first error, after which further events are ignored.
+    error: Option<anyhow::Error>,
+}
+
+impl Builder {
+    /// Start a node at the line `line`, returning the key or index of the collection holding
+    /// it, or `None` if the node is a key.
+    fn start(&mut self, line: usize) -> Result<Option<String>> {
+        let step = match self.stack.last() {
+            Some(Collection::Seq(items)) => items.len().to_string(),
+            Some(Collection::Map(_, None)) => return Ok(None),
+            Some(Collection::Map(_, Some(key))) => return Ok(Some(key.clone())),
+            None => return Ok(Some(String::new())),
+        };
+        self.locate(&step, line);
+        Ok(Some(step))
+    }
+
+    /// Note that the node `step` of the innermost collection is found at `line`.
+    fn locate(&mut self, step: &str, line: usize) {
+        let mut path = self.path.clone();
+        path.push(String::from(step));
+        self.lines.0.insert(path.join("/"), line);
+    }
+
+    /// Add the finished node `value` to the collection holding it.
+    fn finish(&mut self, value: Yaml) {
+        match self.stack.last_mut() {
+            Some(Collection::Seq(items)) => items.push(value),
+            Some(Collection::Map(entries, key)) => {
+                let key = key.take().expect("a value follows its key");
+                entries.push((key, value));
+            }
+            None => self.root = Some(value),
+        }
+    }
+
+    fn event(&mut self, event: Event, mark: Marker) -> Result<()> {
+        let line = mark.line();
+        match event {
+            Event::Alias(_)
+            | Event::Scalar(_, _, 1.., _)
+            | Event::Scalar(_, _, _, Some(_))
+            | Event::SequenceStart(1..)
+            | Event::MappingStart(1..) => {
+                bail!("line {}: anchors, aliases and tags are not supported", line)
+            }
+            Event::Scalar(text, style, _, _) => match self.start(line)? {
+                // keys are kept as text, even if they are plain nulls
+                None => {
+                    if let Some(Collection::Map(entries, key)) = self.stack.last_mut() {
+                        if entries.iter().any(|(k, _)| *k == text) {
+                            bail!("line {}: duplicate key `{}`", line, text);
+                        }
+                        *key = Some(text.clone());
+                    }
+                    self.locate(&text, line);
+                }
+                Some(_) => {
+                    let value = match (style, text.as_str()) {
+                        (TScalarStyle::Plain, "") | (TScalarStyle::Plain, "~") => Yaml::Null,
+                        (TScalarStyle::Plain, "null") => Yaml::Null,
+                        _ => Yaml::Scalar(text),
+                    };
+                    self.finish(value);
+                }
+            },
+            Event::SequenceStart(_) | Event::MappingStart(_) => {
+                let step = self
+                    .start(line)?
+                    .ok_or_else(|| anyhow!("line {}: keys must be scalars", line))?;
+                if !self.stack.is_empty() {
+                    self.path.push(step);
+                }
+                self.stack.push(match event {
+                    Event::SequenceStart(_) => Collection::Seq(vec![]),
+                    _ => Collection::Map(vec![], None),
+                });
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                let value = match self.stack.pop() {
+                    Some(Collection::Seq(items)) => Yaml::Seq(items),
+                    Some(Collection::Map(entries, _)) => Yaml::Map(entries),
+                    None => unreachable!("collections end after they start"),
+                };
+                if !self.stack.is_empty() {
+                    self.path.pop();
+                }
+                self.finish(value);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl MarkedEventReceiver for Builder {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        if self.error.is_none() {
+            if let Err(error) = self.event(event, mark) {
+                self.error = Some(error);
+            }
+        }
+    }
+}
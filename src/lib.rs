@@ -1,3 +1,8 @@
+pub mod check;
+pub mod doc;
 pub mod file;
 pub mod generate;
+pub mod import;
+pub mod protocols;
+pub mod tree;
 pub mod util;
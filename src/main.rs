@@ -0,0 +1,281 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use anyhow::{bail, Context, Result};
+use log::{error, info};
+
+use prot2rust::check::{self, Severity};
+use prot2rust::import::{ksy, svd, Import};
+use prot2rust::protocols::{self, PROTOCOLS};
+use prot2rust::tree::{crate_name, GenTree};
+use prot2rust::util::{Config, Derives, IoBackend, SourceType};
+
+const USAGE: &str = "\
+Usage:
+    prot2rust gen <protocol> [options]
+    prot2rust gen <file.ksy> [options]
+    prot2rust gen <file.svd> [options]
+    prot2rust check <protocol|file> [--strict]
+    prot2rust list
+
+Options of gen:
+    -o, --output <dir>  Directory to write to, defaults to the name of the protocol or file
+    --make-crate        Write a complete crate including its manifest, also `--crate`
+    --make-mod          Write a `mod.rs` instead of the `lib.rs` of a crate
+    --generic-mod       Write the generic support to its own `generic.rs`
+    --const-generic     Select the alternatives of structures by const parameters instead of
+                        type parameters
+    --io <backend>      I/O traits used, one of `core2` (default), `core` or `embedded-io`
+    --rename <name>=<ident>
+                        Generate the identifiers of the definitions named `name` from `ident`,
+                        e.g. `802_15_4_hdr=Ieee802154Hdr`
+    --raw-idents        Keep names which are keywords as raw identifiers, e.g. `r#type`,
+                        instead of appending an underscore
+    --serde             Derive `serde::Serialize` and `serde::Deserialize`, compiled with the
+                        `serde` feature of the generated crate
+    --defmt             Derive `defmt::Format`, compiled with the `defmt` feature of the
+                        generated crate
+    --strict            Treat lints as errors
+    --tests             Emit round trip tests
+    --proptests         Emit property tests
+    --fuzz <dir>        Also write a `cargo fuzz` crate with a target for every structure and
+                        bitfield to the directory, e.g. `<output>/fuzz`, requires `--make-crate`
+    --doc <file>        Also write the documentation of the protocol, as HTML if the file
+                        ends in `.html` and as Markdown otherwise
+
+check reports the problems of a protocol or description without generating any code, and
+fails if any of them is an error.";
+
+/// Options of the `gen` subcommand.
+struct GenOptions {
+    protocol: String,
+    config: Config,
+    derives: Derives,
+    make_crate: bool,
+    tests: bool,
+    proptests: bool,
+    fuzz: Option<PathBuf>,
+    doc: Option<PathBuf>,
+}
+
+fn parse_gen(mut args: impl Iterator<Item = String>) -> Result<GenOptions> {
+    let mut protocol = None;
+    let mut output = None;
+    let mut config = Config::default();
+    let mut derives = Derives::default();
+    let mut make_crate = false;
+    let mut tests = false;
+    let mut proptests = false;
+    let mut fuzz = None;
+    let mut doc = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                let dir = args.next().context("missing directory after `--output`")?;
+                output = Some(PathBuf::from(dir));
+            }
+            "--make-crate" | "--crate" => make_crate = true,
+            "--make-mod" => config.make_mod = true,
+            "--generic-mod" => config.generic_mod = true,
+            "--const-generic" => config.const_generic = true,
+            "--io" => {
+                let io = args.next().context("missing backend after `--io`")?;
+                config.io = IoBackend::parse(&io)?;
+            }
+            "--rename" => {
+                let rename = args.next().context("missing rename after `--rename`")?;
+                let (name, ident) = rename
+                    .split_once('=')
+                    .context("renames must be given as `<name>=<ident>`")?;
+                config.naming.rename(name, ident)?;
+            }
+            "--raw-idents" => config.naming.raw_idents = true,
+            "--serde" => derives = derives.with_serde(true),
+            "--defmt" => derives = derives.with_defmt(true),
+            "--strict" => config.strict = true,
+            "--tests" => tests = true,
+            "--proptests" => proptests = true,
+            "--fuzz" => {
+                let dir = args.next().context("missing directory after `--fuzz`")?;
+                fuzz = Some(PathBuf::from(dir));
+            }
+            "--doc" => {
+                let file = args.next().context("missing file after `--doc`")?;
+                doc = Some(PathBuf::from(file));
+            }
+            _ if arg.starts_with('-') => bail!("unknown option `{}`\n\n{}", arg, USAGE),
+            _ if protocol.is_none() => protocol = Some(arg),
+            _ => bail!("unexpected argument `{}`\n\n{}", arg, USAGE),
+        }
+    }
+
+    let protocol = protocol.with_context(|| format!("missing protocol\n\n{}", USAGE))?;
+    if make_crate && config.make_mod {
+        bail!("`--make-crate` and `--make-mod` cannot be combined");
+    }
+    if fuzz.is_some() && !make_crate {
+        bail!("`--fuzz` requires `--make-crate`, as the fuzz crate depends on the generated one");
+    }
+    // descriptions are written to a directory named after their file
+    let path = Path::new(&protocol);
+    config.output_dir = output.unwrap_or_else(|| match path.extension() {
+        Some(_) => PathBuf::from(path.file_stem().unwrap_or_default()),
+        None => PathBuf::from(&protocol),
+    });
+
+    Ok(GenOptions {
+        protocol,
+        config,
+        derives,
+        make_crate,
+        tests,
+        proptests,
+        fuzz,
+        doc,
+    })
+}
+
+/// Import the description at `path` if its extension is the one of a Kaitai Struct (`.ksy`)
+/// or SVD (`.svd` or `.xml`) description.
+fn import(path: &str) -> Result<Option<Import>> {
+    let ext = Path::new(path).extension().and_then(|ext| ext.to_str());
+    let import = match ext {
+        // Kaitai Struct descriptions may import others relative to their own path
+        Some("ksy") => ksy::import_file(Path::new(path)),
+        Some(ext) if SourceType::from_extension(ext) == Some(SourceType::Xml) => {
+            let src =
+                fs::read_to_string(path).with_context(|| format!("Error reading {}", path))?;
+            svd::import(&src)
+        }
+        _ => return Ok(None),
+    };
+    let import = import.with_context(|| format!("Error importing {}", path))?;
+    Ok(Some(import))
+}
+
+fn gen(options: GenOptions) -> Result<()> {
+    let mut tree = GenTree::from_config(&options.config);
+    tree.set_derives(options.derives.clone());
+    tree.set_tests(options.tests);
+    tree.set_proptests(options.proptests);
+
+    // the fuzz targets reach the types through the generated crate, named after the protocol
+    let fuzz = |tree: &mut GenTree, name: &str| match &options.fuzz {
+        Some(_) => tree.set_fuzz_targets(&crate_name(name)),
+        None => Ok(()),
+    };
+
+    // descriptions in other formats are imported, other names refer to the built-in protocols
+    let (name, desc) = if let Some(import) = import(&options.protocol)? {
+        fuzz(&mut tree, &import.name)?;
+        import
+            .define(&mut tree)
+            .with_context(|| format!("Error defining protocol `{}`", import.name))?;
+        (import.name, import.desc)
+    } else {
+        let protocol = protocols::find(&options.protocol)?;
+        fuzz(&mut tree, protocol.name)?;
+        (protocol.define)(&mut tree)
+            .with_context(|| format!("Error defining protocol `{}`", protocol.name))?;
+        (String::from(protocol.name), String::from(protocol.desc))
+    };
+
+    let dir = &options.config.output_dir;
+    if options.make_crate {
+        let dir = dir
+            .to_str()
+            .context("output directory is not valid UTF-8")?;
+        tree.write_crate(dir, &name, "0.1.0")?;
+    } else {
+        tree.write()?;
+    }
+    info!("Generated `{}` into {}", name, dir.display());
+
+    if let Some(fuzz) = &options.fuzz {
+        let fuzz = fuzz.to_str().context("fuzz directory is not valid UTF-8")?;
+        let dir = dir
+            .to_str()
+            .context("output directory is not valid UTF-8")?;
+        tree.write_fuzz_crate(fuzz, dir, &name)
+            .with_context(|| format!("Error writing the fuzz crate to {}", fuzz))?;
+        info!("Wrote the fuzz targets of `{}` to {}", name, fuzz);
+    }
+
+    if let Some(path) = &options.doc {
+        tree.write_doc(path, &name, &desc)
+            .with_context(|| format!("Error writing {}", path.display()))?;
+        info!("Documented `{}` in {}", options.protocol, path.display());
+    }
+    Ok(())
+}
+
+fn check(args: impl Iterator<Item = String>) -> Result<()> {
+    let mut protocol = None;
+    let mut config = Config::default();
+    for arg in args {
+        match arg.as_str() {
+            "--strict" => config.strict = true,
+            _ if arg.starts_with('-') => bail!("unknown option `{}`\n\n{}", arg, USAGE),
+            _ if protocol.is_none() => protocol = Some(arg),
+            _ => bail!("unexpected argument `{}`\n\n{}", arg, USAGE),
+        }
+    }
+    let protocol = protocol.with_context(|| format!("missing protocol\n\n{}", USAGE))?;
+
+    // a description which cannot be imported is a single error
+    let diagnostics = match import(&protocol) {
+        Ok(Some(import)) => check::check_import(&import, &config),
+        Ok(None) => check::check_protocol(protocols::find(&protocol)?.define, &config),
+        Err(e) => bail!("{:#}", e),
+    };
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic.report(&protocol));
+    }
+
+    let count = |severity| {
+        diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    };
+    let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+    info!(
+        "Checked `{}`: {} error(s), {} warning(s)",
+        protocol, errors, warnings
+    );
+    if errors > 0 {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+fn run() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("gen") => gen(parse_gen(args)?),
+        Some("check") => check(args),
+        Some("list") => {
+            for protocol in PROTOCOLS {
+                println!("{:<16}{}", protocol.name, protocol.desc);
+            }
+            Ok(())
+        }
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Some(command) => bail!("unknown command `{}`\n\n{}", command, USAGE),
+        None => bail!("missing command\n\n{}", USAGE),
+    }
+}
+
+fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    if let Err(e) = run() {
+        error!("{:#}", e);
+        process::exit(1);
+    }
+}
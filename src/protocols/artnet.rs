@@ -0,0 +1,175 @@
+//! Art-Net 4, which carries DMX512 universes of lighting control over UDP port 6454: the header
+//! of a packet with its `Art-Net` id, OpCode and protocol version, along with the packets of
+//! DMX data, non-zero start code data, synchronisation and polling.
+//!
+//! The OpCode is the only little endian field of the header, so its values are byte swapped as
+//! all structures are big endian. Packets of other OpCodes are read as an `ArtNetHeader`
+//! followed by their fields.
+
+use anyhow::Result;
+
+use crate::generate::bitfield::BitField;
+use crate::generate::frame::Frames;
+use crate::generate::structure::Structure;
+use crate::tree::GenTree;
+use crate::util::ByteOrder;
+
+/// The OpCodes of packets.
+const OP_CODES: &[(&str, u16)] = &[
+    ("Op_poll", 0x2000),
+    ("Op_poll_reply", 0x2100),
+    ("Op_diag_data", 0x2300),
+    ("Op_command", 0x2400),
+    ("Op_data_request", 0x2700),
+    ("Op_data_reply", 0x2800),
+    ("Op_dmx", 0x5000),
+    ("Op_nzs", 0x5100),
+    ("Op_sync", 0x5200),
+    ("Op_address", 0x6000),
+    ("Op_input", 0x7000),
+    ("Op_tod_request", 0x8000),
+    ("Op_tod_data", 0x8100),
+    ("Op_tod_control", 0x8200),
+    ("Op_rdm", 0x8300),
+    ("Op_rdm_sub", 0x8400),
+    ("Op_time_code", 0x9700),
+    ("Op_time_sync", 0x9800),
+    ("Op_trigger", 0x9900),
+    ("Op_directory", 0x9a00),
+    ("Op_directory_reply", 0x9b00),
+    ("Op_ip_prog", 0xf800),
+    ("Op_ip_prog_reply", 0xf900),
+];
+
+/// The id starting every packet.
+const ID: &[u8; 8] = b"Art-Net\0";
+
+/// The protocol version.
+const PROT_VER: u64 = 14;
+
+/// Number of channels of a universe.
+const UNIVERSE_CHANNELS: u32 = 512;
+
+/// The OpCode `variant` as read in big endian.
+fn op_code(variant: &str) -> u64 {
+    let (_, value) = OP_CODES
+        .iter()
+        .find(|(v, _)| *v == variant)
+        .expect("unknown OpCode");
+    value.swap_bytes() as u64
+}
+
+/// Start the packet `name` of the OpCode `variant`.
+fn packet(name: &str, desc: &str, variant: &str) -> Structure {
+    Structure::new(name)
+        .with_desc(desc)
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_const_field("id", 8, u64::from_be_bytes(*ID))
+        .add_const_field("op_code", 2, op_code(variant))
+        .add_const_field("prot_ver", 2, PROT_VER)
+}
+
+/// Add the definitions of Art-Net to `tree`.
+pub fn define(tree: &mut GenTree) -> Result<()> {
+    let op_code_bits = BitField::new("op_code", "OpCode of a packet").add_bit_field(
+        "Op_code",
+        "OpCode of the packet, byte swapped",
+        16,
+        |f| {
+            OP_CODES.iter().fold(f, |f, (variant, value)| {
+                f.add_enum_value(variant, value.swap_bytes() as u64)
+            })
+        },
+    );
+    tree.add_bitfield(&op_code_bits)?;
+
+    let port_address = BitField::new("port_address", "Port-Address of a universe")
+        .add_bit_field("Net", "Net of the universe", 7, |f| f.numeric())
+        .add_reserved(1)
+        .add_bit_field("Universe", "Universe within the sub-net", 4, |f| {
+            f.numeric()
+        })
+        .add_bit_field("Sub_net", "Sub-net within the net", 4, |f| f.numeric());
+    tree.add_bitfield(&port_address)?;
+
+    let talk_to_me = BitField::new("talk_to_me", "Behaviour requested of nodes by a poll")
+        .add_reserved(1)
+        .add_bit_field(
+            "Reply_on_change",
+            "Send a poll reply whenever the node changes",
+            1,
+            |f| f,
+        )
+        .add_bit_field("Diagnostics", "Send diagnostics messages", 1, |f| f)
+        .add_bit_field(
+            "Unicast_diagnostics",
+            "Unicast rather than broadcast them",
+            1,
+            |f| f,
+        )
+        .add_bit_field("Disable_vlc", "Disable VLC transmission", 1, |f| f)
+        .add_bit_field(
+            "Targeted",
+            "Only reply within the target Port-Addresses",
+            1,
+            |f| f,
+        )
+        .add_reserved(2);
+    tree.add_bitfield(&talk_to_me)?;
+
+    let header = Structure::new("art_net_header")
+        .with_desc("Header of a packet of any OpCode")
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_const_field("id", 8, u64::from_be_bytes(*ID))
+        .add_bitfield_of("op_code", &op_code_bits)
+        .add_u16_field_desc("prot_ver", "Version of the protocol, 14")
+        .with_default("prot_ver", PROT_VER);
+    tree.add_struct(&header)?;
+
+    let packets = [
+        (
+            "Op_poll",
+            packet("art_poll", "Discovery of the nodes of a network", "Op_poll")
+                .add_bitfield_of("flags", &talk_to_me)
+                .add_u8_field_desc("diag_priority", "Lowest priority of diagnostics to send"),
+        ),
+        (
+            "Op_dmx",
+            packet("art_dmx", "DMX512 data of a universe", "Op_dmx")
+                .add_u8_field_desc("sequence", "Sequence number, or zero if disabled")
+                .add_u8_field_desc("physical", "Physical input port of the data")
+                .add_bitfield_of("port_address", &port_address)
+                .add_len_field("length", 2)
+                .add_bytes_field("data", "length", UNIVERSE_CHANNELS),
+        ),
+        (
+            "Op_nzs",
+            packet(
+                "art_nzs",
+                "DMX512 data of a universe with a non-zero start code",
+                "Op_nzs",
+            )
+            .add_u8_field_desc("sequence", "Sequence number, or zero if disabled")
+            .add_u8_field_desc("start_code", "Start code of the data")
+            .add_bitfield_of("port_address", &port_address)
+            .add_len_field("length", 2)
+            .add_bytes_field("data", "length", UNIVERSE_CHANNELS),
+        ),
+        (
+            "Op_sync",
+            packet("art_sync", "Output of the buffered DMX data", "Op_sync")
+                .add_u8_field("aux1")
+                .add_u8_field("aux2"),
+        ),
+    ];
+
+    let mut frames = Frames::new("art_net_packet", "op_code", 2, "Op_code")
+        .with_desc("Packet of a known OpCode, selected by the OpCode following the id.")
+        .with_byte_order(ByteOrder::BigEndian)
+        .with_offset(8);
+    for (variant, packet) in &packets {
+        tree.add_struct(packet)?;
+        frames = frames.add_frame(variant, packet);
+    }
+    tree.add_frames(&frames)
+}
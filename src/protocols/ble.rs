@@ -0,0 +1,402 @@
+//! Bluetooth LE link layer: the legacy advertising physical channel PDUs and the data physical
+//! channel PDUs with their LL control PDUs, dispatched on their header by `parse` and
+//! `parse_data` respectively.
+//!
+//! Advertiser, scanner, initiator and target addresses are public or random addresses as
+//! selected by the `TxAdd` and `RxAdd` flags of the header. The length of a PDU is kept
+//! consistent with its advertising or LL data, while the length of an LL control PDU counts its
+//! opcode and control data, which is left to the user as `1 + ctr_data.wire_size()`. The AD
+//! structures of advertising and scan response data are read by `AdStructure::iter` and written
+//! by `AdStructureBuilder`. The significant part of the data ends at the first AD structure of
+//! zero length, which the iterator reports as a length mismatch, and AD types of no generated
+//! structure are kept raw. Extended advertising PDUs, encrypted payloads and their MIC as well
+//! as the CRC following every PDU on the air are left to the user.
+
+use anyhow::Result;
+
+use crate::generate::bitfield::BitField;
+use crate::generate::frame::Frames;
+use crate::generate::structure::{Alternatives, Structure};
+use crate::generate::tlv::Tlv;
+use crate::tree::GenTree;
+
+/// Capacity of advertising and scan response data.
+const ADV_DATA_CAPACITY: u32 = 31;
+/// Capacity of the payload of an LL data PDU.
+const DATA_CAPACITY: u32 = 251;
+
+/// The LL control PDUs along with their opcode.
+const CONTROL_PDUS: &[(&str, u64)] = &[
+    ("Connection_update_ind", 0x00),
+    ("Channel_map_ind", 0x01),
+    ("Terminate_ind", 0x02),
+    ("Enc_req", 0x03),
+    ("Enc_rsp", 0x04),
+    ("Start_enc_req", 0x05),
+    ("Start_enc_rsp", 0x06),
+    ("Unknown_rsp", 0x07),
+    ("Feature_req", 0x08),
+    ("Feature_rsp", 0x09),
+    ("Pause_enc_req", 0x0a),
+    ("Pause_enc_rsp", 0x0b),
+    ("Version_ind", 0x0c),
+    ("Reject_ind", 0x0d),
+    ("Peripheral_feature_req", 0x0e),
+    ("Connection_param_req", 0x0f),
+    ("Connection_param_rsp", 0x10),
+    ("Reject_ext_ind", 0x11),
+    ("Ping_req", 0x12),
+    ("Ping_rsp", 0x13),
+    ("Length_req", 0x14),
+    ("Length_rsp", 0x15),
+    ("Phy_req", 0x16),
+    ("Phy_rsp", 0x17),
+    ("Phy_update_ind", 0x18),
+    ("Min_used_channels_ind", 0x19),
+];
+
+/// Start the control data of the LL control PDU `variant`.
+fn ctr_data(variant: &str) -> Structure {
+    Structure::new(&format!("ll_{}", variant.to_lowercase()))
+}
+
+/// Add the fields of the connection parameters request and response to `s`.
+fn connection_params(s: Structure) -> Structure {
+    (0..6).fold(
+        s.add_u16_field("interval_min")
+            .add_u16_field("interval_max")
+            .add_u16_field("latency")
+            .add_u16_field("timeout")
+            .add_u8_field("preferred_periodicity")
+            .add_u16_field("reference_conn_event_count"),
+        |s, i| s.add_u16_field(&format!("offset{}", i)),
+    )
+}
+
+/// Add the fields of the length request and response to `s`.
+fn lengths(s: Structure) -> Structure {
+    s.add_u16_field_desc("max_rx_octets", "Largest payload the sender can receive")
+        .add_u16_field_desc("max_rx_time", "Longest reception in microseconds")
+        .add_u16_field_desc("max_tx_octets", "Largest payload the sender will send")
+        .add_u16_field_desc("max_tx_time", "Longest transmission in microseconds")
+}
+
+/// Add the definitions of the Bluetooth LE link layer to `tree`.
+pub fn define(tree: &mut GenTree) -> Result<()> {
+    let adv_header = BitField::new("adv_header", "Header of an advertising channel PDU")
+        .add_bit_field("Pdu_type", "Type of the PDU", 4, |f| {
+            f.add_enum_value("Adv_ind", 0b0000)
+                .add_enum_value("Adv_direct_ind", 0b0001)
+                .add_enum_value("Adv_nonconn_ind", 0b0010)
+                .add_enum_value("Scan_req", 0b0011)
+                .add_enum_value("Scan_rsp", 0b0100)
+                .add_enum_value("Connect_ind", 0b0101)
+                .add_enum_value("Adv_scan_ind", 0b0110)
+                .add_enum_value("Adv_ext_ind", 0b0111)
+                .add_enum_value("Aux_connect_rsp", 0b1000)
+        })
+        .add_reserved(1)
+        .add_bit_field(
+            "Ch_sel",
+            "The advertiser supports channel selection algorithm #2",
+            1,
+            |f| f,
+        )
+        .add_bit_field("Tx_add", "Type of the address of the sender", 1, |f| {
+            f.add_enum_value("Public", 0).add_enum_value("Random", 1)
+        })
+        .add_bit_field("Rx_add", "Type of the address of the recipient", 1, |f| {
+            f.add_enum_value("Public", 0).add_enum_value("Random", 1)
+        });
+    tree.add_bitfield(&adv_header)?;
+
+    let hop_sca = BitField::new("hop_sca", "Hop increment and sleep clock accuracy")
+        .add_bit_field("Hop", "Hop increment of the channel selection", 5, |f| {
+            f.numeric()
+        })
+        .add_bit_field("Sca", "Sleep clock accuracy of the central", 3, |f| {
+            f.add_enum_value("Ppm_251_500", 0)
+                .add_enum_value("Ppm_151_250", 1)
+                .add_enum_value("Ppm_101_150", 2)
+                .add_enum_value("Ppm_76_100", 3)
+                .add_enum_value("Ppm_51_75", 4)
+                .add_enum_value("Ppm_31_50", 5)
+                .add_enum_value("Ppm_21_30", 6)
+                .add_enum_value("Ppm_0_20", 7)
+        });
+    tree.add_bitfield(&hop_sca)?;
+
+    // device addresses, selected by the address type flags of the header
+    let public_address = Structure::new("public_address")
+        .with_desc("Public device address")
+        .add_eui48_field("addr");
+    let random_address = Structure::new("random_address")
+        .with_desc("Random device address")
+        .add_eui48_field("addr");
+    let addresses = Alternatives::new().insert_new_option("address", &public_address, |o| {
+        o.set_discriminant(&public_address, "Public")
+            .insert_type_discriminant(&random_address, "Random")
+    });
+    tree.add_struct(&public_address)?;
+    tree.add_struct(&random_address)?;
+    tree.add_alternatives(&addresses)?;
+    let address = addresses.get("address")?;
+
+    // undirected advertising, carrying the address of the advertiser and its data
+    let advertising = |name: &str, desc: &str| {
+        Structure::new(name)
+            .with_desc(desc)
+            .add_bitfield_of("header", &adv_header)
+            .add_len_field_extra("length", 1, 6)
+            .add_alt_field_discriminated("adv_a", address, "header", "Tx_add")
+            .add_bytes_field("adv_data", "length", ADV_DATA_CAPACITY)
+    };
+    // PDUs holding the addresses of both the sender and the recipient
+    let addressed = |name: &str, desc: &str, tx: &str, rx: &str, len: u64| {
+        Structure::new(name)
+            .with_desc(desc)
+            .add_bitfield_of("header", &adv_header)
+            .add_const_field("length", 1, len)
+            .add_alt_field_discriminated(tx, address, "header", "Tx_add")
+            .add_alt_field_discriminated(rx, address, "header", "Rx_add")
+    };
+
+    let advertising_pdus = vec![
+        (
+            "Adv_ind",
+            advertising(
+                "adv_ind",
+                "Connectable and scannable undirected advertising",
+            ),
+        ),
+        (
+            "Adv_direct_ind",
+            addressed(
+                "adv_direct_ind",
+                "Connectable directed advertising",
+                "adv_a",
+                "target_a",
+                12,
+            ),
+        ),
+        (
+            "Adv_nonconn_ind",
+            advertising("adv_nonconn_ind", "Non-connectable undirected advertising"),
+        ),
+        (
+            "Scan_req",
+            addressed(
+                "scan_req",
+                "Request for scan response data",
+                "scan_a",
+                "adv_a",
+                12,
+            ),
+        ),
+        (
+            "Scan_rsp",
+            Structure::new("scan_rsp")
+                .with_desc("Scan response data")
+                .add_bitfield_of("header", &adv_header)
+                .add_len_field_extra("length", 1, 6)
+                .add_alt_field_discriminated("adv_a", address, "header", "Tx_add")
+                .add_bytes_field("scan_rsp_data", "length", ADV_DATA_CAPACITY),
+        ),
+        (
+            "Connect_ind",
+            addressed(
+                "connect_ind",
+                "Request to connect to an advertiser",
+                "init_a",
+                "adv_a",
+                34,
+            )
+            .add_u32_field_desc("access_address", "Access address of the connection")
+            .add_u24_field_desc("crc_init", "Initial value of the CRC")
+            .add_u8_field_desc("win_size", "Transmit window size in units of 1.25 ms")
+            .add_u16_field_desc("win_offset", "Transmit window offset in units of 1.25 ms")
+            .add_u16_field_desc("interval", "Connection interval in units of 1.25 ms")
+            .add_u16_field_desc("latency", "Peripheral latency in connection events")
+            .add_u16_field_desc("timeout", "Supervision timeout in units of 10 ms")
+            .add_u40_field_desc("chm", "Map of the used data channels")
+            .add_bitfield_of("hop_sca", &hop_sca),
+        ),
+        (
+            "Adv_scan_ind",
+            advertising("adv_scan_ind", "Scannable undirected advertising"),
+        ),
+    ];
+
+    let mut frames = Frames::new("advertising_pdu", "adv_header", 1, "Pdu_type")
+        .with_desc("Advertising channel PDU, selected by the type in its header.");
+    for (variant, pdu) in &advertising_pdus {
+        tree.add_struct_with_alts(pdu, &addresses)?;
+        frames = frames.add_frame(variant, pdu);
+    }
+    tree.add_frames(&frames)?;
+
+    // AD structures of advertising and scan response data, whose length counts their type
+    let ad_structures = [
+        (
+            0x01,
+            Structure::new("ad_flags")
+                .with_desc("Discoverability and BR/EDR support of the advertiser")
+                .add_u8_field("flags"),
+        ),
+        (
+            0x0a,
+            Structure::new("ad_tx_power_level")
+                .with_desc("Transmit power of the advertiser")
+                .add_u8_field_desc("level", "Power in dBm as a signed integer"),
+        ),
+        (
+            0x19,
+            Structure::new("ad_appearance")
+                .with_desc("External appearance of the advertiser")
+                .add_u16_field("appearance"),
+        ),
+        (
+            0x1a,
+            Structure::new("ad_advertising_interval")
+                .with_desc("Interval of the advertising events")
+                .add_u16_field_desc("interval", "Interval in units of 0.625 ms"),
+        ),
+    ];
+    let mut ad_structure = Tlv::new("ad_structure", 1, 1)
+        .with_desc("AD structure of advertising or scan response data, selected by its AD type.")
+        .with_len_first()
+        .with_len_extra(1);
+    for (ad_type, structure) in &ad_structures {
+        tree.add_struct(structure)?;
+        ad_structure = ad_structure.add_entry(*ad_type, structure);
+    }
+    tree.add_tlv(&ad_structure)?;
+
+    let data_header = BitField::new("data_header", "Header of a data channel PDU")
+        .add_bit_field("Llid", "Type of the PDU", 2, |f| {
+            f.add_enum_value_desc(
+                "Continuation",
+                "Continuation of an L2CAP message or an empty PDU",
+                1,
+            )
+            .add_enum_value_desc("Start", "Start of an L2CAP message", 2)
+            .add_enum_value_desc("Control", "LL control PDU", 3)
+        })
+        .add_bit_field("Nesn", "Next expected sequence number", 1, |f| f)
+        .add_bit_field("Sn", "Sequence number", 1, |f| f)
+        .add_bit_field("Md", "The sender has more data", 1, |f| f)
+        .add_bit_field("Cp", "The CTE info follows the length", 1, |f| {
+            f.add_enum_value("Absent", 0).add_enum_value("Present", 1)
+        })
+        .add_reserved(2);
+    tree.add_bitfield(&data_header)?;
+
+    let cte_info_field = BitField::new("cte_params", "Parameters of a constant tone extension")
+        .add_bit_field("Cte_time", "Length of the CTE in units of 8 µs", 5, |f| {
+            f.numeric()
+        })
+        .add_reserved(1)
+        .add_bit_field("Cte_type", "Type of the CTE", 2, |f| {
+            f.add_enum_value_desc("Aoa", "Angle of arrival", 0)
+                .add_enum_value_desc("Aod_1us", "Angle of departure with 1 µs slots", 1)
+                .add_enum_value_desc("Aod_2us", "Angle of departure with 2 µs slots", 2)
+        });
+    tree.add_bitfield(&cte_info_field)?;
+    let cte_info = Structure::new("cte_info")
+        .with_desc("Constant tone extension following the PDU")
+        .add_bitfield_of("params", &cte_info_field);
+    tree.add_struct(&cte_info)?;
+
+    let ll_control_opcode = BitField::new("ll_control_opcode", "Opcode of an LL control PDU")
+        .add_bit_field("Opcode", "Type of the control PDU", 8, |f| {
+            CONTROL_PDUS
+                .iter()
+                .fold(f, |f, (variant, value)| f.add_enum_value(variant, *value))
+        });
+    tree.add_bitfield(&ll_control_opcode)?;
+
+    let control_data = CONTROL_PDUS
+        .iter()
+        .map(|(variant, _)| {
+            let s = ctr_data(variant);
+            match *variant {
+                "Connection_update_ind" => s
+                    .add_u8_field("win_size")
+                    .add_u16_field("win_offset")
+                    .add_u16_field("interval")
+                    .add_u16_field("latency")
+                    .add_u16_field("timeout")
+                    .add_u16_field("instant"),
+                "Channel_map_ind" => s.add_u40_field("chm").add_u16_field("instant"),
+                "Terminate_ind" | "Reject_ind" => s.add_u8_field("error_code"),
+                "Enc_req" => s
+                    .add_u64_field("rand")
+                    .add_u16_field("ediv")
+                    .add_u64_field("skd_c")
+                    .add_u32_field("iv_c"),
+                "Enc_rsp" => s.add_u64_field("skd_p").add_u32_field("iv_p"),
+                "Unknown_rsp" => s.add_u8_field("unknown_type"),
+                "Feature_req" | "Feature_rsp" | "Peripheral_feature_req" => {
+                    s.add_u64_field("feature_set")
+                }
+                "Version_ind" => s
+                    .add_u8_field_desc("vers_nr", "Version of the link layer")
+                    .add_u16_field_desc("comp_id", "Company identifier of the controller")
+                    .add_u16_field("sub_vers_nr"),
+                "Connection_param_req" | "Connection_param_rsp" => connection_params(s),
+                "Reject_ext_ind" => s.add_u8_field("reject_opcode").add_u8_field("error_code"),
+                "Length_req" | "Length_rsp" => lengths(s),
+                "Phy_req" | "Phy_rsp" => s.add_u8_field("tx_phys").add_u8_field("rx_phys"),
+                "Phy_update_ind" => s
+                    .add_u8_field("phy_c_to_p")
+                    .add_u8_field("phy_p_to_c")
+                    .add_u16_field("instant"),
+                "Min_used_channels_ind" => s.add_u8_field("phys").add_u8_field("min_used_channels"),
+                // encryption and ping procedures without control data
+                _ => s,
+            }
+        })
+        .collect::<Vec<_>>();
+    let controls = Alternatives::new().insert_new_option("ctr_data", &control_data[0], |o| {
+        control_data.iter().zip(CONTROL_PDUS).skip(1).fold(
+            o.set_discriminant(&control_data[0], CONTROL_PDUS[0].0),
+            |o, (data, (variant, _))| o.insert_type_discriminant(data, variant),
+        )
+    });
+    for data in &control_data {
+        tree.add_struct(data)?;
+    }
+    tree.add_alternatives(&controls)?;
+
+    let ll_data = |name: &str, desc: &str| {
+        Structure::new(name)
+            .with_desc(desc)
+            .add_bitfield_of("header", &data_header)
+            .add_len_field("length", 1)
+            .add_optional_field("cte_info", &cte_info, "header", "Cp")
+            .add_bytes_field("payload", "length", DATA_CAPACITY)
+    };
+    let ll_data_continuation = ll_data(
+        "ll_data_continuation",
+        "Continuation of an L2CAP message, or an empty PDU",
+    );
+    let ll_data_start = ll_data("ll_data_start", "Start of an L2CAP message");
+    let ll_control = Structure::new("ll_control")
+        .with_desc("LL control PDU")
+        .add_bitfield_of("header", &data_header)
+        .add_u8_field_desc("length", "Length of the opcode and the control data")
+        .add_optional_field("cte_info", &cte_info, "header", "Cp")
+        .add_bitfield_of("opcode", &ll_control_opcode)
+        .add_alt_field_discriminated("ctr_data", controls.get("ctr_data")?, "opcode", "Opcode");
+    tree.add_struct(&ll_data_continuation)?;
+    tree.add_struct(&ll_data_start)?;
+    tree.add_struct_with_alts(&ll_control, &controls)?;
+
+    let frames = Frames::new("data_pdu", "data_header", 1, "Llid")
+        .with_desc("Data channel PDU, selected by the LLID in its header.")
+        .with_parse_fn("parse_data")
+        .add_frame("Continuation", &ll_data_continuation)
+        .add_frame("Start", &ll_data_start)
+        .add_frame("Control", &ll_control);
+    tree.add_frames(&frames)
+}
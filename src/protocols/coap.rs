@@ -0,0 +1,195 @@
+//! CoAP (RFC 7252): the message header with its token, the options encoded as nibble-extended
+//! deltas and lengths, the payload marker as well as the option numbers and content formats.
+//!
+//! A message is read as its header and token, followed by options until the input ends or the
+//! payload marker is found, after which the remainder of the message is the payload. The option
+//! number of each option is the sum of its delta and the number of the previous option, which
+//! is left to the user along with the payload. Option values are limited to 255 bytes, which
+//! excludes the longest proxy URIs.
+
+use anyhow::Result;
+
+use crate::generate::bitfield::{BitField, BitOrder, Enumeration};
+use crate::generate::structure::{Alternatives, Structure};
+use crate::tree::GenTree;
+use crate::util::ByteOrder;
+
+/// The request and response codes along with the class and detail they are written as.
+const CODES: &[(&str, u64)] = &[
+    ("Empty", 0x00),
+    ("Get", 0x01),
+    ("Post", 0x02),
+    ("Put", 0x03),
+    ("Delete", 0x04),
+    ("Fetch", 0x05),
+    ("Patch", 0x06),
+    ("Ipatch", 0x07),
+    ("Created", 0x41),
+    ("Deleted", 0x42),
+    ("Valid", 0x43),
+    ("Changed", 0x44),
+    ("Content", 0x45),
+    ("Continue", 0x5f),
+    ("Bad_request", 0x80),
+    ("Unauthorized", 0x81),
+    ("Bad_option", 0x82),
+    ("Forbidden", 0x83),
+    ("Not_found", 0x84),
+    ("Method_not_allowed", 0x85),
+    ("Not_acceptable", 0x86),
+    ("Request_entity_incomplete", 0x88),
+    ("Conflict", 0x89),
+    ("Precondition_failed", 0x8c),
+    ("Request_entity_too_large", 0x8d),
+    ("Unsupported_content_format", 0x8f),
+    ("Unprocessable_entity", 0x96),
+    ("Too_many_requests", 0x9d),
+    ("Internal_server_error", 0xa0),
+    ("Not_implemented", 0xa1),
+    ("Bad_gateway", 0xa2),
+    ("Service_unavailable", 0xa3),
+    ("Gateway_timeout", 0xa4),
+    ("Proxying_not_supported", 0xa5),
+];
+
+/// The registered option numbers.
+const OPTIONS: &[(&str, u64)] = &[
+    ("If_match", 1),
+    ("Uri_host", 3),
+    ("Etag", 4),
+    ("If_none_match", 5),
+    ("Observe", 6),
+    ("Uri_port", 7),
+    ("Location_path", 8),
+    ("Uri_path", 11),
+    ("Content_format", 12),
+    ("Max_age", 14),
+    ("Uri_query", 15),
+    ("Accept", 17),
+    ("Location_query", 20),
+    ("Block2", 23),
+    ("Block1", 27),
+    ("Size2", 28),
+    ("Proxy_uri", 35),
+    ("Proxy_scheme", 39),
+    ("Size1", 60),
+];
+
+/// The registered content formats of the `Content-Format` and `Accept` options.
+const CONTENT_FORMATS: &[(&str, u64)] = &[
+    ("Text_plain", 0),
+    ("Link_format", 40),
+    ("Xml", 41),
+    ("Octet_stream", 42),
+    ("Exi", 47),
+    ("Json", 50),
+    ("Json_patch", 51),
+    ("Merge_patch", 52),
+    ("Cbor", 60),
+    ("Cwt", 61),
+    ("Senml_json", 110),
+    ("Sensml_json", 111),
+    ("Senml_cbor", 112),
+    ("Sensml_cbor", 113),
+];
+
+/// Capacity of option values.
+const VALUE_CAPACITY: u32 = 255;
+
+/// The longest token.
+const TOKEN_MAX: u32 = 8;
+
+/// Add the definitions of CoAP to `tree`.
+pub fn define(tree: &mut GenTree) -> Result<()> {
+    let option_number = OPTIONS.iter().fold(
+        Enumeration::new("option_number", "Number of an option", 16),
+        |e, (variant, value)| e.add_enum_value(variant, *value),
+    );
+    tree.add_enumeration(&option_number)?;
+
+    let content_format = CONTENT_FORMATS.iter().fold(
+        Enumeration::new("content_format", "Format of a representation", 16),
+        |e, (variant, value)| e.add_enum_value(variant, *value),
+    );
+    tree.add_enumeration(&content_format)?;
+
+    let header = BitField::new("header", "Fixed header of a message")
+        .with_bit_order(BitOrder::MsbFirst)
+        .add_bit_field_default("Version", "Protocol version, always 1", 2, 1, |f| {
+            f.numeric()
+        })
+        .add_bit_field("Type", "Type of the message", 2, |f| {
+            f.add_enum_value_desc("Confirmable", "Must be acknowledged", 0)
+                .add_enum_value_desc("Non_confirmable", "Is not acknowledged", 1)
+                .add_enum_value_desc("Acknowledgement", "Acknowledges a message", 2)
+                .add_enum_value_desc("Reset", "Rejects a message", 3)
+        })
+        .add_bit_field("Token_length", "Length of the token", 4, |f| {
+            (1..=TOKEN_MAX).fold(
+                f.add_enum_value_desc("None", "The message has no token", 0),
+                |f, len| f.add_enum_value(&format!("Bytes_{}", len), len as u64),
+            )
+        })
+        .add_bit_field(
+            "Code",
+            "Method of a request or class and detail of a response",
+            8,
+            |f| {
+                CODES
+                    .iter()
+                    .fold(f, |f, (variant, value)| f.add_enum_value(variant, *value))
+            },
+        )
+        .add_bit_field(
+            "Message_id",
+            "Identifier matching acknowledgements and detecting duplicates",
+            16,
+            |f| f.numeric(),
+        );
+    tree.add_bitfield(&header)?;
+
+    // tokens of up to 8 bytes, selected by the token length
+    let token_none = Structure::new("token_none").with_desc("Absent token");
+    let tokens = (1..=TOKEN_MAX)
+        .map(|len| {
+            Structure::new(&format!("token_{}", len))
+                .with_desc(&format!("Token of {} bytes", len))
+                .add_array_field("token", 1, len)
+        })
+        .collect::<Vec<_>>();
+    let token_alternatives = Alternatives::new().insert_new_option("token", &token_none, |o| {
+        tokens.iter().zip(1..).fold(
+            o.set_discriminant(&token_none, "None"),
+            |o, (token, len)| o.insert_type_discriminant(token, &format!("Bytes_{}", len)),
+        )
+    });
+    tree.add_struct(&token_none)?;
+    for token in &tokens {
+        tree.add_struct(token)?;
+    }
+    tree.add_alternatives(&token_alternatives)?;
+
+    let message = Structure::new("message")
+        .with_desc("Header and token of a message, followed by its options and payload")
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_bitfield_of("header", &header)
+        .add_alt_field_discriminated(
+            "token",
+            token_alternatives.get("token")?,
+            "header",
+            "Token_length",
+        );
+    tree.add_struct_with_alts(&message, &token_alternatives)?;
+
+    let option = Structure::new("message_option")
+        .with_desc("Option of a message, numbered by its delta to the previous option")
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_nibble_varints("delta", "length")
+        .add_bytes_field("value", "length", VALUE_CAPACITY);
+    tree.add_struct(&option)?;
+
+    let payload_marker = Structure::new("payload_marker")
+        .with_desc("Marker ending the options, followed by the payload")
+        .add_const_field("marker", 1, 0xff);
+    tree.add_struct(&payload_marker)
+}
@@ -0,0 +1,207 @@
+//! CRSF, the serial protocol of TBS Crossfire and ExpressLRS links between receivers, flight
+//! controllers and radio modules: the framing with its address, length, type and CRC-8, along
+//! with the frames of the packed RC channels and of common telemetry.
+//!
+//! Frames of other types are read as a `CrsfFrame` holding their payload as bytes. Multi-byte
+//! fields are big endian, except for the channels which are packed as for SBUS, and signed
+//! fields are held as the unsigned integers of their bits.
+
+use anyhow::Result;
+
+use crate::generate::bitfield::BitField;
+use crate::generate::checksum::ChecksumAlgorithm;
+use crate::generate::frame::Frames;
+use crate::generate::structure::Structure;
+use crate::protocols::sbus;
+use crate::tree::GenTree;
+use crate::util::ByteOrder;
+
+/// The addresses of devices.
+const ADDRESSES: &[(&str, u64)] = &[
+    ("Broadcast", 0x00),
+    ("Usb", 0x10),
+    ("Current_sensor", 0xc0),
+    ("Gps", 0xc2),
+    ("Blackbox", 0xc4),
+    ("Flight_controller", 0xc8),
+    ("Race_tag", 0xcc),
+    ("Radio_transmitter", 0xea),
+    ("Receiver", 0xec),
+    ("Transmitter", 0xee),
+];
+
+/// The types of frames.
+const FRAME_TYPES: &[(&str, u64)] = &[
+    ("Gps", 0x02),
+    ("Vario", 0x07),
+    ("Battery_sensor", 0x08),
+    ("Baro_altitude", 0x09),
+    ("Heartbeat", 0x0b),
+    ("Link_statistics", 0x14),
+    ("Rc_channels_packed", 0x16),
+    ("Subset_rc_channels_packed", 0x17),
+    ("Link_rx_id", 0x1c),
+    ("Link_tx_id", 0x1d),
+    ("Attitude", 0x1e),
+    ("Flight_mode", 0x21),
+    ("Device_ping", 0x28),
+    ("Device_info", 0x29),
+    ("Parameter_settings_entry", 0x2b),
+    ("Parameter_read", 0x2c),
+    ("Parameter_write", 0x2d),
+    ("Command", 0x32),
+    ("Radio_id", 0x3a),
+];
+
+/// Capacity of the payload of a frame of any type, which is at most 64 bytes long.
+const PAYLOAD_CAPACITY: u32 = 60;
+
+/// Start the frame `name` of the type `variant`, whose payload is `len` bytes.
+fn frame(name: &str, desc: &str, variant: &str, len: u64, address: &BitField) -> Structure {
+    let (_, value) = FRAME_TYPES
+        .iter()
+        .find(|(v, _)| *v == variant)
+        .expect("unknown frame type");
+    Structure::new(name)
+        .with_desc(desc)
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_bitfield_of("address", address)
+        .add_const_field("length", 1, len + 2)
+        .add_const_field("frame_type", 1, *value)
+}
+
+/// Finish the frame `s` with its checksum.
+fn checksum(s: Structure) -> Structure {
+    s.add_checksum_field("crc", "frame_type", ChecksumAlgorithm::Crc8DvbS2)
+}
+
+/// Add the definitions of CRSF to `tree`.
+pub fn define(tree: &mut GenTree) -> Result<()> {
+    let channels = sbus::channels();
+    tree.add_bitfield(&channels)?;
+
+    let address = BitField::new("address", "Address of a device").add_bit_field(
+        "Address",
+        "Address of the device the frame is sent to",
+        8,
+        |f| {
+            ADDRESSES
+                .iter()
+                .fold(f, |f, (variant, value)| f.add_enum_value(variant, *value))
+        },
+    );
+    tree.add_bitfield(&address)?;
+
+    let frame_type = BitField::new("frame_type", "Type of a frame").add_bit_field(
+        "Frame_type",
+        "Type of the frame",
+        8,
+        |f| {
+            FRAME_TYPES
+                .iter()
+                .fold(f, |f, (variant, value)| f.add_enum_value(variant, *value))
+        },
+    );
+    tree.add_bitfield(&frame_type)?;
+
+    let any_frame = Structure::new("crsf_frame")
+        .with_desc("Frame of any type, holding its payload as bytes")
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_bitfield_of("address", &address)
+        .add_len_field_extra("length", 1, 2)
+        .add_bitfield_of("frame_type", &frame_type)
+        .add_bytes_field("payload", "length", PAYLOAD_CAPACITY)
+        .add_checksum_field("crc", "frame_type", ChecksumAlgorithm::Crc8DvbS2);
+    tree.add_struct(&any_frame)?;
+
+    let frames = [
+        (
+            "Gps",
+            checksum(
+                frame("gps", "Position of the GPS", "Gps", 15, &address)
+                    .add_u32_field_desc("latitude", "Latitude in 1e-7 degrees, signed")
+                    .add_u32_field_desc("longitude", "Longitude in 1e-7 degrees, signed")
+                    .add_u16_field_desc("groundspeed", "Ground speed in 0.1 km/h")
+                    .add_u16_field_desc("heading", "Heading in 0.01 degrees")
+                    .add_u16_field_desc("altitude", "Altitude in m, offset by 1000 m")
+                    .add_u8_field_desc("satellites", "Number of satellites in view"),
+            ),
+        ),
+        (
+            "Battery_sensor",
+            checksum(
+                frame(
+                    "battery_sensor",
+                    "State of the battery",
+                    "Battery_sensor",
+                    8,
+                    &address,
+                )
+                .add_u16_field_desc("voltage", "Voltage in 0.1 V")
+                .add_u16_field_desc("current", "Current in 0.1 A")
+                .add_u24_field_desc("capacity", "Capacity drawn in mAh")
+                .add_u8_field_desc("remaining", "Remaining capacity in percent"),
+            ),
+        ),
+        (
+            "Link_statistics",
+            checksum(
+                frame(
+                    "link_statistics",
+                    "Quality of the link",
+                    "Link_statistics",
+                    10,
+                    &address,
+                )
+                .add_u8_field_desc("uplink_rssi_1", "RSSI of the first antenna in -dBm")
+                .add_u8_field_desc("uplink_rssi_2", "RSSI of the second antenna in -dBm")
+                .add_u8_field_desc("uplink_link_quality", "Packet success rate in percent")
+                .add_u8_field_desc("uplink_snr", "Signal to noise ratio in dB, signed")
+                .add_u8_field_desc("active_antenna", "Antenna receiving the uplink")
+                .add_u8_field_desc("rf_mode", "Packet rate of the link")
+                .add_u8_field_desc("uplink_tx_power", "Transmit power of the transmitter")
+                .add_u8_field_desc("downlink_rssi", "RSSI of the telemetry in -dBm")
+                .add_u8_field_desc("downlink_link_quality", "Packet success rate in percent")
+                .add_u8_field_desc("downlink_snr", "Signal to noise ratio in dB, signed"),
+            ),
+        ),
+        (
+            "Rc_channels_packed",
+            checksum(
+                frame(
+                    "rc_channels_packed",
+                    "Proportional channels of the transmitter",
+                    "Rc_channels_packed",
+                    22,
+                    &address,
+                )
+                .add_bitfield_of("channels", &channels),
+            ),
+        ),
+        (
+            "Attitude",
+            checksum(
+                frame(
+                    "attitude",
+                    "Attitude of the vehicle",
+                    "Attitude",
+                    6,
+                    &address,
+                )
+                .add_u16_field_desc("pitch", "Pitch in 1e-4 rad, signed")
+                .add_u16_field_desc("roll", "Roll in 1e-4 rad, signed")
+                .add_u16_field_desc("yaw", "Yaw in 1e-4 rad, signed"),
+            ),
+        ),
+    ];
+
+    let mut message = Frames::new("crsf_message", "frame_type", 1, "Frame_type")
+        .with_desc("Frame of a known type, selected by the type following its address and length.")
+        .with_byte_order(ByteOrder::BigEndian)
+        .with_offset(2);
+    for (variant, frame) in &frames {
+        tree.add_struct(frame)?;
+        message = message.add_frame(variant, frame);
+    }
+    tree.add_frames(&message)
+}
@@ -0,0 +1,188 @@
+//! DNS (RFC 1035): the header of a message with its flags and section counts, the parts of a
+//! domain name, the fixed tails of questions and resource records as well as the data of
+//! address records.
+//!
+//! A domain name is read part by part using `NamePart::read_from`, until a label of length
+//! zero or a compression pointer ends it. The offset of a pointer refers to the start of the
+//! whole message, and may be resolved against it to continue reading the name there. Questions
+//! and resource records are read as their name followed by their tail, and each section holds
+//! as many of them as counted by the header. The data of resource records is limited to 512
+//! bytes, the largest message without EDNS.
+
+use anyhow::Result;
+
+use crate::generate::bitfield::{BitField, BitOrder};
+use crate::generate::frame::Frames;
+use crate::generate::structure::Structure;
+use crate::tree::GenTree;
+use crate::util::ByteOrder;
+
+/// The types of resource records and queries.
+const TYPES: &[(&str, u64)] = &[
+    ("A", 1),
+    ("Ns", 2),
+    ("Cname", 5),
+    ("Soa", 6),
+    ("Ptr", 12),
+    ("Hinfo", 13),
+    ("Mx", 15),
+    ("Txt", 16),
+    ("Aaaa", 28),
+    ("Srv", 33),
+    ("Naptr", 35),
+    ("Opt", 41),
+    ("Ds", 43),
+    ("Rrsig", 46),
+    ("Nsec", 47),
+    ("Dnskey", 48),
+    ("Svcb", 64),
+    ("Https", 65),
+    ("Axfr", 252),
+    ("Any", 255),
+    ("Caa", 257),
+];
+
+/// The classes of resource records and queries.
+const CLASSES: &[(&str, u64)] = &[("In", 1), ("Ch", 3), ("Hs", 4), ("None", 254), ("Any", 255)];
+
+/// The longest label.
+const LABEL_MAX: u32 = 63;
+
+/// Capacity of the data of a resource record.
+const RDATA_CAPACITY: u32 = 512;
+
+/// Add the definitions of DNS to `tree`.
+pub fn define(tree: &mut GenTree) -> Result<()> {
+    let flags = BitField::new("flags", "Flags of a message")
+        .with_bit_order(BitOrder::MsbFirst)
+        .add_bit_field(
+            "Qr",
+            "Whether the message is a query or a response",
+            1,
+            |f| f.add_enum_value("Query", 0).add_enum_value("Response", 1),
+        )
+        .add_bit_field("Opcode", "Kind of the query", 4, |f| {
+            f.add_enum_value_desc("Query", "A standard query", 0)
+                .add_enum_value_desc("Iquery", "An inverse query", 1)
+                .add_enum_value_desc("Status", "A server status request", 2)
+                .add_enum_value_desc("Notify", "A change of a zone", 4)
+                .add_enum_value_desc("Update", "A dynamic update", 5)
+        })
+        .add_bit_field("Aa", "The answer is authoritative", 1, |f| f)
+        .add_bit_field("Tc", "The message was truncated", 1, |f| f)
+        .add_bit_field("Rd", "Recursion is desired", 1, |f| f)
+        .add_bit_field("Ra", "Recursion is available", 1, |f| f)
+        .add_reserved(1)
+        .add_bit_field("Ad", "The answer was authenticated", 1, |f| f)
+        .add_bit_field("Cd", "Checking is disabled", 1, |f| f)
+        .add_bit_field("Rcode", "Result of the query", 4, |f| {
+            f.add_enum_value("No_error", 0)
+                .add_enum_value("Form_err", 1)
+                .add_enum_value("Serv_fail", 2)
+                .add_enum_value("Nx_domain", 3)
+                .add_enum_value("Not_imp", 4)
+                .add_enum_value("Refused", 5)
+                .add_enum_value("Yx_domain", 6)
+                .add_enum_value("Yx_rrset", 7)
+                .add_enum_value("Nx_rrset", 8)
+                .add_enum_value("Not_auth", 9)
+                .add_enum_value("Not_zone", 10)
+        });
+    tree.add_bitfield(&flags)?;
+
+    let rr_type = BitField::new("rr_type", "Type of a resource record or query").add_bit_field(
+        "Type",
+        "Type of the record",
+        16,
+        |f| {
+            TYPES
+                .iter()
+                .fold(f, |f, (variant, value)| f.add_enum_value(variant, *value))
+        },
+    );
+    tree.add_bitfield(&rr_type)?;
+
+    let rr_class = BitField::new("rr_class", "Class of a resource record or query").add_bit_field(
+        "Class",
+        "Class of the record",
+        16,
+        |f| {
+            CLASSES
+                .iter()
+                .fold(f, |f, (variant, value)| f.add_enum_value(variant, *value))
+        },
+    );
+    tree.add_bitfield(&rr_class)?;
+
+    let label_kind = BitField::new("label_kind", "First byte of a part of a domain name")
+        .with_bit_order(BitOrder::MsbFirst)
+        .add_bit_field("Kind", "Kind of the part", 2, |f| {
+            f.add_enum_value_desc("Label", "A label, preceded by its length", 0)
+                .add_enum_value_desc("Pointer", "A pointer to the rest of the name", 3)
+        })
+        .add_bit_field(
+            "Bits",
+            "Length of a label or high bits of a pointer",
+            6,
+            |f| f.numeric(),
+        );
+    tree.add_bitfield(&label_kind)?;
+
+    let header = Structure::new("header")
+        .with_desc("Header of a message")
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_u16_field_desc("id", "Identifier matching a response to its query")
+        .add_bitfield_of("flags", &flags)
+        .add_u16_field_desc("qdcount", "Number of questions")
+        .add_u16_field_desc("ancount", "Number of answers")
+        .add_u16_field_desc("nscount", "Number of authority records")
+        .add_u16_field_desc("arcount", "Number of additional records");
+    tree.add_struct(&header)?;
+
+    let label = Structure::new("label")
+        .with_desc("Label of a domain name, the name ends with a label of length zero")
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_len_field("length", 1)
+        .add_bytes_field("label", "length", LABEL_MAX);
+    tree.add_struct(&label)?;
+
+    let pointer = Structure::new("pointer")
+        .with_desc("Pointer to the rest of a domain name, which ends the name")
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_offset_field("offset", 2, 2, 0b11);
+    tree.add_struct(&pointer)?;
+
+    let name_part = Frames::new("name_part", "label_kind", 1, "Kind")
+        .with_desc("Part of a domain name, selected by the high bits of its first byte.")
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_frame("Label", &label)
+        .add_frame("Pointer", &pointer);
+    tree.add_frames(&name_part)?;
+
+    let question = Structure::new("question")
+        .with_desc("Type and class of a question, following its name")
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_bitfield_of("qtype", &rr_type)
+        .add_bitfield_of("qclass", &rr_class);
+    tree.add_struct(&question)?;
+
+    let resource_record = Structure::new("resource_record")
+        .with_desc("Type, class, time to live and data of a resource record, following its name")
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_bitfield_of("rtype", &rr_type)
+        .add_bitfield_of("rclass", &rr_class)
+        .add_u32_field_desc("ttl", "Time in seconds the record may be cached")
+        .add_len_field("rdlength", 2)
+        .add_bytes_field("rdata", "rdlength", RDATA_CAPACITY);
+    tree.add_struct(&resource_record)?;
+
+    let a = Structure::new("a_data")
+        .with_desc("Data of an `A` record")
+        .add_array_field("address", 1, 4);
+    tree.add_struct(&a)?;
+
+    let aaaa = Structure::new("aaaa_data")
+        .with_desc("Data of an `AAAA` record")
+        .add_array_field("address", 1, 16);
+    tree.add_struct(&aaaa)
+}
@@ -0,0 +1,88 @@
+//! Ethernet II with IEEE 802.1Q VLAN tags: the header of a frame, its EtherTypes and up to two
+//! stacked tags as used by 802.1ad QinQ.
+//!
+//! A tag follows the addresses when the EtherType of the header announces one, and an inner
+//! customer tag follows an outer tag whose EtherType is again `Vlan`. The payload is of the
+//! EtherType of the innermost tag, or of the header if the frame is untagged. The frame check
+//! sequence is usually stripped by the hardware, so it is left to the user.
+
+use anyhow::Result;
+
+use crate::generate::bitfield::{BitField, BitOrder};
+use crate::generate::structure::Structure;
+use crate::tree::GenTree;
+use crate::util::ByteOrder;
+
+/// The EtherTypes of payloads and tags.
+const ETHER_TYPES: &[(&str, u64)] = &[
+    ("Ipv4", 0x0800),
+    ("Arp", 0x0806),
+    ("Wake_on_lan", 0x0842),
+    ("Vlan", 0x8100),
+    ("Ipv6", 0x86dd),
+    ("Mpls", 0x8847),
+    ("Mpls_multicast", 0x8848),
+    ("Pppoe_discovery", 0x8863),
+    ("Pppoe_session", 0x8864),
+    ("Eapol", 0x888e),
+    ("Service_vlan", 0x88a8),
+    ("Lldp", 0x88cc),
+    ("Macsec", 0x88e5),
+    ("Ptp", 0x88f7),
+    ("Qinq_legacy", 0x9100),
+];
+
+/// The EtherTypes announcing an outer tag.
+const OUTER_TAGS: &[&str] = &["Vlan", "Service_vlan", "Qinq_legacy"];
+
+/// Add the definitions of Ethernet to `tree`.
+pub fn define(tree: &mut GenTree) -> Result<()> {
+    let ether_type = BitField::new("ether_type", "Type of a payload or tag").add_bit_field(
+        "Ether_type",
+        "Type of the payload or tag which follows",
+        16,
+        |f| {
+            ETHER_TYPES
+                .iter()
+                .fold(f, |f, (variant, value)| f.add_enum_value(variant, *value))
+        },
+    );
+    tree.add_bitfield(&ether_type)?;
+
+    let tci = BitField::new("tci", "Tag control information")
+        .with_bit_order(BitOrder::MsbFirst)
+        .add_bit_field("Pcp", "Priority code point", 3, |f| f.numeric())
+        .add_bit_field("Dei", "The frame may be dropped under congestion", 1, |f| f)
+        .add_bit_field("Vid", "VLAN identifier", 12, |f| f.numeric());
+    tree.add_bitfield(&tci)?;
+
+    let customer_tag = Structure::new("customer_tag")
+        .with_desc("Inner tag of a double tagged frame")
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_bitfield_of("tci", &tci)
+        .add_bitfield_of("ether_type", &ether_type);
+    tree.add_struct(&customer_tag)?;
+
+    let vlan_tag = Structure::new("vlan_tag")
+        .with_desc("Tag of a frame, which may be followed by an inner tag")
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_bitfield_of("tci", &tci)
+        .add_bitfield_of("ether_type", &ether_type)
+        .add_optional_field_when(
+            "inner",
+            &customer_tag,
+            "ether_type",
+            "Ether_type",
+            &["Vlan"],
+        );
+    tree.add_struct(&vlan_tag)?;
+
+    let header = Structure::new("ethernet_header")
+        .with_desc("Header of a frame, followed by its payload")
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_eui48_field("destination")
+        .add_eui48_field("source")
+        .add_bitfield_of("ether_type", &ether_type)
+        .add_optional_field_when("tag", &vlan_tag, "ether_type", "Ether_type", OUTER_TAGS);
+    tree.add_struct(&header)
+}
@@ -0,0 +1,652 @@
+//! The MAC layer of IEEE 802.15.4-2015: the frame control field, the MAC header with its
+//! addressing fields and auxiliary security header, acknowledgment frames, beacon frames with
+//! their GTS descriptors and pending addresses, MAC command frames and the descriptors of header
+//! and payload information elements, along with the Zigbee network and application support
+//! sublayers carried by data frames.
+//!
+//! Every command identifier of IEEE 802.15.4-2015 is known, but only the payloads of the
+//! commands of IEEE 802.15.4-2006 are modelled: association and disassociation, data request,
+//! PAN ID conflict and orphan notification, beacon request, coordinator realignment and GTS
+//! request. Reading a frame of the TRLE, DSME, RIT, DBS or vendor specific commands fails with
+//! an invalid `Command_id`, and the beacon payload is left out of beacon frames.
+//!
+//! The sequence number, the PAN identifiers and the frame counter and key identifier of the
+//! auxiliary security header are present as the frame control and security control fields
+//! select them. Writing leaves these fields as they are, so the optional members must agree
+//! with them. Data frames end their payload with the frame check sequence, so only their MAC
+//! header is read, leaving the payload and its CRC-16/KERMIT to the user through `Crc16Kermit`.
+
+use anyhow::Result;
+
+use crate::generate::bitfield::{BitField, Enumeration};
+use crate::generate::checksum::ChecksumAlgorithm;
+use crate::generate::structure::{
+    AlternativeOptions, Alternatives, Condition, SimpleStructure, Structure,
+};
+use crate::tree::GenTree;
+
+use super::zigbee;
+
+/// The types of the MAC header, which starts every frame but the acknowledgments.
+struct Header<'a> {
+    frame_control: &'a BitField,
+    sequence_number: &'a Structure,
+    pan_id: &'a Structure,
+    address: &'a AlternativeOptions,
+    aux_sec_header: &'a Structure,
+}
+
+impl Header<'_> {
+    /// Add the members of the MAC header to `s`. The PAN identifiers are present as table 7-2
+    /// of the standard selects them by the addressing modes, the frame version and PAN ID
+    /// compression, where the frames of IEEE 802.15.4-2003 and 2006 carry a PAN identifier
+    /// along with each address but drop the source one when compressed.
+    fn add(&self, s: Structure) -> Structure {
+        let addressed = |field: &str| !Condition::is(field, &["None"]);
+        let std2015 = Condition::is("Frame_version", &["Std2015"]);
+        let compressed = Condition::set("Pan_id_compression");
+        let both_extended = Condition::all(vec![
+            Condition::is("Dest_addr_mode", &["Extended"]),
+            Condition::is("Source_addr_mode", &["Extended"]),
+        ]);
+        let dest_pan = Condition::any(vec![
+            Condition::all(vec![
+                addressed("Dest_addr_mode"),
+                Condition::any(vec![
+                    !std2015.clone(),
+                    !compressed.clone(),
+                    Condition::all(vec![addressed("Source_addr_mode"), !both_extended.clone()]),
+                ]),
+            ]),
+            Condition::all(vec![
+                std2015.clone(),
+                !addressed("Dest_addr_mode"),
+                !addressed("Source_addr_mode"),
+                compressed.clone(),
+            ]),
+        ]);
+        let src_pan = Condition::all(vec![
+            addressed("Source_addr_mode"),
+            !compressed,
+            Condition::any(vec![!std2015, !both_extended]),
+        ]);
+
+        s.add_bitfield_of("frame_control", self.frame_control)
+            .add_optional_field_if(
+                "sequence_number",
+                self.sequence_number,
+                "frame_control",
+                !Condition::set("Seq_num_suppression"),
+            )
+            .add_optional_field_if("dest_pan", self.pan_id, "frame_control", dest_pan)
+            .add_alt_field_discriminated(
+                "dest_address",
+                self.address,
+                "frame_control",
+                "Dest_addr_mode",
+            )
+            .add_optional_field_if("src_pan", self.pan_id, "frame_control", src_pan)
+            .add_alt_field_discriminated(
+                "src_address",
+                self.address,
+                "frame_control",
+                "Source_addr_mode",
+            )
+            .add_optional_field(
+                "aux_sec_header",
+                self.aux_sec_header,
+                "frame_control",
+                "Security_enabled",
+            )
+    }
+}
+
+/// The types of the fields of a beacon, which follow the MAC header of beacon frames.
+struct Beacon<'a> {
+    superframe_spec: &'a BitField,
+    gts_spec: &'a BitField,
+    gts_directions: &'a Structure,
+    pending_address_spec: &'a BitField,
+}
+
+impl Beacon<'_> {
+    /// Add the fields of a beacon to `s`: the superframe specification, the GTS specification
+    /// with the directions and descriptors of the guaranteed time slots, and the pending address
+    /// specification with the short and extended addresses of the devices the coordinator holds
+    /// data for. A descriptor holds the short address of its device in the low 16 bits, then
+    /// the starting slot and the length of the GTS in a nibble each. The counts are updated when
+    /// writing, while the directions are only present along with descriptors, so must be set
+    /// whenever any are.
+    fn add(&self, s: Structure) -> Structure {
+        s.add_bitfield_of("superframe_spec", self.superframe_spec)
+            .add_bitfield_of("gts_spec", self.gts_spec)
+            .add_optional_field_if(
+                "gts_directions",
+                self.gts_directions,
+                "gts_spec",
+                Condition::non_zero("Descriptor_count"),
+            )
+            .add_repeated_field_counted_by("gts_descriptors", 3, "gts_spec", "Descriptor_count", 7)
+            .add_bitfield_of("pending_address_spec", self.pending_address_spec)
+            .add_repeated_field_counted_by(
+                "short_addresses",
+                2,
+                "pending_address_spec",
+                "Short_count",
+                7,
+            )
+            .add_repeated_field_counted_by(
+                "extended_addresses",
+                8,
+                "pending_address_spec",
+                "Extended_count",
+                7,
+            )
+    }
+}
+
+/// Finish the frame `s` with its frame check sequence.
+fn fcs(s: Structure) -> Structure {
+    s.add_checksum_field("fcs", "frame_control", ChecksumAlgorithm::Crc16Kermit)
+}
+
+/// Add the definitions of IEEE 802.15.4 to `tree`.
+pub fn define(tree: &mut GenTree) -> Result<()> {
+    let addr_mode = Enumeration::new("addr_mode", "Addressing mode of an address field", 2)
+        .add_enum_value_desc("None", "The address is not present", 0)
+        .add_enum_value_desc("Short", "16 bit short address", 2)
+        .add_enum_value_desc("Extended", "64 bit extended address", 3);
+    tree.add_enumeration(&addr_mode)?;
+
+    let frame_control = BitField::new("frame_control", "Frame control field")
+        .add_bit_field("Frame_type", "Type of the frame", 3, |f| {
+            f.add_enum_value("Beacon", 0)
+                .add_enum_value("Data", 1)
+                .add_enum_value("Ack", 2)
+                .add_enum_value("Mac_command", 3)
+                .add_enum_value("Multipurpose", 5)
+                .add_enum_value("Fragment", 6)
+                .add_enum_value("Extended", 7)
+        })
+        .add_bit_field(
+            "Security_enabled",
+            "The auxiliary security header is present",
+            1,
+            |f| f.add_enum_value("Disabled", 0).add_enum_value("Enabled", 1),
+        )
+        .add_bit_field(
+            "Frame_pending",
+            "The sender has more data for the recipient",
+            1,
+            |f| f,
+        )
+        .add_bit_field(
+            "Ack_request",
+            "The recipient must acknowledge the frame",
+            1,
+            |f| f,
+        )
+        .add_bit_field(
+            "Pan_id_compression",
+            "The source PAN identifier is omitted",
+            1,
+            |f| f,
+        )
+        .add_reserved(1)
+        .add_bit_field(
+            "Seq_num_suppression",
+            "The sequence number is omitted",
+            1,
+            |f| f,
+        )
+        .add_bit_field("Ie_present", "Information elements are present", 1, |f| f)
+        .add_bit_field("Dest_addr_mode", "Destination addressing mode", 2, |f| {
+            f.with_enumeration(&addr_mode)
+        })
+        .add_bit_field("Frame_version", "Version of the frame", 2, |f| {
+            f.add_enum_value_desc("Std2003", "IEEE 802.15.4-2003", 0)
+                .add_enum_value_desc("Std2006", "IEEE 802.15.4-2006", 1)
+                .add_enum_value_desc("Std2015", "IEEE 802.15.4-2015", 2)
+        })
+        .add_bit_field("Source_addr_mode", "Source addressing mode", 2, |f| {
+            f.with_enumeration(&addr_mode)
+        });
+    tree.add_bitfield(&frame_control)?;
+
+    let security_control = BitField::new("security_control", "Security control field")
+        .add_bit_field(
+            "Security_level",
+            "Protection applied to the frame",
+            3,
+            |f| {
+                f.add_enum_value_desc("None", "No protection", 0)
+                    .add_enum_value_desc("Mic_32", "32 bit message integrity code", 1)
+                    .add_enum_value_desc("Mic_64", "64 bit message integrity code", 2)
+                    .add_enum_value_desc("Mic_128", "128 bit message integrity code", 3)
+                    .add_enum_value_desc("Enc", "Encryption", 4)
+                    .add_enum_value_desc("Enc_mic_32", "Encryption and 32 bit MIC", 5)
+                    .add_enum_value_desc("Enc_mic_64", "Encryption and 64 bit MIC", 6)
+                    .add_enum_value_desc("Enc_mic_128", "Encryption and 128 bit MIC", 7)
+            },
+        )
+        .add_bit_field("Key_id_mode", "How the key is identified", 2, |f| {
+            f.add_enum_value_desc("Implicit", "The key is known to both parties", 0)
+                .add_enum_value_desc("Index", "A key index", 1)
+                .add_enum_value_desc("Source_4", "A 4 byte key source and a key index", 2)
+                .add_enum_value_desc("Source_8", "An 8 byte key source and a key index", 3)
+        })
+        .add_bit_field(
+            "Frame_counter_suppression",
+            "The frame counter is omitted",
+            1,
+            |f| f,
+        )
+        .add_bit_field(
+            "Asn_in_nonce",
+            "The nonce holds the absolute slot number",
+            1,
+            |f| f,
+        )
+        .add_reserved(1);
+    tree.add_bitfield(&security_control)?;
+
+    let superframe_spec = BitField::new("superframe_spec", "Superframe specification")
+        .add_bit_field("Beacon_order", "Interval of the beacons", 4, |f| {
+            f.numeric()
+        })
+        .add_bit_field(
+            "Superframe_order",
+            "Length of the active superframe",
+            4,
+            |f| f.numeric(),
+        )
+        .add_bit_field(
+            "Final_cap_slot",
+            "Last slot of the contention access period",
+            4,
+            |f| f.numeric(),
+        )
+        .add_bit_field(
+            "Battery_life_extension",
+            "Battery life extension is used",
+            1,
+            |f| f,
+        )
+        .add_reserved(1)
+        .add_bit_field("Pan_coordinator", "Sent by the PAN coordinator", 1, |f| f)
+        .add_bit_field(
+            "Association_permit",
+            "The coordinator accepts associations",
+            1,
+            |f| f,
+        );
+    tree.add_bitfield(&superframe_spec)?;
+
+    let gts_spec = BitField::new("gts_spec", "Guaranteed time slot specification")
+        .add_bit_field(
+            "Descriptor_count",
+            "Number of GTS descriptors following",
+            3,
+            |f| f.numeric(),
+        )
+        .add_reserved(4)
+        .add_bit_field(
+            "Gts_permit",
+            "The coordinator accepts GTS requests",
+            1,
+            |f| f,
+        );
+    tree.add_bitfield(&gts_spec)?;
+
+    let pending_address_spec =
+        BitField::new("pending_address_spec", "Pending address specification")
+            .add_bit_field("Short_count", "Number of pending short addresses", 3, |f| {
+                f.numeric()
+            })
+            .add_reserved(1)
+            .add_bit_field(
+                "Extended_count",
+                "Number of pending extended addresses",
+                3,
+                |f| f.numeric(),
+            )
+            .add_reserved(1);
+    tree.add_bitfield(&pending_address_spec)?;
+
+    let gts_direction_mask =
+        BitField::new("gts_direction_mask", "Directions of the GTS descriptors")
+            .add_bit_field(
+                "Directions",
+                "Mask of the descriptors of receive-only slots, starting with the first",
+                7,
+                |f| f.numeric(),
+            )
+            .add_reserved(1);
+    tree.add_bitfield(&gts_direction_mask)?;
+
+    let command_id = BitField::new("command_id", "Command frame identifier").add_bit_field(
+        "Command_id",
+        "The MAC command",
+        8,
+        |f| {
+            f.add_enum_value("Association_request", 0x01)
+                .add_enum_value("Association_response", 0x02)
+                .add_enum_value("Disassociation_notification", 0x03)
+                .add_enum_value("Data_request", 0x04)
+                .add_enum_value("Pan_id_conflict_notification", 0x05)
+                .add_enum_value("Orphan_notification", 0x06)
+                .add_enum_value("Beacon_request", 0x07)
+                .add_enum_value("Coordinator_realignment", 0x08)
+                .add_enum_value("Gts_request", 0x09)
+                .add_enum_value("Trle_management_request", 0x0a)
+                .add_enum_value("Trle_management_response", 0x0b)
+                .add_enum_value("Dsme_association_request", 0x13)
+                .add_enum_value("Dsme_association_response", 0x14)
+                .add_enum_value("Dsme_gts_request", 0x15)
+                .add_enum_value("Dsme_gts_reply", 0x16)
+                .add_enum_value("Dsme_gts_notify", 0x17)
+                .add_enum_value("Dsme_information_request", 0x18)
+                .add_enum_value("Dsme_information_reply", 0x19)
+                .add_enum_value("Dsme_beacon_allocation_notification", 0x1a)
+                .add_enum_value("Dsme_beacon_collision_notification", 0x1b)
+                .add_enum_value("Dsme_link_report", 0x1c)
+                .add_enum_value("Rit_data_request", 0x20)
+                .add_enum_value("Dbs_request", 0x21)
+                .add_enum_value("Dbs_response", 0x22)
+                .add_enum_value("Rit_data_response", 0x23)
+                .add_enum_value("Vendor_specific", 0x24)
+        },
+    );
+    tree.add_bitfield(&command_id)?;
+
+    let capability_information = BitField::new(
+        "capability_information",
+        "Capabilities of a device requesting association",
+    )
+    .add_reserved(1)
+    .add_bit_field(
+        "Device_type",
+        "The device is a full function device",
+        1,
+        |f| f,
+    )
+    .add_bit_field("Power_source", "The device is mains powered", 1, |f| f)
+    .add_bit_field(
+        "Receiver_on_when_idle",
+        "The receiver is enabled while idle",
+        1,
+        |f| f,
+    )
+    .add_bit_field(
+        "Association_type",
+        "The device requests fast association",
+        1,
+        |f| f,
+    )
+    .add_reserved(1)
+    .add_bit_field(
+        "Security_capability",
+        "The device can secure MAC frames",
+        1,
+        |f| f,
+    )
+    .add_bit_field(
+        "Allocate_address",
+        "The device asks for a short address",
+        1,
+        |f| f,
+    );
+    tree.add_bitfield(&capability_information)?;
+
+    let association_status = BitField::new("association_status", "Outcome of an association")
+        .add_bit_field("Status", "Outcome of the association", 8, |f| {
+            f.add_enum_value("Successful", 0x00)
+                .add_enum_value("Pan_at_capacity", 0x01)
+                .add_enum_value("Pan_access_denied", 0x02)
+                .add_enum_value("Hopping_sequence_offset_duplication", 0x03)
+                .add_enum_value("Fast_association_successful", 0x80)
+        });
+    tree.add_bitfield(&association_status)?;
+
+    let disassociation_reason = BitField::new(
+        "disassociation_reason",
+        "Reason of a disassociation",
+    )
+    .add_bit_field("Reason", "Reason of the disassociation", 8, |f| {
+        f.add_enum_value("Coordinator_leave", 0x01)
+            .add_enum_value("Device_leave", 0x02)
+    });
+    tree.add_bitfield(&disassociation_reason)?;
+
+    let gts_characteristics =
+        BitField::new("gts_characteristics", "Characteristics of a requested GTS")
+            .add_bit_field("Gts_length", "Number of superframe slots", 4, |f| {
+                f.numeric()
+            })
+            .add_bit_field("Gts_direction", "The GTS is a receive slot", 1, |f| f)
+            .add_bit_field(
+                "Characteristics_type",
+                "The GTS is allocated rather than deallocated",
+                1,
+                |f| f,
+            )
+            .add_reserved(2);
+    tree.add_bitfield(&gts_characteristics)?;
+
+    let header_ie = BitField::new("header_ie", "Header information element descriptor")
+        .add_bit_field("Length", "Length of the content", 7, |f| f.numeric())
+        .add_bit_field("Element_id", "The information element", 8, |f| {
+            f.add_enum_value("Vendor_specific", 0x00)
+                .add_enum_value("Csl", 0x1a)
+                .add_enum_value("Rit", 0x1b)
+                .add_enum_value("Dsme_pan_descriptor", 0x1c)
+                .add_enum_value("Rendezvous_time", 0x1d)
+                .add_enum_value("Time_correction", 0x1e)
+                .add_enum_value("Extended_dsme_pan_descriptor", 0x21)
+                .add_enum_value("Fragment_sequence_context", 0x22)
+                .add_enum_value("Simplified_superframe_spec", 0x23)
+                .add_enum_value("Simplified_gts_spec", 0x24)
+                .add_enum_value("Lecim_capabilities", 0x25)
+                .add_enum_value("Trle_descriptor", 0x26)
+                .add_enum_value("Rcc_capabilities", 0x27)
+                .add_enum_value("Rccn_descriptor", 0x28)
+                .add_enum_value("Global_time", 0x29)
+                .add_enum_value("Da", 0x2b)
+                .add_enum_value("Header_termination_1", 0x7e)
+                .add_enum_value("Header_termination_2", 0x7f)
+        })
+        .add_bit_field("Type", "Always clear for header IEs", 1, |f| f);
+    tree.add_bitfield(&header_ie)?;
+
+    let payload_ie = BitField::new("payload_ie", "Payload information element descriptor")
+        .add_bit_field("Length", "Length of the content", 11, |f| f.numeric())
+        .add_bit_field("Group_id", "The group of information elements", 4, |f| {
+            f.add_enum_value("Esdu", 0x0)
+                .add_enum_value("Mlme", 0x1)
+                .add_enum_value("Vendor_specific", 0x2)
+                .add_enum_value("Payload_termination", 0xf)
+        })
+        .add_bit_field("Type", "Always set for payload IEs", 1, |f| f);
+    tree.add_bitfield(&payload_ie)?;
+
+    // addressing fields of the MAC header, selected by the addressing modes
+    let addr_none = Structure::new("addr_none").with_desc("Absent address");
+    let addr_short = SimpleStructure::new("addr_short", "addr", 2);
+    let addr_extended = Structure::new("addr_extended")
+        .with_desc("64 bit extended address")
+        .add_eui64_field("addr");
+    let addresses = Alternatives::new().insert_new_option("address", &addr_none, |o| {
+        o.set_discriminant(&addr_none, "None")
+            .insert_type_discriminant(&addr_short, "Short")
+            .insert_type_discriminant(&addr_extended, "Extended")
+    });
+    tree.add_struct(&addr_none)?;
+    tree.add_struct_simple(&addr_short)?;
+    tree.add_struct(&addr_extended)?;
+    tree.add_alternatives(&addresses)?;
+
+    // fields of the auxiliary security header, selected by the security control field
+    let frame_counter = Structure::new("frame_counter")
+        .with_desc("Frame counter of the sender")
+        .add_u32_field("frame_counter");
+    let key_source_4 = Structure::new("key_source_4")
+        .with_desc("4 byte source of the key")
+        .add_u32_field("key_source");
+    let key_source_8 = Structure::new("key_source_8")
+        .with_desc("8 byte source of the key")
+        .add_u64_field("key_source");
+    let key_index = Structure::new("key_index")
+        .with_desc("Index of the key")
+        .add_u8_field("key_index");
+    tree.add_struct(&frame_counter)?;
+    tree.add_struct(&key_source_4)?;
+    tree.add_struct(&key_source_8)?;
+    tree.add_struct(&key_index)?;
+
+    let aux_sec_header = Structure::new("aux_sec_header")
+        .with_desc("Auxiliary security header")
+        .add_bitfield_of("security_control", &security_control)
+        .add_optional_field_if(
+            "frame_counter",
+            &frame_counter,
+            "security_control",
+            !Condition::set("Frame_counter_suppression"),
+        )
+        .add_optional_field_if(
+            "key_source_4",
+            &key_source_4,
+            "security_control",
+            Condition::is("Key_id_mode", &["Source_4"]),
+        )
+        .add_optional_field_if(
+            "key_source_8",
+            &key_source_8,
+            "security_control",
+            Condition::is("Key_id_mode", &["Source_8"]),
+        )
+        .add_optional_field_if(
+            "key_index",
+            &key_index,
+            "security_control",
+            Condition::is("Key_id_mode", &["Index", "Source_4", "Source_8"]),
+        );
+    tree.add_struct(&aux_sec_header)?;
+
+    let sequence_number = Structure::new("sequence_number")
+        .with_desc("Sequence number of the frame")
+        .add_u8_field("sequence_number");
+    let pan_id = Structure::new("pan_id")
+        .with_desc("PAN identifier")
+        .add_u16_field("pan_id");
+    tree.add_struct(&sequence_number)?;
+    tree.add_struct(&pan_id)?;
+
+    let header = Header {
+        frame_control: &frame_control,
+        sequence_number: &sequence_number,
+        pan_id: &pan_id,
+        address: addresses.get("address")?,
+        aux_sec_header: &aux_sec_header,
+    };
+    let mhr = header.add(Structure::new("mhr").with_desc("MAC header"));
+    tree.add_struct_with_alts(&mhr, &addresses)?;
+
+    let imm_ack = Structure::new("imm_ack")
+        .with_desc("Immediate acknowledgment frame")
+        .add_bitfield_of("frame_control", &frame_control)
+        .add_u8_field("sequence_number");
+    tree.add_struct(&fcs(imm_ack))?;
+
+    let gts_directions = Structure::new("gts_directions")
+        .with_desc("Directions of the GTS descriptors of a beacon")
+        .add_bitfield_of("directions", &gts_direction_mask);
+    tree.add_struct(&gts_directions)?;
+
+    let beacon_fields = Beacon {
+        superframe_spec: &superframe_spec,
+        gts_spec: &gts_spec,
+        gts_directions: &gts_directions,
+        pending_address_spec: &pending_address_spec,
+    };
+    let beacon = beacon_fields.add(
+        Structure::new("beacon")
+            .with_desc("Fields of a beacon following the MAC header, without the beacon payload"),
+    );
+    tree.add_struct(&beacon)?;
+
+    let beacon_frame =
+        beacon_fields.add(header.add(
+            Structure::new("beacon_frame").with_desc("Beacon frame without the beacon payload"),
+        ));
+    tree.add_struct_with_alts(&fcs(beacon_frame), &addresses)?;
+
+    // payloads of the MAC commands, selected by the command identifier
+    let association_request = Structure::new("association_request")
+        .add_bitfield_of("capability_information", &capability_information);
+    let association_response = Structure::new("association_response")
+        .add_u16_field_desc("short_address", "Short address allocated to the device")
+        .add_bitfield_of("association_status", &association_status);
+    let disassociation_notification = Structure::new("disassociation_notification")
+        .add_bitfield_of("disassociation_reason", &disassociation_reason);
+    let data_request = Structure::new("data_request");
+    let pan_id_conflict_notification = Structure::new("pan_id_conflict_notification");
+    let orphan_notification = Structure::new("orphan_notification");
+    let beacon_request = Structure::new("beacon_request");
+    let coordinator_realignment = Structure::new("coordinator_realignment")
+        .add_u16_field("pan_id")
+        .add_u16_field("coordinator_short_address")
+        .add_u8_field("channel_number")
+        .add_u16_field("short_address")
+        .add_u8_field("channel_page");
+    let gts_request =
+        Structure::new("gts_request").add_bitfield_of("gts_characteristics", &gts_characteristics);
+    let commands = Alternatives::new().insert_new_option("command", &data_request, |o| {
+        o.set_discriminant(&data_request, "Data_request")
+            .insert_type_discriminant(&association_request, "Association_request")
+            .insert_type_discriminant(&association_response, "Association_response")
+            .insert_type_discriminant(&disassociation_notification, "Disassociation_notification")
+            .insert_type_discriminant(
+                &pan_id_conflict_notification,
+                "Pan_id_conflict_notification",
+            )
+            .insert_type_discriminant(&orphan_notification, "Orphan_notification")
+            .insert_type_discriminant(&beacon_request, "Beacon_request")
+            .insert_type_discriminant(&coordinator_realignment, "Coordinator_realignment")
+            .insert_type_discriminant(&gts_request, "Gts_request")
+    });
+    for command in &[
+        &association_request,
+        &association_response,
+        &disassociation_notification,
+        &data_request,
+        &pan_id_conflict_notification,
+        &orphan_notification,
+        &beacon_request,
+        &coordinator_realignment,
+        &gts_request,
+    ] {
+        tree.add_struct(command)?;
+    }
+    tree.add_alternatives(&commands)?;
+
+    let mac_command = Structure::new("mac_command")
+        .with_desc("Payload of a MAC command frame")
+        .add_bitfield_of("command_id", &command_id)
+        .add_alt_field_discriminated(
+            "command",
+            commands.get("command")?,
+            "command_id",
+            "Command_id",
+        );
+    tree.add_struct_with_alts(&mac_command, &commands)?;
+
+    let command = commands.get("command")?;
+    let mac_command_frame = header
+        .add(Structure::new("mac_command_frame").with_desc("MAC command frame"))
+        .add_bitfield_of("command_id", &command_id)
+        .add_alt_field_discriminated("command", command, "command_id", "Command_id");
+    let alts = Alternatives::new().insert(header.address).insert(command);
+    tree.add_struct_with_alts(&fcs(mac_command_frame), &alts)?;
+
+    zigbee::define(tree)
+}
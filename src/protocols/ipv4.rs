@@ -0,0 +1,226 @@
+//! IPv4 (RFC 791) with its options, UDP (RFC 768) and ICMP (RFC 792) echo messages: the headers
+//! which precede the payload of a packet, e.g. after the MAC frame of a radio.
+//!
+//! The options of an IPv4 header follow it and are read using `Ipv4Option::read_from` until
+//! `ihl` words are read, after which the payload of the protocol of the header follows. The
+//! checksums of all headers cover bytes following them or a pseudo header, so they are held as
+//! plain fields which are left to the user to compute.
+
+use anyhow::Result;
+
+use crate::generate::bitfield::{BitField, BitOrder};
+use crate::generate::frame::Frames;
+use crate::generate::structure::Structure;
+use crate::tree::GenTree;
+use crate::util::ByteOrder;
+
+/// The protocols of the payload of a packet.
+const PROTOCOLS: &[(&str, u64)] = &[
+    ("Icmp", 1),
+    ("Igmp", 2),
+    ("Tcp", 6),
+    ("Udp", 17),
+    ("Ipv6", 41),
+    ("Gre", 47),
+    ("Esp", 50),
+    ("Ah", 51),
+    ("Icmpv6", 58),
+    ("Sctp", 132),
+    ("Udp_lite", 136),
+];
+
+/// The types of options along with whether they hold a length and data.
+const OPTIONS: &[(&str, u64, bool)] = &[
+    ("End_of_list", 0, false),
+    ("No_operation", 1, false),
+    ("Record_route", 7, true),
+    ("Timestamp", 68, true),
+    ("Security", 130, true),
+    ("Loose_source_route", 131, true),
+    ("Stream_id", 136, true),
+    ("Strict_source_route", 137, true),
+    ("Router_alert", 148, true),
+];
+
+/// The types of ICMP messages.
+const ICMP_TYPES: &[(&str, u64)] = &[
+    ("Echo_reply", 0),
+    ("Destination_unreachable", 3),
+    ("Source_quench", 4),
+    ("Redirect", 5),
+    ("Echo_request", 8),
+    ("Time_exceeded", 11),
+    ("Parameter_problem", 12),
+    ("Timestamp", 13),
+    ("Timestamp_reply", 14),
+];
+
+/// Capacity of the data of an option, the longest options filling all 40 bytes.
+const OPTION_CAPACITY: u32 = 38;
+
+/// Start an echo message of the ICMP type `value`.
+fn echo(name: &str, desc: &str, value: u64) -> Structure {
+    Structure::new(name)
+        .with_desc(desc)
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_const_field("icmp_type", 1, value)
+        .add_const_field("code", 1, 0)
+        .add_u16_field_desc("checksum", "Internet checksum of the message")
+        .add_u16_field_desc("identifier", "Identifier matching replies to requests")
+        .add_u16_field_desc("sequence", "Sequence number matching replies to requests")
+}
+
+/// Add the definitions of IPv4, UDP and ICMP to `tree`.
+pub fn define(tree: &mut GenTree) -> Result<()> {
+    let version_ihl = BitField::new("version_ihl", "Version and header length")
+        .with_bit_order(BitOrder::MsbFirst)
+        .add_bit_field_default("Version", "Version of the protocol", 4, 4, |f| {
+            f.add_enum_value("V4", 4)
+        })
+        .add_bit_field_default(
+            "Ihl",
+            "Length of the header including its options in 32-bit words",
+            4,
+            5,
+            |f| f.numeric(),
+        );
+    tree.add_bitfield(&version_ihl)?;
+
+    let tos = BitField::new("tos", "Type of service")
+        .with_bit_order(BitOrder::MsbFirst)
+        .add_bit_field("Dscp", "Differentiated services code point", 6, |f| {
+            f.numeric()
+        })
+        .add_bit_field("Ecn", "Explicit congestion notification", 2, |f| {
+            f.add_enum_value_desc("Not_ect", "Not capable of ECN", 0)
+                .add_enum_value_desc("Ect1", "Capable of ECN", 1)
+                .add_enum_value_desc("Ect0", "Capable of ECN", 2)
+                .add_enum_value_desc("Ce", "Congestion was experienced", 3)
+        });
+    tree.add_bitfield(&tos)?;
+
+    let fragment = BitField::new("fragment", "Flags and offset of a fragment")
+        .with_bit_order(BitOrder::MsbFirst)
+        .add_reserved(1)
+        .add_bit_field("Df", "The packet must not be fragmented", 1, |f| f)
+        .add_bit_field("Mf", "More fragments follow", 1, |f| f)
+        .add_bit_field(
+            "Fragment_offset",
+            "Offset of the fragment in 8 byte units",
+            13,
+            |f| f.numeric(),
+        );
+    tree.add_bitfield(&fragment)?;
+
+    let protocol = BitField::new("protocol", "Protocol of the payload").add_bit_field(
+        "Protocol",
+        "Protocol of the payload",
+        8,
+        |f| {
+            PROTOCOLS
+                .iter()
+                .fold(f, |f, (variant, value)| f.add_enum_value(variant, *value))
+        },
+    );
+    tree.add_bitfield(&protocol)?;
+
+    let option_type = BitField::new("option_type", "Type of an option").add_bit_field(
+        "Option_type",
+        "Type of the option, with the copied flag, class and number",
+        8,
+        |f| {
+            OPTIONS.iter().fold(f, |f, (variant, value, _)| {
+                f.add_enum_value(variant, *value)
+            })
+        },
+    );
+    tree.add_bitfield(&option_type)?;
+
+    let icmp_type = BitField::new("icmp_type", "Type of an ICMP message").add_bit_field(
+        "Icmp_type",
+        "Type of the message",
+        8,
+        |f| {
+            ICMP_TYPES
+                .iter()
+                .fold(f, |f, (variant, value)| f.add_enum_value(variant, *value))
+        },
+    );
+    tree.add_bitfield(&icmp_type)?;
+
+    let ipv4_header = Structure::new("ipv4_header")
+        .with_desc("Header of a packet, followed by its options")
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_bitfield_of("version_ihl", &version_ihl)
+        .add_bitfield_of("tos", &tos)
+        .add_u16_field_desc("total_length", "Length of the packet including its header")
+        .add_u16_field_desc("identification", "Identifier of the fragments of a packet")
+        .add_bitfield_of("fragment", &fragment)
+        .add_u8_field_desc("ttl", "Number of hops the packet may still take")
+        .with_default("ttl", 64)
+        .add_bitfield_of("protocol", &protocol)
+        .add_u16_field_desc("header_checksum", "Internet checksum of the header")
+        .add_array_field("source", 1, 4)
+        .add_array_field("destination", 1, 4);
+    tree.add_struct(&ipv4_header)?;
+
+    // every option is its own structure, as a frame may only be selected by a single type
+    let mut ipv4_option = Frames::new("ipv4_option", "option_type", 1, "Option_type")
+        .with_desc("Option of a packet, selected by its type.")
+        .with_byte_order(ByteOrder::BigEndian)
+        .with_parse_fn("parse_option");
+    for (variant, value, tlv) in OPTIONS {
+        let option = Structure::new(&format!("option_{}", variant.to_lowercase()))
+            .with_byte_order(ByteOrder::BigEndian)
+            .add_const_field("option_type", 1, *value);
+        let option = if *tlv {
+            option
+                .with_desc("Option with a length and data")
+                .add_len_field_extra("length", 1, 2)
+                .add_bytes_field("data", "length", OPTION_CAPACITY)
+        } else {
+            option.with_desc("Option of a single byte")
+        };
+        tree.add_struct(&option)?;
+        ipv4_option = ipv4_option.add_frame(variant, &option);
+    }
+    tree.add_frames(&ipv4_option)?;
+
+    let udp_header = Structure::new("udp_header")
+        .with_desc("Header of a datagram, followed by its payload")
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_u16_field("source_port")
+        .add_u16_field("destination_port")
+        .add_u16_field_desc("length", "Length of the datagram including its header")
+        .add_u16_field_desc(
+            "checksum",
+            "Internet checksum of a pseudo header and the datagram",
+        );
+    tree.add_struct(&udp_header)?;
+
+    let icmp_header = Structure::new("icmp_header")
+        .with_desc("Header of an ICMP message of any type")
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_bitfield_of("icmp_type", &icmp_type)
+        .add_u8_field_desc("code", "Subtype of the message")
+        .add_u16_field_desc("checksum", "Internet checksum of the message")
+        .add_u32_field_desc("rest", "Rest of the header depending on the type");
+    tree.add_struct(&icmp_header)?;
+
+    let echo_request = echo("echo_request", "Echo request, followed by its data", 8);
+    tree.add_struct(&echo_request)?;
+    let echo_reply = echo(
+        "echo_reply",
+        "Echo reply, followed by the data of the request",
+        0,
+    );
+    tree.add_struct(&echo_reply)?;
+
+    let icmp_message = Frames::new("icmp_message", "icmp_type", 1, "Icmp_type")
+        .with_desc("Echo message, selected by its type.")
+        .with_byte_order(ByteOrder::BigEndian)
+        .with_parse_fn("parse_icmp")
+        .add_frame("Echo_request", &echo_request)
+        .add_frame("Echo_reply", &echo_reply);
+    tree.add_frames(&icmp_message)
+}
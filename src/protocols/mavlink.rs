@@ -0,0 +1,381 @@
+//! MAVLink 2: the frame of a message with its header, payload, CRC and signature, along with the
+//! payload structures and message ids of a message table such as the common messages.
+//!
+//! The payload of a frame is kept as bytes counted by its length and read as the structure of
+//! its message id. The CRC of a frame covers its header and payload followed by the `CRC_EXTRA`
+//! of the message, so frames of messages missing from the table fail to read and write. Senders
+//! may truncate the trailing zero bytes of a payload, which must be restored by the user before
+//! reading its structure, e.g. by reading it from a zeroed buffer of the payload's `SIZE`.
+//! Frames hold their payload in a `heapless::Vec`, so they do not copy 255 bytes around.
+//! Signed, floating point and character fields are held as the unsigned integers of their
+//! bits, e.g. `f32::from_bits(attitude.roll().read())`.
+
+use anyhow::{bail, Result};
+
+use crate::generate::bitfield::{BitField, Enumeration};
+use crate::generate::checksum::{ChecksumAlgorithm, ChecksumExtra};
+use crate::generate::structure::{Epoch, Payloads, Structure};
+use crate::tree::GenTree;
+
+/// Seconds from the Unix epoch to the epoch of the timestamps of signatures, 2015-01-01.
+const SIGNATURE_EPOCH: i64 = 1_420_070_400;
+
+/// A message of a message table as declared by a MAVLink dialect.
+pub struct Message {
+    /// Name of the message in upper case, which is part of its `CRC_EXTRA`.
+    pub name: &'static str,
+    pub id: u32,
+    pub desc: &'static str,
+    /// The fields in their declared order, each by its C type, e.g. `uint16_t` or `char[16]`,
+    /// and its name.
+    pub fields: &'static [(&'static str, &'static str)],
+    /// Extension fields, which follow the other fields in their declared order and are not
+    /// part of the `CRC_EXTRA`.
+    pub extensions: &'static [(&'static str, &'static str)],
+}
+
+impl Message {
+    /// The fields in their order on the wire, which sorts the fields by the size of their type,
+    /// largest first, followed by the extension fields.
+    pub fn wire_fields(&self) -> Result<Vec<(&'static str, u32, u32, &'static str)>> {
+        let mut fields = self
+            .fields
+            .iter()
+            .map(|(ty, name)| field_type(ty).map(|(ty, size, count)| (ty, size, count, *name)))
+            .collect::<Result<Vec<_>>>()?;
+        fields.sort_by_key(|(_, size, _, _)| std::cmp::Reverse(*size));
+        for (ty, name) in self.extensions {
+            let (ty, size, count) = field_type(ty)?;
+            fields.push((ty, size, count, name));
+        }
+        Ok(fields)
+    }
+
+    /// The `CRC_EXTRA` of the message, which covers its name and the types and names of the
+    /// fields besides the extension fields.
+    pub fn crc_extra(&self) -> Result<u8> {
+        let mut crc = x25(0xffff, format!("{} ", self.name).as_bytes());
+        for (ty, _, count, name) in self.wire_fields()?.iter().take(self.fields.len()) {
+            crc = x25(crc, format!("{} ", ty).as_bytes());
+            crc = x25(crc, format!("{} ", name).as_bytes());
+            if *count > 0 {
+                crc = x25(crc, &[*count as u8]);
+            }
+        }
+        Ok(((crc & 0xff) ^ (crc >> 8)) as u8)
+    }
+
+    /// The structure of the payload of the message, named after the message.
+    pub fn payload(&self) -> Result<Structure> {
+        Ok(self.wire_fields()?.into_iter().fold(
+            Structure::new(&self.name.to_lowercase()).with_desc(self.desc),
+            |s, (_, size, count, name)| match count {
+                0 => s.add_prim_field(name, size),
+                _ => s.add_array_field(name, size, count),
+            },
+        ))
+    }
+}
+
+/// The base type, its size and the number of elements of the C type `ty` of a field, which is
+/// 0 for fields other than arrays. `uint8_t_mavlink_version` is a `uint8_t` set by the protocol.
+fn field_type(ty: &'static str) -> Result<(&'static str, u32, u32)> {
+    let (base, count) = match ty.strip_suffix(']').and_then(|ty| ty.split_once('[')) {
+        Some((base, count)) => match count.parse::<u32>() {
+            Ok(count) if count > 0 && count < 256 => (base, count),
+            _ => bail!("invalid array length of type `{}`", ty),
+        },
+        None => (ty, 0),
+    };
+    let (base, size) = match base {
+        "uint8_t_mavlink_version" => ("uint8_t", 1),
+        "uint8_t" | "int8_t" | "char" => (base, 1),
+        "uint16_t" | "int16_t" => (base, 2),
+        "uint32_t" | "int32_t" | "float" => (base, 4),
+        "uint64_t" | "int64_t" | "double" => (base, 8),
+        _ => bail!("unknown field type `{}`", ty),
+    };
+    Ok((base, size, count))
+}
+
+/// Update the CRC-16/MCRF4XX `crc` with `bytes`.
+fn x25(crc: u16, bytes: &[u8]) -> u16 {
+    bytes.iter().fold(crc, |crc, byte| {
+        let tmp = byte ^ (crc as u8);
+        let tmp = (tmp ^ (tmp << 4)) as u16;
+        (crc >> 8) ^ (tmp << 8) ^ (tmp << 3) ^ (tmp >> 4)
+    })
+}
+
+/// Declare the message `name` without extension fields.
+const fn message(
+    name: &'static str,
+    id: u32,
+    desc: &'static str,
+    fields: &'static [(&'static str, &'static str)],
+) -> Message {
+    Message {
+        name,
+        id,
+        desc,
+        fields,
+        extensions: &[],
+    }
+}
+
+/// A selection of the common messages.
+pub const COMMON: &[Message] = &[
+    message(
+        "HEARTBEAT",
+        0,
+        "Presence of a system along with its type and state",
+        &[
+            ("uint8_t", "type"),
+            ("uint8_t", "autopilot"),
+            ("uint8_t", "base_mode"),
+            ("uint32_t", "custom_mode"),
+            ("uint8_t", "system_status"),
+            ("uint8_t_mavlink_version", "mavlink_version"),
+        ],
+    ),
+    Message {
+        name: "SYS_STATUS",
+        id: 1,
+        desc: "General system state",
+        fields: &[
+            ("uint32_t", "onboard_control_sensors_present"),
+            ("uint32_t", "onboard_control_sensors_enabled"),
+            ("uint32_t", "onboard_control_sensors_health"),
+            ("uint16_t", "load"),
+            ("uint16_t", "voltage_battery"),
+            ("int16_t", "current_battery"),
+            ("int8_t", "battery_remaining"),
+            ("uint16_t", "drop_rate_comm"),
+            ("uint16_t", "errors_comm"),
+            ("uint16_t", "errors_count1"),
+            ("uint16_t", "errors_count2"),
+            ("uint16_t", "errors_count3"),
+            ("uint16_t", "errors_count4"),
+        ],
+        extensions: &[
+            ("uint32_t", "onboard_control_sensors_present_extended"),
+            ("uint32_t", "onboard_control_sensors_enabled_extended"),
+            ("uint32_t", "onboard_control_sensors_health_extended"),
+        ],
+    },
+    message(
+        "SYSTEM_TIME",
+        2,
+        "System time of the sender",
+        &[("uint64_t", "time_unix_usec"), ("uint32_t", "time_boot_ms")],
+    ),
+    message(
+        "PING",
+        4,
+        "Ping measuring the latency",
+        &[
+            ("uint64_t", "time_usec"),
+            ("uint32_t", "seq"),
+            ("uint8_t", "target_system"),
+            ("uint8_t", "target_component"),
+        ],
+    ),
+    message(
+        "PARAM_REQUEST_READ",
+        20,
+        "Request for the value of a parameter",
+        &[
+            ("uint8_t", "target_system"),
+            ("uint8_t", "target_component"),
+            ("char[16]", "param_id"),
+            ("int16_t", "param_index"),
+        ],
+    ),
+    message(
+        "PARAM_REQUEST_LIST",
+        21,
+        "Request for all parameters",
+        &[
+            ("uint8_t", "target_system"),
+            ("uint8_t", "target_component"),
+        ],
+    ),
+    message(
+        "PARAM_VALUE",
+        22,
+        "Value of a parameter",
+        &[
+            ("char[16]", "param_id"),
+            ("float", "param_value"),
+            ("uint8_t", "param_type"),
+            ("uint16_t", "param_count"),
+            ("uint16_t", "param_index"),
+        ],
+    ),
+    message(
+        "PARAM_SET",
+        23,
+        "Request to set a parameter",
+        &[
+            ("uint8_t", "target_system"),
+            ("uint8_t", "target_component"),
+            ("char[16]", "param_id"),
+            ("float", "param_value"),
+            ("uint8_t", "param_type"),
+        ],
+    ),
+    Message {
+        name: "GPS_RAW_INT",
+        id: 24,
+        desc: "Position as reported by the GPS receiver",
+        fields: &[
+            ("uint64_t", "time_usec"),
+            ("uint8_t", "fix_type"),
+            ("int32_t", "lat"),
+            ("int32_t", "lon"),
+            ("int32_t", "alt"),
+            ("uint16_t", "eph"),
+            ("uint16_t", "epv"),
+            ("uint16_t", "vel"),
+            ("uint16_t", "cog"),
+            ("uint8_t", "satellites_visible"),
+        ],
+        extensions: &[
+            ("int32_t", "alt_ellipsoid"),
+            ("uint32_t", "h_acc"),
+            ("uint32_t", "v_acc"),
+            ("uint32_t", "vel_acc"),
+            ("uint32_t", "hdg_acc"),
+            ("uint16_t", "yaw"),
+        ],
+    },
+    message(
+        "ATTITUDE",
+        30,
+        "Attitude in the aeronautical frame",
+        &[
+            ("uint32_t", "time_boot_ms"),
+            ("float", "roll"),
+            ("float", "pitch"),
+            ("float", "yaw"),
+            ("float", "rollspeed"),
+            ("float", "pitchspeed"),
+            ("float", "yawspeed"),
+        ],
+    ),
+    message(
+        "GLOBAL_POSITION_INT",
+        33,
+        "Filtered global position",
+        &[
+            ("uint32_t", "time_boot_ms"),
+            ("int32_t", "lat"),
+            ("int32_t", "lon"),
+            ("int32_t", "alt"),
+            ("int32_t", "relative_alt"),
+            ("int16_t", "vx"),
+            ("int16_t", "vy"),
+            ("int16_t", "vz"),
+            ("uint16_t", "hdg"),
+        ],
+    ),
+    message(
+        "COMMAND_LONG",
+        76,
+        "Command with up to seven parameters",
+        &[
+            ("uint8_t", "target_system"),
+            ("uint8_t", "target_component"),
+            ("uint16_t", "command"),
+            ("uint8_t", "confirmation"),
+            ("float", "param1"),
+            ("float", "param2"),
+            ("float", "param3"),
+            ("float", "param4"),
+            ("float", "param5"),
+            ("float", "param6"),
+            ("float", "param7"),
+        ],
+    ),
+    Message {
+        name: "COMMAND_ACK",
+        id: 77,
+        desc: "Result of a command",
+        fields: &[("uint16_t", "command"), ("uint8_t", "result")],
+        extensions: &[
+            ("uint8_t", "progress"),
+            ("int32_t", "result_param2"),
+            ("uint8_t", "target_system"),
+            ("uint8_t", "target_component"),
+        ],
+    },
+    Message {
+        name: "STATUSTEXT",
+        id: 253,
+        desc: "Status text to be displayed",
+        fields: &[("uint8_t", "severity"), ("char[50]", "text")],
+        extensions: &[("uint16_t", "id"), ("uint8_t", "chunk_seq")],
+    },
+];
+
+/// Add the frame of MAVLink 2 to `tree` along with the payloads and message ids of `messages`,
+/// e.g. the messages of a dialect of your own.
+pub fn define_dialect(tree: &mut GenTree, messages: &[Message]) -> Result<()> {
+    let message_id = messages.iter().fold(
+        Enumeration::new("message_id", "Id of a message", 24),
+        |e, message| e.add_enum_value_desc(message.name, message.desc, message.id as u64),
+    );
+    tree.add_enumeration(&message_id)?;
+
+    let mut crc_extra = ChecksumExtra::new("msgid");
+    for message in messages {
+        tree.add_struct(&message.payload()?)?;
+        crc_extra = crc_extra.add_value(message.id as u64, message.crc_extra()?);
+    }
+
+    let incompat_flags = BitField::new("incompat_flags", "Flags which must be understood")
+        .add_bit_field("Signed", "The frame is followed by a signature", 1, |f| {
+            f.add_enum_value("Unsigned", 0).add_enum_value("Signed", 1)
+        })
+        .add_reserved(7);
+    tree.add_bitfield(&incompat_flags)?;
+
+    let signature = Structure::new("signature")
+        .with_desc("Signature authenticating a frame")
+        .add_u8_field_desc("link_id", "Channel the frame was sent on")
+        .add_timestamp_field_desc(
+            "timestamp",
+            "Time in units of 10 µs since 2015",
+            6,
+            Epoch::Custom(SIGNATURE_EPOCH),
+            100_000,
+        )
+        .add_u48_field_desc("signature", "First six bytes of the SHA-256 of the frame");
+    tree.add_struct(&signature)?;
+
+    let frame = Structure::new("frame")
+        .with_desc("Frame of a message")
+        .with_payloads(Payloads::Heapless)
+        .add_const_field("magic", 1, 0xfd)
+        .add_len_field("len", 1)
+        .add_bitfield_of("incompat_flags", &incompat_flags)
+        .add_u8_field_desc("compat_flags", "Flags which may be ignored")
+        .add_u8_field_desc("seq", "Sequence number of the sender")
+        .add_u8_field_desc("sysid", "System sending the frame")
+        .add_u8_field_desc("compid", "Component sending the frame")
+        .add_u24_field_desc("msgid", "Id of the message of the payload")
+        .add_bytes_field("payload", "len", 255)
+        .add_checksum_field_extra(
+            "checksum",
+            "len",
+            ChecksumAlgorithm::Crc16Mcrf4xx,
+            &crc_extra,
+        )
+        .add_optional_field("signature", &signature, "incompat_flags", "Signed");
+    tree.add_struct(&frame)
+}
+
+/// Add the definitions of MAVLink 2 with the common messages to `tree`.
+pub fn define(tree: &mut GenTree) -> Result<()> {
+    define_dialect(tree, COMMON)
+}
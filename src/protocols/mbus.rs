@@ -0,0 +1,334 @@
+//! M-Bus (EN 13757-2/-3) and wireless M-Bus (EN 13757-4) metering: the single character, short
+//! and long frames of the wired link layer, the link layer header of a wireless frame, the
+//! transport headers selected by the CI field and the data information and value information
+//! blocks of the data records.
+//!
+//! The user data of a long frame and the bytes following the header of a wireless frame start
+//! with the CI field, and are read using `TransportHeader::read_from`. The data records follow
+//! the transport header, each read as a `Dib` with a DIF and its extensions, a `Vib` with a VIF
+//! and its extensions unless the DIF is a special function, and the data whose length is given
+//! by the `Data` field of the DIF. The CRCs of the blocks of a wireless frame are usually
+//! stripped by the receiver, so they are left to the user, as is the `length_repeated` of a
+//! long frame which must equal its `length`.
+
+use anyhow::Result;
+
+use crate::generate::bitfield::BitField;
+use crate::generate::checksum::ChecksumAlgorithm;
+use crate::generate::frame::Frames;
+use crate::generate::structure::Structure;
+use crate::tree::GenTree;
+
+/// The CI fields of the transport layer.
+const CI_FIELDS: &[(&str, u64)] = &[
+    ("Application_reset", 0x50),
+    ("Data_send", 0x51),
+    ("Selection", 0x52),
+    ("Application_error", 0x70),
+    ("Alarm", 0x71),
+    ("Response_long", 0x72),
+    ("Response_none", 0x78),
+    ("Response_short", 0x7a),
+    ("Ell_short", 0x8c),
+    ("Ell_long", 0x8d),
+    ("Afl", 0x90),
+];
+
+/// The media of meters.
+const DEVICE_TYPES: &[(&str, u64)] = &[
+    ("Other", 0x00),
+    ("Oil", 0x01),
+    ("Electricity", 0x02),
+    ("Gas", 0x03),
+    ("Heat", 0x04),
+    ("Steam", 0x05),
+    ("Warm_water", 0x06),
+    ("Water", 0x07),
+    ("Heat_cost_allocator", 0x08),
+    ("Compressed_air", 0x09),
+    ("Cooling_outlet", 0x0a),
+    ("Cooling_inlet", 0x0b),
+    ("Heat_inlet", 0x0c),
+    ("Heat_cooling", 0x0d),
+    ("Bus_system", 0x0e),
+    ("Unknown", 0x0f),
+    ("Hot_water", 0x15),
+    ("Cold_water", 0x16),
+    ("Dual_water", 0x17),
+    ("Pressure", 0x18),
+    ("Ad_converter", 0x19),
+    ("Smoke_detector", 0x1a),
+    ("Room_sensor", 0x1b),
+    ("Gas_detector", 0x1c),
+    ("Breaker", 0x20),
+    ("Valve", 0x21),
+    ("Customer_unit", 0x25),
+    ("Waste_water", 0x28),
+    ("Garbage", 0x29),
+    ("Communication_controller", 0x31),
+    ("Unidirectional_repeater", 0x32),
+    ("Bidirectional_repeater", 0x33),
+    ("Radio_converter_system", 0x36),
+    ("Radio_converter_meter", 0x37),
+];
+
+/// The control fields of wireless frames.
+const WIRELESS_CONTROLS: &[(&str, u64)] = &[
+    ("Ack", 0x00),
+    ("Nack", 0x01),
+    ("Cnf_ir", 0x06),
+    ("Rsp_ud", 0x08),
+    ("Snd_nke", 0x40),
+    ("Snd_ud", 0x53),
+    ("Snd_nr", 0x44),
+    ("Snd_ir", 0x46),
+    ("Acc_nr", 0x47),
+    ("Acc_dmd", 0x48),
+    ("Req_ud1", 0x5a),
+    ("Req_ud2", 0x5b),
+];
+
+/// Capacity of the user data of a long frame, which the length also counts the control and
+/// address fields of.
+const USER_DATA_CAPACITY: u32 = 253;
+
+/// The most bytes of a DIB or VIB, a DIF or VIF with up to ten extensions.
+const BLOCK_CAPACITY: u32 = 11;
+
+/// Add the definitions of M-Bus to `tree`.
+pub fn define(tree: &mut GenTree) -> Result<()> {
+    let start = BitField::new("start", "First byte of a wired frame").add_bit_field(
+        "Start",
+        "Kind of the frame",
+        8,
+        |f| {
+            f.add_enum_value("Short", 0x10)
+                .add_enum_value("Long", 0x68)
+                .add_enum_value("Ack", 0xe5)
+        },
+    );
+    tree.add_bitfield(&start)?;
+
+    let control = BitField::new("control", "Control field of a wired frame")
+        .add_bit_field("Function", "Function of the frame", 4, |f| {
+            f.add_enum_value_desc("Snd_nke", "Initialise the slave", 0x0)
+                .add_enum_value_desc("Snd_ud", "Send user data to the slave", 0x3)
+                .add_enum_value_desc("Rsp_ud", "Respond with user data", 0x8)
+                .add_enum_value_desc("Req_ud1", "Request class 1 data", 0xa)
+                .add_enum_value_desc("Req_ud2", "Request class 2 data", 0xb)
+        })
+        .add_bit_field(
+            "Fcv_dfc",
+            "The frame count bit is valid, or the slave cannot accept data",
+            1,
+            |f| f,
+        )
+        .add_bit_field(
+            "Fcb_acd",
+            "Frame count bit, or class 1 data is available",
+            1,
+            |f| f,
+        )
+        .add_bit_field("Prm", "Direction of the frame", 1, |f| {
+            f.add_enum_value("Slave_to_master", 0)
+                .add_enum_value("Master_to_slave", 1)
+        })
+        .add_reserved(1);
+    tree.add_bitfield(&control)?;
+
+    let wireless_control = BitField::new("wireless_control", "Control field of a wireless frame")
+        .add_bit_field("Control", "Function of the frame", 8, |f| {
+            WIRELESS_CONTROLS
+                .iter()
+                .fold(f, |f, (variant, value)| f.add_enum_value(variant, *value))
+        });
+    tree.add_bitfield(&wireless_control)?;
+
+    let ci = BitField::new("ci", "Control information field").add_bit_field(
+        "Ci",
+        "Kind of the transport header and data following",
+        8,
+        |f| {
+            CI_FIELDS
+                .iter()
+                .fold(f, |f, (variant, value)| f.add_enum_value(variant, *value))
+        },
+    );
+    tree.add_bitfield(&ci)?;
+
+    let manufacturer = BitField::new("manufacturer", "Manufacturer of a meter")
+        .add_bit_field("Third", "Third letter, less 64", 5, |f| f.numeric())
+        .add_bit_field("Second", "Second letter, less 64", 5, |f| f.numeric())
+        .add_bit_field("First", "First letter, less 64", 5, |f| f.numeric())
+        .add_reserved(1);
+    tree.add_bitfield(&manufacturer)?;
+
+    let device_type = BitField::new("device_type", "Medium measured by a meter").add_bit_field(
+        "Device_type",
+        "Medium of the meter",
+        8,
+        |f| {
+            DEVICE_TYPES
+                .iter()
+                .fold(f, |f, (variant, value)| f.add_enum_value(variant, *value))
+        },
+    );
+    tree.add_bitfield(&device_type)?;
+
+    let status = BitField::new("status", "Status of a meter")
+        .add_bit_field("Application", "Status of the application", 2, |f| {
+            f.add_enum_value("No_error", 0)
+                .add_enum_value("Busy", 1)
+                .add_enum_value("Error", 2)
+                .add_enum_value("Alarm", 3)
+        })
+        .add_bit_field("Power_low", "The power is low", 1, |f| f)
+        .add_bit_field("Permanent_error", "A permanent error occurred", 1, |f| f)
+        .add_bit_field("Temporary_error", "A temporary error occurred", 1, |f| f)
+        .add_bit_field("Manufacturer", "Specific to the manufacturer", 3, |f| {
+            f.numeric()
+        });
+    tree.add_bitfield(&status)?;
+
+    let dif = BitField::new("dif", "Data information field, the first byte of a DIB")
+        .add_bit_field("Data", "Coding and length of the data", 4, |f| {
+            f.add_enum_value_desc("No_data", "No data", 0x0)
+                .add_enum_value_desc("Int8", "8 bit integer", 0x1)
+                .add_enum_value_desc("Int16", "16 bit integer", 0x2)
+                .add_enum_value_desc("Int24", "24 bit integer", 0x3)
+                .add_enum_value_desc("Int32", "32 bit integer", 0x4)
+                .add_enum_value_desc("Real32", "32 bit real", 0x5)
+                .add_enum_value_desc("Int48", "48 bit integer", 0x6)
+                .add_enum_value_desc("Int64", "64 bit integer", 0x7)
+                .add_enum_value_desc("Selection", "Selection for readout, no data", 0x8)
+                .add_enum_value_desc("Bcd2", "2 digit BCD in 1 byte", 0x9)
+                .add_enum_value_desc("Bcd4", "4 digit BCD in 2 bytes", 0xa)
+                .add_enum_value_desc("Bcd6", "6 digit BCD in 3 bytes", 0xb)
+                .add_enum_value_desc("Bcd8", "8 digit BCD in 4 bytes", 0xc)
+                .add_enum_value_desc("Variable", "Preceded by its length", 0xd)
+                .add_enum_value_desc("Bcd12", "12 digit BCD in 6 bytes", 0xe)
+                .add_enum_value_desc("Special", "A special function", 0xf)
+        })
+        .add_bit_field("Function", "Function of the value", 2, |f| {
+            f.add_enum_value("Instantaneous", 0)
+                .add_enum_value("Maximum", 1)
+                .add_enum_value("Minimum", 2)
+                .add_enum_value("Error", 3)
+        })
+        .add_bit_field("Storage", "Lowest bit of the storage number", 1, |f| {
+            f.numeric()
+        })
+        .add_bit_field("Extension", "A DIFE follows", 1, |f| f);
+    tree.add_bitfield(&dif)?;
+
+    let dife = BitField::new("dife", "Data information field extension")
+        .add_bit_field("Storage", "Next four bits of the storage number", 4, |f| {
+            f.numeric()
+        })
+        .add_bit_field("Tariff", "Next two bits of the tariff", 2, |f| f.numeric())
+        .add_bit_field("Subunit", "Next bit of the subunit", 1, |f| f.numeric())
+        .add_bit_field("Extension", "Another DIFE follows", 1, |f| f);
+    tree.add_bitfield(&dife)?;
+
+    let vif = BitField::new("vif", "Value information field, the first byte of a VIB")
+        .add_bit_field(
+            "Value",
+            "Unit and multiplier, or the table of the first VIFE",
+            7,
+            |f| f.numeric(),
+        )
+        .add_bit_field("Extension", "A VIFE follows", 1, |f| f);
+    tree.add_bitfield(&vif)?;
+
+    let ack = Structure::new("ack")
+        .with_desc("Single character acknowledging a frame")
+        .add_const_field("start", 1, 0xe5);
+
+    let short_frame = Structure::new("short_frame")
+        .with_desc("Short frame without user data, e.g. a request")
+        .add_const_field("start", 1, 0x10)
+        .add_bitfield_of("control", &control)
+        .add_u8_field_desc("address", "Primary address of the slave")
+        .add_checksum_field("checksum", "control", ChecksumAlgorithm::Sum8)
+        .add_const_field("stop", 1, 0x16);
+
+    let long_frame = Structure::new("long_frame")
+        .with_desc("Long frame with user data starting with the CI field")
+        .add_const_field("start", 1, 0x68)
+        .add_len_field_extra("length", 1, 2)
+        .add_u8_field_desc("length_repeated", "Repetition of the length")
+        .add_const_field("start_repeated", 1, 0x68)
+        .add_bitfield_of("control", &control)
+        .add_u8_field_desc("address", "Primary address of the slave")
+        .add_bytes_field("user_data", "length", USER_DATA_CAPACITY)
+        .add_checksum_field("checksum", "control", ChecksumAlgorithm::Sum8)
+        .add_const_field("stop", 1, 0x16);
+
+    let mut mbus_frame = Frames::new("mbus_frame", "start", 1, "Start")
+        .with_desc("Wired frame, selected by its first byte.")
+        .with_parse_fn("parse_frame");
+    for (variant, frame) in [
+        ("Ack", &ack),
+        ("Short", &short_frame),
+        ("Long", &long_frame),
+    ] {
+        tree.add_struct(frame)?;
+        mbus_frame = mbus_frame.add_frame(variant, frame);
+    }
+    tree.add_frames(&mbus_frame)?;
+
+    let wireless_header = Structure::new("wireless_header")
+        .with_desc("Link layer header of a wireless frame, followed by the CI field")
+        .add_u8_field_desc("length", "Number of bytes following, without the CRCs")
+        .add_bitfield_of("control", &wireless_control)
+        .add_bitfield_of("manufacturer", &manufacturer)
+        .add_bcd_field_desc("identification", "Identification number of the meter", 4)
+        .add_u8_field_desc("version", "Version of the meter")
+        .add_bitfield_of("device_type", &device_type);
+    tree.add_struct(&wireless_header)?;
+
+    let no_header = Structure::new("no_header")
+        .with_desc("Response without a transport header")
+        .add_const_field("ci", 1, 0x78);
+
+    let short_header = Structure::new("short_header")
+        .with_desc("Response with a short transport header")
+        .add_const_field("ci", 1, 0x7a)
+        .add_u8_field_desc("access_number", "Number of the transmission")
+        .add_bitfield_of("status", &status)
+        .add_u16_field_desc("configuration", "Configuration, e.g. the encryption mode");
+
+    let long_header = Structure::new("long_header")
+        .with_desc("Response with a long transport header identifying the meter")
+        .add_const_field("ci", 1, 0x72)
+        .add_bcd_field_desc("identification", "Identification number of the meter", 4)
+        .add_bitfield_of("manufacturer", &manufacturer)
+        .add_u8_field_desc("version", "Version of the meter")
+        .add_bitfield_of("device_type", &device_type)
+        .add_u8_field_desc("access_number", "Number of the transmission")
+        .add_bitfield_of("status", &status)
+        .add_u16_field_desc("configuration", "Configuration, e.g. the encryption mode");
+
+    let mut transport_header = Frames::new("transport_header", "ci", 1, "Ci")
+        .with_desc("Transport header of a response, selected by its CI field.")
+        .with_parse_fn("parse_transport_header");
+    for (variant, header) in [
+        ("Response_none", &no_header),
+        ("Response_short", &short_header),
+        ("Response_long", &long_header),
+    ] {
+        tree.add_struct(header)?;
+        transport_header = transport_header.add_frame(variant, header);
+    }
+    tree.add_frames(&transport_header)?;
+
+    let dib = Structure::new("dib")
+        .with_desc("Data information block of a data record")
+        .add_extended_field("dif", BLOCK_CAPACITY);
+    tree.add_struct(&dib)?;
+
+    let vib = Structure::new("vib")
+        .with_desc("Value information block of a data record")
+        .add_extended_field("vif", BLOCK_CAPACITY);
+    tree.add_struct(&vib)
+}
@@ -0,0 +1,129 @@
+//! Definitions of common protocols, which are generated by `prot2rust gen <protocol>` or may be
+//! added to a [`GenTree`] along with definitions of your own.
+
+use anyhow::{bail, Result};
+
+use crate::tree::GenTree;
+
+pub mod artnet;
+pub mod ble;
+pub mod coap;
+pub mod crsf;
+pub mod dns;
+pub mod ethernet;
+pub mod ieee802154;
+pub mod ipv4;
+pub mod mavlink;
+pub mod mbus;
+pub mod modbus;
+pub mod mqttsn;
+pub mod sbus;
+pub mod ubx;
+pub mod usb;
+pub mod zigbee;
+
+/// A built-in protocol definition.
+pub struct Protocol {
+    /// Name selecting the protocol on the command line, also used as the crate name.
+    pub name: &'static str,
+    pub desc: &'static str,
+    /// Add the definitions of the protocol to a tree.
+    pub define: fn(&mut GenTree) -> Result<()>,
+}
+
+/// All built-in protocols.
+pub const PROTOCOLS: &[Protocol] = &[
+    Protocol {
+        name: "artnet",
+        desc: "Art-Net DMX512 over UDP packets",
+        define: artnet::define,
+    },
+    Protocol {
+        name: "ble",
+        desc: "Bluetooth LE link layer PDUs",
+        define: ble::define,
+    },
+    Protocol {
+        name: "coap",
+        desc: "CoAP messages and options",
+        define: coap::define,
+    },
+    Protocol {
+        name: "crsf",
+        desc: "CRSF RC channel and telemetry frames",
+        define: crsf::define,
+    },
+    Protocol {
+        name: "dns",
+        desc: "DNS messages and resource records",
+        define: dns::define,
+    },
+    Protocol {
+        name: "ethernet",
+        desc: "Ethernet II headers with 802.1Q VLAN tags",
+        define: ethernet::define,
+    },
+    Protocol {
+        name: "ieee802154",
+        desc: "IEEE 802.15.4-2015 MAC frames with Zigbee NWK and APS headers",
+        define: ieee802154::define,
+    },
+    Protocol {
+        name: "ipv4",
+        desc: "IPv4 headers and options, UDP and ICMP echo",
+        define: ipv4::define,
+    },
+    Protocol {
+        name: "mavlink",
+        desc: "MAVLink 2 frames and common messages",
+        define: mavlink::define,
+    },
+    Protocol {
+        name: "mbus",
+        desc: "Wired and wireless M-Bus metering frames",
+        define: mbus::define,
+    },
+    Protocol {
+        name: "modbus",
+        desc: "Modbus RTU and TCP requests and responses",
+        define: modbus::define,
+    },
+    Protocol {
+        name: "mqttsn",
+        desc: "MQTT-SN 1.2 messages",
+        define: mqttsn::define,
+    },
+    Protocol {
+        name: "sbus",
+        desc: "SBUS RC receiver frames",
+        define: sbus::define,
+    },
+    Protocol {
+        name: "ubx",
+        desc: "u-blox UBX packets",
+        define: ubx::define,
+    },
+    Protocol {
+        name: "usb",
+        desc: "USB SETUP packets and standard descriptors",
+        define: usb::define,
+    },
+];
+
+/// Find the built-in protocol `name`.
+pub fn find(name: &str) -> Result<&'static Protocol> {
+    match PROTOCOLS.iter().find(|protocol| protocol.name == name) {
+        Some(protocol) => Ok(protocol),
+        None => {
+            let names = PROTOCOLS
+                .iter()
+                .map(|protocol| format!("`{}`", protocol.name))
+                .collect::<Vec<_>>();
+            bail!(
+                "unknown protocol `{}`, known protocols are {}",
+                name,
+                names.join(", ")
+            )
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! Modbus: the request and response PDUs of the common public function codes, framed for
+//! serial lines as RTU ADUs with a CRC and for TCP/IP with an MBAP header.
+//!
+//! The PDU of an ADU is selected by its function code. Exception responses, whose function code
+//! is the one of the request with the most significant bit set, are read as `RtuException` or
+//! `TcpException` instead of a response. The length of the MBAP header counts the unit
+//! identifier and the PDU, which is left to the user, e.g. as `1 + 1 + pdu.wire_size()`.
+
+use anyhow::Result;
+
+use crate::generate::bitfield::BitField;
+use crate::generate::checksum::ChecksumAlgorithm;
+use crate::generate::structure::{Alternatives, Structure};
+use crate::tree::GenTree;
+use crate::util::ByteOrder;
+
+/// The function codes.
+const FUNCTIONS: &[(&str, u64)] = &[
+    ("Read_coils", 0x01),
+    ("Read_discrete_inputs", 0x02),
+    ("Read_holding_registers", 0x03),
+    ("Read_input_registers", 0x04),
+    ("Write_single_coil", 0x05),
+    ("Write_single_register", 0x06),
+    ("Write_multiple_coils", 0x0f),
+    ("Write_multiple_registers", 0x10),
+    ("Mask_write_register", 0x16),
+    ("Read_write_multiple_registers", 0x17),
+];
+
+/// Capacity of the data of a response, which fills the largest PDU of 253 bytes.
+const RESPONSE_CAPACITY: u32 = 250;
+
+/// Start the PDU `name`, which holds `desc`.
+fn pdu(name: &str, desc: &str) -> Structure {
+    Structure::new(name)
+        .with_desc(desc)
+        .with_byte_order(ByteOrder::BigEndian)
+}
+
+/// Start a structure framing a PDU for serial lines.
+fn rtu(name: &str, desc: &str) -> Structure {
+    pdu(name, desc).add_u8_field_desc("address", "Address of the server, 0 for broadcast")
+}
+
+/// Start a structure framing a PDU for TCP/IP with the MBAP header.
+fn tcp(name: &str, desc: &str) -> Structure {
+    pdu(name, desc)
+        .add_u16_field_desc(
+            "transaction_id",
+            "Identifier pairing requests and responses",
+        )
+        .add_const_field("protocol_id", 2, 0)
+        .add_u16_field_desc("length", "Number of the following bytes")
+        .add_u8_field_desc("unit_id", "Server behind a gateway, 0xff if unused")
+}
+
+/// Add the definitions of Modbus to `tree`.
+pub fn define(tree: &mut GenTree) -> Result<()> {
+    let function_code = BitField::new("function_code", "Function code of a PDU").add_bit_field(
+        "Function_code",
+        "Function requested",
+        8,
+        |f| {
+            FUNCTIONS
+                .iter()
+                .fold(f, |f, (variant, value)| f.add_enum_value(variant, *value))
+        },
+    );
+    tree.add_bitfield(&function_code)?;
+
+    let exception_code = BitField::new("exception_code", "Reason of an exception response")
+        .add_bit_field("Exception_code", "Reason the request failed", 8, |f| {
+            f.add_enum_value("Illegal_function", 0x01)
+                .add_enum_value("Illegal_data_address", 0x02)
+                .add_enum_value("Illegal_data_value", 0x03)
+                .add_enum_value("Server_device_failure", 0x04)
+                .add_enum_value("Acknowledge", 0x05)
+                .add_enum_value("Server_device_busy", 0x06)
+                .add_enum_value("Memory_parity_error", 0x08)
+                .add_enum_value("Gateway_path_unavailable", 0x0a)
+                .add_enum_value("Gateway_target_failed", 0x0b)
+        });
+    tree.add_bitfield(&exception_code)?;
+
+    // reads of bits and registers, which only differ by their function code
+    let read = |name: &str, desc: &str| {
+        pdu(name, desc)
+            .add_u16_field("start_address")
+            .add_u16_field("quantity")
+    };
+    let read_data = |name: &str, desc: &str, data: &str| {
+        pdu(name, desc)
+            .add_len_field("byte_count", 1)
+            .add_bytes_field(data, "byte_count", RESPONSE_CAPACITY)
+    };
+    let write_multiple = |name: &str, desc: &str| {
+        pdu(name, desc)
+            .add_u16_field("start_address")
+            .add_u16_field("quantity")
+    };
+
+    let read_coils_request = read("read_coils_request", "Read coils");
+    let read_discrete_inputs_request = read("read_discrete_inputs_request", "Read discrete inputs");
+    let read_holding_registers_request =
+        read("read_holding_registers_request", "Read holding registers");
+    let read_input_registers_request = read("read_input_registers_request", "Read input registers");
+    let write_single_coil = pdu(
+        "write_single_coil",
+        "Write a single coil, echoed by the response",
+    )
+    .add_u16_field("output_address")
+    .add_u16_field_desc("output_value", "0xff00 for on, 0x0000 for off");
+    let write_single_register = pdu(
+        "write_single_register",
+        "Write a single register, echoed by the response",
+    )
+    .add_u16_field("register_address")
+    .add_u16_field("register_value");
+    let write_multiple_coils_request =
+        write_multiple("write_multiple_coils_request", "Write multiple coils")
+            .add_len_field("byte_count", 1)
+            .add_bytes_field("outputs", "byte_count", 246);
+    let write_multiple_registers_request = write_multiple(
+        "write_multiple_registers_request",
+        "Write multiple registers",
+    )
+    .add_len_field("byte_count", 1)
+    .add_bytes_field("registers", "byte_count", 246);
+    let mask_write_register = pdu(
+        "mask_write_register",
+        "Modify a register with AND and OR masks, echoed by the response",
+    )
+    .add_u16_field("reference_address")
+    .add_u16_field("and_mask")
+    .add_u16_field("or_mask");
+    let read_write_multiple_registers_request = pdu(
+        "read_write_multiple_registers_request",
+        "Write multiple registers and read multiple registers",
+    )
+    .add_u16_field("read_start_address")
+    .add_u16_field("read_quantity")
+    .add_u16_field("write_start_address")
+    .add_u16_field("write_quantity")
+    .add_len_field("byte_count", 1)
+    .add_bytes_field("registers", "byte_count", 242);
+
+    let read_coils_response = read_data(
+        "read_coils_response",
+        "Coils read, eight per byte",
+        "coil_status",
+    );
+    let read_discrete_inputs_response = read_data(
+        "read_discrete_inputs_response",
+        "Discrete inputs read, eight per byte",
+        "input_status",
+    );
+    let read_holding_registers_response = read_data(
+        "read_holding_registers_response",
+        "Holding registers read, two bytes each",
+        "registers",
+    );
+    let read_input_registers_response = read_data(
+        "read_input_registers_response",
+        "Input registers read, two bytes each",
+        "registers",
+    );
+    let write_multiple_coils_response =
+        write_multiple("write_multiple_coils_response", "Coils written");
+    let write_multiple_registers_response =
+        write_multiple("write_multiple_registers_response", "Registers written");
+    let read_write_multiple_registers_response = read_data(
+        "read_write_multiple_registers_response",
+        "Registers read after writing, two bytes each",
+        "registers",
+    );
+
+    let requests = [
+        &read_coils_request,
+        &read_discrete_inputs_request,
+        &read_holding_registers_request,
+        &read_input_registers_request,
+        &write_single_coil,
+        &write_single_register,
+        &write_multiple_coils_request,
+        &write_multiple_registers_request,
+        &mask_write_register,
+        &read_write_multiple_registers_request,
+    ];
+    let responses = [
+        &read_coils_response,
+        &read_discrete_inputs_response,
+        &read_holding_registers_response,
+        &read_input_registers_response,
+        &write_single_coil,
+        &write_single_register,
+        &write_multiple_coils_response,
+        &write_multiple_registers_response,
+        &mask_write_register,
+        &read_write_multiple_registers_response,
+    ];
+
+    // the first function selects the default PDUs, which are already inserted
+    let (default, _) = FUNCTIONS[0];
+    let pdus = Alternatives::new()
+        .insert_new_option("request", requests[0], |o| {
+            requests.iter().zip(FUNCTIONS).skip(1).fold(
+                o.set_discriminant(requests[0], default),
+                |o, (request, (variant, _))| o.insert_type_discriminant(*request, variant),
+            )
+        })
+        .insert_new_option("response", responses[0], |o| {
+            responses.iter().zip(FUNCTIONS).skip(1).fold(
+                o.set_discriminant(responses[0], default),
+                |o, (response, (variant, _))| o.insert_type_discriminant(*response, variant),
+            )
+        });
+    for request in &requests {
+        tree.add_struct(request)?;
+    }
+    // the echoed writes are requests as well
+    for response in &responses {
+        if !requests.iter().any(|request| request.name == response.name) {
+            tree.add_struct(response)?;
+        }
+    }
+    tree.add_alternatives(&pdus)?;
+
+    let request = pdus.get("request")?;
+    let response = pdus.get("response")?;
+    let adus = [
+        rtu("rtu_request", "Request on a serial line")
+            .add_bitfield_of("function_code", &function_code)
+            .add_alt_field_discriminated("pdu", request, "function_code", "Function_code")
+            .add_checksum_field("crc", "address", ChecksumAlgorithm::Crc16Modbus),
+        rtu("rtu_response", "Response on a serial line")
+            .add_bitfield_of("function_code", &function_code)
+            .add_alt_field_discriminated("pdu", response, "function_code", "Function_code")
+            .add_checksum_field("crc", "address", ChecksumAlgorithm::Crc16Modbus),
+        tcp("tcp_request", "Request over TCP/IP")
+            .add_bitfield_of("function_code", &function_code)
+            .add_alt_field_discriminated("pdu", request, "function_code", "Function_code"),
+        tcp("tcp_response", "Response over TCP/IP")
+            .add_bitfield_of("function_code", &function_code)
+            .add_alt_field_discriminated("pdu", response, "function_code", "Function_code"),
+    ];
+    for adu in &adus {
+        tree.add_struct_with_alts(adu, &pdus)?;
+    }
+
+    let rtu_exception = rtu("rtu_exception", "Exception response on a serial line")
+        .add_u8_field_desc("function", "Function code of the request plus 0x80")
+        .add_bitfield_of("exception_code", &exception_code)
+        .add_checksum_field("crc", "address", ChecksumAlgorithm::Crc16Modbus);
+    tree.add_struct(&rtu_exception)?;
+
+    let tcp_exception = tcp("tcp_exception", "Exception response over TCP/IP")
+        .add_u8_field_desc("function", "Function code of the request plus 0x80")
+        .add_bitfield_of("exception_code", &exception_code);
+    tree.add_struct(&tcp_exception)
+}
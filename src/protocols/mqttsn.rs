@@ -0,0 +1,240 @@
+//! MQTT-SN 1.2: the messages exchanged between clients, gateways and forwarders along with the
+//! flags, return codes and a `parse` function dispatching on the message type.
+//!
+//! Every message starts with its length and its type, which are constants of the fixed size
+//! messages. Topic names, client identifiers and payloads take up the remainder of the message
+//! as counted by its length. Only the single byte length is supported, so messages are at most
+//! 255 bytes, and encapsulated messages as well as the sleeping form of `DISCONNECT` carrying a
+//! duration are left to the user.
+
+use anyhow::Result;
+
+use crate::generate::bitfield::BitField;
+use crate::generate::frame::Frames;
+use crate::generate::structure::Structure;
+use crate::tree::GenTree;
+use crate::util::ByteOrder;
+
+/// The message types along with the structures of the messages and their descriptions.
+const MESSAGES: &[(&str, u64, &str, &str)] = &[
+    ("Advertise", 0x00, "advertise", "Gateway advertisement"),
+    ("Search_gw", 0x01, "search_gw", "Search for a gateway"),
+    ("Gw_info", 0x02, "gw_info", "Response to a gateway search"),
+    ("Connect", 0x04, "connect", "Connection request"),
+    ("Conn_ack", 0x05, "conn_ack", "Connection response"),
+    (
+        "Will_topic_req",
+        0x06,
+        "will_topic_req",
+        "Request for the will topic",
+    ),
+    ("Will_topic", 0x07, "will_topic", "Will topic"),
+    (
+        "Will_msg_req",
+        0x08,
+        "will_msg_req",
+        "Request for the will message",
+    ),
+    ("Will_msg", 0x09, "will_msg", "Will message"),
+    ("Register", 0x0a, "register", "Registration of a topic name"),
+    ("Reg_ack", 0x0b, "reg_ack", "Registration response"),
+    ("Publish", 0x0c, "publish", "Published data"),
+    ("Pub_ack", 0x0d, "pub_ack", "Publish acknowledgment"),
+    (
+        "Pub_comp",
+        0x0e,
+        "pub_comp",
+        "Publish complete, QoS 2 part 3",
+    ),
+    ("Pub_rec", 0x0f, "pub_rec", "Publish received, QoS 2 part 1"),
+    ("Pub_rel", 0x10, "pub_rel", "Publish release, QoS 2 part 2"),
+    ("Subscribe", 0x12, "subscribe", "Subscription request"),
+    ("Sub_ack", 0x13, "sub_ack", "Subscription response"),
+    ("Unsubscribe", 0x14, "unsubscribe", "Unsubscription request"),
+    ("Unsub_ack", 0x15, "unsub_ack", "Unsubscription response"),
+    ("Ping_req", 0x16, "ping_req", "Keep alive request"),
+    ("Ping_resp", 0x17, "ping_resp", "Keep alive response"),
+    ("Disconnect", 0x18, "disconnect", "Disconnection"),
+    (
+        "Will_topic_upd",
+        0x1a,
+        "will_topic_upd",
+        "Update of the will topic",
+    ),
+    (
+        "Will_topic_resp",
+        0x1b,
+        "will_topic_resp",
+        "Will topic update response",
+    ),
+    (
+        "Will_msg_upd",
+        0x1c,
+        "will_msg_upd",
+        "Update of the will message",
+    ),
+    (
+        "Will_msg_resp",
+        0x1d,
+        "will_msg_resp",
+        "Will message update response",
+    ),
+];
+
+/// Capacity of topic names, client identifiers and will messages.
+const NAME_CAPACITY: u32 = 64;
+/// Capacity of published data.
+const DATA_CAPACITY: u32 = 128;
+
+/// Start the message `name`, whose length is the constant `len`.
+fn fixed(name: &str, len: u64) -> Structure {
+    let s = Structure::new(name).add_const_field("length", 1, len);
+    msg_type(s)
+}
+
+/// Start the message `name`, whose length counts `extra` bytes besides its variable part.
+fn variable(name: &str, extra: u32) -> Structure {
+    let s = Structure::new(name).add_len_field_extra("length", 1, extra);
+    msg_type(s)
+}
+
+/// Add the message type of the message `s`.
+fn msg_type(s: Structure) -> Structure {
+    let (_, value, _, desc) = MESSAGES
+        .iter()
+        .find(|(_, _, structure, _)| *structure == s.name)
+        .expect("unknown message");
+    s.with_desc(desc)
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_const_field("msg_type", 1, *value)
+}
+
+/// Add the definitions of MQTT-SN to `tree`.
+pub fn define(tree: &mut GenTree) -> Result<()> {
+    let msg_type_header = BitField::new("msg_type", "Message type").add_bit_field(
+        "Msg_type",
+        "Type of the message",
+        8,
+        |f| {
+            MESSAGES.iter().fold(f, |f, (variant, value, _, _)| {
+                f.add_enum_value(variant, *value)
+            })
+        },
+    );
+    tree.add_bitfield(&msg_type_header)?;
+
+    let flags = BitField::new("flags", "Flags of a message")
+        .add_bit_field("Topic_id_type", "How the topic is identified", 2, |f| {
+            f.add_enum_value_desc("Normal", "A registered topic id or a topic name", 0)
+                .add_enum_value_desc("Predefined", "A predefined topic id", 1)
+                .add_enum_value_desc("Short", "A two character topic name", 2)
+        })
+        .add_bit_field(
+            "Clean_session",
+            "Discard the previous session of the client",
+            1,
+            |f| f,
+        )
+        .add_bit_field(
+            "Will",
+            "The client asks for a will topic and message",
+            1,
+            |f| f,
+        )
+        .add_bit_field("Retain", "The gateway keeps the published data", 1, |f| f)
+        .add_bit_field("Qos", "Quality of service", 2, |f| {
+            f.add_enum_value_desc("At_most_once", "QoS 0", 0)
+                .add_enum_value_desc("At_least_once", "QoS 1", 1)
+                .add_enum_value_desc("Exactly_once", "QoS 2", 2)
+                .add_enum_value_desc("Connectionless", "QoS -1, without a connection", 3)
+        })
+        .add_bit_field("Dup", "The message is retransmitted", 1, |f| f);
+    tree.add_bitfield(&flags)?;
+
+    let return_code = BitField::new("return_code", "Outcome of a request").add_bit_field(
+        "Return_code",
+        "Outcome of the request",
+        8,
+        |f| {
+            f.add_enum_value("Accepted", 0x00)
+                .add_enum_value("Rejected_congestion", 0x01)
+                .add_enum_value("Rejected_invalid_topic_id", 0x02)
+                .add_enum_value("Rejected_not_supported", 0x03)
+        },
+    );
+    tree.add_bitfield(&return_code)?;
+
+    let messages = vec![
+        fixed("advertise", 5)
+            .add_u8_field("gw_id")
+            .add_u16_field_desc("duration", "Seconds until the next advertisement"),
+        fixed("search_gw", 3).add_u8_field_desc("radius", "Broadcast radius in hops"),
+        variable("gw_info", 3)
+            .add_u8_field("gw_id")
+            .add_bytes_field("gw_add", "length", NAME_CAPACITY),
+        variable("connect", 6)
+            .add_bitfield_of("flags", &flags)
+            .add_const_field("protocol_id", 1, 0x01)
+            .add_u16_field_desc("duration", "Keep alive period in seconds")
+            .add_bytes_field("client_id", "length", 23),
+        fixed("conn_ack", 3).add_bitfield_of("return_code", &return_code),
+        fixed("will_topic_req", 2),
+        variable("will_topic", 3)
+            .add_bitfield_of("flags", &flags)
+            .add_bytes_field("will_topic", "length", NAME_CAPACITY),
+        fixed("will_msg_req", 2),
+        variable("will_msg", 2).add_bytes_field("will_msg", "length", NAME_CAPACITY),
+        variable("register", 6)
+            .add_u16_field("topic_id")
+            .add_u16_field("msg_id")
+            .add_bytes_field("topic_name", "length", NAME_CAPACITY),
+        fixed("reg_ack", 7)
+            .add_u16_field("topic_id")
+            .add_u16_field("msg_id")
+            .add_bitfield_of("return_code", &return_code),
+        variable("publish", 7)
+            .add_bitfield_of("flags", &flags)
+            .add_u16_field("topic_id")
+            .add_u16_field("msg_id")
+            .add_bytes_field("data", "length", DATA_CAPACITY),
+        fixed("pub_ack", 7)
+            .add_u16_field("topic_id")
+            .add_u16_field("msg_id")
+            .add_bitfield_of("return_code", &return_code),
+        fixed("pub_comp", 4).add_u16_field("msg_id"),
+        fixed("pub_rec", 4).add_u16_field("msg_id"),
+        fixed("pub_rel", 4).add_u16_field("msg_id"),
+        variable("subscribe", 5)
+            .add_bitfield_of("flags", &flags)
+            .add_u16_field("msg_id")
+            .add_bytes_field("topic", "length", NAME_CAPACITY),
+        fixed("sub_ack", 8)
+            .add_bitfield_of("flags", &flags)
+            .add_u16_field("topic_id")
+            .add_u16_field("msg_id")
+            .add_bitfield_of("return_code", &return_code),
+        variable("unsubscribe", 5)
+            .add_bitfield_of("flags", &flags)
+            .add_u16_field("msg_id")
+            .add_bytes_field("topic", "length", NAME_CAPACITY),
+        fixed("unsub_ack", 4).add_u16_field("msg_id"),
+        variable("ping_req", 2).add_bytes_field("client_id", "length", 23),
+        fixed("ping_resp", 2),
+        fixed("disconnect", 2),
+        variable("will_topic_upd", 3)
+            .add_bitfield_of("flags", &flags)
+            .add_bytes_field("will_topic", "length", NAME_CAPACITY),
+        fixed("will_topic_resp", 3).add_bitfield_of("return_code", &return_code),
+        variable("will_msg_upd", 2).add_bytes_field("will_msg", "length", NAME_CAPACITY),
+        fixed("will_msg_resp", 3).add_bitfield_of("return_code", &return_code),
+    ];
+
+    let mut frames = Frames::new("message", "msg_type", 1, "Msg_type")
+        .with_desc("MQTT-SN message, selected by its type following the length.")
+        .with_offset(1);
+    for (message, (variant, _, _, _)) in messages.iter().zip(MESSAGES) {
+        tree.add_struct(message)?;
+        frames = frames.add_frame(variant, message);
+    }
+    tree.add_frames(&frames)
+}
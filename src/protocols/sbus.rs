@@ -0,0 +1,64 @@
+//! SBUS, the serial protocol of Futaba RC receivers: a frame of 16 proportional channels of 11
+//! bits each, packed least significant bit first across 22 bytes, followed by two digital
+//! channels and the failsafe flags.
+//!
+//! Frames are sent at 100000 baud with 8 data bits, even parity and two stop bits, on an
+//! inverted line. The last byte of a frame is zero, or selects the slot group of the telemetry
+//! following the frame for SBUS2.
+
+use anyhow::Result;
+
+use crate::generate::bitfield::BitField;
+use crate::generate::structure::Structure;
+use crate::tree::GenTree;
+
+/// Number of proportional channels.
+const CHANNELS: u32 = 16;
+
+/// Bits of a proportional channel.
+const CHANNEL_BITS: u32 = 11;
+
+/// The packed proportional channels of SBUS, which are also sent by CRSF.
+pub fn channels() -> BitField {
+    (1..=CHANNELS).fold(
+        BitField::new("channels", "Proportional channels packed into 11 bits each"),
+        |bitfield, channel| {
+            bitfield.add_bit_field(
+                &format!("Ch{}", channel),
+                &format!("Value of channel {}", channel),
+                CHANNEL_BITS,
+                |f| f.numeric(),
+            )
+        },
+    )
+}
+
+/// Add the definitions of SBUS to `tree`.
+pub fn define(tree: &mut GenTree) -> Result<()> {
+    let channels = channels();
+    tree.add_bitfield(&channels)?;
+
+    let flags = BitField::new("flags", "Digital channels and failsafe flags of a frame")
+        .add_bit_field("Ch17", "Value of digital channel 17", 1, |f| f)
+        .add_bit_field("Ch18", "Value of digital channel 18", 1, |f| f)
+        .add_bit_field(
+            "Frame_lost",
+            "The receiver lost a frame of the transmitter",
+            1,
+            |f| f,
+        )
+        .add_bit_field("Failsafe", "The receiver is in failsafe", 1, |f| f)
+        .add_reserved(4);
+    tree.add_bitfield(&flags)?;
+
+    let frame = Structure::new("sbus_frame")
+        .with_desc("Frame of the channels of a receiver")
+        .add_const_field("header", 1, 0x0f)
+        .add_bitfield_of("channels", &channels)
+        .add_bitfield_of("flags", &flags)
+        .add_u8_field_desc(
+            "footer",
+            "Zero, or the slot group of the telemetry for SBUS2",
+        );
+    tree.add_struct(&frame)
+}
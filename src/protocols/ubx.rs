@@ -0,0 +1,275 @@
+//! UBX, the binary protocol of u-blox GNSS receivers: the framing of a packet with its sync
+//! characters, class, id, length and checksum, along with the packets of a handful of NAV and
+//! ACK messages and a `parse` function dispatching on their class and id.
+//!
+//! Packets of other messages are read as a `Packet` holding their payload as bytes. Signed
+//! fields are held as the unsigned integers of their bits, e.g. `lon as i32`, and reserved
+//! bytes are kept as plain fields to be written as zeros.
+
+use anyhow::Result;
+
+use crate::generate::bitfield::BitField;
+use crate::generate::checksum::ChecksumAlgorithm;
+use crate::generate::frame::Frames;
+use crate::generate::structure::Structure;
+use crate::tree::GenTree;
+
+/// The message classes.
+const CLASSES: &[(&str, u64)] = &[
+    ("Nav", 0x01),
+    ("Rxm", 0x02),
+    ("Inf", 0x04),
+    ("Ack", 0x05),
+    ("Cfg", 0x06),
+    ("Upd", 0x09),
+    ("Mon", 0x0a),
+    ("Aid", 0x0b),
+    ("Tim", 0x0d),
+    ("Esf", 0x10),
+    ("Mga", 0x13),
+    ("Log", 0x21),
+    ("Sec", 0x27),
+    ("Hnr", 0x28),
+];
+
+/// The messages with a known packet along with their class and id.
+const MESSAGES: &[(&str, u64, u64)] = &[
+    ("Ack_nak", 0x05, 0x00),
+    ("Ack_ack", 0x05, 0x01),
+    ("Nav_posllh", 0x01, 0x02),
+    ("Nav_status", 0x01, 0x03),
+    ("Nav_dop", 0x01, 0x04),
+    ("Nav_pvt", 0x01, 0x07),
+    ("Nav_velned", 0x01, 0x12),
+    ("Nav_timeutc", 0x01, 0x21),
+];
+
+/// Capacity of the payload of a packet of any message.
+const PAYLOAD_CAPACITY: u32 = 1024;
+
+/// The class and id of `message`, as read from the wire in little endian.
+fn message_id(message: &str) -> u64 {
+    let (_, class, id) = MESSAGES
+        .iter()
+        .find(|(variant, _, _)| variant.to_lowercase() == message)
+        .expect("unknown message");
+    class | id << 8
+}
+
+/// Start the packet of the message `name`, whose payload is `len` bytes.
+fn packet(name: &str, desc: &str, len: u64) -> Structure {
+    Structure::new(name)
+        .with_desc(desc)
+        .add_const_field("sync", 2, 0x62b5)
+        .add_const_field("message", 2, message_id(name))
+        .add_const_field("length", 2, len)
+}
+
+/// Finish the packet `s` with its checksum.
+fn checksum(s: Structure) -> Structure {
+    s.add_checksum_field("checksum", "message", ChecksumAlgorithm::Fletcher8)
+}
+
+/// Add the date and time of a NAV message to `s`.
+fn date_time(s: Structure) -> Structure {
+    s.add_u16_field_desc("year", "Year in UTC")
+        .add_u8_field("month")
+        .add_u8_field("day")
+        .add_u8_field("hour")
+        .add_u8_field("min")
+        .add_u8_field("sec")
+}
+
+/// Add the definitions of UBX to `tree`.
+pub fn define(tree: &mut GenTree) -> Result<()> {
+    let class = BitField::new("class", "Class of a message").add_bit_field(
+        "Class",
+        "Class of the message",
+        8,
+        |f| {
+            CLASSES
+                .iter()
+                .fold(f, |f, (variant, value)| f.add_enum_value(variant, *value))
+        },
+    );
+    tree.add_bitfield(&class)?;
+
+    let class_id = BitField::new("class_id", "Class and id of a message").add_bit_field(
+        "Message",
+        "Message, with the class in the low byte and the id in the high byte",
+        16,
+        |f| {
+            MESSAGES.iter().fold(f, |f, (variant, class, id)| {
+                f.add_enum_value(variant, class | id << 8)
+            })
+        },
+    );
+    tree.add_bitfield(&class_id)?;
+
+    let gnss_fix = BitField::new("gnss_fix", "Type of a GNSS fix").add_bit_field(
+        "Fix_type",
+        "Type of the fix",
+        8,
+        |f| {
+            f.add_enum_value("No_fix", 0x00)
+                .add_enum_value("Dead_reckoning", 0x01)
+                .add_enum_value("Fix_2d", 0x02)
+                .add_enum_value("Fix_3d", 0x03)
+                .add_enum_value("Gnss_dead_reckoning", 0x04)
+                .add_enum_value("Time_only", 0x05)
+        },
+    );
+    tree.add_bitfield(&gnss_fix)?;
+
+    let pvt_valid = BitField::new("pvt_valid", "Validity of the date and time of a solution")
+        .add_bit_field("Valid_date", "The UTC date is valid", 1, |f| f)
+        .add_bit_field("Valid_time", "The UTC time of day is valid", 1, |f| f)
+        .add_bit_field(
+            "Fully_resolved",
+            "The UTC time of day has no seconds uncertainty",
+            1,
+            |f| f,
+        )
+        .add_bit_field("Valid_mag", "The magnetic declination is valid", 1, |f| f)
+        .add_reserved(4);
+    tree.add_bitfield(&pvt_valid)?;
+
+    let pvt_flags = BitField::new("pvt_flags", "Status of a navigation solution")
+        .add_bit_field("Gnss_fix_ok", "The fix is within the limits", 1, |f| f)
+        .add_bit_field(
+            "Diff_soln",
+            "Differential corrections were applied",
+            1,
+            |f| f,
+        )
+        .add_bit_field("Psm_state", "State of the power save mode", 3, |f| {
+            f.numeric()
+        })
+        .add_bit_field(
+            "Head_veh_valid",
+            "The heading of the vehicle is valid",
+            1,
+            |f| f,
+        )
+        .add_bit_field("Carr_soln", "Carrier phase range solution", 2, |f| {
+            f.add_enum_value("None", 0)
+                .add_enum_value("Float", 1)
+                .add_enum_value("Fixed", 2)
+        });
+    tree.add_bitfield(&pvt_flags)?;
+
+    let any_packet = Structure::new("packet")
+        .with_desc("Packet of any message, holding its payload as bytes")
+        .add_const_field("sync", 2, 0x62b5)
+        .add_bitfield_of("class", &class)
+        .add_u8_field_desc("id", "Id of the message within its class")
+        .add_len_field("length", 2)
+        .add_bytes_field("payload", "length", PAYLOAD_CAPACITY)
+        .add_checksum_field("checksum", "class", ChecksumAlgorithm::Fletcher8);
+    tree.add_struct(&any_packet)?;
+
+    let acknowledged = |name: &str, desc: &str| {
+        checksum(
+            packet(name, desc, 2)
+                .add_bitfield_of("cls_id", &class)
+                .add_u8_field_desc("msg_id", "Id of the message within its class"),
+        )
+    };
+
+    let packets = [
+        acknowledged("ack_nak", "A message was not acknowledged"),
+        acknowledged("ack_ack", "A message was acknowledged"),
+        checksum(
+            packet("nav_posllh", "Geodetic position", 28)
+                .add_u32_field_desc("i_tow", "GPS time of week in ms")
+                .add_u32_field_desc("lon", "Longitude in 1e-7 degrees")
+                .add_u32_field_desc("lat", "Latitude in 1e-7 degrees")
+                .add_u32_field_desc("height", "Height above the ellipsoid in mm")
+                .add_u32_field_desc("h_msl", "Height above mean sea level in mm")
+                .add_u32_field_desc("h_acc", "Horizontal accuracy estimate in mm")
+                .add_u32_field_desc("v_acc", "Vertical accuracy estimate in mm"),
+        ),
+        checksum(
+            packet("nav_status", "Receiver navigation status", 16)
+                .add_u32_field_desc("i_tow", "GPS time of week in ms")
+                .add_bitfield_of("gps_fix", &gnss_fix)
+                .add_u8_field("flags")
+                .add_u8_field("fix_stat")
+                .add_u8_field("flags2")
+                .add_u32_field_desc("ttff", "Time to first fix in ms")
+                .add_u32_field_desc("msss", "Milliseconds since startup or reset"),
+        ),
+        checksum(
+            packet("nav_dop", "Dilution of precision in units of 0.01", 18)
+                .add_u32_field_desc("i_tow", "GPS time of week in ms")
+                .add_u16_field_desc("g_dop", "Geometric DOP")
+                .add_u16_field_desc("p_dop", "Position DOP")
+                .add_u16_field_desc("t_dop", "Time DOP")
+                .add_u16_field_desc("v_dop", "Vertical DOP")
+                .add_u16_field_desc("h_dop", "Horizontal DOP")
+                .add_u16_field_desc("n_dop", "Northing DOP")
+                .add_u16_field_desc("e_dop", "Easting DOP"),
+        ),
+        checksum(
+            date_time(
+                packet("nav_pvt", "Navigation position velocity time solution", 92)
+                    .add_u32_field_desc("i_tow", "GPS time of week in ms"),
+            )
+            .add_bitfield_of("valid", &pvt_valid)
+            .add_u32_field_desc("t_acc", "Time accuracy estimate in ns")
+            .add_u32_field_desc("nano", "Fraction of a second in ns, signed")
+            .add_bitfield_of("fix_type", &gnss_fix)
+            .add_bitfield_of("flags", &pvt_flags)
+            .add_u8_field("flags2")
+            .add_u8_field_desc("num_sv", "Number of satellites used")
+            .add_u32_field_desc("lon", "Longitude in 1e-7 degrees")
+            .add_u32_field_desc("lat", "Latitude in 1e-7 degrees")
+            .add_u32_field_desc("height", "Height above the ellipsoid in mm")
+            .add_u32_field_desc("h_msl", "Height above mean sea level in mm")
+            .add_u32_field_desc("h_acc", "Horizontal accuracy estimate in mm")
+            .add_u32_field_desc("v_acc", "Vertical accuracy estimate in mm")
+            .add_u32_field_desc("vel_n", "Velocity north in mm/s")
+            .add_u32_field_desc("vel_e", "Velocity east in mm/s")
+            .add_u32_field_desc("vel_d", "Velocity down in mm/s")
+            .add_u32_field_desc("g_speed", "Ground speed in mm/s")
+            .add_u32_field_desc("head_mot", "Heading of motion in 1e-5 degrees")
+            .add_u32_field_desc("s_acc", "Speed accuracy estimate in mm/s")
+            .add_u32_field_desc("head_acc", "Heading accuracy estimate in 1e-5 degrees")
+            .add_u16_field_desc("p_dop", "Position DOP in units of 0.01")
+            .add_array_field("reserved1", 1, 6)
+            .add_u32_field_desc("head_veh", "Heading of the vehicle in 1e-5 degrees")
+            .add_u16_field_desc("mag_dec", "Magnetic declination in 1e-2 degrees")
+            .add_u16_field_desc("mag_acc", "Magnetic declination accuracy in 1e-2 degrees"),
+        ),
+        checksum(
+            packet("nav_velned", "Velocity in the NED frame", 36)
+                .add_u32_field_desc("i_tow", "GPS time of week in ms")
+                .add_u32_field_desc("vel_n", "Velocity north in cm/s")
+                .add_u32_field_desc("vel_e", "Velocity east in cm/s")
+                .add_u32_field_desc("vel_d", "Velocity down in cm/s")
+                .add_u32_field_desc("speed", "Speed in cm/s")
+                .add_u32_field_desc("g_speed", "Ground speed in cm/s")
+                .add_u32_field_desc("heading", "Heading of motion in 1e-5 degrees")
+                .add_u32_field_desc("s_acc", "Speed accuracy estimate in cm/s")
+                .add_u32_field_desc("c_acc", "Heading accuracy estimate in 1e-5 degrees"),
+        ),
+        checksum(
+            date_time(
+                packet("nav_timeutc", "UTC time solution", 20)
+                    .add_u32_field_desc("i_tow", "GPS time of week in ms")
+                    .add_u32_field_desc("t_acc", "Time accuracy estimate in ns")
+                    .add_u32_field_desc("nano", "Fraction of a second in ns, signed"),
+            )
+            .add_u8_field("valid"),
+        ),
+    ];
+
+    let mut frames = Frames::new("message", "class_id", 2, "Message")
+        .with_desc("Packet of a known message, selected by its class and id following the sync characters.")
+        .with_offset(2);
+    for (packet, (variant, _, _)) in packets.iter().zip(MESSAGES) {
+        tree.add_struct(packet)?;
+        frames = frames.add_frame(variant, packet);
+    }
+    tree.add_frames(&frames)
+}
@@ -0,0 +1,306 @@
+//! USB 2.0 chapter 9: the SETUP packet of a control transfer with its standard requests, and
+//! the standard device, configuration, string, interface and endpoint descriptors.
+//!
+//! The descriptors returned for a configuration follow each other, so they may be walked using
+//! `Descriptor::read_from`, skipping descriptors of other types such as class descriptors by
+//! the length in their first byte. String descriptors hold their string as UTF-16LE bytes.
+
+use anyhow::Result;
+
+use crate::generate::bitfield::BitField;
+use crate::generate::frame::Frames;
+use crate::generate::structure::Structure;
+use crate::tree::GenTree;
+
+/// The standard requests.
+const REQUESTS: &[(&str, u64)] = &[
+    ("Get_status", 0),
+    ("Clear_feature", 1),
+    ("Set_feature", 3),
+    ("Set_address", 5),
+    ("Get_descriptor", 6),
+    ("Set_descriptor", 7),
+    ("Get_configuration", 8),
+    ("Set_configuration", 9),
+    ("Get_interface", 10),
+    ("Set_interface", 11),
+    ("Synch_frame", 12),
+];
+
+/// The descriptor types.
+const DESCRIPTOR_TYPES: &[(&str, u64)] = &[
+    ("Device", 1),
+    ("Configuration", 2),
+    ("String", 3),
+    ("Interface", 4),
+    ("Endpoint", 5),
+    ("Device_qualifier", 6),
+    ("Other_speed_configuration", 7),
+    ("Interface_power", 8),
+    ("Otg", 9),
+    ("Debug", 10),
+    ("Interface_association", 11),
+    ("Bos", 15),
+    ("Device_capability", 16),
+    ("Hid", 0x21),
+    ("Hid_report", 0x22),
+    ("Cs_interface", 0x24),
+    ("Cs_endpoint", 0x25),
+];
+
+/// The class codes of devices and interfaces.
+const CLASSES: &[(&str, u64)] = &[
+    ("Per_interface", 0x00),
+    ("Audio", 0x01),
+    ("Cdc", 0x02),
+    ("Hid", 0x03),
+    ("Physical", 0x05),
+    ("Image", 0x06),
+    ("Printer", 0x07),
+    ("Mass_storage", 0x08),
+    ("Hub", 0x09),
+    ("Cdc_data", 0x0a),
+    ("Smart_card", 0x0b),
+    ("Content_security", 0x0d),
+    ("Video", 0x0e),
+    ("Personal_healthcare", 0x0f),
+    ("Audio_video", 0x10),
+    ("Billboard", 0x11),
+    ("Usb_c_bridge", 0x12),
+    ("Diagnostic", 0xdc),
+    ("Wireless", 0xe0),
+    ("Miscellaneous", 0xef),
+    ("Application_specific", 0xfe),
+    ("Vendor_specific", 0xff),
+];
+
+/// The longest string of a string descriptor in bytes.
+const STRING_CAPACITY: u32 = 253;
+
+/// Start the descriptor `name` of `len` bytes and the type `variant`.
+fn descriptor(name: &str, desc: &str, len: u64, variant: &str) -> Structure {
+    let (_, value) = DESCRIPTOR_TYPES
+        .iter()
+        .find(|(v, _)| *v == variant)
+        .expect("unknown descriptor type");
+    Structure::new(name)
+        .with_desc(desc)
+        .add_const_field("length", 1, len)
+        .add_const_field("descriptor_type", 1, *value)
+}
+
+/// Add the definitions of USB to `tree`.
+pub fn define(tree: &mut GenTree) -> Result<()> {
+    let request_type = BitField::new("request_type", "Characteristics of a request")
+        .add_bit_field("Recipient", "Recipient of the request", 5, |f| {
+            f.add_enum_value("Device", 0)
+                .add_enum_value("Interface", 1)
+                .add_enum_value("Endpoint", 2)
+                .add_enum_value("Other", 3)
+        })
+        .add_bit_field("Type", "Type of the request", 2, |f| {
+            f.add_enum_value("Standard", 0)
+                .add_enum_value("Class", 1)
+                .add_enum_value("Vendor", 2)
+        })
+        .add_bit_field("Direction", "Direction of the data stage", 1, |f| {
+            f.add_enum_value("Host_to_device", 0)
+                .add_enum_value("Device_to_host", 1)
+        });
+    tree.add_bitfield(&request_type)?;
+
+    let request = BitField::new("request", "Request of a SETUP packet").add_bit_field(
+        "Request",
+        "Standard request, or a class or vendor request",
+        8,
+        |f| {
+            REQUESTS
+                .iter()
+                .fold(f, |f, (variant, value)| f.add_enum_value(variant, *value))
+        },
+    );
+    tree.add_bitfield(&request)?;
+
+    let descriptor_type = BitField::new("descriptor_type", "Type of a descriptor").add_bit_field(
+        "Descriptor_type",
+        "Type of the descriptor",
+        8,
+        |f| {
+            DESCRIPTOR_TYPES
+                .iter()
+                .fold(f, |f, (variant, value)| f.add_enum_value(variant, *value))
+        },
+    );
+    tree.add_bitfield(&descriptor_type)?;
+
+    let class = BitField::new("class", "Class code of a device or interface").add_bit_field(
+        "Class",
+        "Class of the device or interface",
+        8,
+        |f| {
+            CLASSES
+                .iter()
+                .fold(f, |f, (variant, value)| f.add_enum_value(variant, *value))
+        },
+    );
+    tree.add_bitfield(&class)?;
+
+    let config_attributes = BitField::new("config_attributes", "Attributes of a configuration")
+        .add_reserved(5)
+        .add_bit_field(
+            "Remote_wakeup",
+            "The configuration supports remote wakeup",
+            1,
+            |f| f,
+        )
+        .add_bit_field(
+            "Self_powered",
+            "The configuration is self powered",
+            1,
+            |f| f,
+        )
+        .add_bit_field(
+            "Bus_powered",
+            "Must be set for historical reasons",
+            1,
+            |f| f,
+        );
+    tree.add_bitfield(&config_attributes)?;
+
+    let endpoint_address = BitField::new("endpoint_address", "Address of an endpoint")
+        .add_bit_field("Number", "Number of the endpoint", 4, |f| f.numeric())
+        .add_reserved(3)
+        .add_bit_field("Direction", "Direction of the endpoint", 1, |f| {
+            f.add_enum_value("Out", 0).add_enum_value("In", 1)
+        });
+    tree.add_bitfield(&endpoint_address)?;
+
+    let endpoint_attributes = BitField::new("endpoint_attributes", "Attributes of an endpoint")
+        .add_bit_field("Transfer_type", "Type of the transfers", 2, |f| {
+            f.add_enum_value("Control", 0)
+                .add_enum_value("Isochronous", 1)
+                .add_enum_value("Bulk", 2)
+                .add_enum_value("Interrupt", 3)
+        })
+        .add_bit_field(
+            "Synchronization",
+            "Synchronization of an isochronous endpoint",
+            2,
+            |f| {
+                f.add_enum_value("None", 0)
+                    .add_enum_value("Asynchronous", 1)
+                    .add_enum_value("Adaptive", 2)
+                    .add_enum_value("Synchronous", 3)
+            },
+        )
+        .add_bit_field("Usage", "Usage of an isochronous endpoint", 2, |f| {
+            f.add_enum_value("Data", 0)
+                .add_enum_value("Feedback", 1)
+                .add_enum_value("Implicit_feedback", 2)
+        })
+        .add_reserved(2);
+    tree.add_bitfield(&endpoint_attributes)?;
+
+    let setup = Structure::new("setup_packet")
+        .with_desc("SETUP packet starting a control transfer")
+        .add_bitfield_of("request_type", &request_type)
+        .add_bitfield_of("request", &request)
+        .add_u16_field_desc("value", "Parameter of the request")
+        .add_u16_field_desc(
+            "index",
+            "Parameter of the request, often an interface or endpoint",
+        )
+        .add_u16_field_desc("length", "Number of bytes of the data stage");
+    tree.add_struct(&setup)?;
+
+    let device = descriptor("device_descriptor", "Descriptor of a device", 18, "Device")
+        .add_u16_field_desc("bcd_usb", "Release of the USB specification in BCD")
+        .add_bitfield_of("device_class", &class)
+        .add_u8_field("device_sub_class")
+        .add_u8_field("device_protocol")
+        .add_u8_field_desc("max_packet_size0", "Largest packet of endpoint zero")
+        .add_u16_field_desc("id_vendor", "Vendor id assigned by the USB-IF")
+        .add_u16_field_desc("id_product", "Product id assigned by the vendor")
+        .add_u16_field_desc("bcd_device", "Release of the device in BCD")
+        .add_u8_field_desc(
+            "i_manufacturer",
+            "Index of the string describing the manufacturer",
+        )
+        .add_u8_field_desc("i_product", "Index of the string describing the product")
+        .add_u8_field_desc(
+            "i_serial_number",
+            "Index of the string holding the serial number",
+        )
+        .add_u8_field_desc("num_configurations", "Number of configurations");
+
+    let configuration = descriptor(
+        "configuration_descriptor",
+        "Descriptor of a configuration, followed by the descriptors of its interfaces",
+        9,
+        "Configuration",
+    )
+    .add_u16_field_desc(
+        "total_length",
+        "Length of the configuration including all its descriptors",
+    )
+    .add_u8_field_desc("num_interfaces", "Number of interfaces")
+    .add_u8_field_desc("configuration_value", "Value selecting the configuration")
+    .add_u8_field_desc(
+        "i_configuration",
+        "Index of the string describing the configuration",
+    )
+    .add_bitfield_of("attributes", &config_attributes)
+    .add_u8_field_desc(
+        "max_power",
+        "Largest current drawn from the bus in units of 2 mA",
+    );
+
+    let string = Structure::new("string_descriptor")
+        .with_desc("Descriptor holding a string, or the supported languages for index zero")
+        .add_len_field_extra("length", 1, 2)
+        .add_const_field("descriptor_type", 1, 3)
+        .add_bytes_field("string", "length", STRING_CAPACITY);
+
+    let interface = descriptor(
+        "interface_descriptor",
+        "Descriptor of an interface, followed by the descriptors of its endpoints",
+        9,
+        "Interface",
+    )
+    .add_u8_field_desc("interface_number", "Number of the interface")
+    .add_u8_field_desc("alternate_setting", "Alternate setting of the interface")
+    .add_u8_field_desc("num_endpoints", "Number of endpoints besides endpoint zero")
+    .add_bitfield_of("interface_class", &class)
+    .add_u8_field("interface_sub_class")
+    .add_u8_field("interface_protocol")
+    .add_u8_field_desc(
+        "i_interface",
+        "Index of the string describing the interface",
+    );
+
+    let endpoint = descriptor(
+        "endpoint_descriptor",
+        "Descriptor of an endpoint",
+        7,
+        "Endpoint",
+    )
+    .add_bitfield_of("endpoint_address", &endpoint_address)
+    .add_bitfield_of("attributes", &endpoint_attributes)
+    .add_u16_field_desc("max_packet_size", "Largest packet of the endpoint")
+    .add_u8_field_desc("interval", "Interval for polling the endpoint");
+
+    let mut frames = Frames::new("descriptor", "descriptor_type", 1, "Descriptor_type")
+        .with_desc("Standard descriptor, selected by its type following its length.")
+        .with_offset(1);
+    for (variant, descriptor) in [
+        ("Device", &device),
+        ("Configuration", &configuration),
+        ("String", &string),
+        ("Interface", &interface),
+        ("Endpoint", &endpoint),
+    ] {
+        tree.add_struct(descriptor)?;
+        frames = frames.add_frame(variant, descriptor);
+    }
+    tree.add_frames(&frames)
+}
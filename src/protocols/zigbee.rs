@@ -0,0 +1,414 @@
+//! Zigbee PRO on top of the IEEE 802.15.4 MAC: the header of the network layer (NWK) with its
+//! IEEE addresses, multicast control, source route and auxiliary security header, and the
+//! headers of the frames of the application support sublayer (APS).
+//!
+//! The NWK header follows the MAC header of a data frame, and is followed either by a NWK
+//! command or by an APS frame, which are read using `ApsFrame::read_from`. The payload of a
+//! link status command is read with `LinkStatus::read_with_entries`, which iterates over the
+//! neighbors up to the end of the command. The message integrity code trailing a secured frame
+//! is left to the user, as is the payload which is encrypted in that case.
+
+use anyhow::Result;
+
+use crate::generate::bitfield::{BitField, BitFieldMember};
+use crate::generate::frame::Frames;
+use crate::generate::structure::{Alternatives, Structure};
+use crate::tree::GenTree;
+
+/// The NWK commands.
+const NWK_COMMANDS: &[(&str, u64)] = &[
+    ("Route_request", 0x01),
+    ("Route_reply", 0x02),
+    ("Network_status", 0x03),
+    ("Leave", 0x04),
+    ("Route_record", 0x05),
+    ("Rejoin_request", 0x06),
+    ("Rejoin_response", 0x07),
+    ("Link_status", 0x08),
+    ("Network_report", 0x09),
+    ("Network_update", 0x0a),
+    ("End_device_timeout_request", 0x0b),
+    ("End_device_timeout_response", 0x0c),
+    ("Link_power_delta", 0x0d),
+];
+
+/// The APS commands.
+const APS_COMMANDS: &[(&str, u64)] = &[
+    ("Transport_key", 0x05),
+    ("Update_device", 0x06),
+    ("Remove_device", 0x07),
+    ("Request_key", 0x08),
+    ("Switch_key", 0x09),
+    ("Tunnel", 0x0e),
+    ("Verify_key", 0x0f),
+    ("Confirm_key", 0x10),
+    ("Relay_message_downstream", 0x11),
+    ("Relay_message_upstream", 0x12),
+];
+
+/// The most relays of a source route.
+const RELAY_CAPACITY: u32 = 32;
+
+/// Make the single bit field `f` a flag of an optional field.
+fn presence(f: BitFieldMember) -> BitFieldMember {
+    f.add_enum_value("Absent", 0).add_enum_value("Present", 1)
+}
+
+/// Add the definitions of the Zigbee NWK and APS layers to `tree`.
+pub fn define(tree: &mut GenTree) -> Result<()> {
+    let nwk_frame_control = BitField::new("nwk_frame_control", "NWK frame control field")
+        .add_bit_field("Frame_type", "Type of the frame", 2, |f| {
+            f.add_enum_value("Data", 0)
+                .add_enum_value("Nwk_command", 1)
+                .add_enum_value("Inter_pan", 3)
+        })
+        .add_bit_field("Protocol_version", "Version of the protocol", 4, |f| {
+            f.numeric()
+        })
+        .add_bit_field(
+            "Discover_route",
+            "Whether a route may be discovered",
+            2,
+            |f| f.add_enum_value("Suppress", 0).add_enum_value("Enable", 1),
+        )
+        .add_bit_field(
+            "Multicast",
+            "The destination is a group and the multicast control is present",
+            1,
+            presence,
+        )
+        .add_bit_field(
+            "Security",
+            "The auxiliary security header is present",
+            1,
+            presence,
+        )
+        .add_bit_field("Source_route", "The source route is present", 1, presence)
+        .add_bit_field(
+            "Destination_ieee",
+            "The IEEE address of the destination is present",
+            1,
+            presence,
+        )
+        .add_bit_field(
+            "Source_ieee",
+            "The IEEE address of the source is present",
+            1,
+            presence,
+        )
+        .add_bit_field(
+            "End_device_initiator",
+            "The frame was sent by an end device",
+            1,
+            |f| f,
+        )
+        .add_reserved(2);
+    tree.add_bitfield(&nwk_frame_control)?;
+
+    let multicast_control = BitField::new("multicast_control", "Multicast control field")
+        .add_bit_field("Multicast_mode", "Whether the sender is a member", 2, |f| {
+            f.add_enum_value("Non_member", 0)
+                .add_enum_value("Member", 1)
+        })
+        .add_bit_field(
+            "Nonmember_radius",
+            "Remaining hops through devices outside the group",
+            3,
+            |f| f.numeric(),
+        )
+        .add_bit_field(
+            "Max_nonmember_radius",
+            "Initial hops through devices outside the group",
+            3,
+            |f| f.numeric(),
+        );
+    tree.add_bitfield(&multicast_control)?;
+
+    let nwk_security_control = BitField::new("nwk_security_control", "Security control field")
+        .add_bit_field(
+            "Security_level",
+            "Protection applied to the frame, usually sent as zero",
+            3,
+            |f| f.numeric(),
+        )
+        .add_bit_field("Key_id", "Kind of the key securing the frame", 2, |f| {
+            f.add_enum_value("Data", 0)
+                .add_enum_value("Network", 1)
+                .add_enum_value("Key_transport", 2)
+                .add_enum_value("Key_load", 3)
+        })
+        .add_bit_field(
+            "Extended_nonce",
+            "The IEEE address of the sender is present",
+            1,
+            presence,
+        )
+        .add_reserved(2);
+    tree.add_bitfield(&nwk_security_control)?;
+
+    let nwk_command_id = BitField::new("nwk_command_id", "Identifier of a NWK command")
+        .add_bit_field("Command_id", "The NWK command", 8, |f| {
+            NWK_COMMANDS
+                .iter()
+                .fold(f, |f, (variant, value)| f.add_enum_value(variant, *value))
+        });
+    tree.add_bitfield(&nwk_command_id)?;
+
+    let aps_frame_control = BitField::new("aps_frame_control", "APS frame control field")
+        .add_bit_field("Frame_type", "Type of the frame", 2, |f| {
+            f.add_enum_value("Data", 0)
+                .add_enum_value("Command", 1)
+                .add_enum_value("Ack", 2)
+                .add_enum_value("Inter_pan", 3)
+        })
+        .add_bit_field("Delivery_mode", "How the frame is delivered", 2, |f| {
+            f.add_enum_value_desc("Unicast", "To the destination endpoint", 0)
+                .add_enum_value_desc("Broadcast", "To the destination endpoint of all", 2)
+                .add_enum_value_desc("Group", "To the endpoints of a group", 3)
+        })
+        .add_bit_field("Ack_format", "What an acknowledgement refers to", 1, |f| {
+            f.add_enum_value("Data_ack", 0)
+                .add_enum_value("Command_ack", 1)
+        })
+        .add_bit_field("Security", "The payload is secured by the APS", 1, |f| f)
+        .add_bit_field(
+            "Ack_request",
+            "The recipient must acknowledge the frame",
+            1,
+            |f| f,
+        )
+        .add_bit_field(
+            "Extended_header",
+            "The extended header is present",
+            1,
+            presence,
+        );
+    tree.add_bitfield(&aps_frame_control)?;
+
+    let extended_frame_control =
+        BitField::new("extended_frame_control", "Extended APS frame control field")
+            .add_bit_field("Fragmentation", "Fragmentation of the payload", 2, |f| {
+                f.add_enum_value_desc("None", "The payload is not fragmented", 0)
+                    .add_enum_value_desc("First", "The first fragment", 1)
+                    .add_enum_value_desc("Part", "A later fragment", 2)
+            })
+            .add_reserved(6);
+    tree.add_bitfield(&extended_frame_control)?;
+
+    let aps_command_id = BitField::new("aps_command_id", "Identifier of an APS command")
+        .add_bit_field("Command_id", "The APS command", 8, |f| {
+            APS_COMMANDS
+                .iter()
+                .fold(f, |f, (variant, value)| f.add_enum_value(variant, *value))
+        });
+    tree.add_bitfield(&aps_command_id)?;
+
+    // parts of the NWK header which are present depending on its frame control
+    let ieee_address = Structure::new("ieee_address")
+        .with_desc("IEEE address of a device")
+        .add_eui64_field("address");
+    tree.add_struct(&ieee_address)?;
+
+    let multicast = Structure::new("multicast")
+        .with_desc("Multicast control of a frame sent to a group")
+        .add_bitfield_of("control", &multicast_control);
+    tree.add_struct(&multicast)?;
+
+    let source_route = Structure::new("source_route")
+        .with_desc("Relays of a source routed frame")
+        .add_len_field("relay_count", 1)
+        .add_u8_field_desc("relay_index", "Index of the next relay")
+        .add_repeated_field("relay_list", 2, "relay_count", RELAY_CAPACITY);
+    tree.add_struct(&source_route)?;
+
+    let key_sequence = Structure::new("key_sequence")
+        .with_desc("Sequence number of a network key")
+        .add_u8_field("key_sequence_number");
+    tree.add_struct(&key_sequence)?;
+
+    let nwk_aux_header = Structure::new("nwk_aux_header")
+        .with_desc("Auxiliary security header of a secured frame")
+        .add_bitfield_of("security_control", &nwk_security_control)
+        .add_u32_field_desc("frame_counter", "Frame counter of the sender")
+        .add_optional_field(
+            "source_address",
+            &ieee_address,
+            "security_control",
+            "Extended_nonce",
+        )
+        .add_optional_field_when(
+            "key",
+            &key_sequence,
+            "security_control",
+            "Key_id",
+            &["Network"],
+        );
+    tree.add_struct(&nwk_aux_header)?;
+
+    let nwk_header = Structure::new("nwk_header")
+        .with_desc("NWK header, following the MAC header of a data frame")
+        .add_bitfield_of("frame_control", &nwk_frame_control)
+        .add_u16_field_desc("destination", "Short address of the destination or group")
+        .add_u16_field_desc("source", "Short address of the source")
+        .add_u8_field_desc("radius", "Number of hops the frame may still take")
+        .add_u8_field("sequence_number")
+        .add_optional_field(
+            "destination_ieee",
+            &ieee_address,
+            "frame_control",
+            "Destination_ieee",
+        )
+        .add_optional_field("source_ieee", &ieee_address, "frame_control", "Source_ieee")
+        .add_optional_field("multicast", &multicast, "frame_control", "Multicast")
+        .add_optional_field(
+            "source_route",
+            &source_route,
+            "frame_control",
+            "Source_route",
+        )
+        .add_optional_field("aux_header", &nwk_aux_header, "frame_control", "Security");
+    tree.add_struct(&nwk_header)?;
+
+    let nwk_command = Structure::new("nwk_command")
+        .with_desc("Identifier of a NWK command, followed by its payload")
+        .add_bitfield_of("command_id", &nwk_command_id);
+    tree.add_struct(&nwk_command)?;
+
+    let link_status_entry = Structure::new("link_status_entry")
+        .with_desc("Link to a neighbor of the sender")
+        .add_u16_field("network_address")
+        .add_u8_field_desc(
+            "link_status",
+            "Incoming cost in bits 0 to 2 and outgoing cost in bits 4 to 6",
+        );
+    let link_status = Structure::new("link_status")
+        .with_desc("Payload of a link status command, followed by its entries")
+        .add_u8_field_desc(
+            "options",
+            "Number of entries in bits 0 to 4, first and last frame in bits 5 and 6",
+        )
+        .with_trailing("entries", &link_status_entry);
+    tree.add_struct(&link_status_entry)?;
+    tree.add_struct(&link_status)?;
+
+    // parts of the APS headers which are present depending on their frame control
+    // destinations of APS data frames, selected by the delivery mode
+    let unicast = Structure::new("unicast")
+        .with_desc("Destination endpoint of a unicast frame")
+        .add_u8_field("endpoint");
+    let broadcast = Structure::new("broadcast")
+        .with_desc("Destination endpoint of a broadcast frame")
+        .add_u8_field("endpoint");
+    let group = Structure::new("group")
+        .with_desc("Destination group of a group addressed frame")
+        .add_u16_field("group_address");
+    let destinations = Alternatives::new().insert_new_option("destination", &unicast, |o| {
+        o.set_discriminant(&unicast, "Unicast")
+            .insert_type_discriminant(&broadcast, "Broadcast")
+            .insert_type_discriminant(&group, "Group")
+    });
+    tree.add_struct(&unicast)?;
+    tree.add_struct(&broadcast)?;
+    tree.add_struct(&group)?;
+    tree.add_alternatives(&destinations)?;
+
+    let block = Structure::new("block")
+        .with_desc("Block of a fragmented frame")
+        .add_u8_field_desc(
+            "block_number",
+            "Number of blocks for the first, else its index",
+        );
+    tree.add_struct(&block)?;
+
+    let block_ack = Structure::new("block_ack")
+        .with_desc("Blocks acknowledged of a fragmented frame")
+        .add_u8_field_desc("block_number", "Index of the first acknowledged block")
+        .add_u8_field_desc("ack_bitfield", "Blocks received following the first");
+    tree.add_struct(&block_ack)?;
+
+    let extended_header = Structure::new("extended_header")
+        .with_desc("Extended header of a data frame")
+        .add_bitfield_of("control", &extended_frame_control)
+        .add_optional_field_when(
+            "block",
+            &block,
+            "control",
+            "Fragmentation",
+            &["First", "Part"],
+        );
+    tree.add_struct(&extended_header)?;
+
+    let ack_extended_header = Structure::new("ack_extended_header")
+        .with_desc("Extended header of an acknowledgement")
+        .add_bitfield_of("control", &extended_frame_control)
+        .add_optional_field_when(
+            "block_ack",
+            &block_ack,
+            "control",
+            "Fragmentation",
+            &["First", "Part"],
+        );
+    tree.add_struct(&ack_extended_header)?;
+
+    let ack_addressing = Structure::new("ack_addressing")
+        .with_desc("Addressing of the data frame an acknowledgement refers to")
+        .add_u8_field("destination_endpoint")
+        .add_u16_field("cluster_id")
+        .add_u16_field("profile_id")
+        .add_u8_field("source_endpoint");
+    tree.add_struct(&ack_addressing)?;
+
+    let aps_data = Structure::new("aps_data")
+        .with_desc("Header of an APS data frame, followed by its payload")
+        .add_bitfield_of("frame_control", &aps_frame_control)
+        .add_alt_field_discriminated(
+            "destination",
+            destinations.get("destination")?,
+            "frame_control",
+            "Delivery_mode",
+        )
+        .add_u16_field("cluster_id")
+        .add_u16_field("profile_id")
+        .add_u8_field("source_endpoint")
+        .add_u8_field_desc("aps_counter", "Counter matching acknowledgements to frames")
+        .add_optional_field(
+            "extended_header",
+            &extended_header,
+            "frame_control",
+            "Extended_header",
+        );
+    tree.add_struct_with_alts(&aps_data, &destinations)?;
+
+    let aps_command = Structure::new("aps_command")
+        .with_desc("Header of an APS command, followed by its payload")
+        .add_bitfield_of("frame_control", &aps_frame_control)
+        .add_u8_field_desc("aps_counter", "Counter matching acknowledgements to frames")
+        .add_bitfield_of("command_id", &aps_command_id);
+
+    let aps_ack = Structure::new("aps_ack")
+        .with_desc("APS acknowledgement of a data frame or command")
+        .add_bitfield_of("frame_control", &aps_frame_control)
+        .add_optional_field_when(
+            "addressing",
+            &ack_addressing,
+            "frame_control",
+            "Ack_format",
+            &["Data_ack"],
+        )
+        .add_u8_field_desc("aps_counter", "Counter of the acknowledged frame")
+        .add_optional_field(
+            "extended_header",
+            &ack_extended_header,
+            "frame_control",
+            "Extended_header",
+        );
+    tree.add_struct(&aps_command)?;
+    tree.add_struct(&aps_ack)?;
+
+    let aps_frame = Frames::new("aps_frame", "aps_frame_control", 1, "Frame_type")
+        .with_desc("APS frame, selected by the type in its frame control field.")
+        .add_frame("Data", &aps_data)
+        .add_frame("Command", &aps_command)
+        .add_frame("Ack", &aps_ack);
+    tree.add_frames(&aps_frame)
+}
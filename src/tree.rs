@@ -0,0 +1,794 @@
+use std::collections::HashMap;
+use std::fs::create_dir_all;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+
+use crate::doc::Document;
+use crate::file::GenFile;
+use crate::generate::bitfield::{BitField, BitfieldRegistry, Enumeration};
+use crate::generate::checksum;
+use crate::generate::frame::Frames;
+use crate::generate::frame_trait;
+use crate::generate::framing;
+use crate::generate::generic;
+use crate::generate::structure::{
+    self, Alternatives, Payloads, SimpleStructure, StructMember, Structure,
+};
+use crate::generate::tlv::Tlv;
+use crate::util::{
+    self, Config, Derives, IoBackend, Namespace, Naming, ToSanitizedPascalCase,
+    ToSanitizedSnakeCase,
+};
+
+/// The kind of items held by a module of a [`GenTree`], which decides how it is exposed by the
+/// generated `mod.rs`.
+#[derive(Clone, Copy, PartialEq)]
+enum ModuleKind {
+    /// Structures, which are re-exported and may refer to the other modules.
+    Structure,
+    /// Alternatives, which are re-exported.
+    Alternatives,
+    /// The frames of a protocol, which are re-exported and refer to the structures.
+    Frames,
+    /// TLV entries, which are re-exported and refer to the structures.
+    Tlv,
+    /// Enumerations shared by bitfields, which are re-exported for the bitfields to refer to.
+    Enumeration,
+    /// A bitfield, which is kept in its own public module as its `R` and `W` would clash.
+    Bitfield,
+}
+
+struct Module {
+    name: String,
+    kind: ModuleKind,
+    file: GenFile,
+    /// Condition under which the module is compiled, for the layouts of versioned structures.
+    cfg: Option<TokenStream>,
+    /// Enums of the enumerated fields of a bitfield, which are re-exported by the prelude.
+    field_enums: Vec<String>,
+}
+
+/// Generated code split into one module file per structure, alternatives, frames, enumeration
+/// and bitfield, which are tied together by a generated `mod.rs`. Structures, alternatives and
+/// frames are re-exported from `mod.rs` and see each other through `use super::*`, enumerations are
+/// re-exported for the bitfields sharing them, and bitfields are public modules named after the
+/// bitfield, similar to the peripherals of svd2rust. A `prelude` module re-exports all of them.
+#[derive(Default)]
+pub struct GenTree {
+    items: TokenStream,
+    modules: Vec<Module>,
+    config: Config,
+    derives: Derives,
+    tests: bool,
+    proptests: bool,
+    fuzz: Option<String>,
+    heapless: bool,
+    /// Whether any structure holds `alloc` payloads, which are only compiled with the `alloc`
+    /// feature of the generated crate.
+    alloc: bool,
+    /// Whether any structure holds a timestamp, which may be converted into the types of
+    /// `chrono` and `fugit`.
+    timestamps: bool,
+    /// Whether any definition overrides the derives of the tree to derive the `serde` traits.
+    serde: bool,
+    /// Whether any definition overrides the derives of the tree to derive `defmt::Format`.
+    defmt: bool,
+    bitfields: BitfieldRegistry,
+    /// Functions parsing the frames of the tree, which are all re-exported from `mod.rs`.
+    parse_fns: Vec<String>,
+    /// Documentation of everything added to the tree, see [`GenTree::write_doc`].
+    doc: Document,
+    /// Versions of the protocol from the oldest to the newest, see [`GenTree::set_versions`].
+    versions: Vec<String>,
+}
+
+/// The condition compiling the module of the structure `s`, which needs the `alloc` feature of
+/// the generated crate if it holds `alloc` payloads.
+fn alloc_cfg(s: &Structure) -> Option<TokenStream> {
+    (s.payloads == Payloads::Alloc).then(|| quote! { #[cfg(feature = "alloc")] })
+}
+
+impl GenTree {
+    pub fn new() -> Self {
+        GenTree::default()
+    }
+
+    /// Make a tree generated according to `config`, which also decides how [`GenTree::write`]
+    /// lays out the output.
+    pub fn from_config(config: &Config) -> Self {
+        GenTree {
+            config: config.clone(),
+            ..GenTree::default()
+        }
+    }
+
+    /// Emit round trip tests in every module, see [`GenFile::set_tests`].
+    pub fn set_tests(&mut self, tests: bool) {
+        self.tests = tests;
+    }
+
+    /// Emit property tests in every module, see [`GenFile::set_proptests`]. The strategies of
+    /// the structures are shared with the other modules, so optional structures may be added
+    /// to any module of the tree.
+    pub fn set_proptests(&mut self, proptests: bool) {
+        self.proptests = proptests;
+    }
+
+    /// Collect a `cargo fuzz` target for every structure and bitfield, where `path` is the path
+    /// of the generated `mod.rs` from the fuzz crate, e.g. `protocol::ieee802154`, or the
+    /// [`crate_name`] of a generated crate, see [`GenTree::write_fuzz_crate`].
+    pub fn set_fuzz_targets(&mut self, path: &str) -> Result<()> {
+        syn::parse_str::<syn::Path>(path)?;
+        self.fuzz = Some(path.into());
+        Ok(())
+    }
+
+    /// Select the I/O traits used by the generated code.
+    pub fn set_io(&mut self, io: IoBackend) {
+        self.config.io = io;
+    }
+
+    /// Generate the identifiers of the definitions named `name` from `ident` instead, see
+    /// [`Naming::rename`]. Only definitions added afterwards are renamed.
+    pub fn rename(&mut self, name: &str, ident: &str) -> Result<()> {
+        self.config.naming.rename(name, ident)
+    }
+
+    /// Select the traits derived on the generated types, which structures may override.
+    pub fn set_derives(&mut self, derives: Derives) {
+        self.derives = derives;
+    }
+
+    /// Generate the versioned structures for the protocol `versions`, ordered from the oldest
+    /// to the newest, each of which is selected by the cargo feature of its name. The newest
+    /// version whose feature is enabled is generated, or the oldest if none is, so enabling
+    /// more features stays additive. Every layout of a versioned structure is a module of its
+    /// own, named after the structure and the first version having that layout, which is only
+    /// compiled and re-exported when one of the versions having the layout is selected.
+    pub fn set_versions(&mut self, versions: &[&str]) -> Result<()> {
+        for version in versions {
+            let valid = version
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if version.is_empty() || !valid {
+                bail!("version `{}` is not a valid feature name", version);
+            }
+        }
+        self.versions = versions.iter().map(|v| String::from(*v)).collect();
+        Ok(())
+    }
+
+    /// The condition selecting one of the consecutive versions `first..=last`.
+    fn version_cfg(&self, first: usize, last: usize) -> TokenStream {
+        let enabled = &self.versions[first..=last];
+        let newer = &self.versions[last + 1..];
+        let mut conditions = vec![];
+        if first > 0 {
+            conditions.push(quote! { any(#(feature = #enabled),*) });
+        }
+        if !newer.is_empty() {
+            conditions.push(quote! { not(any(#(feature = #newer),*)) });
+        }
+        match conditions.as_slice() {
+            [condition] => quote! { #[cfg(#condition)] },
+            _ => quote! { #[cfg(all(#(#conditions),*))] },
+        }
+    }
+
+    /// Add a module `name` of the given `kind`, which has the settings of the tree.
+    fn module(&mut self, name: &str, kind: ModuleKind) -> Result<&mut GenFile> {
+        let name = name
+            .to_sanitized_snake_case(&self.config.naming.types())
+            .to_string();
+        if self.modules.iter().any(|module| module.name == name) {
+            bail!("module `{}` is already part of the tree", name);
+        }
+
+        let mut file = GenFile::from_config(&self.config);
+        file.set_derives(self.derives.clone());
+        file.set_tests(self.tests);
+        file.set_proptests(self.proptests);
+        if let Some(path) = &self.fuzz {
+            // structures are reached through their re-export
+            match kind {
+                ModuleKind::Bitfield => file.set_fuzz_targets(&format!("{}::{}", path, name))?,
+                _ => file.set_fuzz_targets(path)?,
+            }
+        }
+        if let ModuleKind::Structure
+        | ModuleKind::Alternatives
+        | ModuleKind::Frames
+        | ModuleKind::Tlv = kind
+        {
+            file.add_struct_imports()?;
+            file.register_bitfields(&self.bitfields);
+        }
+
+        self.modules.push(Module {
+            name,
+            kind,
+            file,
+            cfg: None,
+            field_enums: vec![],
+        });
+        Ok(&mut self.modules.last_mut().unwrap().file)
+    }
+
+    /// Note the derives of a definition overriding those of the tree.
+    fn derive(&mut self, derives: Option<&Derives>) {
+        if let Some(derives) = derives {
+            self.serde |= derives.serde;
+            self.defmt |= derives.defmt;
+        }
+    }
+
+    /// Note the dependencies of the structure `s`, and its byte order for the bitfields it
+    /// holds, see [`GenFile::note_byte_order`].
+    fn depend(&mut self, s: &Structure) {
+        self.derive(s.derives.as_ref());
+        for mem in &s.members {
+            let mem = match mem {
+                StructMember::BitfieldMember(mem) => mem,
+                _ => continue,
+            };
+            let name = mem
+                .bitfield
+                .to_sanitized_snake_case(&self.config.naming.types());
+            let module = self
+                .modules
+                .iter_mut()
+                .find(|module| module.kind == ModuleKind::Bitfield && module.name == name);
+            if let Some(module) = module {
+                module.file.note_byte_order(&mem.bitfield, s.byte_order);
+            }
+        }
+        self.heapless |= s.members.iter().any(|mem| match mem {
+            StructMember::RepeatedMember(_) => true,
+            StructMember::StringMember(_) => s.payloads != Payloads::Alloc,
+            StructMember::BytesMember(_) => s.payloads == Payloads::Heapless,
+            _ => false,
+        });
+        self.alloc |= s.payloads == Payloads::Alloc;
+        self.timestamps |= s
+            .members
+            .iter()
+            .any(|mem| matches!(mem, StructMember::TimestampMember(_)));
+    }
+
+    /// Add the structure `s`, whose bitfield members must refer to bitfields defined inline or
+    /// already added to the tree.
+    pub fn add_struct(&mut self, s: &Structure) -> Result<()> {
+        if s.is_versioned() {
+            return self.add_versioned_struct(s);
+        }
+        self.depend(s);
+        s.declare(&mut self.config.naming);
+        self.module(&s.name, ModuleKind::Structure)?.add_struct(s)?;
+        self.modules.last_mut().unwrap().cfg = alloc_cfg(s);
+        self.doc.add_struct(s)
+    }
+
+    /// Add a module for every layout of the versioned structure `s`, see
+    /// [`GenTree::set_versions`].
+    fn add_versioned_struct(&mut self, s: &Structure) -> Result<()> {
+        if self.versions.is_empty() {
+            bail!(
+                "structure `{}` is versioned, but the tree has no versions",
+                s.name
+            );
+        }
+
+        // consecutive versions with the same members share a layout
+        let mut layouts: Vec<(usize, usize, Structure)> = vec![];
+        for idx in 0..self.versions.len() {
+            let layout = match s
+                .at_version(&self.versions, idx)
+                .with_context(|| format!("in structure `{}`", s.name))?
+            {
+                Some(layout) => layout,
+                None => continue,
+            };
+            if let Some((_, last, previous)) = layouts.last_mut() {
+                let names = previous.members.iter().map(StructMember::name);
+                if *last + 1 == idx && names.eq(layout.members.iter().map(StructMember::name)) {
+                    *last = idx;
+                    continue;
+                }
+            }
+            layouts.push((idx, idx, layout));
+        }
+
+        for (first, last, mut layout) in layouts {
+            let versions = if first == last {
+                format!("version `{}`", self.versions[first])
+            } else {
+                format!(
+                    "versions `{}` to `{}`",
+                    self.versions[first], self.versions[last]
+                )
+            };
+            if !layout.desc.is_empty() {
+                layout.desc.push_str("\n\n");
+            }
+            layout.desc.push_str(&format!("Layout of {}.", versions));
+
+            self.depend(&layout);
+            layout.declare(&mut self.config.naming);
+            let name = format!("{}_{}", s.name, self.versions[first]);
+            let mut cfg = self.version_cfg(first, last);
+            cfg.extend(alloc_cfg(&layout));
+            self.module(&name, ModuleKind::Structure)?
+                .add_struct(&layout)
+                .with_context(|| format!("in the layout of {}", versions))?;
+            self.modules.last_mut().unwrap().cfg = Some(cfg);
+            self.doc.add_struct(&layout)?;
+        }
+        Ok(())
+    }
+
+    pub fn add_struct_simple(&mut self, s: &SimpleStructure) -> Result<()> {
+        self.derive(s.derives.as_ref());
+        self.config
+            .naming
+            .declare(Namespace::Types, [s.name.as_str()]);
+        self.module(&s.name, ModuleKind::Structure)?
+            .add_struct_simple(s)?;
+        self.doc.add_struct_simple(s);
+        Ok(())
+    }
+
+    pub fn add_struct_with_alts(&mut self, s: &Structure, alts: &Alternatives) -> Result<()> {
+        self.depend(s);
+        s.declare(&mut self.config.naming);
+        self.module(&s.name, ModuleKind::Structure)?
+            .add_struct_with_alts(s, alts)?;
+        self.modules.last_mut().unwrap().cfg = alloc_cfg(s);
+        self.doc.add_struct(s)
+    }
+
+    /// Add every set of alternatives in `alts` as a module named after its key.
+    pub fn add_alternatives(&mut self, alts: &Alternatives) -> Result<()> {
+        alts.declare(&mut self.config.naming);
+        let mut keys = alts.map.keys().collect::<Vec<_>>();
+        keys.sort();
+        for key in keys {
+            let options = Alternatives::new().insert(&alts.map[key]);
+            self.module(key, ModuleKind::Alternatives)?
+                .add_alternatives(&options)?;
+            self.doc.add_alternatives(&alts.map[key]);
+        }
+        Ok(())
+    }
+
+    /// Add the enum of the `frames` and their `parse` function as a module re-exported like the
+    /// structures, whose header must already be added to the tree. Further frames need another
+    /// name for their function, see [`Frames::with_parse_fn`].
+    pub fn add_frames(&mut self, frames: &Frames) -> Result<()> {
+        let parse_fn = frames
+            .parse_fn
+            .to_sanitized_snake_case(&self.config.naming.types())
+            .to_string();
+        if self.parse_fns.contains(&parse_fn) {
+            bail!(
+                "function `{}` of frames `{}` is already part of the tree",
+                parse_fn,
+                frames.name
+            );
+        }
+        self.derive(frames.derives.as_ref());
+        self.config
+            .naming
+            .declare(Namespace::Types, [frames.name.as_str()]);
+        self.module(&frames.name, ModuleKind::Frames)?
+            .add_frames(frames)?;
+        self.parse_fns.push(parse_fn);
+        self.doc.add_frames(frames);
+        Ok(())
+    }
+
+    /// Add the enum of the TLV entries `tlv` along with their iterator and builder as a module
+    /// re-exported like the structures, whose entries must already be added to the tree.
+    pub fn add_tlv(&mut self, tlv: &Tlv) -> Result<()> {
+        self.derive(tlv.derives.as_ref());
+        self.config
+            .naming
+            .declare(Namespace::Types, [tlv.name.as_str()]);
+        self.module(&tlv.name, ModuleKind::Tlv)?.add_tlv(tlv)?;
+        self.doc.add_tlv(tlv);
+        Ok(())
+    }
+
+    pub fn add_bitfield(&mut self, bitfield: &BitField) -> Result<()> {
+        self.bitfields.register(bitfield)?;
+        bitfield.declare(&mut self.config.naming);
+        self.module(&bitfield.name, ModuleKind::Bitfield)?
+            .add_bitfield(bitfield)?;
+        self.modules.last_mut().unwrap().field_enums = bitfield.field_enums(&self.config.naming);
+        self.doc.add_bitfield(bitfield)
+    }
+
+    pub fn add_enumeration(&mut self, enumeration: &Enumeration) -> Result<()> {
+        enumeration.declare(&mut self.config.naming);
+        self.module(&enumeration.name, ModuleKind::Enumeration)?
+            .add_enumeration(enumeration)?;
+        self.doc.add_enumeration(enumeration);
+        Ok(())
+    }
+
+    /// Add the `Error` to `mod.rs`, which is only valid when the tree is the root of the
+    /// generated crate, see [`GenFile::add_error_support`].
+    pub fn add_error_support(&mut self) -> Result<()> {
+        self.items
+            .extend(structure::render_error_support(self.config.io));
+        Ok(())
+    }
+
+    /// Render the generated `mod.rs`.
+    pub fn render(&self) -> TokenStream {
+        self.render_tree(false)
+    }
+
+    /// Render the modules of the tree along with its prelude, which also re-exports the support
+    /// at the root of a generated crate if `root` is set.
+    fn render_tree(&self, root: bool) -> TokenStream {
+        let span = Span::call_site();
+        let mut dat = self.items.clone();
+
+        for module in &self.modules {
+            let name = util::ident(&module.name, span);
+            let cfg = &module.cfg;
+            dat.extend(match module.kind {
+                ModuleKind::Bitfield => quote! { pub mod #name; },
+                _ => quote! {
+                    #cfg
+                    mod #name;
+                    #cfg
+                    pub use self::#name::*;
+                },
+            });
+            if self.proptests && module.kind == ModuleKind::Structure {
+                dat.extend(quote! {
+                    #cfg
+                    #[cfg(test)]
+                    use self::#name::proptests::*;
+                });
+            }
+        }
+        dat.extend(self.render_prelude(root));
+
+        dat
+    }
+
+    /// Render the `prelude` module, re-exporting the types re-exported by `mod.rs` along with
+    /// every bitfield module, its `R` and `W` prefixed by the name of the bitfield, e.g.
+    /// `FrameControlR`, and the enums of its enumerated fields. The enums of fields of the same
+    /// name in several bitfields are prefixed by the name of their bitfield as well.
+    fn render_prelude(&self, root: bool) -> TokenStream {
+        let span = Span::call_site();
+        let mut items = TokenStream::new();
+        if root {
+            items.extend(quote! {
+                pub use super::Error;
+                pub use super::traits::Frame;
+            });
+        }
+
+        let bitfields = self
+            .modules
+            .iter()
+            .filter(|module| module.kind == ModuleKind::Bitfield);
+        let mut field_enums = HashMap::<&str, usize>::new();
+        for module in bitfields.clone() {
+            for field_enum in &module.field_enums {
+                *field_enums.entry(field_enum).or_default() += 1;
+            }
+        }
+
+        for module in &self.modules {
+            let name = util::ident(&module.name, span);
+            let cfg = &module.cfg;
+            if module.kind != ModuleKind::Bitfield {
+                items.extend(quote! {
+                    #cfg
+                    pub use super::#name::*;
+                });
+                continue;
+            }
+
+            let name_pc = module
+                .name
+                .to_sanitized_pascal_case(&self.config.naming.types());
+            let r = util::ident(&format!("{}R", name_pc), span);
+            let w = util::ident(&format!("{}W", name_pc), span);
+            items.extend(quote! {
+                pub use super::#name::{self, R as #r, W as #w};
+            });
+            for field_enum in &module.field_enums {
+                let ident = util::ident(field_enum, span);
+                items.extend(if field_enums[field_enum.as_str()] > 1 {
+                    let alias = util::ident(&format!("{}{}", name_pc, field_enum), span);
+                    quote! { pub use super::#name::#ident as #alias; }
+                } else {
+                    quote! { pub use super::#name::#ident; }
+                });
+            }
+        }
+
+        quote! {
+            #[doc = "The types of the protocol, e.g. for `use prelude::*`."]
+            pub mod prelude {
+                #items
+            }
+        }
+    }
+
+    /// Write the documentation of everything added to the tree to `path`, titled `title` and
+    /// introduced by `desc`, as HTML if `path` ends in `.html` and as Markdown otherwise.
+    pub fn write_doc<P: AsRef<Path>>(&self, path: P, title: &str, desc: &str) -> Result<()> {
+        let path = path.as_ref();
+        let html = path
+            .extension()
+            .is_some_and(|ext| ext == "html" || ext == "htm");
+        let doc = if html {
+            self.doc.to_html(title, desc)
+        } else {
+            self.doc.to_markdown(title, desc)
+        };
+        let mut file = File::create(path)?;
+        file.write_all(doc.as_bytes())?;
+        Ok(())
+    }
+
+    /// Write `<dir>/mod.rs` and a `<dir>/<module>.rs` for every module.
+    pub fn write_tree<P: AsRef<Path>>(&self, dir: P) -> Result<()> {
+        let dir = dir.as_ref();
+        create_dir_all(dir)?;
+
+        let mut file = File::create(dir.join("mod.rs"))?;
+        file.write_all(util::unparse(&self.render())?.as_ref())?;
+
+        self.write_modules(dir)
+    }
+
+    /// Write a complete `no_std` crate `name` to `dir`, which holds the modules of the tree at
+    /// its root along with the `Error`, `FieldReader`, checksum and I/O support they rely on.
+    /// The manifest depends on the I/O backend and on `heapless` when needed. Crates whose
+    /// types derive the `serde` traits have a `serde` feature compiling these derives, and
+    /// crates deriving `defmt::Format` a `defmt` feature. Crates holding timestamps also
+    /// have `chrono` and `fugit` features enabling their conversions, and crates holding
+    /// `alloc` payloads an `alloc` feature compiling the structures holding them. The package
+    /// is named like a module, e.g. `_802_15_4_hdr` for `802_15_4_hdr`.
+    pub fn write_crate(&self, dir: &str, name: &str, version: &str) -> Result<()> {
+        let dir = Path::new(dir);
+        let src = dir.join("src");
+        create_dir_all(&src)?;
+
+        let name = crate_name(name);
+        let mut file = File::create(dir.join("Cargo.toml"))?;
+        file.write_all(self.render_manifest(&name, version).as_ref())?;
+
+        self.write_lib(&src)
+    }
+
+    /// Write the tree to the `output_dir` of its configuration, as a `mod.rs` if `make_mod` is
+    /// set and as the `lib.rs` of a `no_std` crate otherwise.
+    pub fn write(&self) -> Result<()> {
+        let dir = &self.config.output_dir;
+        if self.config.make_mod {
+            self.write_tree(dir)
+        } else {
+            self.write_lib(dir)
+        }
+    }
+
+    /// Write `<dir>/lib.rs`, holding the modules and the support they rely on, and the module
+    /// files. The `FieldReader` support is written to `<dir>/generic.rs` if `generic_mod` is
+    /// set.
+    fn write_lib(&self, dir: &Path) -> Result<()> {
+        create_dir_all(dir)?;
+        let io = self.config.io;
+
+        let mut lib = quote! {
+            #![cfg_attr(not(test), no_std)]
+        };
+        if self.alloc {
+            lib.extend(quote! {
+                #[cfg(feature = "alloc")]
+                extern crate alloc;
+            });
+        }
+        if self.config.generic_mod {
+            lib.extend(quote! {
+                mod generic;
+                pub use generic::*;
+            });
+            let mut file = File::create(dir.join("generic.rs"))?;
+            file.write_all(util::unparse(&generic::render())?.as_ref())?;
+        } else {
+            lib.extend(generic::render());
+        }
+        lib.extend(structure::render_error_support(io));
+        lib.extend(checksum::render_support(io));
+        lib.extend(framing::render_support());
+        lib.extend(frame_trait::render_support());
+        lib.extend(structure::render_io_support(io));
+        lib.extend(self.render_tree(true));
+
+        let mut file = File::create(dir.join("lib.rs"))?;
+        file.write_all(util::unparse(&lib)?.as_ref())?;
+
+        self.write_modules(dir)
+    }
+
+    /// Render the `Cargo.toml` of a generated crate.
+    fn render_manifest(&self, name: &str, version: &str) -> String {
+        let mut dependencies = String::new();
+        let serde = self.derives.serde || self.serde;
+        let defmt = self.derives.defmt || self.defmt;
+        let mut serde_features = vec!["\"dep:serde\""];
+        let mut defmt_features = vec!["\"dep:defmt\""];
+
+        match self.config.io {
+            IoBackend::Core2 => {
+                dependencies.push_str("core2 = { version = \"0.4\", default-features = false }\n")
+            }
+            IoBackend::Core => {}
+            IoBackend::EmbeddedIo => dependencies.push_str("embedded-io = \"0.6\"\n"),
+        }
+        if self.heapless {
+            dependencies.push_str("heapless = \"0.8\"\n");
+            serde_features.push("\"heapless/serde\"");
+            defmt_features.push("\"heapless/defmt-03\"");
+        }
+        let mut features = String::new();
+        if serde {
+            dependencies.push_str(concat!(
+                "serde = { version = \"1.0\", default-features = false, ",
+                "features = [\"derive\"], optional = true }\n",
+            ));
+            features.push_str(&format!("serde = [{}]\n", serde_features.join(", ")));
+        }
+        if defmt {
+            dependencies.push_str("defmt = { version = \"0.3\", optional = true }\n");
+            features.push_str(&format!("defmt = [{}]\n", defmt_features.join(", ")));
+        }
+        if self.timestamps {
+            dependencies.push_str(concat!(
+                "chrono = { version = \"0.4.31\", default-features = false, optional = true }\n",
+                "fugit = { version = \"0.3\", optional = true }\n",
+            ));
+            features.push_str("chrono = [\"dep:chrono\"]\nfugit = [\"dep:fugit\"]\n");
+        }
+        if self.alloc {
+            let mut alloc = vec![];
+            if serde {
+                alloc.push("\"serde?/alloc\"");
+            }
+            if defmt {
+                alloc.push("\"defmt?/alloc\"");
+            }
+            features.push_str(&format!("alloc = [{}]\n", alloc.join(", ")));
+        }
+        for version in &self.versions {
+            features.push_str(&format!("{} = []\n", version));
+        }
+
+        let dev_dependencies = if self.proptests {
+            "\n[dev-dependencies]\nproptest = \"1.0\"\n"
+        } else {
+            ""
+        };
+
+        format!(
+            "[package]\nname = \"{}\"\nversion = \"{}\"\nedition = \"2021\"\n\n\
+             [dependencies]\n{}\n\
+             [features]\n{}{}",
+            name, version, dependencies, features, dev_dependencies
+        )
+    }
+
+    /// Write a `<dir>/<module>.rs` for every module.
+    fn write_modules(&self, dir: &Path) -> Result<()> {
+        for module in &self.modules {
+            let mut dat = TokenStream::new();
+            if let ModuleKind::Structure
+            | ModuleKind::Alternatives
+            | ModuleKind::Frames
+            | ModuleKind::Tlv = module.kind
+            {
+                dat.extend(quote! {
+                    #[allow(unused_imports)]
+                    use super::*;
+                });
+            }
+            dat.extend(module.file.render());
+
+            // `mod r#type;` is looked up in `type.rs`
+            let path = format!("{}.rs", module.name.trim_start_matches("r#"));
+            let mut file = File::create(dir.join(path))?;
+            file.write_all(util::unparse(&dat)?.as_ref())?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the fuzz targets of every module to `dir` and return their names, see
+    /// [`GenFile::write_fuzz_targets`].
+    pub fn write_fuzz_targets(&self, dir: &str) -> Result<Vec<String>> {
+        let mut names = vec![];
+        for module in &self.modules {
+            names.extend(module.file.write_fuzz_targets(dir)?);
+        }
+        Ok(names)
+    }
+
+    /// Write a complete `cargo fuzz` crate to `dir`, holding the fuzz targets of the tree in
+    /// `fuzz_targets` with a `[[bin]]` entry for each of them. It depends on the crate `name`
+    /// written to `crate_dir` by [`GenTree::write_crate`], whose types the targets must reach,
+    /// i.e. [`GenTree::set_fuzz_targets`] is given the [`crate_name`] of `name`.
+    pub fn write_fuzz_crate(&self, dir: &str, crate_dir: &str, name: &str) -> Result<()> {
+        let dir = Path::new(dir);
+        create_dir_all(dir)?;
+        let targets = dir.join("fuzz_targets");
+        let names = self.write_fuzz_targets(targets.to_str().context("invalid directory")?)?;
+
+        // the generated crate is referred to relative to the fuzz crate, like `cargo fuzz init`
+        let path = relative_path(&dir.canonicalize()?, &Path::new(crate_dir).canonicalize()?);
+        let path = path
+            .to_str()
+            .context("path of the crate is not valid UTF-8")?;
+        let name = crate_name(name);
+        let features = if self.alloc {
+            ", features = [\"alloc\"]"
+        } else {
+            ""
+        };
+
+        let mut manifest = format!(
+            "[package]\nname = \"{}-fuzz\"\nversion = \"0.0.0\"\npublish = false\n\
+             edition = \"2021\"\n\n[package.metadata]\ncargo-fuzz = true\n\n\
+             [dependencies]\nlibfuzzer-sys = \"0.4\"\n{} = {{ path = \"{}\"{} }}\n\n\
+             [workspace]\nmembers = [\".\"]\n",
+            name, name, path, features
+        );
+        for target in names {
+            manifest.push_str(&format!(
+                "\n[[bin]]\nname = \"{}\"\npath = \"fuzz_targets/{}.rs\"\n\
+                 test = false\ndoc = false\nbench = false\n",
+                target, target
+            ));
+        }
+        let mut file = File::create(dir.join("Cargo.toml"))?;
+        file.write_all(manifest.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// The name of the package of the crate `name` written by [`GenTree::write_crate`], which is
+/// also the path of its root from other crates.
+pub fn crate_name(name: &str) -> String {
+    // package names cannot start with a digit or be keywords either
+    name.to_sanitized_snake_case(&Naming::default().types())
+        .into()
+}
+
+/// The path of `to` relative to `from`, both of which are absolute.
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from = from.components().collect::<Vec<_>>();
+    let to = to.components().collect::<Vec<_>>();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut path = PathBuf::new();
+    for _ in common..from.len() {
+        path.push(Component::ParentDir);
+    }
+    path.extend(&to[common..]);
+    if path.as_os_str().is_empty() {
+        path.push(Component::CurDir);
+    }
+    path
+}
@@ -1,8 +1,10 @@
 use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 
 use inflections::Inflect;
 use proc_macro2::{Ident, Literal, Span, TokenStream};
-use quote::ToTokens;
+use quote::{quote, ToTokens};
 use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, bail, Result};
@@ -19,12 +21,17 @@ pub struct Config {
     pub nightly: bool,
     pub generic_mod: bool,
     pub make_mod: bool,
+    /// Select the alternative held by each alternatives member of a typed structure by the
+    /// `u8` index of the alternative as a const parameter instead of a type parameter.
     pub const_generic: bool,
     pub ignore_groups: bool,
     pub keep_list: bool,
     pub strict: bool,
     pub output_dir: PathBuf,
     pub source_type: SourceType,
+    pub io: IoBackend,
+    /// How the identifiers are generated from the names of the protocol.
+    pub naming: Naming,
 }
 
 impl Default for Config {
@@ -40,14 +47,237 @@ impl Default for Config {
             strict: false,
             output_dir: PathBuf::from("."),
             source_type: SourceType::default(),
+            io: IoBackend::default(),
+            naming: Naming::default(),
         }
     }
 }
 
+impl Config {
+    /// Report a questionable definition described by `msg`, which is an error in strict mode
+    /// and a warning otherwise.
+    pub fn lint(&self, msg: &str) -> Result<()> {
+        if self.strict {
+            bail!("{}", msg);
+        }
+        let collected = LINTS.with(|lints| match lints.borrow_mut().as_mut() {
+            Some(lints) => {
+                lints.push(String::from(msg));
+                true
+            }
+            None => false,
+        });
+        if !collected {
+            log::warn!("{}", msg);
+        }
+        Ok(())
+    }
+}
+
+/// How the identifiers are generated from the names of a protocol, whose scopes are given to
+/// the sanitizers, see [`Scope`].
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct Naming {
+    /// Names replacing names of the protocol in the generated identifiers, see
+    /// [`Naming::rename`].
+    pub renames: BTreeMap<String, String>,
+    /// Keep names which are keywords as raw identifiers, e.g. `r#type`, instead of appending an
+    /// underscore to them.
+    pub raw_idents: bool,
+    /// The names of every namespace in the order they are declared, which tell apart the
+    /// names derived as the same identifier, see [`Naming::declare`].
+    namespaces: BTreeMap<Namespace, Vec<String>>,
+}
+
+impl Naming {
+    /// Generate the identifiers of the structures, members, fields and enumerated values named
+    /// `name` from `ident` instead, e.g. `Ieee802154Hdr` for `802_15_4_hdr`, which is cased
+    /// like the name it replaces, e.g. `ieee802154_hdr` for its module. Documentation and
+    /// messages keep the name of the protocol. Every definition of the name is renamed.
+    pub fn rename(&mut self, name: &str, ident: &str) -> Result<()> {
+        if syn::parse_str::<syn::Ident>(ident).is_err() {
+            bail!("`{}` cannot rename `{}` as it is not an identifier", ident, name);
+        }
+        match self.renames.get(name) {
+            Some(other) if other != ident => {
+                bail!("`{}` is already renamed to `{}`", name, other)
+            }
+            _ => {
+                self.renames.insert(String::from(name), String::from(ident));
+                Ok(())
+            }
+        }
+    }
+
+    /// Declare the `names` defined in the `namespace`, which are numbered in the order they
+    /// are first declared when they are derived as the same identifier, see [`ident_chars`].
+    /// Names declared again keep their number.
+    pub fn declare<'a, I>(&mut self, namespace: Namespace, names: I)
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let declared = self.namespaces.entry(namespace).or_default();
+        for name in names {
+            if !declared.iter().any(|other| other == name) {
+                declared.push(String::from(name));
+            }
+        }
+    }
+
+    /// The scope of the identifiers of the `namespace`.
+    fn scope(&self, namespace: &Namespace) -> Scope<'_> {
+        let names = self
+            .namespaces
+            .get(namespace)
+            .map_or(&[][..], Vec::as_slice);
+        Scope {
+            naming: self,
+            names,
+        }
+    }
+
+    /// The scope of the types of the tree and their modules.
+    pub fn types(&self) -> Scope<'_> {
+        self.scope(&Namespace::Types)
+    }
+
+    /// The scope of the members of the structure, the fields of the bitfield or the values of
+    /// the enumeration named `owner`.
+    pub fn names_of(&self, owner: &str) -> Scope<'_> {
+        self.scope(&Namespace::Names(String::from(owner)))
+    }
+
+    /// The scope of the enumerated values of the `field` of the `bitfield`.
+    pub fn values_of(&self, bitfield: &str, field: &str) -> Scope<'_> {
+        self.scope(&Namespace::Values(
+            String::from(bitfield),
+            String::from(field),
+        ))
+    }
+}
+
+/// The names whose identifiers must differ from each other.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Namespace {
+    /// The structures, bitfields, enumerations and other types of a tree.
+    Types,
+    /// The members of a structure, the fields of a bitfield or the values of an enumeration,
+    /// named by their owner.
+    Names(String),
+    /// The enumerated values of a field, named by its bitfield and itself.
+    Values(String, String),
+}
+
+/// The identifiers of a namespace as generated by a naming, which is given to the sanitizers,
+/// e.g. [`ToSanitizedSnakeCase`].
+#[derive(Clone, Copy, Debug)]
+pub struct Scope<'a> {
+    naming: &'a Naming,
+    /// The names declared in the namespace.
+    names: &'a [String],
+}
+
+thread_local! {
+    /// The lints reported by [`Config::lint`] while collecting them.
+    static LINTS: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+/// The identifier derived from `name` without separators if it is not an identifier already,
+/// where characters which cannot be part of an identifier, e.g. `°` or `.`, are replaced by
+/// underscores, and a name without any letter or digit left is derived as `unnamed`.
+fn derived_chars(name: &str) -> Option<String> {
+    fn is_ident_char(c: char) -> bool {
+        c.is_ascii_alphanumeric() || c == '_'
+    }
+
+    let s = name.replace(BLACKLIST_CHARS, "");
+    if s.chars().all(is_ident_char) && s.chars().any(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+
+    let mut base = String::new();
+    for c in s.chars() {
+        if is_ident_char(c) {
+            base.push(c);
+        } else if !base.is_empty() && !base.ends_with('_') {
+            base.push('_');
+        }
+    }
+    let base = base.trim_end_matches('_');
+    if base.chars().any(|c| c.is_ascii_alphanumeric()) {
+        Some(String::from(base))
+    } else {
+        Some(String::from("unnamed"))
+    }
+}
+
+/// The identifier generated for `name` in the `scope` before it is cased, which is its rename
+/// or the name without separators, see [`derived_chars`]. Distinct names of the namespace
+/// derived as the same identifier are told apart by numbered suffixes in the order they are
+/// declared, e.g. `unnamed_2`, where a name which is not declared comes after the declared
+/// ones.
+fn ident_chars(name: &str, scope: &Scope) -> String {
+    if let Some(ident) = scope.naming.renames.get(name) {
+        return ident.clone();
+    }
+    let base = match derived_chars(name) {
+        Some(base) => base,
+        None => return name.replace(BLACKLIST_CHARS, ""),
+    };
+
+    // renamed names keep their rename, so they are not numbered
+    let key = Some(base.to_snake_case());
+    let same = scope
+        .names
+        .iter()
+        .filter(|other| !scope.naming.renames.contains_key(other.as_str()))
+        .filter(|other| derived_chars(other).map(|other| other.to_snake_case()) == key)
+        .collect::<Vec<_>>();
+    let nth = same
+        .iter()
+        .position(|other| *other == name)
+        .unwrap_or(same.len());
+    if nth > 0 {
+        format!("{}_{}", base, nth + 1)
+    } else {
+        base
+    }
+}
+
+/// Call `f`, returning the lints reported by it instead of logging them.
+pub fn collect_lints<T>(f: impl FnOnce() -> T) -> (T, Vec<String>) {
+    let outer = LINTS.with(|lints| lints.replace(Some(vec![])));
+    let value = f();
+    let lints = LINTS.with(|lints| lints.replace(outer)).unwrap_or_default();
+    (value, lints)
+}
+
+/// Accessors generated for a bitfield field or a structure member.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Access {
+    #[default]
+    ReadWrite,
+    /// Only readers, for fields which must never be set by the host.
+    ReadOnly,
+    /// Only writers.
+    WriteOnly,
+}
+
+impl Access {
+    pub fn can_read(self) -> bool {
+        self != Access::WriteOnly
+    }
+
+    pub fn can_write(self) -> bool {
+        self != Access::ReadOnly
+    }
+}
+
 #[allow(clippy::upper_case_acronyms)]
 #[allow(non_camel_case_types)]
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
 pub enum Target {
+    #[default]
     CortexM,
     Msp430,
     RISCV,
@@ -70,25 +300,14 @@ impl Target {
     }
 }
 
-impl Default for Target {
-    fn default() -> Self {
-        Self::CortexM
-    }
-}
-
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
 pub enum SourceType {
+    #[default]
     Xml,
     Yaml,
     Json,
 }
 
-impl Default for SourceType {
-    fn default() -> Self {
-        Self::Xml
-    }
-}
-
 impl SourceType {
     /// Make a new [`Source`] from a given extension.
     pub fn from_extension(s: &str) -> Option<Self> {
@@ -107,62 +326,250 @@ impl SourceType {
     }
 }
 
+/// The I/O traits used by the generated code.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum IoBackend {
+    /// The `Read`/`Write` traits of the `core2` crate.
+    #[default]
+    Core2,
+    /// Minimal `Read`/`Write` traits over byte slices generated into the `io` module at the
+    /// root of the output, only depending on `core`.
+    Core,
+    /// The `Read`/`Write` traits of the `embedded-io` crate, adapted by the `io` module generated
+    /// at the root of the output.
+    EmbeddedIo,
+}
+
+impl IoBackend {
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "core2" => IoBackend::Core2,
+            "core" => IoBackend::Core,
+            "embedded-io" => IoBackend::EmbeddedIo,
+            _ => bail!("unknown io backend {}", s),
+        })
+    }
+
+    /// Path of the module providing `Error`, `ErrorKind`, `Read` and `Write`.
+    pub fn path(&self) -> TokenStream {
+        match self {
+            IoBackend::Core2 => quote! { core2::io },
+            IoBackend::Core | IoBackend::EmbeddedIo => quote! { crate::io },
+        }
+    }
+}
+
+/// Order of the bytes of the integers and bitfields of a structure on the wire. Bitfields of
+/// more than 8 bytes are kept as byte arrays in the order of the wire.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum ByteOrder {
+    #[default]
+    LittleEndian,
+    /// Network byte order.
+    BigEndian,
+}
+
+impl ByteOrder {
+    /// The `from_*_bytes` conversion of the integer types.
+    pub fn from_bytes(&self) -> Ident {
+        match self {
+            ByteOrder::LittleEndian => Ident::new("from_le_bytes", Span::call_site()),
+            ByteOrder::BigEndian => Ident::new("from_be_bytes", Span::call_site()),
+        }
+    }
+
+    /// The `to_*_bytes` conversion of the integer types.
+    pub fn to_bytes(&self) -> Ident {
+        match self {
+            ByteOrder::LittleEndian => Ident::new("to_le_bytes", Span::call_site()),
+            ByteOrder::BigEndian => Ident::new("to_be_bytes", Span::call_site()),
+        }
+    }
+
+    /// The range of the `bytes` bytes on the wire within the bytes of an integer type of
+    /// `width` bytes, which are the least significant ones.
+    pub fn range(&self, bytes: u32, width: u32) -> TokenStream {
+        match self {
+            ByteOrder::LittleEndian => {
+                let bytes = unsuffixed(bytes as u64);
+                quote! { ..#bytes }
+            }
+            ByteOrder::BigEndian => {
+                let start = unsuffixed((width - bytes) as u64);
+                quote! { #start.. }
+            }
+        }
+    }
+}
+
+/// Traits derived on the generated types.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Derives {
+    /// Paths of the derived traits. Types which cannot be `Copy` never derive `Copy`, and
+    /// bitfield types always derive `Clone`, `Copy` and `PartialEq` as their accessors rely on
+    /// them.
+    pub traits: Vec<String>,
+    /// Derive `serde::Serialize` and `serde::Deserialize`, gated on a `serde` feature of the
+    /// generated crate. Note that serde only supports arrays of up to 32 elements.
+    pub serde: bool,
+    /// Derive `defmt::Format`, gated on a `defmt` feature of the generated crate.
+    pub defmt: bool,
+}
+
+impl Default for Derives {
+    fn default() -> Self {
+        Self {
+            traits: ["Clone", "Copy", "Debug", "Eq", "PartialEq"]
+                .iter()
+                .map(|t| t.to_string())
+                .collect(),
+            serde: false,
+            defmt: false,
+        }
+    }
+}
+
+impl Derives {
+    /// Derives without any traits.
+    pub fn empty() -> Self {
+        Self {
+            traits: vec![],
+            serde: false,
+            defmt: false,
+        }
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.traits.iter().any(|t| t == path)
+    }
+
+    /// Derive the trait at `path`, e.g. `Hash` or `PartialOrd`.
+    pub fn add_derive(mut self, path: &str) -> Self {
+        if !self.contains(path) {
+            self.traits.push(path.to_string());
+        }
+        self
+    }
+
+    /// Stop deriving the trait at `path`.
+    pub fn remove_derive(mut self, path: &str) -> Self {
+        self.traits.retain(|t| t != path);
+        self
+    }
+
+    pub fn with_serde(mut self, serde: bool) -> Self {
+        self.serde = serde;
+        self
+    }
+
+    pub fn with_defmt(mut self, defmt: bool) -> Self {
+        self.defmt = defmt;
+        self
+    }
+
+    /// Attributes deriving all traits on a type.
+    pub fn tokens(&self) -> Result<TokenStream> {
+        let mut tokens = TokenStream::new();
+        if !self.traits.is_empty() {
+            let paths = self
+                .traits
+                .iter()
+                .map(|t| {
+                    syn::parse_str::<syn::Path>(t).map_err(|_| anyhow!("invalid derive `{}`", t))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            tokens.extend(quote! {
+                #[derive(#(#paths),*)]
+            });
+        }
+        tokens.extend(self.optional_tokens());
+        Ok(tokens)
+    }
+
+    /// Attributes adding the derives which are gated on features of the generated crate.
+    pub fn optional_tokens(&self) -> TokenStream {
+        let mut tokens = TokenStream::new();
+        if self.serde {
+            tokens.extend(quote! {
+                #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+            });
+        }
+        if self.defmt {
+            tokens.extend(quote! {
+                #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+            });
+        }
+        tokens
+    }
+}
+
 pub trait ToSanitizedPascalCase {
-    fn to_sanitized_pascal_case(&self) -> Cow<str>;
+    fn to_sanitized_pascal_case(&self, scope: &Scope) -> Cow<'_, str>;
 }
 
 pub trait ToSanitizedUpperCase {
-    fn to_sanitized_upper_case(&self) -> Cow<str>;
+    fn to_sanitized_upper_case(&self, scope: &Scope) -> Cow<'_, str>;
 }
 
 pub trait ToSanitizedSnakeCase {
-    fn to_sanitized_not_keyword_snake_case(&self) -> Cow<str>;
-    fn to_sanitized_snake_case(&self) -> Cow<str> {
-        let s = self.to_sanitized_not_keyword_snake_case();
-        sanitize_keyword(s)
+    fn to_sanitized_not_keyword_snake_case(&self, scope: &Scope) -> Cow<'_, str>;
+    fn to_sanitized_snake_case(&self, scope: &Scope) -> Cow<'_, str> {
+        let s = self.to_sanitized_not_keyword_snake_case(scope);
+        sanitize_keyword(s, scope.naming.raw_idents)
     }
 }
 
 impl ToSanitizedSnakeCase for str {
-    fn to_sanitized_not_keyword_snake_case(&self) -> Cow<str> {
-        const INTERNALS: [&str; 4] = ["set_bit", "clear_bit", "bit", "bits"];
-
-        let s = self.replace(BLACKLIST_CHARS, "");
+    fn to_sanitized_not_keyword_snake_case(&self, scope: &Scope) -> Cow<'_, str> {
+        let s = ident_chars(self, scope);
         match s.chars().next().unwrap_or('\0') {
             '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
                 format!("_{}", s.to_snake_case()).into()
             }
-            _ => {
-                let s = Cow::from(s.to_snake_case());
-                if INTERNALS.contains(&s.as_ref()) {
-                    s + "_"
-                } else {
-                    s
-                }
-            }
+            _ => Cow::from(s.to_snake_case()),
         }
     }
 }
 
-pub fn sanitize_keyword(sc: Cow<str>) -> Cow<str> {
-    const KEYWORDS: [&str; 54] = [
-        "abstract", "alignof", "as", "async", "await", "become", "box", "break", "const",
-        "continue", "crate", "do", "else", "enum", "extern", "false", "final", "fn", "for", "if",
-        "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut", "offsetof",
-        "override", "priv", "proc", "pub", "pure", "ref", "return", "self", "sizeof", "static",
-        "struct", "super", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
-        "virtual", "where", "while", "yield",
-    ];
-    if KEYWORDS.contains(&sc.as_ref()) {
-        sc + "_"
-    } else {
+/// Keywords of every edition, reserved and weak ones included, as well as `yeet` of the unstable
+/// `do yeet`, none of which are used as identifiers even where they would be allowed.
+const KEYWORDS: [&str; 60] = [
+    "abstract", "alignof", "as", "async", "await", "become", "box", "break", "const", "continue",
+    "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if",
+    "impl", "in", "let", "loop", "macro", "macro_rules", "match", "mod", "move", "mut",
+    "offsetof", "override", "priv", "proc", "pub", "pure", "ref", "return", "self", "sizeof",
+    "static", "struct", "super", "trait", "true", "try", "type", "typeof", "union", "unsafe",
+    "unsized", "use", "virtual", "where", "while", "yeet", "yield", "Self",
+];
+
+/// Keywords which cannot be raw identifiers.
+const NOT_RAW: [&str; 4] = ["crate", "self", "super", "Self"];
+
+/// The identifier `sc` with an underscore appended if it is a keyword, or as a raw identifier
+/// if `raw` is set and the keyword can be one, e.g. `r#type`.
+pub fn sanitize_keyword(sc: Cow<'_, str>, raw: bool) -> Cow<'_, str> {
+    if !KEYWORDS.contains(&sc.as_ref()) {
         sc
+    } else if raw && !NOT_RAW.contains(&sc.as_ref()) {
+        Cow::from(format!("r#{}", sc))
+    } else {
+        sc + "_"
+    }
+}
+
+/// The identifier `name` sanitized by the sanitizers, which is a raw identifier if it is one,
+/// e.g. `r#type`. Identifiers composed of raw identifiers drop their prefix, e.g. `get_type`
+/// for `get_r#type`.
+pub fn ident(name: &str, span: Span) -> Ident {
+    match name.strip_prefix("r#") {
+        Some(keyword) if KEYWORDS.contains(&keyword) => Ident::new_raw(keyword, span),
+        _ => Ident::new(&name.replace("r#", ""), span),
     }
 }
 
 impl ToSanitizedUpperCase for str {
-    fn to_sanitized_upper_case(&self) -> Cow<str> {
-        let s = self.replace(BLACKLIST_CHARS, "");
+    fn to_sanitized_upper_case(&self, scope: &Scope) -> Cow<'_, str> {
+        let s = ident_chars(self, scope);
 
         match s.chars().next().unwrap_or('\0') {
             '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
@@ -174,14 +581,20 @@ impl ToSanitizedUpperCase for str {
 }
 
 impl ToSanitizedPascalCase for str {
-    fn to_sanitized_pascal_case(&self) -> Cow<str> {
-        let s = self.replace(BLACKLIST_CHARS, "");
+    fn to_sanitized_pascal_case(&self, scope: &Scope) -> Cow<'_, str> {
+        // renames already in pascal case are kept as given, e.g. `Ieee802154Hdr`
+        if let Some(ident) = scope.naming.renames.get(self) {
+            if ident.starts_with(char::is_uppercase) && !ident.contains('_') {
+                return Cow::from(ident.clone());
+            }
+        }
+        let s = ident_chars(self, scope);
 
         match s.chars().next().unwrap_or('\0') {
             '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
                 Cow::from(format!("_{}", s.to_pascal_case()))
             }
-            _ => Cow::from(s.to_pascal_case()),
+            _ => sanitize_keyword(Cow::from(s.to_pascal_case()), scope.naming.raw_idents),
         }
     }
 }
@@ -216,6 +629,37 @@ pub fn escape_brackets(s: &str) -> String {
         })
 }
 
+/// Describe the `names` of the `kind`s of `owner` which are generated as the same identifier by
+/// `ident`, e.g. members `Frame Control` and `frame_control` of a structure.
+pub fn name_conflicts<F>(kind: &str, owner: &str, names: &[&str], ident: F) -> Vec<String>
+where
+    F: Fn(&str) -> String,
+{
+    let idents = names.iter().map(|name| ident(name)).collect::<Vec<_>>();
+    let mut conflicts = vec![];
+    for (i, name) in names.iter().enumerate() {
+        if let Some(first) = (0..i).find(|&j| idents[j] == idents[i]) {
+            if names[first] == *name {
+                conflicts.push(format!("{} `{}` of {} is defined twice", kind, name, owner));
+            } else {
+                conflicts.push(format!(
+                    "{}s `{}` and `{}` of {} are both named `{}`",
+                    kind, names[first], name, owner, idents[i]
+                ));
+            }
+        }
+    }
+    conflicts
+}
+
+/// Fail with all the `conflicts`, one per line, if there are any.
+pub fn check_conflicts(conflicts: &[String]) -> Result<()> {
+    if !conflicts.is_empty() {
+        bail!("{}", conflicts.join("\n"));
+    }
+    Ok(())
+}
+
 pub fn replace_suffix(name: &str, suffix: &str) -> String {
     if name.contains("[%s]") {
         name.replace("[%s]", suffix)
@@ -250,11 +694,37 @@ pub fn hex(n: u64) -> TokenStream {
     .into_token_stream()
 }
 
+/// Lay out the generated code `tokens` of a file for writing it.
+pub fn unparse(tokens: &TokenStream) -> Result<String> {
+    Ok(prettyplease::unparse(&syn::parse2(tokens.clone())?))
+}
+
 /// Turns `n` into an unsuffixed token
 pub fn unsuffixed(n: u64) -> TokenStream {
     Literal::u64_unsuffixed(n).into_token_stream()
 }
 
+/// The Rust type of `bytes` raw little endian bytes, which is the smallest integer holding
+/// them and a byte array for more than 8 bytes.
+pub fn raw_ty(bytes: u32) -> Result<TokenStream> {
+    Ok(if bytes > 8 {
+        let bytes = unsuffixed(bytes as u64);
+        quote! { [u8; #bytes] }
+    } else {
+        (bytes * BITS_PER_BYTE).to_ty()?.into_token_stream()
+    })
+}
+
+/// The value of `bytes` raw little endian bytes, which are all zero, of type [`raw_ty`].
+pub fn raw_zero(bytes: u32) -> TokenStream {
+    if bytes > 8 {
+        let bytes = unsuffixed(bytes as u64);
+        quote! { [0; #bytes] }
+    } else {
+        quote! { 0 }
+    }
+}
+
 pub fn unsuffixed_or_bool(n: u64, width: u32) -> TokenStream {
     if width == 1 {
         Ident::new(if n == 0 { "false" } else { "true" }, Span::call_site()).into_token_stream()
@@ -0,0 +1,715 @@
+//! Checks of the behaviour of generated code, which is written as a crate to a temporary
+//! directory along with the checks and tested using `cargo test`.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+
+use prot2rust::generate::bitfield::{BitField, BitOrder, ReservedPolicy};
+use prot2rust::generate::structure::{Payloads, Structure};
+use prot2rust::protocols;
+use prot2rust::tree::{crate_name, GenTree};
+use prot2rust::util::{ByteOrder, Derives, IoBackend};
+
+/// Write `tree` as the crate `name` with the test module `checks` appended to its `lib.rs`,
+/// and run its tests.
+fn check_crate(name: &str, tree: &GenTree, checks: &str) {
+    check_crate_with(name, tree, "", &[], checks);
+}
+
+/// Check the crate `name` like [`check_crate`], with `manifest` appended to its `Cargo.toml`
+/// and its `features` enabled.
+fn check_crate_with(name: &str, tree: &GenTree, manifest: &str, features: &[&str], checks: &str) {
+    let tmp = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let dir = tmp.join(name);
+    let _ = fs::remove_dir_all(&dir);
+    tree.write_crate(dir.to_str().unwrap(), name, "0.1.0")
+        .unwrap();
+
+    let mut cargo = OpenOptions::new()
+        .append(true)
+        .open(dir.join("Cargo.toml"))
+        .unwrap();
+    write!(cargo, "{}", manifest).unwrap();
+
+    let mut lib = OpenOptions::new()
+        .append(true)
+        .open(dir.join("src").join("lib.rs"))
+        .unwrap();
+    write!(
+        lib,
+        "\n#[cfg(test)]\nmod checks {{\n    use super::*;\n\n{}\n}}\n",
+        checks
+    )
+    .unwrap();
+
+    let status = Command::new(env!("CARGO"))
+        .arg("test")
+        .arg(format!("--features={}", features.join(",")))
+        .current_dir(&dir)
+        .env("CARGO_TARGET_DIR", tmp.join("target"))
+        .status()
+        .unwrap();
+    assert!(
+        status.success(),
+        "tests of the generated crate `{}` failed",
+        name
+    );
+}
+
+#[test]
+fn msb_first_bitfield_of_three_bytes() {
+    let mut tree = GenTree::new();
+    tree.set_io(IoBackend::Core);
+
+    let bitfield = BitField::new("msb", "Bitfield of 24 bits")
+        .with_bit_order(BitOrder::MsbFirst)
+        .add_bit_field("A", "First byte", 8, |f| f.numeric())
+        .add_bit_field("B", "Last two bytes", 16, |f| f.numeric());
+    tree.add_bitfield(&bitfield).unwrap();
+    let structure = Structure::new("frame")
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_bitfield_of("msb", &bitfield);
+    tree.add_struct(&structure).unwrap();
+
+    check_crate(
+        "msb_first_bitfield",
+        &tree,
+        r#"
+    #[test]
+    fn fields_round_trip() {
+        let mut value = frame::Frame::new();
+        value.msb().modify(|w| {
+            *w = w.a(0x12).b(0x3456);
+            w
+        });
+
+        let mut buf = [0u8; frame::Frame::MAX_SIZE];
+        let len = value.write_to(&mut buf).unwrap();
+        assert_eq!(&buf[..len], &[0x12, 0x34, 0x56]);
+
+        let (read, _) = frame::Frame::read_from(&buf[..len]).unwrap();
+        assert_eq!(read.get_msb().a(), 0x12);
+        assert_eq!(read.get_msb().b(), 0x3456);
+    }
+"#,
+    );
+}
+
+#[test]
+fn msb_first_field_wider_than_a_byte() {
+    let mut tree = GenTree::new();
+    tree.set_io(IoBackend::Core);
+
+    let bitfield = BitField::new("reversed", "Bitfield of 16 bits with a reversed field")
+        .add_bit_field_default("A", "Reversed 12 bits", 12, 1, |f| {
+            f.numeric().with_bit_order(BitOrder::MsbFirst)
+        })
+        .add_bit_field("B", "Upper 4 bits", 4, |f| f.numeric());
+    tree.add_bitfield(&bitfield).unwrap();
+    let structure = Structure::new("frame")
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_bitfield_of("reversed", &bitfield);
+    tree.add_struct(&structure).unwrap();
+
+    check_crate(
+        "msb_first_field",
+        &tree,
+        r#"
+    #[test]
+    fn field_is_reversed() {
+        // the default of 1 is the most significant bit of the field
+        assert_eq!(reversed::RESET_VALUE, 0x0800);
+
+        let mut value = frame::Frame::new();
+        value.reversed().modify(|w| {
+            *w = w.a(0x123).b(0x5);
+            w
+        });
+
+        let mut buf = [0u8; frame::Frame::MAX_SIZE];
+        let len = value.write_to(&mut buf).unwrap();
+        assert_eq!(&buf[..len], &[0x5c, 0x48]);
+
+        let (read, _) = frame::Frame::read_from(&buf[..len]).unwrap();
+        assert_eq!(read.get_reversed().a(), 0x123);
+        assert_eq!(read.get_reversed().b(), 0x5);
+    }
+"#,
+    );
+}
+
+#[test]
+fn field_without_bits_is_rejected() {
+    let mut tree = GenTree::new();
+    let bitfield = BitField::new("empty", "Bitfield with an empty field")
+        .add_bit_field("A", "No bits", 0, |f| f.numeric())
+        .add_bit_field("B", "Eight bits", 8, |f| f.numeric());
+    let error = tree.add_bitfield(&bitfield).unwrap_err().to_string();
+    assert_eq!(error, "field `A` of bitfield `empty` has no bits");
+}
+
+#[test]
+fn reserved_value_above_bit_63_is_rejected() {
+    let mut tree = GenTree::new();
+    let bitfield = BitField::new("wide", "Bitfield of 72 bits")
+        .with_reserved(ReservedPolicy::Value(0))
+        .add_bit_field("A", "First 64 bits", 64, |f| f.numeric())
+        .add_reserved(8);
+    let error = tree.add_bitfield(&bitfield).unwrap_err().to_string();
+    assert_eq!(
+        error,
+        "bitfield `wide` has reserved bits above bit 63, which a reserved value cannot set"
+    );
+}
+
+#[test]
+fn declared_width_mismatch() {
+    let bitfield = BitField::new("short", "Bitfield missing bits")
+        .with_width(16)
+        .add_bit_field("A", "First field", 4, |f| f.numeric())
+        .add_bit_field_at("B", "Second field", 6, 4, |f| f.numeric());
+    let error = GenTree::new()
+        .add_bitfield(&bitfield)
+        .unwrap_err()
+        .to_string();
+    assert_eq!(
+        error,
+        "bitfield `short` is declared as 16 bits, but its fields and reserved bits add up to 10 \
+         (6 bits missing): `A` 0..4, reserved 4..6, `B` 6..10"
+    );
+}
+
+#[test]
+fn signed_scaled_and_default_fields() {
+    let mut tree = GenTree::new();
+    tree.set_io(IoBackend::Core);
+
+    // bits 5 to 7 are left as a reserved gap by placing `Rssi` at bit 8
+    let bitfield = BitField::new("sample", "Bitfield of signed and scaled fields")
+        .add_bit_field_default("Level", "Signed level", 5, 3, |f| f.signed())
+        .add_bit_field_at("Rssi", "RSSI in 0.5 dBm steps", 8, 8, |f| {
+            f.signed().with_scale(0.5, "dBm")
+        });
+    tree.add_bitfield(&bitfield).unwrap();
+
+    check_crate(
+        "bitfield_values",
+        &tree,
+        r#"
+    #[test]
+    fn gap_is_reserved() {
+        assert_eq!(sample::RESERVED_MASK, 0x00e0);
+        assert_eq!(sample::RESET_VALUE, 0x0003);
+    }
+
+    #[test]
+    fn default_ignores_reserved_bits() {
+        assert!(sample::R::new(sample::RESET_VALUE).is_default());
+        assert!(sample::R::new(0x00e3).is_default());
+        assert!(!sample::R::new(0x0004).is_default());
+        assert!(!sample::R::new(0x0103).is_default());
+    }
+
+    #[test]
+    fn signed_field_is_sign_extended() {
+        let w = sample::W::reset().level_value(-1);
+        assert_eq!(*w, 0x001f);
+        let r = sample::R::new(*w);
+        assert_eq!(r.level(), 0x1f);
+        assert_eq!(r.level_value(), -1);
+        assert_eq!(sample::R::new(0x000f).level_value(), 15);
+        assert_eq!(sample::R::new(0x0010).level_value(), -16);
+    }
+
+    #[test]
+    fn scaled_negative_value_is_rounded() {
+        // -6.6 steps round to -7, away from zero
+        let w = sample::W::reset().rssi_value(-3.3);
+        assert_eq!(sample::R::new(*w).rssi(), 0xf9);
+        assert_eq!(sample::R::new(*w).rssi_value(), -3.5);
+        // -6.4 steps round to -6
+        let w = sample::W::reset().rssi_value(-3.2);
+        assert_eq!(sample::R::new(*w).rssi_value(), -3.0);
+        assert_eq!(sample::R::new(0x1403).rssi_value(), 10.0);
+    }
+"#,
+    );
+}
+
+#[test]
+fn enumerated_64_bit_field() {
+    let mut tree = GenTree::new();
+    tree.set_io(IoBackend::Core);
+
+    // every value of the field is enumerated, so reading its variant cannot fail
+    let bitfield =
+        BitField::new("wide", "Bitfield of 64 bits").add_bit_field("Id", "Identifier", 64, |f| {
+            f.add_enum_value("Unassigned", 0)
+                .add_enum_range("Assigned", 1..=u64::MAX)
+        });
+    tree.add_bitfield(&bitfield).unwrap();
+
+    check_crate(
+        "enumerated_64_bit_field",
+        &tree,
+        r#"
+    #[test]
+    fn variant_is_complete() {
+        let unassigned: wide::IdA = wide::R::new(0).id().variant();
+        assert_eq!(unassigned, wide::IdA::Unassigned);
+        let assigned: wide::IdA = wide::R::new(u64::MAX).id().variant();
+        assert_eq!(assigned, wide::IdA::Assigned(u64::MAX));
+    }
+"#,
+    );
+}
+
+#[test]
+fn ieee802154_frames() {
+    let mut tree = GenTree::new();
+    tree.set_io(IoBackend::Core);
+    (protocols::find("ieee802154").unwrap().define)(&mut tree).unwrap();
+
+    // frames as sent on the air, without the PHY header, ending with their FCS
+    check_crate(
+        "ieee802154",
+        &tree,
+        r#"
+    /// Read `frame` as `T` and check that writing it back gives the same bytes.
+    macro_rules! read_frame {
+        ($t:ty, $frame:expr) => {{
+            let frame: &[u8] = $frame;
+            let (value, len) = <$t>::read_from(frame).unwrap();
+            assert_eq!(len, frame.len());
+            let mut buf = [0u8; 128];
+            let len = value.write_to(&mut buf).unwrap();
+            assert_eq!(&buf[..len], frame);
+            value
+        }};
+    }
+
+    #[test]
+    fn imm_ack() {
+        let frame = [0x02, 0x00, 0x2a, 0xe0, 0x3b];
+        let ack = read_frame!(ImmAck, &frame);
+        assert!(ack.get_frame_control().frame_type().is_ack());
+        assert_eq!(ack.get_sequence_number(), 0x2a);
+
+        let mut corrupted = frame;
+        corrupted[2] ^= 1;
+        assert!(ImmAck::read_from(&corrupted).is_err());
+    }
+
+    #[test]
+    fn beacon_request() {
+        let frame = [0x03, 0x08, 0x11, 0xff, 0xff, 0xff, 0xff, 0x07, 0xa3, 0x6f];
+        let command = read_frame!(MacCommandFrameGeneric, &frame);
+        assert_eq!(command.sequence_number.unwrap().get_sequence_number(), 0x11);
+        assert_eq!(command.dest_pan.unwrap().get_pan_id(), 0xffff);
+        assert_eq!(command.dest_address, AddressA::AddrShort(AddrShort::of_value(0xffff)));
+        assert!(command.src_pan.is_none());
+        assert_eq!(command.src_address, AddressA::AddrNone(AddrNone::new()));
+        assert!(command.aux_sec_header.is_none());
+        assert_eq!(command.command, CommandA::BeaconRequest(BeaconRequest::new()));
+    }
+
+    #[test]
+    fn association_request() {
+        let frame = [
+            0x23, 0xc8, 0x5a, 0x34, 0x12, 0x00, 0x00, 0xff, 0xff, 0x77, 0x66, 0x55, 0x44, 0x33,
+            0x22, 0x11, 0x00, 0x01, 0x8e, 0x5a, 0x59,
+        ];
+        let command = read_frame!(MacCommandFrameGeneric, &frame);
+        assert!(command.sequence_number.is_some());
+        assert_eq!(command.dest_pan.unwrap().get_pan_id(), 0x1234);
+        assert_eq!(command.dest_address, AddressA::AddrShort(AddrShort::of_value(0x0000)));
+        assert_eq!(command.src_pan.unwrap().get_pan_id(), 0xffff);
+        let src = match command.src_address {
+            AddressA::AddrExtended(address) => address.get_addr(),
+            other => panic!("unexpected source address {:?}", other),
+        };
+        assert_eq!(src, Eui64([0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77]));
+        let capabilities = match command.command {
+            CommandA::AssociationRequest(request) => request.get_capability_information(),
+            other => panic!("unexpected command {:?}", other),
+        };
+        assert!(capabilities.device_type().bit_is_set());
+        assert!(capabilities.allocate_address().bit_is_set());
+        assert!(!capabilities.security_capability().bit_is_set());
+    }
+
+    #[test]
+    fn beacon() {
+        let frame = [
+            0x00, 0x80, 0x7b, 0x34, 0x12, 0x00, 0x00, 0xff, 0xcf, 0x00, 0x00, 0x5e, 0x19,
+        ];
+        let beacon = read_frame!(BeaconFrameGeneric, &frame);
+        assert!(beacon.dest_pan.is_none());
+        assert_eq!(beacon.src_pan.unwrap().get_pan_id(), 0x1234);
+        let superframe = superframe_spec::R::new(beacon.superframe_spec);
+        assert_eq!(superframe.beacon_order(), 15);
+        assert!(superframe.pan_coordinator().bit_is_set());
+        assert!(superframe.association_permit().bit_is_set());
+    }
+
+    #[test]
+    fn beacon_with_gts_and_pending_addresses() {
+        let frame = [
+            0x00, 0x80, 0x7b, 0x34, 0x12, 0x00, 0x00, 0xff, 0xcf, 0x82, 0x01, 0x78, 0x56, 0x2a,
+            0xbc, 0x9a, 0x3c, 0x11, 0x21, 0x43, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x00,
+            0x67, 0x52,
+        ];
+        let mut beacon = read_frame!(BeaconFrameGeneric, &frame);
+        let gts = gts_spec::R::new(beacon.gts_spec);
+        assert_eq!(gts.descriptor_count(), 2);
+        assert!(gts.gts_permit().bit_is_set());
+        assert_eq!(beacon.gts_directions.unwrap().get_directions().directions(), 0b01);
+        assert_eq!(&beacon.gts_descriptors[..], &[0x2a5678, 0x3c9abc]);
+        assert_eq!(&beacon.short_addresses[..], &[0x4321]);
+        assert_eq!(&beacon.extended_addresses[..], &[0x0011_2233_4455_6677]);
+
+        // the counts follow the lists when writing
+        beacon.gts_descriptors.pop();
+        beacon.short_addresses.clear();
+        let mut buf = [0u8; 128];
+        let len = beacon.write_to(&mut buf).unwrap();
+        assert_eq!(len, frame.len() - 5);
+        assert_eq!(buf[9], 0x81);
+        assert_eq!(buf[14], 0x10);
+        let (read, _) = BeaconFrameGeneric::read_from(&buf[..len]).unwrap();
+        assert_eq!(&read.gts_descriptors[..], &[0x2a5678]);
+        assert!(read.short_addresses.is_empty());
+        assert_eq!(&read.extended_addresses[..], &[0x0011_2233_4455_6677]);
+    }
+
+    #[test]
+    fn secured_data_frame() {
+        let frame = [
+            0x49, 0xed, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x11, 0x12, 0x13, 0x14,
+            0x15, 0x16, 0x17, 0x18, 0x0d, 0x01, 0x00, 0x00, 0x00, 0x01, 0xaa, 0xbb, 0xb8, 0xaa,
+        ];
+        let (header, len) = MhrGeneric::read_from(&frame).unwrap();
+        assert_eq!(len, 24);
+        assert!(header.sequence_number.is_none());
+        assert!(header.dest_pan.is_none());
+        assert!(header.src_pan.is_none());
+        let security = header.aux_sec_header.unwrap();
+        assert!(security.get_security_control().security_level().is_enc_mic_32());
+        assert_eq!(security.get_frame_counter().unwrap().get_frame_counter(), 1);
+        assert!(security.get_key_source_4().is_none());
+        assert!(security.get_key_source_8().is_none());
+        assert_eq!(security.get_key_index().unwrap().get_key_index(), 1);
+
+        let mut fcs = Crc16Kermit::new();
+        fcs.update(&frame[..frame.len() - 2]);
+        assert_eq!(fcs.finish().to_le_bytes(), [0xb8, 0xaa]);
+    }
+
+    #[test]
+    fn key_sources() {
+        let header = [0x15, 0x2a, 0x00, 0x00, 0x00, 0x78, 0x56, 0x34, 0x12, 0x03];
+        let security = read_frame!(AuxSecHeader, &header);
+        assert_eq!(security.get_frame_counter().unwrap().get_frame_counter(), 0x2a);
+        assert_eq!(security.get_key_source_4().unwrap().get_key_source(), 0x12345678);
+        assert_eq!(security.get_key_index().unwrap().get_key_index(), 3);
+
+        let header = [0x3d, 0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01, 0x04];
+        let security = read_frame!(AuxSecHeader, &header);
+        assert!(security.get_frame_counter().is_none());
+        assert_eq!(
+            security.get_key_source_8().unwrap().get_key_source(),
+            0x0102030405060708
+        );
+        assert_eq!(security.get_key_index().unwrap().get_key_index(), 4);
+    }
+"#,
+    );
+}
+
+#[test]
+fn bytes_longer_than_capacity() {
+    let mut tree = GenTree::new();
+    tree.set_io(IoBackend::Core);
+
+    for (name, payloads) in [("array", Payloads::Array), ("held", Payloads::Heapless)] {
+        let structure = Structure::new(name)
+            .with_payloads(payloads)
+            .add_len_field_extra("len", 1, 2)
+            .add_bytes_field("payload", "len", 4);
+        tree.add_struct(&structure).unwrap();
+    }
+
+    // the capacity along with the extra bytes must be counted by the length field
+    let overflowing = Structure::new("overflowing")
+        .add_len_field_extra("len", 1, 252)
+        .add_bytes_field("payload", "len", 4);
+    let error = GenTree::new()
+        .add_struct(&overflowing)
+        .unwrap_err()
+        .to_string();
+    assert_eq!(
+        error,
+        "capacity of `payload` in `overflowing` cannot be counted by `len`"
+    );
+
+    check_crate(
+        "bytes_capacity",
+        &tree,
+        r#"
+    #[test]
+    fn set_fails_beyond_capacity() {
+        let mut value = array::Array::new();
+        value.set_payload(&[1, 2, 3]).unwrap();
+        assert!(matches!(
+            value.set_payload(&[1, 2, 3, 4, 5]),
+            Err(Error::LengthMismatch { field: "payload", len: 5 })
+        ));
+        assert_eq!(value.get_payload(), &[1, 2, 3]);
+
+        let mut buf = [0u8; array::Array::MAX_SIZE];
+        let len = value.write_to(&mut buf).unwrap();
+        assert_eq!(&buf[..len], &[5, 1, 2, 3]);
+
+        let mut value = held::Held::new();
+        assert!(value.payload().set(&[1, 2, 3, 4]).is_ok());
+        assert!(value.payload().set(&[0; 5]).is_err());
+        assert_eq!(value.get_payload(), &[1, 2, 3, 4]);
+    }
+"#,
+    );
+}
+
+#[test]
+fn mqttsn_client_id_capacity() {
+    let mut tree = GenTree::new();
+    tree.set_io(IoBackend::Core);
+    (protocols::find("mqttsn").unwrap().define)(&mut tree).unwrap();
+
+    check_crate(
+        "mqttsn",
+        &tree,
+        r#"
+    #[test]
+    fn client_id_fits_capacity() {
+        let mut connect = connect::Connect::new();
+        connect.set_client_id(b"sensor").unwrap();
+        assert!(matches!(
+            connect.set_client_id(&[b'a'; 24]),
+            Err(Error::LengthMismatch { field: "client_id", len: 24 })
+        ));
+
+        // the view has room for a longer identifier, but still holds to the capacity
+        let mut buf = [0u8; 64];
+        connect.write_to(&mut buf).unwrap();
+        let mut view = connect::ConnectMut::new(&mut buf).unwrap();
+        view.set_client_id(&[b'a'; 23]).unwrap();
+        assert!(matches!(
+            view.set_client_id(&[b'a'; 24]),
+            Err(Error::LengthMismatch { field: "client_id", len: 24 })
+        ));
+        assert_eq!(view.as_ref().client_id(), &[b'a'; 23]);
+    }
+"#,
+    );
+}
+
+#[test]
+fn modify_structure_through_proxies() {
+    let mut tree = GenTree::new();
+    tree.set_io(IoBackend::Core);
+
+    let structure = Structure::new("pair")
+        .add_u8_field("a")
+        .add_bcd_field("b", 1);
+    tree.add_struct(&structure).unwrap();
+
+    check_crate(
+        "modify_structure",
+        &tree,
+        r#"
+    #[test]
+    fn modify_sets_all_members_or_none() {
+        let mut value = pair::Pair::new();
+        value
+            .modify(|w| {
+                w.a().set(1);
+                w.b().set(42)?;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(value.get_a(), 1);
+        assert_eq!(value.get_b(), 42);
+
+        // the first member is valid, but is not written as the second is not
+        let result = value.modify(|w| {
+            w.a().set(2);
+            w.b().set(100)?;
+            Ok(())
+        });
+        assert!(matches!(result, Err(Error::OutOfRange { field: "b", value: 100 })));
+        assert_eq!(value.get_a(), 1);
+        assert_eq!(value.get_b(), 42);
+
+        // two digits do not fit a byte of BCD
+        assert!(matches!(
+            value.b().set(100),
+            Err(Error::OutOfRange { field: "b", value: 100 })
+        ));
+        assert!(value.set_b(100).is_err());
+        assert_eq!(value.get_b(), 42);
+    }
+"#,
+    );
+}
+
+#[test]
+fn derived_identifiers_numbered_per_structure() {
+    let mut tree = GenTree::new();
+    tree.set_io(IoBackend::Core);
+
+    // both are derived as `s`, which only tells apart the members of a structure
+    let second = Structure::new("second").add_u8_field("µs");
+    let first = Structure::new("first")
+        .add_u8_field("°s")
+        .add_u8_field("µs");
+    tree.add_struct(&second).unwrap();
+    tree.add_struct(&first).unwrap();
+
+    check_crate(
+        "derived_identifiers",
+        &tree,
+        r#"
+    #[test]
+    fn suffixes_follow_the_members_of_each_structure() {
+        let mut first = first::First::new();
+        first.set_s(1);
+        first.set_s_2(2);
+        let mut second = second::Second::new();
+        second.set_s(3);
+
+        let mut buf = [0u8; first::First::MAX_SIZE];
+        let len = first.write_to(&mut buf).unwrap();
+        assert_eq!(&buf[..len], &[1, 2]);
+        assert_eq!(second.get_s(), 3);
+    }
+"#,
+    );
+}
+
+#[test]
+fn serialize_with_serde_feature() {
+    let mut tree = GenTree::new();
+    tree.set_io(IoBackend::Core);
+    tree.set_derives(Derives::default().with_serde(true));
+
+    let structure = Structure::new("reading")
+        .add_u8_field("channel")
+        .add_u16_field("value");
+    tree.add_struct(&structure).unwrap();
+
+    check_crate_with(
+        "serde_feature",
+        &tree,
+        "\n[dev-dependencies]\nserde_json = \"1.0\"\n",
+        &["serde"],
+        r#"
+    #[test]
+    fn structure_round_trips_through_json() {
+        let mut value = reading::Reading::new();
+        value.set_channel(3);
+        value.set_value(0x1234);
+
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, "{\"channel\":3,\"value\":4660}");
+        let read: reading::Reading = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, value);
+    }
+"#,
+    );
+}
+
+#[test]
+fn format_with_defmt_feature() {
+    let mut tree = GenTree::new();
+    tree.set_io(IoBackend::Core);
+    tree.set_derives(Derives::default().with_defmt(true));
+
+    let bitfield = BitField::new("flags", "Flags of a reading")
+        .add_bit_field("valid", "Whether the value is valid", 1, |f| f.numeric())
+        .add_bit_field("unit", "Unit of the value", 7, |f| f.numeric());
+    tree.add_bitfield(&bitfield).unwrap();
+    let structure = Structure::new("reading")
+        .add_bitfield_of("flags", &bitfield)
+        .add_u16_field("value");
+    tree.add_struct(&structure).unwrap();
+
+    check_crate_with(
+        "defmt_feature",
+        &tree,
+        "",
+        &["defmt"],
+        r#"
+    fn assert_format<T: defmt::Format>() {}
+
+    #[test]
+    fn types_implement_format() {
+        assert_format::<reading::Reading>();
+        assert_format::<flags::R>();
+        assert_format::<flags::W>();
+    }
+"#,
+    );
+}
+
+#[test]
+fn fuzz_crate_builds() {
+    let mut tree = GenTree::new();
+    tree.set_io(IoBackend::Core);
+    tree.set_fuzz_targets(&crate_name("fuzzed")).unwrap();
+
+    let bitfield = BitField::new("header", "Header of 24 bits")
+        .add_bit_field("kind", "Kind of the packet", 4, |f| f.numeric())
+        .add_bit_field("len", "Length of the payload", 20, |f| f.numeric());
+    tree.add_bitfield(&bitfield).unwrap();
+    let structure = Structure::new("packet")
+        .with_byte_order(ByteOrder::BigEndian)
+        .add_bitfield_of("header", &bitfield)
+        .add_u16_field("checksum");
+    tree.add_struct(&structure).unwrap();
+
+    let tmp = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let dir = tmp.join("fuzzed");
+    let fuzz = dir.join("fuzz");
+    let _ = fs::remove_dir_all(&dir);
+    tree.write_crate(dir.to_str().unwrap(), "fuzzed", "0.1.0")
+        .unwrap();
+    tree.write_fuzz_crate(fuzz.to_str().unwrap(), dir.to_str().unwrap(), "fuzzed")
+        .unwrap();
+
+    // the bitfield is read like the structure holding it reads it
+    let target = fs::read_to_string(fuzz.join("fuzz_targets").join("header.rs")).unwrap();
+    assert!(target.contains("from_be_bytes"), "{}", target);
+    let manifest = fs::read_to_string(fuzz.join("Cargo.toml")).unwrap();
+    assert!(
+        manifest.contains("fuzzed = { path = \"..\" }"),
+        "{}",
+        manifest
+    );
+    for target in ["header", "packet"] {
+        assert!(
+            manifest.contains(&format!("path = \"fuzz_targets/{}.rs\"", target)),
+            "{}",
+            manifest
+        );
+    }
+
+    let status = Command::new(env!("CARGO"))
+        .arg("build")
+        .current_dir(&fuzz)
+        .env("CARGO_TARGET_DIR", tmp.join("target"))
+        .status()
+        .unwrap();
+    assert!(status.success(), "the fuzz crate failed to build");
+}
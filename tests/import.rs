@@ -0,0 +1,170 @@
+//! Checks of the importers of descriptions in other formats.
+
+use prot2rust::import::ksy;
+use prot2rust::import::xml;
+use prot2rust::import::yaml::{self, Yaml};
+
+fn scalar(text: &str) -> Yaml {
+    Yaml::Scalar(String::from(text))
+}
+
+#[test]
+fn yaml_sequences() {
+    let doc = yaml::parse("block:\n  - a\n  - [b, c]\nflow: [1, [2, 3], {k: v}]\nindented:\n- x\n")
+        .unwrap();
+    assert_eq!(
+        doc.get("block"),
+        Some(&Yaml::Seq(vec![
+            scalar("a"),
+            Yaml::Seq(vec![scalar("b"), scalar("c")])
+        ]))
+    );
+    assert_eq!(
+        doc.get("flow"),
+        Some(&Yaml::Seq(vec![
+            scalar("1"),
+            Yaml::Seq(vec![scalar("2"), scalar("3")]),
+            Yaml::Map(vec![(String::from("k"), scalar("v"))]),
+        ]))
+    );
+    assert_eq!(doc.get("indented"), Some(&Yaml::Seq(vec![scalar("x")])));
+}
+
+#[test]
+fn yaml_scalars() {
+    let doc = yaml::parse(concat!(
+        "single: 'it''s # not a comment'\n",
+        "double: \"tab\\tand \\\"quotes\\\"\"\n",
+        "plain: 0x1_0 # a comment\n",
+        "quoted_null: 'null'\n",
+        "null: ~\n",
+        "empty:\n",
+    ))
+    .unwrap();
+    assert_eq!(doc.get("single"), Some(&scalar("it's # not a comment")));
+    assert_eq!(doc.get("double"), Some(&scalar("tab\tand \"quotes\"")));
+    assert_eq!(doc.get("plain").and_then(Yaml::as_u64), Some(16));
+    assert_eq!(doc.get("quoted_null"), Some(&scalar("null")));
+    assert_eq!(doc.get("null"), Some(&Yaml::Null));
+    assert_eq!(doc.get("empty"), Some(&Yaml::Null));
+}
+
+#[test]
+fn yaml_multi_line_strings() {
+    let doc = yaml::parse(concat!(
+        "literal: |\n",
+        "  first\n",
+        "    indented\n",
+        "\n",
+        "  last\n",
+        "folded: >\n",
+        "  one\n",
+        "  line\n",
+        "stripped: |-\n",
+        "  no break\n",
+    ))
+    .unwrap();
+    assert_eq!(
+        doc.get("literal"),
+        Some(&scalar("first\n  indented\n\nlast\n"))
+    );
+    assert_eq!(doc.get("folded"), Some(&scalar("one line\n")));
+    assert_eq!(doc.get("stripped"), Some(&scalar("no break")));
+}
+
+#[test]
+fn yaml_lines() {
+    let (_, lines) =
+        yaml::parse_with_lines("# comment\nseq:\n  - id: a\n\n  - id: b\n    type: u1\n").unwrap();
+    assert_eq!(lines.get("seq"), Some(2));
+    assert_eq!(lines.get("seq/1"), Some(5));
+    assert_eq!(lines.get("seq/1/type"), Some(6));
+    assert_eq!(lines.get("seq/1/doc"), Some(5));
+}
+
+#[test]
+fn yaml_errors() {
+    let error = |src: &str| yaml::parse(src).unwrap_err().to_string();
+    assert_eq!(
+        error("a: &anchor 1\nb: *anchor\n"),
+        "line 1: anchors, aliases and tags are not supported"
+    );
+    assert_eq!(
+        error("a: !tag 1\n"),
+        "line 1: anchors, aliases and tags are not supported"
+    );
+    assert_eq!(error("a: 1\na: 2\n"), "line 2: duplicate key `a`");
+    assert_eq!(error("? [a]\n: 1\n"), "line 1: keys must be scalars");
+    assert!(error("a: [1, 2\n").contains("line"));
+}
+
+#[test]
+fn ksy_bits_with_enum() {
+    let src = concat!(
+        "meta:\n",
+        "  id: flags\n",
+        "  endian: be\n",
+        "seq:\n",
+        "  - id: kind\n",
+        "    type: b4\n",
+        "    enum: kinds\n",
+        "  - id: rest\n",
+        "    type: b4\n",
+        "enums:\n",
+        "  kinds:\n",
+        "    1: first\n",
+    );
+    let error = ksy::import(src).err().unwrap();
+    assert!(
+        format!("{:#}", error).contains("bit members cannot have an `enum`"),
+        "{:#}",
+        error
+    );
+}
+
+#[test]
+fn xml_content() {
+    let root = xml::parse(concat!(
+        "<?xml version=\"1.0\"?>\n",
+        "<!-- before the root -->\n",
+        "<device xmlns:xs=\"http://www.w3.org/2001/XMLSchema-instance\" ",
+        "xs:noNamespaceSchemaLocation=\"CMSIS-SVD.xsd\">\n",
+        "  <name>a &amp; b &#x41;&#66;</name>\n",
+        "  <description><![CDATA[<raw> & text]]><!-- skipped --> end</description>\n",
+        "  <register name='r' access=\"read&#x2d;only\"/>\n",
+        "</device>\n",
+    ))
+    .unwrap();
+    assert_eq!(root.name, "device");
+    assert_eq!(root.line, 3);
+    assert_eq!(
+        root.attr("noNamespaceSchemaLocation"),
+        Some("CMSIS-SVD.xsd")
+    );
+    assert_eq!(root.child_text("name"), Some("a & b AB"));
+    assert_eq!(root.child_text("description"), Some("<raw> & text end"));
+    let register = root.child("register").unwrap();
+    assert_eq!(register.attr("name"), Some("r"));
+    assert_eq!(register.attr("access"), Some("read-only"));
+    assert_eq!(register.line, 6);
+}
+
+#[test]
+fn xml_entities_of_the_document_type() {
+    let root = xml::parse(concat!(
+        "<!DOCTYPE register [<!ENTITY width \"8\">]>\n",
+        "<register><size>&width;</size></register>\n",
+    ))
+    .unwrap();
+    assert_eq!(root.child_text("size"), Some("8"));
+}
+
+#[test]
+fn xml_errors() {
+    let error = |src: &str| xml::parse(src).unwrap_err().to_string();
+    assert!(error("<a><b></a>").contains("at 1:"));
+    assert!(error("<a>&unknown;</a>").contains("unknown"));
+    assert!(error("<a>").contains("never closed"));
+    assert!(error("<a></a><b/>").contains("at 1:"));
+    assert!(error("<a x=1/>").contains("at 1:"));
+}